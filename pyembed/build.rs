@@ -133,5 +133,14 @@ fn main() {
         env::var("CARGO_MANIFEST_DIR").unwrap()
     );
 
+    // Crates linking this one can find the C API header via
+    // DEP_PYTHONXY_INCLUDE.
+    if env::var("CARGO_FEATURE_C_API").is_ok() {
+        println!(
+            "cargo:include={}/include",
+            env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+    }
+
     println!("cargo:rustc-cfg=library_mode=\"{}\"", library_mode);
 }
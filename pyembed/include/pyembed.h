@@ -0,0 +1,54 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+/* C API to the pyembed crate. Available when built with the c-api feature. */
+
+#ifndef PYEMBED_H
+#define PYEMBED_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An embedded Python interpreter. */
+typedef struct PyembedInterpreter PyembedInterpreter;
+
+/* Create and initialize an interpreter from a JSON object config.
+ *
+ * Recognized keys are "profile", "argv", "module_search_paths",
+ * "packed_resources", "oxidized_importer", "filesystem_importer", "origin",
+ * "run_command", and "run_module".
+ *
+ * Returns NULL on error. */
+PyembedInterpreter *pyembed_new_interpreter(const char *config_json);
+
+/* Run the configured main, finalize, and free the interpreter.
+ *
+ * Returns an exit code for the process. The interpreter can't be used
+ * after this call. */
+int pyembed_run_main(PyembedInterpreter *interpreter);
+
+/* Evaluate a Python expression and return str() of the result.
+ *
+ * Returns NULL on error. The result must be freed with
+ * pyembed_free_string(). */
+char *pyembed_eval(PyembedInterpreter *interpreter, const char *code);
+
+/* Finalize and free an interpreter. NULL is ignored. */
+void pyembed_free_interpreter(PyembedInterpreter *interpreter);
+
+/* Free a string returned by this API. NULL is ignored. */
+void pyembed_free_string(char *value);
+
+/* Describe the last error on the current thread, or NULL.
+ *
+ * The string is valid until the next call into this API on the same
+ * thread. */
+const char *pyembed_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PYEMBED_H */
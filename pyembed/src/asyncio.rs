@@ -0,0 +1,190 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Drive an asyncio event loop from a host event loop.
+
+Running asyncio code normally means calling `asyncio.run()`, which blocks
+until the coroutine completes. Applications with their own event loop (a GUI
+toolkit, tokio) would need to dedicate a thread to Python.
+
+Instead, we run the asyncio event loop one iteration at a time when the host
+asks us to. Each iteration runs callbacks that are ready and polls I/O
+without blocking. Coroutines are exposed as Rust futures, which are completed
+by the iteration in which their asyncio task finishes.
+*/
+
+use {
+    crate::error::{format_exception, AsyncioError},
+    cpython::{NoArgs, ObjectProtocol, PyClone, PyObject, PyResult, Python},
+    std::{
+        future::Future,
+        pin::Pin,
+        sync::{Arc, Mutex},
+        task::{Context, Poll, Waker},
+    },
+};
+
+#[derive(Default)]
+struct CoroutineState {
+    result: Option<Result<PyObject, AsyncioError>>,
+    waker: Option<Waker>,
+}
+
+impl CoroutineState {
+    fn complete(&mut self, result: Result<PyObject, AsyncioError>) {
+        self.result = Some(result);
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+struct PendingCoroutine {
+    task: PyObject,
+    state: Arc<Mutex<CoroutineState>>,
+}
+
+/// A future resolving to the return value of a coroutine spawned with
+/// [MainPythonInterpreter::spawn_coroutine()](crate::MainPythonInterpreter::spawn_coroutine).
+///
+/// The future only makes progress when
+/// [MainPythonInterpreter::run_asyncio_step()](crate::MainPythonInterpreter::run_asyncio_step)
+/// is called. Polling it doesn't require the GIL. The returned `PyObject`
+/// must be dropped before the interpreter is.
+pub struct PythonCoroutineFuture {
+    state: Arc<Mutex<CoroutineState>>,
+}
+
+impl Future for PythonCoroutineFuture {
+    type Output = Result<PyObject, AsyncioError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self
+            .state
+            .lock()
+            .expect("coroutine state lock should not be poisoned");
+
+        if let Some(result) = state.result.take() {
+            Poll::Ready(result)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// An asyncio event loop and the coroutines spawned on it.
+pub(crate) struct AsyncioState {
+    event_loop: PyObject,
+    pending: Vec<PendingCoroutine>,
+}
+
+impl AsyncioState {
+    /// Create a new event loop and make it the current event loop.
+    pub(crate) fn new(py: Python) -> PyResult<Self> {
+        let asyncio = py.import("asyncio")?;
+
+        let event_loop = asyncio.call(py, "new_event_loop", NoArgs, None)?;
+        asyncio.call(py, "set_event_loop", (event_loop.clone_ref(py),), None)?;
+
+        Ok(Self {
+            event_loop,
+            pending: vec![],
+        })
+    }
+
+    /// Schedule a coroutine obtained by calling `module.function()`.
+    pub(crate) fn spawn(
+        &mut self,
+        py: Python,
+        module: &str,
+        function: &str,
+    ) -> PyResult<PythonCoroutineFuture> {
+        let coroutine = py
+            .import(module)?
+            .get(py, function)?
+            .call(py, NoArgs, None)?;
+
+        let task = self
+            .event_loop
+            .call_method(py, "create_task", (coroutine,), None)?;
+
+        let state = Arc::new(Mutex::new(CoroutineState::default()));
+
+        self.pending.push(PendingCoroutine {
+            task,
+            state: state.clone(),
+        });
+
+        Ok(PythonCoroutineFuture { state })
+    }
+
+    /// Run a single iteration of the event loop.
+    ///
+    /// Futures of coroutines that finished are completed and their wakers
+    /// are woken.
+    pub(crate) fn step(&mut self, py: Python) -> PyResult<()> {
+        // Scheduling stop() before run_forever() runs exactly 1 iteration.
+        // Since a callback is ready, polling for I/O doesn't block.
+        let stop = self.event_loop.getattr(py, "stop")?;
+        self.event_loop
+            .call_method(py, "call_soon", (stop,), None)?;
+        self.event_loop
+            .call_method(py, "run_forever", NoArgs, None)?;
+
+        let mut i = 0;
+        while i < self.pending.len() {
+            let done = self.pending[i]
+                .task
+                .call_method(py, "done", NoArgs, None)?
+                .is_true(py)?;
+
+            if done {
+                let pending = self.pending.swap_remove(i);
+                let result = task_result(py, &pending.task)?;
+
+                pending
+                    .state
+                    .lock()
+                    .expect("coroutine state lock should not be poisoned")
+                    .complete(result);
+            } else {
+                i += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Cancel pending coroutines and close the event loop.
+    pub(crate) fn close(self, py: Python) {
+        for pending in self.pending {
+            let _ = pending.task.call_method(py, "cancel", NoArgs, None);
+
+            pending
+                .state
+                .lock()
+                .expect("coroutine state lock should not be poisoned")
+                .complete(Err(AsyncioError::Cancelled));
+        }
+
+        let _ = self.event_loop.call_method(py, "close", NoArgs, None);
+    }
+}
+
+/// Obtain the outcome of a finished asyncio task.
+fn task_result(py: Python, task: &PyObject) -> PyResult<Result<PyObject, AsyncioError>> {
+    if task
+        .call_method(py, "cancelled", NoArgs, None)?
+        .is_true(py)?
+    {
+        return Ok(Err(AsyncioError::Cancelled));
+    }
+
+    Ok(task
+        .call_method(py, "result", NoArgs, None)
+        .map_err(|err| AsyncioError::Python(format_exception(py, err))))
+}
@@ -0,0 +1,200 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Bridge PEP 578 audit events to Rust.
+
+Hooks are registered with `PySys_AddAuditHook()`, which works before the
+interpreter is initialized and, unlike `sys.addaudithook()`, is invisible to
+Python code. Audit hooks can't be removed, so state they use is leaked to
+live as long as the process.
+*/
+
+use {
+    crate::{
+        config::{AuditEvent, AuditHook},
+        NewInterpreterError,
+    },
+    cpython::{exc::RuntimeError, ObjectProtocol, PyClone, PyErr, PyObject, Python},
+    python3_sys as pyffi,
+    std::{
+        ffi::CStr,
+        fs::File,
+        io::Write,
+        os::raw::{c_char, c_int, c_void},
+        path::Path,
+        sync::Mutex,
+        time::SystemTime,
+    },
+};
+
+/// Signature of a C audit hook.
+pub(crate) type AuditHookFunction =
+    unsafe extern "C" fn(*const c_char, *mut pyffi::PyObject, *mut c_void) -> c_int;
+
+extern "C" {
+    fn PySys_AddAuditHook(hook: AuditHookFunction, user_data: *mut c_void) -> c_int;
+}
+
+/// Register a C audit hook.
+///
+/// `user_data` is passed to every invocation of `hook`. It must remain
+/// valid for the lifetime of the process.
+///
+/// Returns false if an existing hook rejected adding the hook.
+pub(crate) fn add_audit_hook(hook: AuditHookFunction, user_data: *mut c_void) -> bool {
+    unsafe { PySys_AddAuditHook(hook, user_data) == 0 }
+}
+
+/// Whether an audit event name matches a pattern.
+///
+/// Patterns ending in `.` match all events with that prefix. Other patterns
+/// must match exactly.
+pub(crate) fn event_matches(pattern: &str, event: &str) -> bool {
+    if pattern.ends_with('.') {
+        event.starts_with(pattern)
+    } else {
+        event == pattern
+    }
+}
+
+/// State of the audit hook bridging events to Rust.
+struct AuditState {
+    events: Vec<String>,
+    hooks: Vec<AuditHook>,
+    log: Option<Mutex<File>>,
+}
+
+impl AuditState {
+    fn wants(&self, event: &str) -> bool {
+        self.events.is_empty()
+            || self
+                .events
+                .iter()
+                .any(|pattern| event_matches(pattern, event))
+    }
+}
+
+/// Audit hook dispatching to the [AuditState] pointed to by `user_data`.
+unsafe extern "C" fn audit_hook(
+    event: *const c_char,
+    args: *mut pyffi::PyObject,
+    user_data: *mut c_void,
+) -> c_int {
+    let state = &*(user_data as *const AuditState);
+    let event = CStr::from_ptr(event).to_string_lossy();
+
+    if !state.wants(&event) {
+        return 0;
+    }
+
+    let py = Python::assume_gil_acquired();
+    let args = PyObject::from_borrowed_ptr(py, args);
+
+    if let Some(log) = &state.log {
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+        let args = args
+            .repr(py)
+            .map(|s| s.to_string_lossy(py).to_string())
+            .unwrap_or_else(|_| "<unknown>".to_string());
+
+        // Failing the operation because the log couldn't be written would
+        // make applications unusable when e.g. the disk is full.
+        if let Ok(mut f) = log.lock() {
+            let _ = writeln!(f, "{:.6}\t{}\t{}", time, event, args);
+        }
+    }
+
+    for hook in &state.hooks {
+        let event = AuditEvent {
+            name: event.to_string(),
+            args: args.clone_ref(py),
+        };
+
+        if let Err(message) = hook(event) {
+            PyErr::new::<RuntimeError, _>(py, message).restore(py);
+            return -1;
+        }
+    }
+
+    0
+}
+
+/// Register an audit hook calling `hooks` and writing to `log_path`.
+///
+/// Only events matching a pattern in `events` are processed. If empty, all
+/// events are.
+pub(crate) fn install_audit_hooks(
+    hooks: &[AuditHook],
+    events: &[String],
+    log_path: Option<&Path>,
+) -> Result<(), NewInterpreterError> {
+    let log = if let Some(path) = log_path {
+        let open = || {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+        };
+
+        Some(Mutex::new(open().map_err(|source| {
+            NewInterpreterError::Io {
+                context: "opening audit log".to_string(),
+                path: path.to_path_buf(),
+                source,
+            }
+        })?))
+    } else {
+        None
+    };
+
+    let state = Box::into_raw(Box::new(AuditState {
+        events: events.to_vec(),
+        hooks: hooks.to_vec(),
+        log,
+    }));
+
+    if add_audit_hook(audit_hook, state as *mut c_void) {
+        Ok(())
+    } else {
+        Err(NewInterpreterError::InvalidConfig(
+            "an existing audit hook rejected adding audit hooks".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_matches() {
+        assert!(event_matches("open", "open"));
+        assert!(!event_matches("open", "os.open"));
+        assert!(event_matches("socket.", "socket.connect"));
+        assert!(!event_matches("socket.", "socket"));
+    }
+
+    #[test]
+    fn test_wants() {
+        let mut state = AuditState {
+            events: vec![],
+            hooks: vec![],
+            log: None,
+        };
+        assert!(state.wants("open"));
+
+        state.events = vec!["open".to_string(), "socket.".to_string()];
+        assert!(state.wants("open"));
+        assert!(state.wants("socket.bind"));
+        assert!(!state.wants("import"));
+    }
+}
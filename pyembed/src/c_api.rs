@@ -0,0 +1,330 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+C API for embedding applications not written in Rust.
+
+Functions are declared in `include/pyembed.h`. Interpreter configuration is
+a JSON object so C callers don't need to mirror Rust data structures.
+
+Functions never unwind into the caller. On failure they return `NULL` or
+`-1` and `pyembed_last_error()` describes what went wrong.
+*/
+
+use {
+    crate::{
+        error::format_exception, MainPythonInterpreter, OxidizedPythonInterpreterConfig,
+        PackedResourcesSource, PythonInterpreterProfile,
+    },
+    cpython::ObjectProtocol,
+    serde_json::{Map, Value},
+    std::{
+        cell::RefCell,
+        convert::TryFrom,
+        ffi::{CStr, CString, OsString},
+        os::raw::{c_char, c_int},
+        panic::{catch_unwind, AssertUnwindSafe},
+        path::PathBuf,
+        ptr,
+    },
+};
+
+/// Opaque handle to an interpreter, as seen by C.
+pub struct PyembedInterpreter {
+    inner: MainPythonInterpreter<'static, 'static, 'static>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_last_error(message: impl Into<Vec<u8>>) {
+    let mut message = message.into();
+    // Interior NULs would truncate the message. Dropping them is better
+    // than failing to report the error.
+    message.retain(|b| *b != 0);
+
+    LAST_ERROR.with(|e| *e.borrow_mut() = CString::new(message).ok());
+}
+
+/// Run a function, converting errors and panics to a return value.
+fn guard<T>(default: T, f: impl FnOnce() -> Result<T, String>) -> T {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(message)) => {
+            set_last_error(message);
+            default
+        }
+        Err(_) => {
+            set_last_error("panic in pyembed");
+            default
+        }
+    }
+}
+
+unsafe fn c_str<'a>(value: *const c_char, name: &str) -> Result<&'a str, String> {
+    if value.is_null() {
+        return Err(format!("{} must not be NULL", name));
+    }
+
+    CStr::from_ptr(value)
+        .to_str()
+        .map_err(|_| format!("{} is not valid UTF-8", name))
+}
+
+fn string_list(key: &str, value: &Value) -> Result<Vec<String>, String> {
+    value
+        .as_array()
+        .and_then(|values| {
+            values
+                .iter()
+                .map(|v| v.as_str().map(|s| s.to_string()))
+                .collect::<Option<Vec<_>>>()
+        })
+        .ok_or_else(|| format!("{} must be a list of strings", key))
+}
+
+fn string(key: &str, value: &Value) -> Result<String, String> {
+    value
+        .as_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| format!("{} must be a string", key))
+}
+
+fn boolean(key: &str, value: &Value) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("{} must be a boolean", key))
+}
+
+/// Derive an interpreter config from a JSON object.
+///
+/// Keys not present keep the values of
+/// `OxidizedPythonInterpreterConfig::default()`. Unknown keys are an error
+/// so typos don't go unnoticed.
+fn config_from_json(json: &str) -> Result<OxidizedPythonInterpreterConfig<'static>, String> {
+    let object: Map<String, Value> =
+        serde_json::from_str(json).map_err(|e| format!("invalid config JSON: {}", e))?;
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+
+    for (key, value) in &object {
+        match key.as_str() {
+            "profile" => {
+                config.interpreter_config.profile =
+                    PythonInterpreterProfile::try_from(string(key, value)?.as_str())?;
+            }
+            "argv" => {
+                config.argv = Some(
+                    string_list(key, value)?
+                        .into_iter()
+                        .map(OsString::from)
+                        .collect(),
+                );
+            }
+            "module_search_paths" => {
+                config.interpreter_config.module_search_paths = Some(
+                    string_list(key, value)?
+                        .into_iter()
+                        .map(PathBuf::from)
+                        .collect(),
+                );
+            }
+            "packed_resources" => {
+                config.packed_resources = string_list(key, value)?
+                    .into_iter()
+                    .map(|path| PackedResourcesSource::MemoryMappedPath(PathBuf::from(path)))
+                    .collect();
+            }
+            "oxidized_importer" => {
+                config.oxidized_importer = boolean(key, value)?;
+            }
+            "filesystem_importer" => {
+                config.filesystem_importer = boolean(key, value)?;
+            }
+            "origin" => {
+                config.origin = Some(PathBuf::from(string(key, value)?));
+            }
+            "run_command" => {
+                config.interpreter_config.run_command = Some(string(key, value)?);
+            }
+            "run_module" => {
+                config.interpreter_config.run_module = Some(string(key, value)?);
+            }
+            _ => return Err(format!("unknown config key: {}", key)),
+        }
+    }
+
+    Ok(config)
+}
+
+/// Create and initialize an interpreter from a JSON config.
+///
+/// Returns `NULL` on error.
+///
+/// # Safety
+///
+/// `config_json` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_new_interpreter(
+    config_json: *const c_char,
+) -> *mut PyembedInterpreter {
+    guard(ptr::null_mut(), || {
+        let config = config_from_json(c_str(config_json, "config_json")?)?;
+        let inner = MainPythonInterpreter::new(config).map_err(|e| e.to_string())?;
+
+        Ok(Box::into_raw(Box::new(PyembedInterpreter { inner })))
+    })
+}
+
+/// Run the configured main, finalize, and free the interpreter.
+///
+/// Returns an exit code, or `-1` if `interpreter` is `NULL`.
+///
+/// # Safety
+///
+/// `interpreter` must have been returned by `pyembed_new_interpreter()`
+/// and not freed. It can't be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_run_main(interpreter: *mut PyembedInterpreter) -> c_int {
+    guard(-1, || {
+        if interpreter.is_null() {
+            return Err("interpreter must not be NULL".to_string());
+        }
+
+        Ok(Box::from_raw(interpreter).inner.py_runmain())
+    })
+}
+
+/// Evaluate a Python expression and return `str()` of the result.
+///
+/// Returns `NULL` on error, including if the expression raises. The
+/// returned string must be freed with `pyembed_free_string()`.
+///
+/// # Safety
+///
+/// `interpreter` must be a live interpreter and `code` a NUL terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_eval(
+    interpreter: *mut PyembedInterpreter,
+    code: *const c_char,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let interpreter = interpreter
+            .as_mut()
+            .ok_or_else(|| "interpreter must not be NULL".to_string())?;
+        let code = c_str(code, "code")?;
+
+        let py = interpreter.inner.acquire_gil();
+        let result = py
+            .eval(code, None, None)
+            .and_then(|value| value.str(py))
+            .map_err(|err| format_exception(py, err))?;
+
+        CString::new(result.to_string_lossy(py).as_bytes())
+            .map(CString::into_raw)
+            .map_err(|_| "result contains a NUL byte".to_string())
+    })
+}
+
+/// Finalize and free an interpreter. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `interpreter` must have been returned by `pyembed_new_interpreter()`
+/// and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_free_interpreter(interpreter: *mut PyembedInterpreter) {
+    if !interpreter.is_null() {
+        guard((), || {
+            drop(Box::from_raw(interpreter));
+            Ok(())
+        })
+    }
+}
+
+/// Free a string returned by this API. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `value` must have been returned by this API and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_free_string(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/// Describe the last error on the current thread.
+///
+/// Returns `NULL` if no error occurred. The string is owned by pyembed and
+/// valid until the next call into this API on the same thread.
+#[no_mangle]
+pub extern "C" fn pyembed_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| match &*e.borrow() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_from_json() {
+        let config = config_from_json(
+            r#"{
+                "profile": "isolated",
+                "argv": ["app", "--verbose"],
+                "packed_resources": ["packed-resources"],
+                "filesystem_importer": true,
+                "run_module": "app"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.interpreter_config.profile,
+            PythonInterpreterProfile::Isolated
+        );
+        assert_eq!(
+            config.argv,
+            Some(vec![OsString::from("app"), OsString::from("--verbose")])
+        );
+        assert_eq!(
+            config.packed_resources,
+            vec![PackedResourcesSource::MemoryMappedPath(PathBuf::from(
+                "packed-resources"
+            ))]
+        );
+        assert!(config.filesystem_importer);
+        assert_eq!(
+            config.interpreter_config.run_module,
+            Some("app".to_string())
+        );
+
+        assert!(config_from_json("{}").is_ok());
+        assert!(config_from_json("[]").is_err());
+        assert!(config_from_json(r#"{"argv": "app"}"#).is_err());
+        assert!(config_from_json(r#"{"unknown": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_header_declares_functions() {
+        let header = include_str!("../include/pyembed.h");
+
+        for name in &[
+            "pyembed_new_interpreter",
+            "pyembed_run_main",
+            "pyembed_eval",
+            "pyembed_free_interpreter",
+            "pyembed_free_string",
+            "pyembed_last_error",
+        ] {
+            assert!(header.contains(&format!("{}(", name)), "{}", name);
+        }
+    }
+}
@@ -6,16 +6,19 @@
 
 use {
     crate::NewInterpreterError,
+    cpython::PyObject,
     python3_sys as pyffi,
     python_packaging::interpreter::{
-        MemoryAllocatorBackend, PythonInterpreterConfig, PythonInterpreterProfile,
-        TerminfoResolution,
+        MemoryAllocatorBackend, ModuleSearchPathsMode, PythonInterpreterConfig,
+        PythonInterpreterProfile, RemoteResourcesConfig, RestrictedModeConfig, RunModuleMode,
+        RunReportSection, SelfUpdateConfig, TerminfoResolution,
     },
     std::{
+        collections::{BTreeMap, BTreeSet, HashMap},
         convert::TryFrom,
         ffi::{CString, OsString},
         ops::Deref,
-        path::PathBuf,
+        path::{Path, PathBuf},
     },
 };
 
@@ -26,9 +29,28 @@ pub struct ExtensionModule {
     pub name: CString,
 
     /// Extension module initialization function.
+    ///
+    /// Both single-phase and multi-phase initialization are supported. The
+    /// function may return a fully initialized module object or a
+    /// `PyModuleDef` obtained from `PyModuleDef_Init()`, in which case the
+    /// module is created from the definition's slots (e.g. `Py_mod_exec`)
+    /// at import time.
     pub init_func: unsafe extern "C" fn() -> *mut pyffi::PyObject,
 }
 
+/// Defines an extra frozen module to make available.
+#[derive(Clone, Debug)]
+pub struct FrozenModule<'a> {
+    /// Name of the module.
+    pub name: CString,
+
+    /// Marshalled code object of the module.
+    pub code: &'a [u8],
+
+    /// Whether the module is a package.
+    pub is_package: bool,
+}
+
 /// A source for packed resources data.
 #[derive(Clone, Debug, PartialEq)]
 pub enum PackedResourcesSource<'a> {
@@ -37,6 +59,12 @@ pub enum PackedResourcesSource<'a> {
 
     /// Load resources data from a filesystem path using memory mapped I/O.
     MemoryMappedPath(PathBuf),
+
+    /// Like `MemoryMappedPath` except nothing is loaded if the path doesn't exist.
+    ///
+    /// This allows resources in a file to take precedence over resources
+    /// from an earlier source, which is used if the file isn't installed.
+    OptionalMemoryMappedPath(PathBuf),
 }
 
 impl<'a> From<&'a [u8]> for PackedResourcesSource<'a> {
@@ -45,6 +73,32 @@ impl<'a> From<&'a [u8]> for PackedResourcesSource<'a> {
     }
 }
 
+/// Function reversing a transform applied to resource payloads at packaging time.
+///
+/// The function receives a stored payload and returns the original data.
+pub type ResourcePayloadDecoder = fn(Vec<u8>) -> Result<Vec<u8>, String>;
+
+/// Function transforming process arguments before they define `sys.argv`.
+///
+/// The function receives the arguments, starting with the program name, and
+/// returns the arguments to use.
+pub type ArgvRewriter = fn(Vec<OsString>) -> Vec<OsString>;
+
+/// A Python audit event, as defined by PEP 578.
+pub struct AuditEvent {
+    /// Name of the event, such as `open` or `socket.connect`.
+    pub name: String,
+
+    /// Tuple holding the arguments of the event.
+    pub args: PyObject,
+}
+
+/// Function called for Python audit events.
+///
+/// Returning an error raises a `RuntimeError` with the message, aborting
+/// the operation that raised the event.
+pub type AuditHook = fn(AuditEvent) -> Result<(), String>;
+
 /// Configure a Python interpreter.
 ///
 /// This type defines the configuration of a Python interpreter. It is used
@@ -178,11 +232,28 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// relative to the process's current working directory.
     pub packed_resources: Vec<PackedResourcesSource<'a>>,
 
+    /// Function decoding Python module payloads in `packed_resources`.
+    ///
+    /// If set, in-memory Python module source and bytecode indexed from
+    /// `packed_resources` is passed through this function before use. It
+    /// must reverse the transform applied to these payloads when they were
+    /// packaged. Resources indexed from other sources aren't decoded.
+    pub resource_payload_decoder: Option<ResourcePayloadDecoder>,
+
     /// Extra extension modules to make available to the interpreter.
     ///
     /// The values will effectively be passed to ``PyImport_ExtendInitTab()``.
     pub extra_extension_modules: Option<Vec<ExtensionModule>>,
 
+    /// Extra frozen modules to make available to the interpreter.
+    ///
+    /// The values will be appended to ``PyImport_FrozenModules``, making
+    /// them importable via the ``FrozenImporter`` and ``OxidizedFinder``
+    /// without unmarshalling them from packed resources data. Entries take
+    /// precedence over frozen modules of the same name compiled into
+    /// libpython.
+    pub extra_frozen_modules: Option<Vec<FrozenModule<'a>>>,
+
     /// Command line arguments to initialize `sys.argv` with.
     ///
     /// If `Some(T)`, interpreter initialization will set `PyConfig.argv`
@@ -194,6 +265,24 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// `.interpreter_config.argv` is `Some(T)`.
     pub argv: Option<Vec<OsString>>,
 
+    /// Function transforming arguments before they initialize `sys.argv`.
+    ///
+    /// If set, the function is called during resolution with the arguments
+    /// that will populate `PyConfig.argv`: `.interpreter_config.argv` if
+    /// set, otherwise the resolved value of `argv`. This can be used to strip
+    /// arguments meant for a wrapper or to inject default arguments.
+    ///
+    /// The function is called before `multicall_run_modules` is consulted.
+    pub argv_rewriter: Option<ArgvRewriter>,
+
+    /// Whether to set `sys.orig_argv` to the original process arguments.
+    ///
+    /// The original arguments are those before `argv_rewriter` and
+    /// `multicall_subcommand` modified them. Python 3.10+ defines
+    /// `sys.orig_argv` itself. Setting this makes the attribute available on
+    /// older versions and reflects arguments before our rewriting.
+    pub sys_orig_argv: bool,
+
     /// Whether to set sys.argvb with bytes versions of process arguments.
     ///
     /// On Windows, bytes will be UTF-16. On POSIX, bytes will be raw char*
@@ -231,6 +320,242 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// the directory specified containing a ``\n`` delimited list of modules
     /// loaded in ``sys.modules``.
     pub write_modules_directory_env: Option<String>,
+
+    /// Environment variable holding the directory to write a run report.
+    ///
+    /// If this value is set and the environment variable it refers to is set,
+    /// modules executed and resources loaded by our importer are recorded.
+    /// On interpreter shutdown, a ``run-report-<random>.json`` file
+    /// containing `run_report_sections` is written to the directory
+    /// specified.
+    ///
+    /// This is a more detailed alternative to `write_modules_directory_env`.
+    pub run_report_directory_env: Option<String>,
+
+    /// Sections to write to a run report.
+    ///
+    /// The environment variable `PYEMBED_RUN_REPORT_SECTIONS` can override
+    /// this at run-time with a comma delimited list of section names.
+    pub run_report_sections: BTreeSet<RunReportSection>,
+
+    /// Environment variable holding a directory overriding `origin`.
+    ///
+    /// If this value is set and the environment variable it refers to is set
+    /// to a non-empty value at run-time, its value is used as `$ORIGIN`
+    /// instead of `origin` or the directory of the current executable. This
+    /// relocates the packed resources files, filesystem-relative resources,
+    /// and anything else resolved relative to `$ORIGIN`.
+    ///
+    /// This is useful for relocatable installs and for testing alternate
+    /// resource sets without rebuilding the executable.
+    pub origin_env: Option<String>,
+
+    /// Mapping of executable names to Python modules to run.
+    ///
+    /// This enables "multi-call" binaries, where a single executable behaves
+    /// differently depending on the name it was invoked as. e.g. the same
+    /// binary can be installed or symlinked as `mytool` and `mytool-admin`.
+    ///
+    /// During resolution, the file name of the first argument (the program
+    /// name), minus any `.exe` suffix, is looked up in this mapping. If
+    /// found, `.interpreter_config.run_module` is set to the mapped value
+    /// and `run_command` and `run_filename` are cleared.
+    ///
+    /// If the mapping is empty or no entry matches, the run mode from
+    /// `.interpreter_config` is used.
+    pub multicall_run_modules: HashMap<String, String>,
+
+    /// Whether the first argument can select an entry from `multicall_run_modules`.
+    ///
+    /// If `true` and the program name doesn't match an entry in
+    /// `multicall_run_modules`, the second process argument is looked up
+    /// instead. If it matches, it is removed from the arguments, yielding
+    /// "subcommand" style dispatch (`mytool admin ...`).
+    pub multicall_subcommand: bool,
+
+    /// Environment variable selecting an entry from `multicall_run_modules`.
+    ///
+    /// If this value is set and the environment variable it refers to is set
+    /// to a non-empty value at run-time, that value is looked up in
+    /// `multicall_run_modules` instead of the program name or first argument.
+    /// Resolution fails if it doesn't name an entry.
+    pub multicall_env: Option<String>,
+
+    /// Environment variable holding Python code to run instead of the configured code.
+    ///
+    /// If this value is set and the environment variable it refers to is set
+    /// to a non-empty value at run-time, `.interpreter_config.run_command` is
+    /// set to its value and `run_module` and `run_filename` are cleared. This
+    /// takes precedence over `multicall_run_modules`.
+    ///
+    /// PyOxidizer uses this to verify imports in a built executable without
+    /// building a variant of it.
+    pub run_command_env: Option<String>,
+
+    /// Metadata describing how the binary was built.
+    ///
+    /// Keys and values are opaque to the interpreter. PyOxidizer populates
+    /// this with the PyOxidizer version, target triple, build timestamp, and
+    /// other details. Values are available at run-time via
+    /// `MainPythonInterpreter::build_info()` and
+    /// `oxidized_importer.build_info()`.
+    pub build_info: BTreeMap<String, String>,
+
+    /// Whether to provide line editing for interactive input.
+    ///
+    /// If true, Python's function for reading lines from a terminal (used by
+    /// the REPL and `input()`) is replaced by one backed by `rustyline`. This
+    /// provides line editing and history even when the `readline` extension
+    /// module isn't available, such as in statically linked builds. History is
+    /// persisted to `~/.python_history`, like Python's own REPL.
+    ///
+    /// If the `readline` module is imported, it replaces our function.
+    ///
+    /// Requires the `line-editing` crate feature.
+    pub line_editing: bool,
+
+    /// Whether to forward Python log records to the Rust `log` crate.
+    ///
+    /// If true, a handler is added to the root logger of Python's `logging`
+    /// module. It forwards records to whatever logger is registered with the
+    /// `log` crate, using the Python logger name as the target. The level of
+    /// the root logger isn't changed.
+    ///
+    /// Requires the `log-bridge` crate feature.
+    pub python_logging_to_rust: bool,
+
+    /// Mapping of shared library names to paths of bundled shared libraries.
+    ///
+    /// If non-empty, `ctypes.util.find_library()` and `ctypes.CDLL()` are
+    /// patched during interpreter initialization to look up the name they
+    /// are given in this mapping. If the name is present and the mapped file
+    /// exists, the mapped path is used. Otherwise the name is resolved by
+    /// the original implementation, which searches system locations.
+    ///
+    /// Names are matched exactly. e.g. `find_library("ssl")` looks up `ssl`
+    /// and `CDLL("libssl.so.1.1")` looks up `libssl.so.1.1`.
+    ///
+    /// `$ORIGIN` in paths is expanded to the directory of the current
+    /// executable. Relative paths are evaluated relative to `$ORIGIN`.
+    pub ctypes_libraries: BTreeMap<String, PathBuf>,
+
+    /// Whether `gettext` can find message catalogs in package resources.
+    ///
+    /// If true, package resources named `<language>/LC_MESSAGES/<domain>.mo`
+    /// are indexed by domain and language. `gettext.find()` and
+    /// `gettext.translation()` are patched to consult this index when no
+    /// catalog is found on the filesystem.
+    ///
+    /// Requires `oxidized_importer`.
+    pub gettext_catalogs: bool,
+
+    /// Directory in which to write in-memory gettext message catalogs.
+    ///
+    /// If set, in-memory catalogs are written to this directory on first use
+    /// so `gettext.find()` can return a path to them. Otherwise they are only
+    /// available via `gettext.translation()`.
+    ///
+    /// `$ORIGIN` in the path is expanded to the directory of the current
+    /// executable.
+    pub gettext_cache_directory: Option<PathBuf>,
+
+    /// How `.interpreter_config.run_module` is run by `MainPythonInterpreter::py_runmain()`.
+    ///
+    /// `RunModuleMode::RunMain` lets `Py_RunMain()` run the module.
+    /// `RunModuleMode::Runpy` runs it via `MainPythonInterpreter::run_module()`
+    /// with `alter_sys` set, which retains `sys.argv[0]` for modules loaded
+    /// from memory.
+    pub run_module_mode: RunModuleMode,
+
+    /// How `.interpreter_config.module_search_paths` relates to the default search paths.
+    ///
+    /// `ModuleSearchPathsMode::Replace` uses only the configured paths.
+    /// `ModuleSearchPathsMode::Extend` adds them before the paths Python
+    /// computes by default, as if they were listed in `PYTHONPATH`. They
+    /// also precede `.interpreter_config.python_path_env`.
+    pub module_search_paths_mode: ModuleSearchPathsMode,
+
+    /// Directory to change the current working directory to.
+    ///
+    /// The working directory is changed before the interpreter is
+    /// initialized.
+    ///
+    /// `$ORIGIN` in the path is expanded to the directory of the current
+    /// executable. Relative paths are evaluated relative to `$ORIGIN`.
+    pub working_directory: Option<PathBuf>,
+
+    /// File mode creation mask to set before the interpreter is initialized.
+    ///
+    /// Only has an effect on UNIX platforms.
+    pub umask: Option<u32>,
+
+    /// Name of the process shown by tools like `ps`, `top`, and `killall`.
+    ///
+    /// The name is set before the interpreter is initialized. On Linux, it
+    /// is set via `prctl(PR_SET_NAME)`, which truncates it to 15 bytes.
+    /// Has no effect on other platforms.
+    pub process_title: Option<String>,
+
+    /// Where `OxidizedFinder` logs its `find_spec()` decisions.
+    ///
+    /// `stderr` logs to stderr. Other values are paths of files to append
+    /// to. Each call is logged with the module name, the decision made
+    /// (in-memory, relative path, built-in, frozen, or not found), and how
+    /// long it took.
+    ///
+    /// The `OXIDIZED_IMPORTER_DEBUG` environment variable enables logging
+    /// with the same values and takes precedence.
+    pub importer_debug: Option<String>,
+
+    /// Update the application from a signed update manifest.
+    ///
+    /// If set, an update staged by a previous run is installed before the
+    /// interpreter is initialized and the `oxidized_importer.updates` module
+    /// checks for and downloads updates.
+    ///
+    /// Requires the `self-update` crate feature.
+    pub self_update: Option<SelfUpdateConfig>,
+
+    /// Import modules missing from the binary from a remote resource server.
+    ///
+    /// If set, a finder appended to `sys.meta_path` looks up modules no other
+    /// finder found in a signed resource index and imports their verified
+    /// source, which is cached in the configured directory.
+    ///
+    /// This is experimental and requires the `remote-resources` crate feature.
+    pub remote_resources: Option<RemoteResourcesConfig>,
+
+    /// Deny Python code access to facilities able to affect the host system.
+    ///
+    /// If set, modules providing facilities not allowed by the config are
+    /// removed from `sys.modules` and can't be imported once the interpreter
+    /// is initialized. A PEP 578 audit hook additionally rejects operations
+    /// using these facilities, such as creating sockets or processes.
+    pub restricted_mode: Option<RestrictedModeConfig>,
+
+    /// Functions called for Python audit events.
+    ///
+    /// The functions are registered with `PySys_AddAuditHook()` before the
+    /// interpreter is initialized, so they observe all events raised by
+    /// Python code. Unlike hooks added with `sys.addaudithook()`, Python code
+    /// can't observe or tamper with them.
+    pub audit_hooks: Vec<AuditHook>,
+
+    /// Names of audit events passed to `audit_hooks` and written to `audit_log`.
+    ///
+    /// Values ending in `.` match all events with that prefix, e.g.
+    /// `socket.` matches `socket.connect`. If empty, all events are passed.
+    pub audit_events: Vec<String>,
+
+    /// Path of a file audit events are appended to.
+    ///
+    /// Each line holds the time of the event in seconds since the UNIX
+    /// epoch, the name of the event, and the `repr()` of its arguments,
+    /// separated by tabs.
+    ///
+    /// `$ORIGIN` is expanded to the directory of the current executable.
+    /// Relative paths are evaluated relative to `$ORIGIN`.
+    pub audit_log: Option<PathBuf>,
 }
 
 impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
@@ -254,14 +579,43 @@ impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
             oxidized_importer: false,
             filesystem_importer: true,
             packed_resources: vec![],
+            resource_payload_decoder: None,
             extra_extension_modules: None,
+            extra_frozen_modules: None,
             argv: None,
+            argv_rewriter: None,
+            sys_orig_argv: false,
             argvb: false,
             sys_frozen: false,
             sys_meipass: false,
             terminfo_resolution: TerminfoResolution::Dynamic,
             tcl_library: None,
             write_modules_directory_env: None,
+            run_report_directory_env: None,
+            run_report_sections: RunReportSection::all().into_iter().collect(),
+            origin_env: None,
+            multicall_run_modules: HashMap::new(),
+            multicall_subcommand: false,
+            multicall_env: None,
+            run_command_env: None,
+            build_info: BTreeMap::new(),
+            line_editing: false,
+            python_logging_to_rust: false,
+            ctypes_libraries: BTreeMap::new(),
+            gettext_catalogs: false,
+            gettext_cache_directory: None,
+            run_module_mode: RunModuleMode::RunMain,
+            module_search_paths_mode: ModuleSearchPathsMode::Replace,
+            working_directory: None,
+            umask: None,
+            process_title: None,
+            importer_debug: None,
+            self_update: None,
+            remote_resources: None,
+            restricted_mode: None,
+            audit_hooks: vec![],
+            audit_events: vec![],
+            audit_log: None,
         }
     }
 }
@@ -271,7 +625,19 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
     pub fn resolve(
         self,
     ) -> Result<ResolvedOxidizedPythonInterpreterConfig<'a>, NewInterpreterError> {
-        let argv = if let Some(args) = self.argv {
+        self.resolve_with_env(|key| std::env::var_os(key))
+    }
+
+    /// Resolve values, reading environment variables via `getenv`.
+    fn resolve_with_env(
+        self,
+        getenv: impl Fn(&String) -> Option<OsString>,
+    ) -> Result<ResolvedOxidizedPythonInterpreterConfig<'a>, NewInterpreterError> {
+        self.interpreter_config
+            .validate()
+            .map_err(NewInterpreterError::InvalidConfig)?;
+
+        let mut argv = if let Some(args) = self.argv {
             Some(args)
         } else if self.interpreter_config.argv.is_some() {
             None
@@ -279,24 +645,112 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
             Some(std::env::args_os().collect::<Vec<_>>())
         };
 
+        let mut interpreter_config = self.interpreter_config;
+
+        // `.interpreter_config.argv` takes precedence when defining sys.argv.
+        let orig_argv = interpreter_config
+            .argv
+            .as_ref()
+            .or_else(|| argv.as_ref())
+            .cloned()
+            .unwrap_or_default();
+
+        if let Some(rewriter) = self.argv_rewriter {
+            if let Some(args) = interpreter_config.argv.take() {
+                interpreter_config.argv = Some(rewriter(args));
+            } else if let Some(args) = argv.take() {
+                argv = Some(rewriter(args));
+            }
+        }
+
+        if !self.multicall_run_modules.is_empty() {
+            // `.interpreter_config.argv` takes precedence when defining sys.argv.
+            let args = if let Some(args) = interpreter_config.argv.as_mut() {
+                args
+            } else {
+                argv.as_mut().expect("argv should be defined")
+            };
+
+            let selected = self
+                .multicall_env
+                .as_ref()
+                .and_then(&getenv)
+                .filter(|value| !value.is_empty());
+
+            let module = if let Some(name) = selected {
+                let name = name.to_string_lossy();
+
+                Some(
+                    self.multicall_run_modules
+                        .get(name.as_ref())
+                        .cloned()
+                        .ok_or_else(|| {
+                            let mut available = self
+                                .multicall_run_modules
+                                .keys()
+                                .map(|k| k.as_str())
+                                .collect::<Vec<_>>();
+                            available.sort_unstable();
+
+                            NewInterpreterError::InvalidConfig(format!(
+                                "{} does not name an entry point; available: {}",
+                                name,
+                                available.join(", ")
+                            ))
+                        })?,
+                )
+            } else {
+                resolve_multicall_run_module(
+                    &self.multicall_run_modules,
+                    args,
+                    self.multicall_subcommand,
+                )
+            };
+
+            if let Some(module) = module {
+                interpreter_config.run_command = None;
+                interpreter_config.run_filename = None;
+                interpreter_config.run_module = Some(module);
+            }
+        }
+
+        let run_command_override = self
+            .run_command_env
+            .as_ref()
+            .and_then(&getenv)
+            .filter(|value| !value.is_empty());
+
+        if let Some(command) = run_command_override {
+            interpreter_config.run_command = Some(command.into_string().map_err(|value| {
+                NewInterpreterError::StringConversion {
+                    context: "resolving run command override".to_string(),
+                    value: value.to_string_lossy().to_string(),
+                }
+            })?);
+            interpreter_config.run_filename = None;
+            interpreter_config.run_module = None;
+        }
+
         let exe = if let Some(exe) = self.exe {
             exe
         } else {
-            dunce::canonicalize(
-                std::env::current_exe().map_err(|_| {
-                    NewInterpreterError::Simple("could not obtain current executable")
-                })?,
-            )
-            .map_err(|_| NewInterpreterError::Simple("could not obtain current executable path"))?
+            dunce::canonicalize(std::env::current_exe().map_err(NewInterpreterError::CurrentExe)?)
+                .map_err(NewInterpreterError::CurrentExe)?
         };
 
-        let origin = if let Some(origin) = self.origin {
+        let origin_override = self
+            .origin_env
+            .as_ref()
+            .and_then(&getenv)
+            .filter(|value| !value.is_empty());
+
+        let origin = if let Some(origin) = origin_override {
+            PathBuf::from(origin)
+        } else if let Some(origin) = self.origin {
             origin
         } else {
             exe.parent()
-                .ok_or(NewInterpreterError::Simple(
-                    "unable to obtain current executable parent directory",
-                ))?
+                .ok_or_else(|| NewInterpreterError::Origin(exe.clone()))?
                 .to_path_buf()
         };
 
@@ -312,10 +766,15 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
                         p.display().to_string().replace("$ORIGIN", &origin_string),
                     ))
                 }
+                PackedResourcesSource::OptionalMemoryMappedPath(p) => {
+                    PackedResourcesSource::OptionalMemoryMappedPath(PathBuf::from(
+                        p.display().to_string().replace("$ORIGIN", &origin_string),
+                    ))
+                }
             })
             .collect::<Vec<_>>();
 
-        let module_search_paths = match &self.interpreter_config.module_search_paths {
+        let module_search_paths = match &interpreter_config.module_search_paths {
             Some(paths) => Some(
                 paths
                     .iter()
@@ -327,6 +786,40 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
             None => None,
         };
 
+        // When extending, paths are passed like `PYTHONPATH` so Python still
+        // computes its default search paths.
+        let (module_search_paths, python_path_env) = match module_search_paths {
+            Some(paths) if self.module_search_paths_mode == ModuleSearchPathsMode::Extend => {
+                let joined = std::env::join_paths(&paths)
+                    .map_err(|e| {
+                        NewInterpreterError::InvalidConfig(format!(
+                            "unable to join module_search_paths: {}",
+                            e
+                        ))
+                    })?
+                    .into_string()
+                    .map_err(|_| {
+                        NewInterpreterError::InvalidConfig(
+                            "module_search_paths must be valid UTF-8 to extend search paths"
+                                .to_string(),
+                        )
+                    })?;
+
+                let python_path_env = match interpreter_config.python_path_env.take() {
+                    Some(existing) if !existing.is_empty() && !joined.is_empty() => {
+                        let delimiter = if cfg!(windows) { ";" } else { ":" };
+
+                        Some(format!("{}{}{}", joined, delimiter, existing))
+                    }
+                    Some(existing) if joined.is_empty() => Some(existing),
+                    _ => Some(joined),
+                };
+
+                (None, python_path_env)
+            }
+            paths => (paths, interpreter_config.python_path_env.take()),
+        };
+
         let tcl_library = if let Some(tcl_library) = self.tcl_library {
             Some(PathBuf::from(
                 tcl_library
@@ -338,26 +831,164 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
             None
         };
 
+        let gettext_cache_directory = self.gettext_cache_directory.map(|path| {
+            PathBuf::from(
+                path.display()
+                    .to_string()
+                    .replace("$ORIGIN", &origin_string),
+            )
+        });
+
+        let ctypes_libraries = self
+            .ctypes_libraries
+            .into_iter()
+            .map(|(name, path)| {
+                let path = PathBuf::from(
+                    path.display()
+                        .to_string()
+                        .replace("$ORIGIN", &origin_string),
+                );
+
+                (name, origin.join(path))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let working_directory = self.working_directory.map(|path| {
+            origin.join(PathBuf::from(
+                path.display()
+                    .to_string()
+                    .replace("$ORIGIN", &origin_string),
+            ))
+        });
+
+        let self_update = self.self_update.map(|config| SelfUpdateConfig {
+            staging_directory: origin.join(PathBuf::from(
+                config
+                    .staging_directory
+                    .display()
+                    .to_string()
+                    .replace("$ORIGIN", &origin_string),
+            )),
+            ..config
+        });
+
+        let remote_resources = self.remote_resources.map(|config| RemoteResourcesConfig {
+            cache_directory: origin.join(PathBuf::from(
+                config
+                    .cache_directory
+                    .display()
+                    .to_string()
+                    .replace("$ORIGIN", &origin_string),
+            )),
+            ..config
+        });
+
+        let audit_log = self.audit_log.map(|path| {
+            origin.join(PathBuf::from(
+                path.display()
+                    .to_string()
+                    .replace("$ORIGIN", &origin_string),
+            ))
+        });
+
         Ok(ResolvedOxidizedPythonInterpreterConfig {
             inner: Self {
                 exe: Some(exe),
                 origin: Some(origin),
                 interpreter_config: PythonInterpreterConfig {
                     module_search_paths,
-                    ..self.interpreter_config
+                    python_path_env,
+                    ..interpreter_config
                 },
                 argv,
                 packed_resources,
                 tcl_library,
+                ctypes_libraries,
+                gettext_cache_directory,
+                working_directory,
+                self_update,
+                remote_resources,
+                audit_log,
                 ..self
             },
+            orig_argv,
         })
     }
 }
 
+/// Resolve the module to run for a multi-call binary.
+///
+/// `args` are the process arguments, starting with the program name. If
+/// `subcommand` is true and the second argument selected the module, it
+/// is removed from `args`.
+fn resolve_multicall_run_module(
+    run_modules: &HashMap<String, String>,
+    args: &mut Vec<OsString>,
+    subcommand: bool,
+) -> Option<String> {
+    let program = args.get(0).and_then(|arg| {
+        let name = Path::new(arg).file_name()?.to_string_lossy().to_string();
+
+        if name.to_ascii_lowercase().ends_with(".exe") {
+            Some(name[0..name.len() - 4].to_string())
+        } else {
+            Some(name)
+        }
+    });
+
+    if let Some(module) = program.and_then(|name| run_modules.get(&name)) {
+        return Some(module.clone());
+    }
+
+    if subcommand && args.len() > 1 {
+        if let Some(module) = run_modules.get(args[1].to_string_lossy().as_ref()) {
+            args.remove(1);
+            return Some(module.clone());
+        }
+    }
+
+    None
+}
+
+/// Describes how a running interpreter was configured.
+///
+/// This is a subset of the configuration useful for introspecting a built
+/// binary. It is exposed to Python as `sys._oxidized_config`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OxidizedConfigSummary {
+    /// Whether the `oxidized_importer` meta path importer is installed.
+    pub oxidized_importer: bool,
+
+    /// Whether the standard filesystem importer is enabled.
+    pub filesystem_importer: bool,
+
+    /// Where packed resources are loaded from, in order.
+    ///
+    /// `memory` for data in memory. Otherwise the resolved filesystem path.
+    pub packed_resources: Vec<String>,
+
+    /// The memory allocator backend.
+    pub allocator_backend: MemoryAllocatorBackend,
+
+    /// What the interpreter runs as its main.
+    ///
+    /// One of `command`, `module`, `filename`, or `repl`, mirroring the
+    /// precedence Python gives the `run_*` configuration fields.
+    pub run_mode: &'static str,
+
+    /// The resolved value of `$ORIGIN`.
+    pub origin: PathBuf,
+
+    /// Whether restricted mode is enabled.
+    pub restricted_mode: bool,
+}
+
 /// An `OxidizedPythonInterpreterConfig` that has fields resolved.
 pub struct ResolvedOxidizedPythonInterpreterConfig<'a> {
     inner: OxidizedPythonInterpreterConfig<'a>,
+
+    /// Arguments before they were rewritten.
+    orig_argv: Vec<OsString>,
 }
 
 impl<'a> Deref for ResolvedOxidizedPythonInterpreterConfig<'a> {
@@ -392,6 +1023,45 @@ impl<'a> ResolvedOxidizedPythonInterpreterConfig<'a> {
             .expect("origin should have a value")
     }
 
+    /// Obtain the arguments before `argv_rewriter` and multi-call resolution modified them.
+    pub fn orig_argv(&self) -> &[OsString] {
+        &self.orig_argv
+    }
+
+    /// Obtain a summary of this configuration suitable for introspection.
+    pub fn summary(&self) -> OxidizedConfigSummary {
+        let interpreter_config = &self.inner.interpreter_config;
+
+        OxidizedConfigSummary {
+            oxidized_importer: self.inner.oxidized_importer,
+            filesystem_importer: self.inner.filesystem_importer,
+            packed_resources: self
+                .inner
+                .packed_resources
+                .iter()
+                .map(|source| match source {
+                    PackedResourcesSource::Memory(_) => "memory".to_string(),
+                    PackedResourcesSource::MemoryMappedPath(path)
+                    | PackedResourcesSource::OptionalMemoryMappedPath(path) => {
+                        path.display().to_string()
+                    }
+                })
+                .collect(),
+            allocator_backend: self.inner.allocator_backend,
+            run_mode: if interpreter_config.run_command.is_some() {
+                "command"
+            } else if interpreter_config.run_module.is_some() {
+                "module"
+            } else if interpreter_config.run_filename.is_some() {
+                "filename"
+            } else {
+                "repl"
+            },
+            origin: self.origin().clone(),
+            restricted_mode: self.inner.restricted_mode.is_some(),
+        }
+    }
+
     /// Resolve the value to use for `sys.argvb`.
     pub fn resolve_sys_argvb(&self) -> Vec<OsString> {
         if let Some(args) = &self.inner.interpreter_config.argv {
@@ -408,6 +1078,18 @@ impl<'a> ResolvedOxidizedPythonInterpreterConfig<'a> {
 mod tests {
     use {super::*, anyhow::Result};
 
+    /// An environment variables lookup for `resolve_with_env()`.
+    ///
+    /// Tests don't modify the process environment, as that races other tests.
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&String) -> Option<OsString> {
+        let vars = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), OsString::from(v)))
+            .collect::<HashMap<_, _>>();
+
+        move |key| vars.get(key).cloned()
+    }
+
     #[test]
     fn test_packed_resources_implicit_origin() -> Result<()> {
         let mut config = OxidizedPythonInterpreterConfig::default();
@@ -450,4 +1132,371 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_origin_env() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.origin = Some(PathBuf::from("/other/origin"));
+        config.origin_env = Some("PYEMBED_TEST_ORIGIN_ENV".to_string());
+        config
+            .packed_resources
+            .push(PackedResourcesSource::MemoryMappedPath(PathBuf::from(
+                "$ORIGIN/lib/packed-resources",
+            )));
+
+        let resolved = config.clone().resolve_with_env(|_| None)?;
+        assert_eq!(resolved.origin(), &PathBuf::from("/other/origin"));
+
+        let resolved = config.resolve_with_env(env(&[("PYEMBED_TEST_ORIGIN_ENV", "/override")]))?;
+
+        assert_eq!(resolved.origin(), &PathBuf::from("/override"));
+        assert_eq!(
+            resolved.packed_resources,
+            vec![PackedResourcesSource::MemoryMappedPath(PathBuf::from(
+                "/override/lib/packed-resources"
+            ))]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ctypes_libraries() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.origin = Some(PathBuf::from("/other/origin"));
+        config
+            .ctypes_libraries
+            .insert("foo".to_string(), PathBuf::from("$ORIGIN/lib/libfoo.so"));
+        config
+            .ctypes_libraries
+            .insert("bar".to_string(), PathBuf::from("lib/libbar.so"));
+        config
+            .ctypes_libraries
+            .insert("baz".to_string(), PathBuf::from("/usr/lib/libbaz.so"));
+
+        let resolved = config.resolve()?;
+
+        assert_eq!(
+            resolved.ctypes_libraries,
+            vec![
+                (
+                    "bar".to_string(),
+                    PathBuf::from("/other/origin/lib/libbar.so")
+                ),
+                ("baz".to_string(), PathBuf::from("/usr/lib/libbaz.so")),
+                (
+                    "foo".to_string(),
+                    PathBuf::from("/other/origin/lib/libfoo.so")
+                ),
+            ]
+            .into_iter()
+            .collect()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_working_directory() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.origin = Some(PathBuf::from("/other/origin"));
+        config.working_directory = Some(PathBuf::from("$ORIGIN/../var"));
+        assert_eq!(
+            config.resolve()?.working_directory,
+            Some(PathBuf::from("/other/origin/../var"))
+        );
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.origin = Some(PathBuf::from("/other/origin"));
+        config.working_directory = Some(PathBuf::from("data"));
+        assert_eq!(
+            config.resolve()?.working_directory,
+            Some(PathBuf::from("/other/origin/data"))
+        );
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.working_directory = Some(PathBuf::from("/srv/app"));
+        assert_eq!(
+            config.resolve()?.working_directory,
+            Some(PathBuf::from("/srv/app"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_log() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.origin = Some(PathBuf::from("/other/origin"));
+        config.audit_log = Some(PathBuf::from("$ORIGIN/audit.log"));
+        assert_eq!(
+            config.resolve()?.audit_log,
+            Some(PathBuf::from("/other/origin/audit.log"))
+        );
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.origin = Some(PathBuf::from("/other/origin"));
+        config.audit_log = Some(PathBuf::from("/var/log/app-audit.log"));
+        assert_eq!(
+            config.resolve()?.audit_log,
+            Some(PathBuf::from("/var/log/app-audit.log"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multicall_program_name() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.run_command = Some("pass".to_string());
+        config.argv = Some(vec!["/usr/bin/mytool-admin".into(), "arg".into()]);
+        config
+            .multicall_run_modules
+            .insert("mytool".to_string(), "mytool.main".to_string());
+        config
+            .multicall_run_modules
+            .insert("mytool-admin".to_string(), "mytool.admin".to_string());
+
+        let resolved = config.resolve()?;
+
+        assert_eq!(
+            resolved.interpreter_config.run_module,
+            Some("mytool.admin".to_string())
+        );
+        assert_eq!(resolved.interpreter_config.run_command, None);
+        assert_eq!(
+            resolved.argv,
+            Some(vec!["/usr/bin/mytool-admin".into(), "arg".into()])
+        );
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.argv = Some(vec!["tools/MyTool.EXE".into()]);
+        config
+            .multicall_run_modules
+            .insert("MyTool".to_string(), "mytool.main".to_string());
+
+        let resolved = config.resolve()?;
+
+        assert_eq!(
+            resolved.interpreter_config.run_module,
+            Some("mytool.main".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multicall_subcommand() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.argv = Some(vec!["mytool".into(), "admin".into(), "arg".into()]);
+        config
+            .multicall_run_modules
+            .insert("admin".to_string(), "mytool.admin".to_string());
+
+        // Subcommand dispatch is opt-in.
+        let resolved = config.clone().resolve()?;
+        assert_eq!(resolved.interpreter_config.run_module, None);
+
+        config.multicall_subcommand = true;
+        let resolved = config.resolve()?;
+        assert_eq!(
+            resolved.interpreter_config.run_module,
+            Some("mytool.admin".to_string())
+        );
+        assert_eq!(resolved.argv, Some(vec!["mytool".into(), "arg".into()]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multicall_env() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.argv = Some(vec!["mytool".into(), "admin".into()]);
+        config
+            .multicall_run_modules
+            .insert("mytool".to_string(), "mytool.main".to_string());
+        config
+            .multicall_run_modules
+            .insert("admin".to_string(), "mytool.admin".to_string());
+        config.multicall_subcommand = true;
+        config.multicall_env = Some("PYEMBED_TEST_MULTICALL_ENV".to_string());
+
+        // Unset or empty variables fall back to the program name.
+        let resolved = config.clone().resolve_with_env(|_| None)?;
+        assert_eq!(
+            resolved.interpreter_config.run_module,
+            Some("mytool.main".to_string())
+        );
+
+        let resolved = config
+            .clone()
+            .resolve_with_env(env(&[("PYEMBED_TEST_MULTICALL_ENV", "")]))?;
+        assert_eq!(
+            resolved.interpreter_config.run_module,
+            Some("mytool.main".to_string())
+        );
+
+        let resolved = config
+            .clone()
+            .resolve_with_env(env(&[("PYEMBED_TEST_MULTICALL_ENV", "admin")]))?;
+        assert_eq!(
+            resolved.interpreter_config.run_module,
+            Some("mytool.admin".to_string())
+        );
+        // Arguments are left alone.
+        assert_eq!(resolved.argv, Some(vec!["mytool".into(), "admin".into()]));
+
+        assert!(config
+            .resolve_with_env(env(&[("PYEMBED_TEST_MULTICALL_ENV", "missing")]))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_command_env() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.argv = Some(vec!["mytool".into()]);
+        config
+            .multicall_run_modules
+            .insert("mytool".to_string(), "mytool.main".to_string());
+        config.run_command_env = Some("PYEMBED_TEST_RUN_COMMAND_ENV".to_string());
+
+        let resolved = config.clone().resolve_with_env(|_| None)?;
+        assert_eq!(
+            resolved.interpreter_config.run_module,
+            Some("mytool.main".to_string())
+        );
+        assert_eq!(resolved.interpreter_config.run_command, None);
+
+        let resolved =
+            config.resolve_with_env(env(&[("PYEMBED_TEST_RUN_COMMAND_ENV", "import sys")]))?;
+
+        assert_eq!(resolved.interpreter_config.run_module, None);
+        assert_eq!(
+            resolved.interpreter_config.run_command,
+            Some("import sys".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_argv_rewriter() -> Result<()> {
+        fn strip_wrapper_args(args: Vec<OsString>) -> Vec<OsString> {
+            args.into_iter()
+                .filter(|arg| arg != "--wrapper-flag")
+                .collect()
+        }
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.argv = Some(vec!["mytool".into(), "--wrapper-flag".into(), "arg".into()]);
+        config.argv_rewriter = Some(strip_wrapper_args);
+
+        let resolved = config.clone().resolve()?;
+        assert_eq!(resolved.argv, Some(vec!["mytool".into(), "arg".into()]));
+        assert_eq!(
+            resolved.orig_argv().to_vec(),
+            vec![
+                OsString::from("mytool"),
+                OsString::from("--wrapper-flag"),
+                OsString::from("arg")
+            ]
+        );
+
+        // `.interpreter_config.argv` is rewritten when it defines sys.argv.
+        config.argv = None;
+        config.interpreter_config.argv = Some(vec!["other".into(), "--wrapper-flag".into()]);
+        let resolved = config.resolve()?;
+        assert_eq!(resolved.interpreter_config.argv, Some(vec!["other".into()]));
+        assert_eq!(resolved.argv, None);
+        assert_eq!(
+            resolved.orig_argv().to_vec(),
+            vec![OsString::from("other"), OsString::from("--wrapper-flag")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_orig_argv_multicall_subcommand() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.argv = Some(vec!["mytool".into(), "admin".into()]);
+        config
+            .multicall_run_modules
+            .insert("admin".to_string(), "mytool.admin".to_string());
+        config.multicall_subcommand = true;
+
+        let resolved = config.resolve()?;
+        assert_eq!(resolved.argv, Some(vec!["mytool".into()]));
+        assert_eq!(
+            resolved.orig_argv().to_vec(),
+            vec![OsString::from("mytool"), OsString::from("admin")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.origin = Some(PathBuf::from("/origin"));
+        config.packed_resources = vec![
+            PackedResourcesSource::Memory(b""),
+            PackedResourcesSource::MemoryMappedPath(PathBuf::from("$ORIGIN/packed-resources")),
+        ];
+        config.interpreter_config.run_module = Some("app".to_string());
+
+        let summary = config.resolve()?.summary();
+        assert!(!summary.oxidized_importer);
+        assert!(summary.filesystem_importer);
+        assert_eq!(
+            summary.packed_resources,
+            vec![
+                "memory".to_string(),
+                PathBuf::from("/origin/packed-resources")
+                    .display()
+                    .to_string()
+            ]
+        );
+        assert_eq!(summary.run_mode, "module");
+        assert_eq!(summary.origin, PathBuf::from("/origin"));
+        assert!(!summary.restricted_mode);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_module_search_paths_mode() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.origin = Some(PathBuf::from("/origin"));
+        config.interpreter_config.module_search_paths =
+            Some(vec![PathBuf::from("$ORIGIN/lib"), PathBuf::from("/other")]);
+
+        let resolved = config.clone().resolve()?;
+        assert_eq!(
+            resolved.interpreter_config.module_search_paths,
+            Some(vec![PathBuf::from("/origin/lib"), PathBuf::from("/other")])
+        );
+        assert_eq!(resolved.interpreter_config.python_path_env, None);
+
+        let delimiter = if cfg!(windows) { ";" } else { ":" };
+
+        config.module_search_paths_mode = ModuleSearchPathsMode::Extend;
+        let resolved = config.clone().resolve()?;
+        assert_eq!(resolved.interpreter_config.module_search_paths, None);
+        assert_eq!(
+            resolved.interpreter_config.python_path_env,
+            Some(format!("/origin/lib{}/other", delimiter))
+        );
+
+        config.interpreter_config.python_path_env = Some("/env".to_string());
+        let resolved = config.resolve()?;
+        assert_eq!(
+            resolved.interpreter_config.python_path_env,
+            Some(format!("/origin/lib{}/other{}/env", delimiter, delimiter))
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,69 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Resolve shared libraries loaded via `ctypes` to bundled libraries.
+
+Many packages load shared libraries by name with `ctypes.util.find_library()`
+or `ctypes.CDLL()`. Both only search system locations, so libraries shipped
+next to a packaged application aren't found.
+
+We patch both to consult a mapping of library names to bundled library paths
+first. Names not in the mapping, or whose bundled library doesn't exist, are
+resolved as before.
+*/
+
+use {
+    crate::conversion::path_to_pyobject,
+    cpython::{PyDict, PyResult, Python},
+    std::{collections::BTreeMap, path::PathBuf},
+};
+
+/// Python code patching `ctypes`.
+///
+/// Executed with `libraries` bound to a dict of library names to paths.
+const PATCH_SOURCE: &str = "import ctypes
+import ctypes.util
+import os
+
+_find_library = ctypes.util.find_library
+_cdll_init = ctypes.CDLL.__init__
+
+def _bundled_library(name):
+    path = libraries.get(name)
+
+    if path is not None and os.path.exists(path):
+        return path
+
+    return None
+
+def find_library(name):
+    return _bundled_library(name) or _find_library(name)
+
+def cdll_init(self, name, *args, **kwargs):
+    if isinstance(name, str):
+        name = _bundled_library(name) or name
+
+    _cdll_init(self, name, *args, **kwargs)
+
+ctypes.util.find_library = find_library
+ctypes.CDLL.__init__ = cdll_init
+";
+
+/// Patch `ctypes` to resolve library names to bundled libraries.
+pub(crate) fn install_ctypes_libraries(
+    py: Python,
+    libraries: &BTreeMap<String, PathBuf>,
+) -> PyResult<()> {
+    let mapping = PyDict::new(py);
+    for (name, path) in libraries {
+        mapping.set_item(py, name, path_to_pyobject(py, path)?)?;
+    }
+
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__builtins__", py.import("builtins")?)?;
+    globals.set_item(py, "libraries", mapping)?;
+
+    py.run(PATCH_SOURCE, Some(&globals), None)
+}
@@ -0,0 +1,161 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Run closures needing Python on the thread owning the interpreter.
+
+A [MainPythonInterpreter](crate::MainPythonInterpreter) is bound to the
+thread that created it. Other threads calling into Python would need to
+reason about when that thread holds the GIL and how long the interpreter
+lives. A [PythonDispatcher] is a cloneable, `Send` handle that instead queues
+closures for the owning thread, which runs them when it calls
+`MainPythonInterpreter::run_dispatched()`. Results are sent back over a
+channel.
+*/
+
+use {
+    crate::error::DispatchError,
+    cpython::Python,
+    std::{
+        panic::{catch_unwind, AssertUnwindSafe},
+        sync::{mpsc, Arc, Mutex, PoisonError},
+        time::Duration,
+    },
+};
+
+type Job = Box<dyn FnOnce(Python) + Send>;
+
+/// The sending half of a queue, shared by its dispatchers.
+///
+/// `None` once the queue is closed. Dispatchers hold the lock while sending,
+/// so once closed, no closure can be queued.
+type SharedSender = Arc<Mutex<Option<mpsc::Sender<Job>>>>;
+
+/// Closures queued by dispatchers of an interpreter.
+pub(crate) struct DispatchQueue {
+    sender: SharedSender,
+    receiver: mpsc::Receiver<Job>,
+}
+
+impl DispatchQueue {
+    pub(crate) fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            sender: Arc::new(Mutex::new(Some(sender))),
+            receiver,
+        }
+    }
+
+    pub(crate) fn dispatcher(&self) -> PythonDispatcher {
+        PythonDispatcher {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Stop accepting closures and remove the ones already queued.
+    ///
+    /// Waits for dispatches in progress on other threads. Once this returns,
+    /// every closure that will ever be queued is in the returned `Vec`.
+    pub(crate) fn close(&self) -> Vec<Job> {
+        self.sender
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+
+        self.receiver.try_iter().collect()
+    }
+
+    /// Remove queued closures.
+    ///
+    /// If none are queued and `timeout` is set, waits up to that long for
+    /// one to be queued.
+    pub(crate) fn take_jobs(&self, timeout: Option<Duration>) -> Vec<Job> {
+        let mut jobs = self.receiver.try_iter().collect::<Vec<_>>();
+
+        if jobs.is_empty() {
+            if let Some(timeout) = timeout {
+                if let Ok(job) = self.receiver.recv_timeout(timeout) {
+                    jobs.push(job);
+                    jobs.extend(self.receiver.try_iter());
+                }
+            }
+        }
+
+        jobs
+    }
+}
+
+/// A handle to queue closures for the thread owning an interpreter.
+///
+/// Obtained from `MainPythonInterpreter::dispatcher()`. Closures queued after
+/// the interpreter is dropped fail with [DispatchError::Disconnected].
+#[derive(Clone)]
+pub struct PythonDispatcher {
+    sender: SharedSender,
+}
+
+impl PythonDispatcher {
+    /// Queue a closure to run on the thread owning the interpreter.
+    ///
+    /// The closure is called with the GIL held.
+    pub fn dispatch<T, F>(&self, f: F) -> Result<PythonDispatchResult<T>, DispatchError>
+    where
+        T: Send + 'static,
+        F: FnOnce(Python) -> T + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        let queue = self.sender.lock().unwrap_or_else(PoisonError::into_inner);
+
+        queue
+            .as_ref()
+            .ok_or(DispatchError::Disconnected)?
+            .send(Box::new(move |py| {
+                let res =
+                    catch_unwind(AssertUnwindSafe(|| f(py))).map_err(|_| DispatchError::Panicked);
+
+                // The caller may have dropped the result without waiting on it.
+                let _ = sender.send(res);
+            }))
+            .map_err(|_| DispatchError::Disconnected)?;
+
+        Ok(PythonDispatchResult { receiver })
+    }
+
+    /// Queue a closure and wait for its result.
+    ///
+    /// This blocks until the owning thread runs the closure. So it must not
+    /// be called from the owning thread.
+    pub fn call<T, F>(&self, f: F) -> Result<T, DispatchError>
+    where
+        T: Send + 'static,
+        F: FnOnce(Python) -> T + Send + 'static,
+    {
+        self.dispatch(f)?.wait()
+    }
+}
+
+/// The pending result of a closure queued by a [PythonDispatcher].
+pub struct PythonDispatchResult<T> {
+    receiver: mpsc::Receiver<Result<T, DispatchError>>,
+}
+
+impl<T> PythonDispatchResult<T> {
+    /// Wait for the closure to run and obtain its return value.
+    pub fn wait(self) -> Result<T, DispatchError> {
+        self.receiver
+            .recv()
+            .map_err(|_| DispatchError::Disconnected)?
+    }
+
+    /// Obtain the return value of the closure if it has run.
+    pub fn try_wait(&self) -> Option<Result<T, DispatchError>> {
+        match self.receiver.try_recv() {
+            Ok(res) => Some(res),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err(DispatchError::Disconnected)),
+        }
+    }
+}
@@ -3,11 +3,13 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    cpython::{ObjectProtocol, PyErr, Python},
+    cpython::{ObjectProtocol, PyClone, PyErr, PyString, Python},
     python3_sys as pyffi,
+    python_packed_resources::parser::LoadError,
     std::{
         ffi::CStr,
         fmt::{Display, Formatter},
+        path::PathBuf,
     },
 };
 
@@ -38,55 +40,370 @@ fn format_pyerr(py: Python, err: PyErr) -> Result<String, &'static str> {
     }
 }
 
+/// Format a Python exception including its traceback.
+#[cfg(not(library_mode = "extension"))]
+pub(crate) fn format_exception(py: Python, mut err: PyErr) -> String {
+    let value = err.instance(py);
+    let traceback = err
+        .ptraceback
+        .as_ref()
+        .map(|tb| tb.clone_ref(py))
+        .unwrap_or_else(|| py.None());
+
+    let formatted = py.import("traceback").and_then(|module| {
+        let lines = module.call(
+            py,
+            "format_exception",
+            (err.ptype.clone_ref(py), value, traceback),
+            None,
+        )?;
+
+        PyString::new(py, "")
+            .as_object()
+            .call_method(py, "join", (lines,), None)?
+            .extract::<String>(py)
+    });
+
+    formatted.unwrap_or_else(|_| "unable to format Python exception".to_string())
+}
+
 /// Represents an error encountered when creating an embedded Python interpreter.
+///
+/// Each variant corresponds to a kind of failure. [NewInterpreterError::code()]
+/// returns a stable identifier for the kind, suitable for reporting.
 #[derive(Debug)]
 pub enum NewInterpreterError {
-    Simple(&'static str),
-    Dynamic(String),
-}
+    /// The interpreter configuration is invalid.
+    InvalidConfig(String),
 
-impl From<&'static str> for NewInterpreterError {
-    fn from(v: &'static str) -> Self {
-        NewInterpreterError::Simple(v)
-    }
+    /// The path of the current executable could not be resolved.
+    CurrentExe(std::io::Error),
+
+    /// The directory to use as `$ORIGIN` could not be derived from a path.
+    Origin(PathBuf),
+
+    /// The global interpreter guard could not be acquired.
+    ///
+    /// This occurs if a thread panicked while holding the guard.
+    InterpreterGuard,
+
+    /// A value could not be converted to a string type used by Python.
+    StringConversion {
+        /// What was being converted.
+        context: String,
+        /// Lossy representation of the offending value.
+        value: String,
+    },
+
+    /// Resources data could not be loaded or indexed.
+    Resources {
+        /// Path the resources data was loaded from, if any.
+        path: Option<PathBuf>,
+        message: String,
+    },
+
+    /// Packed resources data is malformed or of an unsupported format.
+    PackedResources {
+        /// Path the data was loaded from or `None` if it is embedded in the binary.
+        path: Option<PathBuf>,
+        error: LoadError,
+    },
+
+    /// An attribute of the `sys` module could not be set.
+    SysAttribute(&'static str),
+
+    /// An I/O operation on a path failed.
+    Io {
+        context: String,
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// A `PyStatus` returned by the Python C API signaled an error.
+    PyStatus {
+        context: String,
+        /// Formatted function and message of the status.
+        message: String,
+    },
+
+    /// A Python exception was raised.
+    Python {
+        context: String,
+        /// Formatted type and value of the exception.
+        message: String,
+    },
 }
 
 impl Display for NewInterpreterError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match &self {
-            NewInterpreterError::Simple(value) => value.fmt(f),
-            NewInterpreterError::Dynamic(value) => value.fmt(f),
+        match self {
+            NewInterpreterError::InvalidConfig(message) => {
+                write!(f, "invalid interpreter configuration: {}", message)
+            }
+            NewInterpreterError::CurrentExe(_) => write!(f, "could not obtain current executable"),
+            NewInterpreterError::Origin(path) => {
+                write!(f, "unable to obtain parent directory of {}", path.display())
+            }
+            NewInterpreterError::InterpreterGuard => {
+                write!(f, "unable to acquire global interpreter guard")
+            }
+            NewInterpreterError::StringConversion { context, value } => {
+                write!(
+                    f,
+                    "during {}: unable to convert {} to string",
+                    context, value
+                )
+            }
+            NewInterpreterError::Resources {
+                path: Some(path),
+                message,
+            } => write!(
+                f,
+                "error indexing resources from {}: {}",
+                path.display(),
+                message
+            ),
+            NewInterpreterError::Resources {
+                path: None,
+                message,
+            } => write!(f, "error indexing resources: {}", message),
+            NewInterpreterError::PackedResources {
+                path: Some(path),
+                error,
+            } => write!(
+                f,
+                "error loading packed resources from {}: {}",
+                path.display(),
+                error
+            ),
+            NewInterpreterError::PackedResources { path: None, error } => {
+                write!(f, "error loading embedded packed resources: {}", error)
+            }
+            NewInterpreterError::SysAttribute(name) => write!(f, "unable to set sys.{}", name),
+            NewInterpreterError::Io { context, path, .. } => {
+                write!(f, "error {} {}", context, path.display())
+            }
+            NewInterpreterError::PyStatus { context, message } => {
+                write!(f, "during {}: {}", context, message)
+            }
+            NewInterpreterError::Python { context, message } => {
+                write!(f, "during {}: {}", context, message)
+            }
         }
     }
 }
 
-impl std::error::Error for NewInterpreterError {}
+impl std::error::Error for NewInterpreterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NewInterpreterError::CurrentExe(e) => Some(e),
+            NewInterpreterError::PackedResources { error, .. } => Some(error),
+            NewInterpreterError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
 
 impl NewInterpreterError {
+    /// Obtain a stable identifier for the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            NewInterpreterError::InvalidConfig(_) => "PYEMBED_INVALID_CONFIG",
+            NewInterpreterError::CurrentExe(_) => "PYEMBED_CURRENT_EXE",
+            NewInterpreterError::Origin(_) => "PYEMBED_ORIGIN",
+            NewInterpreterError::InterpreterGuard => "PYEMBED_INTERPRETER_GUARD",
+            NewInterpreterError::StringConversion { .. } => "PYEMBED_STRING_CONVERSION",
+            NewInterpreterError::Resources { .. } => "PYEMBED_RESOURCES",
+            NewInterpreterError::PackedResources { .. } => "PYEMBED_PACKED_RESOURCES",
+            NewInterpreterError::SysAttribute(_) => "PYEMBED_SYS_ATTRIBUTE",
+            NewInterpreterError::Io { .. } => "PYEMBED_IO",
+            NewInterpreterError::PyStatus { .. } => "PYEMBED_PYSTATUS",
+            NewInterpreterError::Python { .. } => "PYEMBED_PYTHON_EXCEPTION",
+        }
+    }
+
     pub fn new_from_pyerr(py: Python, err: PyErr, context: &str) -> Self {
-        match format_pyerr(py, err) {
-            Ok(value) => NewInterpreterError::Dynamic(format!("during {}: {}", context, value)),
-            Err(msg) => NewInterpreterError::Dynamic(format!("during {}: {}", context, msg)),
+        let message = match format_pyerr(py, err) {
+            Ok(value) => value,
+            Err(msg) => msg.to_string(),
+        };
+
+        NewInterpreterError::Python {
+            context: context.to_string(),
+            message,
         }
     }
 
     pub fn new_from_pystatus(status: &pyffi::PyStatus, context: &str) -> Self {
-        if !status.func.is_null() && !status.err_msg.is_null() {
+        let message = if !status.func.is_null() && !status.err_msg.is_null() {
             let func = unsafe { CStr::from_ptr(status.func) };
             let msg = unsafe { CStr::from_ptr(status.err_msg) };
 
-            NewInterpreterError::Dynamic(format!(
-                "during {}: {}: {}",
-                context,
-                func.to_string_lossy(),
-                msg.to_string_lossy()
-            ))
+            format!("{}: {}", func.to_string_lossy(), msg.to_string_lossy())
         } else if !status.err_msg.is_null() {
             let msg = unsafe { CStr::from_ptr(status.err_msg) };
 
-            NewInterpreterError::Dynamic(format!("during {}: {}", context, msg.to_string_lossy()))
+            msg.to_string_lossy().to_string()
         } else {
-            NewInterpreterError::Dynamic(format!("during {}: could not format PyStatus", context))
+            "could not format PyStatus".to_string()
+        };
+
+        NewInterpreterError::PyStatus {
+            context: context.to_string(),
+            message,
+        }
+    }
+}
+
+/// Represents an error running Python calls in a worker pool.
+#[cfg(not(library_mode = "extension"))]
+#[derive(Debug)]
+pub enum WorkerPoolError {
+    /// A pool must have at least 1 worker.
+    NoWorkers,
+
+    /// A worker thread could not be spawned.
+    Spawn(std::io::Error),
+
+    /// A function reference isn't of the form `module:function`.
+    InvalidTarget(String),
+
+    /// The pool stopped before the call completed.
+    ShutDown,
+
+    /// The call raised a Python exception.
+    ///
+    /// Holds the formatted exception and traceback.
+    Python(String),
+}
+
+#[cfg(not(library_mode = "extension"))]
+impl Display for WorkerPoolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkerPoolError::NoWorkers => write!(f, "worker pool must have at least 1 worker"),
+            WorkerPoolError::Spawn(_) => write!(f, "unable to spawn worker thread"),
+            WorkerPoolError::InvalidTarget(target) => write!(
+                f,
+                "invalid function reference {}; expected module:function",
+                target
+            ),
+            WorkerPoolError::ShutDown => write!(f, "worker pool shut down"),
+            WorkerPoolError::Python(message) => write!(f, "Python exception: {}", message),
+        }
+    }
+}
+
+#[cfg(not(library_mode = "extension"))]
+impl std::error::Error for WorkerPoolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WorkerPoolError::Spawn(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Represents an error driving an asyncio event loop.
+#[cfg(not(library_mode = "extension"))]
+#[derive(Debug)]
+pub enum AsyncioError {
+    /// The coroutine was cancelled or the event loop was closed.
+    Cancelled,
+
+    /// A Python exception was raised.
+    ///
+    /// Holds the formatted exception and traceback.
+    Python(String),
+}
+
+#[cfg(not(library_mode = "extension"))]
+impl Display for AsyncioError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsyncioError::Cancelled => write!(f, "coroutine was cancelled"),
+            AsyncioError::Python(message) => write!(f, "Python exception: {}", message),
+        }
+    }
+}
+
+#[cfg(not(library_mode = "extension"))]
+impl std::error::Error for AsyncioError {}
+
+/// Represents an error running a closure via a `PythonDispatcher`.
+#[cfg(not(library_mode = "extension"))]
+#[derive(Debug)]
+pub enum DispatchError {
+    /// The interpreter was dropped before the closure ran.
+    Disconnected,
+
+    /// The closure panicked.
+    Panicked,
+}
+
+#[cfg(not(library_mode = "extension"))]
+impl Display for DispatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DispatchError::Disconnected => write!(f, "interpreter no longer running"),
+            DispatchError::Panicked => write!(f, "dispatched closure panicked"),
         }
     }
 }
+
+#[cfg(not(library_mode = "extension"))]
+impl std::error::Error for DispatchError {}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::error::Error};
+
+    #[test]
+    fn test_source_chain() {
+        let err = NewInterpreterError::Io {
+            context: "creating directory".to_string(),
+            path: PathBuf::from("/modules"),
+            source: std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied"),
+        };
+
+        assert_eq!(err.code(), "PYEMBED_IO");
+        assert_eq!(err.to_string(), "error creating directory /modules");
+        assert_eq!(err.source().unwrap().to_string(), "denied");
+
+        let err = NewInterpreterError::SysAttribute("frozen");
+        assert_eq!(err.code(), "PYEMBED_SYS_ATTRIBUTE");
+        assert_eq!(err.to_string(), "unable to set sys.frozen");
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn test_packed_resources() {
+        let err = NewInterpreterError::PackedResources {
+            path: None,
+            error: LoadError::UnsupportedVersion {
+                found: 5,
+                supported: 4,
+            },
+        };
+
+        assert_eq!(err.code(), "PYEMBED_PACKED_RESOURCES");
+        assert_eq!(
+            err.to_string(),
+            "error loading embedded packed resources: format version 5 is not supported (supported version: 4)"
+        );
+
+        let err = NewInterpreterError::PackedResources {
+            path: Some(PathBuf::from("/app/packed-resources")),
+            error: LoadError::Malformed {
+                offset: 42,
+                message: "blob data extends beyond end of data",
+            },
+        };
+
+        assert_eq!(
+            err.to_string(),
+            "error loading packed resources from /app/packed-resources: malformed data at offset 42: blob data extends beyond end of data"
+        );
+        assert!(err.source().is_some());
+    }
+}
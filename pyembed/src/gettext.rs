@@ -0,0 +1,216 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Make gettext message catalogs in package resources available to `gettext`.
+
+`gettext.find()` and `gettext.translation()` only look for catalogs on the
+filesystem, under a `localedir` that applications typically derive from
+`__file__`. Neither works for resources loaded from memory.
+
+We index catalogs named `<language>/LC_MESSAGES/<domain>.mo` in package
+resources by domain and language. `gettext.find()` is patched to consult
+this index when the filesystem has no catalog. Catalogs installed next to the
+binary are found at their path. In-memory catalogs are written to a cache
+directory if one is configured. Otherwise `gettext.translation()` is patched
+to load them from memory.
+*/
+
+use {
+    crate::{
+        conversion::path_to_pyobject,
+        python_resources::{resolve_relative_path, PythonResourcesState},
+    },
+    cpython::{PyDict, PyResult, Python},
+    python_packaging::resource::parse_gettext_catalog_path,
+    std::path::Path,
+};
+
+/// Index gettext message catalogs in package resources.
+///
+/// Returns a dict of `(domain, language)` to the filesystem path of the
+/// catalog or, for in-memory catalogs, `(package, resource name)`.
+pub(crate) fn index_gettext_catalogs(
+    py: Python,
+    state: &PythonResourcesState<u8>,
+) -> PyResult<PyDict> {
+    let catalogs = PyDict::new(py);
+
+    for (package, entry) in &state.resources {
+        if let Some(resources) = &entry.in_memory_package_resources {
+            for name in resources.keys() {
+                if let Some((language, domain)) = parse_gettext_catalog_path(name) {
+                    catalogs.set_item(py, (domain, language), (package.as_ref(), name.as_ref()))?;
+                }
+            }
+        }
+
+        if let Some(resources) = &entry.relative_path_package_resources {
+            for (name, path) in resources {
+                if let Some((language, domain)) = parse_gettext_catalog_path(name) {
+                    catalogs.set_item(
+                        py,
+                        (domain, language),
+                        path_to_pyobject(py, &resolve_relative_path(&state.origin, path))?,
+                    )?;
+                }
+            }
+        }
+    }
+
+    Ok(catalogs)
+}
+
+/// Python code patching `gettext`.
+///
+/// Executed with `catalogs` bound to the result of [index_gettext_catalogs]
+/// and `cache_directory` bound to a path or `None`.
+const PATCH_SOURCE: &str = r#"import gettext
+import os
+import sys
+
+import oxidized_importer
+
+_find = gettext.find
+_translation = gettext.translation
+
+
+def _languages(languages):
+    # Mirrors how gettext.find() resolves languages.
+    if languages is None:
+        languages = []
+        for envar in ("LANGUAGE", "LC_ALL", "LC_MESSAGES", "LANG"):
+            value = os.environ.get(envar)
+            if value:
+                languages = value.split(":")
+                break
+        if "C" not in languages:
+            languages.append("C")
+
+    expanded = []
+    for language in languages:
+        for value in gettext._expand_lang(language):
+            if value not in expanded:
+                expanded.append(value)
+
+    return expanded
+
+
+def _catalogs(domain, languages):
+    for language in _languages(languages):
+        if language == "C":
+            break
+
+        entry = catalogs.get((domain, language))
+        if entry is not None:
+            yield language, entry
+
+
+def _open_in_memory(entry):
+    package, resource = entry
+
+    for finder in sys.meta_path:
+        if isinstance(finder, oxidized_importer.OxidizedFinder):
+            reader = finder.get_resource_reader(package)
+            if reader is not None:
+                return reader.open_resource(resource)
+
+    raise FileNotFoundError("%s:%s" % (package, resource))
+
+
+def _catalog_path(domain, language, entry):
+    if isinstance(entry, str):
+        return entry if os.path.exists(entry) else None
+
+    if cache_directory is None:
+        return None
+
+    path = os.path.join(cache_directory, language, "LC_MESSAGES", domain + ".mo")
+
+    if not os.path.exists(path):
+        os.makedirs(os.path.dirname(path), exist_ok=True)
+
+        with _open_in_memory(entry) as fh:
+            data = fh.read()
+
+        temp_path = "%s.%d" % (path, os.getpid())
+        with open(temp_path, "wb") as fh:
+            fh.write(data)
+        os.replace(temp_path, path)
+
+    return path
+
+
+def find(domain, localedir=None, languages=None, all=False):
+    result = _find(domain, localedir, languages, all)
+    if result and not all:
+        return result
+
+    paths = list(result) if all else []
+
+    for language, entry in _catalogs(domain, languages):
+        path = _catalog_path(domain, language, entry)
+        if path is None or path in paths:
+            continue
+
+        if not all:
+            return path
+
+        paths.append(path)
+
+    return paths if all else None
+
+
+def translation(domain, localedir=None, languages=None, class_=None, fallback=False, **kwargs):
+    try:
+        return _translation(domain, localedir, languages, class_, **kwargs)
+    except FileNotFoundError:
+        # find() returns everything but catalogs only available in memory.
+        result = None
+
+        for _language, entry in _catalogs(domain, languages):
+            if isinstance(entry, str):
+                continue
+
+            with _open_in_memory(entry) as fh:
+                t = (class_ or gettext.GNUTranslations)(fh)
+
+            if result is None:
+                result = t
+            else:
+                result.add_fallback(t)
+
+        if result is not None:
+            return result
+
+        if fallback:
+            return gettext.NullTranslations()
+
+        raise
+
+
+gettext.find = find
+gettext.translation = translation
+"#;
+
+/// Patch `gettext` to find message catalogs indexed by [index_gettext_catalogs].
+pub(crate) fn install_gettext_catalogs(
+    py: Python,
+    catalogs: PyDict,
+    cache_directory: Option<&Path>,
+) -> PyResult<()> {
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__builtins__", py.import("builtins")?)?;
+    globals.set_item(py, "catalogs", catalogs)?;
+    globals.set_item(
+        py,
+        "cache_directory",
+        match cache_directory {
+            Some(path) => path_to_pyobject(py, path)?,
+            None => py.None(),
+        },
+    )?;
+
+    py.run(PATCH_SOURCE, Some(&globals), None)
+}
@@ -15,31 +15,47 @@ use cpython::NoArgs;
 use {
     crate::memory_dll::{free_library_memory, get_proc_address_memory, load_library_memory},
     cpython::exc::SystemError,
-    std::ffi::{c_void, CString},
+    std::ffi::c_void,
 };
 use {
     crate::{
         conversion::pyobject_to_pathbuf,
+        importer_debug::{self, FindSpecDecision},
         python_resources::{
             pyobject_to_resource, resource_to_pyobject, ModuleFlavor, OptimizeLevel,
             OxidizedResource, PythonResourcesState,
         },
         resource_scanning::find_resources_in_path,
+        run_report,
     },
     cpython::{
-        exc::{FileNotFoundError, ImportError, ValueError},
+        exc::{FileNotFoundError, ImportError, OSError, TypeError, ValueError},
         {
             py_class, py_fn, ObjectProtocol, PyBytes, PyCapsule, PyClone, PyDict, PyErr, PyList,
             PyModule, PyObject, PyResult, PyString, PyTuple, Python, PythonObject, ToPyObject,
         },
     },
+    once_cell::sync::Lazy,
     python3_sys as pyffi,
-    std::sync::Arc,
+    std::{
+        collections::BTreeMap,
+        ffi::{CStr, CString},
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+    },
 };
 
 pub const OXIDIZED_IMPORTER_NAME_STR: &str = "oxidized_importer";
 pub const OXIDIZED_IMPORTER_NAME: &[u8] = b"oxidized_importer\0";
 
+/// Name of the file resource holding type stubs for the `oxidized_importer` module.
+pub const OXIDIZED_IMPORTER_STUB_NAME: &str = "oxidized_importer.pyi";
+
+/// Type stubs describing the Python API of the `oxidized_importer` module.
+pub const OXIDIZED_IMPORTER_STUB: &[u8] = include_bytes!("oxidized_importer.pyi");
+
 #[cfg(windows)]
 #[allow(non_camel_case_types)]
 type py_init_fn = extern "C" fn() -> *mut pyffi::PyObject;
@@ -247,6 +263,47 @@ fn load_dynamic_library(
     }
 }
 
+/// Counters describing lookups performed by an `OxidizedFinder`.
+#[derive(Debug, Default)]
+pub(crate) struct ImporterStats {
+    /// `find_spec()` calls that resolved a module.
+    find_spec_hits: AtomicU64,
+    /// `find_spec()` calls for modules we know nothing about.
+    find_spec_misses: AtomicU64,
+    /// `find_spec()` calls for missing modules within packages we index.
+    find_spec_negative_lookups: AtomicU64,
+}
+
+impl ImporterStats {
+    fn to_dict(&self, py: Python) -> PyResult<PyDict> {
+        let dict = PyDict::new(py);
+
+        dict.set_item(
+            py,
+            "find_spec_hits",
+            self.find_spec_hits.load(Ordering::Relaxed),
+        )?;
+        dict.set_item(
+            py,
+            "find_spec_misses",
+            self.find_spec_misses.load(Ordering::Relaxed),
+        )?;
+        dict.set_item(
+            py,
+            "find_spec_negative_lookups",
+            self.find_spec_negative_lookups.load(Ordering::Relaxed),
+        )?;
+
+        Ok(dict)
+    }
+
+    fn reset(&self) {
+        self.find_spec_hits.store(0, Ordering::Relaxed);
+        self.find_spec_misses.store(0, Ordering::Relaxed);
+        self.find_spec_negative_lookups.store(0, Ordering::Relaxed);
+    }
+}
+
 /// Holds state for the custom MetaPathFinder.
 pub(crate) struct ImporterState {
     /// `imp` Python module.
@@ -286,6 +343,8 @@ pub(crate) struct ImporterState {
     /// the backing memory instead of forcing all resource data to be backed
     /// by 'static.
     resources_state: PyCapsule,
+    /// Counters of lookups performed.
+    stats: ImporterStats,
 }
 
 impl ImporterState {
@@ -295,6 +354,8 @@ impl ImporterState {
         bootstrap_module: &PyModule,
         resources_state: Box<PythonResourcesState<'a, u8>>,
     ) -> Result<Self, PyErr> {
+        importer_debug::enable_from_env();
+
         let decode_source = importer_module.get(py, "decode_source")?;
 
         let io_module = py.import("_io")?;
@@ -396,6 +457,7 @@ impl ImporterState {
             exec_fn,
             optimize_level,
             resources_state: capsule,
+            stats: ImporterStats::default(),
         })
     }
 
@@ -568,6 +630,20 @@ py_class!(class OxidizedFinder |py| {
     def serialize_indexed_resources(&self, ignore_builtin: bool = true, ignore_frozen: bool = true) -> PyResult<PyObject> {
         self.serialize_indexed_resources_impl(py, ignore_builtin, ignore_frozen)
     }
+
+    def read_packed_file(&self, path: &PyString) -> PyResult<PyObject> {
+        self.read_packed_file_impl(py, path)
+    }
+
+    def stats(&self) -> PyResult<PyDict> {
+        self.state(py).stats.to_dict(py)
+    }
+
+    def reset_stats(&self) -> PyResult<PyObject> {
+        self.state(py).stats.reset();
+
+        Ok(py.None())
+    }
 });
 
 // importlib.abc.MetaPathFinder interface.
@@ -579,18 +655,45 @@ impl OxidizedFinder {
         path: &PyObject,
         target: Option<PyObject>,
     ) -> PyResult<PyObject> {
+        let debug_start = importer_debug::start();
+
         let state = self.state(py);
         let key = fullname.to_string(py)?;
 
-        let module = match state
-            .get_resources_state()
-            .resolve_importable_module(&key, state.optimize_level)
-        {
-            Some(module) => module,
-            None => return Ok(py.None()),
+        let resources_state = state.get_resources_state();
+
+        // Missing modules within packages we index are rejected via the
+        // package index, without resolving the name against our resources.
+        if resources_state.is_negative_lookup(&key) {
+            state
+                .stats
+                .find_spec_negative_lookups
+                .fetch_add(1, Ordering::Relaxed);
+
+            if let Some(start) = debug_start {
+                importer_debug::find_spec(&key, FindSpecDecision::NegativeLookup, start);
+            }
+
+            return Ok(py.None());
+        }
+
+        let module = match resources_state.resolve_importable_module(&key, state.optimize_level) {
+            Some(module) => {
+                state.stats.find_spec_hits.fetch_add(1, Ordering::Relaxed);
+                module
+            }
+            None => {
+                state.stats.find_spec_misses.fetch_add(1, Ordering::Relaxed);
+
+                if let Some(start) = debug_start {
+                    importer_debug::find_spec(&key, FindSpecDecision::NotFound, start);
+                }
+
+                return Ok(py.None());
+            }
         };
 
-        match module.flavor {
+        let res = match module.flavor {
             ModuleFlavor::Extension | ModuleFlavor::SourceBytecode => module.resolve_module_spec(
                 py,
                 &state.module_spec_type,
@@ -609,7 +712,23 @@ impl OxidizedFinder {
                     .frozen_importer
                     .call_method(py, "find_spec", (fullname, path, target), None)
             }
+        };
+
+        if let Some(start) = debug_start {
+            let decision = match (&module.flavor, &res) {
+                (_, Err(_)) => FindSpecDecision::Error,
+                (ModuleFlavor::Builtin, _) => FindSpecDecision::BuiltIn,
+                (ModuleFlavor::Frozen, _) => FindSpecDecision::Frozen,
+                _ => match module.origin_path() {
+                    Some(path) => FindSpecDecision::RelativePath(path),
+                    None => FindSpecDecision::InMemory,
+                },
+            };
+
+            importer_debug::find_spec(&key, decision, start);
         }
+
+        res
     }
 
     fn invalidate_caches_impl(&self, py: Python) -> PyResult<PyObject> {
@@ -689,6 +808,9 @@ impl OxidizedFinder {
         let name = module.getattr(py, "__name__")?;
         let key = name.extract::<String>(py)?;
 
+        // Records execution time and nested imports until dropped.
+        let _run_report_guard = run_report::module_exec(&key);
+
         let mut entry = match state
             .get_resources_state()
             .resolve_importable_module(&key, state.optimize_level)
@@ -744,6 +866,8 @@ impl OxidizedFinder {
     /// found. The path is expected to be constructed using a module’s __file__
     /// attribute or an item from a package’s __path__.
     fn get_data_impl(&self, py: Python, path: &PyString) -> PyResult<PyObject> {
+        run_report::resource_accessed(&path.to_string(py)?);
+
         self.state(py)
             .get_resources_state()
             .resolve_resource_data_from_path(py, path)
@@ -946,7 +1070,8 @@ fn oxidized_finder_new(
     let bootstrap_module = py.import("_frozen_importlib")?;
 
     let mut resources_state = Box::new(
-        PythonResourcesState::new_from_env().map_err(|err| PyErr::new::<ValueError, _>(py, err))?,
+        PythonResourcesState::new_from_env()
+            .map_err(|err| PyErr::new::<ValueError, _>(py, err.to_string()))?,
     );
 
     // Update origin if a value is given.
@@ -983,7 +1108,7 @@ impl OxidizedFinder {
             self.state(py).get_resources_state_mut();
         resources_state
             .index_path_memory_mapped(path)
-            .map_err(|e| PyErr::new::<ValueError, _>(py, e))?;
+            .map_err(|e| PyErr::new::<ValueError, _>(py, e.to_string()))?;
 
         Ok(py.None())
     }
@@ -993,7 +1118,7 @@ impl OxidizedFinder {
             self.state(py).get_resources_state_mut();
         resources_state
             .index_interpreter_builtins()
-            .map_err(|e| PyErr::new::<ValueError, _>(py, e))?;
+            .map_err(|e| PyErr::new::<ValueError, _>(py, e.to_string()))?;
 
         Ok(py.None())
     }
@@ -1003,7 +1128,7 @@ impl OxidizedFinder {
             self.state(py).get_resources_state_mut();
         resources_state
             .index_interpreter_builtin_extension_modules()
-            .map_err(|e| PyErr::new::<ValueError, _>(py, e))?;
+            .map_err(|e| PyErr::new::<ValueError, _>(py, e.to_string()))?;
 
         Ok(py.None())
     }
@@ -1013,7 +1138,7 @@ impl OxidizedFinder {
             self.state(py).get_resources_state_mut();
         resources_state
             .index_interpreter_frozen_modules()
-            .map_err(|e| PyErr::new::<ValueError, _>(py, e))?;
+            .map_err(|e| PyErr::new::<ValueError, _>(py, e.to_string()))?;
 
         Ok(py.None())
     }
@@ -1078,6 +1203,36 @@ impl OxidizedFinder {
 
         Ok(PyBytes::new(py, &data).into_object())
     }
+
+    fn read_packed_file_impl(&self, py: Python, path: &PyString) -> PyResult<PyObject> {
+        let path = path.to_string(py)?;
+
+        match self.lookup_packed_file(py, &path)? {
+            Some(data) => Ok(data),
+            None => Err(PyErr::new::<FileNotFoundError, _>(
+                py,
+                format!("packed file not found: {}", path),
+            )),
+        }
+    }
+
+    /// Resolve a file resource as `bytes`, returning `None` if it isn't known.
+    fn lookup_packed_file(&self, py: Python, path: &str) -> PyResult<Option<PyObject>> {
+        let resources_state: &PythonResourcesState<u8> = self.state(py).get_resources_state();
+
+        match resources_state.resolve_file_data(path) {
+            Ok(Some(data)) => {
+                run_report::resource_accessed(path);
+
+                Ok(Some(PyBytes::new(py, &data).into_object()))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => Err(PyErr::new::<OSError, _>(
+                py,
+                format!("error reading packed file {}: {}", path, e),
+            )),
+        }
+    }
 }
 
 // Implements in-memory reading of resource data.
@@ -1117,6 +1272,8 @@ impl OxidizedResourceReader {
             &package,
             &resource.to_string(py)?,
         )? {
+            run_report::resource_accessed(&format!("{}:{}", package, resource.to_string(py)?));
+
             Ok(file)
         } else {
             Err(PyErr::new::<FileNotFoundError, _>(py, "resource not found"))
@@ -1379,6 +1536,158 @@ pub extern "C" fn PyInit_oxidized_importer() -> *mut pyffi::PyObject {
     }
 }
 
+/// Read a file resource from the first `OxidizedFinder` on `sys.meta_path` knowing it.
+///
+/// Raises `FileNotFoundError` if no registered finder knows the path.
+fn read_packed_file(py: Python, path: String) -> PyResult<PyObject> {
+    let sys_module = py.import("sys")?;
+    let meta_path = sys_module.get(py, "meta_path")?;
+
+    for finder in meta_path.iter(py)? {
+        if let Ok(finder) = finder?.cast_into::<OxidizedFinder>(py) {
+            if let Some(data) = finder.lookup_packed_file(py, &path)? {
+                return Ok(data);
+            }
+        }
+    }
+
+    Err(PyErr::new::<FileNotFoundError, _>(
+        py,
+        format!("packed file not found: {}", path),
+    ))
+}
+
+/// Metadata describing how the running binary was built.
+///
+/// Set by the main interpreter from its configuration. Empty when
+/// `oxidized_importer` is loaded as a standalone extension module.
+static BUILD_INFO: Lazy<Mutex<BTreeMap<String, String>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// Define the metadata returned by `build_info()`.
+#[cfg(not(library_mode = "extension"))]
+pub(crate) fn set_build_info(info: BTreeMap<String, String>) {
+    *BUILD_INFO.lock().unwrap() = info;
+}
+
+/// Obtain a dict of metadata describing how the running binary was built.
+fn build_info(py: Python) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+
+    for (key, value) in BUILD_INFO.lock().unwrap().iter() {
+        dict.set_item(py, key, value)?;
+    }
+
+    Ok(dict.into_object())
+}
+
+/// Storage backing `PyImport_Inittab` after run-time extension module registration.
+static mut DYNAMIC_BUILTIN_EXTENSIONS: Option<Box<Vec<pyffi::_inittab>>> = None;
+
+/// Names of extension modules registered at run-time.
+///
+/// `PyImport_Inittab` entries reference this memory, so it is never freed.
+static mut DYNAMIC_BUILTIN_EXTENSION_NAMES: Vec<CString> = Vec::new();
+
+/// Register a built-in extension module after interpreter initialization.
+///
+/// `PyImport_Inittab` is replaced with a copy containing the new entry, making
+/// the module importable via `BuiltinImporter` on its next import.
+/// `OxidizedFinder` instances on `sys.meta_path` index the module as a
+/// built-in extension module so they can service it as well.
+///
+/// `PyImport_AppendInittab()` can't be used because it refuses to operate
+/// after interpreter initialization and its memory handling is buggy (see
+/// `set_pyimport_inittab()`).
+pub(crate) fn register_builtin_extension_module(
+    py: Python,
+    name: &str,
+    init_func: unsafe extern "C" fn() -> *mut pyffi::PyObject,
+) -> PyResult<()> {
+    let name_cstring = CString::new(name)
+        .map_err(|_| PyErr::new::<ValueError, _>(py, "extension module name contains NUL byte"))?;
+
+    let mut extensions = Vec::new();
+
+    for i in 0.. {
+        let record = unsafe { *pyffi::PyImport_Inittab.offset(i) };
+
+        if record.name.is_null() {
+            break;
+        }
+
+        if unsafe { CStr::from_ptr(record.name) } == name_cstring.as_c_str() {
+            return Err(PyErr::new::<ValueError, _>(
+                py,
+                format!("extension module {} is already registered", name),
+            ));
+        }
+
+        extensions.push(record);
+    }
+
+    let ptr = init_func as *const ();
+    extensions.push(pyffi::_inittab {
+        name: name_cstring.as_ptr() as *mut _,
+        initfunc: Some(unsafe { std::mem::transmute::<*const (), extern "C" fn()>(ptr) }),
+    });
+    extensions.push(pyffi::_inittab {
+        name: std::ptr::null_mut(),
+        initfunc: None,
+    });
+
+    // The GIL is held, so nothing can be reading the old array while we swap
+    // it out.
+    unsafe {
+        DYNAMIC_BUILTIN_EXTENSION_NAMES.push(name_cstring);
+        let mut extensions = Box::new(extensions);
+        pyffi::PyImport_Inittab = extensions.as_mut_ptr();
+        DYNAMIC_BUILTIN_EXTENSIONS = Some(extensions);
+    }
+
+    let sys_module = py.import("sys")?;
+    let meta_path = sys_module.get(py, "meta_path")?;
+
+    for finder in meta_path.iter(py)? {
+        if let Ok(finder) = finder?.cast_into::<OxidizedFinder>(py) {
+            finder
+                .state(py)
+                .get_resources_state_mut()
+                .index_builtin_extension_module(name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Register a built-in extension module from a capsule holding its init function.
+fn register_builtin_extension(
+    py: Python,
+    name: String,
+    init_ptr_capsule: PyObject,
+) -> PyResult<PyObject> {
+    let capsule = init_ptr_capsule.as_ptr();
+
+    if unsafe { pyffi::PyCapsule_CheckExact(capsule) } == 0 {
+        return Err(PyErr::new::<TypeError, _>(
+            py,
+            "init_ptr_capsule must be a capsule",
+        ));
+    }
+
+    let ptr = unsafe { pyffi::PyCapsule_GetPointer(capsule, pyffi::PyCapsule_GetName(capsule)) };
+    if ptr.is_null() {
+        return Err(PyErr::fetch(py));
+    }
+
+    let init_func =
+        unsafe { std::mem::transmute::<_, unsafe extern "C" fn() -> *mut pyffi::PyObject>(ptr) };
+
+    register_builtin_extension_module(py, &name, init_func)?;
+
+    Ok(py.None())
+}
+
 /// Initialize the Python module object.
 ///
 /// This is called as part of the PyInit_* function to create the internal
@@ -1409,6 +1718,7 @@ fn module_init(py: Python, m: &PyModule) -> PyResult<()> {
 
     state.initialized = false;
 
+    m.add(py, "build_info", py_fn!(py, build_info()))?;
     m.add(
         py,
         "decode_source",
@@ -1422,6 +1732,19 @@ fn module_init(py: Python, m: &PyModule) -> PyResult<()> {
         "find_resources_in_path",
         py_fn!(py, find_resources_in_path(path: PyObject)),
     )?;
+    m.add(
+        py,
+        "read_packed_file",
+        py_fn!(py, read_packed_file(path: String)),
+    )?;
+    m.add(
+        py,
+        "register_builtin_extension",
+        py_fn!(
+            py,
+            register_builtin_extension(name: String, init_ptr_capsule: PyObject)
+        ),
+    )?;
 
     m.add(py, "OxidizedFinder", py.get_type::<OxidizedFinder>())?;
     m.add(py, "OxidizedResource", py.get_type::<OxidizedResource>())?;
@@ -1463,6 +1786,20 @@ fn module_init(py: Python, m: &PyModule) -> PyResult<()> {
 
     crate::package_metadata::module_init(py, m)?;
 
+    #[cfg(all(feature = "self-update", not(library_mode = "extension")))]
+    crate::self_update_module::module_init(py, m)?;
+
+    // Advertise everything registered above as the public API.
+    let mut all = m
+        .dict(py)
+        .items(py)
+        .into_iter()
+        .filter_map(|(k, _)| k.extract::<String>(py).ok())
+        .filter(|k| !k.starts_with('_'))
+        .collect::<Vec<_>>();
+    all.sort();
+    m.add(py, "__all__", all)?;
+
     Ok(())
 }
 
@@ -0,0 +1,188 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Log the decisions of our importer.
+
+Logging is disabled by default. Once enabled, every `find_spec()` call of
+`OxidizedFinder` is logged with the decision made and how long it took. This
+helps understanding why a module is or isn't found.
+
+Logging is enabled by setting the environment variable defined by
+[IMPORTER_DEBUG_ENV] or by `OxidizedPythonInterpreterConfig.importer_debug`.
+*/
+
+use {
+    once_cell::sync::Lazy,
+    std::{
+        ffi::OsStr,
+        fmt::{Display, Formatter},
+        io::Write,
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex,
+        },
+        time::{Duration, Instant},
+    },
+};
+
+/// Environment variable enabling importer debug logging.
+///
+/// If the value is `stderr`, messages are written to stderr. Otherwise it is
+/// the path of a file messages are appended to.
+pub const IMPORTER_DEBUG_ENV: &str = "OXIDIZED_IMPORTER_DEBUG";
+
+/// Open a destination for diagnostic output.
+///
+/// `stderr` denotes stderr. Other values are paths of files to append to.
+pub(crate) fn open_output(value: &OsStr) -> Result<Box<dyn Write + Send>, String> {
+    if value == "stderr" {
+        Ok(Box::new(std::io::stderr()))
+    } else {
+        let path = Path::new(value);
+
+        let f = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("could not open {}: {}", path.display(), e))?;
+
+        Ok(Box::new(f))
+    }
+}
+
+/// The outcome of a `find_spec()` call.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum FindSpecDecision {
+    /// A module whose data is in memory.
+    InMemory,
+    /// A module loaded from a path relative to the origin.
+    RelativePath(PathBuf),
+    /// A built-in extension module, delegated to `BuiltinImporter`.
+    BuiltIn,
+    /// A frozen module, delegated to `FrozenImporter`.
+    Frozen,
+    /// A module we know nothing about.
+    NotFound,
+    /// A missing module within a package we index.
+    NegativeLookup,
+    /// Resolving the spec of a known module failed.
+    Error,
+}
+
+impl Display for FindSpecDecision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InMemory => f.write_str("in-memory"),
+            Self::RelativePath(path) => write!(f, "relative path {}", path.display()),
+            Self::BuiltIn => f.write_str("built-in"),
+            Self::Frozen => f.write_str("frozen"),
+            Self::NotFound => f.write_str("not found"),
+            Self::NegativeLookup => f.write_str("not found in indexed package"),
+            Self::Error => f.write_str("error"),
+        }
+    }
+}
+
+fn format_find_spec(name: &str, decision: &FindSpecDecision, duration: Duration) -> String {
+    format!(
+        "oxidized_importer: find_spec({}) -> {} [{} us]",
+        name,
+        decision,
+        duration.as_micros()
+    )
+}
+
+/// Whether logging is enabled.
+///
+/// Checked before taking the lock so logging has no meaningful overhead
+/// when disabled.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static OUTPUT: Lazy<Mutex<Option<Box<dyn Write + Send>>>> = Lazy::new(|| Mutex::new(None));
+
+/// Start logging to a destination.
+pub(crate) fn enable(destination: &OsStr) -> Result<(), String> {
+    let output = open_output(destination)?;
+
+    if let Ok(mut guard) = OUTPUT.lock() {
+        guard.replace(output);
+        ENABLED.store(true, Ordering::Relaxed);
+    }
+
+    Ok(())
+}
+
+/// Start logging if the environment variable defined by [IMPORTER_DEBUG_ENV]
+/// is set.
+///
+/// It takes precedence over a destination passed to [enable()].
+pub(crate) fn enable_from_env() {
+    match std::env::var_os(IMPORTER_DEBUG_ENV) {
+        Some(value) if !value.is_empty() => {
+            if let Err(msg) = enable(&value) {
+                eprintln!("error enabling importer debug logging: {}", msg);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Obtain the start time of an operation to log.
+///
+/// Returns `None` if logging is disabled.
+pub(crate) fn start() -> Option<Instant> {
+    if ENABLED.load(Ordering::Relaxed) {
+        Some(Instant::now())
+    } else {
+        None
+    }
+}
+
+/// Log the outcome of a `find_spec()` call which started at `start`.
+pub(crate) fn find_spec(name: &str, decision: FindSpecDecision, start: Instant) {
+    let line = format_find_spec(name, &decision, start.elapsed());
+
+    if let Ok(mut guard) = OUTPUT.lock() {
+        if let Some(output) = guard.as_mut() {
+            // Errors are ignored. Logging must not break imports.
+            let _ = writeln!(output, "{}", line);
+            let _ = output.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_find_spec() {
+        assert_eq!(
+            format_find_spec(
+                "foo.bar",
+                &FindSpecDecision::InMemory,
+                Duration::from_micros(12)
+            ),
+            "oxidized_importer: find_spec(foo.bar) -> in-memory [12 us]"
+        );
+        assert_eq!(
+            format_find_spec(
+                "foo",
+                &FindSpecDecision::RelativePath(PathBuf::from("lib/foo/__init__.py")),
+                Duration::from_micros(40)
+            ),
+            "oxidized_importer: find_spec(foo) -> relative path lib/foo/__init__.py [40 us]"
+        );
+        assert_eq!(
+            format_find_spec(
+                "foo.missing",
+                &FindSpecDecision::NegativeLookup,
+                Duration::from_micros(1)
+            ),
+            "oxidized_importer: find_spec(foo.missing) -> not found in indexed package [1 us]"
+        );
+    }
+}
@@ -6,27 +6,41 @@
 
 use {
     crate::{
-        config::{OxidizedPythonInterpreterConfig, ResolvedOxidizedPythonInterpreterConfig},
-        conversion::osstring_to_bytes,
-        error::NewInterpreterError,
+        asyncio::{AsyncioState, PythonCoroutineFuture},
+        config::{
+            ExtensionModule, OxidizedConfigSummary, OxidizedPythonInterpreterConfig,
+            ResolvedOxidizedPythonInterpreterConfig,
+        },
+        conversion::{osstr_to_pyobject, osstring_to_bytes, path_to_pyobject},
+        dispatcher::{DispatchQueue, PythonDispatcher},
+        error::{format_exception, AsyncioError, NewInterpreterError},
         importer::{
-            replace_meta_path_importers, PyInit_oxidized_importer, OXIDIZED_IMPORTER_NAME,
-            OXIDIZED_IMPORTER_NAME_STR,
+            register_builtin_extension_module, replace_meta_path_importers, set_build_info,
+            PyInit_oxidized_importer, OXIDIZED_IMPORTER_NAME, OXIDIZED_IMPORTER_NAME_STR,
         },
-        osutils::resolve_terminfo_dirs,
+        importer_debug,
+        osutils::{resolve_terminfo_dirs, set_process_title},
         pyalloc::PythonMemoryAllocator,
         python_resources::PythonResourcesState,
+        run_report,
+        startup_timing::{StartupPhase, StartupTimer, StartupTimings},
+    },
+    cpython::{
+        GILGuard, NoArgs, ObjectProtocol, PyDict, PyList, PyObject, PyResult, PyString, PyTuple,
+        Python, PythonObject, ToPyObject,
     },
-    cpython::{GILGuard, NoArgs, ObjectProtocol, PyDict, PyList, PyString, Python, ToPyObject},
     once_cell::sync::Lazy,
     python3_sys as pyffi,
-    python_packaging::interpreter::TerminfoResolution,
+    python_packaging::interpreter::{RunModuleMode, TerminfoResolution},
     std::{
-        collections::BTreeSet,
+        collections::{BTreeMap, BTreeSet},
         convert::{TryFrom, TryInto},
-        env, fs,
+        env,
+        ffi::OsStr,
+        fs,
         io::Write,
         path::{Path, PathBuf},
+        time::Duration,
     },
 };
 
@@ -57,6 +71,14 @@ pub struct MainPythonInterpreter<'python, 'interpreter: 'python, 'resources: 'in
     py: Option<Python<'python>>,
     /// File to write containing list of modules when the interpreter finalizes.
     write_modules_path: Option<PathBuf>,
+    /// File to write a run report to when the interpreter finalizes.
+    run_report_path: Option<PathBuf>,
+    /// asyncio event loop driven by `run_asyncio_step()`.
+    asyncio: Option<AsyncioState>,
+    /// How long the phases of initialization took.
+    startup_timings: StartupTimings,
+    /// Closures queued by `PythonDispatcher` instances.
+    dispatch_queue: DispatchQueue,
 }
 
 impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpreter, 'resources> {
@@ -87,6 +109,10 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             gil: None,
             py: None,
             write_modules_path: None,
+            run_report_path: None,
+            asyncio: None,
+            startup_timings: StartupTimings::default(),
+            dispatch_queue: DispatchQueue::new(),
         };
 
         res.init()?;
@@ -108,17 +134,94 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
     /// Returns a Python instance which has the GIL acquired.
     fn init(&mut self) -> Result<(), NewInterpreterError> {
         assert!(self.interpreter_guard.is_none());
-        self.interpreter_guard = Some(GLOBAL_INTERPRETER_GUARD.lock().map_err(|_| {
-            NewInterpreterError::Simple("unable to acquire global interpreter guard")
-        })?);
+        self.interpreter_guard = Some(
+            GLOBAL_INTERPRETER_GUARD
+                .lock()
+                .map_err(|_| NewInterpreterError::InterpreterGuard)?,
+        );
+
+        // If initialization fails, dropping the timer reports the phase
+        // that failed.
+        let mut timer = StartupTimer::from_env();
+        timer.begin(StartupPhase::PreInit);
 
         let origin_string = self.config.origin().display().to_string();
 
+        // Process setup happens before anything in the interpreter can
+        // observe the process state.
+        if let Some(path) = &self.config.working_directory {
+            env::set_current_dir(path).map_err(|source| NewInterpreterError::Io {
+                context: "changing working directory to".to_string(),
+                path: path.clone(),
+                source,
+            })?;
+        }
+
+        #[cfg(unix)]
+        if let Some(mask) = self.config.umask {
+            crate::osutils::set_umask(mask);
+        }
+
+        if let Some(title) = &self.config.process_title {
+            set_process_title(title).map_err(NewInterpreterError::InvalidConfig)?;
+        }
+
+        set_build_info(self.config.build_info.clone());
+
+        // Updates must be installed before any file they replace is loaded.
+        if let Some(config) = &self.config.self_update {
+            #[cfg(feature = "self-update")]
+            {
+                let origin = self.config.origin();
+
+                crate::self_update::install_staged_update(&config.staging_directory, origin)
+                    .map_err(|source| NewInterpreterError::Io {
+                        context: "installing update staged in".to_string(),
+                        path: config.staging_directory.clone(),
+                        source,
+                    })?;
+
+                crate::self_update_module::set_self_update_config(
+                    config.clone(),
+                    origin.to_path_buf(),
+                );
+            }
+
+            #[cfg(not(feature = "self-update"))]
+            {
+                let _ = config;
+                return Err(NewInterpreterError::InvalidConfig(
+                    "self_update requires the self-update feature of pyembed".to_string(),
+                ));
+            }
+        }
+
+        // Logging must be enabled before our importer is installed so
+        // lookups during initialization are logged.
+        if let Some(destination) = &self.config.importer_debug {
+            importer_debug::enable(OsStr::new(destination))
+                .map_err(NewInterpreterError::InvalidConfig)?;
+        }
+
+        // Recording must be enabled before our importer is installed so
+        // modules imported during initialization are recorded.
+        let run_report_directory = self
+            .config
+            .run_report_directory_env
+            .as_ref()
+            .and_then(|key| std::env::var_os(key))
+            .map(PathBuf::from);
+
+        if run_report_directory.is_some() {
+            run_report::enable();
+        }
+
         if let Some(tcl_library) = &self.config.tcl_library {
             std::env::set_var("TCL_LIBRARY", tcl_library);
         }
 
         set_pyimport_inittab(&self.config);
+        set_pyimport_frozen_modules(&self.config);
 
         // Pre-configure Python.
         let pre_config = pyffi::PyPreConfig::try_from(&self.config)?;
@@ -152,7 +255,7 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
 
             if self.config.allocator_pymalloc_arena {
                 if self.config.allocator_mem || self.config.allocator_obj {
-                    return Err(NewInterpreterError::Simple("A custom pymalloc arena allocator cannot be used with custom `mem` or `obj` domain allocators"));
+                    return Err(NewInterpreterError::InvalidConfig("a custom pymalloc arena allocator cannot be used with custom `mem` or `obj` domain allocators".to_string()));
                 }
 
                 allocator.set_arena_allocator();
@@ -167,6 +270,19 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             }
         }
 
+        // Hooks are added after allocators are configured, since Python frees
+        // their memory with the configured allocator, and before
+        // initialization so they see every event.
+        if !self.config.audit_hooks.is_empty() || self.config.audit_log.is_some() {
+            crate::audit::install_audit_hooks(
+                &self.config.audit_hooks,
+                &self.config.audit_events,
+                self.config.audit_log.as_deref(),
+            )?;
+        }
+
+        timer.begin(StartupPhase::CoreInit);
+
         let mut py_config: pyffi::PyConfig = (&self.config).try_into()?;
 
         // Enable multi-phase initialization. This allows us to initialize
@@ -189,9 +305,27 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
         let py = unsafe { Python::assume_gil_acquired() };
         self.py = Some(py);
 
+        timer.begin(StartupPhase::ImporterSetup);
+
+        let mut gettext_catalogs = None;
+
         if self.config.oxidized_importer {
             let resources_state = Box::new(PythonResourcesState::try_from(&self.config)?);
 
+            if self.config.gettext_catalogs {
+                gettext_catalogs = Some(
+                    crate::gettext::index_gettext_catalogs(py, &resources_state).map_err(
+                        |err| {
+                            NewInterpreterError::new_from_pyerr(
+                                py,
+                                err,
+                                "indexing gettext message catalogs",
+                            )
+                        },
+                    )?,
+                );
+            }
+
             let oxidized_importer = py.import(OXIDIZED_IMPORTER_NAME_STR).map_err(|err| {
                 NewInterpreterError::new_from_pyerr(py, err, "import of oxidized importer module")
             })?;
@@ -217,6 +351,8 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
         // Now proceed with the Python main initialization. This will initialize
         // importlib. And if the custom importlib bytecode was registered above,
         // our extension module will get imported and initialized.
+        timer.begin(StartupPhase::MainInit);
+
         let status = unsafe { pyffi::_Py_InitializeMain() };
 
         if unsafe { pyffi::PyStatus_Exception(status) } != 0 {
@@ -226,6 +362,8 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             ));
         }
 
+        timer.begin(StartupPhase::PostConfig);
+
         // When the main initialization ran, it initialized the "external"
         // importer (importlib._bootstrap_external). Our meta path importer
         // should have been registered first and would have been used for
@@ -255,6 +393,73 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
          * PyObject_SetArenaAllocator()
          */
 
+        if self.config.line_editing {
+            #[cfg(feature = "rustyline")]
+            crate::line_editing::install_line_editor();
+
+            #[cfg(not(feature = "rustyline"))]
+            return Err(NewInterpreterError::InvalidConfig(
+                "line editing requires the line-editing feature of pyembed".to_string(),
+            ));
+        }
+
+        if self.config.python_logging_to_rust {
+            #[cfg(feature = "log")]
+            crate::log_bridge::install_python_handler(py).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "installing Rust logging handler")
+            })?;
+
+            #[cfg(not(feature = "log"))]
+            return Err(NewInterpreterError::InvalidConfig(
+                "python_logging_to_rust requires the log-bridge feature of pyembed".to_string(),
+            ));
+        }
+
+        if self.config.gettext_catalogs {
+            let catalogs = gettext_catalogs.ok_or_else(|| {
+                NewInterpreterError::InvalidConfig(
+                    "gettext_catalogs requires oxidized_importer".to_string(),
+                )
+            })?;
+
+            crate::gettext::install_gettext_catalogs(
+                py,
+                catalogs,
+                self.config.gettext_cache_directory.as_deref(),
+            )
+            .map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "patching gettext catalog lookup")
+            })?;
+        }
+
+        if !self.config.ctypes_libraries.is_empty() {
+            crate::ctypes_libraries::install_ctypes_libraries(py, &self.config.ctypes_libraries)
+                .map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(py, err, "patching ctypes library loading")
+                })?;
+        }
+
+        if let Some(config) = &self.config.remote_resources {
+            #[cfg(feature = "remote-resources")]
+            crate::remote_resources::install_remote_resources_finder(py, config.clone()).map_err(
+                |err| {
+                    NewInterpreterError::new_from_pyerr(
+                        py,
+                        err,
+                        "installing remote resources finder",
+                    )
+                },
+            )?;
+
+            #[cfg(not(feature = "remote-resources"))]
+            {
+                let _ = config;
+                return Err(NewInterpreterError::InvalidConfig(
+                    "remote_resources requires the remote-resources feature of pyembed".to_string(),
+                ));
+            }
+        }
+
         if self.config.argvb {
             let args_objs = self
                 .config
@@ -272,7 +477,35 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
 
             match res {
                 0 => (),
-                _ => return Err(NewInterpreterError::Simple("unable to set sys.argvb")),
+                _ => return Err(NewInterpreterError::SysAttribute("argvb")),
+            }
+        }
+
+        if self.config.sys_orig_argv {
+            let args_objs = self
+                .config
+                .orig_argv()
+                .iter()
+                .map(|x| {
+                    osstr_to_pyobject(py, x, None).map_err(|_| {
+                        NewInterpreterError::StringConversion {
+                            context: "setting sys.orig_argv".to_string(),
+                            value: x.to_string_lossy().to_string(),
+                        }
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let args = PyList::new(py, &args_objs);
+            let orig_argv = b"orig_argv\0";
+
+            let res = args.with_borrowed_ptr(py, |args_ptr| unsafe {
+                pyffi::PySys_SetObject(orig_argv.as_ptr() as *const i8, args_ptr)
+            });
+
+            match res {
+                0 => (),
+                _ => return Err(NewInterpreterError::SysAttribute("orig_argv")),
             }
         }
 
@@ -286,9 +519,13 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
 
         match res {
             0 => (),
-            _ => return Err(NewInterpreterError::Simple("unable to set sys.oxidized")),
+            _ => return Err(NewInterpreterError::SysAttribute("oxidized")),
         }
 
+        set_sys_oxidized_config(py, &self.config.summary()).map_err(|err| {
+            NewInterpreterError::new_from_pyerr(py, err, "setting sys._oxidized_config")
+        })?;
+
         if self.config.sys_frozen {
             let frozen = b"frozen\0";
 
@@ -296,7 +533,7 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
                 pyffi::PySys_SetObject(frozen.as_ptr() as *const i8, py_true)
             }) {
                 0 => (),
-                _ => return Err(NewInterpreterError::Simple("unable to set sys.frozen")),
+                _ => return Err(NewInterpreterError::SysAttribute("frozen")),
             }
         }
 
@@ -308,7 +545,7 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
                 pyffi::PySys_SetObject(meipass.as_ptr() as *const i8, py_value)
             }) {
                 0 => (),
-                _ => return Err(NewInterpreterError::Simple("unable to set sys._MEIPASS")),
+                _ => return Err(NewInterpreterError::SysAttribute("_MEIPASS")),
             }
         }
 
@@ -316,44 +553,63 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             if let Ok(path) = std::env::var(key) {
                 let path = PathBuf::from(path);
 
-                std::fs::create_dir_all(&path).map_err(|e| {
-                    NewInterpreterError::Dynamic(format!(
-                        "error creating directory for loaded modules files: {}",
-                        e.to_string()
-                    ))
+                std::fs::create_dir_all(&path).map_err(|e| NewInterpreterError::Io {
+                    context: "creating directory for loaded modules files".to_string(),
+                    path: path.clone(),
+                    source: e,
                 })?;
 
-                // We use Python's uuid module to generate a filename. This avoids
-                // a dependency on a Rust crate, which cuts down on dependency bloat.
-                let uuid_mod = py.import("uuid").map_err(|e| {
-                    NewInterpreterError::new_from_pyerr(py, e, "importing uuid module")
-                })?;
-                let uuid = uuid_mod.call(py, "uuid4", NoArgs, None).map_err(|e| {
-                    NewInterpreterError::new_from_pyerr(py, e, "calling uuid.uuid()")
-                })?;
-                let uuid_str = uuid
-                    .str(py)
-                    .map_err(|e| {
-                        NewInterpreterError::new_from_pyerr(py, e, "converting uuid to str")
-                    })?
-                    .to_string(py)
-                    .map_err(|e| {
-                        NewInterpreterError::new_from_pyerr(
-                            py,
-                            e,
-                            "converting uuid str to Rust string",
-                        )
-                    })?
-                    .to_string();
+                let uuid_str = uuid4_string(py)?;
 
                 self.write_modules_path =
                     Some(PathBuf::from(path).join(format!("modules-{}", uuid_str)));
             }
         }
 
+        if let Some(path) = run_report_directory {
+            std::fs::create_dir_all(&path).map_err(|e| NewInterpreterError::Io {
+                context: "creating directory for run reports".to_string(),
+                path: path.clone(),
+                source: e,
+            })?;
+
+            self.run_report_path =
+                Some(path.join(format!("run-report-{}.json", uuid4_string(py)?)));
+        }
+
+        // Restrictions apply once our own initialization is done, since it may
+        // use facilities Python code is denied.
+        if let Some(config) = &self.config.restricted_mode {
+            crate::restricted::install_restricted_mode(py, config).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "installing restricted mode")
+            })?;
+        }
+
+        self.startup_timings = timer.finish();
+
         Ok(())
     }
 
+    /// Obtain metadata describing how the running binary was built.
+    ///
+    /// This is the `build_info` value of the configuration used to
+    /// construct this instance.
+    pub fn build_info(&self) -> &BTreeMap<String, String> {
+        &self.config.build_info
+    }
+
+    /// Obtain a summary of how this interpreter was configured.
+    ///
+    /// This is the same data exposed to Python as `sys._oxidized_config`.
+    pub fn config_summary(&self) -> OxidizedConfigSummary {
+        self.config.summary()
+    }
+
+    /// Obtain how long the phases of interpreter initialization took.
+    pub fn startup_timings(&self) -> &StartupTimings {
+        &self.startup_timings
+    }
+
     /// Ensure the Python GIL is released.
     pub fn release_gil(&mut self) {
         self.py = None;
@@ -381,17 +637,152 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
         }
     }
 
+    /// Obtain a handle other threads can use to run closures on this thread.
+    ///
+    /// Queued closures run when [Self::run_dispatched] is called.
+    pub fn dispatcher(&self) -> PythonDispatcher {
+        self.dispatch_queue.dispatcher()
+    }
+
+    /// Run closures queued by [PythonDispatcher] instances.
+    ///
+    /// If none are queued and `timeout` is set, waits up to that long for one
+    /// to be queued. Waiting doesn't acquire the GIL, but doesn't release it
+    /// if this thread holds it either.
+    ///
+    /// Returns the number of closures that ran.
+    pub fn run_dispatched(&mut self, timeout: Option<Duration>) -> usize {
+        let jobs = self.dispatch_queue.take_jobs(timeout);
+        let count = jobs.len();
+
+        if count > 0 {
+            let py = self.acquire_gil();
+
+            for job in jobs {
+                job(py);
+            }
+        }
+
+        count
+    }
+
+    /// Register an extension module after the interpreter has been initialized.
+    ///
+    /// This is like `OxidizedPythonInterpreterConfig.extra_extension_modules`
+    /// except it can be called once the interpreter is running, e.g. to expose
+    /// native plugins discovered at run-time. The extension module is
+    /// initialized the next time it is imported. An error occurs if an
+    /// extension module with the same name is already registered.
+    pub fn register_extension_module(
+        &mut self,
+        module: ExtensionModule,
+    ) -> Result<(), NewInterpreterError> {
+        let py = self.acquire_gil();
+
+        let name = module
+            .name
+            .to_str()
+            .map_err(|_| NewInterpreterError::StringConversion {
+                context: "registering extension module".to_string(),
+                value: module.name.to_string_lossy().to_string(),
+            })?;
+
+        register_builtin_extension_module(py, name, module.init_func).map_err(|err| {
+            NewInterpreterError::new_from_pyerr(py, err, "registering extension module")
+        })
+    }
+
+    /// Run a function against the asyncio event loop, creating it if needed.
+    fn with_asyncio<T>(
+        &mut self,
+        f: impl FnOnce(Python, &mut AsyncioState) -> PyResult<T>,
+    ) -> Result<T, AsyncioError> {
+        let state = self.asyncio.take();
+        let py = self.acquire_gil();
+
+        let mut state = match state {
+            Some(state) => state,
+            None => AsyncioState::new(py)
+                .map_err(|err| AsyncioError::Python(format_exception(py, err)))?,
+        };
+
+        let res = f(py, &mut state).map_err(|err| AsyncioError::Python(format_exception(py, err)));
+
+        self.asyncio = Some(state);
+
+        res
+    }
+
+    /// Run a single iteration of the interpreter's asyncio event loop.
+    ///
+    /// Callbacks that are ready are run and I/O is polled without blocking.
+    /// Futures of coroutines that finished during the iteration are completed.
+    ///
+    /// This is meant to be called repeatedly by a host event loop (e.g. from
+    /// a timer or an idle callback) to run asyncio code without dedicating a
+    /// thread to it. The event loop is created and installed as the current
+    /// event loop on first use.
+    pub fn run_asyncio_step(&mut self) -> Result<(), AsyncioError> {
+        self.with_asyncio(|py, state| state.step(py))
+    }
+
+    /// Schedule the coroutine returned by `module.function()` on the interpreter's
+    /// asyncio event loop.
+    ///
+    /// Returns a future resolving to the coroutine's return value. The
+    /// coroutine only makes progress when `run_asyncio_step()` is called.
+    /// Coroutines still pending when the interpreter is dropped are cancelled.
+    pub fn spawn_coroutine(
+        &mut self,
+        module: &str,
+        function: &str,
+    ) -> Result<PythonCoroutineFuture, AsyncioError> {
+        self.with_asyncio(|py, state| state.spawn(py, module, function))
+    }
+
+    /// Run a module as `__main__`.
+    ///
+    /// If `alter_sys` is true, the module is run like `python -m` does: its
+    /// code is executed in the namespace of the `__main__` module, with
+    /// `__spec__`, `__package__`, and `__loader__` describing the module so
+    /// relative imports work. `sys.argv[0]` is set to the module's origin if
+    /// it has one. Packages are run via their `__main__` submodule.
+    ///
+    /// If `alter_sys` is false, the module is run in a fresh namespace named
+    /// `__main__` via `runpy.run_module()` and `sys` isn't modified.
+    ///
+    /// Returns the globals of the executed module.
+    pub fn run_module(&mut self, name: &str, alter_sys: bool) -> PyResult<PyObject> {
+        let py = self.acquire_gil();
+
+        crate::runpy::run_module(py, name, alter_sys)
+    }
+
     /// Runs `Py_RunMain()` and finalizes the interpreter.
     ///
     /// This will execute whatever is configured by the Python interpreter config
     /// and return an integer suitable for use as a process exit code.
     ///
+    /// If `run_module_mode` is `RunModuleMode::Runpy` and a `run_module` is
+    /// configured, the module is run via `run_module()` instead.
+    ///
     /// Calling this function will finalize the interpreter and only gives you an
     /// exit code: there is no opportunity to inspect the return value or handle
     /// an uncaught exception. If you want to keep the interpreter alive or inspect
     /// the evaluation result, consider calling a function on the interpreter handle
     /// that executes code.
-    pub fn py_runmain(self) -> i32 {
+    pub fn py_runmain(mut self) -> i32 {
+        if self.config.run_module_mode == RunModuleMode::Runpy {
+            if let Some(name) = self.config.interpreter_config.run_module.clone() {
+                let py = self.acquire_gil();
+
+                return match crate::runpy::run_module(py, &name, true) {
+                    Ok(_) => 0,
+                    Err(err) => crate::runpy::exit_code_for_err(py, err),
+                };
+            }
+        }
+
         unsafe { pyffi::Py_RunMain() }
     }
 }
@@ -442,6 +833,14 @@ fn set_pyimport_inittab(config: &OxidizedPythonInterpreterConfig) {
     }
 
     // Add additional extension modules from the config.
+    //
+    // Entries are handled by `_imp.create_builtin()`, which inspects the value
+    // returned by the init function. Single-phase init functions return a
+    // module and have their `PyModuleDef` recorded for re-initialization.
+    // Multi-phase init functions return a `PyModuleDef`, from which a module
+    // is created using the import spec, and `_imp.exec_builtin()` later runs
+    // its `Py_mod_exec` slots. Since both styles share a signature, we can
+    // register them identically.
     if let Some(extra_extension_modules) = &config.extra_extension_modules {
         for extension in extra_extension_modules {
             let ptr = extension.init_func as *const ();
@@ -465,38 +864,156 @@ fn set_pyimport_inittab(config: &OxidizedPythonInterpreterConfig) {
     }
 }
 
+static mut ORIGINAL_FROZEN_MODULES: Option<Vec<pyffi::_frozen>> = None;
+static mut REPLACED_FROZEN_MODULES: Option<Box<Vec<pyffi::_frozen>>> = None;
+
+/// Set PyImport_FrozenModules from config options.
+///
+/// Like `set_pyimport_inittab()`, we maintain a shadow copy of the original
+/// array so repeated interpreter initialization doesn't accumulate entries.
+fn set_pyimport_frozen_modules(config: &OxidizedPythonInterpreterConfig) {
+    unsafe {
+        if ORIGINAL_FROZEN_MODULES.is_none() {
+            let mut entries: Vec<pyffi::_frozen> = Vec::new();
+
+            for i in 0.. {
+                let record = pyffi::PyImport_FrozenModules.offset(i);
+
+                if (*record).name.is_null() {
+                    break;
+                }
+
+                entries.push(*record);
+            }
+
+            ORIGINAL_FROZEN_MODULES = Some(entries);
+        }
+    }
+
+    // Our entries come first so they take precedence over same-named entries
+    // compiled into libpython, as lookups stop at the first match.
+    let mut modules = Box::new(Vec::new());
+
+    if let Some(extra_frozen_modules) = &config.extra_frozen_modules {
+        for module in extra_frozen_modules {
+            let size = module.code.len() as libc::c_int;
+
+            modules.push(pyffi::_frozen {
+                name: module.name.as_ptr(),
+                code: module.code.as_ptr(),
+                // Packages are denoted by a negative size.
+                size: if module.is_package { -size } else { size },
+            });
+        }
+    }
+
+    modules.extend(unsafe { ORIGINAL_FROZEN_MODULES.as_ref().unwrap().iter().copied() });
+
+    // Add sentinel record with NULLs.
+    modules.push(pyffi::_frozen {
+        name: std::ptr::null(),
+        code: std::ptr::null(),
+        size: 0,
+    });
+
+    unsafe {
+        REPLACED_FROZEN_MODULES = Some(modules);
+        pyffi::PyImport_FrozenModules = REPLACED_FROZEN_MODULES.as_ref().unwrap().as_ptr();
+    }
+}
+
+/// Set `sys._oxidized_config` to a read-only mapping describing the configuration.
+fn set_sys_oxidized_config(py: Python, summary: &OxidizedConfigSummary) -> PyResult<()> {
+    let values = PyDict::new(py);
+    values.set_item(py, "oxidized_importer", summary.oxidized_importer)?;
+    values.set_item(py, "filesystem_importer", summary.filesystem_importer)?;
+    values.set_item(
+        py,
+        "packed_resources",
+        PyTuple::new(
+            py,
+            &summary
+                .packed_resources
+                .iter()
+                .map(|source| PyString::new(py, source).into_object())
+                .collect::<Vec<_>>(),
+        ),
+    )?;
+    values.set_item(
+        py,
+        "allocator_backend",
+        summary.allocator_backend.to_string(),
+    )?;
+    values.set_item(py, "run_mode", summary.run_mode)?;
+    values.set_item(py, "origin", path_to_pyobject(py, &summary.origin)?)?;
+    values.set_item(py, "restricted_mode", summary.restricted_mode)?;
+
+    let proxy = py
+        .import("types")?
+        .call(py, "MappingProxyType", (values,), None)?;
+
+    py.import("sys")?.add(py, "_oxidized_config", proxy)
+}
+
+/// Obtain a random UUID as a string.
+///
+/// We use Python's uuid module to generate a filename. This avoids
+/// a dependency on a Rust crate, which cuts down on dependency bloat.
+fn uuid4_string(py: Python) -> Result<String, NewInterpreterError> {
+    let uuid_mod = py
+        .import("uuid")
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "importing uuid module"))?;
+    let uuid = uuid_mod
+        .call(py, "uuid4", NoArgs, None)
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "calling uuid.uuid()"))?;
+
+    Ok(uuid
+        .str(py)
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "converting uuid to str"))?
+        .to_string(py)
+        .map_err(|e| {
+            NewInterpreterError::new_from_pyerr(py, e, "converting uuid str to Rust string")
+        })?
+        .to_string())
+}
+
 /// Write loaded Python modules to a directory.
 ///
 /// Given a Python interpreter and a path to a directory, this will create a
 /// file in that directory named ``modules-<UUID>`` and write a ``\n`` delimited
 /// list of loaded names from ``sys.modules`` into that file.
-fn write_modules_to_path(py: Python, path: &Path) -> Result<(), &'static str> {
-    // TODO this needs better error handling all over.
-
+fn write_modules_to_path(py: Python, path: &Path) -> Result<(), NewInterpreterError> {
     let sys = py
         .import("sys")
-        .map_err(|_| "could not obtain sys module")?;
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "obtaining sys module"))?;
     let modules = sys
         .get(py, "modules")
-        .map_err(|_| "could not obtain sys.modules")?;
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "obtaining sys.modules"))?;
 
     let modules = modules
         .cast_as::<PyDict>(py)
-        .map_err(|_| "sys.modules is not a dict")?;
+        .map_err(|_| NewInterpreterError::Python {
+            context: "obtaining sys.modules".to_string(),
+            message: "sys.modules is not a dict".to_string(),
+        })?;
 
     let mut names = BTreeSet::new();
     for (key, _value) in modules.items(py) {
-        names.insert(
-            key.extract::<String>(py)
-                .map_err(|_| "module name is not a str")?,
-        );
+        names.insert(key.extract::<String>(py).map_err(|e| {
+            NewInterpreterError::new_from_pyerr(py, e, "converting module name to Rust string")
+        })?);
     }
 
-    let mut f = fs::File::create(path).map_err(|_| "could not open file for writing")?;
+    let io_error = |source| NewInterpreterError::Io {
+        context: "writing modules file".to_string(),
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let mut f = fs::File::create(path).map_err(io_error)?;
 
     for name in names {
-        f.write_fmt(format_args!("{}\n", name))
-            .map_err(|_| "could not write")?;
+        f.write_fmt(format_args!("{}\n", name)).map_err(io_error)?;
     }
 
     Ok(())
@@ -512,6 +1029,28 @@ impl<'python, 'interpreter, 'resources> Drop
             }
         }
 
+        if let Some(path) = self.run_report_path.clone() {
+            let sections = self.config.run_report_sections.clone();
+
+            if let Err(msg) = run_report::write_run_report(self.acquire_gil(), &path, &sections) {
+                eprintln!("error writing run report: {}", msg);
+            }
+        }
+
+        if let Some(state) = self.asyncio.take() {
+            state.close(self.acquire_gil());
+        }
+
+        // Closures that never ran may own Python objects, which must be
+        // released before finalizing. Closing the queue waits for dispatches
+        // in progress and makes further dispatches fail, so nothing can be
+        // queued after this.
+        let jobs = self.dispatch_queue.close();
+        if !jobs.is_empty() {
+            let _py = self.acquire_gil();
+            drop(jobs);
+        }
+
         let _ = unsafe { pyffi::Py_FinalizeEx() };
     }
 }
@@ -20,7 +20,7 @@ use {
 };
 
 #[cfg(target_family = "unix")]
-use std::{ffi::NulError, os::unix::ffi::OsStrExt};
+use std::os::unix::ffi::OsStrExt;
 
 #[cfg(target_family = "windows")]
 use std::os::windows::prelude::OsStrExt;
@@ -45,10 +45,10 @@ fn set_config_string_from_str(
                 Ok(())
             }
         },
-        Err(_) => Err(NewInterpreterError::Dynamic(format!(
-            "during {}: unable to convert {} to C string",
-            context, value
-        ))),
+        Err(_) => Err(NewInterpreterError::StringConversion {
+            context: context.to_string(),
+            value: value.to_string(),
+        }),
     }
 }
 
@@ -59,8 +59,12 @@ fn set_config_string_from_path(
     path: &Path,
     context: &str,
 ) -> Result<(), NewInterpreterError> {
-    let value = CString::new(path.as_os_str().as_bytes())
-        .map_err(|_| NewInterpreterError::Simple("cannot convert path to C string"))?;
+    let value = CString::new(path.as_os_str().as_bytes()).map_err(|_| {
+        NewInterpreterError::StringConversion {
+            context: context.to_string(),
+            value: path.display().to_string(),
+        }
+    })?;
 
     let status = unsafe {
         pyffi::PyConfig_SetBytesString(
@@ -109,18 +113,20 @@ fn append_wide_string_list_from_str(
     value: &str,
     context: &str,
 ) -> Result<(), NewInterpreterError> {
-    let value = CString::new(value)
-        .map_err(|_| NewInterpreterError::Simple("unable to convert value to C string"))?;
+    let value = CString::new(value).map_err(|_| NewInterpreterError::StringConversion {
+        context: context.to_string(),
+        value: value.to_string(),
+    })?;
 
     let mut len: size_t = 0;
 
     let decoded = unsafe { pyffi::Py_DecodeLocale(value.as_ptr() as *const _, &mut len) };
 
     if decoded.is_null() {
-        Err(NewInterpreterError::Dynamic(format!(
-            "during {}: unable to decode value",
-            context
-        )))
+        Err(NewInterpreterError::StringConversion {
+            context: context.to_string(),
+            value: value.to_string_lossy().to_string(),
+        })
     } else {
         let status = unsafe { pyffi::PyWideStringList_Append(dest as *mut _, decoded) };
         unsafe {
@@ -144,7 +150,10 @@ fn append_wide_string_list_from_path(
     let value = path
         .as_os_str()
         .to_str()
-        .ok_or_else(|| NewInterpreterError::Simple("unable to convert value to str"))?;
+        .ok_or_else(|| NewInterpreterError::StringConversion {
+            context: context.to_string(),
+            value: path.display().to_string(),
+        })?;
 
     append_wide_string_list_from_str(dest, value, context)
 }
@@ -194,9 +203,13 @@ pub fn set_argv(
     let argc = args.len() as isize;
     let argv = args
         .iter()
-        .map(|x| CString::new(x.as_bytes()))
-        .collect::<Result<Vec<_>, NulError>>()
-        .map_err(|_| NewInterpreterError::Simple("unable to construct C string from OsString"))?;
+        .map(|x| {
+            CString::new(x.as_bytes()).map_err(|_| NewInterpreterError::StringConversion {
+                context: "setting argv".to_string(),
+                value: x.to_string_lossy().to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
     let argvp = argv
         .iter()
         .map(|x| x.as_ptr() as *mut i8)
@@ -582,13 +595,13 @@ impl<'a> TryInto<pyffi::PyConfig> for &'a ResolvedOxidizedPythonInterpreterConfi
         }
 
         if self.exe.is_none() {
-            return Err(NewInterpreterError::Simple(
-                "current executable not set; must call ensure_origin() 1st",
+            return Err(NewInterpreterError::InvalidConfig(
+                "current executable not set; must call ensure_origin() 1st".to_string(),
             ));
         }
         if self.origin.is_none() {
-            return Err(NewInterpreterError::Simple(
-                "origin not set; must call ensure_origin() 1st",
+            return Err(NewInterpreterError::InvalidConfig(
+                "origin not set; must call ensure_origin() 1st".to_string(),
             ));
         }
         let exe = self.exe.as_ref().unwrap();
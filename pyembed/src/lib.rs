@@ -29,6 +29,13 @@ and [`MainPythonInterpreter`](struct.MainPythonInterpreter.html). An
 `OxidizedPythonInterpreterConfig` defines how a Python interpreter is to
 behave. A `MainPythonInterpreter` creates and manages that interpreter and
 serves as a high-level interface for running code in the interpreter.
+[`PythonWorkerPool`](struct.PythonWorkerPool.html) runs Python functions
+concurrently on a pool of threads sharing a `MainPythonInterpreter`.
+[`PythonDispatcher`](struct.PythonDispatcher.html) lets other Rust threads
+queue closures to run on the thread owning a `MainPythonInterpreter`.
+`MainPythonInterpreter::run_asyncio_step()` and
+`MainPythonInterpreter::spawn_coroutine()` integrate asyncio code with an
+event loop owned by the embedding application.
 
 # Dependencies
 
@@ -44,7 +51,10 @@ At this time, we have required direct dependencies on published versions of the
 list is extended by `memory-module-sys` and `winapi`, which are required to
 support loading DLLs from memory. We also have an optional direct dependency
 on the `jemalloc-sys`, `libmimalloc-sys`, and `snmalloc-sys` crates for custom
-memory allocators.
+memory allocators, on the `rustyline` crate for line editing, on the `log`
+crate for bridging log records, on the `ed25519-dalek` and `sha2` crates for
+verifying updates, on the `serde_json` crate for the C API, and on Windows on
+the `windows-service` crate for running as a service.
 
 This crate requires linking against a library providing CPython C symbols.
 (This dependency is via the `python3-sys` crate.) On Windows, this library
@@ -66,6 +76,42 @@ The optional `snmalloc` feature controls support for using
 [snmalloc](https://github.com/microsoft/snmalloc) as Python's memory allocator.
 The feature behaves similarly to `jemalloc`, which is documented above.
 
+The optional `line-editing` feature provides line editing and history for
+interactive input (such as the REPL) via
+[rustyline](https://github.com/kkawakam/rustyline), without requiring the
+`readline` extension module or a system readline library. Use of line editing
+is a run-time configuration option controlled by the
+`OxidizedPythonInterpreterConfig` type.
+
+The optional `log-bridge` feature bridges log records between the
+[log](https://crates.io/crates/log) crate and Python's `logging` module.
+`PythonLogger` is a `log` backend forwarding Rust log records to Python.
+Forwarding Python log records to `log` is a run-time configuration option
+controlled by the `OxidizedPythonInterpreterConfig` type.
+
+The optional `windows-service` feature provides `run_windows_service()`, which
+runs the process as a Windows service calling into a Python entry point, via
+[windows-service](https://crates.io/crates/windows-service). It has no effect
+on other platforms.
+
+The optional `self-update` feature provides updating applications from
+signed update manifests. Updates are checked for and downloaded with the
+`oxidized_importer.updates` Python module and installed when the next
+interpreter is initialized. Use of self update is a run-time configuration
+option controlled by the `OxidizedPythonInterpreterConfig` type.
+
+The optional and experimental `remote-resources` feature imports modules
+missing from the binary from a remote resource server. Module sources are
+listed in a signed index, verified, and cached locally. It implies the
+`self-update` feature. Use of remote resources is a run-time configuration
+option controlled by the `OxidizedPythonInterpreterConfig` type.
+
+The optional `c-api` feature provides `extern "C"` functions for creating
+interpreters from a JSON config, running them, and evaluating code, so
+applications written in C, C++, Go, etc can embed Python without writing
+Rust. The functions are declared in `include/pyembed.h`. Link a `staticlib`
+or `cdylib` crate depending on this crate to obtain a library exporting them.
+
 There exist mutually exclusive `build-mode-*` features to control how the
 `build.rs` build script works.
 
@@ -93,22 +139,40 @@ That crate's build script will attempt to find a `libpython` from the
 
 */
 
+#[cfg(not(library_mode = "extension"))]
+mod asyncio;
+#[cfg(not(library_mode = "extension"))]
+mod audit;
+#[cfg(all(feature = "c-api", not(library_mode = "extension")))]
+pub mod c_api;
 #[allow(unused)]
 mod config;
 mod conversion;
+#[cfg(not(library_mode = "extension"))]
+mod ctypes_libraries;
+#[cfg(not(library_mode = "extension"))]
+mod dispatcher;
 mod error;
+#[cfg(not(library_mode = "extension"))]
+mod gettext;
 #[allow(clippy::transmute_ptr_to_ptr, clippy::zero_ptr)]
 mod importer;
+mod importer_debug;
 #[cfg(not(library_mode = "extension"))]
 mod interpreter;
 #[cfg(not(library_mode = "extension"))]
 mod interpreter_config;
+#[cfg(all(feature = "rustyline", not(library_mode = "extension")))]
+mod line_editing;
+#[cfg(all(feature = "log", not(library_mode = "extension")))]
+mod log_bridge;
 #[cfg(windows)]
 mod memory_dll;
 #[cfg(not(library_mode = "extension"))]
 mod osutils;
 #[allow(clippy::transmute_ptr_to_ptr, clippy::zero_ptr)]
 mod package_metadata;
+mod pathutils;
 #[cfg(not(library_mode = "extension"))]
 mod pyalloc;
 #[allow(unused_variables, clippy::transmute_ptr_to_ptr, clippy::zero_ptr)]
@@ -117,19 +181,65 @@ mod python_resource_collector;
 mod python_resource_types;
 #[allow(clippy::transmute_ptr_to_ptr, clippy::zero_ptr)]
 mod python_resources;
+#[cfg(all(feature = "remote-resources", not(library_mode = "extension")))]
+mod remote_resources;
 mod resource_scanning;
 #[cfg(not(library_mode = "extension"))]
+mod restricted;
+#[allow(unused)]
+mod run_report;
+#[cfg(not(library_mode = "extension"))]
+mod runpy;
+#[cfg(feature = "self-update")]
+mod self_update;
+#[cfg(all(feature = "self-update", not(library_mode = "extension")))]
+mod self_update_module;
+#[cfg(not(library_mode = "extension"))]
+mod startup_timing;
+#[cfg(not(library_mode = "extension"))]
 pub mod technotes;
 #[cfg(test)]
 mod test;
+#[cfg(all(windows, feature = "windows-service", not(library_mode = "extension")))]
+mod windows_service;
+#[cfg(not(library_mode = "extension"))]
+mod worker_pool;
 
-pub use crate::{config::PackedResourcesSource, error::NewInterpreterError};
+pub use crate::{
+    config::PackedResourcesSource, error::NewInterpreterError, importer_debug::IMPORTER_DEBUG_ENV,
+};
 
 #[cfg(not(library_mode = "extension"))]
 #[allow(unused_imports)]
 pub use crate::{
-    config::{ExtensionModule, OxidizedPythonInterpreterConfig},
+    asyncio::PythonCoroutineFuture,
+    config::{
+        ArgvRewriter, AuditEvent, AuditHook, ExtensionModule, FrozenModule, OxidizedConfigSummary,
+        OxidizedPythonInterpreterConfig, ResourcePayloadDecoder,
+    },
+    dispatcher::{PythonDispatchResult, PythonDispatcher},
+    error::{AsyncioError, DispatchError, WorkerPoolError},
     interpreter::MainPythonInterpreter,
+    run_report::RUN_REPORT_SECTIONS_ENV,
+    startup_timing::{StartupPhase, StartupTimings, STARTUP_TIMINGS_ENV},
+    worker_pool::{PythonCall, PythonTask, PythonWorkerPool},
+};
+
+#[cfg(all(feature = "log", not(library_mode = "extension")))]
+pub use crate::log_bridge::PythonLogger;
+
+#[cfg(all(windows, feature = "windows-service", not(library_mode = "extension")))]
+pub use crate::windows_service::{run_windows_service, WindowsServiceConfig};
+
+#[cfg(feature = "self-update")]
+pub use crate::self_update::{
+    install_staged_update, parse_version, verify_signature, Fetch, UpdateDelta, UpdateFile,
+    UpdateManifest, Updater,
+};
+
+#[cfg(all(feature = "remote-resources", not(library_mode = "extension")))]
+pub use crate::remote_resources::{
+    load_module_source, RemoteModule, RemoteResourcesIndex, INDEX_HEADER,
 };
 
 #[cfg(library_mode = "extension")]
@@ -140,7 +250,10 @@ pub use crate::importer::PyInit_oxidized_importer;
 pub use python_packaging::{
     interpreter::{
         Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, MemoryAllocatorBackend,
-        PythonInterpreterConfig, PythonInterpreterProfile, TerminfoResolution,
+        ModuleSearchPathsMode, PythonInterpreterConfig, PythonInterpreterProfile,
+        RemoteResourcesConfig, RestrictedFacility, RestrictedModeConfig, RunModuleMode,
+        RunReportSection, SelfUpdateConfig, TerminfoResolution,
     },
     resource::BytecodeOptimizationLevel,
+    resource_collection::xor_resource_payload,
 };
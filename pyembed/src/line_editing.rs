@@ -0,0 +1,135 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Line editing for interactive input.
+
+Python reads lines from a terminal (for the REPL and `input()`) by calling
+`PyOS_ReadlineFunctionPointer`. By default this reads raw lines without any
+editing. Importing the `readline` extension module replaces the function with
+one backed by GNU readline or libedit. But that extension module isn't always
+available, notably in statically linked builds.
+
+This module provides a replacement function backed by `rustyline`, giving
+line editing and history without a system readline library. If the `readline`
+module is imported later, it takes precedence.
+*/
+
+use {
+    once_cell::sync::Lazy,
+    python3_sys as pyffi,
+    rustyline::{error::ReadlineError, Editor},
+    std::{ffi::CStr, os::raw::c_char, path::PathBuf, sync::Mutex},
+};
+
+type ReadlineFunction =
+    unsafe extern "C" fn(*mut libc::FILE, *mut libc::FILE, *const c_char) -> *mut c_char;
+
+extern "C" {
+    static mut PyOS_ReadlineFunctionPointer: Option<ReadlineFunction>;
+}
+
+struct LineEditor {
+    editor: Editor<()>,
+    history_path: Option<PathBuf>,
+}
+
+static LINE_EDITOR: Lazy<Mutex<Option<LineEditor>>> = Lazy::new(|| Mutex::new(None));
+
+/// Resolve the path of the file holding the history of entered lines.
+///
+/// This is the same file Python's REPL uses.
+fn history_path() -> Option<PathBuf> {
+    let home = if cfg!(windows) {
+        std::env::var_os("USERPROFILE")
+    } else {
+        std::env::var_os("HOME")
+    }?;
+
+    Some(PathBuf::from(home).join(".python_history"))
+}
+
+/// Copy a string into memory allocated by Python's raw memory allocator.
+///
+/// Python takes ownership of strings returned by readline functions and
+/// releases them with `PyMem_RawFree()`.
+unsafe fn python_raw_string(s: &str) -> *mut c_char {
+    let data = s.as_bytes();
+
+    let buffer = pyffi::PyMem_RawMalloc(data.len() + 1) as *mut u8;
+    if buffer.is_null() {
+        return std::ptr::null_mut();
+    }
+
+    std::ptr::copy_nonoverlapping(data.as_ptr(), buffer, data.len());
+    *buffer.add(data.len()) = 0;
+
+    buffer as *mut c_char
+}
+
+/// Our implementation of `PyOS_ReadlineFunctionPointer`.
+///
+/// This is called without the GIL held.
+unsafe extern "C" fn readline(
+    _stdin: *mut libc::FILE,
+    _stdout: *mut libc::FILE,
+    prompt: *const c_char,
+) -> *mut c_char {
+    let prompt = if prompt.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(prompt).to_string_lossy().to_string()
+    };
+
+    let mut guard = match LINE_EDITOR.lock() {
+        Ok(guard) => guard,
+        Err(_) => return python_raw_string(""),
+    };
+
+    let state = match guard.as_mut() {
+        Some(state) => state,
+        None => return python_raw_string(""),
+    };
+
+    match state.editor.readline(&prompt) {
+        Ok(line) => {
+            if !line.trim().is_empty() {
+                state.editor.add_history_entry(line.as_str());
+
+                if let Some(path) = &state.history_path {
+                    // Failing to persist history shouldn't interrupt the user.
+                    let _ = state.editor.save_history(path);
+                }
+            }
+
+            python_raw_string(&format!("{}\n", line))
+        }
+        // NULL without an exception set makes Python raise KeyboardInterrupt.
+        Err(ReadlineError::Interrupted) => std::ptr::null_mut(),
+        // An empty string (without a newline) signals end of input.
+        Err(_) => python_raw_string(""),
+    }
+}
+
+/// Install our line editing function as Python's readline function.
+pub(crate) fn install_line_editor() {
+    let history_path = history_path();
+
+    let mut editor = Editor::<()>::new();
+    if let Some(path) = &history_path {
+        // The history file may not exist yet.
+        let _ = editor.load_history(path);
+    }
+
+    if let Ok(mut guard) = LINE_EDITOR.lock() {
+        guard.replace(LineEditor {
+            editor,
+            history_path,
+        });
+    }
+
+    unsafe {
+        PyOS_ReadlineFunctionPointer = Some(readline);
+    }
+}
@@ -0,0 +1,220 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Bridge log records between the `log` crate and Python's `logging` module.
+
+[PythonLogger] is a `log` backend emitting Rust log records to Python
+loggers. Conversely, a `logging.Handler` installed on Python's root logger
+emits Python log records to the `log` facade.
+
+If both directions are active, a record would bounce between them forever.
+So records emitted while forwarding a record on the same thread are dropped.
+*/
+
+use {
+    cpython::{py_fn, ObjectProtocol, PyDict, PyObject, PyResult, Python},
+    log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError},
+    python3_sys as pyffi,
+    std::cell::Cell,
+};
+
+thread_local! {
+    static FORWARDING: Cell<bool> = Cell::new(false);
+}
+
+/// Run a function unless a record is already being forwarded on this thread.
+fn forward(f: impl FnOnce()) {
+    if FORWARDING.with(|forwarding| forwarding.replace(true)) {
+        return;
+    }
+
+    f();
+
+    FORWARDING.with(|forwarding| forwarding.set(false));
+}
+
+/// Convert a `log` level to a `logging` level.
+fn python_level(level: Level) -> i32 {
+    match level {
+        Level::Error => 40,
+        Level::Warn => 30,
+        Level::Info => 20,
+        Level::Debug => 10,
+        Level::Trace => 5,
+    }
+}
+
+/// Convert a `logging` level to a `log` level.
+fn rust_level(level: i32) -> Level {
+    if level >= 40 {
+        Level::Error
+    } else if level >= 30 {
+        Level::Warn
+    } else if level >= 20 {
+        Level::Info
+    } else if level >= 10 {
+        Level::Debug
+    } else {
+        Level::Trace
+    }
+}
+
+/// Convert a `log` target to a Python logger name.
+fn python_logger_name(target: &str) -> String {
+    target.replace("::", ".")
+}
+
+/// A `log` backend emitting records to Python's `logging` module.
+///
+/// The record target is used as the logger name, with `::` replaced by `.`.
+/// Records are dropped when no Python interpreter is running.
+pub struct PythonLogger;
+
+static LOGGER: PythonLogger = PythonLogger;
+
+impl PythonLogger {
+    /// Register this logger as the `log` crate's logger.
+    pub fn install(max_level: LevelFilter) -> Result<(), SetLoggerError> {
+        log::set_logger(&LOGGER)?;
+        log::set_max_level(max_level);
+
+        Ok(())
+    }
+
+    fn emit(py: Python, record: &Record) -> PyResult<()> {
+        let logger = py.import("logging")?.call(
+            py,
+            "getLogger",
+            (python_logger_name(record.target()),),
+            None,
+        )?;
+
+        logger.call_method(
+            py,
+            "log",
+            (
+                python_level(record.level()),
+                "%s",
+                record.args().to_string(),
+            ),
+            None,
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Log for PythonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) || unsafe { pyffi::Py_IsInitialized() } == 0 {
+            return;
+        }
+
+        forward(|| {
+            let gil = Python::acquire_gil();
+            let py = gil.python();
+
+            if let Err(err) = Self::emit(py, record) {
+                err.print(py);
+            }
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// Python code defining a `logging.Handler` emitting records to Rust.
+///
+/// Executed with `emit` bound to a function accepting the level, logger name,
+/// formatted message, filename, and line number of a record.
+const HANDLER_SOURCE: &str = "import logging
+
+class RustLogHandler(logging.Handler):
+    def emit(self, record):
+        try:
+            message = self.format(record)
+        except Exception:
+            self.handleError(record)
+            return
+
+        emit(record.levelno, record.name, message, record.pathname, record.lineno)
+
+logging.getLogger().addHandler(RustLogHandler())
+";
+
+fn emit_rust_record(
+    py: Python,
+    level: i32,
+    name: String,
+    message: String,
+    pathname: String,
+    lineno: u32,
+) -> PyResult<PyObject> {
+    forward(|| {
+        log::logger().log(
+            &Record::builder()
+                .level(rust_level(level))
+                .target(&name)
+                .args(format_args!("{}", message))
+                .file(Some(&pathname))
+                .line(Some(lineno))
+                .build(),
+        );
+    });
+
+    Ok(py.None())
+}
+
+/// Install a handler forwarding Python log records to the `log` crate.
+pub(crate) fn install_python_handler(py: Python) -> PyResult<()> {
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__builtins__", py.import("builtins")?)?;
+    globals.set_item(
+        py,
+        "emit",
+        py_fn!(
+            py,
+            emit_rust_record(
+                level: i32,
+                name: String,
+                message: String,
+                pathname: String,
+                lineno: u32
+            )
+        ),
+    )?;
+
+    py.run(HANDLER_SOURCE, Some(&globals), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels() {
+        for level in &[
+            Level::Error,
+            Level::Warn,
+            Level::Info,
+            Level::Debug,
+            Level::Trace,
+        ] {
+            assert_eq!(rust_level(python_level(*level)), *level);
+        }
+
+        assert_eq!(rust_level(50), Level::Error);
+        assert_eq!(rust_level(0), Level::Trace);
+    }
+
+    #[test]
+    fn test_python_logger_name() {
+        assert_eq!(python_logger_name("myapp::db"), "myapp.db");
+    }
+}
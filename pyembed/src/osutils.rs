@@ -141,3 +141,37 @@ pub fn resolve_terminfo_dirs() -> Option<String> {
         OsVariant::Other => None,
     }
 }
+
+/// Set the file mode creation mask of the process.
+#[cfg(unix)]
+pub fn set_umask(mask: u32) {
+    unsafe {
+        libc::umask(mask as libc::mode_t);
+    }
+}
+
+/// Set the name of the process shown by tools like `ps` and `top`.
+///
+/// On Linux, this sets the name of the calling thread, which is the name of
+/// the process when called from the main thread. The kernel truncates it to
+/// 15 bytes. This is a no-op on other platforms.
+#[allow(unused_variables)]
+pub fn set_process_title(title: &str) -> Result<(), String> {
+    let title = std::ffi::CString::new(title)
+        .map_err(|_| format!("process title {:?} contains a NUL byte", title))?;
+
+    #[cfg(target_os = "linux")]
+    {
+        let res =
+            unsafe { libc::prctl(libc::PR_SET_NAME, title.as_ptr() as libc::c_ulong, 0, 0, 0) };
+
+        if res != 0 {
+            return Err(format!(
+                "unable to set process title: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+# Type stubs for the oxidized_importer extension module.
+#
+# These describe the Python API implemented in Rust by the pyembed crate.
+# Keep them in sync with importer.rs and friends.
+
+import importlib.abc
+import importlib.machinery
+import io
+import os
+import pathlib
+import types
+from typing import (
+    Any,
+    Dict,
+    Iterable,
+    Iterator,
+    List,
+    Optional,
+    Tuple,
+    Union,
+)
+
+_PathLike = Union[str, bytes, os.PathLike]
+
+__all__: List[str]
+
+class OxidizedFinder(importlib.abc.MetaPathFinder, importlib.abc.Loader):
+    def __init__(self, relative_path_origin: Optional[_PathLike] = ...) -> None: ...
+    def find_spec(
+        self,
+        fullname: str,
+        path: Optional[Iterable[str]],
+        target: Optional[types.ModuleType] = ...,
+    ) -> Optional[importlib.machinery.ModuleSpec]: ...
+    def find_module(
+        self, fullname: str, path: Optional[Iterable[str]]
+    ) -> Optional[OxidizedFinder]: ...
+    def invalidate_caches(self) -> None: ...
+    def create_module(
+        self, spec: importlib.machinery.ModuleSpec
+    ) -> Optional[types.ModuleType]: ...
+    def exec_module(self, module: types.ModuleType) -> None: ...
+    def get_data(self, path: str) -> bytes: ...
+    def get_code(self, fullname: str) -> Optional[types.CodeType]: ...
+    def get_source(self, fullname: str) -> Optional[str]: ...
+    def get_filename(self, fullname: str) -> str: ...
+    def get_resource_reader(
+        self, fullname: str
+    ) -> Optional[OxidizedResourceReader]: ...
+    def find_distributions(
+        self, context: Optional[Any] = ...
+    ) -> Iterator[OxidizedDistribution]: ...
+    def iter_modules(self, prefix: str = ...) -> List[Tuple[str, bool]]: ...
+    def index_bytes(self, data: bytes) -> None: ...
+    def index_file_memory_mapped(self, path: _PathLike) -> None: ...
+    def index_interpreter_builtins(self) -> None: ...
+    def index_interpreter_builtin_extension_modules(self) -> None: ...
+    def index_interpreter_frozen_modules(self) -> None: ...
+    def indexed_resources(self) -> List[OxidizedResource]: ...
+    def add_resource(self, resource: OxidizedResource) -> None: ...
+    def add_resources(self, resources: List[OxidizedResource]) -> None: ...
+    def serialize_indexed_resources(
+        self, ignore_builtin: bool = ..., ignore_frozen: bool = ...
+    ) -> bytes: ...
+    def read_packed_file(self, path: str) -> bytes: ...
+    def stats(self) -> Dict[str, int]: ...
+    def reset_stats(self) -> None: ...
+
+class OxidizedResourceReader(importlib.abc.ResourceReader):
+    def open_resource(self, resource: str) -> io.BytesIO: ...
+    def resource_path(self, resource: str) -> str: ...
+    def is_resource(self, name: str) -> bool: ...
+    def contents(self) -> List[str]: ...
+
+class OxidizedDistribution:
+    @classmethod
+    def from_name(cls, name: str) -> OxidizedDistribution: ...
+    @classmethod
+    def discover(cls, **kwargs: Any) -> Iterator[OxidizedDistribution]: ...
+    def read_text(self, filename: str) -> Optional[str]: ...
+    @property
+    def metadata(self) -> Any: ...
+    @property
+    def version(self) -> str: ...
+    @property
+    def entry_points(self) -> List[Any]: ...
+    @property
+    def files(self) -> Optional[List[Any]]: ...
+    @property
+    def requires(self) -> Optional[List[str]]: ...
+
+class OxidizedResource:
+    def __init__(self) -> None: ...
+    is_module: bool
+    is_builtin_extension_module: bool
+    is_frozen_module: bool
+    is_extension_module: bool
+    is_shared_library: bool
+    name: str
+    is_package: bool
+    is_namespace_package: bool
+    in_memory_source: Optional[bytes]
+    in_memory_bytecode: Optional[bytes]
+    in_memory_bytecode_opt1: Optional[bytes]
+    in_memory_bytecode_opt2: Optional[bytes]
+    in_memory_extension_module_shared_library: Optional[bytes]
+    in_memory_package_resources: Optional[Dict[str, bytes]]
+    in_memory_distribution_resources: Optional[Dict[str, bytes]]
+    in_memory_shared_library: Optional[bytes]
+    shared_library_dependency_names: Optional[List[str]]
+    relative_path_module_source: Optional[pathlib.Path]
+    relative_path_module_bytecode: Optional[pathlib.Path]
+    relative_path_module_bytecode_opt1: Optional[pathlib.Path]
+    relative_path_module_bytecode_opt2: Optional[pathlib.Path]
+    relative_path_extension_module_shared_library: Optional[pathlib.Path]
+    relative_path_package_resources: Optional[Dict[str, pathlib.Path]]
+    relative_path_distribution_resources: Optional[Dict[str, pathlib.Path]]
+
+_PythonResource = Union[
+    PythonModuleSource,
+    PythonModuleBytecode,
+    PythonPackageResource,
+    PythonPackageDistributionResource,
+    PythonExtensionModule,
+]
+
+class OxidizedResourceCollector:
+    def __init__(self, allowed_locations: List[str]) -> None: ...
+    @property
+    def allowed_locations(self) -> List[str]: ...
+    def add_in_memory(self, resource: _PythonResource) -> None: ...
+    def add_filesystem_relative(
+        self, prefix: str, resource: _PythonResource
+    ) -> None: ...
+    def oxidize(
+        self, python_exe: Optional[_PathLike] = ...
+    ) -> Tuple[List[OxidizedResource], List[Tuple[pathlib.Path, bytes, bool]]]: ...
+
+class PythonModuleSource:
+    module: str
+    source: bytes
+    is_package: bool
+
+class PythonModuleBytecode:
+    module: str
+    bytecode: bytes
+    optimize_level: int
+    is_package: bool
+
+class PythonPackageResource:
+    package: str
+    name: str
+    data: bytes
+
+class PythonPackageDistributionResource:
+    package: str
+    version: str
+    name: str
+    data: bytes
+
+class PythonExtensionModule:
+    @property
+    def name(self) -> str: ...
+
+def build_info() -> Dict[str, str]: ...
+def decode_source(io_module: types.ModuleType, source_bytes: bytes) -> str: ...
+def find_resources_in_path(path: _PathLike) -> List[_PythonResource]: ...
+def read_packed_file(path: str) -> bytes: ...
+def register_builtin_extension(name: str, init_ptr_capsule: Any) -> None: ...
@@ -0,0 +1,191 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Filesystem path normalization.
+
+Windows APIs reject paths longer than `MAX_PATH` (260) characters unless
+they use the extended-length `\\?\` prefix (or `\\?\UNC\` for UNC shares).
+Extended-length paths are passed to the filesystem verbatim, so they must
+be absolute, use `\` separators, and have no `.` or `..` components.
+
+The functions in this module convert between conventional and
+extended-length paths. They are no-ops on other platforms.
+*/
+
+use std::{borrow::Cow, path::Path};
+
+/// Paths at least this long need the extended-length form on Windows.
+#[cfg_attr(not(windows), allow(unused))]
+const MAX_PATH: usize = 260;
+
+/// Convert a conventional absolute Windows path to extended-length form.
+///
+/// Returns `None` if the path is relative or already uses a `\\?\` or
+/// `\\.\` prefix.
+#[cfg_attr(not(windows), allow(unused))]
+fn extended_length_path(path: &str) -> Option<String> {
+    let path = path.replace('/', "\\");
+
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return None;
+    }
+
+    let (prefix, root_len, rest) = if let Some(rest) = path.strip_prefix(r"\\") {
+        // `server\share` is the root of UNC paths.
+        (r"\\?\UNC\", 2, rest)
+    } else if path.len() >= 3 && path.as_bytes()[1] == b':' && path.as_bytes()[2] == b'\\' {
+        (r"\\?\", 1, path.as_str())
+    } else {
+        return None;
+    };
+
+    let mut parts = rest.split('\\');
+    let mut components = parts.by_ref().take(root_len).collect::<Vec<_>>();
+    if components.len() < root_len || components.iter().any(|c| c.is_empty()) {
+        return None;
+    }
+
+    for part in parts {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if components.len() > root_len {
+                    components.pop();
+                }
+            }
+            part => components.push(part),
+        }
+    }
+
+    // Drive roots need a trailing separator to remain absolute.
+    let mut result = format!("{}{}", prefix, components.join("\\"));
+    if components.len() == root_len && root_len == 1 {
+        result.push('\\');
+    }
+
+    Some(result)
+}
+
+/// Convert an extended-length Windows path to conventional form.
+///
+/// Returns `None` if the path doesn't use the `\\?\` prefix.
+#[cfg_attr(not(windows), allow(unused))]
+fn conventional_path(path: &str) -> Option<String> {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        Some(format!(r"\\{}", rest))
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        let bytes = rest.as_bytes();
+
+        // Other verbatim prefixes (e.g. volume GUIDs) have no conventional form.
+        if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+            Some(rest.to_string())
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+/// Obtain a path suitable for performing filesystem I/O.
+///
+/// On Windows, absolute paths too long for conventional Windows APIs are
+/// converted to extended-length form.
+#[cfg(windows)]
+pub fn io_path(path: &Path) -> Cow<'_, Path> {
+    match path.to_str() {
+        Some(s) if s.len() >= MAX_PATH => match extended_length_path(s) {
+            Some(extended) => Cow::Owned(extended.into()),
+            None => Cow::Borrowed(path),
+        },
+        _ => Cow::Borrowed(path),
+    }
+}
+
+/// Obtain a path suitable for performing filesystem I/O.
+#[cfg(not(windows))]
+pub fn io_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+/// Obtain the conventional form of a path.
+///
+/// On Windows, extended-length `\\?\` paths are converted to their conventional
+/// equivalent so they can be compared with paths that don't use the prefix.
+#[cfg(windows)]
+pub fn conventional(path: &Path) -> Cow<'_, Path> {
+    match path.to_str().and_then(conventional_path) {
+        Some(conventional) => Cow::Owned(conventional.into()),
+        None => Cow::Borrowed(path),
+    }
+}
+
+/// Obtain the conventional form of a path.
+#[cfg(not(windows))]
+pub fn conventional(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extended_length_path() {
+        assert_eq!(
+            extended_length_path(r"C:\Program Files\app\lib\foo.py"),
+            Some(r"\\?\C:\Program Files\app\lib\foo.py".to_string())
+        );
+        assert_eq!(
+            extended_length_path("C:/app/./lib/../prefix/foo.py"),
+            Some(r"\\?\C:\app\prefix\foo.py".to_string())
+        );
+        assert_eq!(
+            extended_length_path(r"C:\..\.."),
+            Some(r"\\?\C:\".to_string())
+        );
+        assert_eq!(
+            extended_length_path(r"\\server\share\app\..\lib\foo.py"),
+            Some(r"\\?\UNC\server\share\lib\foo.py".to_string())
+        );
+        assert_eq!(
+            extended_length_path(r"\\server\share\..\.."),
+            Some(r"\\?\UNC\server\share".to_string())
+        );
+        assert_eq!(extended_length_path(r"\\server"), None);
+        assert_eq!(extended_length_path(r"lib\foo.py"), None);
+        assert_eq!(extended_length_path(r"\lib\foo.py"), None);
+        assert_eq!(extended_length_path(r"\\?\C:\app"), None);
+        assert_eq!(extended_length_path(r"\\.\pipe\foo"), None);
+    }
+
+    #[test]
+    fn test_conventional_path() {
+        assert_eq!(
+            conventional_path(r"\\?\C:\app\lib"),
+            Some(r"C:\app\lib".to_string())
+        );
+        assert_eq!(
+            conventional_path(r"\\?\UNC\server\share\lib"),
+            Some(r"\\server\share\lib".to_string())
+        );
+        assert_eq!(
+            conventional_path(r"\\?\Volume{b75e2c83-0000-0000-0000-602f00000000}\app"),
+            None
+        );
+        assert_eq!(conventional_path(r"C:\app"), None);
+        assert_eq!(conventional_path("/usr/lib"), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for path in &[r"C:\app\lib\foo.py", r"\\server\share\app\foo.py"] {
+            assert_eq!(
+                conventional_path(&extended_length_path(path).unwrap()),
+                Some(path.to_string())
+            );
+        }
+    }
+}
@@ -8,13 +8,17 @@ Management of Python resources.
 
 use {
     crate::{
-        config::{PackedResourcesSource, ResolvedOxidizedPythonInterpreterConfig},
+        config::{
+            PackedResourcesSource, ResolvedOxidizedPythonInterpreterConfig, ResourcePayloadDecoder,
+        },
         conversion::{
             path_to_pathlib_path, path_to_pyobject, pyobject_optional_resources_map_to_owned_bytes,
             pyobject_optional_resources_map_to_pathbuf, pyobject_to_owned_bytes_optional,
             pyobject_to_pathbuf_optional,
         },
         error::NewInterpreterError,
+        importer::{OXIDIZED_IMPORTER_STUB, OXIDIZED_IMPORTER_STUB_NAME},
+        pathutils::{conventional, io_path},
     },
     anyhow::Result,
     cpython::{
@@ -23,18 +27,36 @@ use {
         py_class, NoArgs, ObjectProtocol, PyBytes, PyDict, PyErr, PyList, PyModule, PyObject,
         PyResult, PyString, PyTuple, Python, PythonObject, ToPyObject,
     },
+    once_cell::unsync::OnceCell,
     python3_sys as pyffi,
-    python_packed_resources::data::Resource,
+    python_packed_resources::{
+        data::{Resource, METADATA_PYTHON_VERSION},
+        parser::{load_resources_detailed, LoadError},
+    },
     std::{
         borrow::Cow,
         cell::RefCell,
-        collections::{hash_map::Entry, HashMap},
+        collections::{hash_map::Entry, BTreeSet, HashMap, HashSet},
         convert::TryFrom,
         ffi::CStr,
         path::{Path, PathBuf},
     },
 };
 
+/// The `X.Y` version of the Python interpreter we're linked against.
+fn running_python_version() -> String {
+    let version = unsafe { CStr::from_ptr(pyffi::Py_GetVersion()) }.to_string_lossy();
+
+    version
+        .split(|c: char| c != '.' && !c.is_ascii_digit())
+        .next()
+        .unwrap_or_default()
+        .splitn(3, '.')
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
 /// Python bytecode optimization level.
 #[derive(Clone, Copy, Debug)]
 pub(crate) enum OptimizeLevel {
@@ -65,6 +87,87 @@ where
         }
 }
 
+/// Expand `$NAME` and `${NAME}` references in a filesystem-relative path.
+///
+/// `ORIGIN` expands to `origin`. Other names expand to the value of the
+/// environment variable of that name, as returned by `getenv`. References to
+/// unset environment variables are left as-is so errors mention the
+/// unexpanded path.
+fn expand_path_variables(
+    path: &str,
+    origin: &Path,
+    getenv: impl Fn(&str) -> Option<String>,
+) -> String {
+    let mut result = String::with_capacity(path.len());
+    let mut remaining = path;
+
+    while let Some(pos) = remaining.find('$') {
+        result.push_str(&remaining[..pos]);
+        let after = &remaining[pos + 1..];
+
+        let (name, rest) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", after),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+
+        let reference = &remaining[pos..remaining.len() - rest.len()];
+
+        if name.is_empty() {
+            result.push('$');
+            remaining = after;
+            continue;
+        } else if name == "ORIGIN" {
+            result.push_str(&origin.display().to_string());
+        } else if let Some(value) = getenv(name) {
+            result.push_str(&value);
+        } else {
+            result.push_str(reference);
+        }
+
+        remaining = rest;
+    }
+
+    result.push_str(remaining);
+
+    result
+}
+
+/// Resolve the filesystem path of a filesystem-relative resource.
+///
+/// Paths recorded in resources data are normally relative to `origin`. They
+/// may also reference `$ORIGIN` (e.g. `$ORIGIN/../share/app`) and
+/// environment variables (e.g. `${APP_DATA}/lib`), which are expanded. Paths
+/// that are still relative after expansion are interpreted relative to
+/// `origin`.
+pub(crate) fn resolve_relative_path(origin: &Path, path: &Path) -> PathBuf {
+    resolve_relative_path_with_env(origin, path, |name| std::env::var(name).ok())
+}
+
+/// Resolve a filesystem-relative path, reading environment variables via `getenv`.
+fn resolve_relative_path_with_env(
+    origin: &Path,
+    path: &Path,
+    getenv: impl Fn(&str) -> Option<String>,
+) -> PathBuf {
+    let path = match path.to_str() {
+        Some(s) if s.contains('$') => PathBuf::from(expand_path_variables(s, origin, getenv)),
+        _ => path.to_path_buf(),
+    };
+
+    if path.is_absolute() {
+        path
+    } else {
+        origin.join(path)
+    }
+}
+
 /// Describes the type of an importable Python module.
 #[derive(Debug, PartialEq)]
 pub(crate) enum ModuleFlavor {
@@ -91,6 +194,9 @@ where
     /// Path from which relative paths should be interpreted.
     origin: &'a Path,
 
+    /// Function decoding in-memory source and bytecode, if they are encoded.
+    payload_decoder: Option<ResourcePayloadDecoder>,
+
     /// The type of importable module.
     pub flavor: ModuleFlavor,
     /// Whether this module is a package.
@@ -98,6 +204,23 @@ where
 }
 
 impl<'a> ImportablePythonModule<'a, u8> {
+    /// Obtain in-memory payload data, decoding it if necessary.
+    fn decode_payload<'d>(&self, py: Python, data: &'d [u8]) -> PyResult<Cow<'d, [u8]>> {
+        if let Some(decoder) = self.payload_decoder {
+            Ok(Cow::Owned(decoder(data.to_vec()).map_err(|e| {
+                PyErr::new::<ImportError, _>(
+                    py,
+                    (
+                        format!("error decoding resource payload: {}", e),
+                        self.resource.name.clone(),
+                    ),
+                )
+            })?))
+        } else {
+            Ok(Cow::Borrowed(data))
+        }
+    }
+
     /// Attempt to resolve a Python `bytes` for the source code behind this module.
     ///
     /// Will return a PyErr if an error occurs resolving source. If there is no source,
@@ -110,11 +233,11 @@ impl<'a> ImportablePythonModule<'a, u8> {
         io_module: &PyModule,
     ) -> PyResult<Option<PyObject>> {
         let bytes = if let Some(data) = &self.resource.in_memory_source {
-            Some(PyBytes::new(py, data))
+            Some(PyBytes::new(py, &self.decode_payload(py, data)?))
         } else if let Some(relative_path) = &self.resource.relative_path_module_source {
-            let path = self.origin.join(relative_path);
+            let path = resolve_relative_path(self.origin, relative_path);
 
-            let source = std::fs::read(&path).map_err(|e| {
+            let source = std::fs::read(io_path(&path)).map_err(|e| {
                 PyErr::new::<ImportError, _>(
                     py,
                     (
@@ -156,6 +279,12 @@ impl<'a> ImportablePythonModule<'a, u8> {
             OptimizeLevel::One => &self.resource.in_memory_bytecode_opt1,
             OptimizeLevel::Two => &self.resource.in_memory_bytecode_opt2,
         } {
+            if self.payload_decoder.is_some() {
+                return Ok(Some(
+                    PyBytes::new(py, &self.decode_payload(py, data)?).into_object(),
+                ));
+            }
+
             let ptr = unsafe {
                 pyffi::PyMemoryView_FromMemory(
                     data.as_ptr() as _,
@@ -168,7 +297,7 @@ impl<'a> ImportablePythonModule<'a, u8> {
         } else if let Some(path) = self.bytecode_path(optimize_level) {
             // TODO we could potentially avoid the double allocation for bytecode
             // by reading directly into a buffer transferred to Python.
-            let bytecode = std::fs::read(&path).map_err(|e| {
+            let bytecode = std::fs::read(io_path(&path)).map_err(|e| {
                 PyErr::new::<ImportError, _>(
                     py,
                     (
@@ -268,10 +397,16 @@ impl<'a> ImportablePythonModule<'a, u8> {
             // names `os.path.join()`d to the end. e.g.
             // `/path/to/myapp.zip/mypackage/subpackage`.
             let mut locations = if let Some(origin_path) = self.origin_path() {
-                if let Some(parent_path) = origin_path.parent() {
-                    vec![path_to_pyobject(py, parent_path)?]
-                } else {
-                    vec![]
+                match origin_path.parent() {
+                    // Sourceless packages have their origin in `__pycache__`.
+                    Some(parent_path) if parent_path.ends_with("__pycache__") => {
+                        match parent_path.parent() {
+                            Some(package_path) => vec![path_to_pyobject(py, package_path)?],
+                            None => vec![],
+                        }
+                    }
+                    Some(parent_path) => vec![path_to_pyobject(py, parent_path)?],
+                    None => vec![],
                 }
             } else {
                 vec![]
@@ -322,18 +457,24 @@ impl<'a> ImportablePythonModule<'a, u8> {
     }
 
     /// Obtain the filesystem path to this resource to be used for `ModuleSpec.origin`.
-    fn origin_path(&self) -> Option<PathBuf> {
+    pub fn origin_path(&self) -> Option<PathBuf> {
         match self.flavor {
             ModuleFlavor::SourceBytecode => {
                 if let Some(path) = &self.resource.relative_path_module_source {
-                    Some(self.origin.join(path))
+                    Some(resolve_relative_path(self.origin, path))
+                } else if self.resource.in_memory_source.is_none() {
+                    // Modules without source, like those from sourceless
+                    // distributions, are located by their bytecode.
+                    [OptimizeLevel::Zero, OptimizeLevel::One, OptimizeLevel::Two]
+                        .iter()
+                        .find_map(|level| self.bytecode_path(*level))
                 } else {
                     None
                 }
             }
             ModuleFlavor::Extension => {
                 if let Some(path) = &self.resource.relative_path_extension_module_shared_library {
-                    Some(self.origin.join(path))
+                    Some(resolve_relative_path(self.origin, path))
                 } else {
                     None
                 }
@@ -351,7 +492,7 @@ impl<'a> ImportablePythonModule<'a, u8> {
         };
 
         if let Some(bytecode_path) = bytecode_path {
-            Some(self.origin.join(bytecode_path))
+            Some(resolve_relative_path(self.origin, bytecode_path))
         } else {
             None
         }
@@ -379,6 +520,17 @@ where
     /// Named resources available for loading.
     pub resources: HashMap<Cow<'a, str>, Resource<'a, X>>,
 
+    /// Function decoding in-memory module payloads of `encoded_modules`.
+    payload_decoder: Option<ResourcePayloadDecoder>,
+
+    /// Names of modules whose in-memory payloads must be decoded.
+    encoded_modules: HashSet<String>,
+
+    /// Index of package names to the names of their direct child modules.
+    ///
+    /// Built on first use and discarded whenever resources are indexed.
+    package_index: OnceCell<HashMap<String, BTreeSet<String>>>,
+
     /// List of `PyObject` that back indexed data.
     ///
     /// Holding a reference to these prevents them from being gc'd and for
@@ -395,6 +547,9 @@ impl<'a> Default for PythonResourcesState<'a, u8> {
             current_exe: PathBuf::new(),
             origin: PathBuf::new(),
             resources: HashMap::new(),
+            payload_decoder: None,
+            encoded_modules: HashSet::new(),
+            package_index: OnceCell::new(),
             backing_py_objects: vec![],
             backing_mmaps: vec![],
         }
@@ -418,21 +573,34 @@ impl<'a, 'config: 'a> TryFrom<&ResolvedOxidizedPythonInterpreterConfig<'config>>
         for source in &config.packed_resources {
             match source {
                 PackedResourcesSource::Memory(data) => {
-                    state
-                        .index_data(data)
-                        .map_err(NewInterpreterError::Simple)?;
+                    state.index_data(data).map_err(|error| {
+                        NewInterpreterError::PackedResources { path: None, error }
+                    })?;
                 }
                 PackedResourcesSource::MemoryMappedPath(path) => {
-                    state
-                        .index_path_memory_mapped(path)
-                        .map_err(NewInterpreterError::Dynamic)?;
+                    state.index_path_memory_mapped(path)?;
+                }
+                PackedResourcesSource::OptionalMemoryMappedPath(path) => {
+                    if path.exists() {
+                        state.index_path_memory_mapped(path)?;
+                    }
                 }
             }
         }
 
-        state
-            .index_interpreter_builtins()
-            .map_err(NewInterpreterError::Simple)?;
+        // Only payloads from configured packed resources were transformed at
+        // packaging time.
+        if let Some(decoder) = config.resource_payload_decoder {
+            state.payload_decoder = Some(decoder);
+            state.encoded_modules = state
+                .resources
+                .values()
+                .filter(|r| r.is_module)
+                .map(|r| r.name.to_string())
+                .collect();
+        }
+
+        state.index_interpreter_builtins()?;
 
         Ok(state)
     }
@@ -440,11 +608,11 @@ impl<'a, 'config: 'a> TryFrom<&ResolvedOxidizedPythonInterpreterConfig<'config>>
 
 impl<'a> PythonResourcesState<'a, u8> {
     /// Construct an instance from environment state.
-    pub fn new_from_env() -> Result<Self, &'static str> {
-        let exe = std::env::current_exe().map_err(|_| "unable to obtain current executable")?;
+    pub fn new_from_env() -> Result<Self, NewInterpreterError> {
+        let exe = std::env::current_exe().map_err(NewInterpreterError::CurrentExe)?;
         let origin = exe
             .parent()
-            .ok_or("unable to get executable parent")?
+            .ok_or_else(|| NewInterpreterError::Origin(exe.clone()))?
             .to_path_buf();
 
         Ok(Self {
@@ -460,19 +628,38 @@ impl<'a> PythonResourcesState<'a, u8> {
     /// on the incoming entry will overwrite fields on the existing entry.
     ///
     /// If an entry doesn't exist, the resource will be inserted as-is.
-    pub fn index_data(&mut self, data: &'a [u8]) -> Result<(), &'static str> {
-        let resources = python_packed_resources::parser::load_resources(data)?;
+    ///
+    /// Data recording the Python version it was built for is refused if that
+    /// isn't the version of the running interpreter.
+    pub fn index_data(&mut self, data: &'a [u8]) -> Result<(), LoadError> {
+        let mut resources = load_resources_detailed(data)?;
+
+        if let Some(built) = resources.metadata().get(METADATA_PYTHON_VERSION) {
+            let running = running_python_version();
+
+            if *built != running {
+                return Err(LoadError::PythonVersionMismatch {
+                    built: built.to_string(),
+                    running,
+                });
+            }
+        }
+
+        self.package_index = OnceCell::new();
 
         // Reserve space for expected number of incoming items so we can avoid extra
         // allocations.
         self.resources.reserve(resources.expected_resources_count());
 
-        for resource in resources {
-            let resource = resource?;
+        while let Some(resource) = resources.next() {
+            let resource = resource.map_err(|message| resources.error(message))?;
 
             match self.resources.entry(resource.name.clone()) {
                 Entry::Occupied(existing) => {
-                    existing.into_mut().merge_from(resource)?;
+                    existing
+                        .into_mut()
+                        .merge_from(resource)
+                        .map_err(|message| resources.error(message))?;
                 }
                 Entry::Vacant(vacant) => {
                     vacant.insert(resource);
@@ -484,15 +671,27 @@ impl<'a> PythonResourcesState<'a, u8> {
     }
 
     /// Load resources data from a filesystem path using memory mapped I/O.
-    pub fn index_path_memory_mapped(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
+    pub fn index_path_memory_mapped(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<(), NewInterpreterError> {
         let path = path.as_ref();
-        let f = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        let io_error = |e: std::io::Error| NewInterpreterError::Resources {
+            path: Some(path.to_path_buf()),
+            message: e.to_string(),
+        };
 
-        let mapped = Box::new(unsafe { memmap::Mmap::map(&f) }.map_err(|e| e.to_string())?);
+        let f = std::fs::File::open(io_path(path)).map_err(io_error)?;
+
+        let mapped = Box::new(unsafe { memmap::Mmap::map(&f) }.map_err(io_error)?);
 
         let data = unsafe { std::slice::from_raw_parts::<u8>(mapped.as_ptr(), mapped.len()) };
 
-        self.index_data(data)?;
+        self.index_data(data)
+            .map_err(|error| NewInterpreterError::PackedResources {
+                path: Some(path.to_path_buf()),
+                error,
+            })?;
         self.backing_mmaps.push(mapped);
 
         Ok(())
@@ -509,14 +708,18 @@ impl<'a> PythonResourcesState<'a, u8> {
         };
 
         self.index_data(data)
-            .map_err(|msg| PyErr::new::<ValueError, _>(py, msg))?;
+            .map_err(|e| PyErr::new::<ValueError, _>(py, e.to_string()))?;
         self.backing_py_objects.push(obj);
 
         Ok(())
     }
 
     /// Load `builtin` modules from the Python interpreter.
-    pub fn index_interpreter_builtin_extension_modules(&mut self) -> Result<(), &'static str> {
+    pub fn index_interpreter_builtin_extension_modules(
+        &mut self,
+    ) -> Result<(), NewInterpreterError> {
+        self.package_index = OnceCell::new();
+
         for i in 0.. {
             let record = unsafe { pyffi::PyImport_Inittab.offset(i) };
 
@@ -528,27 +731,42 @@ impl<'a> PythonResourcesState<'a, u8> {
             let name_str = match name.to_str() {
                 Ok(v) => v,
                 Err(_) => {
-                    return Err("unable to parse PyImport_Inittab");
+                    return Err(NewInterpreterError::StringConversion {
+                        context: "indexing PyImport_Inittab".to_string(),
+                        value: name.to_string_lossy().to_string(),
+                    });
                 }
             };
 
-            self.resources
-                .entry(name_str.into())
-                .and_modify(|r| {
-                    r.is_builtin_extension_module = true;
-                })
-                .or_insert_with(|| Resource {
-                    is_builtin_extension_module: true,
-                    name: Cow::Owned(name_str.to_string()),
-                    ..Resource::default()
-                });
+            self.index_builtin_extension_module(name_str);
         }
 
         Ok(())
     }
 
+    /// Mark a named module as a `builtin` extension module.
+    ///
+    /// This is used to index extension modules registered in
+    /// `PyImport_Inittab` after the interpreter was initialized.
+    pub fn index_builtin_extension_module(&mut self, name: &str) {
+        self.package_index = OnceCell::new();
+
+        self.resources
+            .entry(name.to_string().into())
+            .and_modify(|r| {
+                r.is_builtin_extension_module = true;
+            })
+            .or_insert_with(|| Resource {
+                is_builtin_extension_module: true,
+                name: Cow::Owned(name.to_string()),
+                ..Resource::default()
+            });
+    }
+
     /// Load `frozen` modules from the Python interpreter.
-    pub fn index_interpreter_frozen_modules(&mut self) -> Result<(), &'static str> {
+    pub fn index_interpreter_frozen_modules(&mut self) -> Result<(), NewInterpreterError> {
+        self.package_index = OnceCell::new();
+
         for i in 0.. {
             let record = unsafe { pyffi::PyImport_FrozenModules.offset(i) };
 
@@ -560,7 +778,10 @@ impl<'a> PythonResourcesState<'a, u8> {
             let name_str = match name.to_str() {
                 Ok(v) => v,
                 Err(_) => {
-                    return Err("unable to parse PyImport_FrozenModules");
+                    return Err(NewInterpreterError::StringConversion {
+                        context: "indexing PyImport_FrozenModules".to_string(),
+                        value: name.to_string_lossy().to_string(),
+                    });
                 }
             };
 
@@ -584,7 +805,7 @@ impl<'a> PythonResourcesState<'a, u8> {
     /// If this instance's resources are being used by the sole Python importer,
     /// this needs to be called to ensure modules required during interpreter
     /// initialization are indexed and loadable by our importer.
-    pub fn index_interpreter_builtins(&mut self) -> Result<(), &'static str> {
+    pub fn index_interpreter_builtins(&mut self) -> Result<(), NewInterpreterError> {
         self.index_interpreter_builtin_extension_modules()?;
         self.index_interpreter_frozen_modules()?;
 
@@ -598,12 +819,67 @@ impl<'a> PythonResourcesState<'a, u8> {
     pub fn add_resource<'resource: 'a>(
         &mut self,
         resource: Resource<'resource, u8>,
-    ) -> Result<(), &'static str> {
+    ) -> Result<(), NewInterpreterError> {
+        self.package_index = OnceCell::new();
+        self.encoded_modules.remove(resource.name.as_ref());
         self.resources.insert(resource.name.clone(), resource);
 
         Ok(())
     }
 
+    /// Obtain the index of package names to the names of their direct child modules.
+    ///
+    /// Only resources that are modules are indexed. Top-level modules are
+    /// indexed under the empty string.
+    pub fn package_index(&self) -> &HashMap<String, BTreeSet<String>> {
+        self.package_index.get_or_init(|| {
+            let mut index: HashMap<String, BTreeSet<String>> = HashMap::new();
+
+            for resource in self.resources.values().filter(|r| {
+                r.is_module
+                    || r.is_builtin_extension_module
+                    || r.is_frozen_module
+                    || r.is_extension_module
+            }) {
+                let (parent, child) = match resource.name.rfind('.') {
+                    Some(idx) => (&resource.name[0..idx], &resource.name[idx + 1..]),
+                    None => ("", resource.name.as_ref()),
+                };
+
+                index
+                    .entry(parent.to_string())
+                    .or_default()
+                    .insert(child.to_string());
+
+                if resource.is_package {
+                    index.entry(resource.name.to_string()).or_default();
+                }
+            }
+
+            index
+        })
+    }
+
+    /// Whether a module name is definitively not provided by this instance.
+    ///
+    /// This is the case when the module's parent package is indexed but the
+    /// module isn't one of its children. Lookups for such names can be
+    /// answered without resolving the module against our resources.
+    pub fn is_negative_lookup(&self, name: &str) -> bool {
+        // `foo.__init__` is an alias for `foo`. See `resolve_importable_module()`.
+        if name.ends_with(".__init__") {
+            return false;
+        }
+
+        match name.rfind('.') {
+            Some(idx) => match self.package_index().get(&name[0..idx]) {
+                Some(children) => !children.contains(&name[idx + 1..]),
+                None => false,
+            },
+            None => false,
+        }
+    }
+
     /// Attempt to resolve an importable Python module.
     pub fn resolve_importable_module(
         &self,
@@ -671,6 +947,7 @@ impl<'a> PythonResourcesState<'a, u8> {
                 resource,
                 current_exe: &self.current_exe,
                 origin: &self.origin,
+                payload_decoder: None,
                 flavor: ModuleFlavor::Builtin,
                 is_package: resource.is_package,
             })
@@ -679,6 +956,7 @@ impl<'a> PythonResourcesState<'a, u8> {
                 resource,
                 current_exe: &self.current_exe,
                 origin: &self.origin,
+                payload_decoder: None,
                 flavor: ModuleFlavor::Frozen,
                 is_package: resource.is_package,
             })
@@ -687,6 +965,7 @@ impl<'a> PythonResourcesState<'a, u8> {
                 resource,
                 current_exe: &self.current_exe,
                 origin: &self.origin,
+                payload_decoder: None,
                 flavor: ModuleFlavor::Extension,
                 is_package: resource.is_package,
             })
@@ -696,6 +975,11 @@ impl<'a> PythonResourcesState<'a, u8> {
                     resource,
                     current_exe: &self.current_exe,
                     origin: &self.origin,
+                    payload_decoder: if self.encoded_modules.contains(resource.name.as_ref()) {
+                        self.payload_decoder
+                    } else {
+                        None
+                    },
                     flavor: ModuleFlavor::SourceBytecode,
                     is_package: resource.is_package,
                 })
@@ -735,13 +1019,13 @@ impl<'a> PythonResourcesState<'a, u8> {
 
         if let Some(resources) = &entry.relative_path_package_resources {
             if let Some(path) = resources.get(resource_name) {
-                let path = self.origin.join(path);
+                let path = resolve_relative_path(&self.origin, path);
                 let io_module = py.import("io")?;
 
                 return Ok(Some(io_module.call(
                     py,
                     "FileIO",
-                    (path_to_pyobject(py, &path)?, "r"),
+                    (path_to_pyobject(py, &io_path(&path))?, "r"),
                     None,
                 )?));
             }
@@ -750,6 +1034,40 @@ impl<'a> PythonResourcesState<'a, u8> {
         Ok(None)
     }
 
+    /// Resolve the content of a file resource given its virtual path.
+    ///
+    /// File resources are non-Python files added to the resources data and are
+    /// addressed by their relative path, using `/` as the directory separator.
+    ///
+    /// Type stubs for the `oxidized_importer` module are always available as
+    /// `oxidized_importer.pyi` unless a file resource of that name exists. They
+    /// aren't indexed as a resource so they don't end up in serialized data.
+    ///
+    /// `Ok(None)` is returned if no file resource exists with the given path.
+    /// Err occurs if reading the data for a file stored outside the resources
+    /// data fails.
+    pub fn resolve_file_data(&self, path: &str) -> Result<Option<Cow<[u8]>>, std::io::Error> {
+        let path = path.replace('\\', "/");
+
+        let entry = match self.resources.get(path.as_str()) {
+            Some(entry) if entry.is_utf8_filename_data => entry,
+            None if path == OXIDIZED_IMPORTER_STUB_NAME => {
+                return Ok(Some(Cow::Borrowed(OXIDIZED_IMPORTER_STUB)))
+            }
+            _ => return Ok(None),
+        };
+
+        if let Some(data) = &entry.file_data_embedded {
+            Ok(Some(Cow::Borrowed(data.as_ref())))
+        } else if let Some(relative_path) = &entry.file_data_utf8_relative_path {
+            let path = resolve_relative_path(&self.origin, Path::new(&**relative_path));
+
+            Ok(Some(Cow::Owned(std::fs::read(io_path(&path))?)))
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Determines whether a specific package + name pair is a known Python package resource.
     pub fn is_package_resource(&self, package: &str, resource_name: &str) -> bool {
         if let Some(entry) = self.resources.get(package) {
@@ -842,12 +1160,18 @@ impl<'a> PythonResourcesState<'a, u8> {
         // this functionality some day. But it should likely never be the default
         // because it goes against the spirit of requiring all resources to be
         // known ahead-of-time.
+        //
+        // Extended-length (`\\?\`) paths are compared in their conventional
+        // form so either spelling of a path is recognized.
         let native_path = PathBuf::from(path.to_string_lossy(py).to_string());
+        let native_path = conventional(&native_path);
+        let current_exe = conventional(&self.current_exe);
+        let origin = conventional(&self.origin);
 
         let (relative_path, check_in_memory, check_relative_path) =
-            if let Ok(relative_path) = native_path.strip_prefix(&self.current_exe) {
+            if let Ok(relative_path) = native_path.strip_prefix(current_exe.as_ref()) {
                 (relative_path, true, false)
-            } else if let Ok(relative_path) = native_path.strip_prefix(&self.origin) {
+            } else if let Ok(relative_path) = native_path.strip_prefix(origin.as_ref()) {
                 (relative_path, false, true)
             } else {
                 return Err(PyErr::new::<OSError, _>(
@@ -912,14 +1236,15 @@ impl<'a> PythonResourcesState<'a, u8> {
                 if check_relative_path {
                     if let Some(resources) = &entry.relative_path_package_resources {
                         if let Some(resource_relative_path) = resources.get(resource_name_ref) {
-                            let resource_path = self.origin.join(resource_relative_path);
+                            let resource_path =
+                                resolve_relative_path(&self.origin, resource_relative_path);
 
                             let io_module = py.import("io")?;
 
                             let fh = io_module.call(
                                 py,
                                 "FileIO",
-                                (path_to_pyobject(py, &resource_path)?, "r"),
+                                (path_to_pyobject(py, &io_path(&resource_path))?, "r"),
                                 None,
                             )?;
 
@@ -1004,7 +1329,13 @@ impl<'a> PythonResourcesState<'a, u8> {
 
         let mut buffer = Vec::new();
 
-        python_packed_resources::writer::write_packed_resources_v3(&resources, &mut buffer, None)?;
+        python_packed_resources::writer::write_packed_resources_v4(
+            &resources,
+            &mut buffer,
+            None,
+            0,
+            &[],
+        )?;
 
         Ok(buffer)
     }
@@ -1476,6 +1807,12 @@ mod tests {
 
         assert!(resources.resources.contains_key("foo".into()));
         assert!(resources.resources.contains_key("bar".into()));
+        assert!(resources
+            .resolve_file_data(OXIDIZED_IMPORTER_STUB_NAME)?
+            .is_some());
+        assert!(!resources
+            .resources
+            .contains_key(OXIDIZED_IMPORTER_STUB_NAME.into()));
 
         Ok(())
     }
@@ -1531,6 +1868,161 @@ mod tests {
         let resources = PythonResourcesState::try_from(&resolved)?;
         assert!(resources.resources.contains_key("foo".into()));
 
+        // Optional paths are ignored if they don't exist.
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config
+            .packed_resources
+            .push(PackedResourcesSource::OptionalMemoryMappedPath(
+                resources_dir.join("does-not-exist"),
+            ));
+        config
+            .packed_resources
+            .push(PackedResourcesSource::OptionalMemoryMappedPath(
+                resources_path,
+            ));
+
+        let resolved = config.resolve()?;
+        let resources = PythonResourcesState::try_from(&resolved)?;
+        assert!(resources.resources.contains_key("foo".into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_index() -> Result<()> {
+        let mut state = PythonResourcesState::default();
+
+        state
+            .add_resource(Resource {
+                name: "foo".into(),
+                is_module: true,
+                is_package: true,
+                ..Default::default()
+            })
+            .unwrap();
+        state
+            .add_resource(Resource {
+                name: "foo.bar".into(),
+                is_module: true,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(
+            state.package_index().get("foo"),
+            Some(&vec!["bar".to_string()].into_iter().collect())
+        );
+        assert_eq!(
+            state.package_index().get(""),
+            Some(&vec!["foo".to_string()].into_iter().collect())
+        );
+        assert!(!state.is_negative_lookup("foo.bar"));
+        assert!(state.is_negative_lookup("foo.baz"));
+        assert!(!state.is_negative_lookup("foo.__init__"));
+        assert!(!state.is_negative_lookup("other.baz"));
+        assert!(!state.is_negative_lookup("other"));
+
+        // Adding a resource invalidates the index.
+        state
+            .add_resource(Resource {
+                name: "foo.baz".into(),
+                is_module: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(!state.is_negative_lookup("foo.baz"));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_resolve_relative_path() {
+        let origin = PathBuf::from("/app/bin");
+        let getenv = |name: &str| {
+            if name == "PYEMBED_TEST_RELATIVE_PATH" {
+                Some("/data".to_string())
+            } else {
+                None
+            }
+        };
+
+        for (path, expected) in &[
+            ("lib/foo.py", "/app/bin/lib/foo.py"),
+            ("../share/foo.py", "/app/bin/../share/foo.py"),
+            ("$ORIGIN/lib/foo.py", "/app/bin/lib/foo.py"),
+            ("$ORIGIN/../share/foo.py", "/app/bin/../share/foo.py"),
+            ("${ORIGIN}/lib/foo.py", "/app/bin/lib/foo.py"),
+            ("${PYEMBED_TEST_RELATIVE_PATH}/foo.py", "/data/foo.py"),
+            ("$PYEMBED_TEST_RELATIVE_PATH/foo.py", "/data/foo.py"),
+            (
+                "$PYEMBED_TEST_RELATIVE_PATH_UNSET/foo.py",
+                "/app/bin/$PYEMBED_TEST_RELATIVE_PATH_UNSET/foo.py",
+            ),
+            ("lib/$/foo.py", "/app/bin/lib/$/foo.py"),
+            ("lib/${foo.py", "/app/bin/lib/${foo.py"),
+        ] {
+            assert_eq!(
+                resolve_relative_path_with_env(&origin, Path::new(path), getenv),
+                PathBuf::from(expected),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_file_data() -> Result<()> {
+        let temp_dir = tempfile::Builder::new().prefix("pyembed-test").tempdir()?;
+
+        let resources_dir = temp_dir.path().join("resources");
+        std::fs::create_dir(&resources_dir)?;
+        std::fs::write(resources_dir.join("test_resolve_file_data.txt"), b"on disk")?;
+
+        let mut state = PythonResourcesState {
+            origin: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        state
+            .add_resource(Resource {
+                name: "assets/in-memory.txt".into(),
+                is_utf8_filename_data: true,
+                file_data_embedded: Some(b"in memory".to_vec().into()),
+                ..Default::default()
+            })
+            .unwrap();
+        state
+            .add_resource(Resource {
+                name: "assets/on-disk.txt".into(),
+                is_utf8_filename_data: true,
+                file_data_utf8_relative_path: Some("resources/test_resolve_file_data.txt".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        state
+            .add_resource(Resource {
+                name: "foo".into(),
+                is_module: true,
+                in_memory_source: Some(vec![42].into()),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(
+            state.resolve_file_data("assets/in-memory.txt")?,
+            Some(Cow::Borrowed(b"in memory".as_ref()))
+        );
+        assert_eq!(
+            state.resolve_file_data("assets\\in-memory.txt")?,
+            Some(Cow::Borrowed(b"in memory".as_ref()))
+        );
+        assert_eq!(
+            state.resolve_file_data("assets/on-disk.txt")?,
+            Some(Cow::Owned(b"on disk".to_vec()))
+        );
+        assert_eq!(state.resolve_file_data("assets/missing.txt")?, None);
+        assert_eq!(state.resolve_file_data("foo")?, None);
+
         Ok(())
     }
 }
@@ -0,0 +1,543 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Import modules missing from the binary from a remote resource server.
+
+This functionality is experimental.
+
+A resource index lists the modules available from the server. Indices are
+signed with an Ed25519 key and the public key is built into the application,
+so only code published by the holder of the private key is imported. Module
+sources are verified against the SHA-256 recorded in the index and cached in
+a local directory under that digest. Cached sources are verified each time
+they are loaded.
+
+Indices are UTF-8 text. The first line is [INDEX_HEADER]. Each following
+line is one of:
+
+* `module <name> <sha256> <url>` - the source of a module.
+* `package <name> <sha256> <url>` - the source of a package's `__init__`.
+
+Empty lines and lines starting with `#` are ignored. URLs not containing
+`://` are relative to the URL of the index.
+
+A finder appended to `sys.meta_path` consults the index when no other finder
+found a module in one of the configured packages. Other modules are never
+looked up. The index is fetched with `urllib.request` the first time that
+happens. The result, including failure to fetch it, is then reused for the
+life of the process. While the index is unavailable, the finder finds
+nothing, so imports fail with `ModuleNotFoundError` as they would without it.
+*/
+
+use {
+    crate::{
+        self_update::{parse_sha256, resolve_url, sha256_hex, verify_signature, Fetch},
+        self_update_module::fetch_url,
+    },
+    cpython::{exc::ImportError, py_fn, PyBytes, PyDict, PyErr, PyResult, Python},
+    once_cell::sync::Lazy,
+    python_packaging::interpreter::RemoteResourcesConfig,
+    std::{
+        cell::Cell,
+        collections::BTreeMap,
+        path::Path,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// First line of resource indices.
+pub const INDEX_HEADER: &str = "pyoxidizer-remote-resources 1";
+
+/// A module available from a resource server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemoteModule {
+    /// Whether the module is a package.
+    pub is_package: bool,
+    /// SHA-256 of the module source.
+    pub sha256: String,
+    /// URL of the module source.
+    pub url: String,
+}
+
+/// A parsed resource index.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RemoteResourcesIndex {
+    /// Modules available from the server, by name.
+    pub modules: BTreeMap<String, RemoteModule>,
+}
+
+impl RemoteResourcesIndex {
+    /// Parse an index.
+    ///
+    /// URLs are resolved relative to `index_url`.
+    pub fn parse(data: &[u8], index_url: &str) -> Result<Self, String> {
+        let text = std::str::from_utf8(data)
+            .map_err(|_| "resource index is not valid UTF-8".to_string())?;
+
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        match lines.next() {
+            Some((_, line)) if line == INDEX_HEADER => {}
+            _ => return Err("unrecognized resource index format".to_string()),
+        }
+
+        let mut modules = BTreeMap::new();
+
+        for (number, line) in lines {
+            let error = |message: String| format!("resource index line {}: {}", number, message);
+
+            let (is_package, name, sha256, url) =
+                match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    ["module", name, sha256, url] => (false, *name, *sha256, *url),
+                    ["package", name, sha256, url] => (true, *name, *sha256, *url),
+                    _ => return Err(error("unrecognized line".to_string())),
+                };
+
+            if name.split('.').any(|part| part.is_empty()) {
+                return Err(error(format!("{} is not a valid module name", name)));
+            }
+
+            let module = RemoteModule {
+                is_package,
+                sha256: parse_sha256(sha256).map_err(error)?,
+                url: resolve_url(index_url, url),
+            };
+
+            if modules.insert(name.to_string(), module).is_some() {
+                return Err(error(format!("duplicate module {}", name)));
+            }
+        }
+
+        Ok(Self { modules })
+    }
+
+    /// Fetch and verify the resource index.
+    pub fn fetch(config: &RemoteResourcesConfig, fetch: &mut Fetch) -> Result<Self, String> {
+        let data = fetch(&config.index_url)?;
+        let signature = fetch(&format!("{}.sig", config.index_url))?;
+
+        verify_signature(&data, &signature, &config.public_key)
+            .map_err(|e| format!("error verifying resource index: {}", e))?;
+
+        Self::parse(&data, &config.index_url)
+    }
+}
+
+/// Whether a module is in one of the packages served by the resource server.
+fn in_remote_packages(packages: &[String], name: &str) -> bool {
+    packages.iter().any(|package| {
+        name == package
+            || (name.starts_with(package.as_str()) && name[package.len()..].starts_with('.'))
+    })
+}
+
+/// Obtain the source of a module, fetching it if it isn't cached.
+///
+/// Fetched sources are written to `cache_directory`. Failing to write them
+/// isn't an error: they are fetched again next time.
+pub fn load_module_source(
+    module: &RemoteModule,
+    cache_directory: &Path,
+    fetch: &mut Fetch,
+) -> Result<Vec<u8>, String> {
+    let path = cache_directory.join(&module.sha256);
+
+    if let Ok(data) = std::fs::read(&path) {
+        if sha256_hex(&data) == module.sha256 {
+            return Ok(data);
+        }
+    }
+
+    let data = fetch(&module.url)?;
+
+    if sha256_hex(&data) != module.sha256 {
+        return Err(format!(
+            "content of {} doesn't match the resource index",
+            module.url
+        ));
+    }
+
+    // Other processes may read the cache concurrently. So write to a file
+    // unique to this process and rename it into place.
+    let temp_path = cache_directory.join(format!("{}.{}.tmp", module.sha256, std::process::id()));
+    let written = std::fs::create_dir_all(cache_directory)
+        .and_then(|_| std::fs::write(&temp_path, &data))
+        .and_then(|_| std::fs::rename(&temp_path, &path));
+
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+
+    Ok(data)
+}
+
+/// The remote resources configuration of the main interpreter.
+static CONFIG: Lazy<Mutex<Option<RemoteResourcesConfig>>> = Lazy::new(|| Mutex::new(None));
+
+/// The resource index, once fetching it was attempted.
+static INDEX: Lazy<Mutex<Option<Result<Arc<RemoteResourcesIndex>, String>>>> =
+    Lazy::new(|| Mutex::new(None));
+
+thread_local! {
+    /// Whether this thread is fetching from the resource server.
+    ///
+    /// `urllib.request` imports modules while fetching. Those must not be
+    /// looked up on the server.
+    static FETCHING: Cell<bool> = Cell::new(false);
+}
+
+/// Marks the current thread as fetching until dropped.
+struct FetchingGuard;
+
+impl FetchingGuard {
+    fn new() -> Self {
+        FETCHING.with(|fetching| fetching.set(true));
+
+        Self
+    }
+}
+
+impl Drop for FetchingGuard {
+    fn drop(&mut self) {
+        FETCHING.with(|fetching| fetching.set(false));
+    }
+}
+
+fn import_error(py: Python, message: String) -> PyErr {
+    PyErr::new::<ImportError, _>(py, message)
+}
+
+/// Obtain the resource index, fetching it on first use.
+///
+/// Returns `None` if the index couldn't be fetched or verified.
+fn index(config: &RemoteResourcesConfig, fetch: &mut Fetch) -> Option<Arc<RemoteResourcesIndex>> {
+    // The lock isn't held while fetching, which runs Python code that could
+    // release the GIL and let another thread wait for the lock while
+    // holding it. Concurrent first uses may fetch the index more than once.
+    let cached = INDEX.lock().unwrap().clone();
+
+    let index = match cached {
+        Some(index) => index,
+        None => {
+            let index = RemoteResourcesIndex::fetch(config, fetch).map(Arc::new);
+            *INDEX.lock().unwrap() = Some(index.clone());
+
+            index
+        }
+    };
+
+    index.ok()
+}
+
+/// Find the source of a module on the resource server.
+///
+/// Returns `(source, is_package)` or `None` if the server doesn't have the
+/// module.
+fn find_source(py: Python, name: String) -> PyResult<Option<(PyBytes, bool)>> {
+    if FETCHING.with(|fetching| fetching.get()) {
+        return Ok(None);
+    }
+
+    let config = match CONFIG.lock().unwrap().clone() {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+
+    // Missing modules are routinely probed for, e.g. optional dependencies.
+    // Only modules the server is configured to provide are looked up.
+    if !in_remote_packages(&config.packages, &name) {
+        return Ok(None);
+    }
+
+    let _guard = FetchingGuard::new();
+
+    let urllib = py.import("urllib.request")?;
+    let mut fetch = |url: &str| fetch_url(py, &urllib, url);
+
+    // Without an index, behave as if the server doesn't have the module.
+    let index = match index(&config, &mut fetch) {
+        Some(index) => index,
+        None => return Ok(None),
+    };
+
+    let module = match index.modules.get(&name) {
+        Some(module) => module,
+        None => return Ok(None),
+    };
+
+    let source = load_module_source(module, &config.cache_directory, &mut fetch)
+        .map_err(|e| import_error(py, format!("error loading {}: {}", name, e)))?;
+
+    Ok(Some((PyBytes::new(py, &source), module.is_package)))
+}
+
+/// Python code defining and registering the finder.
+///
+/// Executed with `find_source` bound to [find_source].
+const FINDER_SOURCE: &str = r#"import importlib.abc
+import importlib.util
+import sys
+
+
+class RemoteResourcesLoader(importlib.abc.InspectLoader):
+    def __init__(self, source, is_package):
+        self._source = source
+        self._is_package = is_package
+
+    def is_package(self, fullname):
+        return self._is_package
+
+    def get_source(self, fullname):
+        return importlib.util.decode_source(self._source)
+
+    def get_code(self, fullname):
+        return compile(self._source, "<remote %s>" % fullname, "exec", dont_inherit=True)
+
+
+class RemoteResourcesFinder(importlib.abc.MetaPathFinder):
+    def find_spec(self, fullname, path=None, target=None):
+        found = find_source(fullname)
+        if found is None:
+            return None
+
+        source, is_package = found
+
+        return importlib.util.spec_from_loader(
+            fullname,
+            RemoteResourcesLoader(source, is_package),
+            origin="remote",
+            is_package=is_package,
+        )
+
+
+sys.meta_path.append(RemoteResourcesFinder())
+"#;
+
+/// Append a finder importing modules from the resource server to `sys.meta_path`.
+///
+/// The cache directory of `config` must have been resolved.
+pub(crate) fn install_remote_resources_finder(
+    py: Python,
+    config: RemoteResourcesConfig,
+) -> PyResult<()> {
+    *CONFIG.lock().unwrap() = Some(config);
+
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__builtins__", py.import("builtins")?)?;
+    globals.set_item(py, "find_source", py_fn!(py, find_source(name: String)))?;
+
+    py.run(FINDER_SOURCE, Some(&globals), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer},
+        std::{cell::RefCell, collections::HashMap},
+    };
+
+    const INDEX_URL: &str = "https://example.com/resources/index";
+
+    fn keypair() -> Keypair {
+        let secret = SecretKey::from_bytes(&[7; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+
+        Keypair { secret, public }
+    }
+
+    fn config(cache_directory: &Path) -> RemoteResourcesConfig {
+        RemoteResourcesConfig {
+            index_url: INDEX_URL.to_string(),
+            public_key: keypair()
+                .public
+                .as_bytes()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect(),
+            packages: vec!["foo".to_string()],
+            cache_directory: cache_directory.to_path_buf(),
+        }
+    }
+
+    #[test]
+    fn test_in_remote_packages() {
+        let packages = vec!["foo".to_string(), "bar.baz".to_string()];
+
+        assert!(in_remote_packages(&packages, "foo"));
+        assert!(in_remote_packages(&packages, "foo.bar"));
+        assert!(in_remote_packages(&packages, "bar.baz"));
+        assert!(in_remote_packages(&packages, "bar.baz.qux"));
+        assert!(!in_remote_packages(&packages, "foobar"));
+        assert!(!in_remote_packages(&packages, "bar"));
+        assert!(!in_remote_packages(&packages, "bar.other"));
+        assert!(!in_remote_packages(&[], "foo"));
+    }
+
+    #[test]
+    fn test_parse_index() -> Result<(), String> {
+        let sha = "ab".repeat(32);
+        let index = RemoteResourcesIndex::parse(
+            format!(
+                "# comment\n{}\n\npackage foo {} foo/__init__.py\nmodule foo.bar {} https://cdn.example.com/{}\n",
+                INDEX_HEADER, sha, sha, sha
+            )
+            .as_bytes(),
+            INDEX_URL,
+        )?;
+
+        assert_eq!(index.modules.len(), 2);
+        assert_eq!(
+            index.modules.get("foo"),
+            Some(&RemoteModule {
+                is_package: true,
+                sha256: sha.clone(),
+                url: "https://example.com/resources/foo/__init__.py".to_string(),
+            })
+        );
+        assert_eq!(
+            index.modules.get("foo.bar"),
+            Some(&RemoteModule {
+                is_package: false,
+                sha256: sha.clone(),
+                url: format!("https://cdn.example.com/{}", sha),
+            })
+        );
+
+        let parse = |body: &str| {
+            RemoteResourcesIndex::parse(format!("{}\n{}", INDEX_HEADER, body).as_bytes(), "")
+                .unwrap_err()
+        };
+
+        assert_eq!(
+            RemoteResourcesIndex::parse(b"module foo\n", "").unwrap_err(),
+            "unrecognized resource index format"
+        );
+        assert_eq!(
+            parse("module foo 1234 foo.py"),
+            "resource index line 2: 1234 is not a SHA-256 digest"
+        );
+        assert_eq!(
+            parse(&format!("module foo..bar {} foo.py", sha)),
+            "resource index line 2: foo..bar is not a valid module name"
+        );
+        assert_eq!(
+            parse(&format!(
+                "module foo {} a.py\npackage foo {} b.py",
+                sha, sha
+            )),
+            "resource index line 3: duplicate module foo"
+        );
+        assert_eq!(parse("unknown"), "resource index line 2: unrecognized line");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_module_source() -> Result<(), String> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyembed-test")
+            .tempdir()
+            .unwrap();
+        let cache_directory = temp_dir.path().join("cache");
+        let config = config(&cache_directory);
+
+        let source = b"VALUE = 42\n".to_vec();
+        let index = format!(
+            "{}\nmodule foo {} foo.py\nmodule bad {} bad.py\n",
+            INDEX_HEADER,
+            sha256_hex(&source),
+            sha256_hex(b"other"),
+        );
+
+        let mut content = HashMap::new();
+        content.insert(INDEX_URL.to_string(), index.as_bytes().to_vec());
+        content.insert(
+            format!("{}.sig", INDEX_URL),
+            keypair().sign(index.as_bytes()).to_bytes().to_vec(),
+        );
+        content.insert(
+            "https://example.com/resources/foo.py".to_string(),
+            source.clone(),
+        );
+        content.insert(
+            "https://example.com/resources/bad.py".to_string(),
+            b"tampered".to_vec(),
+        );
+
+        let fetched = RefCell::new(vec![]);
+        let mut fetch = |url: &str| {
+            fetched.borrow_mut().push(url.to_string());
+            content
+                .get(url)
+                .cloned()
+                .ok_or_else(|| format!("{} not found", url))
+        };
+
+        let index = RemoteResourcesIndex::fetch(&config, &mut fetch)?;
+        let foo = index.modules.get("foo").unwrap();
+
+        assert_eq!(
+            load_module_source(foo, &cache_directory, &mut fetch)?,
+            source
+        );
+        assert_eq!(
+            std::fs::read(cache_directory.join(&foo.sha256)).unwrap(),
+            source
+        );
+
+        // Cached sources aren't fetched again.
+        assert_eq!(
+            load_module_source(foo, &cache_directory, &mut fetch)?,
+            source
+        );
+        assert_eq!(
+            fetched
+                .borrow()
+                .iter()
+                .filter(|url| url.ends_with("foo.py"))
+                .count(),
+            1
+        );
+
+        // Modified cached sources are fetched again.
+        std::fs::write(cache_directory.join(&foo.sha256), b"modified").unwrap();
+        assert_eq!(
+            load_module_source(foo, &cache_directory, &mut fetch)?,
+            source
+        );
+
+        assert_eq!(
+            load_module_source(
+                index.modules.get("bad").unwrap(),
+                &cache_directory,
+                &mut fetch
+            ),
+            Err(
+                "content of https://example.com/resources/bad.py doesn't match the resource index"
+                    .to_string()
+            )
+        );
+
+        // An index signed by another key is refused.
+        content.insert(format!("{}.sig", INDEX_URL), vec![0; 64]);
+        let mut fetch = |url: &str| {
+            content
+                .get(url)
+                .cloned()
+                .ok_or_else(|| format!("{} not found", url))
+        };
+        assert_eq!(
+            RemoteResourcesIndex::fetch(&config, &mut fetch),
+            Err("error verifying resource index: signature verification failed".to_string())
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Deny Python code access to facilities able to affect the host system.
+
+Enforcement is a PEP 578 audit hook. The hook rejects imports of modules
+providing denied facilities as well as audited operations using them, such
+as creating sockets or processes. Audit hooks can't be removed, so Python
+code can't disable enforcement.
+
+Not every operation is audited and modules built into the binary can be
+loaded without raising the `import` event. So this is a defense in depth
+measure and not a sandbox for untrusted code.
+*/
+
+use {
+    crate::audit::{add_audit_hook, event_matches},
+    cpython::{
+        exc::{ImportError, PermissionError, RuntimeError},
+        ObjectProtocol, PyDict, PyErr, PyObject, PyResult, Python,
+    },
+    python3_sys as pyffi,
+    python_packaging::interpreter::{RestrictedFacility, RestrictedModeConfig},
+    std::{
+        ffi::CStr,
+        os::raw::{c_char, c_int, c_void},
+    },
+};
+
+/// Modules providing a facility.
+fn facility_modules(facility: RestrictedFacility) -> &'static [&'static str] {
+    match facility {
+        RestrictedFacility::Ctypes => &["ctypes", "_ctypes"],
+        RestrictedFacility::Socket => &["socket", "_socket", "ssl", "_ssl"],
+        RestrictedFacility::Subprocess => &["subprocess", "_posixsubprocess", "pty"],
+    }
+}
+
+/// Audit events raised when using a facility.
+///
+/// Values ending in `.` match all events with that prefix.
+fn facility_events(facility: RestrictedFacility) -> &'static [&'static str] {
+    match facility {
+        RestrictedFacility::Ctypes => &["ctypes."],
+        RestrictedFacility::Socket => &["socket."],
+        RestrictedFacility::Subprocess => &[
+            "subprocess.Popen",
+            "os.exec",
+            "os.fork",
+            "os.forkpty",
+            "os.posix_spawn",
+            "os.spawn",
+            "os.startfile",
+            "os.system",
+        ],
+    }
+}
+
+/// What restricted mode denies.
+#[derive(Debug)]
+struct Policy {
+    modules: Vec<String>,
+    events: Vec<&'static str>,
+}
+
+impl Policy {
+    fn new(config: &RestrictedModeConfig) -> Self {
+        let denied = RestrictedFacility::all()
+            .into_iter()
+            .filter(|facility| !config.allowed_facilities.contains(facility))
+            .collect::<Vec<_>>();
+
+        Self {
+            modules: denied
+                .iter()
+                .flat_map(|facility| facility_modules(*facility))
+                .map(|name| name.to_string())
+                .chain(config.denied_modules.iter().cloned())
+                .collect(),
+            events: denied
+                .iter()
+                .flat_map(|facility| facility_events(*facility))
+                .copied()
+                .collect(),
+        }
+    }
+
+    fn is_module_denied(&self, name: &str) -> bool {
+        self.modules.iter().any(|denied| {
+            name == denied
+                || (name.starts_with(denied.as_str()) && name[denied.len()..].starts_with('.'))
+        })
+    }
+
+    fn is_event_denied(&self, event: &str) -> bool {
+        self.events
+            .iter()
+            .any(|denied| event_matches(denied, event))
+    }
+}
+
+/// Audit hook enforcing the [Policy] pointed to by `user_data`.
+unsafe extern "C" fn audit_hook(
+    event: *const c_char,
+    args: *mut pyffi::PyObject,
+    user_data: *mut c_void,
+) -> c_int {
+    let policy = &*(user_data as *const Policy);
+    let py = Python::assume_gil_acquired();
+    let event = CStr::from_ptr(event).to_string_lossy();
+
+    let err = if event == "import" {
+        // The first argument is the name of the module being imported.
+        let args = PyObject::from_borrowed_ptr(py, args);
+
+        match args
+            .get_item(py, 0)
+            .and_then(|name| name.extract::<String>(py))
+        {
+            Ok(name) if policy.is_module_denied(&name) => Some(PyErr::new::<ImportError, _>(
+                py,
+                format!("import of {} is denied in restricted mode", name),
+            )),
+            _ => None,
+        }
+    } else if policy.is_event_denied(&event) {
+        Some(PyErr::new::<PermissionError, _>(
+            py,
+            format!("{} is denied in restricted mode", event),
+        ))
+    } else {
+        None
+    };
+
+    match err {
+        Some(err) => {
+            err.restore(py);
+            -1
+        }
+        None => 0,
+    }
+}
+
+/// Enable restricted mode in the current interpreter.
+pub(crate) fn install_restricted_mode(py: Python, config: &RestrictedModeConfig) -> PyResult<()> {
+    // The hook lives until the interpreter is finalized. So the policy is
+    // leaked to outlive it.
+    let policy = Box::into_raw(Box::new(Policy::new(config)));
+
+    if !add_audit_hook(audit_hook, policy as *mut c_void) {
+        return Err(PyErr::new::<RuntimeError, _>(
+            py,
+            "an audit hook rejected the restricted mode audit hook",
+        ));
+    }
+
+    // Denied modules imported during initialization remain usable by
+    // importing them again unless they are removed.
+    let policy = unsafe { &*policy };
+    let modules = py
+        .import("sys")?
+        .get(py, "modules")?
+        .cast_into::<PyDict>(py)?;
+
+    for (key, _) in modules.items(py) {
+        if let Ok(name) = key.extract::<String>(py) {
+            if policy.is_module_denied(&name) {
+                modules.del_item(py, key)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy() {
+        let mut config = RestrictedModeConfig::default();
+        config.allowed_facilities.insert(RestrictedFacility::Socket);
+        config.denied_modules.push("http".to_string());
+
+        let policy = Policy::new(&config);
+
+        assert!(policy.is_module_denied("ctypes"));
+        assert!(policy.is_module_denied("ctypes.util"));
+        assert!(policy.is_module_denied("_posixsubprocess"));
+        assert!(policy.is_module_denied("http.client"));
+        assert!(!policy.is_module_denied("ctypesx"));
+        assert!(!policy.is_module_denied("socket"));
+
+        assert!(policy.is_event_denied("ctypes.dlopen"));
+        assert!(policy.is_event_denied("os.system"));
+        assert!(policy.is_event_denied("subprocess.Popen"));
+        assert!(!policy.is_event_denied("os.systemd"));
+        assert!(!policy.is_event_denied("socket.connect"));
+        assert!(!policy.is_event_denied("open"));
+    }
+}
@@ -0,0 +1,436 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Record what an interpreter did and report it when it shuts down.
+
+Recording is disabled by default. Once enabled, our importer records the
+modules it executes, the module executing when each was imported, how long
+execution took, and the resources read through it. When the interpreter is
+finalized, the recorded data and the content of `sys.modules` are written as
+a JSON document.
+*/
+
+use {
+    once_cell::sync::Lazy,
+    python_packaging::interpreter::RunReportSection,
+    std::{
+        collections::{BTreeMap, BTreeSet, HashMap},
+        convert::TryFrom,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex,
+        },
+        thread::ThreadId,
+        time::{Duration, Instant},
+    },
+};
+
+#[cfg(not(library_mode = "extension"))]
+use {
+    cpython::{ObjectProtocol, PyDict, Python},
+    std::{io::Write, path::Path},
+};
+
+/// Environment variable overriding the sections to write to a run report.
+///
+/// The value is a comma delimited list of section names. e.g.
+/// `modules,import_times`.
+pub const RUN_REPORT_SECTIONS_ENV: &str = "PYEMBED_RUN_REPORT_SECTIONS";
+
+/// Parse a comma delimited list of section names.
+pub fn parse_sections(value: &str) -> Result<BTreeSet<RunReportSection>, String> {
+    value
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(RunReportSection::try_from)
+        .collect()
+}
+
+/// Describes the execution of a module.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct ModuleRecord {
+    /// Module executing when this module was imported.
+    parent: Option<String>,
+    /// Time spent executing the module, including modules it imported.
+    cumulative: Duration,
+    /// Time spent executing the module, excluding modules it imported.
+    own: Duration,
+}
+
+/// A module being executed.
+#[derive(Debug)]
+struct ExecFrame {
+    name: String,
+    start: Instant,
+    /// Time spent executing modules imported by this one.
+    children: Duration,
+}
+
+/// Accumulates what happened during a run.
+#[derive(Debug, Default)]
+struct RunRecorder {
+    /// Modules being executed, per thread.
+    ///
+    /// Imports on different threads are independent, so each thread has its
+    /// own stack to attribute imports to the right parent.
+    stacks: HashMap<ThreadId, Vec<ExecFrame>>,
+    modules: BTreeMap<String, ModuleRecord>,
+    resources: BTreeSet<String>,
+}
+
+impl RunRecorder {
+    fn module_exec_start(&mut self, thread: ThreadId, name: &str, now: Instant) {
+        self.stacks.entry(thread).or_default().push(ExecFrame {
+            name: name.to_string(),
+            start: now,
+            children: Duration::default(),
+        });
+    }
+
+    fn module_exec_end(&mut self, thread: ThreadId, name: &str, now: Instant) {
+        let stack = match self.stacks.get_mut(&thread) {
+            Some(stack) => stack,
+            None => return,
+        };
+
+        // Frames are popped in the order they were pushed. But be defensive
+        // against an unbalanced stack so a bug here doesn't corrupt the
+        // records of unrelated modules.
+        let index = match stack.iter().rposition(|frame| frame.name == name) {
+            Some(index) => index,
+            None => return,
+        };
+        stack.truncate(index + 1);
+        let frame = stack.pop().expect("frame should exist");
+
+        let cumulative = now.saturating_duration_since(frame.start);
+
+        if let Some(parent) = stack.last_mut() {
+            parent.children += cumulative;
+        }
+
+        let parent = stack.last().map(|frame| frame.name.clone());
+
+        if stack.is_empty() {
+            self.stacks.remove(&thread);
+        }
+
+        self.modules.insert(
+            frame.name,
+            ModuleRecord {
+                parent,
+                cumulative,
+                own: cumulative.checked_sub(frame.children).unwrap_or_default(),
+            },
+        );
+    }
+
+    fn resource_accessed(&mut self, name: &str) {
+        self.resources.insert(name.to_string());
+    }
+
+    /// Render a JSON document holding the requested sections.
+    ///
+    /// `modules` are the names of loaded modules.
+    fn render(&self, sections: &BTreeSet<RunReportSection>, modules: &BTreeSet<String>) -> String {
+        let mut entries = vec![];
+
+        for section in sections {
+            let value = match section {
+                RunReportSection::Modules => json_list(modules.iter()),
+                RunReportSection::ImportGraph => json_object(self.modules.iter().map(|(k, v)| {
+                    (
+                        k,
+                        v.parent
+                            .as_ref()
+                            .map(|p| json_string(p))
+                            .unwrap_or_else(|| "null".to_string()),
+                    )
+                })),
+                RunReportSection::ImportTimes => json_object(self.modules.iter().map(|(k, v)| {
+                    (
+                        k,
+                        format!(
+                            "{{\"cumulative_us\": {}, \"self_us\": {}}}",
+                            v.cumulative.as_micros(),
+                            v.own.as_micros()
+                        ),
+                    )
+                })),
+                RunReportSection::Resources => json_list(self.resources.iter()),
+            };
+
+            entries.push(format!(
+                "  {}: {}",
+                json_string(&section.to_string()),
+                value
+            ));
+        }
+
+        format!("{{\n{}\n}}\n", entries.join(",\n"))
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut res = String::with_capacity(value.len() + 2);
+    res.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+
+    res.push('"');
+    res
+}
+
+fn json_list<'a>(values: impl Iterator<Item = &'a String>) -> String {
+    format!(
+        "[{}]",
+        values
+            .map(|v| json_string(v))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+fn json_object<'a>(values: impl Iterator<Item = (&'a String, String)>) -> String {
+    format!(
+        "{{{}}}",
+        values
+            .map(|(k, v)| format!("{}: {}", json_string(k), v))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Whether recording is enabled.
+///
+/// Checked before taking the lock so recording has no meaningful overhead
+/// when disabled.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static RECORDER: Lazy<Mutex<RunRecorder>> = Lazy::new(|| Mutex::new(RunRecorder::default()));
+
+/// Start recording events.
+#[cfg(not(library_mode = "extension"))]
+pub(crate) fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Records the execution of a module until dropped.
+pub(crate) struct ModuleExecGuard {
+    name: String,
+}
+
+impl Drop for ModuleExecGuard {
+    fn drop(&mut self) {
+        if let Ok(mut recorder) = RECORDER.lock() {
+            recorder.module_exec_end(std::thread::current().id(), &self.name, Instant::now());
+        }
+    }
+}
+
+/// Record that a module started executing.
+///
+/// The execution is recorded as finished when the returned value is dropped.
+pub(crate) fn module_exec(name: &str) -> Option<ModuleExecGuard> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    if let Ok(mut recorder) = RECORDER.lock() {
+        recorder.module_exec_start(std::thread::current().id(), name, Instant::now());
+
+        Some(ModuleExecGuard {
+            name: name.to_string(),
+        })
+    } else {
+        None
+    }
+}
+
+/// Record that a resource was read.
+pub(crate) fn resource_accessed(name: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if let Ok(mut recorder) = RECORDER.lock() {
+        recorder.resource_accessed(name);
+    }
+}
+
+/// Write a run report to a file.
+///
+/// `sections` are written unless overridden by the environment variable
+/// defined by `RUN_REPORT_SECTIONS_ENV`.
+#[cfg(not(library_mode = "extension"))]
+pub(crate) fn write_run_report(
+    py: Python,
+    path: &Path,
+    sections: &BTreeSet<RunReportSection>,
+) -> Result<(), String> {
+    let sections = match std::env::var(RUN_REPORT_SECTIONS_ENV) {
+        Ok(value) => parse_sections(&value)?,
+        Err(_) => sections.clone(),
+    };
+
+    let sys = py
+        .import("sys")
+        .map_err(|_| "could not obtain sys module".to_string())?;
+    let modules = sys
+        .get(py, "modules")
+        .map_err(|_| "could not obtain sys.modules".to_string())?;
+    let modules = modules
+        .cast_as::<PyDict>(py)
+        .map_err(|_| "sys.modules is not a dict".to_string())?;
+
+    let mut names = BTreeSet::new();
+    for (key, _value) in modules.items(py) {
+        names.insert(
+            key.extract::<String>(py)
+                .map_err(|_| "module name is not a str".to_string())?,
+        );
+    }
+
+    let report = RECORDER
+        .lock()
+        .map_err(|_| "unable to lock run recorder".to_string())?
+        .render(&sections, &names);
+
+    let mut f = std::fs::File::create(path)
+        .map_err(|e| format!("could not open {}: {}", path.display(), e))?;
+    f.write_all(report.as_bytes())
+        .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sections() {
+        assert_eq!(
+            parse_sections("modules, import_times,"),
+            Ok(
+                vec![RunReportSection::Modules, RunReportSection::ImportTimes]
+                    .into_iter()
+                    .collect()
+            )
+        );
+        assert_eq!(parse_sections(""), Ok(BTreeSet::new()));
+        assert!(parse_sections("modules,bogus").is_err());
+    }
+
+    #[test]
+    fn test_recorder() {
+        let start = Instant::now();
+        let thread = std::thread::current().id();
+        let mut recorder = RunRecorder::default();
+
+        recorder.module_exec_start(thread, "app", start);
+        recorder.module_exec_start(thread, "app.util", start + Duration::from_micros(10));
+        recorder.module_exec_end(thread, "app.util", start + Duration::from_micros(40));
+        recorder.module_exec_end(thread, "app", start + Duration::from_micros(100));
+        recorder.resource_accessed("app:data.txt");
+
+        assert_eq!(
+            recorder.modules.get("app"),
+            Some(&ModuleRecord {
+                parent: None,
+                cumulative: Duration::from_micros(100),
+                own: Duration::from_micros(70),
+            })
+        );
+        assert_eq!(
+            recorder.modules.get("app.util"),
+            Some(&ModuleRecord {
+                parent: Some("app".to_string()),
+                cumulative: Duration::from_micros(30),
+                own: Duration::from_micros(30),
+            })
+        );
+
+        let modules = vec!["app".to_string(), "sys".to_string()]
+            .into_iter()
+            .collect();
+
+        assert_eq!(
+            recorder.render(&RunReportSection::all().into_iter().collect(), &modules),
+            "{\n  \
+            \"modules\": [\"app\", \"sys\"],\n  \
+            \"import_graph\": {\"app\": null, \"app.util\": \"app\"},\n  \
+            \"import_times\": {\"app\": {\"cumulative_us\": 100, \"self_us\": 70}, \
+            \"app.util\": {\"cumulative_us\": 30, \"self_us\": 30}},\n  \
+            \"resources\": [\"app:data.txt\"]\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_recorder_threads() {
+        let start = Instant::now();
+        let main = std::thread::current().id();
+        let other = std::thread::spawn(|| std::thread::current().id())
+            .join()
+            .unwrap();
+        let mut recorder = RunRecorder::default();
+
+        // Interleave imports on two threads. Each import is attributed to the
+        // module executing on its own thread.
+        recorder.module_exec_start(main, "app", start);
+        recorder.module_exec_start(other, "worker", start + Duration::from_micros(10));
+        recorder.module_exec_start(other, "worker.util", start + Duration::from_micros(20));
+        recorder.module_exec_start(main, "app.util", start + Duration::from_micros(30));
+        recorder.module_exec_end(other, "worker.util", start + Duration::from_micros(40));
+        recorder.module_exec_end(main, "app.util", start + Duration::from_micros(50));
+        recorder.module_exec_end(other, "worker", start + Duration::from_micros(60));
+        recorder.module_exec_end(main, "app", start + Duration::from_micros(100));
+
+        assert_eq!(
+            recorder.modules.get("app.util").map(|r| r.parent.clone()),
+            Some(Some("app".to_string()))
+        );
+        assert_eq!(
+            recorder
+                .modules
+                .get("worker.util")
+                .map(|r| r.parent.clone()),
+            Some(Some("worker".to_string()))
+        );
+        assert_eq!(
+            recorder.modules.get("worker"),
+            Some(&ModuleRecord {
+                parent: None,
+                cumulative: Duration::from_micros(50),
+                own: Duration::from_micros(30),
+            })
+        );
+        assert_eq!(
+            recorder.modules.get("app"),
+            Some(&ModuleRecord {
+                parent: None,
+                cumulative: Duration::from_micros(100),
+                own: Duration::from_micros(80),
+            })
+        );
+        assert!(recorder.stacks.is_empty());
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+    }
+}
@@ -0,0 +1,82 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Run modules as `__main__` with `runpy`.
+
+`python -m` is implemented by `runpy._run_module_as_main()`, which runs the
+module's code in the namespace of the existing `__main__` module and sets
+`__spec__`, `__package__`, etc. so relative imports work.
+
+It also sets `sys.argv[0]` to the module's origin. Modules loaded from memory
+have no origin, so `sys.argv[0]` would become `None` and break code deriving
+the program name from it (e.g. `argparse`). We mirror `_run_module_as_main()`
+but retain `sys.argv[0]` in that case.
+*/
+
+use cpython::{exc::SystemExit, ObjectProtocol, PyDict, PyErr, PyObject, PyResult, Python};
+
+/// Python code defining a `run_module(mod_name, alter_sys)` function.
+const RUN_MODULE_SOURCE: &str = r#"import runpy
+import sys
+
+
+def run_module(mod_name, alter_sys):
+    if not alter_sys:
+        return runpy.run_module(mod_name, run_name="__main__")
+
+    # Mirrors runpy._run_module_as_main().
+    try:
+        mod_name, mod_spec, code = runpy._get_module_details(mod_name, runpy._Error)
+    except runpy._Error as e:
+        sys.exit("%s: %s" % (sys.executable, e))
+
+    if mod_spec.origin is not None and sys.argv:
+        sys.argv[0] = mod_spec.origin
+
+    main_globals = sys.modules["__main__"].__dict__
+
+    return runpy._run_code(code, main_globals, None, "__main__", mod_spec)
+"#;
+
+/// Run a module as `__main__`.
+///
+/// Returns the globals of the executed module.
+pub(crate) fn run_module(py: Python, name: &str, alter_sys: bool) -> PyResult<PyObject> {
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__builtins__", py.import("builtins")?)?;
+
+    py.run(RUN_MODULE_SOURCE, Some(&globals), None)?;
+
+    globals
+        .get_item(py, "run_module")
+        .expect("run_module should be defined")
+        .call(py, (name, alter_sys), None)
+}
+
+/// Resolve the process exit code for an exception raised by `__main__`.
+///
+/// Like `Py_RunMain()`, `SystemExit` yields its code and other exceptions
+/// are printed and yield 1.
+pub(crate) fn exit_code_for_err(py: Python, mut err: PyErr) -> i32 {
+    if !err.matches(py, py.get_type::<SystemExit>()) {
+        err.print(py);
+        return 1;
+    }
+
+    let code = match err.instance(py).getattr(py, "code") {
+        Ok(code) => code,
+        Err(_) => return 1,
+    };
+
+    if code.is_none(py) {
+        0
+    } else if let Ok(value) = code.extract::<i32>(py) {
+        value
+    } else {
+        // Other values are printed, like `sys.exit("message")` does.
+        eprintln!("{}", code);
+        1
+    }
+}
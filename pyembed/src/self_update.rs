@@ -0,0 +1,795 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Update applications from signed update manifests.
+
+An update manifest describes the files of a version of an application.
+Manifests are signed with an Ed25519 key and the public key is built into
+the application, so only updates published by the holder of the private
+key are installed.
+
+Updating happens in 2 steps. First, the files of the new version which
+differ from the installed files are downloaded and verified into a staging
+directory. Files providing a delta (see [python_packed_resources::delta])
+against the installed file are downloaded as deltas. Once all files are
+staged, a marker file is written. Then, the next time the application
+starts, the staged files are moved into place before anything is loaded
+from them. Replaced files are kept until all files are in place, so a
+failed installation is rolled back. An interrupted installation resumes on
+the following start.
+
+Manifests are UTF-8 text. The first line is [MANIFEST_HEADER]. Each
+following line is one of:
+
+* `version <version>` - the version of the application. Versions are dot
+  delimited integers (e.g. `1.10.2`) and an update is only installed if its
+  version is newer than the running version.
+* `file <path> <sha256> <url>` - a file of the application. The path is
+  relative to the directory of the executable.
+* `delta <path> <base sha256> <url>` - a delta producing the file at
+  `<path>` from the file having the given SHA-256.
+
+Empty lines and lines starting with `#` are ignored. URLs not containing
+`://` are relative to the URL of the manifest.
+*/
+
+use {
+    ed25519_dalek::{PublicKey, Signature, Verifier},
+    python_packaging::interpreter::SelfUpdateConfig,
+    python_packed_resources::delta::apply_delta,
+    sha2::{Digest, Sha256},
+    std::{
+        convert::TryFrom,
+        path::{Component, Path, PathBuf},
+    },
+};
+
+/// First line of update manifests.
+pub const MANIFEST_HEADER: &str = "pyoxidizer-update-manifest 1";
+
+/// Directory in the staging directory holding staged files.
+const FILES_DIRECTORY: &str = "files";
+
+/// Marker file written once an update is fully staged.
+const READY_FILENAME: &str = "ready";
+
+/// Suffix of files replaced by an update until the update is installed.
+const BACKUP_SUFFIX: &str = ".old";
+
+/// Function fetching the content of a URL.
+pub type Fetch<'a> = dyn FnMut(&str) -> Result<Vec<u8>, String> + 'a;
+
+/// A delta producing a file from another version of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdateDelta {
+    /// SHA-256 of the file the delta applies to.
+    pub base_sha256: String,
+    /// URL of the delta.
+    pub url: String,
+}
+
+/// A file of an application version.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdateFile {
+    /// Path of the file relative to the directory of the executable.
+    pub path: PathBuf,
+    /// SHA-256 of the file content.
+    pub sha256: String,
+    /// URL of the file content.
+    pub url: String,
+    /// Delta producing the file from an installed version.
+    pub delta: Option<UpdateDelta>,
+}
+
+/// A parsed update manifest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdateManifest {
+    /// Version of the application.
+    pub version: String,
+    /// Files of the application.
+    pub files: Vec<UpdateFile>,
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 || !value.is_ascii() {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok())
+        .collect()
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+pub(crate) fn parse_sha256(value: &str) -> Result<String, String> {
+    if value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(value.to_ascii_lowercase())
+    } else {
+        Err(format!("{} is not a SHA-256 digest", value))
+    }
+}
+
+/// Parse a version into its numeric components.
+///
+/// Versions are dot delimited integers. Trailing zero components are
+/// removed, so `1.1` and `1.1.0` compare equal.
+pub fn parse_version(value: &str) -> Result<Vec<u64>, String> {
+    let mut components = value
+        .split('.')
+        .map(|c| {
+            if !c.is_empty() && c.chars().all(|c| c.is_ascii_digit()) {
+                c.parse::<u64>().ok()
+            } else {
+                None
+            }
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| format!("{} is not a version of dot delimited integers", value))?;
+
+    while components.last() == Some(&0) {
+        components.pop();
+    }
+
+    Ok(components)
+}
+
+/// Validate a path in a manifest, which must stay within its base directory.
+fn parse_relative_path(value: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(value);
+
+    if value.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        Err(format!("{} is not a normalized relative path", value))
+    } else {
+        Ok(path)
+    }
+}
+
+/// Resolve a URL in a manifest relative to the URL of the manifest.
+pub(crate) fn resolve_url(manifest_url: &str, url: &str) -> String {
+    if url.contains("://") {
+        url.to_string()
+    } else {
+        let base = manifest_url
+            .rfind('/')
+            .map(|i| &manifest_url[..=i])
+            .unwrap_or_default();
+
+        format!("{}{}", base, url)
+    }
+}
+
+impl UpdateManifest {
+    /// Parse a manifest.
+    ///
+    /// URLs are resolved relative to `manifest_url`.
+    pub fn parse(data: &[u8], manifest_url: &str) -> Result<Self, String> {
+        let text =
+            std::str::from_utf8(data).map_err(|_| "manifest is not valid UTF-8".to_string())?;
+
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        match lines.next() {
+            Some((_, line)) if line == MANIFEST_HEADER => {}
+            _ => return Err("unrecognized manifest format".to_string()),
+        }
+
+        let mut version = None;
+        let mut files: Vec<UpdateFile> = vec![];
+
+        for (number, line) in lines {
+            let error = |message: String| format!("manifest line {}: {}", number, message);
+
+            match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["version", value] => {
+                    parse_version(value).map_err(error)?;
+                    version = Some(value.to_string());
+                }
+                ["file", path, sha256, url] => {
+                    let path = parse_relative_path(path).map_err(error)?;
+                    if files.iter().any(|f| f.path == path) {
+                        return Err(error(format!("duplicate file {}", path.display())));
+                    }
+
+                    files.push(UpdateFile {
+                        path,
+                        sha256: parse_sha256(sha256).map_err(error)?,
+                        url: resolve_url(manifest_url, url),
+                        delta: None,
+                    });
+                }
+                ["delta", path, base_sha256, url] => {
+                    let path = parse_relative_path(path).map_err(error)?;
+                    let base_sha256 = parse_sha256(base_sha256).map_err(error)?;
+
+                    let file = files.iter_mut().find(|f| f.path == path).ok_or_else(|| {
+                        error(format!("delta for unknown file {}", path.display()))
+                    })?;
+
+                    file.delta = Some(UpdateDelta {
+                        base_sha256,
+                        url: resolve_url(manifest_url, url),
+                    });
+                }
+                _ => return Err(error("unrecognized line".to_string())),
+            }
+        }
+
+        Ok(Self {
+            version: version.ok_or_else(|| "manifest doesn't define a version".to_string())?,
+            files,
+        })
+    }
+}
+
+/// Verify the Ed25519 signature of data.
+pub fn verify_signature(data: &[u8], signature: &[u8], public_key: &str) -> Result<(), String> {
+    let public_key = decode_hex(public_key)
+        .and_then(|key| PublicKey::from_bytes(&key).ok())
+        .ok_or_else(|| "public key is not a hex encoded Ed25519 public key".to_string())?;
+
+    let signature = Signature::try_from(signature)
+        .map_err(|_| "signature is not an Ed25519 signature".to_string())?;
+
+    public_key
+        .verify(data, &signature)
+        .map_err(|_| "signature verification failed".to_string())
+}
+
+/// Checks for, downloads, and stages updates.
+pub struct Updater<'a> {
+    config: &'a SelfUpdateConfig,
+    origin: &'a Path,
+}
+
+impl<'a> Updater<'a> {
+    /// Create an instance updating the application in `origin`.
+    ///
+    /// The staging directory of `config` must have been resolved.
+    pub fn new(config: &'a SelfUpdateConfig, origin: &'a Path) -> Self {
+        Self { config, origin }
+    }
+
+    /// Fetch and verify the update manifest.
+    ///
+    /// Returns `None` unless the manifest describes a version newer than
+    /// the running version.
+    pub fn check(&self, fetch: &mut Fetch) -> Result<Option<UpdateManifest>, String> {
+        let current = parse_version(&self.config.version)
+            .map_err(|e| format!("invalid running version: {}", e))?;
+
+        let data = fetch(&self.config.manifest_url)?;
+        let signature = fetch(&format!("{}.sig", self.config.manifest_url))?;
+
+        verify_signature(&data, &signature, &self.config.public_key)
+            .map_err(|e| format!("error verifying update manifest: {}", e))?;
+
+        let manifest = UpdateManifest::parse(&data, &self.config.manifest_url)?;
+
+        Ok(if parse_version(&manifest.version)? > current {
+            Some(manifest)
+        } else {
+            None
+        })
+    }
+
+    /// Obtain the content of a file of an update.
+    ///
+    /// A delta is used if one applies to the installed file. The content is
+    /// verified against the manifest.
+    fn fetch_file(
+        &self,
+        file: &UpdateFile,
+        installed: Option<&[u8]>,
+        fetch: &mut Fetch,
+    ) -> Result<Vec<u8>, String> {
+        let patched = match (&file.delta, installed) {
+            (Some(delta), Some(installed)) if sha256_hex(installed) == delta.base_sha256 => {
+                fetch(&delta.url)
+                    .and_then(|delta| apply_delta(installed, &delta).map_err(|e| e.to_string()))
+                    .ok()
+                    .filter(|data| sha256_hex(data) == file.sha256)
+            }
+            _ => None,
+        };
+
+        // Fall back to downloading the entire file if the delta can't be
+        // used.
+        let data = match patched {
+            Some(data) => data,
+            None => fetch(&file.url)?,
+        };
+
+        if sha256_hex(&data) == file.sha256 {
+            Ok(data)
+        } else {
+            Err(format!(
+                "content of {} doesn't match the update manifest",
+                file.path.display()
+            ))
+        }
+    }
+
+    /// Download the files of an update into the staging directory.
+    ///
+    /// Files matching the installed files are skipped. The update is
+    /// installed the next time the application starts.
+    pub fn stage(&self, manifest: &UpdateManifest, fetch: &mut Fetch) -> Result<(), String> {
+        let staging = &self.config.staging_directory;
+        let io_error =
+            |path: &Path, e: std::io::Error| format!("error writing {}: {}", path.display(), e);
+
+        if staging.exists() {
+            std::fs::remove_dir_all(staging).map_err(|e| io_error(staging, e))?;
+        }
+
+        let mut staged = vec![];
+
+        for file in &manifest.files {
+            let target = self.origin.join(&file.path);
+            let installed = std::fs::read(&target).ok();
+
+            if installed.as_deref().map(sha256_hex).as_ref() == Some(&file.sha256) {
+                continue;
+            }
+
+            let data = self.fetch_file(file, installed.as_deref(), fetch)?;
+
+            let dest = staging.join(FILES_DIRECTORY).join(&file.path);
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| io_error(parent, e))?;
+            }
+            std::fs::write(&dest, &data).map_err(|e| io_error(&dest, e))?;
+
+            // Retain the permissions of the installed file, notably whether
+            // it is executable.
+            if let Ok(metadata) = std::fs::metadata(&target) {
+                std::fs::set_permissions(&dest, metadata.permissions())
+                    .map_err(|e| io_error(&dest, e))?;
+            }
+
+            staged.push(file.path.display().to_string());
+        }
+
+        let mut ready = format!("version {}\n", manifest.version);
+        for path in staged {
+            ready.push_str(&path);
+            ready.push('\n');
+        }
+
+        // The marker is renamed into place so it is never seen incomplete.
+        let temp_path = staging.join(format!("{}.tmp", READY_FILENAME));
+        let ready_path = staging.join(READY_FILENAME);
+        std::fs::create_dir_all(staging).map_err(|e| io_error(staging, e))?;
+        std::fs::write(&temp_path, ready).map_err(|e| io_error(&temp_path, e))?;
+        std::fs::rename(&temp_path, &ready_path).map_err(|e| io_error(&ready_path, e))?;
+
+        Ok(())
+    }
+
+    /// Obtain the version of the update waiting to be installed, if any.
+    pub fn staged_version(&self) -> Option<String> {
+        read_ready_marker(&self.config.staging_directory)
+            .ok()
+            .flatten()
+            .map(|(version, _)| version)
+    }
+}
+
+/// Read the marker of a fully staged update.
+fn read_ready_marker(staging: &Path) -> std::io::Result<Option<(String, Vec<PathBuf>)>> {
+    let content = match std::fs::read_to_string(staging.join(READY_FILENAME)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid update marker");
+
+    let mut lines = content.lines();
+    let version = lines
+        .next()
+        .and_then(|line| line.strip_prefix("version "))
+        .ok_or_else(invalid)?
+        .to_string();
+
+    let paths = lines
+        .map(|line| parse_relative_path(line).map_err(|_| invalid()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Some((version, paths)))
+}
+
+/// Obtain the path a file replaced by an update is kept at.
+fn backup_path(target: &Path) -> PathBuf {
+    let mut path = target.as_os_str().to_os_string();
+    path.push(BACKUP_SUFFIX);
+
+    PathBuf::from(path)
+}
+
+/// Move staged files into place, moving replaced files aside.
+///
+/// Paths whose installed file may have been touched are added to `attempted`.
+fn install_files(
+    staging: &Path,
+    origin: &Path,
+    paths: &[PathBuf],
+    attempted: &mut Vec<PathBuf>,
+) -> std::io::Result<()> {
+    for path in paths {
+        let staged = staging.join(FILES_DIRECTORY).join(path);
+
+        // The file was installed by an interrupted earlier attempt.
+        if !staged.exists() {
+            continue;
+        }
+
+        let target = origin.join(path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Replacing a running executable isn't allowed on Windows. But
+        // renaming it is.
+        if target.exists() {
+            let backup = backup_path(&target);
+            if backup.exists() {
+                std::fs::remove_file(&backup)?;
+            }
+
+            attempted.push(path.clone());
+            std::fs::rename(&target, &backup)?;
+        } else {
+            attempted.push(path.clone());
+        }
+
+        std::fs::rename(&staged, &target)?;
+    }
+
+    Ok(())
+}
+
+/// Restore the files replaced by a partially installed update.
+fn rollback_files(
+    staging: &Path,
+    origin: &Path,
+    paths: &[PathBuf],
+    attempted: &[PathBuf],
+) -> std::io::Result<()> {
+    for path in paths.iter().rev() {
+        let staged = staging.join(FILES_DIRECTORY).join(path);
+
+        // Files installed by an interrupted earlier attempt no longer have
+        // a staged file and are rolled back as well.
+        if staged.exists() && !attempted.contains(path) {
+            continue;
+        }
+
+        let target = origin.join(path);
+        let backup = backup_path(&target);
+
+        if backup.exists() {
+            std::fs::rename(&backup, &target)?;
+        } else if !staged.exists() && target.exists() {
+            // The file didn't exist before the update.
+            std::fs::remove_file(&target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Install an update staged in a directory into `origin`.
+///
+/// Replaced files are moved aside until all staged files are in place. If
+/// installing fails, the replaced files are restored and the staged update
+/// is discarded before the error is returned, leaving the running version
+/// installed.
+///
+/// Returns the installed version or `None` if no update is staged.
+pub fn install_staged_update(staging: &Path, origin: &Path) -> std::io::Result<Option<String>> {
+    let (version, paths) = match read_ready_marker(staging)? {
+        Some(marker) => marker,
+        None => return Ok(None),
+    };
+
+    let mut attempted = vec![];
+    if let Err(e) = install_files(staging, origin, &paths, &mut attempted) {
+        let message = match rollback_files(staging, origin, &paths, &attempted) {
+            Ok(()) => {
+                std::fs::remove_dir_all(staging)?;
+                format!("{}; the update was rolled back", e)
+            }
+            Err(rollback_error) => {
+                format!("{}; error rolling back the update: {}", e, rollback_error)
+            }
+        };
+
+        return Err(std::io::Error::new(e.kind(), message));
+    }
+
+    std::fs::remove_file(staging.join(READY_FILENAME))?;
+    // Leftovers are harmless and removed when the next update is staged.
+    let _ = std::fs::remove_dir_all(staging);
+
+    // Running executables can't be removed on Windows. They are replaced
+    // when the file is updated again instead.
+    for path in &paths {
+        let _ = std::fs::remove_file(backup_path(&origin.join(path)));
+    }
+
+    Ok(Some(version))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        ed25519_dalek::{Keypair, SecretKey, Signer},
+        python_packed_resources::delta::create_delta,
+        std::collections::HashMap,
+    };
+
+    const MANIFEST_URL: &str = "https://example.com/app/manifest";
+
+    fn keypair() -> Keypair {
+        let secret = SecretKey::from_bytes(&[7; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+
+        Keypair { secret, public }
+    }
+
+    fn public_key_hex() -> String {
+        keypair()
+            .public
+            .as_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_manifest() -> Result<(), String> {
+        let sha = "ab".repeat(32);
+        let manifest = UpdateManifest::parse(
+            format!(
+                "# comment\n{}\nversion 1.1\n\nfile app {} app-1.1\nfile lib/app.pypacked {} https://cdn.example.com/app.pypacked\ndelta lib/app.pypacked {} app.pypacked.delta\n",
+                MANIFEST_HEADER, sha, sha, sha
+            )
+            .as_bytes(),
+            MANIFEST_URL,
+        )?;
+
+        assert_eq!(manifest.version, "1.1");
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(manifest.files[0].url, "https://example.com/app/app-1.1");
+        assert_eq!(manifest.files[1].path, PathBuf::from("lib/app.pypacked"));
+        assert_eq!(
+            manifest.files[1].url,
+            "https://cdn.example.com/app.pypacked"
+        );
+        assert_eq!(
+            manifest.files[1].delta,
+            Some(UpdateDelta {
+                base_sha256: sha.clone(),
+                url: "https://example.com/app/app.pypacked.delta".to_string(),
+            })
+        );
+
+        let parse = |body: &str| {
+            UpdateManifest::parse(format!("{}\n{}", MANIFEST_HEADER, body).as_bytes(), "")
+                .unwrap_err()
+        };
+
+        assert_eq!(
+            UpdateManifest::parse(b"version 1.1\n", "").unwrap_err(),
+            "unrecognized manifest format"
+        );
+        assert_eq!(parse(""), "manifest doesn't define a version");
+        assert_eq!(
+            parse(&format!("file ../app {} app", sha)),
+            "manifest line 2: ../app is not a normalized relative path"
+        );
+        assert_eq!(
+            parse("file app 1234 app"),
+            "manifest line 2: 1234 is not a SHA-256 digest"
+        );
+        assert_eq!(
+            parse(&format!("delta app {} app.delta", sha)),
+            "manifest line 2: delta for unknown file app"
+        );
+        assert_eq!(parse("unknown"), "manifest line 2: unrecognized line");
+        assert_eq!(
+            parse("version 1.1-beta"),
+            "manifest line 2: 1.1-beta is not a version of dot delimited integers"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("1.10.2"), Ok(vec![1, 10, 2]));
+        assert_eq!(parse_version("1.1.0"), parse_version("1.1"));
+        assert!(parse_version("1.10").unwrap() > parse_version("1.9").unwrap());
+        assert!(parse_version("1.1.1").unwrap() > parse_version("1.1").unwrap());
+        assert!(parse_version("").is_err());
+        assert!(parse_version("1..1").is_err());
+        assert!(parse_version("+1").is_err());
+        assert!(parse_version("v1").is_err());
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let data = b"data";
+        let signature = keypair().sign(data).to_bytes();
+
+        assert_eq!(
+            verify_signature(data, &signature, &public_key_hex()),
+            Ok(())
+        );
+        assert_eq!(
+            verify_signature(b"other", &signature, &public_key_hex()),
+            Err("signature verification failed".to_string())
+        );
+        assert!(verify_signature(data, &signature[0..10], &public_key_hex()).is_err());
+        assert!(verify_signature(data, &signature, "00").is_err());
+    }
+
+    #[test]
+    fn test_update() -> Result<(), String> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyembed-test")
+            .tempdir()
+            .unwrap();
+        let origin = temp_dir.path().join("app");
+        std::fs::create_dir_all(&origin).unwrap();
+
+        let old_resources = (0..20000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut new_resources = old_resources.clone();
+        new_resources[10000] = 0xff;
+        std::fs::write(origin.join("app"), b"binary").unwrap();
+        std::fs::write(origin.join("app.pypacked"), &old_resources).unwrap();
+
+        let manifest = format!(
+            "{}\nversion 1.1\nfile app {} app\nfile app.pypacked {} app.pypacked\ndelta app.pypacked {} app.pypacked.delta\nfile lib/new.txt {} new.txt\n",
+            MANIFEST_HEADER,
+            sha256_hex(b"binary"),
+            sha256_hex(&new_resources),
+            sha256_hex(&old_resources),
+            sha256_hex(b"new"),
+        );
+
+        let mut content = HashMap::new();
+        content.insert(MANIFEST_URL.to_string(), manifest.as_bytes().to_vec());
+        content.insert(
+            format!("{}.sig", MANIFEST_URL),
+            keypair().sign(manifest.as_bytes()).to_bytes().to_vec(),
+        );
+        content.insert(
+            "https://example.com/app/app.pypacked.delta".to_string(),
+            create_delta(&old_resources, &new_resources).unwrap(),
+        );
+        content.insert(
+            "https://example.com/app/new.txt".to_string(),
+            b"new".to_vec(),
+        );
+
+        let mut fetched = vec![];
+        let mut fetch = |url: &str| {
+            fetched.push(url.to_string());
+            content
+                .get(url)
+                .cloned()
+                .ok_or_else(|| format!("{} not found", url))
+        };
+
+        let mut config = SelfUpdateConfig {
+            manifest_url: MANIFEST_URL.to_string(),
+            public_key: public_key_hex(),
+            version: "1.1".to_string(),
+            staging_directory: origin.join(".update"),
+        };
+
+        assert_eq!(Updater::new(&config, &origin).check(&mut fetch)?, None);
+
+        // Older versions aren't updates.
+        config.version = "1.10".to_string();
+        assert_eq!(Updater::new(&config, &origin).check(&mut fetch)?, None);
+
+        config.version = "1.0".to_string();
+        let updater = Updater::new(&config, &origin);
+        let manifest = updater.check(&mut fetch)?.unwrap();
+        assert_eq!(manifest.version, "1.1");
+        assert_eq!(updater.staged_version(), None);
+
+        updater.stage(&manifest, &mut fetch)?;
+        assert_eq!(updater.staged_version(), Some("1.1".to_string()));
+
+        // The unchanged binary isn't downloaded and the resources are
+        // downloaded as a delta.
+        assert!(!fetched.contains(&"https://example.com/app/app".to_string()));
+        assert!(!fetched.contains(&"https://example.com/app/app.pypacked".to_string()));
+
+        // Nothing is installed before the next start.
+        assert_eq!(
+            std::fs::read(origin.join("app.pypacked")).unwrap(),
+            old_resources
+        );
+
+        assert_eq!(
+            install_staged_update(&config.staging_directory, &origin).unwrap(),
+            Some("1.1".to_string())
+        );
+        assert_eq!(
+            std::fs::read(origin.join("app.pypacked")).unwrap(),
+            new_resources
+        );
+        assert_eq!(std::fs::read(origin.join("lib/new.txt")).unwrap(), b"new");
+        assert!(!config.staging_directory.exists());
+
+        assert_eq!(
+            install_staged_update(&config.staging_directory, &origin).unwrap(),
+            None
+        );
+
+        // A manifest signed by another key is refused.
+        content.insert(format!("{}.sig", MANIFEST_URL), vec![0; 64]);
+        let mut fetch = |url: &str| {
+            content
+                .get(url)
+                .cloned()
+                .ok_or_else(|| format!("{} not found", url))
+        };
+        assert_eq!(
+            Updater::new(&config, &origin).check(&mut fetch),
+            Err("error verifying update manifest: signature verification failed".to_string())
+        );
+
+        Ok(())
+    }
+    #[test]
+    fn test_install_staged_update_rollback() {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyembed-test")
+            .tempdir()
+            .unwrap();
+        let origin = temp_dir.path().join("app");
+        let staging = origin.join(".update");
+        std::fs::create_dir_all(staging.join(FILES_DIRECTORY)).unwrap();
+
+        std::fs::write(origin.join("app"), b"old").unwrap();
+        std::fs::write(origin.join("data"), b"data").unwrap();
+        std::fs::write(staging.join(FILES_DIRECTORY).join("app"), b"new").unwrap();
+        std::fs::write(staging.join(FILES_DIRECTORY).join("new.txt"), b"new").unwrap();
+        // The parent directory of data/file can't be created because data is a file.
+        std::fs::create_dir_all(staging.join(FILES_DIRECTORY).join("data")).unwrap();
+        std::fs::write(staging.join(FILES_DIRECTORY).join("data/file"), b"new").unwrap();
+        std::fs::write(
+            staging.join(READY_FILENAME),
+            "version 1.1\napp\nnew.txt\ndata/file\n",
+        )
+        .unwrap();
+
+        let err = install_staged_update(&staging, &origin).unwrap_err();
+        assert!(err.to_string().ends_with("the update was rolled back"));
+
+        assert_eq!(std::fs::read(origin.join("app")).unwrap(), b"old");
+        assert_eq!(std::fs::read(origin.join("data")).unwrap(), b"data");
+        assert!(!origin.join("new.txt").exists());
+        assert!(!backup_path(&origin.join("app")).exists());
+        assert!(!staging.exists());
+
+        assert_eq!(install_staged_update(&staging, &origin).unwrap(), None);
+    }
+}
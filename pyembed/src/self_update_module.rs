@@ -0,0 +1,132 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+The `oxidized_importer.updates` Python module.
+
+Exposes [crate::self_update] to Python. URLs are fetched with
+`urllib.request`, so no HTTP client is compiled into the binary.
+*/
+
+use {
+    crate::self_update::{Fetch, Updater},
+    cpython::{
+        exc::RuntimeError, py_fn, NoArgs, ObjectProtocol, PyBytes, PyErr, PyModule, PyResult,
+        Python,
+    },
+    once_cell::sync::Lazy,
+    python_packaging::interpreter::SelfUpdateConfig,
+    std::{path::PathBuf, sync::Mutex},
+};
+
+/// Name of the module.
+const MODULE_NAME: &str = "oxidized_importer.updates";
+
+/// The self update configuration of the main interpreter and `$ORIGIN`.
+static CONFIG: Lazy<Mutex<Option<(SelfUpdateConfig, PathBuf)>>> = Lazy::new(|| Mutex::new(None));
+
+/// Define the configuration used by the module.
+pub(crate) fn set_self_update_config(config: SelfUpdateConfig, origin: PathBuf) {
+    *CONFIG.lock().unwrap() = Some((config, origin));
+}
+
+fn runtime_error(py: Python, message: String) -> PyErr {
+    PyErr::new::<RuntimeError, _>(py, message)
+}
+
+fn config(py: Python) -> PyResult<(SelfUpdateConfig, PathBuf)> {
+    // The lock isn't held while running Python code, which could release
+    // the GIL and let another thread wait for the lock while holding it.
+    CONFIG
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| runtime_error(py, "self update is not configured".to_string()))
+}
+
+/// Fetch the content of a URL with `urllib.request`.
+///
+/// `urllib` is the imported `urllib.request` module.
+pub(crate) fn fetch_url(py: Python, urllib: &PyModule, url: &str) -> Result<Vec<u8>, String> {
+    let fetch = || -> PyResult<Vec<u8>> {
+        let response = urllib.call(py, "urlopen", (url,), None)?;
+        let data = response.call_method(py, "read", NoArgs, None)?;
+        response.call_method(py, "close", NoArgs, None)?;
+
+        Ok(data.cast_into::<PyBytes>(py)?.data(py).to_vec())
+    };
+
+    fetch().map_err(|mut e| {
+        format!(
+            "error fetching {}: {}",
+            url,
+            e.instance(py)
+                .str(py)
+                .map(|s| s.to_string_lossy(py).to_string())
+                .unwrap_or_default()
+        )
+    })
+}
+
+/// Run a function with an [Updater] for the configured application.
+fn with_updater<T>(
+    py: Python,
+    f: impl FnOnce(&Updater, &mut Fetch) -> Result<T, String>,
+) -> PyResult<T> {
+    let (config, origin) = config(py)?;
+
+    let urllib = py.import("urllib.request")?;
+    let mut fetch = |url: &str| fetch_url(py, &urllib, url);
+
+    f(&Updater::new(&config, &origin), &mut fetch).map_err(|e| runtime_error(py, e))
+}
+
+/// Obtain the version of the running application.
+fn current_version(py: Python) -> PyResult<String> {
+    Ok(config(py)?.0.version)
+}
+
+/// Obtain the version of an available update, if any.
+fn check(py: Python) -> PyResult<Option<String>> {
+    with_updater(py, |updater, fetch| {
+        Ok(updater.check(fetch)?.map(|manifest| manifest.version))
+    })
+}
+
+/// Download an available update so it is installed on the next start.
+///
+/// Returns the version of the update, if any.
+fn download(py: Python) -> PyResult<Option<String>> {
+    with_updater(py, |updater, fetch| match updater.check(fetch)? {
+        Some(manifest) => {
+            updater.stage(&manifest, fetch)?;
+            Ok(Some(manifest.version))
+        }
+        None => Ok(None),
+    })
+}
+
+/// Obtain the version of the update installed on the next start, if any.
+fn staged_version(py: Python) -> PyResult<Option<String>> {
+    with_updater(py, |updater, _| Ok(updater.staged_version()))
+}
+
+/// Create the `oxidized_importer.updates` module and register it on `m`.
+pub(crate) fn module_init(py: Python, m: &PyModule) -> PyResult<()> {
+    let updates = PyModule::new(py, MODULE_NAME)?;
+
+    updates.add(py, "check", py_fn!(py, check()))?;
+    updates.add(py, "current_version", py_fn!(py, current_version()))?;
+    updates.add(py, "download", py_fn!(py, download()))?;
+    updates.add(py, "staged_version", py_fn!(py, staged_version()))?;
+
+    // Registering in `sys.modules` allows `import oxidized_importer.updates`.
+    py.import("sys")?
+        .get(py, "modules")?
+        .set_item(py, MODULE_NAME, &updates)?;
+
+    m.add(py, "updates", updates)?;
+
+    Ok(())
+}
@@ -0,0 +1,259 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Measure how long the phases of interpreter initialization take.
+
+Timings are always recorded and are available from
+`MainPythonInterpreter::startup_timings()`. If the environment variable
+defined by [STARTUP_TIMINGS_ENV] is set, each phase is also reported as it
+ends. Lines are written and flushed one at a time, so the phases which
+completed are reported even if initialization fails or the process crashes.
+*/
+
+use {
+    crate::importer_debug::open_output,
+    std::{
+        fmt::{Display, Formatter},
+        io::Write,
+        time::{Duration, Instant},
+    },
+};
+
+/// Environment variable enabling the startup timings report.
+///
+/// If the value is `stderr`, the report is written to stderr. Otherwise it
+/// is the path of a file the report is appended to.
+pub const STARTUP_TIMINGS_ENV: &str = "PYEMBED_STARTUP_TIMINGS";
+
+/// A phase of interpreter initialization.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StartupPhase {
+    /// Process setup and `Py_PreInitialize()`.
+    PreInit,
+    /// `Py_InitializeFromConfig()`, which initializes the core of Python.
+    CoreInit,
+    /// Indexing resources and installing our meta path importer.
+    ImporterSetup,
+    /// `_Py_InitializeMain()`, which initializes importlib and `site`.
+    MainInit,
+    /// Applying the remaining configuration, e.g. `sys` attributes.
+    PostConfig,
+}
+
+impl Display for StartupPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::PreInit => "pre-init",
+            Self::CoreInit => "core-init",
+            Self::ImporterSetup => "importer-setup",
+            Self::MainInit => "main-init",
+            Self::PostConfig => "post-config",
+        })
+    }
+}
+
+/// How long each phase of interpreter initialization took.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StartupTimings {
+    phases: Vec<(StartupPhase, Duration)>,
+}
+
+impl StartupTimings {
+    /// Phases in the order they ran and how long they took.
+    pub fn phases(&self) -> &[(StartupPhase, Duration)] {
+        &self.phases
+    }
+
+    /// How long a phase took.
+    ///
+    /// Returns `None` if the phase didn't run.
+    pub fn get(&self, phase: StartupPhase) -> Option<Duration> {
+        self.phases
+            .iter()
+            .find(|(p, _)| *p == phase)
+            .map(|(_, duration)| *duration)
+    }
+
+    /// How long initialization took.
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|(_, duration)| *duration).sum()
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.3} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Open the destination of the report defined by the environment.
+fn report_sink_from_env() -> Option<Box<dyn Write>> {
+    let value = std::env::var_os(STARTUP_TIMINGS_ENV)?;
+
+    if value.is_empty() {
+        return None;
+    }
+
+    match open_output(&value) {
+        Ok(output) => Some(output),
+        Err(msg) => {
+            eprintln!("error opening startup timings report: {}", msg);
+            None
+        }
+    }
+}
+
+/// Times phases of interpreter initialization.
+///
+/// If dropped while a phase is running, the phase is reported as failed.
+pub(crate) struct StartupTimer {
+    timings: StartupTimings,
+    current: Option<(StartupPhase, Instant)>,
+    sink: Option<Box<dyn Write>>,
+}
+
+impl StartupTimer {
+    /// Create an instance reporting to the destination defined by the
+    /// environment, if any.
+    pub fn from_env() -> Self {
+        Self::with_sink(report_sink_from_env())
+    }
+
+    fn with_sink(sink: Option<Box<dyn Write>>) -> Self {
+        Self {
+            timings: StartupTimings::default(),
+            current: None,
+            sink,
+        }
+    }
+
+    fn report(&mut self, line: String) {
+        if let Some(sink) = &mut self.sink {
+            // Errors are ignored. Reporting must not break initialization.
+            let _ = writeln!(sink, "pyembed startup: {}", line);
+            let _ = sink.flush();
+        }
+    }
+
+    fn end_at(&mut self, now: Instant) {
+        if let Some((phase, start)) = self.current.take() {
+            let duration = now.saturating_duration_since(start);
+            self.timings.phases.push((phase, duration));
+            self.report(format!("{} {}", phase, format_duration(duration)));
+        }
+    }
+
+    fn begin_at(&mut self, phase: StartupPhase, now: Instant) {
+        self.end_at(now);
+        self.current = Some((phase, now));
+    }
+
+    /// End the running phase, if any, and begin another.
+    pub fn begin(&mut self, phase: StartupPhase) {
+        self.begin_at(phase, Instant::now());
+    }
+
+    /// End the running phase and obtain the recorded timings.
+    pub fn finish(mut self) -> StartupTimings {
+        self.end_at(Instant::now());
+
+        let total = self.timings.total();
+        self.report(format!("total {}", format_duration(total)));
+
+        std::mem::take(&mut self.timings)
+    }
+}
+
+impl Drop for StartupTimer {
+    fn drop(&mut self) {
+        if let Some((phase, start)) = self.current.take() {
+            let duration = Instant::now().saturating_duration_since(start);
+            self.report(format!(
+                "{} failed after {}",
+                phase,
+                format_duration(duration)
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{cell::RefCell, rc::Rc},
+    };
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.borrow().clone())
+                .unwrap()
+                .lines()
+                .map(|l| l.to_string())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_timer() {
+        let buffer = SharedBuffer::default();
+        let mut timer = StartupTimer::with_sink(Some(Box::new(buffer.clone())));
+
+        let start = Instant::now();
+        timer.begin_at(StartupPhase::PreInit, start);
+        timer.begin_at(StartupPhase::CoreInit, start + Duration::from_micros(1500));
+        timer.end_at(start + Duration::from_millis(4));
+
+        assert_eq!(
+            buffer.lines(),
+            vec![
+                "pyembed startup: pre-init 1.500 ms",
+                "pyembed startup: core-init 2.500 ms"
+            ]
+        );
+
+        let timings = timer.finish();
+        assert_eq!(
+            timings.phases(),
+            &[
+                (StartupPhase::PreInit, Duration::from_micros(1500)),
+                (StartupPhase::CoreInit, Duration::from_micros(2500)),
+            ]
+        );
+        assert_eq!(
+            timings.get(StartupPhase::CoreInit),
+            Some(Duration::from_micros(2500))
+        );
+        assert_eq!(timings.get(StartupPhase::MainInit), None);
+        assert_eq!(timings.total(), Duration::from_millis(4));
+        assert_eq!(buffer.lines()[2], "pyembed startup: total 4.000 ms");
+    }
+
+    #[test]
+    fn test_timer_failure() {
+        let buffer = SharedBuffer::default();
+        let mut timer = StartupTimer::with_sink(Some(Box::new(buffer.clone())));
+
+        timer.begin(StartupPhase::PreInit);
+        timer.begin(StartupPhase::CoreInit);
+        drop(timer);
+
+        let lines = buffer.lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("pyembed startup: pre-init "));
+        assert!(lines[1].starts_with("pyembed startup: core-init failed after "));
+    }
+}
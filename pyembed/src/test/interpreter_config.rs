@@ -4,7 +4,7 @@
 
 use {
     crate::{MainPythonInterpreter, OxidizedPythonInterpreterConfig},
-    cpython::{ObjectProtocol, PyBytes, PyList, PyObject, PyString, PyStringData},
+    cpython::{NoArgs, ObjectProtocol, PyBytes, PyList, PyObject, PyString, PyStringData},
     python3_sys as pyffi,
     python_packaging::{
         interpreter::{BytesWarning, MemoryAllocatorBackend, PythonInterpreterProfile},
@@ -575,6 +575,65 @@ rusty_fork_test! {
         assert_eq!(config.tcl_library, Some(origin.join("lib").join("tcl8.6")));
     }
 
+    #[test]
+    fn test_build_info() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        // Otherwise the Rust arguments are interpreted as Python arguments.
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.build_info.insert(
+            "target_triple".to_string(),
+            "x86_64-unknown-linux-gnu".to_string(),
+        );
+
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        assert_eq!(
+            interp.build_info().get("target_triple"),
+            Some(&"x86_64-unknown-linux-gnu".to_string())
+        );
+
+        let py = interp.acquire_gil();
+        let oxidized_importer = py.import("oxidized_importer").unwrap();
+
+        let info = oxidized_importer.call(py, "build_info", NoArgs, None).unwrap();
+        let value = info
+            .get_item(py, "target_triple")
+            .unwrap()
+            .extract::<String>(py)
+            .unwrap();
+        assert_eq!(value, "x86_64-unknown-linux-gnu");
+    }
+
+    #[test]
+    fn test_run_report() {
+        let dir = std::env::temp_dir().join("pyembed-test-run-report");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::env::set_var("PYEMBED_TEST_RUN_REPORT_DIR", &dir);
+        std::env::set_var("PYEMBED_RUN_REPORT_SECTIONS", "modules,import_graph");
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        // Otherwise the Rust arguments are interpreted as Python arguments.
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.run_report_directory_env = Some("PYEMBED_TEST_RUN_REPORT_DIR".to_string());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        std::mem::drop(interp);
+
+        let paths = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(paths.len(), 1);
+
+        let report = std::fs::read_to_string(&paths[0]).unwrap();
+        assert!(report.contains("\"modules\": ["));
+        assert!(report.contains("\"sys\""));
+        assert!(report.contains("\"import_graph\": {"));
+        assert!(!report.contains("\"import_times\""));
+    }
+
     #[test]
     fn test_dev_mode() {
         let mut config = OxidizedPythonInterpreterConfig::default();
@@ -626,6 +685,46 @@ rusty_fork_test! {
         assert_eq!(flags.getattr(py, "utf8_mode").unwrap().extract::<i64>(py).unwrap(), 1);
     }
 
+    #[test]
+    fn test_stdio_encoding_errors() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        // Otherwise the Rust arguments are interpreted as Python arguments.
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.interpreter_config.stdio_encoding = Some("latin-1".to_string());
+        config.interpreter_config.stdio_errors = Some("backslashreplace".to_string());
+
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        let py = interp.acquire_gil();
+        let sys = py.import("sys").unwrap();
+
+        for name in &["stdout", "stderr"] {
+            let stream = sys.get(py, name).unwrap();
+            assert_eq!(stream.getattr(py, "encoding").unwrap().extract::<String>(py).unwrap(), "latin-1");
+            assert_eq!(stream.getattr(py, "errors").unwrap().extract::<String>(py).unwrap(), "backslashreplace");
+        }
+    }
+
+    #[test]
+    fn test_filesystem_encoding_errors() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        // Otherwise the Rust arguments are interpreted as Python arguments.
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.interpreter_config.utf8_mode = Some(true);
+        config.interpreter_config.filesystem_encoding = Some("utf-8".to_string());
+        config.interpreter_config.filesystem_errors = Some("strict".to_string());
+
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        let py = interp.acquire_gil();
+        let sys = py.import("sys").unwrap();
+
+        assert_eq!(sys.call(py, "getfilesystemencoding", NoArgs, None).unwrap().extract::<String>(py).unwrap(), "utf-8");
+        assert_eq!(sys.call(py, "getfilesystemencodeerrors", NoArgs, None).unwrap().extract::<String>(py).unwrap(), "strict");
+    }
+
     #[test]
     fn test_bytes_warning_warn() {
         let mut config = OxidizedPythonInterpreterConfig::default();
@@ -3,10 +3,231 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::{MainPythonInterpreter, OxidizedPythonInterpreterConfig},
+    crate::{
+        xor_resource_payload, AsyncioError, AuditEvent, DispatchError, ExtensionModule,
+        MainPythonInterpreter, OxidizedPythonInterpreterConfig, PackedResourcesSource, PythonCall,
+        PythonWorkerPool,
+    },
+    cpython::{ObjectProtocol, PyBytes, PyObject},
+    once_cell::sync::Lazy,
+    python3_sys as pyffi,
+    python_packed_resources::{data::Resource, writer::write_packed_resources_v3},
     rusty_fork::rusty_fork_test,
+    std::{
+        borrow::Cow,
+        collections::HashMap,
+        ffi::CString,
+        future::Future,
+        path::PathBuf,
+        pin::Pin,
+        sync::Mutex,
+        task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+        time::Duration,
+    },
 };
 
+const PAYLOAD_KEY: &[u8] = b"secret";
+
+/// Registers a `coroutines` module defining coroutine functions.
+const COROUTINES_MODULE: &str = r#"
+import asyncio, sys, types
+
+async def answer():
+    await asyncio.sleep(0)
+    await asyncio.sleep(0)
+    return 42
+
+async def fail():
+    raise ValueError("boom")
+
+async def forever():
+    await asyncio.Event().wait()
+
+module = types.ModuleType("coroutines")
+module.answer = answer
+module.fail = fail
+module.forever = forever
+sys.modules["coroutines"] = module
+"#;
+
+static mut SINGLE_PHASE_MODULE_DEF: pyffi::PyModuleDef = pyffi::PyModuleDef {
+    m_base: pyffi::PyModuleDef_HEAD_INIT,
+    m_name: b"single_phase\0" as *const u8 as *const _,
+    m_doc: std::ptr::null(),
+    m_size: -1,
+    m_methods: 0 as *mut _,
+    m_slots: 0 as *mut _,
+    m_traverse: None,
+    m_clear: None,
+    m_free: None,
+};
+
+/// Audit events seen by [record_audit_event].
+static AUDIT_EVENTS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(vec![]));
+
+/// Audit hook recording events and denying `pyembed.denied`.
+fn record_audit_event(event: AuditEvent) -> Result<(), String> {
+    let gil = cpython::Python::acquire_gil();
+    let args = event.args.repr(gil.python()).unwrap();
+
+    AUDIT_EVENTS.lock().unwrap().push(format!(
+        "{} {}",
+        event.name,
+        args.to_string_lossy(gil.python())
+    ));
+
+    if event.name == "pyembed.denied" {
+        Err("denied".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// Initialization function returning a fully constructed module.
+#[allow(non_snake_case)]
+unsafe extern "C" fn PyInit_single_phase() -> *mut pyffi::PyObject {
+    let module = pyffi::PyModule_Create(&mut SINGLE_PHASE_MODULE_DEF);
+
+    if !module.is_null() {
+        pyffi::PyModule_AddIntConstant(module, b"VALUE\0".as_ptr() as *const _, 1);
+    }
+
+    module
+}
+
+unsafe extern "C" fn multi_phase_exec(module: *mut pyffi::PyObject) -> i32 {
+    pyffi::PyModule_AddIntConstant(module, b"VALUE\0".as_ptr() as *const _, 2)
+}
+
+static mut MULTI_PHASE_MODULE_SLOTS: [pyffi::PyModuleDef_Slot; 2] = [
+    pyffi::PyModuleDef_Slot {
+        slot: pyffi::Py_mod_exec,
+        value: 0 as *mut _,
+    },
+    pyffi::PyModuleDef_Slot {
+        slot: 0,
+        value: 0 as *mut _,
+    },
+];
+
+static mut MULTI_PHASE_MODULE_DEF: pyffi::PyModuleDef = pyffi::PyModuleDef {
+    m_base: pyffi::PyModuleDef_HEAD_INIT,
+    m_name: b"multi_phase\0" as *const u8 as *const _,
+    m_doc: std::ptr::null(),
+    m_size: 0,
+    m_methods: 0 as *mut _,
+    m_slots: 0 as *mut _,
+    m_traverse: None,
+    m_clear: None,
+    m_free: None,
+};
+
+/// Initialization function returning a `PyModuleDef` with `Py_mod_exec`.
+#[allow(non_snake_case)]
+unsafe extern "C" fn PyInit_multi_phase() -> *mut pyffi::PyObject {
+    MULTI_PHASE_MODULE_SLOTS[0].value = multi_phase_exec as *mut _;
+    MULTI_PHASE_MODULE_DEF.m_slots = MULTI_PHASE_MODULE_SLOTS.as_mut_ptr();
+
+    pyffi::PyModuleDef_Init(&mut MULTI_PHASE_MODULE_DEF)
+}
+
+/// Build a gettext message catalog translating `original` to `translation`.
+fn gettext_catalog(original: &str, translation: &str) -> Vec<u8> {
+    // Header, 1 original string descriptor, 1 translation descriptor.
+    let strings_offset = 28 + 8 + 8;
+    let translation_offset = strings_offset + original.len() + 1;
+
+    let mut data = vec![];
+    for value in &[
+        0x9504_12de,
+        0,
+        1,
+        28,
+        36,
+        0,
+        0,
+        original.len(),
+        strings_offset,
+        translation.len(),
+        translation_offset,
+    ] {
+        data.extend_from_slice(&(*value as u32).to_le_bytes());
+    }
+
+    data.extend_from_slice(original.as_bytes());
+    data.push(0);
+    data.extend_from_slice(translation.as_bytes());
+    data.push(0);
+
+    data
+}
+
+fn extension_modules_config<'a>(oxidized_importer: bool) -> OxidizedPythonInterpreterConfig<'a> {
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.interpreter_config.parse_argv = Some(false);
+    config.set_missing_path_configuration = false;
+    config.oxidized_importer = oxidized_importer;
+    config.extra_extension_modules = Some(vec![
+        ExtensionModule {
+            name: CString::new("single_phase").unwrap(),
+            init_func: PyInit_single_phase,
+        },
+        ExtensionModule {
+            name: CString::new("multi_phase").unwrap(),
+            init_func: PyInit_multi_phase,
+        },
+    ]);
+
+    config
+}
+
+fn assert_extension_modules_importable(config: OxidizedPythonInterpreterConfig) {
+    let mut interp = MainPythonInterpreter::new(config).unwrap();
+    let py = interp.acquire_gil();
+
+    let sys = py.import("sys").unwrap();
+    let builtin_module_names = sys.get(py, "builtin_module_names").unwrap();
+
+    for (name, value) in &[("single_phase", 1), ("multi_phase", 2)] {
+        assert!(builtin_module_names
+            .call_method(py, "__contains__", (*name,), None)
+            .unwrap()
+            .extract::<bool>(py)
+            .unwrap());
+
+        let module = py.import(name).unwrap();
+        assert_eq!(
+            module.get(py, "VALUE").unwrap().extract::<i32>(py).unwrap(),
+            *value
+        );
+        assert_eq!(
+            module
+                .get(py, "__spec__")
+                .unwrap()
+                .getattr(py, "origin")
+                .unwrap()
+                .extract::<String>(py)
+                .unwrap(),
+            "built-in"
+        );
+    }
+}
+
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    unsafe { Waker::from_raw(clone(std::ptr::null())) }
+}
+
+fn decode_payload(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    Ok(xor_resource_payload(&data, PAYLOAD_KEY))
+}
+
 rusty_fork_test! {
     #[test]
     fn test_instantiate_interpreter() {
@@ -17,4 +238,573 @@ rusty_fork_test! {
         let py = interp.acquire_gil();
         py.import("sys").unwrap();
     }
+
+    #[test]
+    fn test_extra_extension_modules() {
+        assert_extension_modules_importable(extension_modules_config(false));
+    }
+
+    #[test]
+    fn test_extra_extension_modules_oxidized_importer() {
+        assert_extension_modules_importable(extension_modules_config(true));
+    }
+
+    #[test]
+    fn test_register_extension_module() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.oxidized_importer = true;
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp
+            .register_extension_module(ExtensionModule {
+                name: CString::new("single_phase").unwrap(),
+                init_func: PyInit_single_phase,
+            })
+            .unwrap();
+        assert!(interp
+            .register_extension_module(ExtensionModule {
+                name: CString::new("single_phase").unwrap(),
+                init_func: PyInit_single_phase,
+            })
+            .is_err());
+
+        let py = interp.acquire_gil();
+
+        // The capsule-based Python API registers the same way.
+        let capsule = unsafe {
+            PyObject::from_owned_ptr(
+                py,
+                pyffi::PyCapsule_New(PyInit_multi_phase as *mut _, std::ptr::null(), None),
+            )
+        };
+        let oxidized_importer = py.import("oxidized_importer").unwrap();
+        oxidized_importer
+            .call(py, "register_builtin_extension", ("multi_phase", capsule), None)
+            .unwrap();
+
+        for (name, value) in &[("single_phase", 1), ("multi_phase", 2)] {
+            let module = py.import(name).unwrap();
+            assert_eq!(
+                module.get(py, "VALUE").unwrap().extract::<i32>(py).unwrap(),
+                *value
+            );
+        }
+    }
+
+    #[test]
+    fn test_resource_payload_decoder() {
+        let source = b"VALUE = 42\n";
+        let mut data = vec![];
+        write_packed_resources_v3(
+            &[Resource {
+                is_module: true,
+                name: Cow::Borrowed("encoded_module"),
+                in_memory_source: Some(Cow::Owned(xor_resource_payload(source, PAYLOAD_KEY))),
+                ..Resource::default()
+            }],
+            &mut data,
+            None,
+        )
+        .unwrap();
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.oxidized_importer = true;
+        config.packed_resources = vec![PackedResourcesSource::Memory(&data)];
+        config.resource_payload_decoder = Some(decode_payload);
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+        let py = interp.acquire_gil();
+
+        let module = py.import("encoded_module").unwrap();
+        assert_eq!(
+            module.get(py, "VALUE").unwrap().extract::<i32>(py).unwrap(),
+            42
+        );
+
+        let loader = module.get(py, "__loader__").unwrap();
+        assert_eq!(
+            loader
+                .call_method(py, "get_source", ("encoded_module",), None)
+                .unwrap()
+                .extract::<String>(py)
+                .unwrap(),
+            "VALUE = 42\n"
+        );
+    }
+
+    #[test]
+    fn test_worker_pool() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        let (add_args, sqrt_args, callable) = {
+            let py = interp.acquire_gil();
+            let pickle = py.import("pickle").unwrap();
+
+            let dumps = |code: &str| -> Vec<u8> {
+                let value = py.eval(code, None, None).unwrap();
+                let data = pickle.call(py, "dumps", (value,), None).unwrap();
+                data.cast_as::<PyBytes>(py).unwrap().data(py).to_vec()
+            };
+
+            (
+                dumps("(1, 2)"),
+                dumps("(-1,)"),
+                dumps("__import__('os').getpid"),
+            )
+        };
+
+        let results = {
+            let pool = PythonWorkerPool::new(&mut interp, 2).unwrap();
+            assert_eq!(pool.workers(), 2);
+
+            assert!(pool.submit_function("operator", None).is_err());
+
+            let tasks = (0..4)
+                .map(|_| {
+                    pool.submit_function("operator:add", Some(add_args.clone()))
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+
+            let err = pool
+                .submit_function("math:sqrt", Some(sqrt_args))
+                .unwrap()
+                .wait()
+                .unwrap_err();
+            assert!(err.to_string().contains("ValueError"));
+
+            pool.submit(PythonCall::Pickled {
+                callable,
+                args: None,
+            })
+            .unwrap()
+            .wait()
+            .unwrap();
+
+            tasks
+                .into_iter()
+                .map(|task| task.wait().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let py = interp.acquire_gil();
+        let pickle = py.import("pickle").unwrap();
+        for data in results {
+            let value = pickle
+                .call(py, "loads", (PyBytes::new(py, &data),), None)
+                .unwrap();
+            assert_eq!(value.extract::<i32>(py).unwrap(), 3);
+        }
+    }
+
+    #[test]
+    fn test_asyncio() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        {
+            let py = interp.acquire_gil();
+            py.run(COROUTINES_MODULE, None, None).unwrap();
+        }
+
+        assert!(interp.spawn_coroutine("coroutines", "missing").is_err());
+
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut answer = interp.spawn_coroutine("coroutines", "answer").unwrap();
+        let mut fail = interp.spawn_coroutine("coroutines", "fail").unwrap();
+        let mut forever = interp.spawn_coroutine("coroutines", "forever").unwrap();
+
+        assert!(Pin::new(&mut answer).poll(&mut cx).is_pending());
+
+        let mut steps = 0;
+        let value = loop {
+            interp.run_asyncio_step().unwrap();
+            steps += 1;
+
+            if let Poll::Ready(res) = Pin::new(&mut answer).poll(&mut cx) {
+                break res.unwrap();
+            }
+
+            assert!(steps < 10);
+        };
+        assert!(steps > 1);
+
+        match Pin::new(&mut fail).poll(&mut cx) {
+            Poll::Ready(Err(err)) => assert!(err.to_string().contains("ValueError: boom")),
+            _ => panic!("coroutine should have failed"),
+        }
+
+        assert!(Pin::new(&mut forever).poll(&mut cx).is_pending());
+
+        {
+            let py = interp.acquire_gil();
+            assert_eq!(value.extract::<i32>(py).unwrap(), 42);
+        }
+        drop(value);
+
+        drop(interp);
+        match Pin::new(&mut forever).poll(&mut cx) {
+            Poll::Ready(Err(AsyncioError::Cancelled)) => {}
+            _ => panic!("pending coroutine should be cancelled"),
+        }
+    }
+
+    #[test]
+    fn test_ctypes_libraries() {
+        let exe = std::env::current_exe().unwrap();
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config
+            .ctypes_libraries
+            .insert("bundled".to_string(), exe.clone());
+        config.ctypes_libraries.insert(
+            "missing".to_string(),
+            PathBuf::from("/nonexistent/libmissing.so"),
+        );
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        let py = interp.acquire_gil();
+        let util = py.import("ctypes.util").unwrap();
+
+        let bundled = util
+            .call(py, "find_library", ("bundled",), None)
+            .unwrap()
+            .extract::<String>(py)
+            .unwrap();
+        assert_eq!(PathBuf::from(bundled), exe);
+
+        // Missing bundled libraries fall back to the original implementation.
+        assert!(util
+            .call(py, "find_library", ("missing",), None)
+            .unwrap()
+            .is_none(py));
+    }
+
+    #[test]
+    fn test_sys_oxidized_config() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.origin = Some(PathBuf::from("/origin"));
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        let summary = interp.config_summary();
+        assert_eq!(summary.run_mode, "repl");
+        assert!(summary.packed_resources.is_empty());
+
+        let py = interp.acquire_gil();
+        let value = py
+            .import("sys")
+            .unwrap()
+            .get(py, "_oxidized_config")
+            .unwrap();
+        assert_eq!(
+            value
+                .get_item(py, "run_mode")
+                .unwrap()
+                .extract::<String>(py)
+                .unwrap(),
+            "repl"
+        );
+        assert!(value
+            .get_item(py, "filesystem_importer")
+            .unwrap()
+            .extract::<bool>(py)
+            .unwrap());
+        assert_eq!(
+            value
+                .get_item(py, "origin")
+                .unwrap()
+                .extract::<String>(py)
+                .unwrap(),
+            "/origin"
+        );
+
+        // The mapping is read-only.
+        assert!(value.set_item(py, "run_mode", "module").is_err());
+    }
+
+    #[test]
+    fn test_restricted_mode() {
+        let mut restricted = crate::RestrictedModeConfig::default();
+        restricted
+            .allowed_facilities
+            .insert(crate::RestrictedFacility::Socket);
+        restricted.denied_modules.push("json".to_string());
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.restricted_mode = Some(restricted);
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        let py = interp.acquire_gil();
+
+        let err = py.import("ctypes").err().unwrap();
+        assert!(err.matches(py, py.get_type::<cpython::exc::ImportError>()));
+        assert!(py.import("json.decoder").is_err());
+        py.import("socket").unwrap();
+
+        let err = py
+            .run("import os; os.system('exit 0')", None, None)
+            .err()
+            .unwrap();
+        assert!(err.matches(py, py.get_type::<cpython::exc::PermissionError>()));
+    }
+
+    #[test]
+    fn test_audit_hooks() {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyembed-test-")
+            .tempdir()
+            .unwrap();
+        let log_path = temp_dir.path().join("audit.log");
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.audit_hooks.push(record_audit_event);
+        config.audit_events.push("pyembed.".to_string());
+        config.audit_log = Some(log_path.clone());
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        let py = interp.acquire_gil();
+        py.run("import sys; sys.audit('pyembed.event', 42)", None, None).unwrap();
+        assert!(py
+            .run("import sys; sys.audit('pyembed.denied')", None, None)
+            .is_err());
+        py.run("import sys; sys.audit('other.event')", None, None).unwrap();
+
+        assert_eq!(
+            *AUDIT_EVENTS.lock().unwrap(),
+            vec!["pyembed.event (42,)".to_string(), "pyembed.denied ()".to_string()]
+        );
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        let events = log
+            .lines()
+            .map(|line| line.splitn(2, '\t').nth(1).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(events, vec!["pyembed.event\t(42,)", "pyembed.denied\t()"]);
+    }
+
+    #[test]
+    fn test_gettext_catalogs() {
+        let mut resources = HashMap::new();
+        resources.insert(
+            Cow::Borrowed("locale/de/LC_MESSAGES/i18napp.mo"),
+            Cow::Owned(gettext_catalog("hello", "hallo")),
+        );
+
+        let mut data = vec![];
+        write_packed_resources_v3(
+            &[Resource {
+                is_module: true,
+                is_package: true,
+                name: Cow::Borrowed("i18napp"),
+                in_memory_source: Some(Cow::Borrowed(b"")),
+                in_memory_package_resources: Some(resources),
+                ..Resource::default()
+            }],
+            &mut data,
+            None,
+        )
+        .unwrap();
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.oxidized_importer = true;
+        config.packed_resources = vec![PackedResourcesSource::Memory(&data)];
+        config.gettext_catalogs = true;
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+        let py = interp.acquire_gil();
+
+        let value = py
+            .eval(
+                "__import__('gettext').translation('i18napp', languages=['de']).gettext('hello')",
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(value.extract::<String>(py).unwrap(), "hallo");
+
+        // Without a cache directory, in-memory catalogs have no path.
+        let value = py
+            .eval(
+                "__import__('gettext').find('i18napp', languages=['de'])",
+                None,
+                None,
+            )
+            .unwrap();
+        assert!(value.is_none(py));
+
+        let value = py
+            .eval(
+                "__import__('gettext').translation('i18napp', languages=['fr'], fallback=True).gettext('hello')",
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(value.extract::<String>(py).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_dispatcher() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        let dispatcher = interp.dispatcher();
+        let thread = std::thread::spawn(move || {
+            dispatcher.call(|py| {
+                py.eval("1 + 1", None, None)
+                    .unwrap()
+                    .extract::<i64>(py)
+                    .unwrap()
+            })
+        });
+
+        assert_eq!(interp.run_dispatched(Some(Duration::from_secs(30))), 1);
+        assert_eq!(thread.join().unwrap().unwrap(), 2);
+
+        assert_eq!(interp.run_dispatched(None), 0);
+
+        let result = interp
+            .dispatcher()
+            .dispatch(|_| -> i64 { panic!("dispatched panic") })
+            .unwrap();
+        assert!(result.try_wait().is_none());
+        assert_eq!(interp.run_dispatched(None), 1);
+        assert!(matches!(result.wait(), Err(DispatchError::Panicked)));
+
+        let dispatcher = interp.dispatcher();
+        let result = dispatcher.dispatch(|_| 1).unwrap();
+
+        // Dispatch from another thread while the interpreter is dropped.
+        // Closures are either released before finalizing or fail to be
+        // queued.
+        let racing = interp.dispatcher();
+        let thread = std::thread::spawn(move || loop {
+            let res = racing.dispatch(|_| 1);
+
+            if matches!(res, Err(DispatchError::Disconnected)) {
+                break;
+            }
+        });
+
+        drop(interp);
+        thread.join().unwrap();
+        assert!(matches!(result.wait(), Err(DispatchError::Disconnected)));
+        assert!(matches!(
+            dispatcher.dispatch(|_| 1),
+            Err(DispatchError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn test_run_module() {
+        let mut data = vec![];
+        write_packed_resources_v3(
+            &[
+                Resource {
+                    is_module: true,
+                    is_package: true,
+                    name: Cow::Borrowed("app"),
+                    in_memory_source: Some(Cow::Borrowed(b"")),
+                    ..Resource::default()
+                },
+                Resource {
+                    is_module: true,
+                    name: Cow::Borrowed("app.helper"),
+                    in_memory_source: Some(Cow::Borrowed(b"VALUE = 42")),
+                    ..Resource::default()
+                },
+                Resource {
+                    is_module: true,
+                    name: Cow::Borrowed("app.__main__"),
+                    in_memory_source: Some(Cow::Borrowed(b"from .helper import VALUE")),
+                    ..Resource::default()
+                },
+            ],
+            &mut data,
+            None,
+        )
+        .unwrap();
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.oxidized_importer = true;
+        config.packed_resources = vec![PackedResourcesSource::Memory(&data)];
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.run_module("app", true).unwrap();
+
+        let py = interp.acquire_gil();
+        let main = py.import("__main__").unwrap();
+
+        assert_eq!(main.get(py, "VALUE").unwrap().extract::<i64>(py).unwrap(), 42);
+        assert_eq!(
+            main.get(py, "__package__")
+                .unwrap()
+                .extract::<String>(py)
+                .unwrap(),
+            "app"
+        );
+        assert_eq!(
+            py.eval("__import__('__main__').__spec__.name", None, None)
+                .unwrap()
+                .extract::<String>(py)
+                .unwrap(),
+            "app.__main__"
+        );
+
+        // In-memory modules have no origin, so sys.argv[0] is retained.
+        assert!(!py
+            .eval("__import__('sys').argv[0]", None, None)
+            .unwrap()
+            .is_none(py));
+    }
+
+    #[cfg(feature = "c-api")]
+    #[test]
+    fn test_c_api() {
+        use crate::c_api::*;
+        use std::ffi::{CStr, CString};
+
+        unsafe {
+            let config = CString::new(r#"{"profile": "isolated"}"#).unwrap();
+            let interp = pyembed_new_interpreter(config.as_ptr());
+            assert!(!interp.is_null());
+
+            let code = CString::new("1 + 41").unwrap();
+            let result = pyembed_eval(interp, code.as_ptr());
+            assert_eq!(CStr::from_ptr(result).to_str().unwrap(), "42");
+            pyembed_free_string(result);
+
+            let code = CString::new("1 / 0").unwrap();
+            assert!(pyembed_eval(interp, code.as_ptr()).is_null());
+            let error = CStr::from_ptr(pyembed_last_error()).to_str().unwrap();
+            assert!(error.contains("ZeroDivisionError"));
+
+            pyembed_free_interpreter(interp);
+
+            let config = CString::new(r#"{"unknown": true}"#).unwrap();
+            assert!(pyembed_new_interpreter(config.as_ptr()).is_null());
+        }
+    }
 }
@@ -47,6 +47,14 @@ class TestImporterBuiltins(unittest.TestCase):
         with self.assertRaises(ImportError):
             importer.get_filename("_io")
 
+    def test_register_builtin_extension_not_capsule(self):
+        import oxidized_importer
+
+        with self.assertRaisesRegex(TypeError, "must be a capsule"):
+            oxidized_importer.register_builtin_extension("foo", None)
+
+        self.assertNotIn("foo", sys.builtin_module_names)
+
 
 if __name__ == "__main__":
     unittest.main()
@@ -31,6 +31,16 @@ class TestImporterConstruction(unittest.TestCase):
             f = OxidizedFinder()
             f.index_bytes(b"\xde\xad\xbe\xef\xaa\xaa\xaa\xaa")
 
+    def test_resources_unsupported_version(self):
+        with self.assertRaisesRegex(ValueError, "format version 5 is not supported"):
+            f = OxidizedFinder()
+            f.index_bytes(b"pyembed\x05\x05")
+
+    def test_resources_truncated(self):
+        with self.assertRaisesRegex(ValueError, "malformed data at offset 9"):
+            f = OxidizedFinder()
+            f.index_bytes(b"pyembed\x03\x00")
+
     def test_no_indices(self):
         f = OxidizedFinder()
         f.index_bytes(b"pyembed\x03\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00")
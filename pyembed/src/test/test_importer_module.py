@@ -13,6 +13,7 @@ class TestImporterModule(unittest.TestCase):
         self.assertEqual(
             attrs,
             {
+                "build_info",
                 "decode_source",
                 "find_resources_in_path",
                 "OxidizedDistribution",
@@ -25,8 +26,37 @@ class TestImporterModule(unittest.TestCase):
                 "PythonModuleSource",
                 "PythonPackageDistributionResource",
                 "PythonPackageResource",
+                "read_packed_file",
+                "register_builtin_extension",
             },
         )
+        self.assertEqual(set(importer.__all__), attrs)
+        self.assertEqual(importer.__all__, sorted(importer.__all__))
+
+    def test_build_info(self):
+        import oxidized_importer as importer
+
+        info = importer.build_info()
+        self.assertIsInstance(info, dict)
+        for key, value in info.items():
+            self.assertIsInstance(key, str)
+            self.assertIsInstance(value, str)
+
+    def test_type_stubs(self):
+        import oxidized_importer as importer
+
+        stubs = importer.read_packed_file("oxidized_importer.pyi").decode("utf-8")
+
+        for name in importer.__all__:
+            self.assertRegex(stubs, r"\n(class|def) %s\b" % name)
+
+        finder = importer.OxidizedFinder()
+        self.assertEqual(
+            finder.read_packed_file("oxidized_importer.pyi"), stubs.encode("utf-8")
+        )
+        self.assertNotIn(
+            "oxidized_importer.pyi", [r.name for r in finder.indexed_resources()]
+        )
 
     def test_finder_attrs(self):
         from oxidized_importer import OxidizedFinder
@@ -55,7 +85,10 @@ class TestImporterModule(unittest.TestCase):
                 "indexed_resources",
                 "invalidate_caches",
                 "iter_modules",
+                "read_packed_file",
+                "reset_stats",
                 "serialize_indexed_resources",
+                "stats",
             },
         )
 
@@ -62,6 +62,36 @@ class TestImporterModuleLoading(unittest.TestCase):
 
         self.assertIsNone(f.find_spec("my_package", None))
 
+    def test_find_spec_stats(self):
+        self._make_package("my_package")
+
+        f = self._finder_from_td()
+
+        self.assertEqual(
+            f.stats(),
+            {
+                "find_spec_hits": 0,
+                "find_spec_misses": 0,
+                "find_spec_negative_lookups": 0,
+            },
+        )
+
+        self.assertIsNotNone(f.find_spec("my_package", None))
+        self.assertIsNone(f.find_spec("my_package.missing", None))
+        self.assertIsNone(f.find_spec("other_package", None))
+
+        self.assertEqual(
+            f.stats(),
+            {
+                "find_spec_hits": 1,
+                "find_spec_misses": 1,
+                "find_spec_negative_lookups": 1,
+            },
+        )
+
+        f.reset_stats()
+        self.assertEqual(f.stats()["find_spec_hits"], 0)
+
     def test_source_package(self):
         p = self._make_package("my_package")
 
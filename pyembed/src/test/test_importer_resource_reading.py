@@ -13,6 +13,7 @@ from oxidized_importer import (
     OxidizedResourceCollector,
     OxidizedResourceReader,
     find_resources_in_path,
+    read_packed_file,
 )
 
 
@@ -127,6 +128,15 @@ class TestImporterResourceReading(unittest.TestCase):
         self.assertEqual(r.open_resource("child0/a.txt").getvalue(), b"a")
         self.assertEqual(r.open_resource("child1/b.txt").getvalue(), b"b")
 
+    def test_read_packed_file_missing(self):
+        f = OxidizedFinder()
+
+        with self.assertRaises(FileNotFoundError):
+            f.read_packed_file("assets/missing.txt")
+
+        with self.assertRaises(FileNotFoundError):
+            read_packed_file("assets/missing.txt")
+
 
 if __name__ == "__main__":
     unittest.main()
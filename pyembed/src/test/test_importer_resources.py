@@ -5,6 +5,8 @@
 import importlib.machinery
 import marshal
 import pathlib
+import sys
+import traceback
 import unittest
 
 from oxidized_importer import (
@@ -621,6 +623,39 @@ class TestImporterResources(unittest.TestCase):
         self.assertEqual(f.get_source("my_module"), source.decode("utf-8"))
         self.assertEqual(f.get_code("my_module"), code)
 
+    def test_add_resource_bytecode_only_traceback(self):
+        f = OxidizedFinder()
+        resource = OxidizedResource()
+        resource.is_module = True
+        resource.name = "bytecode_only_module"
+
+        source = b"def fail():\n    raise ValueError('bytecode only')\n"
+        code = compile(source, "bytecode_only_module.py", "exec")
+        resource.in_memory_bytecode = marshal.dumps(code)
+
+        f.add_resource(resource)
+
+        self.assertIsNone(f.get_source("bytecode_only_module"))
+
+        old_meta_path = list(sys.meta_path)
+        sys.meta_path.insert(0, f)
+        try:
+            import bytecode_only_module
+
+            with self.assertRaises(ValueError) as cm:
+                bytecode_only_module.fail()
+        finally:
+            sys.meta_path[:] = old_meta_path
+            sys.modules.pop("bytecode_only_module", None)
+
+        # Tracebacks retain file names and line numbers but lack source lines.
+        lines = traceback.format_exception(
+            cm.exception.__class__, cm.exception, cm.exception.__traceback__
+        )
+        self.assertIn('  File "bytecode_only_module.py", line 2, in fail\n', lines)
+        self.assertNotIn("raise ValueError", "".join(lines[:-1]))
+        self.assertEqual(lines[-1], "ValueError: bytecode only\n")
+
     def test_add_resources(self):
         f = OxidizedFinder()
         a = OxidizedResource()
@@ -0,0 +1,349 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Run an embedded Python interpreter as a Windows service.
+
+[run_windows_service()] connects the process to the Service Control Manager
+(SCM). The SCM calls back into a service thread, which initializes the
+interpreter and calls a Python entry point. When the service is stopped,
+another Python entry point is called or `KeyboardInterrupt` is raised in
+the service thread, and the service stops once the entry point returns.
+
+If the process wasn't started by the SCM, the entry point is called in the
+foreground, where Ctrl+C raises `KeyboardInterrupt`. `--install-service` and
+`--uninstall-service` arguments register and unregister the executable as a
+service.
+*/
+
+use {
+    crate::{config::OxidizedPythonInterpreterConfig, interpreter::MainPythonInterpreter},
+    cpython::{exc::KeyboardInterrupt, ObjectProtocol, PyDict, PyObject, PyResult, Python},
+    once_cell::sync::{Lazy, OnceCell},
+    python3_sys as pyffi,
+    std::{
+        ffi::OsString,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex,
+        },
+        time::Duration,
+    },
+    windows_service::{
+        define_windows_service,
+        service::{
+            ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl,
+            ServiceExitCode, ServiceInfo, ServiceStartType, ServiceState, ServiceStatus,
+            ServiceType,
+        },
+        service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle},
+        service_dispatcher,
+        service_manager::{ServiceManager, ServiceManagerAccess},
+    },
+};
+
+/// Error returned by `StartServiceCtrlDispatcher()` if the process wasn't
+/// started by the SCM.
+const ERROR_FAILED_SERVICE_CONTROLLER_CONNECT: i32 = 1063;
+
+/// Python code resolving and calling `module:function` entry points.
+const ENTRY_POINT_SOURCE: &str = r#"import importlib
+import signal
+
+
+def resolve_entry_point(entry_point):
+    module_name, _, attrs = entry_point.partition(":")
+    value = importlib.import_module(module_name)
+    for attr in attrs.split("."):
+        value = getattr(value, attr)
+    return value
+
+
+def run_entry_point(entry_point):
+    # Isolated interpreters don't install a SIGINT handler, which is needed
+    # for PyErr_SetInterrupt() to raise KeyboardInterrupt.
+    signal.signal(signal.SIGINT, signal.default_int_handler)
+    return resolve_entry_point(entry_point)()
+
+
+def call_entry_point(entry_point):
+    return resolve_entry_point(entry_point)()
+"#;
+
+/// Describes how to run an interpreter as a Windows service.
+#[derive(Clone, Debug)]
+pub struct WindowsServiceConfig {
+    /// Name of the service.
+    pub name: String,
+
+    /// Name of the service displayed by user interfaces.
+    pub display_name: String,
+
+    /// Description of the service.
+    pub description: Option<String>,
+
+    /// Python callable running the service, as `module:function`.
+    ///
+    /// The service stops when it returns.
+    pub entry_point: String,
+
+    /// Python callable requesting the service to stop, as `module:function`.
+    ///
+    /// It is called from another thread while `entry_point` runs. If not
+    /// set, `KeyboardInterrupt` is raised in `entry_point` instead.
+    pub stop_entry_point: Option<String>,
+}
+
+struct ServiceDefinition {
+    service: WindowsServiceConfig,
+    config: fn() -> OxidizedPythonInterpreterConfig<'static>,
+}
+
+static SERVICE: OnceCell<ServiceDefinition> = OnceCell::new();
+
+/// Whether the interpreter is initialized and stop requests can call into it.
+static INTERPRETER_READY: AtomicBool = AtomicBool::new(false);
+
+/// Whether the SCM requested the service to stop.
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Held while calling into the interpreter to stop the service, so it isn't
+/// finalized meanwhile.
+static STOP_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn call_python(py: Python, function: &str, entry_point: &str) -> PyResult<PyObject> {
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__builtins__", py.import("builtins")?)?;
+
+    py.run(ENTRY_POINT_SOURCE, Some(&globals), None)?;
+
+    globals
+        .get_item(py, function)
+        .expect("entry point function should be defined")
+        .call(py, (entry_point,), None)
+}
+
+/// Ask the Python code of the service to stop.
+fn request_stop(service: &WindowsServiceConfig) {
+    STOP_REQUESTED.store(true, Ordering::SeqCst);
+
+    let _lock = STOP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    // The service thread checks STOP_REQUESTED before calling the entry
+    // point.
+    if !INTERPRETER_READY.load(Ordering::SeqCst) {
+        return;
+    }
+
+    if let Some(stop_entry_point) = &service.stop_entry_point {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+
+        if let Err(err) = call_python(py, "call_entry_point", stop_entry_point) {
+            err.print(py);
+        }
+    } else {
+        unsafe {
+            pyffi::PyErr_SetInterrupt();
+        }
+    }
+}
+
+fn set_status(
+    handle: &ServiceStatusHandle,
+    state: ServiceState,
+    exit_code: i32,
+) -> windows_service::Result<()> {
+    handle.set_service_status(ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted: if state == ServiceState::Running {
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+        } else {
+            ServiceControlAccept::empty()
+        },
+        exit_code: if exit_code == 0 {
+            ServiceExitCode::Win32(0)
+        } else {
+            ServiceExitCode::ServiceSpecific(exit_code as u32)
+        },
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    })
+}
+
+/// Initialize the interpreter and run the service entry point.
+fn run_service_interpreter(state: &ServiceDefinition) -> i32 {
+    let mut interp = match MainPythonInterpreter::new((state.config)()) {
+        Ok(interp) => interp,
+        Err(msg) => {
+            eprintln!("error instantiating embedded Python interpreter: {}", msg);
+            return 1;
+        }
+    };
+
+    let py = interp.acquire_gil();
+    INTERPRETER_READY.store(true, Ordering::SeqCst);
+
+    let exit_code = if STOP_REQUESTED.load(Ordering::SeqCst) {
+        0
+    } else {
+        match call_python(py, "run_entry_point", &state.service.entry_point) {
+            Ok(_) => 0,
+            Err(err)
+                if STOP_REQUESTED.load(Ordering::SeqCst)
+                    && err.matches(py, py.get_type::<KeyboardInterrupt>()) =>
+            {
+                0
+            }
+            Err(err) => crate::runpy::exit_code_for_err(py, err),
+        }
+    };
+
+    // Wait for a running stop request before the interpreter is finalized.
+    // The GIL is released meanwhile, as the stop request may need it.
+    let lock = py.allow_threads(|| STOP_LOCK.lock().unwrap_or_else(|e| e.into_inner()));
+    INTERPRETER_READY.store(false, Ordering::SeqCst);
+
+    drop(interp);
+    drop(lock);
+
+    exit_code
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    let state = SERVICE.get().expect("service state should be defined");
+
+    let handle =
+        match service_control_handler::register(&state.service.name, move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                request_stop(&state.service);
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        }) {
+            Ok(handle) => handle,
+            Err(e) => {
+                eprintln!("error registering service control handler: {}", e);
+                return;
+            }
+        };
+
+    if let Err(e) = set_status(&handle, ServiceState::Running, 0) {
+        eprintln!("error setting service status: {}", e);
+        return;
+    }
+
+    let exit_code = run_service_interpreter(state);
+
+    if let Err(e) = set_status(&handle, ServiceState::Stopped, exit_code) {
+        eprintln!("error setting service status: {}", e);
+    }
+}
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Register the current executable as a service.
+fn install_service(service: &WindowsServiceConfig) -> windows_service::Result<()> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )?;
+
+    let executable_path = std::env::current_exe().map_err(windows_service::Error::Winapi)?;
+
+    let info = ServiceInfo {
+        name: OsString::from(&service.name),
+        display_name: OsString::from(&service.display_name),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path,
+        launch_arguments: vec![],
+        dependencies: vec![],
+        account_name: None,
+        account_password: None,
+    };
+
+    let created = manager.create_service(&info, ServiceAccess::CHANGE_CONFIG)?;
+
+    if let Some(description) = &service.description {
+        created.set_description(description)?;
+    }
+
+    Ok(())
+}
+
+/// Unregister the service.
+fn uninstall_service(service: &WindowsServiceConfig) -> windows_service::Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+
+    manager
+        .open_service(&service.name, ServiceAccess::DELETE)?
+        .delete()
+}
+
+/// Run the process as a Windows service.
+///
+/// `config` returns the configuration of the interpreter, e.g. the
+/// `default_python_config()` function generated by PyOxidizer.
+///
+/// Returns an exit code for the process. If the first argument is
+/// `--install-service` or `--uninstall-service`, the service is registered
+/// or unregistered instead.
+pub fn run_windows_service(
+    service: WindowsServiceConfig,
+    config: fn() -> OxidizedPythonInterpreterConfig<'static>,
+) -> i32 {
+    match std::env::args_os().nth(1).as_ref().and_then(|a| a.to_str()) {
+        Some("--install-service") => {
+            return match install_service(&service) {
+                Ok(()) => {
+                    println!("installed service {}", service.name);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("error installing service {}: {}", service.name, e);
+                    1
+                }
+            };
+        }
+        Some("--uninstall-service") => {
+            return match uninstall_service(&service) {
+                Ok(()) => {
+                    println!("uninstalled service {}", service.name);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("error uninstalling service {}: {}", service.name, e);
+                    1
+                }
+            };
+        }
+        _ => {}
+    }
+
+    let name = service.name.clone();
+
+    if SERVICE.set(ServiceDefinition { service, config }).is_err() {
+        eprintln!("a Windows service is already running");
+        return 1;
+    }
+
+    match service_dispatcher::start(&name, ffi_service_main) {
+        Ok(()) => 0,
+        Err(windows_service::Error::Winapi(e))
+            if e.raw_os_error() == Some(ERROR_FAILED_SERVICE_CONTROLLER_CONNECT) =>
+        {
+            // Not started by the SCM. Run in the foreground.
+            run_service_interpreter(SERVICE.get().expect("service state should be defined"))
+        }
+        Err(e) => {
+            eprintln!("error starting service {}: {}", name, e);
+            1
+        }
+    }
+}
@@ -0,0 +1,267 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Run Python functions concurrently on a pool of threads.
+
+Each worker thread acquires the GIL for the duration of a single call and
+releases it afterwards. So Python code runs concurrently wherever it releases
+the GIL (I/O, many extension modules) and is otherwise scheduled between
+workers by the interpreter.
+
+Workers share the main interpreter instead of owning subinterpreters.
+Subinterpreters share a GIL with the main interpreter in the Python versions
+we support, so they wouldn't add parallelism. And many extension modules (as
+well as our importer's module state) don't support being loaded in multiple
+interpreters.
+
+Calls and their results cross the Rust/Python boundary as pickles so no
+Python objects are shared between the caller and the workers.
+*/
+
+use {
+    crate::{
+        error::{format_exception, WorkerPoolError},
+        interpreter::MainPythonInterpreter,
+    },
+    cpython::{ObjectProtocol, PyBytes, PyResult, PyTuple, Python},
+    python3_sys as pyffi,
+    std::{
+        marker::PhantomData,
+        sync::{mpsc, Arc, Mutex},
+        thread::JoinHandle,
+    },
+};
+
+/// Describes a Python callable to run in a worker.
+#[derive(Clone, Debug)]
+pub enum PythonCall {
+    /// Call a function defined in a module.
+    Function {
+        /// Name of the module to import.
+        module: String,
+        /// Name of the function in the module.
+        function: String,
+        /// Positional arguments as a pickled tuple. `None` calls without arguments.
+        args: Option<Vec<u8>>,
+    },
+
+    /// Call a pickled callable.
+    Pickled {
+        /// The pickled callable.
+        callable: Vec<u8>,
+        /// Positional arguments as a pickled tuple. `None` calls without arguments.
+        args: Option<Vec<u8>>,
+    },
+}
+
+impl PythonCall {
+    /// Construct an instance calling a function referenced as `module:function`.
+    pub fn from_target(target: &str, args: Option<Vec<u8>>) -> Result<Self, WorkerPoolError> {
+        let mut parts = target.splitn(2, ':');
+
+        match (parts.next(), parts.next()) {
+            (Some(module), Some(function)) if !module.is_empty() && !function.is_empty() => {
+                Ok(Self::Function {
+                    module: module.to_string(),
+                    function: function.to_string(),
+                    args,
+                })
+            }
+            _ => Err(WorkerPoolError::InvalidTarget(target.to_string())),
+        }
+    }
+
+    fn run(&self, py: Python) -> PyResult<Vec<u8>> {
+        let pickle = py.import("pickle")?;
+
+        let (callable, args) = match self {
+            Self::Function {
+                module,
+                function,
+                args,
+            } => (py.import(module)?.get(py, function)?, args),
+            Self::Pickled { callable, args } => (
+                pickle.call(py, "loads", (PyBytes::new(py, callable),), None)?,
+                args,
+            ),
+        };
+
+        let args = match args {
+            Some(data) => pickle
+                .call(py, "loads", (PyBytes::new(py, data),), None)?
+                .extract::<PyTuple>(py)?,
+            None => PyTuple::empty(py),
+        };
+
+        let result = callable.call(py, args, None)?;
+
+        let data = pickle
+            .call(py, "dumps", (result,), None)?
+            .extract::<PyBytes>(py)?;
+
+        Ok(data.data(py).to_vec())
+    }
+}
+
+struct Job {
+    call: PythonCall,
+    sender: mpsc::Sender<Result<Vec<u8>, WorkerPoolError>>,
+}
+
+/// A call submitted to a [PythonWorkerPool].
+pub struct PythonTask {
+    receiver: mpsc::Receiver<Result<Vec<u8>, WorkerPoolError>>,
+}
+
+impl PythonTask {
+    /// Wait for the call to complete.
+    ///
+    /// Returns the pickled return value of the call.
+    pub fn wait(self) -> Result<Vec<u8>, WorkerPoolError> {
+        self.receiver
+            .recv()
+            .map_err(|_| WorkerPoolError::ShutDown)?
+    }
+
+    /// Obtain the result of the call if it has completed.
+    pub fn try_wait(&self) -> Option<Result<Vec<u8>, WorkerPoolError>> {
+        match self.receiver.try_recv() {
+            Ok(res) => Some(res),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err(WorkerPoolError::ShutDown)),
+        }
+    }
+}
+
+/// A pool of threads running Python calls in an embedded interpreter.
+///
+/// The pool mutably borrows the [MainPythonInterpreter] so the interpreter
+/// can't be used or finalized while workers are running. The GIL is released
+/// by the constructing thread when the pool is created and reacquired when
+/// the pool is dropped, after all workers have exited.
+pub struct PythonWorkerPool<'pool> {
+    sender: Option<mpsc::Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    thread_state: *mut pyffi::PyThreadState,
+    _interpreter: PhantomData<&'pool mut ()>,
+}
+
+impl<'pool> PythonWorkerPool<'pool> {
+    /// Construct a pool with `workers` threads.
+    pub fn new<'python, 'interpreter, 'resources>(
+        interpreter: &'pool mut MainPythonInterpreter<'python, 'interpreter, 'resources>,
+        workers: usize,
+    ) -> Result<Self, WorkerPoolError> {
+        if workers == 0 {
+            return Err(WorkerPoolError::NoWorkers);
+        }
+
+        interpreter.release_gil();
+
+        // The thread initializing the interpreter holds the GIL without a
+        // GILGuard. So it may still be held.
+        let thread_state = unsafe {
+            if pyffi::PyGILState_Check() == 1 {
+                pyffi::PyEval_SaveThread()
+            } else {
+                std::ptr::null_mut()
+            }
+        };
+
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let mut pool = Self {
+            sender: Some(sender),
+            workers: Vec::with_capacity(workers),
+            thread_state,
+            _interpreter: PhantomData,
+        };
+
+        // On error, dropping the pool stops and joins already spawned workers.
+        for i in 0..workers {
+            let receiver = receiver.clone();
+
+            pool.workers.push(
+                std::thread::Builder::new()
+                    .name(format!("pyembed-worker-{}", i))
+                    .spawn(move || worker_main(receiver))
+                    .map_err(WorkerPoolError::Spawn)?,
+            );
+        }
+
+        Ok(pool)
+    }
+
+    /// The number of worker threads.
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Submit a call to be run by a worker.
+    pub fn submit(&self, call: PythonCall) -> Result<PythonTask, WorkerPoolError> {
+        let (sender, receiver) = mpsc::channel();
+
+        self.sender
+            .as_ref()
+            .ok_or(WorkerPoolError::ShutDown)?
+            .send(Job { call, sender })
+            .map_err(|_| WorkerPoolError::ShutDown)?;
+
+        Ok(PythonTask { receiver })
+    }
+
+    /// Submit a call to a function referenced as `module:function`.
+    ///
+    /// `args` is a pickled tuple of positional arguments.
+    pub fn submit_function(
+        &self,
+        target: &str,
+        args: Option<Vec<u8>>,
+    ) -> Result<PythonTask, WorkerPoolError> {
+        self.submit(PythonCall::from_target(target, args)?)
+    }
+}
+
+impl<'pool> Drop for PythonWorkerPool<'pool> {
+    fn drop(&mut self) {
+        // Closing the channel makes workers exit once pending jobs are done.
+        self.sender.take();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+
+        if !self.thread_state.is_null() {
+            unsafe {
+                pyffi::PyEval_RestoreThread(self.thread_state);
+            }
+        }
+    }
+}
+
+fn worker_main(receiver: Arc<Mutex<mpsc::Receiver<Job>>>) {
+    loop {
+        let job = match receiver.lock() {
+            Ok(receiver) => match receiver.recv() {
+                Ok(job) => job,
+                Err(_) => return,
+            },
+            Err(_) => return,
+        };
+
+        let res = {
+            let gil = Python::acquire_gil();
+            let py = gil.python();
+
+            job.call
+                .run(py)
+                .map_err(|err| WorkerPoolError::Python(format_exception(py, err)))
+        };
+
+        // The task may have been dropped without waiting for its result.
+        let _ = job.sender.send(res);
+    }
+}
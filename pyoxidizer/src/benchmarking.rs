@@ -0,0 +1,372 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Benchmark Python import performance of built executables.
+
+The benchmarks in this module build a target from a PyOxidizer
+configuration file and run the produced executable with CPython's
+`-X importtime` instrumentation enabled. The same imports are then
+performed by the Python distribution's own `python` executable, which
+loads modules from the filesystem using Python's built-in importers.
+The per-module timings reported by CPython are collected from both runs
+and compared.
+*/
+
+use {
+    crate::{
+        project_building::find_pyoxidizer_config_file_env,
+        projectmgmt::resolve_target,
+        py_packaging::distribution::{
+            default_distribution_location, resolve_distribution, DistributionFlavor,
+        },
+        starlark::eval::EvaluationContextBuilder,
+    },
+    anyhow::{anyhow, Context, Result},
+    slog::warn,
+    starlark_dialect_build_targets::RunMode,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        path::Path,
+        time::{Duration, Instant},
+    },
+};
+
+/// Prefix of lines emitted by CPython's `-X importtime` instrumentation.
+const IMPORT_TIME_PREFIX: &str = "import time:";
+
+/// Timing of a single module import as reported by `-X importtime`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ImportTiming {
+    /// Fully qualified name of the imported module.
+    pub module: String,
+
+    /// Microseconds spent importing this module, excluding nested imports.
+    pub self_us: u64,
+
+    /// Microseconds spent importing this module, including nested imports.
+    pub cumulative_us: u64,
+
+    /// Nesting depth of the import. Top-level imports have depth 0.
+    pub depth: usize,
+}
+
+/// Parse `-X importtime` output into per-module timings.
+///
+/// Lines not emitted by the instrumentation, including its header line,
+/// are ignored.
+pub fn parse_import_time_output(output: &str) -> Vec<ImportTiming> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.strip_prefix(IMPORT_TIME_PREFIX)?;
+            let mut parts = line.splitn(3, '|');
+
+            let self_us = parts.next()?.trim().parse::<u64>().ok()?;
+            let cumulative_us = parts.next()?.trim().parse::<u64>().ok()?;
+            let name = parts.next()?.strip_prefix(' ')?;
+
+            let trimmed = name.trim_start();
+            let depth = (name.len() - trimmed.len()) / 2;
+
+            Some(ImportTiming {
+                module: trimmed.trim_end().to_string(),
+                self_us,
+                cumulative_us,
+                depth,
+            })
+        })
+        .collect()
+}
+
+/// Result of benchmarking imports in a single executable.
+#[derive(Clone, Debug)]
+pub struct ImportBenchmark {
+    /// Wall time of the fastest process execution.
+    pub wall_time: Duration,
+
+    /// Per-module import timings from the fastest process execution.
+    pub timings: Vec<ImportTiming>,
+}
+
+impl ImportBenchmark {
+    /// Obtain the cumulative import time of each module, keyed by name.
+    pub fn cumulative_by_module(&self) -> BTreeMap<&str, u64> {
+        self.timings
+            .iter()
+            .map(|t| (t.module.as_str(), t.cumulative_us))
+            .collect()
+    }
+}
+
+/// Run an executable importing modules and record timings.
+///
+/// The executable is invoked `iterations` times with
+/// `-X importtime -c "import <modules>"` and the fastest run is retained.
+pub fn benchmark_executable_imports(
+    exe: &Path,
+    modules: &[&str],
+    iterations: usize,
+) -> Result<ImportBenchmark> {
+    let code = if modules.is_empty() {
+        "pass".to_string()
+    } else {
+        format!("import {}", modules.join(", "))
+    };
+
+    let mut best: Option<ImportBenchmark> = None;
+
+    for _ in 0..iterations.max(1) {
+        let start = Instant::now();
+        let output = std::process::Command::new(exe)
+            .current_dir(exe.parent().unwrap_or_else(|| Path::new(".")))
+            .arg("-X")
+            .arg("importtime")
+            .arg("-c")
+            .arg(&code)
+            .output()
+            .with_context(|| format!("running {}", exe.display()))?;
+        let wall_time = start.elapsed();
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{} exited with {}: {}",
+                exe.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        let timings = parse_import_time_output(&String::from_utf8_lossy(&output.stderr));
+
+        if timings.is_empty() {
+            return Err(anyhow!(
+                "{} did not emit import timings; is it configured to parse Python command arguments?",
+                exe.display()
+            ));
+        }
+
+        if best.as_ref().map_or(true, |b| wall_time < b.wall_time) {
+            best = Some(ImportBenchmark { wall_time, timings });
+        }
+    }
+
+    Ok(best.unwrap())
+}
+
+/// Render a comparison of oxidized and filesystem import benchmarks.
+pub fn format_import_comparison(
+    oxidized: &ImportBenchmark,
+    filesystem: &ImportBenchmark,
+) -> String {
+    let oxidized_modules = oxidized.cumulative_by_module();
+    let filesystem_modules = filesystem.cumulative_by_module();
+
+    let mut lines = vec![
+        format!(
+            "{:<40} {:>14} {:>14}",
+            "", "oxidized (us)", "filesystem (us)"
+        ),
+        format!(
+            "{:<40} {:>14} {:>14}",
+            "process wall time",
+            oxidized.wall_time.as_micros(),
+            filesystem.wall_time.as_micros()
+        ),
+        format!(
+            "{:<40} {:>14} {:>14}",
+            "total import time",
+            oxidized.timings.iter().map(|t| t.self_us).sum::<u64>(),
+            filesystem.timings.iter().map(|t| t.self_us).sum::<u64>()
+        ),
+        "".to_string(),
+    ];
+
+    let names = oxidized_modules
+        .keys()
+        .chain(filesystem_modules.keys())
+        .collect::<BTreeSet<_>>();
+
+    let mut rows = names
+        .into_iter()
+        .map(|name| {
+            (
+                *name,
+                oxidized_modules.get(name).copied(),
+                filesystem_modules.get(name).copied(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // Slowest oxidized imports first. Modules only imported from the
+    // filesystem sort last.
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.2.cmp(&a.2)));
+
+    let format_value = |v: Option<u64>| match v {
+        Some(v) => v.to_string(),
+        None => "-".to_string(),
+    };
+
+    for (name, oxidized, filesystem) in rows {
+        lines.push(format!(
+            "{:<40} {:>14} {:>14}",
+            name,
+            format_value(oxidized),
+            format_value(filesystem)
+        ));
+    }
+
+    lines.join("\n")
+}
+
+/// Build a target and compare its import performance against the filesystem.
+#[allow(clippy::too_many_arguments)]
+pub fn bench_import(
+    logger: &slog::Logger,
+    project_path: &Path,
+    target_triple: Option<&str>,
+    release: bool,
+    target: Option<&str>,
+    modules: &[&str],
+    iterations: usize,
+    verbose: bool,
+) -> Result<()> {
+    let config_path = find_pyoxidizer_config_file_env(logger, project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+    let target_triple = resolve_target(target_triple)?;
+
+    let mut context =
+        EvaluationContextBuilder::new(logger.clone(), config_path.clone(), target_triple.clone())
+            .release(release)
+            .verbose(verbose)
+            .resolve_target_optional(target)
+            .into_context()?;
+
+    context.evaluate_file(&config_path)?;
+
+    let target = match target {
+        Some(target) => target.to_string(),
+        None => context
+            .default_target()?
+            .ok_or_else(|| anyhow!("unable to determine target to benchmark"))?,
+    };
+
+    let resolved = context.build_resolved_target(&target)?;
+
+    let exe = match &resolved.run_mode {
+        RunMode::Path { path } => path.clone(),
+        RunMode::None => {
+            return Err(anyhow!(
+                "target {} does not produce a runnable executable",
+                target
+            ))
+        }
+    };
+
+    let distribution_location =
+        default_distribution_location(&DistributionFlavor::Standalone, &target_triple, None)?;
+    let temp_dir = tempfile::Builder::new()
+        .prefix("python-distribution")
+        .tempdir()?;
+    let dist = resolve_distribution(logger, &distribution_location, temp_dir.path())?;
+
+    warn!(logger, "benchmarking imports in {}", exe.display());
+    let oxidized = benchmark_executable_imports(&exe, modules, iterations)?;
+
+    warn!(
+        logger,
+        "benchmarking imports in {}",
+        dist.python_exe_path().display()
+    );
+    let filesystem = benchmark_executable_imports(dist.python_exe_path(), modules, iterations)?;
+
+    println!("{}", format_import_comparison(&oxidized, &filesystem));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMPORT_TIME_OUTPUT: &str = "\
+import time: self [us] | cumulative | imported package
+import time:       120 |        120 |   _io
+import time:        45 |         45 |   marshal
+import time:       310 |        475 | _frozen_importlib_external
+some unrelated output
+import time:        12 |         12 |     encodings.aliases
+";
+
+    #[test]
+    fn test_parse_import_time_output() {
+        let timings = parse_import_time_output(IMPORT_TIME_OUTPUT);
+
+        assert_eq!(
+            timings,
+            vec![
+                ImportTiming {
+                    module: "_io".to_string(),
+                    self_us: 120,
+                    cumulative_us: 120,
+                    depth: 1,
+                },
+                ImportTiming {
+                    module: "marshal".to_string(),
+                    self_us: 45,
+                    cumulative_us: 45,
+                    depth: 1,
+                },
+                ImportTiming {
+                    module: "_frozen_importlib_external".to_string(),
+                    self_us: 310,
+                    cumulative_us: 475,
+                    depth: 0,
+                },
+                ImportTiming {
+                    module: "encodings.aliases".to_string(),
+                    self_us: 12,
+                    cumulative_us: 12,
+                    depth: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_import_comparison() {
+        let oxidized = ImportBenchmark {
+            wall_time: Duration::from_micros(1000),
+            timings: parse_import_time_output(IMPORT_TIME_OUTPUT),
+        };
+        let filesystem = ImportBenchmark {
+            wall_time: Duration::from_micros(2000),
+            timings: parse_import_time_output("import time:       900 |        900 | json\n"),
+        };
+
+        let lines = format_import_comparison(&oxidized, &filesystem)
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            vec![
+                "oxidized (us) filesystem (us)",
+                "process wall time 1000 2000",
+                "total import time 487 900",
+                "",
+                "_frozen_importlib_external 475 -",
+                "_io 120 -",
+                "marshal 45 -",
+                "encodings.aliases 12 -",
+                "json - 900",
+            ]
+        );
+    }
+}
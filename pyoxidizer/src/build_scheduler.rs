@@ -0,0 +1,522 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Build multiple targets in parallel.
+
+Each (target triple, target) pair to build is a job. A job can only start
+once the jobs building the targets it depends on (via `register_target()`'s
+`depends`) for the same target triple have finished.
+
+Starlark values can't be shared across threads. So each job evaluates the
+configuration file in its own context, resolving only the target it builds
+and that target's dependencies.
+
+Targets often share dependencies, e.g. an installer and an archive both
+containing the same executable. Every job building such a target builds
+the shared dependency as well. So dependencies shared by several requested
+targets of a target triple are scheduled as jobs of their own, which run
+before the jobs depending on them. And jobs sharing a dependency never run
+concurrently. Later builds of the dependency then find Cargo's output up to
+date instead of racing to overwrite it.
+*/
+
+use {
+    crate::{
+        logging::prefixed_logger, project_building::resolve_pyoxidizer_config_file,
+        py_packaging::distribution::DistributionCache, starlark::eval::EvaluationContextBuilder,
+    },
+    anyhow::{anyhow, Result},
+    slog::warn,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        path::Path,
+        sync::{mpsc, Arc},
+    },
+};
+
+/// A target to build for a target triple.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuildJob {
+    pub target_triple: String,
+    pub target: String,
+
+    /// Indices of jobs that must finish before this one starts.
+    pub depends: Vec<usize>,
+
+    /// Indices of jobs that must not run at the same time as this one.
+    pub conflicts: Vec<usize>,
+}
+
+impl BuildJob {
+    /// Prefix to attribute log messages of this job with.
+    pub fn label(&self) -> String {
+        format!("{}:{}", self.target_triple, self.target)
+    }
+}
+
+/// Resolve the targets a target transitively depends on.
+fn transitive_depends(target: &str, depends: &BTreeMap<String, Vec<String>>) -> BTreeSet<String> {
+    let mut seen = BTreeSet::new();
+    let mut stack = depends.get(target).cloned().unwrap_or_default();
+
+    while let Some(depend) = stack.pop() {
+        if seen.insert(depend.clone()) {
+            stack.extend(depends.get(&depend).cloned().unwrap_or_default());
+        }
+    }
+
+    seen
+}
+
+/// Add jobs building `targets` for a target triple.
+///
+/// `depends` maps registered targets to the targets they directly depend on.
+/// Dependencies shared by several of `targets` are added as jobs of their
+/// own, ahead of `targets`. A job depends on the jobs of the same triple
+/// building any target it transitively depends on. Jobs of the same triple
+/// whose targets share a dependency conflict with each other.
+pub fn add_jobs(
+    jobs: &mut Vec<BuildJob>,
+    target_triple: &str,
+    targets: &[String],
+    depends: &BTreeMap<String, Vec<String>>,
+) {
+    let start = jobs.len();
+
+    let mut shared = BTreeMap::<String, usize>::new();
+    for target in targets {
+        for depend in transitive_depends(target, depends) {
+            *shared.entry(depend).or_default() += 1;
+        }
+    }
+
+    let shared = shared
+        .into_iter()
+        .filter(|(depend, count)| *count > 1 && !targets.contains(depend))
+        .map(|(depend, _)| depend);
+
+    let mut all_targets = vec![];
+    for target in shared.chain(targets.iter().cloned()) {
+        if !all_targets.contains(&target) {
+            all_targets.push(target);
+        }
+    }
+
+    let closures = all_targets
+        .iter()
+        .map(|target| transitive_depends(target, depends))
+        .collect::<Vec<_>>();
+
+    for (i, target) in all_targets.iter().enumerate() {
+        let job_depends = all_targets
+            .iter()
+            .enumerate()
+            .filter(|(j, t)| *j != i && closures[i].contains(*t))
+            .map(|(j, _)| start + j)
+            .collect();
+
+        let conflicts = all_targets
+            .iter()
+            .enumerate()
+            .filter(|(j, t)| {
+                *j != i
+                    && !closures[i].contains(*t)
+                    && !closures[*j].contains(target)
+                    && !closures[i].is_disjoint(&closures[*j])
+            })
+            .map(|(j, _)| start + j)
+            .collect();
+
+        jobs.push(BuildJob {
+            target_triple: target_triple.to_string(),
+            target: target.clone(),
+            depends: job_depends,
+            conflicts,
+        });
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum JobState {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+/// Run jobs on up to `max_jobs` threads.
+///
+/// No new jobs are started once a job fails. The error of the first failed
+/// job is returned after running jobs finish.
+pub fn run_jobs<F>(jobs: &[BuildJob], max_jobs: usize, run: F) -> Result<()>
+where
+    F: Fn(&BuildJob) -> Result<()> + Send + Sync + 'static,
+{
+    let run = Arc::new(run);
+    let max_jobs = max_jobs.max(1);
+
+    let mut states = vec![JobState::Pending; jobs.len()];
+    let mut running = 0;
+    let mut error = None;
+
+    let (sender, receiver) = mpsc::channel();
+
+    loop {
+        if error.is_none() {
+            for (i, job) in jobs.iter().enumerate() {
+                if running >= max_jobs {
+                    break;
+                }
+
+                if states[i] != JobState::Pending
+                    || !job.depends.iter().all(|d| states[*d] == JobState::Done)
+                    || job
+                        .conflicts
+                        .iter()
+                        .any(|c| states[*c] == JobState::Running)
+                {
+                    continue;
+                }
+
+                states[i] = JobState::Running;
+                running += 1;
+
+                let run = run.clone();
+                let sender = sender.clone();
+                let job = job.clone();
+                std::thread::spawn(move || {
+                    // The receiver outlives all running jobs.
+                    sender.send((i, run(&job))).unwrap();
+                });
+            }
+        }
+
+        if running == 0 {
+            break;
+        }
+
+        let (i, res) = receiver.recv()?;
+        running -= 1;
+
+        match res {
+            Ok(()) => {
+                states[i] = JobState::Done;
+            }
+            Err(e) => {
+                states[i] = JobState::Failed;
+                error.get_or_insert(e.context(format!("building {}", jobs[i].label())));
+            }
+        }
+    }
+
+    if let Some(e) = error {
+        Err(e)
+    } else if let Some(i) = states.iter().position(|s| *s == JobState::Pending) {
+        Err(anyhow!(
+            "unable to schedule {}; its dependencies form a cycle",
+            jobs[i].label()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Build targets for multiple target triples in parallel.
+pub fn build_parallel(
+    logger: &slog::Logger,
+    project_path: &Path,
+    config_path: Option<&Path>,
+    target_triples: &[String],
+    resolve_targets: Option<Vec<String>>,
+    release: bool,
+    verbose: bool,
+    max_jobs: usize,
+) -> Result<()> {
+    let config_path = resolve_pyoxidizer_config_file(logger, project_path, config_path)?;
+
+    let distribution_cache = Arc::new(DistributionCache::new(None));
+
+    let mut jobs = vec![];
+
+    for target_triple in target_triples {
+        // Resolving no targets is cheap and tells us what we need to
+        // schedule.
+        let mut context =
+            EvaluationContextBuilder::new(logger.clone(), config_path.clone(), target_triple)
+                .release(release)
+                .verbose(verbose)
+                .resolve_targets(vec![])
+                .distribution_cache(distribution_cache.clone())
+                .into_context()?;
+
+        context.evaluate_file(&config_path)?;
+
+        let targets = if let Some(targets) = &resolve_targets {
+            targets.clone()
+        } else if let Some(target) = context.default_target()? {
+            vec![target]
+        } else {
+            vec![]
+        };
+
+        let mut depends = BTreeMap::new();
+        for target in context.target_names()? {
+            depends.insert(target.clone(), context.target_depends(&target)?);
+        }
+
+        add_jobs(&mut jobs, target_triple, &targets, &depends);
+    }
+
+    warn!(
+        logger,
+        "building {} targets using up to {} jobs",
+        jobs.len(),
+        max_jobs
+    );
+
+    let logger = logger.clone();
+
+    run_jobs(&jobs, max_jobs, move |job| {
+        let logger = prefixed_logger(&logger, &job.label());
+
+        let mut context = EvaluationContextBuilder::new(
+            logger.clone(),
+            config_path.clone(),
+            job.target_triple.clone(),
+        )
+        .release(release)
+        .verbose(verbose)
+        .resolve_target(job.target.clone())
+        .distribution_cache(distribution_cache.clone())
+        .into_context()?;
+
+        context.evaluate_file(&config_path)?;
+        context.build_resolved_target(&job.target)?;
+
+        warn!(logger, "finished");
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::sync::Mutex};
+
+    fn depends_map(entries: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.iter().map(|x| x.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_add_jobs() {
+        let depends = depends_map(&[
+            ("exe", &[]),
+            ("resources", &["exe"]),
+            ("install", &["resources"]),
+            ("msi", &["install"]),
+        ]);
+
+        let mut jobs = vec![];
+        add_jobs(
+            &mut jobs,
+            "a",
+            &["exe".to_string(), "msi".to_string()],
+            &depends,
+        );
+        add_jobs(&mut jobs, "b", &["exe".to_string()], &depends);
+
+        assert_eq!(
+            jobs,
+            vec![
+                BuildJob {
+                    target_triple: "a".to_string(),
+                    target: "exe".to_string(),
+                    depends: vec![],
+                    conflicts: vec![],
+                },
+                // Depends on exe through targets that aren't being built.
+                BuildJob {
+                    target_triple: "a".to_string(),
+                    target: "msi".to_string(),
+                    depends: vec![0],
+                    conflicts: vec![],
+                },
+                BuildJob {
+                    target_triple: "b".to_string(),
+                    target: "exe".to_string(),
+                    depends: vec![],
+                    conflicts: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_add_jobs_shared_depends() {
+        let depends = depends_map(&[
+            ("exe", &[]),
+            ("install", &["exe"]),
+            ("msi", &["exe"]),
+            ("docs", &[]),
+        ]);
+
+        let mut jobs = vec![];
+        add_jobs(
+            &mut jobs,
+            "a",
+            &["install".to_string(), "msi".to_string(), "docs".to_string()],
+            &depends,
+        );
+
+        assert_eq!(
+            jobs,
+            vec![
+                // Shared by install and msi, so built once up front.
+                BuildJob {
+                    target_triple: "a".to_string(),
+                    target: "exe".to_string(),
+                    depends: vec![],
+                    conflicts: vec![],
+                },
+                BuildJob {
+                    target_triple: "a".to_string(),
+                    target: "install".to_string(),
+                    depends: vec![0],
+                    conflicts: vec![2],
+                },
+                BuildJob {
+                    target_triple: "a".to_string(),
+                    target: "msi".to_string(),
+                    depends: vec![0],
+                    conflicts: vec![1],
+                },
+                BuildJob {
+                    target_triple: "a".to_string(),
+                    target: "docs".to_string(),
+                    depends: vec![],
+                    conflicts: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_jobs_conflicts() -> Result<()> {
+        let depends = depends_map(&[("exe", &[]), ("install", &["exe"]), ("msi", &["exe"])]);
+
+        let mut jobs = vec![];
+        add_jobs(
+            &mut jobs,
+            "a",
+            &["install".to_string(), "msi".to_string()],
+            &depends,
+        );
+
+        let active = Arc::new(Mutex::new(BTreeSet::new()));
+        let overlapped = Arc::new(Mutex::new(false));
+        let (active_run, overlapped_run) = (active.clone(), overlapped.clone());
+
+        run_jobs(&jobs, 4, move |job| {
+            {
+                let mut active = active_run.lock().unwrap();
+                if !active.is_empty() {
+                    *overlapped_run.lock().unwrap() = true;
+                }
+                active.insert(job.label());
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            active_run.lock().unwrap().remove(&job.label());
+            Ok(())
+        })?;
+
+        assert!(!*overlapped.lock().unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_jobs_order() -> Result<()> {
+        let depends = depends_map(&[("exe", &[]), ("install", &["exe"])]);
+
+        let mut jobs = vec![];
+        for triple in &["a", "b", "c"] {
+            add_jobs(
+                &mut jobs,
+                triple,
+                &["exe".to_string(), "install".to_string()],
+                &depends,
+            );
+        }
+
+        let finished = Arc::new(Mutex::new(vec![]));
+        let recorded = finished.clone();
+
+        run_jobs(&jobs, 4, move |job| {
+            recorded.lock().unwrap().push(job.label());
+            Ok(())
+        })?;
+
+        let finished = finished.lock().unwrap();
+        assert_eq!(finished.len(), 6);
+        for triple in &["a", "b", "c"] {
+            let exe = finished
+                .iter()
+                .position(|l| *l == format!("{}:exe", triple));
+            let install = finished
+                .iter()
+                .position(|l| *l == format!("{}:install", triple));
+            assert!(exe.unwrap() < install.unwrap());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_jobs_failure() {
+        let depends = depends_map(&[("exe", &[]), ("install", &["exe"])]);
+
+        let mut jobs = vec![];
+        add_jobs(
+            &mut jobs,
+            "a",
+            &["exe".to_string(), "install".to_string()],
+            &depends,
+        );
+
+        let started = Arc::new(Mutex::new(vec![]));
+        let recorded = started.clone();
+
+        let res = run_jobs(&jobs, 2, move |job| {
+            recorded.lock().unwrap().push(job.label());
+            Err(anyhow!("failed"))
+        });
+
+        assert!(res.is_err());
+        assert_eq!(*started.lock().unwrap(), vec!["a:exe".to_string()]);
+    }
+
+    #[test]
+    fn test_run_jobs_cycle() {
+        let jobs = vec![
+            BuildJob {
+                target_triple: "a".to_string(),
+                target: "x".to_string(),
+                depends: vec![1],
+                conflicts: vec![],
+            },
+            BuildJob {
+                target_triple: "a".to_string(),
+                target: "y".to_string(),
+                depends: vec![0],
+                conflicts: vec![],
+            },
+        ];
+
+        assert!(run_jobs(&jobs, 1, |_| Ok(())).is_err());
+    }
+}
@@ -4,11 +4,16 @@
 
 use {
     crate::{
-        environment::PYOXIDIZER_VERSION, logging, project_building, project_layout, projectmgmt,
+        benchmarking, build_scheduler, doctor, environment::PYOXIDIZER_VERSION, logging,
+        packed_resources, project_building, project_layout, projectmgmt,
     },
     anyhow::{anyhow, Result},
     clap::{App, AppSettings, Arg, SubCommand},
-    std::path::{Path, PathBuf},
+    python_packed_resources::data::BlobInteriorPadding,
+    std::{
+        path::{Path, PathBuf},
+        time::Duration,
+    },
 };
 
 const ADD_ABOUT: &str = "\
@@ -29,6 +34,21 @@ distribution URLs and dependency crate versions and locations from the
 PyOxidizer executable that runs this command.
 ";
 
+const BENCH_IMPORT_ABOUT: &str = "\
+Benchmark Python module imports in a built executable.
+
+This command builds a target from a PyOxidizer configuration file and runs
+the produced executable with `-X importtime -c \"import MODULE...\"`. The
+same imports are then performed by the `python` executable of the Python
+distribution, which imports modules from the filesystem.
+
+The per-module import timings reported by Python are compared and printed,
+slowest oxidized imports first, along with the total process wall time.
+
+The built executable must be configured to process command arguments like
+`python` does for `-X importtime` and `-c` to be honored.
+";
+
 const BUILD_ABOUT: &str = "\
 Build a PyOxidizer project.
 
@@ -37,6 +57,44 @@ existing PyOxidizer enabled project.
 
 This command will invoke Rust's build system tool (Cargo) to build
 the project.
+
+With `--watch`, the project is rebuilt whenever a file in the directory
+containing the configuration file, a file or directory targets read (such
+as package roots and virtualenvs), or a path given by `--watch-path`
+changes. Only the targets reading changed paths are rebuilt. The build
+directory, directories like `.git`, `__pycache__`, `*.egg-info`, and
+`target`, and `.pyc` files are not watched.
+
+`--target-triple` can be specified multiple times to build for several
+target triples. `--jobs` controls how many targets are built in parallel.
+Targets are only built after the targets they depend on.
+
+`--config` uses a specific configuration file instead of searching for a
+`pyoxidizer.bzl` file. If the file has a `.py` extension, it is a Python
+script run by the host Python distribution that defines build targets
+using the `pyoxidizer` module.
+";
+
+const CACHE_ABOUT: &str = "\
+Manage the cache of a PyOxidizer project.
+
+Builds keep scratch state - extracted Python distributions, temporary
+directories used by pip and the Rust compiler, and Cargo's target
+directory - in a cache directory. By default this is the `cache`
+directory under the project's build path. The PYOXIDIZER_CACHE_DIR
+environment variable can be defined to use a different directory.
+";
+
+const DOCTOR_ABOUT: &str = "\
+Diagnose problems with the environment PyOxidizer runs in.
+
+This command checks for the tools needed to build for a target - Rust,
+Cargo, a linker, and platform tools such as the Windows SDK and Xcode -
+verifies the hosts serving Python distributions are reachable, evaluates
+the project's configuration file, and reports the state of its cache.
+
+Each problem found is printed along with steps to resolve it. The command
+fails if any check produced an error.
 ";
 
 const INIT_RUST_PROJECT_ABOUT: &str = "\
@@ -69,6 +127,47 @@ This command executes the functionality to derive various artifacts and
 emits special lines that tell the Rust build system how to consume them.
 ";
 
+const PUBLISH_ABOUT: &str = "\
+Perform publish actions defined in a PyOxidizer configuration file.
+
+Publish actions are targets whose function returns a value produced by
+`upload_github_release()` or `upload_s3()`. Each named TARGET must be
+such a target.
+
+The targets a publish action depends on are built first. So a release
+can be built and uploaded with a single command by having the publish
+target depend on the targets producing the artifacts to upload.
+";
+
+const RESOURCES_ABOUT: &str = "\
+Inspect and manipulate packed resources files.
+
+Packed resources files hold the resources data indexed by
+`oxidized_importer`. They are produced by builds (e.g. `packed-resources`
+files in the build directory) and can be embedded in executables.
+
+The `list` sub-command prints each resource in a file along with its
+flags and the sizes of its in-memory data.
+
+The `extract` sub-command writes the in-memory data of resources to a
+directory using a layout resembling a Python installation.
+
+The `convert` sub-command rewrites a file in another format version or
+with different blob interior padding.
+
+The `verify` sub-command validates files the same way `pyembed` does when
+loading them and reports the format version and offset of any problem.
+
+The `diff` sub-command writes a delta describing how to produce a file
+from an older version of it. Deltas are typically much smaller than the
+file, making them suitable for shipping updates of applications loading
+resources from external files. The `patch` sub-command applies a delta,
+producing a file identical to the one the delta was created from.
+
+Sub-commands accepting resource names also select resources nested under
+those names. e.g. `foo` selects `foo.bar`.
+";
+
 const RESOURCES_SCAN_ABOUT: &str = "\
 Scan a directory or file for Python resources.
 
@@ -82,6 +181,13 @@ The directory walking functionality is used by
 
 The file parsing logic is used for parsing the contents of wheels.
 
+If PATH is the root directory of a virtualenv, its `site-packages`
+directories are scanned.
+
+By default, resources are printed in a human readable format. `--json`
+prints a JSON array of objects instead. Each object has a `type` key
+identifying the kind of resource.
+
 This command can be used to debug failures with PyOxidizer's code
 for converting files/directories into strongly typed objects. This
 conversion is critical for properly packaging Python applications and
@@ -120,6 +226,48 @@ pub fn run_cli() -> Result<()> {
                 .setting(AppSettings::ArgRequiredElseHelp)
                 .arg(Arg::with_name("path").help("Path to executable to analyze")),
         )
+        .subcommand(
+            SubCommand::with_name("bench-import")
+                .about("Compare import performance of a built executable against the filesystem")
+                .long_about(BENCH_IMPORT_ABOUT)
+                .arg(
+                    Arg::with_name("target_triple")
+                        .long("target-triple")
+                        .takes_value(true)
+                        .help("Rust target triple to build for"),
+                )
+                .arg(
+                    Arg::with_name("release")
+                        .long("release")
+                        .help("Benchmark a release binary"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .long("path")
+                        .default_value(".")
+                        .value_name("PATH")
+                        .help("Directory containing project to build"),
+                )
+                .arg(
+                    Arg::with_name("target")
+                        .long("target")
+                        .takes_value(true)
+                        .help("Build target to benchmark"),
+                )
+                .arg(
+                    Arg::with_name("iterations")
+                        .long("iterations")
+                        .default_value("5")
+                        .value_name("COUNT")
+                        .help("Number of times to run each executable"),
+                )
+                .arg(
+                    Arg::with_name("modules")
+                        .multiple(true)
+                        .value_name("MODULE")
+                        .help("Python modules to import"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("run-build-script")
                 .setting(AppSettings::ArgRequiredElseHelp)
@@ -137,6 +285,28 @@ pub fn run_cli() -> Result<()> {
                         .help("The config file target to resolve"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("Diagnose problems with the build environment")
+                .long_about(DOCTOR_ABOUT)
+                .arg(
+                    Arg::with_name("target_triple")
+                        .long("target-triple")
+                        .takes_value(true)
+                        .help("Rust target triple to check build requirements of"),
+                )
+                .arg(
+                    Arg::with_name("offline")
+                        .long("offline")
+                        .help("Do not check network access"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .default_value(".")
+                        .value_name("PATH")
+                        .help("Directory containing project to check"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("find-resources")
                 .about("Find resources in a file or directory")
@@ -166,6 +336,11 @@ pub fn run_cli() -> Result<()> {
                         .long("no-classify-files")
                         .help("Whether to skip classifying files as typed resources"),
                 )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Print resources as JSON"),
+                )
                 .arg(
                     Arg::with_name("no_emit_files")
                         .long("no-emit-files")
@@ -193,6 +368,11 @@ pub fn run_cli() -> Result<()> {
                         .number_of_values(1)
                         .help("Python package to install via `pip install`"),
                 )
+                .arg(
+                    Arg::with_name("from-setup")
+                        .long("from-setup")
+                        .help("Generate configuration from the Python project in PATH"),
+                )
                 .arg(
                     Arg::with_name("path")
                         .required(true)
@@ -232,7 +412,19 @@ pub fn run_cli() -> Result<()> {
                     Arg::with_name("target_triple")
                         .long("target-triple")
                         .takes_value(true)
-                        .help("Rust target triple to build for"),
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Rust target triple to build for (can be repeated)"),
+                )
+                .arg(
+                    Arg::with_name("jobs")
+                        .short("j")
+                        .long("jobs")
+                        .takes_value(true)
+                        .default_value("1")
+                        .value_name("N")
+                        .conflicts_with("watch")
+                        .help("Number of targets to build in parallel"),
                 )
                 .arg(
                     Arg::with_name("release")
@@ -247,6 +439,36 @@ pub fn run_cli() -> Result<()> {
                         .value_name("PATH")
                         .help("Directory containing project to build"),
                 )
+                .arg(
+                    Arg::with_name("config")
+                        .long("config")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .help("Configuration file to use (.bzl for Starlark or .py for Python)"),
+                )
+                .arg(
+                    Arg::with_name("watch")
+                        .long("watch")
+                        .help("Rebuild when files change"),
+                )
+                .arg(
+                    Arg::with_name("watch_paths")
+                        .long("watch-path")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("PATH")
+                        .requires("watch")
+                        .help("Additional file or directory to watch for changes"),
+                )
+                .arg(
+                    Arg::with_name("watch_interval")
+                        .long("watch-interval")
+                        .takes_value(true)
+                        .default_value("500")
+                        .value_name("MILLISECONDS")
+                        .help("How often to check watched files for changes"),
+                )
                 .arg(
                     Arg::with_name("targets")
                         .value_name("TARGET")
@@ -254,6 +476,23 @@ pub fn run_cli() -> Result<()> {
                         .help("Target to resolve"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("cache")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .about("Manage the build cache of a project")
+                .long_about(CACHE_ABOUT)
+                .subcommand(
+                    SubCommand::with_name("clean")
+                        .about("Delete the build cache of a project")
+                        .arg(
+                            Arg::with_name("path")
+                                .long("path")
+                                .default_value(".")
+                                .value_name("PATH")
+                                .help("Directory containing project to clean"),
+                        ),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("run")
                 .setting(AppSettings::TrailingVarArg)
@@ -284,6 +523,36 @@ pub fn run_cli() -> Result<()> {
                 )
                 .arg(Arg::with_name("extra").multiple(true)),
         )
+        .subcommand(
+            SubCommand::with_name("publish")
+                .about("Upload build artifacts using publish actions")
+                .long_about(PUBLISH_ABOUT)
+                .arg(
+                    Arg::with_name("target_triple")
+                        .long("target-triple")
+                        .takes_value(true)
+                        .help("Rust target triple to build for"),
+                )
+                .arg(
+                    Arg::with_name("release")
+                        .long("release")
+                        .help("Build a release binary"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .long("path")
+                        .default_value(".")
+                        .value_name("PATH")
+                        .help("Directory containing project to publish"),
+                )
+                .arg(
+                    Arg::with_name("targets")
+                        .value_name("TARGET")
+                        .multiple(true)
+                        .required(true)
+                        .help("Publish target to perform"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("python-distribution-extract")
                 .about("Extract a Python distribution archive to a directory")
@@ -325,6 +594,141 @@ pub fn run_cli() -> Result<()> {
                         .help("Path to Python distribution to analyze"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("resources")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .about("Inspect and manipulate packed resources files")
+                .long_about(RESOURCES_ABOUT)
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("List resources in a packed resources file")
+                        .arg(
+                            Arg::with_name("path")
+                                .required(true)
+                                .value_name("PATH")
+                                .help("Path to packed resources file"),
+                        )
+                        .arg(
+                            Arg::with_name("names")
+                                .multiple(true)
+                                .value_name("NAME")
+                                .help("Names of resources to list"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("extract")
+                        .about("Extract resources in a packed resources file to a directory")
+                        .arg(
+                            Arg::with_name("path")
+                                .required(true)
+                                .value_name("PATH")
+                                .help("Path to packed resources file"),
+                        )
+                        .arg(
+                            Arg::with_name("dest_path")
+                                .required(true)
+                                .value_name("DESTINATION_PATH")
+                                .help("Directory to write resources to"),
+                        )
+                        .arg(
+                            Arg::with_name("names")
+                                .multiple(true)
+                                .value_name("NAME")
+                                .help("Names of resources to extract"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("convert")
+                        .about("Convert a packed resources file to another format")
+                        .arg(
+                            Arg::with_name("format_version")
+                                .long("format-version")
+                                .takes_value(true)
+                                .default_value("4")
+                                .help("Format version to write"),
+                        )
+                        .arg(
+                            Arg::with_name("interior_padding")
+                                .long("interior-padding")
+                                .takes_value(true)
+                                .possible_values(&["none", "null"])
+                                .help("Padding between entries in blob sections"),
+                        )
+                        .arg(
+                            Arg::with_name("source_path")
+                                .required(true)
+                                .value_name("SOURCE_PATH")
+                                .help("Path to packed resources file to read"),
+                        )
+                        .arg(
+                            Arg::with_name("dest_path")
+                                .required(true)
+                                .value_name("DESTINATION_PATH")
+                                .help("Path to packed resources file to write"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("verify")
+                        .about("Validate packed resources files")
+                        .arg(
+                            Arg::with_name("paths")
+                                .required(true)
+                                .multiple(true)
+                                .value_name("PATH")
+                                .help("Paths to packed resources files"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("diff")
+                        .about("Write a delta between two packed resources files")
+                        .arg(
+                            Arg::with_name("output")
+                                .short("o")
+                                .long("output")
+                                .takes_value(true)
+                                .required(true)
+                                .value_name("PATH")
+                                .help("Path to write the delta to"),
+                        )
+                        .arg(
+                            Arg::with_name("old_path")
+                                .required(true)
+                                .value_name("OLD_PATH")
+                                .help("Path to the old packed resources file"),
+                        )
+                        .arg(
+                            Arg::with_name("new_path")
+                                .required(true)
+                                .value_name("NEW_PATH")
+                                .help("Path to the new packed resources file"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("patch")
+                        .about("Apply a delta to a packed resources file")
+                        .arg(
+                            Arg::with_name("output")
+                                .short("o")
+                                .long("output")
+                                .takes_value(true)
+                                .required(true)
+                                .value_name("PATH")
+                                .help("Path to write the new packed resources file to"),
+                        )
+                        .arg(
+                            Arg::with_name("old_path")
+                                .required(true)
+                                .value_name("OLD_PATH")
+                                .help("Path to the old packed resources file"),
+                        )
+                        .arg(
+                            Arg::with_name("delta_path")
+                                .required(true)
+                                .value_name("DELTA_PATH")
+                                .help("Path to the delta produced by `diff`"),
+                        ),
+                ),
+        )
         .get_matches();
 
     let verbose = matches.is_present("verbose");
@@ -352,23 +756,140 @@ pub fn run_cli() -> Result<()> {
             Ok(())
         }
 
+        ("bench-import", Some(args)) => {
+            let target_triple = args.value_of("target_triple");
+            let release = args.is_present("release");
+            let path = args.value_of("path").unwrap();
+            let target = args.value_of("target");
+            let iterations = args
+                .value_of("iterations")
+                .unwrap()
+                .parse::<usize>()
+                .map_err(|e| anyhow!("invalid --iterations value: {}", e))?;
+            let modules: Vec<&str> = args.values_of("modules").unwrap_or_default().collect();
+
+            benchmarking::bench_import(
+                &logger_context.logger,
+                Path::new(path),
+                target_triple,
+                release,
+                target,
+                &modules,
+                iterations,
+                verbose,
+            )
+        }
+
         ("build", Some(args)) => {
             let release = args.is_present("release");
-            let target_triple = args.value_of("target_triple");
+            let target_triples: Vec<&str> = args
+                .values_of("target_triple")
+                .unwrap_or_default()
+                .collect();
+            let target_triple = target_triples.first().copied();
+            let jobs = args
+                .value_of("jobs")
+                .unwrap()
+                .parse::<usize>()
+                .map_err(|e| anyhow!("invalid --jobs value: {}", e))?;
             let path = args.value_of("path").unwrap();
+
+            let config_path = if let Some(config) = args.value_of("config") {
+                let config = std::env::current_dir()?.join(config);
+                if !config.exists() {
+                    return Err(anyhow!("config file {} does not exist", config.display()));
+                }
+                Some(config)
+            } else {
+                None
+            };
+
             let resolve_targets = if let Some(values) = args.values_of("targets") {
                 Some(values.map(|x| x.to_string()).collect())
             } else {
                 None
             };
 
-            projectmgmt::build(
+            if args.is_present("watch") {
+                if target_triples.len() > 1 {
+                    return Err(anyhow!("--watch only supports a single --target-triple"));
+                }
+
+                let watch_paths: Vec<&Path> = args
+                    .values_of("watch_paths")
+                    .unwrap_or_default()
+                    .map(Path::new)
+                    .collect();
+                let interval = args
+                    .value_of("watch_interval")
+                    .unwrap()
+                    .parse::<u64>()
+                    .map_err(|e| anyhow!("invalid --watch-interval value: {}", e))?;
+                if interval == 0 {
+                    return Err(anyhow!("--watch-interval must be greater than 0"));
+                }
+
+                watching::build_watch(
+                    &logger_context.logger,
+                    Path::new(path),
+                    config_path.as_deref(),
+                    target_triple,
+                    resolve_targets,
+                    release,
+                    verbose,
+                    &watch_paths,
+                    Duration::from_millis(interval),
+                )
+            } else if target_triples.len() > 1 || jobs > 1 {
+                let target_triples = if target_triples.is_empty() {
+                    vec![projectmgmt::default_target()?]
+                } else {
+                    target_triples.iter().map(|x| x.to_string()).collect()
+                };
+
+                build_scheduler::build_parallel(
+                    &logger_context.logger,
+                    Path::new(path),
+                    config_path.as_deref(),
+                    &target_triples,
+                    resolve_targets,
+                    release,
+                    verbose,
+                    jobs,
+                )
+            } else {
+                projectmgmt::build(
+                    &logger_context.logger,
+                    Path::new(path),
+                    config_path.as_deref(),
+                    target_triple,
+                    resolve_targets,
+                    release,
+                    verbose,
+                )
+            }
+        }
+
+        ("cache", Some(args)) => match args.subcommand() {
+            ("clean", Some(args)) => {
+                let path = args.value_of("path").unwrap();
+
+                projectmgmt::cache_clean(&logger_context.logger, Path::new(path))
+            }
+
+            _ => Err(anyhow!("invalid sub-command")),
+        },
+
+        ("doctor", Some(args)) => {
+            let target_triple = projectmgmt::resolve_target(args.value_of("target_triple"))?;
+            let offline = args.is_present("offline");
+            let path = args.value_of("path").unwrap();
+
+            doctor::doctor(
                 &logger_context.logger,
                 Path::new(path),
-                target_triple,
-                resolve_targets,
-                release,
-                verbose,
+                &target_triple,
+                offline,
             )
         }
 
@@ -387,6 +908,7 @@ pub fn run_cli() -> Result<()> {
             let target_triple = args.value_of("target_triple").unwrap();
             let classify_files = !args.is_present("no_classify_files");
             let emit_files = !args.is_present("no_emit_files");
+            let json = args.is_present("json");
 
             if path.is_none() && !scan_distribution {
                 Err(anyhow!("must specify a path or --scan-distribution"))
@@ -399,6 +921,7 @@ pub fn run_cli() -> Result<()> {
                     target_triple,
                     classify_files,
                     emit_files,
+                    json,
                 )
             }
         }
@@ -410,10 +933,11 @@ pub fn run_cli() -> Result<()> {
             } else {
                 Vec::new()
             };
+            let from_setup = args.is_present("from-setup");
             let path = args.value_of("path").unwrap();
             let config_path = Path::new(path);
 
-            projectmgmt::init_config_file(&config_path, code, &pip_install)
+            projectmgmt::init_config_file(&config_path, code, &pip_install, from_setup)
         }
 
         ("list-targets", Some(args)) => {
@@ -429,6 +953,26 @@ pub fn run_cli() -> Result<()> {
             projectmgmt::init_rust_project(&project_path)
         }
 
+        ("publish", Some(args)) => {
+            let target_triple = args.value_of("target_triple");
+            let release = args.is_present("release");
+            let path = args.value_of("path").unwrap();
+            let targets = args
+                .values_of("targets")
+                .unwrap()
+                .map(|x| x.to_string())
+                .collect();
+
+            projectmgmt::publish(
+                &logger_context.logger,
+                Path::new(path),
+                target_triple,
+                release,
+                targets,
+                verbose,
+            )
+        }
+
         ("python-distribution-extract", Some(args)) => {
             let download_default = args.is_present("download-default");
             let archive_path = args.value_of("archive-path");
@@ -457,6 +1001,77 @@ pub fn run_cli() -> Result<()> {
             projectmgmt::python_distribution_licenses(path)
         }
 
+        ("resources", Some(args)) => match args.subcommand() {
+            ("list", Some(args)) => {
+                let path = args.value_of("path").unwrap();
+                let names: Vec<&str> = args.values_of("names").unwrap_or_default().collect();
+
+                packed_resources::list_resources(Path::new(path), &names)
+            }
+
+            ("extract", Some(args)) => {
+                let path = args.value_of("path").unwrap();
+                let dest_path = args.value_of("dest_path").unwrap();
+                let names: Vec<&str> = args.values_of("names").unwrap_or_default().collect();
+
+                packed_resources::extract_resources(Path::new(path), Path::new(dest_path), &names)
+            }
+
+            ("convert", Some(args)) => {
+                let source_path = args.value_of("source_path").unwrap();
+                let dest_path = args.value_of("dest_path").unwrap();
+                let format_version = args
+                    .value_of("format_version")
+                    .unwrap()
+                    .parse::<u8>()
+                    .map_err(|e| anyhow!("invalid --format-version value: {}", e))?;
+                let interior_padding = match args.value_of("interior_padding") {
+                    Some("none") => Some(BlobInteriorPadding::None),
+                    Some("null") => Some(BlobInteriorPadding::Null),
+                    _ => None,
+                };
+
+                packed_resources::convert_resources(
+                    Path::new(source_path),
+                    Path::new(dest_path),
+                    format_version,
+                    interior_padding,
+                )
+            }
+
+            ("verify", Some(args)) => {
+                let paths: Vec<&Path> = args.values_of("paths").unwrap().map(Path::new).collect();
+
+                packed_resources::verify_resources_files(&paths)
+            }
+
+            ("diff", Some(args)) => {
+                let old_path = args.value_of("old_path").unwrap();
+                let new_path = args.value_of("new_path").unwrap();
+                let output = args.value_of("output").unwrap();
+
+                packed_resources::diff_resources(
+                    Path::new(old_path),
+                    Path::new(new_path),
+                    Path::new(output),
+                )
+            }
+
+            ("patch", Some(args)) => {
+                let old_path = args.value_of("old_path").unwrap();
+                let delta_path = args.value_of("delta_path").unwrap();
+                let output = args.value_of("output").unwrap();
+
+                packed_resources::patch_resources(
+                    Path::new(old_path),
+                    Path::new(delta_path),
+                    Path::new(output),
+                )
+            }
+
+            _ => Err(anyhow!("invalid sub-command")),
+        },
+
         ("run-build-script", Some(args)) => {
             let build_script = args.value_of("build-script-name").unwrap();
             let target = args.value_of("target");
@@ -0,0 +1,147 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Strip and split debug information of built binaries.
+
+Processing happens after linking using platform tools:
+
+* ELF binaries are processed with `objcopy`. Split debug information is
+  written to a `.debug` file and referenced from the binary with a GNU
+  debuglink.
+* Mach-O binaries are processed with `dsymutil` and `strip`. Split debug
+  information is written to a `.dSYM` bundle.
+* MSVC binaries already store debug information in a PDB file, which is
+  copied next to the binary when splitting.
+*/
+
+use {
+    crate::py_packaging::binary::DebugInfoSettings,
+    anyhow::{anyhow, Context, Result},
+    python_packaging::filesystem_scanning::walk_tree_files,
+    slog::warn,
+    std::path::Path,
+    tugger_file_manifest::FileManifest,
+};
+
+/// Run a tool, failing if it exits with an error.
+fn run_tool(logger: &slog::Logger, program: &str, args: &[&str], dir: &Path) -> Result<()> {
+    warn!(logger, "running {} {}", program, args.join(" "));
+
+    let output = std::process::Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .with_context(|| format!("running {}", program))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(())
+}
+
+/// Process debug information of a binary.
+///
+/// `exe_path` is modified in place, so it should not be a file other builds
+/// rely on. `pdb_path` is the PDB file produced for the binary, if any.
+///
+/// Returns files holding split debug information, relative to the
+/// directory of the binary.
+pub fn process_debug_info(
+    logger: &slog::Logger,
+    target_triple: &str,
+    settings: &DebugInfoSettings,
+    exe_path: &Path,
+    pdb_path: Option<&Path>,
+) -> Result<FileManifest> {
+    let mut debug_files = FileManifest::default();
+
+    if !settings.strip && !settings.split {
+        return Ok(debug_files);
+    }
+
+    let dir = exe_path
+        .parent()
+        .ok_or_else(|| anyhow!("unable to resolve directory of {}", exe_path.display()))?;
+    let exe_name = exe_path
+        .file_name()
+        .ok_or_else(|| anyhow!("unable to resolve file name of {}", exe_path.display()))?
+        .to_string_lossy()
+        .to_string();
+
+    if target_triple.contains("-windows-msvc") {
+        if settings.split {
+            let pdb_path = pdb_path
+                .filter(|path| path.exists())
+                .ok_or_else(|| anyhow!("unable to find PDB file for {}", exe_name))?;
+            let pdb_name = pdb_path
+                .file_name()
+                .ok_or_else(|| anyhow!("unable to resolve file name of PDB"))?;
+
+            let dest_path = dir.join(pdb_name);
+            std::fs::copy(pdb_path, &dest_path)
+                .with_context(|| format!("copying {}", pdb_path.display()))?;
+            debug_files.add_path_memory(&dest_path, dir)?;
+        }
+
+        // MSVC binaries don't contain debug information, so there's nothing
+        // to strip.
+        if settings.strip {
+            warn!(
+                logger,
+                "debug information is already separate from {}; not stripping", exe_name
+            );
+        }
+    } else if target_triple.contains("-apple-") {
+        if settings.split {
+            let dsym_name = format!("{}.dSYM", exe_name);
+            run_tool(logger, "dsymutil", &[&exe_name, "-o", &dsym_name], dir)?;
+
+            for entry in walk_tree_files(&dir.join(&dsym_name)) {
+                debug_files.add_path_memory(entry.path(), dir)?;
+            }
+        }
+
+        if settings.strip {
+            run_tool(logger, "strip", &[&exe_name], dir)?;
+        } else {
+            run_tool(logger, "strip", &["-S", &exe_name], dir)?;
+        }
+    } else {
+        let strip_arg = if settings.strip {
+            "--strip-all"
+        } else {
+            "--strip-debug"
+        };
+
+        if settings.split {
+            let debug_name = format!("{}.debug", exe_name);
+            run_tool(
+                logger,
+                "objcopy",
+                &["--only-keep-debug", &exe_name, &debug_name],
+                dir,
+            )?;
+
+            let debuglink_arg = format!("--add-gnu-debuglink={}", debug_name);
+            run_tool(
+                logger,
+                "objcopy",
+                &[strip_arg, &debuglink_arg, &exe_name],
+                dir,
+            )?;
+
+            debug_files.add_path_memory(dir.join(&debug_name), dir)?;
+        } else {
+            run_tool(logger, "objcopy", &[strip_arg, &exe_name], dir)?;
+        }
+    }
+
+    Ok(debug_files)
+}
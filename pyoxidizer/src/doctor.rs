@@ -0,0 +1,476 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Diagnose problems with the environment PyOxidizer runs in.
+
+use {
+    crate::{
+        environment::{cache_dir_from_env, project_cache_dir, MINIMUM_RUST_VERSION},
+        project_building::find_pyoxidizer_config_file_env,
+        py_packaging::distribution::PythonDistributionLocation,
+        python_distributions::PYTHON_DISTRIBUTIONS,
+        starlark::eval::EvaluationContextBuilder,
+    },
+    anyhow::{anyhow, Result},
+    duct::cmd,
+    std::{collections::BTreeSet, path::Path},
+};
+
+/// Outcome of a diagnostic check.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// The result of a diagnostic check.
+#[derive(Clone, Debug)]
+pub struct CheckResult {
+    /// Short name of the thing checked.
+    pub name: String,
+
+    /// Outcome of the check.
+    pub status: CheckStatus,
+
+    /// Describes what was found.
+    pub message: String,
+
+    /// How to resolve a warning or error.
+    pub remediation: Option<String>,
+}
+
+impl CheckResult {
+    fn ok(name: &str, message: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Ok,
+            message: message.to_string(),
+            remediation: None,
+        }
+    }
+
+    fn warning(name: &str, message: impl ToString, remediation: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Warning,
+            message: message.to_string(),
+            remediation: Some(remediation.to_string()),
+        }
+    }
+
+    fn error(name: &str, message: impl ToString, remediation: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Error,
+            message: message.to_string(),
+            remediation: Some(remediation.to_string()),
+        }
+    }
+}
+
+/// Run a command and obtain the first line of its stdout.
+fn command_first_line(program: &str, args: &[&str]) -> Result<String> {
+    let output = cmd(program, args).stderr_null().read()?;
+
+    Ok(output.lines().next().unwrap_or_default().to_string())
+}
+
+fn check_rust() -> CheckResult {
+    match rustc_version::version() {
+        Ok(version) if version < *MINIMUM_RUST_VERSION => CheckResult::error(
+            "rust",
+            format!(
+                "rustc {} found; {} or newer is required",
+                version, *MINIMUM_RUST_VERSION
+            ),
+            "upgrade Rust by running `rustup update`",
+        ),
+        Ok(version) => CheckResult::ok("rust", format!("rustc {}", version)),
+        Err(e) => CheckResult::error(
+            "rust",
+            format!("unable to run rustc: {}", e),
+            "install Rust from https://rustup.rs/",
+        ),
+    }
+}
+
+fn check_cargo() -> CheckResult {
+    match command_first_line("cargo", &["--version"]) {
+        Ok(version) => CheckResult::ok("cargo", version),
+        Err(e) => CheckResult::error(
+            "cargo",
+            format!("unable to run cargo: {}", e),
+            "install Rust from https://rustup.rs/",
+        ),
+    }
+}
+
+fn check_rust_target(target_triple: &str) -> CheckResult {
+    match cmd("rustup", &["target", "list", "--installed"])
+        .stderr_null()
+        .read()
+    {
+        Ok(output) if output.lines().any(|line| line.trim() == target_triple) => {
+            CheckResult::ok("rust target", format!("{} is installed", target_triple))
+        }
+        Ok(_) => CheckResult::error(
+            "rust target",
+            format!(
+                "Rust standard library for {} is not installed",
+                target_triple
+            ),
+            format!("run `rustup target add {}`", target_triple),
+        ),
+        Err(_) => CheckResult::warning(
+            "rust target",
+            "unable to run rustup to verify installed targets",
+            format!(
+                "ensure the Rust standard library for {} is installed",
+                target_triple
+            ),
+        ),
+    }
+}
+
+fn check_linker(target_triple: &str) -> CheckResult {
+    if target_triple.contains("-windows-msvc") {
+        match cc::windows_registry::find_tool(target_triple, "link.exe") {
+            Some(tool) => CheckResult::ok("linker", tool.path().display()),
+            None => CheckResult::error(
+                "linker",
+                "unable to find the Visual Studio linker (link.exe)",
+                "install Visual Studio or the Build Tools for Visual Studio with the \
+                 `Desktop development with C++` workload",
+            ),
+        }
+    } else {
+        match command_first_line("cc", &["--version"]) {
+            Ok(version) => CheckResult::ok("linker", version),
+            Err(e) => CheckResult::error(
+                "linker",
+                format!("unable to run cc: {}", e),
+                if target_triple.contains("-apple-") {
+                    "run `xcode-select --install` to install the Xcode Command Line Tools"
+                } else {
+                    "install a C compiler and linker, e.g. the `build-essential` \
+                     package on Debian and Ubuntu"
+                },
+            ),
+        }
+    }
+}
+
+fn check_windows_tools() -> Vec<CheckResult> {
+    let signtool = match tugger_windows::find_signtool() {
+        Ok(path) => CheckResult::ok("signtool", path.display()),
+        Err(e) => CheckResult::warning(
+            "signtool",
+            format!("{}", e),
+            "install the Windows SDK if you need to sign executables or installers",
+        ),
+    };
+
+    let wix = match std::env::var_os("WIX") {
+        Some(path) => CheckResult::ok(
+            "wix",
+            format!("WiX Toolset at {}", Path::new(&path).display()),
+        ),
+        None => CheckResult::ok(
+            "wix",
+            "WiX Toolset not installed; it will be downloaded when building installers",
+        ),
+    };
+
+    vec![signtool, wix]
+}
+
+fn check_xcode() -> CheckResult {
+    match command_first_line("xcrun", &["--show-sdk-path"]) {
+        Ok(path) if !path.is_empty() => CheckResult::ok("apple sdk", path),
+        _ => CheckResult::error(
+            "apple sdk",
+            "unable to locate an Apple SDK via xcrun",
+            "install Xcode or run `xcode-select --install` to install the Xcode Command Line \
+             Tools; set DEVELOPER_DIR to use a non-default Xcode",
+        ),
+    }
+}
+
+/// Obtain hosts serving Python distributions.
+fn distribution_hosts() -> BTreeSet<String> {
+    PYTHON_DISTRIBUTIONS
+        .iter()
+        .filter_map(|dist| match &dist.location {
+            PythonDistributionLocation::Url { url, .. } => url::Url::parse(url)
+                .ok()
+                .and_then(|url| url.host_str().map(|host| host.to_string())),
+            PythonDistributionLocation::Local { .. }
+            | PythonDistributionLocation::System { .. } => None,
+        })
+        .collect()
+}
+
+fn check_network() -> Vec<CheckResult> {
+    let client = match tugger_common::http::get_http_client() {
+        Ok(client) => client,
+        Err(e) => {
+            return vec![CheckResult::error(
+                "network",
+                format!("unable to create HTTP client: {}", e),
+                "verify the HTTP_PROXY, HTTPS_PROXY, and ALL_PROXY environment variables",
+            )]
+        }
+    };
+
+    distribution_hosts()
+        .into_iter()
+        .map(|host| {
+            let url = format!("https://{}/", host);
+
+            // Any response means the host is reachable.
+            match client.head(&url).send() {
+                Ok(_) => CheckResult::ok("network", format!("{} is reachable", host)),
+                Err(e) => CheckResult::error(
+                    "network",
+                    format!("unable to connect to {}: {}", host, e),
+                    "Python distributions are downloaded from this host; verify network \
+                     access and the HTTPS_PROXY environment variable",
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Sum the sizes of files in a directory tree.
+fn directory_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+fn check_cache(name: &str, path: &Path) -> CheckResult {
+    if !path.exists() {
+        return CheckResult::ok(name, format!("{} does not exist yet", path.display()));
+    }
+
+    let size = directory_size(path);
+
+    if std::fs::metadata(path)
+        .map(|m| m.permissions().readonly())
+        .unwrap_or(true)
+    {
+        CheckResult::error(
+            name,
+            format!("{} is not writable", path.display()),
+            "fix the permissions of the directory or set PYOXIDIZER_CACHE_DIR to another directory",
+        )
+    } else {
+        CheckResult::ok(
+            name,
+            format!("{} ({} MB)", path.display(), size / 1_000_000),
+        )
+    }
+}
+
+fn check_project(
+    logger: &slog::Logger,
+    project_path: &Path,
+    target_triple: &str,
+) -> Vec<CheckResult> {
+    let config_path = match find_pyoxidizer_config_file_env(logger, project_path) {
+        Some(path) => path,
+        None => {
+            let mut res = vec![CheckResult::warning(
+                "config",
+                format!(
+                    "no PyOxidizer config file found at {}",
+                    project_path.display()
+                ),
+                "run `pyoxidizer init-config-file` to create one",
+            )];
+
+            if let Some(path) = cache_dir_from_env() {
+                res.push(check_cache("cache", &path));
+            }
+
+            return res;
+        }
+    };
+
+    let evaluated = EvaluationContextBuilder::new(
+        logger.clone(),
+        config_path.clone(),
+        target_triple.to_string(),
+    )
+    .resolve_targets(vec![])
+    .into_context()
+    .and_then(|mut context| {
+        context.evaluate_file(&config_path)?;
+        Ok(context)
+    });
+
+    let context = match evaluated {
+        Ok(context) => context,
+        Err(e) => {
+            return vec![CheckResult::error(
+                "config",
+                format!("error evaluating {}: {:?}", config_path.display(), e),
+                "fix the reported error in the config file",
+            )]
+        }
+    };
+
+    let mut res = vec![match context.target_names() {
+        Ok(names) if names.is_empty() => CheckResult::warning(
+            "config",
+            format!("{} defines no targets", config_path.display()),
+            "call `register_target()` in the config file",
+        ),
+        Ok(names) => CheckResult::ok(
+            "config",
+            format!(
+                "{} defines targets: {}",
+                config_path.display(),
+                names.join(", ")
+            ),
+        ),
+        Err(e) => CheckResult::error(
+            "config",
+            format!(
+                "error resolving targets of {}: {:?}",
+                config_path.display(),
+                e
+            ),
+            "fix the reported error in the config file",
+        ),
+    }];
+
+    match context.build_path() {
+        Ok(build_path) => res.push(check_cache("cache", &project_cache_dir(&build_path))),
+        Err(e) => res.push(CheckResult::error(
+            "cache",
+            format!("unable to resolve build path: {:?}", e),
+            "fix the BUILD_PATH setting in the config file",
+        )),
+    }
+
+    res
+}
+
+/// Run all diagnostic checks.
+pub fn run_checks(
+    logger: &slog::Logger,
+    project_path: &Path,
+    target_triple: &str,
+    offline: bool,
+) -> Vec<CheckResult> {
+    let mut res = vec![
+        check_rust(),
+        check_cargo(),
+        check_rust_target(target_triple),
+        check_linker(target_triple),
+    ];
+
+    if target_triple.contains("-windows-") {
+        res.extend(check_windows_tools());
+    }
+
+    if target_triple.contains("-apple-") {
+        res.push(check_xcode());
+    }
+
+    if !offline {
+        res.extend(check_network());
+    }
+
+    res.extend(check_project(logger, project_path, target_triple));
+
+    res
+}
+
+/// Diagnose the environment and print the results.
+///
+/// Returns `Err` if any check failed.
+pub fn doctor(
+    logger: &slog::Logger,
+    project_path: &Path,
+    target_triple: &str,
+    offline: bool,
+) -> Result<()> {
+    let results = run_checks(logger, project_path, target_triple, offline);
+
+    for result in &results {
+        let status = match result.status {
+            CheckStatus::Ok => "ok",
+            CheckStatus::Warning => "warning",
+            CheckStatus::Error => "error",
+        };
+
+        println!("[{}] {}: {}", status, result.name, result.message);
+
+        if let Some(remediation) = &result.remediation {
+            println!("    fix: {}", remediation);
+        }
+    }
+
+    let errors = results
+        .iter()
+        .filter(|r| r.status == CheckStatus::Error)
+        .count();
+
+    if errors > 0 {
+        Err(anyhow!("{} checks failed", errors))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::*};
+
+    #[test]
+    fn test_distribution_hosts() {
+        assert!(distribution_hosts().contains("github.com"));
+    }
+
+    #[test]
+    fn test_check_project_missing_config() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let res = check_project(&logger, temp_dir.path(), env!("HOST"));
+        assert_eq!(res[0].name, "config");
+        assert_eq!(res[0].status, CheckStatus::Warning);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_cache() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let res = check_cache("cache", &temp_dir.path().join("missing"));
+        assert_eq!(res.status, CheckStatus::Ok);
+        assert!(res.message.contains("does not exist"));
+
+        std::fs::write(temp_dir.path().join("file"), vec![0; 10])?;
+        assert_eq!(directory_size(temp_dir.path()), 10);
+        assert_eq!(
+            check_cache("cache", temp_dir.path()).status,
+            CheckStatus::Ok
+        );
+
+        Ok(())
+    }
+}
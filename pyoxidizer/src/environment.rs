@@ -22,6 +22,9 @@ const GIT_REPO_URL: &str = env!("GIT_REPO_URL");
 /// Version string of PyOxidizer.
 pub const PYOXIDIZER_VERSION: &str = env!("PYOXIDIZER_VERSION");
 
+/// Environment variable that overrides the cache directory.
+pub const CACHE_DIR_ENV: &str = "PYOXIDIZER_CACHE_DIR";
+
 /// Filesystem path to Git repository we were built from.
 ///
 /// Will be None if a path is defined in the environment but not present.
@@ -88,8 +91,15 @@ pub static MINIMUM_RUST_VERSION: Lazy<semver::Version> =
     Lazy::new(|| semver::Version::new(1, 45, 0));
 
 /// Target triples for Linux.
-pub static LINUX_TARGET_TRIPLES: Lazy<Vec<&'static str>> =
-    Lazy::new(|| vec!["x86_64-unknown-linux-gnu", "x86_64-unknown-linux-musl"]);
+pub static LINUX_TARGET_TRIPLES: Lazy<Vec<&'static str>> = Lazy::new(|| {
+    vec![
+        "aarch64-unknown-linux-gnu",
+        "aarch64-unknown-linux-musl",
+        "armv7-unknown-linux-gnueabihf",
+        "x86_64-unknown-linux-gnu",
+        "x86_64-unknown-linux-musl",
+    ]
+});
 
 /// Target triples for macOS.
 pub static MACOS_TARGET_TRIPLES: Lazy<Vec<&'static str>> =
@@ -98,6 +108,7 @@ pub static MACOS_TARGET_TRIPLES: Lazy<Vec<&'static str>> =
 /// Target triples for Windows.
 pub static WINDOWS_TARGET_TRIPLES: Lazy<Vec<&'static str>> = Lazy::new(|| {
     vec![
+        "aarch64-pc-windows-msvc",
         "i686-pc-windows-gnu",
         "i686-pc-windows-msvc",
         "x86_64-pc-windows-gnu",
@@ -105,6 +116,95 @@ pub static WINDOWS_TARGET_TRIPLES: Lazy<Vec<&'static str>> = Lazy::new(|| {
     ]
 });
 
+/// Obtain the name of the conventional C cross compiler for a Linux target triple.
+///
+/// These are the names used by Debian's and Ubuntu's cross toolchain packages
+/// and by musl-cross-make.
+pub fn linux_cross_compiler(target_triple: &str) -> Option<&'static str> {
+    match target_triple {
+        "aarch64-unknown-linux-gnu" => Some("aarch64-linux-gnu-gcc"),
+        "aarch64-unknown-linux-musl" => Some("aarch64-linux-musl-gcc"),
+        "armv7-unknown-linux-gnueabihf" => Some("arm-linux-gnueabihf-gcc"),
+        _ => None,
+    }
+}
+
+/// Find an executable in `PATH`.
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths)
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Describes how executables built for a target can be run on this machine.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TargetRunner {
+    /// Executables run natively.
+    Native,
+
+    /// Executables run with QEMU user mode emulation registered with `binfmt_misc`.
+    ///
+    /// `sysroot` holds the target's dynamic loader and libraries, if known.
+    Qemu { sysroot: Option<PathBuf> },
+}
+
+impl TargetRunner {
+    /// Configure a command running a built executable.
+    pub fn configure(&self, command: &mut std::process::Command) {
+        if let Self::Qemu {
+            sysroot: Some(sysroot),
+        } = self
+        {
+            if env::var_os("QEMU_LD_PREFIX").is_none() {
+                command.env("QEMU_LD_PREFIX", sysroot);
+            }
+        }
+    }
+}
+
+/// Resolve how executables built for a target can be run on this machine.
+///
+/// Returns `None` if they can't be run.
+pub fn target_runner(target_triple: &str) -> Option<TargetRunner> {
+    if target_triple == env!("HOST") {
+        return Some(TargetRunner::Native);
+    }
+
+    if !cfg!(target_os = "linux") || !target_triple.contains("-linux-") {
+        return None;
+    }
+
+    // Linux executables for the same architecture run regardless of libc, as
+    // musl executables are statically linked.
+    if target_triple.split('-').next() == env!("HOST").split('-').next() {
+        return Some(TargetRunner::Native);
+    }
+
+    let qemu_arch = match target_triple.split('-').next() {
+        Some("aarch64") => "aarch64",
+        Some("armv7") => "arm",
+        Some("x86_64") => "x86_64",
+        _ => return None,
+    };
+
+    let binfmt =
+        std::fs::read_to_string(format!("/proc/sys/fs/binfmt_misc/qemu-{}", qemu_arch)).ok()?;
+    if binfmt.lines().next() != Some("enabled") {
+        return None;
+    }
+
+    // Dynamically linked executables need the target's dynamic loader, which
+    // cross toolchain packages install into a sysroot.
+    let sysroot = linux_cross_compiler(target_triple)
+        .and_then(|compiler| compiler.strip_suffix("-gcc"))
+        .map(|prefix| PathBuf::from("/usr").join(prefix))
+        .filter(|path| path.is_dir());
+
+    Some(TargetRunner::Qemu { sysroot })
+}
+
 pub fn canonicalize_path(path: &Path) -> Result<PathBuf, std::io::Error> {
     let mut p = path.canonicalize()?;
 
@@ -194,3 +294,58 @@ pub fn resolve_environment() -> Result<Environment> {
 
     Ok(Environment { pyoxidizer_source })
 }
+
+/// Obtain the cache directory defined by the environment, if any.
+pub fn cache_dir_from_env() -> Option<PathBuf> {
+    match env::var_os(CACHE_DIR_ENV) {
+        Some(value) if !value.is_empty() => Some(PathBuf::from(value)),
+        _ => None,
+    }
+}
+
+/// Resolve the cache directory of a project having the given build path.
+///
+/// This is `PYOXIDIZER_CACHE_DIR` if defined or the `cache` directory
+/// under the build path.
+pub fn project_cache_dir(build_path: &Path) -> PathBuf {
+    cache_dir_from_env().unwrap_or_else(|| build_path.join("cache"))
+}
+
+/// Create a temporary directory for scratch state of a build.
+///
+/// The directory is created in the `tmp` directory of `cache_path`, the
+/// cache directory of the project being built, if one is given. This keeps
+/// scratch state of different projects separate and allows it to be cleaned
+/// by `pyoxidizer cache clean`. Otherwise the system temporary directory is
+/// used.
+pub fn temp_dir(cache_path: Option<&Path>, prefix: &str) -> Result<tempfile::TempDir> {
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(prefix);
+
+    if let Some(root) = cache_path {
+        let tmp = root.join("tmp");
+        std::fs::create_dir_all(&tmp)?;
+        Ok(builder.tempdir_in(tmp)?)
+    } else {
+        Ok(builder.tempdir()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_runner() {
+        assert_eq!(target_runner(env!("HOST")), Some(TargetRunner::Native));
+        assert_eq!(target_runner("riscv64gc-unknown-none-elf"), None);
+
+        for triple in LINUX_TARGET_TRIPLES.iter() {
+            if triple.starts_with("x86_64-") {
+                assert_eq!(linux_cross_compiler(triple), None);
+            } else {
+                assert!(linux_cross_compiler(triple).is_some());
+            }
+        }
+    }
+}
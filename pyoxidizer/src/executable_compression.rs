@@ -0,0 +1,93 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Compress built executables.
+
+Compression is performed by [UPX](https://upx.github.io/), which replaces
+an executable with a compressed copy prefixed by a stub that decompresses it
+in memory when run. UPX must be installed separately. If it can't be found,
+executables are left uncompressed.
+*/
+
+use {
+    crate::py_packaging::binary::ExecutableCompression,
+    anyhow::{anyhow, Context, Result},
+    slog::warn,
+    std::path::Path,
+};
+
+/// Environment variable defining the path to the UPX executable.
+pub const UPX_ENV: &str = "PYOXIDIZER_UPX";
+
+/// Resolve the UPX executable to run.
+///
+/// Returns `None` if UPX can't be run.
+fn find_upx() -> Option<String> {
+    let program = std::env::var(UPX_ENV).unwrap_or_else(|_| "upx".to_string());
+
+    match std::process::Command::new(&program)
+        .arg("--version")
+        .output()
+    {
+        Ok(output) if output.status.success() => Some(program),
+        _ => None,
+    }
+}
+
+/// Compress an executable in place.
+pub fn compress_executable(
+    logger: &slog::Logger,
+    target_triple: &str,
+    compression: ExecutableCompression,
+    exe_path: &Path,
+) -> Result<()> {
+    match compression {
+        ExecutableCompression::None => Ok(()),
+        ExecutableCompression::Upx => {
+            let upx = match find_upx() {
+                Some(upx) => upx,
+                None => {
+                    warn!(
+                        logger,
+                        "UPX not found; {} will not be compressed (install UPX or set {})",
+                        exe_path.display(),
+                        UPX_ENV
+                    );
+                    return Ok(());
+                }
+            };
+
+            if target_triple.contains("-apple-") {
+                warn!(
+                    logger,
+                    "UPX compressed executables may fail to run on recent macOS versions"
+                );
+            }
+
+            warn!(
+                logger,
+                "compressing {} with UPX; compressed executables are more likely to be \
+                flagged by antivirus software and use more memory when run",
+                exe_path.display()
+            );
+
+            let output = std::process::Command::new(&upx)
+                .arg("-q")
+                .arg(exe_path)
+                .output()
+                .with_context(|| format!("running {}", upx))?;
+
+            if !output.status.success() {
+                return Err(anyhow!(
+                    "UPX failed to compress {}: {}",
+                    exe_path.display(),
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+            }
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,269 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Verify modules can be imported by built executables. */
+
+use {
+    crate::{
+        environment::{target_runner, TargetRunner},
+        project_building::build_python_executable,
+        py_packaging::binary::{EmbeddedPythonContext, PythonBinaryBuilder},
+    },
+    anyhow::{anyhow, Context, Result},
+    slog::warn,
+    std::{collections::BTreeSet, io::Write, path::Path, sync::Arc},
+};
+
+/// Environment variables that could cause Python to find code outside the executable.
+pub const PYTHON_PATH_ENV_VARS: &[&str] = &["PYTHONHOME", "PYTHONPATH", "PYTHONSTARTUP"];
+
+/// Environment variable verification executables read the code to run from.
+///
+/// This is only set in executables built for verification, never in the
+/// executables being shipped.
+pub const VERIFY_IMPORTS_ENV: &str = "PYOXIDIZER_VERIFY_IMPORTS_SOURCE";
+
+/// Python code verifying modules can be imported.
+///
+/// `{modules}` is replaced by a list literal of module names. Modules failing
+/// to import are reported on a line starting with `FAILED `.
+///
+/// If `{packed_only}` is `True`, the listed modules and the modules loaded by
+/// importing them must have been loaded by `OxidizedFinder`, unless their
+/// top-level package is in the `{exempt}` list literal. Offending modules are
+/// reported on a line starting with `DISK `. Modules loaded at interpreter
+/// startup and modules without a spec, which weren't loaded by any finder,
+/// aren't checked.
+const VERIFY_IMPORTS_SOURCE: &str = "import importlib, sys, traceback
+import oxidized_importer
+entries = {modules}
+failed = []
+startup_modules = set(sys.modules)
+for name in entries:
+    try:
+        importlib.import_module(name)
+        print('OK ' + name, flush=True)
+    except BaseException:
+        failed.append(name)
+        traceback.print_exc()
+        print('FAILED ' + name, flush=True)
+if {packed_only}:
+    loaded = set(sys.modules) - startup_modules
+    loaded.update(name for name in entries if name in sys.modules)
+    for name in sorted(loaded):
+        spec = getattr(sys.modules[name], '__spec__', None)
+        if spec is None or isinstance(spec.loader, oxidized_importer.OxidizedFinder):
+            continue
+        if name.split('.')[0] in {exempt}:
+            continue
+        failed.append(name)
+        print('DISK ' + name + ' ' + str(spec.origin), flush=True)
+sys.exit(1 if failed else 0)
+";
+
+/// Resolve packages packaged in compatibility mode present in an executable.
+pub fn compat_packages_in_executable(exe: &dyn PythonBinaryBuilder) -> BTreeSet<String> {
+    let compat_packages = exe.python_packaging_policy().compat_packages();
+
+    exe.iter_resources()
+        .filter_map(|(name, _)| {
+            let top_level = name.split('.').next().unwrap_or(name);
+
+            if compat_packages
+                .iter()
+                .any(|package| package.eq_ignore_ascii_case(top_level))
+            {
+                Some(top_level.to_string())
+            } else {
+                None
+            }
+        })
+        .collect::<BTreeSet<_>>()
+}
+
+/// Verify modules can be imported by an executable.
+///
+/// If `exe` names a `run_command_env`, the executable built from it at
+/// `exe_path` is copied to `dest_dir`, alongside the files installed with
+/// it (from `binary_data`), and run with that environment variable set to code
+/// importing each module. Otherwise, the shipped executable can't run
+/// arbitrary code and mustn't be changed to. So a variant of `exe` which
+/// only differs by reading the code from `VERIFY_IMPORTS_ENV` is built into
+/// `dest_dir`, with the same packed resources and files, and run instead.
+/// Executables are put in a subdirectory of `dest_dir` if files are installed
+/// above their directory.
+/// The result of each import is logged, along with the traceback of failed
+/// imports. This is skipped when the executable can't run on this machine.
+///
+/// If `packed_only` is true, `modules` and the modules they import must also
+/// be loaded by `OxidizedFinder`: any of them loaded by another importer,
+/// such as the filesystem importer, fails verification. Resources
+/// `OxidizedFinder` loads from files next to the executable are allowed.
+/// Modules in compatibility mode packages are exempt, as they are always
+/// installed next to the executable and imported from the filesystem.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_imports(
+    logger: &slog::Logger,
+    exe: &dyn PythonBinaryBuilder,
+    exe_path: &Path,
+    binary_data: &EmbeddedPythonContext,
+    modules: &BTreeSet<String>,
+    packed_only: bool,
+    dest_dir: &Path,
+    opt_level: &str,
+    release: bool,
+) -> Result<()> {
+    if modules.is_empty() && !packed_only {
+        return Ok(());
+    }
+
+    let runner = match target_runner(exe.target_triple()) {
+        Some(runner) => runner,
+        None => {
+            warn!(
+                logger,
+                "unable to verify imports when cross-compiling; skipping"
+            );
+            return Ok(());
+        }
+    };
+    if let TargetRunner::Qemu { .. } = runner {
+        warn!(
+            logger,
+            "running {} executable with QEMU",
+            exe.target_triple()
+        );
+    }
+
+    let exempt = compat_packages_in_executable(exe);
+    let source = VERIFY_IMPORTS_SOURCE
+        .replace(
+            "{modules}",
+            &serde_json::to_string(&modules.iter().collect::<Vec<_>>())?,
+        )
+        .replace("{packed_only}", if packed_only { "True" } else { "False" })
+        .replace(
+            "{exempt}",
+            &serde_json::to_string(&exempt.iter().collect::<Vec<_>>())?,
+        );
+
+    warn!(
+        logger,
+        "verifying modules can be imported: {}",
+        modules.iter().cloned().collect::<Vec<_>>().join(", ")
+    );
+    if packed_only {
+        warn!(
+            logger,
+            "verifying modules are imported from packed resources only"
+        );
+    }
+
+    if dest_dir.exists() {
+        std::fs::remove_dir_all(dest_dir)
+            .with_context(|| format!("removing {}", dest_dir.display()))?;
+    }
+
+    // Files installed above the executable's directory must stay in `dest_dir`.
+    let exe_dir = (0..binary_data.external_files_depth())
+        .fold(dest_dir.to_path_buf(), |path, _| path.join("bin"));
+
+    let (run_command_env, dest_path) = match &exe.python_interpreter_config().run_command_env {
+        Some(env) => {
+            binary_data.materialize_files(&exe_dir)?;
+
+            let exe_name = exe_path
+                .file_name()
+                .ok_or_else(|| anyhow!("unable to resolve executable file name"))?;
+            let dest_path = exe_dir.join(exe_name);
+            std::fs::copy(exe_path, &dest_path)
+                .with_context(|| format!("copying executable to {}", dest_path.display()))?;
+
+            (env.clone(), dest_path)
+        }
+        None => {
+            warn!(logger, "building executable for verifying imports");
+
+            let mut variant = exe.clone_trait();
+            // We just cloned the instance, so we have the only reference.
+            Arc::get_mut(&mut variant)
+                .expect("cloned executable is uniquely owned")
+                .python_interpreter_config_mut()
+                .run_command_env = Some(VERIFY_IMPORTS_ENV.to_string());
+
+            let build = build_python_executable(
+                logger,
+                &variant.name(),
+                variant.as_ref(),
+                variant.target_triple(),
+                opt_level,
+                release,
+            )?;
+
+            build.binary_data.materialize_files(&exe_dir)?;
+
+            let dest_path = exe_dir.join(&build.exe_name);
+            let mut fh = std::fs::File::create(&dest_path)
+                .with_context(|| format!("creating {}", dest_path.display()))?;
+            fh.write_all(&build.exe_data)
+                .with_context(|| format!("writing {}", dest_path.display()))?;
+            tugger_file_manifest::set_executable(&mut fh).context("making binary executable")?;
+
+            (VERIFY_IMPORTS_ENV.to_string(), dest_path)
+        }
+    };
+
+    let mut command = std::process::Command::new(&dest_path);
+    command.current_dir(&exe_dir).env(&run_command_env, source);
+    for key in PYTHON_PATH_ENV_VARS {
+        command.env_remove(key);
+    }
+    runner.configure(&mut command);
+
+    let output = command
+        .output()
+        .with_context(|| format!("running {}", dest_path.display()))?;
+
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        warn!(logger, "{}", line);
+    }
+
+    let mut failed = vec![];
+    let mut from_disk = vec![];
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(name) = line.strip_prefix("OK ") {
+            warn!(logger, "import {}: ok", name);
+        } else if let Some(name) = line.strip_prefix("FAILED ") {
+            warn!(logger, "import {}: failed", name);
+            failed.push(name.to_string());
+        } else if let Some(rest) = line.strip_prefix("DISK ") {
+            let mut parts = rest.splitn(2, ' ');
+            let name = parts.next().unwrap_or_default();
+            let origin = parts.next().unwrap_or_default();
+            warn!(
+                logger,
+                "import {}: not loaded by OxidizedFinder ({})", name, origin
+            );
+            from_disk.push(name.to_string());
+        } else {
+            warn!(logger, "{}", line);
+        }
+    }
+
+    if !failed.is_empty() {
+        Err(anyhow!(
+            "modules could not be imported: {}",
+            failed.join(", ")
+        ))
+    } else if !from_disk.is_empty() {
+        Err(anyhow!(
+            "modules were not imported from the executable's resources: {}",
+            from_disk.join(", ")
+        ))
+    } else if !output.status.success() {
+        Err(anyhow!("verifying imports failed: {}", output.status))
+    } else {
+        Ok(())
+    }
+}
@@ -11,14 +11,23 @@ distribution and embedding it in a larger binary, oftentimes an executable.
 This library exposes that functionality to other tools.
 */
 
+pub mod benchmarking;
+pub mod build_scheduler;
+pub mod debug_info;
+pub mod doctor;
 pub mod environment;
+pub mod executable_compression;
+pub mod import_verification;
 pub mod logging;
+pub mod packed_resources;
 pub mod project_building;
 pub mod project_layout;
 pub mod projectmgmt;
 pub mod py_packaging;
+pub mod python_config;
 pub mod python_distributions;
 pub mod starlark;
+pub mod watching;
 
 #[cfg(test)]
 mod testutil;
@@ -28,6 +28,49 @@ impl slog::Drain for PrintlnDrain {
     }
 }
 
+/// A slog Drain that prefixes messages and forwards them to another logger.
+///
+/// This attributes messages of concurrent operations sharing a logger.
+pub struct PrefixedDrain {
+    pub prefix: String,
+    pub logger: slog::Logger,
+}
+
+impl slog::Drain for PrefixedDrain {
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(
+        &self,
+        record: &slog::Record,
+        _values: &slog::OwnedKVList,
+    ) -> Result<Self::Ok, Self::Err> {
+        let msg = format!("[{}] {}", self.prefix, record.msg());
+
+        match record.level() {
+            slog::Level::Critical => slog::crit!(self.logger, "{}", msg),
+            slog::Level::Error => slog::error!(self.logger, "{}", msg),
+            slog::Level::Warning => slog::warn!(self.logger, "{}", msg),
+            slog::Level::Info => slog::info!(self.logger, "{}", msg),
+            slog::Level::Debug => slog::debug!(self.logger, "{}", msg),
+            slog::Level::Trace => slog::trace!(self.logger, "{}", msg),
+        }
+
+        Ok(())
+    }
+}
+
+/// Obtain a logger prefixing messages with `[prefix]`.
+pub fn prefixed_logger(logger: &slog::Logger, prefix: &str) -> slog::Logger {
+    slog::Logger::root(
+        PrefixedDrain {
+            prefix: prefix.to_string(),
+            logger: logger.clone(),
+        },
+        slog::o!(),
+    )
+}
+
 /// Context holding state for a logger.
 pub struct LoggerContext {
     pub logger: slog::Logger,
@@ -27,17 +27,26 @@ is implemented in C. Rust is simply a tool to achieve an end goal, albeit
 a rather effective and powerful tool.
 */
 
+mod benchmarking;
+mod build_scheduler;
 mod cli;
+mod debug_info;
+mod doctor;
 mod environment;
+mod executable_compression;
+mod import_verification;
 mod logging;
+mod packed_resources;
 mod project_building;
 mod project_layout;
 mod projectmgmt;
 mod py_packaging;
+mod python_config;
 mod python_distributions;
 pub mod starlark;
 #[cfg(test)]
 mod testutil;
+mod watching;
 
 fn main() {
     std::process::exit(match cli::run_cli() {
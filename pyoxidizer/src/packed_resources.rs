@@ -0,0 +1,695 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Inspect and manipulate packed resources files.
+
+Packed resources files hold the binary resources data indexed by
+`oxidized_importer`. The functionality in this module powers the
+`pyoxidizer resources` commands, which are primarily intended for
+debugging the content of built applications.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    python_packed_resources::{
+        data::{BlobInteriorPadding, Resource, SectionKind},
+        delta::{apply_delta, create_delta},
+        parser::{format_version, load_resources_detailed, verify_resources},
+        writer::{
+            encode_metadata, write_packed_resources_v3, write_packed_resources_v4, PackedSection,
+        },
+    },
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Packed resources format versions that can be written.
+pub const WRITABLE_FORMAT_VERSIONS: &[u8] = &[3, 4];
+
+/// Parse packed resources data into a list of resources.
+pub fn parse_packed_resources(data: &[u8]) -> Result<Vec<Resource<'_, u8>>> {
+    let mut resources = load_resources_detailed(data)
+        .map_err(|e| anyhow!("error parsing packed resources: {}", e))?;
+
+    let mut res = vec![];
+    while let Some(resource) = resources.next() {
+        res.push(resource.map_err(|message| {
+            anyhow!(
+                "error parsing packed resources: {}",
+                resources.error(message)
+            )
+        })?);
+    }
+
+    Ok(res)
+}
+
+/// Obtain the format version of packed resources data.
+pub fn packed_resources_format_version(data: &[u8]) -> Option<u8> {
+    format_version(data).ok()
+}
+
+/// Obtain the metadata of packed resources data.
+///
+/// Data without metadata, including data older than format version 4, has
+/// empty metadata.
+pub fn packed_resources_metadata(data: &[u8]) -> Result<BTreeMap<String, String>> {
+    let resources = load_resources_detailed(data)
+        .map_err(|e| anyhow!("error parsing packed resources: {}", e))?;
+
+    Ok(resources
+        .metadata()
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect())
+}
+
+/// Whether a resource name is selected by a list of names.
+///
+/// Names select the resource with that name and, for Python packages and
+/// file directories, all resources beneath it. An empty list selects
+/// everything.
+fn resource_selected(name: &str, names: &[&str]) -> bool {
+    names.is_empty()
+        || names.iter().any(|n| {
+            name == *n
+                || name.starts_with(&format!("{}.", n))
+                || name.starts_with(&format!("{}/", n))
+        })
+}
+
+/// Describe a resource as human readable lines.
+pub fn describe_resource(resource: &Resource<u8>) -> Vec<String> {
+    let mut flags = vec![];
+    for (set, flag) in &[
+        (resource.is_module, "module"),
+        (resource.is_package, "package"),
+        (resource.is_namespace_package, "namespace-package"),
+        (resource.is_builtin_extension_module, "builtin"),
+        (resource.is_frozen_module, "frozen"),
+        (resource.is_extension_module, "extension-module"),
+        (resource.is_shared_library, "shared-library"),
+        (resource.is_utf8_filename_data, "file"),
+        (resource.file_executable, "executable"),
+    ] {
+        if *set {
+            flags.push(*flag);
+        }
+    }
+
+    let mut lines = vec![format!("{} [{}]", resource.name, flags.join(", "))];
+
+    for (field, value) in &[
+        ("source", &resource.in_memory_source),
+        ("bytecode", &resource.in_memory_bytecode),
+        ("bytecode opt1", &resource.in_memory_bytecode_opt1),
+        ("bytecode opt2", &resource.in_memory_bytecode_opt2),
+        (
+            "extension module",
+            &resource.in_memory_extension_module_shared_library,
+        ),
+        ("shared library", &resource.in_memory_shared_library),
+        ("file data", &resource.file_data_embedded),
+    ] {
+        if let Some(data) = value {
+            lines.push(format!("    in-memory {}: {} bytes", field, data.len()));
+        }
+    }
+
+    for (field, value) in &[
+        ("package resources", &resource.in_memory_package_resources),
+        (
+            "distribution resources",
+            &resource.in_memory_distribution_resources,
+        ),
+    ] {
+        if let Some(entries) = value {
+            lines.push(format!(
+                "    in-memory {}: {} entries, {} bytes",
+                field,
+                entries.len(),
+                entries.values().map(|v| v.len()).sum::<usize>()
+            ));
+        }
+    }
+
+    for (field, value) in &[
+        ("source", &resource.relative_path_module_source),
+        ("bytecode", &resource.relative_path_module_bytecode),
+        (
+            "bytecode opt1",
+            &resource.relative_path_module_bytecode_opt1,
+        ),
+        (
+            "bytecode opt2",
+            &resource.relative_path_module_bytecode_opt2,
+        ),
+        (
+            "extension module",
+            &resource.relative_path_extension_module_shared_library,
+        ),
+    ] {
+        if let Some(path) = value {
+            lines.push(format!("    relative path {}: {}", field, path.display()));
+        }
+    }
+
+    for (field, value) in &[
+        (
+            "package resources",
+            &resource.relative_path_package_resources,
+        ),
+        (
+            "distribution resources",
+            &resource.relative_path_distribution_resources,
+        ),
+    ] {
+        if let Some(entries) = value {
+            lines.push(format!(
+                "    relative path {}: {} entries",
+                field,
+                entries.len()
+            ));
+        }
+    }
+
+    if let Some(path) = &resource.file_data_utf8_relative_path {
+        lines.push(format!("    relative path file data: {}", path));
+    }
+
+    if let Some(names) = &resource.shared_library_dependency_names {
+        lines.push(format!(
+            "    shared library dependencies: {}",
+            names.join(", ")
+        ));
+    }
+
+    lines
+}
+
+/// Print resources in a packed resources file.
+pub fn list_resources(path: &Path, names: &[&str]) -> Result<()> {
+    let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let resources = parse_packed_resources(&data)?;
+
+    println!(
+        "{}: format version {}, {} resources, {} bytes",
+        path.display(),
+        packed_resources_format_version(&data)
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| "unknown".to_string()),
+        resources.len(),
+        data.len()
+    );
+
+    for (key, value) in packed_resources_metadata(&data)? {
+        println!("metadata: {} = {}", key, value);
+    }
+
+    for resource in resources
+        .iter()
+        .filter(|r| resource_selected(&r.name, names))
+    {
+        for line in describe_resource(resource) {
+            println!("{}", line);
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate packed resources files.
+///
+/// Every file is validated and a line describing the outcome is printed for
+/// each. Errors if any file is invalid.
+pub fn verify_resources_files(paths: &[&Path]) -> Result<()> {
+    let mut failures = 0;
+
+    for path in paths {
+        let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+        match verify_resources(&data) {
+            Ok(count) => println!(
+                "{}: OK (format version {}, {} resources, {} bytes)",
+                path.display(),
+                packed_resources_format_version(&data).unwrap_or_default(),
+                count,
+                data.len()
+            ),
+            Err(e) => {
+                println!("{}: {}", path.display(), e);
+                failures += 1;
+            }
+        }
+    }
+
+    if failures > 0 {
+        Err(anyhow!(
+            "{} of {} files failed validation",
+            failures,
+            paths.len()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Obtain the relative filesystem path of a Python module, without extension.
+fn module_path(name: &str, is_package: bool) -> PathBuf {
+    let mut path = name.split('.').collect::<PathBuf>();
+
+    if is_package {
+        path.push("__init__");
+    }
+
+    path
+}
+
+/// Obtain files to write when extracting a resource.
+///
+/// Only data stored in memory is extracted. Module source is written to
+/// `.py` files. Module bytecode is written as raw marshalled code objects
+/// (without a `.pyc` header) to `.bytecode`, `.opt-1.bytecode`, and
+/// `.opt-2.bytecode` files. Package resources are written next to the
+/// package's modules and distribution resources to a `<name>.dist-info`
+/// directory.
+pub fn resource_extraction_files<'a>(resource: &'a Resource<u8>) -> Vec<(PathBuf, &'a [u8])> {
+    let mut files = vec![];
+
+    let module = module_path(&resource.name, resource.is_package);
+    let package = resource.name.split('.').collect::<PathBuf>();
+
+    for (suffix, value) in &[
+        ("py", &resource.in_memory_source),
+        ("bytecode", &resource.in_memory_bytecode),
+        ("opt-1.bytecode", &resource.in_memory_bytecode_opt1),
+        ("opt-2.bytecode", &resource.in_memory_bytecode_opt2),
+    ] {
+        if let Some(data) = value {
+            files.push((module.with_extension(suffix), data.as_ref()));
+        }
+    }
+
+    if let Some(entries) = &resource.in_memory_package_resources {
+        for (name, data) in entries {
+            files.push((package.join(name.as_ref()), data.as_ref()));
+        }
+    }
+
+    if let Some(entries) = &resource.in_memory_distribution_resources {
+        let dist_info = PathBuf::from(format!("{}.dist-info", resource.name));
+
+        for (name, data) in entries {
+            files.push((dist_info.join(name.as_ref()), data.as_ref()));
+        }
+    }
+
+    if resource.is_utf8_filename_data {
+        if let Some(data) = &resource.file_data_embedded {
+            files.push((PathBuf::from(resource.name.as_ref()), data.as_ref()));
+        }
+    }
+
+    if let Some(data) = &resource.in_memory_shared_library {
+        files.push((PathBuf::from(resource.name.as_ref()), data.as_ref()));
+    }
+
+    files.sort();
+
+    files
+}
+
+/// Extract resources in a packed resources file to a directory.
+pub fn extract_resources(path: &Path, dest_dir: &Path, names: &[&str]) -> Result<()> {
+    let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let resources = parse_packed_resources(&data)?;
+
+    for resource in resources
+        .iter()
+        .filter(|r| resource_selected(&r.name, names))
+    {
+        for (rel_path, data) in resource_extraction_files(resource) {
+            // Don't allow resource names to escape the destination directory.
+            if rel_path.is_absolute()
+                || rel_path
+                    .components()
+                    .any(|c| c == std::path::Component::ParentDir)
+            {
+                return Err(anyhow!(
+                    "refusing to extract {} to {}",
+                    resource.name,
+                    rel_path.display()
+                ));
+            }
+
+            let dest_path = dest_dir.join(&rel_path);
+            println!("writing {}", dest_path.display());
+
+            if let Some(parent) = dest_path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("creating {}", parent.display()))?;
+            }
+            std::fs::write(&dest_path, data)
+                .with_context(|| format!("writing {}", dest_path.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewrite a packed resources file using a given format version.
+pub fn convert_resources(
+    source: &Path,
+    dest: &Path,
+    version: u8,
+    interior_padding: Option<BlobInteriorPadding>,
+) -> Result<()> {
+    if !WRITABLE_FORMAT_VERSIONS.contains(&version) {
+        return Err(anyhow!(
+            "unable to write packed resources format version {}; supported versions: {:?}",
+            version,
+            WRITABLE_FORMAT_VERSIONS
+        ));
+    }
+
+    let data = std::fs::read(source).with_context(|| format!("reading {}", source.display()))?;
+    let resources = parse_packed_resources(&data)?;
+    let metadata = packed_resources_metadata(&data)?;
+
+    let mut buffer = vec![];
+    if version == 3 {
+        if !metadata.is_empty() {
+            return Err(anyhow!(
+                "unable to write metadata in packed resources format version 3"
+            ));
+        }
+
+        write_packed_resources_v3(&resources, &mut buffer, interior_padding)?;
+    } else {
+        let encoded = encode_metadata(&metadata)?;
+        let sections = if metadata.is_empty() {
+            vec![]
+        } else {
+            vec![PackedSection {
+                kind: SectionKind::Metadata.into(),
+                optional: true,
+                data: &encoded,
+            }]
+        };
+
+        write_packed_resources_v4(&resources, &mut buffer, interior_padding, 0, &sections)?;
+    }
+
+    std::fs::write(dest, &buffer).with_context(|| format!("writing {}", dest.display()))?;
+
+    println!(
+        "wrote {} resources to {} (format version {}, {} bytes)",
+        resources.len(),
+        dest.display(),
+        version,
+        buffer.len()
+    );
+
+    Ok(())
+}
+
+/// Write a delta producing a packed resources file from another.
+pub fn diff_resources(old: &Path, new: &Path, dest: &Path) -> Result<()> {
+    let old_data = std::fs::read(old).with_context(|| format!("reading {}", old.display()))?;
+    let new_data = std::fs::read(new).with_context(|| format!("reading {}", new.display()))?;
+
+    // Deltas work on any data. But diffing something other than packed
+    // resources is likely a mistake.
+    verify_resources(&old_data).map_err(|e| anyhow!("{}: {}", old.display(), e))?;
+    verify_resources(&new_data).map_err(|e| anyhow!("{}: {}", new.display(), e))?;
+
+    let delta = create_delta(&old_data, &new_data)?;
+    std::fs::write(dest, &delta).with_context(|| format!("writing {}", dest.display()))?;
+
+    println!(
+        "wrote delta to {} ({} bytes; new file is {} bytes)",
+        dest.display(),
+        delta.len(),
+        new_data.len()
+    );
+
+    Ok(())
+}
+
+/// Apply a delta written by [diff_resources()] to a packed resources file.
+pub fn patch_resources(old: &Path, delta: &Path, dest: &Path) -> Result<()> {
+    let old_data = std::fs::read(old).with_context(|| format!("reading {}", old.display()))?;
+    let delta_data =
+        std::fs::read(delta).with_context(|| format!("reading {}", delta.display()))?;
+
+    let new_data = apply_delta(&old_data, &delta_data)
+        .with_context(|| format!("applying {} to {}", delta.display(), old.display()))?;
+    std::fs::write(dest, &new_data).with_context(|| format!("writing {}", dest.display()))?;
+
+    println!("wrote {} ({} bytes)", dest.display(), new_data.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::borrow::Cow};
+
+    fn sample_resources() -> Vec<Resource<'static, u8>> {
+        vec![
+            Resource {
+                is_module: true,
+                is_package: true,
+                name: Cow::Borrowed("foo"),
+                in_memory_source: Some(Cow::Borrowed(b"import bar".as_ref())),
+                in_memory_package_resources: Some(
+                    vec![(Cow::Borrowed("data.txt"), Cow::Borrowed(b"data".as_ref()))]
+                        .into_iter()
+                        .collect(),
+                ),
+                ..Resource::default()
+            },
+            Resource {
+                is_module: true,
+                name: Cow::Borrowed("foo.bar"),
+                in_memory_bytecode: Some(Cow::Borrowed(b"bytecode".as_ref())),
+                relative_path_module_source: Some(Cow::Owned("lib/foo/bar.py".into())),
+                ..Resource::default()
+            },
+            Resource {
+                is_utf8_filename_data: true,
+                name: Cow::Borrowed("assets/logo.png"),
+                file_data_embedded: Some(Cow::Borrowed(b"png".as_ref())),
+                ..Resource::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let resources = sample_resources();
+
+        let mut data = vec![];
+        write_packed_resources_v3(&resources, &mut data, None)?;
+
+        assert_eq!(packed_resources_format_version(&data), Some(3));
+        assert_eq!(packed_resources_format_version(b"garbage"), None);
+
+        let parsed = parse_packed_resources(&data)?;
+        assert_eq!(parsed.len(), 3);
+        assert!(parsed.contains(&resources[1]));
+
+        assert!(parse_packed_resources(b"pyembed\x01").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_resources() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let mut data = vec![];
+        write_packed_resources_v3(&sample_resources(), &mut data, None)?;
+
+        let v3 = temp_dir.path().join("v3");
+        let v4 = temp_dir.path().join("v4");
+        std::fs::write(&v3, &data)?;
+
+        convert_resources(&v3, &v4, 4, None)?;
+        let converted = std::fs::read(&v4)?;
+        assert_eq!(packed_resources_format_version(&converted), Some(4));
+        assert_eq!(
+            parse_packed_resources(&converted)?,
+            parse_packed_resources(&data)?
+        );
+
+        assert!(convert_resources(&v3, &v4, 5, None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_resources_metadata() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let mut metadata = BTreeMap::new();
+        metadata.insert("python_version".to_string(), "3.9".to_string());
+        let encoded = encode_metadata(&metadata)?;
+
+        let mut data = vec![];
+        write_packed_resources_v4(
+            &sample_resources(),
+            &mut data,
+            None,
+            0,
+            &[PackedSection {
+                kind: SectionKind::Metadata.into(),
+                optional: true,
+                data: &encoded,
+            }],
+        )?;
+        assert_eq!(packed_resources_metadata(&data)?, metadata);
+
+        let source = temp_dir.path().join("source");
+        let dest = temp_dir.path().join("dest");
+        std::fs::write(&source, &data)?;
+
+        convert_resources(&source, &dest, 4, None)?;
+        assert_eq!(packed_resources_metadata(&std::fs::read(&dest)?)?, metadata);
+
+        assert!(convert_resources(&source, &dest, 3, None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_patch_resources() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let mut resources = sample_resources();
+        let mut old_data = vec![];
+        write_packed_resources_v4(&resources, &mut old_data, None, 0, &[])?;
+
+        resources[1].in_memory_bytecode = Some(Cow::Borrowed(b"new bytecode".as_ref()));
+        let mut new_data = vec![];
+        write_packed_resources_v4(&resources, &mut new_data, None, 0, &[])?;
+
+        let old = temp_dir.path().join("old");
+        let new = temp_dir.path().join("new");
+        let delta = temp_dir.path().join("delta");
+        let patched = temp_dir.path().join("patched");
+        std::fs::write(&old, &old_data)?;
+        std::fs::write(&new, &new_data)?;
+
+        diff_resources(&old, &new, &delta)?;
+        patch_resources(&old, &delta, &patched)?;
+        assert_eq!(std::fs::read(&patched)?, new_data);
+
+        // The delta only applies to the file it was created from.
+        assert!(patch_resources(&new, &delta, &patched).is_err());
+
+        std::fs::write(&old, b"garbage")?;
+        assert!(diff_resources(&old, &new, &delta).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_resources_files() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let mut data = vec![];
+        write_packed_resources_v3(&sample_resources(), &mut data, None)?;
+
+        let good = temp_dir.path().join("good");
+        let bad = temp_dir.path().join("bad");
+        std::fs::write(&good, &data)?;
+        std::fs::write(&bad, &data[0..data.len() - 1])?;
+
+        verify_resources_files(&[&good])?;
+
+        let err = verify_resources_files(&[&good, &bad]).unwrap_err();
+        assert_eq!(err.to_string(), "1 of 2 files failed validation");
+
+        assert!(parse_packed_resources(&data[0..data.len() - 1])
+            .unwrap_err()
+            .to_string()
+            .starts_with("error parsing packed resources: malformed data at offset "));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_describe_resource() {
+        let resources = sample_resources();
+
+        assert_eq!(
+            describe_resource(&resources[0]),
+            vec![
+                "foo [module, package]",
+                "    in-memory source: 10 bytes",
+                "    in-memory package resources: 1 entries, 4 bytes",
+            ]
+        );
+        assert_eq!(
+            describe_resource(&resources[1]),
+            vec![
+                "foo.bar [module]",
+                "    in-memory bytecode: 8 bytes",
+                "    relative path source: lib/foo/bar.py",
+            ]
+        );
+        assert_eq!(
+            describe_resource(&resources[2]),
+            vec!["assets/logo.png [file]", "    in-memory file data: 3 bytes"]
+        );
+    }
+
+    #[test]
+    fn test_resource_selected() {
+        assert!(resource_selected("foo.bar", &[]));
+        assert!(resource_selected("foo.bar", &["foo"]));
+        assert!(resource_selected("foo", &["foo"]));
+        assert!(!resource_selected("foobar", &["foo"]));
+        assert!(resource_selected("assets/logo.png", &["assets"]));
+    }
+
+    #[test]
+    fn test_resource_extraction_files() {
+        let resources = sample_resources();
+
+        assert_eq!(
+            resource_extraction_files(&resources[0]),
+            vec![
+                (
+                    PathBuf::from("foo").join("__init__.py"),
+                    b"import bar" as &[u8]
+                ),
+                (PathBuf::from("foo").join("data.txt"), b"data" as &[u8]),
+            ]
+        );
+        assert_eq!(
+            resource_extraction_files(&resources[1]),
+            vec![(
+                PathBuf::from("foo").join("bar.bytecode"),
+                b"bytecode" as &[u8]
+            )]
+        );
+        assert_eq!(
+            resource_extraction_files(&resources[2]),
+            vec![(PathBuf::from("assets/logo.png"), b"png" as &[u8])]
+        );
+    }
+}
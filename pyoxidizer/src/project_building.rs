@@ -4,13 +4,21 @@
 
 use {
     crate::{
-        environment::{canonicalize_path, MINIMUM_RUST_VERSION},
-        project_layout::initialize_project,
-        py_packaging::binary::{EmbeddedPythonContext, LibpythonLinkMode, PythonBinaryBuilder},
+        debug_info::process_debug_info,
+        environment::{
+            canonicalize_path, find_executable, linux_cross_compiler, MINIMUM_RUST_VERSION,
+        },
+        executable_compression::compress_executable,
+        project_layout::{customize_rust_project, initialize_project},
+        py_packaging::binary::{
+            EmbeddedPythonContext, ExecutableCompression, LibpythonLinkMode, PythonBinaryBuilder,
+            RustProjectCustomizations,
+        },
         starlark::eval::{EvaluationContext, EvaluationContextBuilder},
     },
     anyhow::{anyhow, Context, Result},
     duct::cmd,
+    fs2::FileExt,
     semver::Version,
     slog::warn,
     starlark_dialect_build_targets::ResolvedTarget,
@@ -18,14 +26,54 @@ use {
         collections::HashMap,
         convert::TryInto,
         env,
-        fs::create_dir_all,
+        fs::{create_dir_all, File},
         io::{BufRead, BufReader},
         path::{Path, PathBuf},
     },
+    tugger_file_manifest::FileManifest,
 };
 
 pub const HOST: &str = env!("HOST");
 
+/// Lock serializing builds of an executable in a shared Cargo target directory.
+///
+/// Cargo locks its target directory while building. But the built executable
+/// is read after Cargo exits, at which point another build of an executable
+/// with the same name and target, from this process or another one sharing
+/// the directory, could be overwriting it. So a lock file for the output path
+/// is held from invoking Cargo until the executable is read.
+struct CargoOutputLock {
+    file: File,
+}
+
+impl CargoOutputLock {
+    fn new(exe_path: &Path) -> Result<Self> {
+        let parent = exe_path
+            .parent()
+            .ok_or_else(|| anyhow!("unable to resolve parent of {}", exe_path.display()))?;
+        create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+
+        let mut lock_path = exe_path.as_os_str().to_os_string();
+        lock_path.push(".pyoxidizer-lock");
+        let lock_path = PathBuf::from(lock_path);
+
+        let file = File::create(&lock_path)
+            .with_context(|| format!("could not create {}", lock_path.display()))?;
+
+        file.lock_exclusive()
+            .with_context(|| format!("failed to obtain lock for {}", lock_path.display()))?;
+
+        Ok(Self { file })
+    }
+}
+
+impl Drop for CargoOutputLock {
+    fn drop(&mut self) {
+        // The lock is released when the file is closed anyway.
+        let _ = self.file.unlock();
+    }
+}
+
 /// Find a pyoxidizer.toml configuration file by walking directory ancestry.
 pub fn find_pyoxidizer_config_file(start_dir: &Path) -> Option<PathBuf> {
     for test_dir in start_dir.ancestors() {
@@ -77,6 +125,27 @@ pub fn find_pyoxidizer_config_file_env(logger: &slog::Logger, start_dir: &Path)
     find_pyoxidizer_config_file(start_dir)
 }
 
+/// Resolve the PyOxidizer configuration file to use for a project.
+///
+/// An explicitly specified file takes precedence over searching for one
+/// via [find_pyoxidizer_config_file_env].
+pub fn resolve_pyoxidizer_config_file(
+    logger: &slog::Logger,
+    project_path: &Path,
+    config_path: Option<&Path>,
+) -> Result<PathBuf> {
+    if let Some(path) = config_path {
+        return Ok(path.to_path_buf());
+    }
+
+    find_pyoxidizer_config_file_env(logger, project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })
+}
+
 /// Describes an environment and settings used to build a project.
 pub struct BuildEnvironment {
     /// Path to cargo executable to run.
@@ -100,6 +169,7 @@ impl BuildEnvironment {
         target_python_path: &Path,
         libpython_link_mode: LibpythonLinkMode,
         libpython_filename: Option<&Path>,
+        rust_project: &RustProjectCustomizations,
     ) -> Result<Self> {
         let rust_version = rustc_version::version()?;
         if rust_version.lt(&MINIMUM_RUST_VERSION) {
@@ -160,7 +230,10 @@ impl BuildEnvironment {
         // This workaround is not ideal.
         // TODO remove /FORCE:MULTIPLE once the distributions eliminate duplicate
         // symbols.
-        if target_triple.contains("-windows-") && libpython_link_mode == LibpythonLinkMode::Static {
+        let requires_static_crt =
+            target_triple.contains("-windows-") && libpython_link_mode == LibpythonLinkMode::Static;
+
+        if requires_static_crt {
             rust_flags.extend(
                 [
                     "-C".to_string(),
@@ -173,6 +246,50 @@ impl BuildEnvironment {
             );
         }
 
+        match rust_project.static_crt {
+            Some(true) if !requires_static_crt => {
+                rust_flags.push("-C".to_string());
+                rust_flags.push("target-feature=+crt-static".to_string());
+            }
+            Some(false) if requires_static_crt => {
+                return Err(anyhow!(
+                    "statically linking libpython on Windows requires the static C runtime"
+                ));
+            }
+            Some(false) => {
+                rust_flags.push("-C".to_string());
+                rust_flags.push("target-feature=-crt-static".to_string());
+            }
+            _ => {}
+        }
+
+        for arg in &rust_project.link_args {
+            rust_flags.push("-C".to_string());
+            rust_flags.push(format!("link-arg={}", arg));
+        }
+
+        // Cargo reads the linker for a target from an environment variable
+        // derived from the target triple.
+        let linker_env = format!(
+            "CARGO_TARGET_{}_LINKER",
+            target_triple.to_uppercase().replace('-', "_")
+        );
+        if let Some(linker) = rust_project.linkers.get(target_triple) {
+            envs.insert(linker_env, linker.display().to_string());
+        } else if target_triple != HOST {
+            // Cross-compiling for Linux requires a C toolchain for the target.
+            // Use a conventionally named one if present and the environment
+            // doesn't define one. The cc crate reads the C compiler from `CC_<triple>`.
+            if let Some(compiler) = linux_cross_compiler(target_triple)
+                .and_then(find_executable)
+                .map(|path| path.display().to_string())
+            {
+                envs.entry(linker_env).or_insert_with(|| compiler.clone());
+                envs.entry(format!("CC_{}", target_triple.replace('-', "_")))
+                    .or_insert(compiler);
+            }
+        }
+
         if !rust_flags.is_empty() {
             let extra_flags = rust_flags.join(" ");
 
@@ -203,8 +320,16 @@ pub struct BuiltExecutable<'a> {
     pub exe_name: String,
 
     /// Holds raw content of built executable.
+    ///
+    /// This reflects any processing of debug information and compression,
+    /// which isn't applied to the file at `exe_path`.
     pub exe_data: Vec<u8>,
 
+    /// Files holding debug information split from the executable.
+    ///
+    /// Paths are relative to the directory of the executable.
+    pub debug_files: FileManifest,
+
     /// Holds state generated from building.
     pub binary_data: EmbeddedPythonContext<'a>,
 }
@@ -231,17 +356,27 @@ pub fn build_executable_with_rust_project<'a>(
     let embedded_data = exe.to_embedded_python_context(logger, opt_level)?;
     embedded_data.write_files(&artifacts_path)?;
 
-    let build_env = BuildEnvironment::new(
+    let mut build_env = BuildEnvironment::new(
         exe.target_triple(),
         artifacts_path,
         exe.target_python_exe_path(),
         exe.libpython_link_mode(),
         embedded_data.linking_info.libpython_filename.as_deref(),
+        exe.rust_project(),
     )
     .context("resolving build environment")?;
 
     warn!(logger, "building with Rust {}", build_env.rust_version);
 
+    // Release builds don't emit debug information by default. But there is
+    // no point in splitting debug information that doesn't exist.
+    if release && exe.debug_info().split {
+        build_env.environment_vars.insert(
+            "CARGO_PROFILE_RELEASE_DEBUG".to_string(),
+            "true".to_string(),
+        );
+    }
+
     let target_base_path = build_path.join("target");
     let target_triple_base_path =
         target_base_path
@@ -284,6 +419,26 @@ pub fn build_executable_with_rust_project<'a>(
         features.push("global-allocator-snmalloc");
         features.push("allocator-snmalloc");
     }
+    if exe.python_interpreter_config().line_editing {
+        features.push("line-editing");
+    }
+    if exe.python_interpreter_config().python_logging_to_rust {
+        features.push("log-bridge");
+    }
+    if exe.python_interpreter_config().self_update.is_some() {
+        features.push("self-update");
+    }
+    if exe.python_interpreter_config().remote_resources.is_some() {
+        features.push("remote-resources");
+    }
+
+    if exe.rust_project().windows_service.is_some() {
+        features.push("windows-service");
+    }
+
+    for feature in &exe.rust_project().cargo_features {
+        features.push(feature);
+    }
 
     let features = features.join(" ");
 
@@ -292,6 +447,16 @@ pub fn build_executable_with_rust_project<'a>(
         args.push(&features);
     }
 
+    let exe_name = if target.contains("pc-windows") {
+        format!("{}.exe", bin_name)
+    } else {
+        bin_name.to_string()
+    };
+
+    let exe_path = target_triple_base_path.join(&exe_name);
+
+    let _output_lock = CargoOutputLock::new(&exe_path)?;
+
     // TODO force cargo to colorize output under certain circumstances?
     let command = cmd(build_env.cargo_exe, &args)
         .dir(&project_path)
@@ -312,25 +477,54 @@ pub fn build_executable_with_rust_project<'a>(
         return Err(anyhow!("cargo build failed"));
     }
 
-    let exe_name = if target.contains("pc-windows") {
-        format!("{}.exe", bin_name)
-    } else {
-        bin_name.to_string()
-    };
-
-    let exe_path = target_triple_base_path.join(&exe_name);
-
     if !exe_path.exists() {
         return Err(anyhow!("{} does not exist", exe_path.display()));
     }
 
-    let exe_data = std::fs::read(&exe_path)?;
     let exe_name = exe_path.file_name().unwrap().to_string_lossy().to_string();
 
+    // The target directory is shared between builds. So the executable is
+    // processed in a copy of it.
+    let (exe_data, debug_files) = if exe.debug_info().strip
+        || exe.debug_info().split
+        || exe.executable_compression() != ExecutableCompression::None
+    {
+        let temp_dir = crate::environment::temp_dir(exe.cache_path(), "pyoxidizer-exe")?;
+        let processed_path = temp_dir.path().join(&exe_name);
+        std::fs::copy(&exe_path, &processed_path)
+            .with_context(|| format!("copying {}", exe_path.display()))?;
+
+        let pdb_path = target_triple_base_path.join(format!("{}.pdb", bin_name.replace('-', "_")));
+
+        let debug_files = process_debug_info(
+            logger,
+            target,
+            exe.debug_info(),
+            &processed_path,
+            Some(&pdb_path),
+        )
+        .context("processing debug information")?;
+
+        // Compression must come last, as the compressed executable can't be
+        // processed by other tools.
+        compress_executable(
+            logger,
+            target,
+            exe.executable_compression(),
+            &processed_path,
+        )
+        .context("compressing executable")?;
+
+        (std::fs::read(&processed_path)?, debug_files)
+    } else {
+        (std::fs::read(&exe_path)?, FileManifest::default())
+    };
+
     Ok(BuiltExecutable {
         exe_path: Some(exe_path),
         exe_name,
         exe_data,
+        debug_files,
         binary_data: embedded_data,
     })
 }
@@ -349,11 +543,20 @@ pub fn build_python_executable<'a>(
     let env = crate::environment::resolve_environment()?;
     let pyembed_location = env.as_pyembed_location();
 
-    let temp_dir = tempfile::Builder::new().prefix("pyoxidizer").tempdir()?;
+    let temp_dir = crate::environment::temp_dir(exe.cache_path(), "pyoxidizer")?;
 
     // Directory needs to have name of project.
     let project_path = temp_dir.path().join(bin_name);
-    let build_path = temp_dir.path().join("build");
+
+    // Cargo's target directory is persisted in the cache so dependencies
+    // don't need to be recompiled on every build. Cargo locks the directory
+    // while building. But the executable is read after Cargo exits, so
+    // builds of the same executable also hold a `CargoOutputLock`.
+    let build_path = if let Some(root) = exe.cache_path() {
+        root.join("cargo")
+    } else {
+        temp_dir.path().join("build")
+    };
     let artifacts_path = temp_dir.path().join("artifacts");
 
     initialize_project(
@@ -363,6 +566,7 @@ pub fn build_python_executable<'a>(
         &[],
         exe.windows_subsystem(),
     )?;
+    customize_rust_project(&project_path, exe.rust_project(), exe.windows_subsystem())?;
 
     let mut build = build_executable_with_rust_project(
         logger,
@@ -5,8 +5,17 @@
 //! Handle file layout of PyOxidizer projects.
 
 use {
-    crate::environment::{PyOxidizerSource, BUILD_GIT_COMMIT, PYOXIDIZER_VERSION},
-    anyhow::{anyhow, Result},
+    crate::{
+        environment::{PyOxidizerSource, BUILD_GIT_COMMIT, PYOXIDIZER_VERSION},
+        py_packaging::{
+            binary::{
+                parse_windows_version, RustProjectCustomizations, WindowsService,
+                WindowsVersionInfo,
+            },
+            project_metadata::ProjectMetadata,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
     handlebars::Handlebars,
     once_cell::sync::Lazy,
     python_packaging::filesystem_scanning::walk_tree_files,
@@ -48,6 +57,12 @@ static HANDLEBARS: Lazy<Handlebars<'static>> = Lazy::new(|| {
             include_str!("templates/new-pyoxidizer.bzl.hbs"),
         )
         .unwrap();
+    handlebars
+        .register_template_string(
+            "version-info.rc",
+            include_str!("templates/version-info.rc.hbs"),
+        )
+        .unwrap();
 
     handlebars
 });
@@ -71,7 +86,9 @@ struct TemplateData {
     python_distributions: Vec<PythonDistribution>,
     program_name: Option<String>,
     code: Option<String>,
+    run_module: Option<String>,
     pip_install_simple: Vec<String>,
+    install_project: bool,
 }
 
 impl TemplateData {
@@ -86,7 +103,9 @@ impl TemplateData {
             python_distributions: Vec::new(),
             program_name: None,
             code: None,
+            run_module: None,
             pip_install_simple: Vec::new(),
+            install_project: false,
         }
     }
 }
@@ -163,15 +182,68 @@ pub fn write_new_build_rs(path: &Path, program_name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Fields of a `pyembed::WindowsServiceConfig`, as Rust expressions.
+#[derive(Serialize)]
+struct WindowsServiceTemplateData {
+    name: String,
+    display_name: String,
+    description: String,
+    entry_point: String,
+    stop_entry_point: String,
+}
+
+impl From<&WindowsService> for WindowsServiceTemplateData {
+    fn from(service: &WindowsService) -> Self {
+        let string = |value: &str| format!("{:?}.to_string()", value);
+        let optional_string = |value: &Option<String>| match value {
+            Some(value) => format!("Some({})", string(value)),
+            None => "None".to_string(),
+        };
+
+        Self {
+            name: string(&service.name),
+            display_name: string(&service.display_name),
+            description: optional_string(&service.description),
+            entry_point: string(&service.entry_point),
+            stop_entry_point: optional_string(&service.stop_entry_point),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct MainRsTemplateData {
+    windows_subsystem: String,
+    modules: Vec<String>,
+    pre_init_hook: Option<String>,
+    windows_service: Option<WindowsServiceTemplateData>,
+}
+
 /// Write a new main.rs file that runs the embedded Python interpreter.
 ///
 /// `windows_subsystem` is the value of the `windows_subsystem` Rust attribute.
 pub fn write_new_main_rs(path: &Path, windows_subsystem: &str) -> Result<()> {
-    let mut data: BTreeMap<String, String> = BTreeMap::new();
-    data.insert(
-        "windows_subsystem".to_string(),
-        windows_subsystem.to_string(),
-    );
+    write_main_rs(path, windows_subsystem, &[], None, None)
+}
+
+/// Write a main.rs file that runs the embedded Python interpreter.
+///
+/// `modules` are names of modules to declare in the crate.
+/// `pre_init_hook` is the path of a function called with the interpreter
+/// config before the interpreter is initialized.
+/// `windows_service` makes the executable run as a service on Windows.
+fn write_main_rs(
+    path: &Path,
+    windows_subsystem: &str,
+    modules: &[String],
+    pre_init_hook: Option<&str>,
+    windows_service: Option<&WindowsService>,
+) -> Result<()> {
+    let data = MainRsTemplateData {
+        windows_subsystem: windows_subsystem.to_string(),
+        modules: modules.to_vec(),
+        pre_init_hook: pre_init_hook.map(|s| s.to_string()),
+        windows_service: windows_service.map(WindowsServiceTemplateData::from),
+    };
     let t = HANDLEBARS.render("new-main.rs", &data)?;
 
     println!("writing {}", path.to_str().unwrap());
@@ -181,6 +253,69 @@ pub fn write_new_main_rs(path: &Path, windows_subsystem: &str) -> Result<()> {
     Ok(())
 }
 
+/// Apply customizations to a Rust project created by `initialize_project()`.
+///
+/// Source files are copied into the `src` directory of the project. The
+/// project's `main.rs` is replaced by the custom main file, if defined, or
+/// regenerated to declare the added modules, call the pre-init hook, and run
+/// as a Windows service.
+pub fn customize_rust_project(
+    project_path: &Path,
+    customizations: &RustProjectCustomizations,
+    windows_subsystem: &str,
+) -> Result<()> {
+    let src_path = project_path.join("src");
+
+    let mut modules = vec![];
+    for source in &customizations.sources {
+        let module = source
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| anyhow!("unable to resolve module name of {}", source.display()))?;
+
+        let dest_path = src_path.join(format!("{}.rs", module));
+        println!("copying {} to {}", source.display(), dest_path.display());
+        std::fs::copy(source, &dest_path)
+            .with_context(|| format!("copying {}", source.display()))?;
+
+        modules.push(module.to_string());
+    }
+
+    let main_path = src_path.join("main.rs");
+
+    if let Some(custom_main) = &customizations.main_path {
+        println!(
+            "copying {} to {}",
+            custom_main.display(),
+            main_path.display()
+        );
+        std::fs::copy(custom_main, &main_path)
+            .with_context(|| format!("copying {}", custom_main.display()))?;
+    } else if !modules.is_empty()
+        || customizations.pre_init_hook.is_some()
+        || customizations.windows_service.is_some()
+    {
+        write_main_rs(
+            &main_path,
+            windows_subsystem,
+            &modules,
+            customizations.pre_init_hook.as_deref(),
+            customizations.windows_service.as_ref(),
+        )?;
+    }
+
+    if let Some(version_info) = &customizations.windows_version_info {
+        let name = project_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("unable to resolve project name"))?;
+
+        write_windows_version_info(project_path, name, version_info)?;
+    }
+
+    Ok(())
+}
+
 /// Writes default PyOxidizer config files into a project directory.
 pub fn write_new_pyoxidizer_config_file(
     project_dir: &Path,
@@ -188,21 +323,74 @@ pub fn write_new_pyoxidizer_config_file(
     code: Option<&str>,
     pip_install: &[&str],
 ) -> Result<()> {
-    let path = project_dir.join("pyoxidizer.bzl");
-
     let mut data = TemplateData::new();
     populate_template_data(&mut data);
     data.program_name = Some(name.to_string());
 
     if let Some(code) = code {
-        // Replace " with \" to work around
-        // https://github.com/google/starlark-rust/issues/230.
-        data.code = Some(code.replace("\"", "\\\""));
+        data.code = Some(escape_template_code(code));
     }
 
     data.pip_install_simple = pip_install.iter().map(|v| (*v).to_string()).collect();
 
-    let t = HANDLEBARS.render("new-pyoxidizer.bzl", &data)?;
+    write_pyoxidizer_config_file(project_dir, &data)
+}
+
+/// Writes a PyOxidizer config file packaging the Python project in a directory.
+///
+/// The project itself is installed from the directory containing the config
+/// file. Its dependencies are installed individually so they are easy to
+/// adjust. If the project defines console scripts, the first one is run by
+/// the built executable.
+pub fn write_project_pyoxidizer_config_file(
+    project_dir: &Path,
+    name: &str,
+    metadata: &ProjectMetadata,
+    code: Option<&str>,
+    pip_install: &[&str],
+) -> Result<()> {
+    let mut data = TemplateData::new();
+    populate_template_data(&mut data);
+    data.program_name = Some(name.to_string());
+    data.install_project = true;
+
+    if let Some(code) = code {
+        data.code = Some(escape_template_code(code));
+    } else if let Some(script) = metadata.console_scripts.first() {
+        match &script.attr {
+            Some(attr) => {
+                data.code = Some(escape_template_code(&format!(
+                    "import sys, {}; sys.exit({}.{}())",
+                    script.module, script.module, attr
+                )));
+            }
+            None => {
+                data.run_module = Some(script.module.clone());
+            }
+        }
+    }
+
+    data.pip_install_simple = metadata
+        .requirements
+        .iter()
+        .map(|v| escape_template_code(v))
+        .chain(pip_install.iter().map(|v| (*v).to_string()))
+        .collect();
+
+    write_pyoxidizer_config_file(project_dir, &data)
+}
+
+/// Escape a value for inclusion in a string literal in a config file.
+fn escape_template_code(value: &str) -> String {
+    // Replace " with \" to work around
+    // https://github.com/google/starlark-rust/issues/230.
+    value.replace("\"", "\\\"")
+}
+
+fn write_pyoxidizer_config_file(project_dir: &Path, data: &TemplateData) -> Result<()> {
+    let path = project_dir.join("pyoxidizer.bzl");
+
+    let t = HANDLEBARS.render("new-pyoxidizer.bzl", data)?;
 
     println!("writing {}", path.to_str().unwrap());
     let mut fh = std::fs::File::create(path)?;
@@ -238,6 +426,90 @@ pub fn write_application_manifest(project_dir: &Path, program_name: &str) -> Res
     Ok(())
 }
 
+#[derive(Serialize)]
+struct VersionInfoString {
+    name: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+struct VersionInfoTemplateData {
+    file_version: String,
+    product_version: String,
+    strings: Vec<VersionInfoString>,
+}
+
+/// Escape a value for use in a resource file string literal.
+fn escape_rc_string(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\"\"")
+        .replace(|c: char| c.is_control(), " ")
+}
+
+/// Add a `VERSIONINFO` resource to the resource file of a project.
+///
+/// The resource is appended to the resource file written by
+/// [write_application_manifest], which the build script compiles on Windows.
+pub fn write_windows_version_info(
+    project_dir: &Path,
+    program_name: &str,
+    version_info: &WindowsVersionInfo,
+) -> Result<()> {
+    let file_version = version_info.file_version.as_deref().unwrap_or("0");
+    let product_version = version_info
+        .product_version
+        .as_deref()
+        .unwrap_or(file_version);
+
+    let format_version = |value: &str| -> Result<String> {
+        Ok(parse_windows_version(value)?
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(","))
+    };
+
+    let mut strings = vec![];
+    let mut add_string = |name: &str, value: Option<&str>| {
+        if let Some(value) = value {
+            strings.push(VersionInfoString {
+                name: name.to_string(),
+                value: escape_rc_string(value),
+            });
+        }
+    };
+
+    add_string("CompanyName", version_info.company_name.as_deref());
+    add_string("FileDescription", version_info.file_description.as_deref());
+    add_string("FileVersion", Some(file_version));
+    add_string("InternalName", Some(program_name));
+    add_string("LegalCopyright", version_info.copyright.as_deref());
+    add_string(
+        "OriginalFilename",
+        Some(format!("{}.exe", program_name).as_str()),
+    );
+    add_string("ProductName", version_info.product_name.as_deref());
+    add_string("ProductVersion", Some(product_version));
+
+    let data = VersionInfoTemplateData {
+        file_version: format_version(file_version)?,
+        product_version: format_version(product_version)?,
+        strings,
+    };
+
+    let rc_path = project_dir.join(format!("{}-manifest.rc", program_name));
+    let rc_data = HANDLEBARS.render("version-info.rc", &data)?;
+    println!("writing version info to {}", rc_path.display());
+    let mut fh = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&rc_path)
+        .with_context(|| format!("opening {}", rc_path.display()))?;
+    fh.write_all(rc_data.as_bytes())?;
+
+    Ok(())
+}
+
 /// Add PyOxidizer to an existing Rust project on the filesystem.
 ///
 /// The target directory must not already have PyOxidizer files. This
@@ -359,6 +631,12 @@ pub fn update_new_cargo_toml(path: &Path, pyembed_location: &PyembedLocation) ->
     content.push_str("allocator-mimalloc = [\"pyembed/mimalloc\"]\n");
     content.push_str("allocator-snmalloc = [\"pyembed/snmalloc\"]\n");
     content.push('\n');
+    content.push_str("line-editing = [\"pyembed/line-editing\"]\n");
+    content.push_str("log-bridge = [\"pyembed/log-bridge\"]\n");
+    content.push_str("self-update = [\"pyembed/self-update\"]\n");
+    content.push_str("remote-resources = [\"pyembed/remote-resources\"]\n");
+    content.push_str("windows-service = [\"pyembed/windows-service\"]\n");
+    content.push('\n');
     content.push_str("build-mode-pyoxidizer-exe = [\"pyembed/build-mode-pyoxidizer-exe\"]\n");
     content
         .push_str("build-mode-prebuilt-artifacts = [\"pyembed/build-mode-prebuilt-artifacts\"]\n");
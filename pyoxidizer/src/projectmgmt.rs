@@ -6,16 +6,20 @@
 
 use {
     crate::{
-        project_building::find_pyoxidizer_config_file_env,
-        project_layout::{initialize_project, write_new_pyoxidizer_config_file},
+        project_building::{find_pyoxidizer_config_file_env, resolve_pyoxidizer_config_file},
+        project_layout::{
+            initialize_project, write_new_pyoxidizer_config_file,
+            write_project_pyoxidizer_config_file,
+        },
         py_packaging::{
             distribution::{
                 default_distribution_location, resolve_distribution,
                 resolve_python_distribution_archive, DistributionFlavor,
             },
+            project_metadata::read_project_metadata,
             standalone_distribution::StandaloneDistribution,
         },
-        starlark::eval::EvaluationContextBuilder,
+        starlark::eval::{EvaluationContext, EvaluationContextBuilder},
     },
     anyhow::{anyhow, Result},
     python_packaging::{
@@ -36,6 +40,8 @@ pub fn default_target() -> Result<String> {
     if cfg!(target_os = "linux") {
         Ok("x86_64-unknown-linux-gnu".to_string())
     } else if cfg!(target_os = "windows") {
+        // There is no built-in distribution for aarch64-pc-windows-msvc.
+        // ARM64 Windows runs x86_64 executables with emulation.
         Ok("x86_64-pc-windows-msvc".to_string())
     } else if cfg!(target_os = "macos") {
         if cfg!(target_arch = "aarch64") {
@@ -94,20 +100,18 @@ pub fn list_targets(logger: &slog::Logger, project_path: &Path) -> Result<()> {
 ///
 /// This is a glorified wrapper around `cargo build`. Our goal is to get the
 /// output from repackaging to give the user something for debugging.
+/// `config_path` is the configuration file to use. If not defined, one is
+/// searched for from `project_path`.
 pub fn build(
     logger: &slog::Logger,
     project_path: &Path,
+    config_path: Option<&Path>,
     target_triple: Option<&str>,
     resolve_targets: Option<Vec<String>>,
     release: bool,
     verbose: bool,
 ) -> Result<()> {
-    let config_path = find_pyoxidizer_config_file_env(logger, project_path).ok_or_else(|| {
-        anyhow!(
-            "unable to find PyOxidizer config file at {}",
-            project_path.display()
-        )
-    })?;
+    let config_path = resolve_pyoxidizer_config_file(logger, project_path, config_path)?;
     let target_triple = resolve_target(target_triple)?;
 
     let mut context =
@@ -155,7 +159,124 @@ pub fn run(
     context.run_target(target)
 }
 
+/// Starlark types whose build performs a publish action.
+const PUBLISH_TYPES: &[&str] = &["GitHubReleaseUpload", "S3Upload"];
+
+/// Build a target after recursively building its dependencies.
+fn build_target_with_depends(
+    context: &mut EvaluationContext,
+    target: &str,
+    built: &mut Vec<String>,
+) -> Result<()> {
+    if built.iter().any(|t| t == target) {
+        return Ok(());
+    }
+
+    for depend in context.target_depends(target)? {
+        build_target_with_depends(context, &depend, built)?;
+    }
+
+    context.build_resolved_target(target)?;
+    built.push(target.to_string());
+
+    Ok(())
+}
+
+/// Perform the publish actions defined by targets.
+///
+/// Each target must resolve to a publish action. The targets it depends on
+/// are built first, so artifacts exist by the time they are uploaded.
+pub fn publish(
+    logger: &slog::Logger,
+    project_path: &Path,
+    target_triple: Option<&str>,
+    release: bool,
+    targets: Vec<String>,
+    verbose: bool,
+) -> Result<()> {
+    let config_path = find_pyoxidizer_config_file_env(logger, project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+    let target_triple = resolve_target(target_triple)?;
+
+    let mut context =
+        EvaluationContextBuilder::new(logger.clone(), config_path.clone(), target_triple)
+            .release(release)
+            .verbose(verbose)
+            .resolve_targets(targets.clone())
+            .into_context()?;
+
+    context.evaluate_file(&config_path)?;
+
+    for target in &targets {
+        match context.target_value_type(target)? {
+            Some(t) if PUBLISH_TYPES.contains(&t.as_str()) => {}
+            Some(t) => {
+                return Err(anyhow!(
+                    "target {} is not a publish action; it resolves to {}",
+                    target,
+                    t
+                ));
+            }
+            None => return Err(anyhow!("target {} is not resolved", target)),
+        }
+    }
+
+    let mut built = vec![];
+    for target in &targets {
+        build_target_with_depends(&mut context, target, &mut built)?;
+    }
+
+    println!("published {}", targets.join(", "));
+
+    Ok(())
+}
+
+/// Delete the build cache of a project.
+///
+/// The cache is `PYOXIDIZER_CACHE_DIR` if defined. Otherwise the project's
+/// config file is evaluated to resolve its build path.
+pub fn cache_clean(logger: &slog::Logger, project_path: &Path) -> Result<()> {
+    let cache_path = if let Some(path) = crate::environment::cache_dir_from_env() {
+        path
+    } else {
+        let config_path =
+            find_pyoxidizer_config_file_env(logger, project_path).ok_or_else(|| {
+                anyhow!(
+                    "unable to find PyOxidizer config file at {}",
+                    project_path.display()
+                )
+            })?;
+
+        let mut context =
+            EvaluationContextBuilder::new(logger.clone(), config_path.clone(), default_target()?)
+                .resolve_targets(vec![])
+                .into_context()?;
+
+        context.evaluate_file(&config_path)?;
+
+        crate::environment::project_cache_dir(
+            &context
+                .build_path()
+                .map_err(|e| anyhow!("resolving build path: {:?}", e))?,
+        )
+    };
+
+    if cache_path.exists() {
+        println!("removing {}", cache_path.display());
+        std::fs::remove_dir_all(&cache_path)?;
+    } else {
+        println!("{} does not exist", cache_path.display());
+    }
+
+    Ok(())
+}
+
 /// Find resources given a source path.
+#[allow(clippy::too_many_arguments)]
 pub fn find_resources(
     logger: &slog::Logger,
     path: Option<&Path>,
@@ -164,6 +285,7 @@ pub fn find_resources(
     target_triple: &str,
     classify_files: bool,
     emit_files: bool,
+    json: bool,
 ) -> Result<()> {
     let distribution_location =
         default_distribution_location(&DistributionFlavor::Standalone, target_triple, None)?;
@@ -183,53 +305,191 @@ pub fn find_resources(
 
     let dist = resolve_distribution(logger, &distribution_location, extract_path)?;
 
-    if scan_distribution {
-        println!("scanning distribution");
-        for resource in dist.python_resources() {
-            print_resource(&resource);
+    // Status messages would corrupt JSON output.
+    let status = |message: String| {
+        if !json {
+            println!("{}", message);
         }
+    };
+
+    let mut resources = vec![];
+
+    if scan_distribution {
+        status("scanning distribution".to_string());
+        resources.extend(dist.python_resources());
     } else if let Some(path) = path {
         if path.is_dir() {
-            println!("scanning directory {}", path.display());
-            for resource in find_python_resources(
-                path,
+            let roots = if let Some(site_packages) = virtualenv_site_packages(path)? {
+                status(format!("scanning virtualenv {}", path.display()));
+                site_packages
+            } else {
+                vec![path.to_path_buf()]
+            };
+
+            for root in roots {
+                status(format!("scanning directory {}", root.display()));
+                for resource in find_python_resources(
+                    &root,
+                    dist.cache_tag(),
+                    &dist.python_module_suffixes()?,
+                    emit_files,
+                    classify_files,
+                ) {
+                    resources.push(resource?);
+                }
+            }
+        } else if path.is_file()
+            && path
+                .extension()
+                .map_or(false, |extension| extension.to_string_lossy() == "whl")
+        {
+            status(format!("parsing {} as a wheel archive", path.display()));
+            let wheel = WheelArchive::from_path(path)?;
+
+            resources.extend(wheel.python_resources(
                 dist.cache_tag(),
                 &dist.python_module_suffixes()?,
                 emit_files,
                 classify_files,
-            ) {
-                print_resource(&resource?);
-            }
-        } else if path.is_file() {
-            if let Some(extension) = path.extension() {
-                if extension.to_string_lossy() == "whl" {
-                    println!("parsing {} as a wheel archive", path.display());
-                    let wheel = WheelArchive::from_path(path)?;
-
-                    for resource in wheel.python_resources(
-                        dist.cache_tag(),
-                        &dist.python_module_suffixes()?,
-                        emit_files,
-                        classify_files,
-                    )? {
-                        print_resource(&resource)
-                    }
-
-                    return Ok(());
-                }
-            }
-
-            println!("do not know how to find resources in {}", path.display());
+            )?);
         } else {
-            println!("do not know how to find resources in {}", path.display());
+            return Err(anyhow!(
+                "do not know how to find resources in {}",
+                path.display()
+            ));
         }
     } else {
-        println!("do not know what to scan");
+        return Err(anyhow!("do not know what to scan"));
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::Value::Array(
+                resources.iter().map(resource_json).collect()
+            ))?
+        );
+    } else {
+        for resource in &resources {
+            print_resource(resource);
+        }
     }
 
     Ok(())
 }
 
+/// Resolve the `site-packages` directories of a virtualenv.
+///
+/// Returns `None` if the path isn't the root directory of a virtualenv.
+fn virtualenv_site_packages(path: &Path) -> Result<Option<Vec<PathBuf>>> {
+    if !path.join("pyvenv.cfg").is_file() {
+        return Ok(None);
+    }
+
+    // Windows virtualenvs have a single `Lib/site-packages`. Others have
+    // `lib/pythonX.Y/site-packages`.
+    let mut site_packages = vec![];
+
+    let windows_path = path.join("Lib").join("site-packages");
+    if windows_path.is_dir() {
+        site_packages.push(windows_path);
+    }
+
+    let lib_path = path.join("lib");
+    if lib_path.is_dir() {
+        for entry in std::fs::read_dir(&lib_path)? {
+            let entry = entry?;
+
+            if entry.file_name().to_string_lossy().starts_with("python") {
+                let candidate = entry.path().join("site-packages");
+                if candidate.is_dir() && !site_packages.contains(&candidate) {
+                    site_packages.push(candidate);
+                }
+            }
+        }
+    }
+
+    site_packages.sort();
+
+    Ok(Some(site_packages))
+}
+
+fn file_data_json(data: &FileData) -> serde_json::Value {
+    match data {
+        FileData::Path(p) => serde_json::Value::String(p.display().to_string()),
+        FileData::Memory(_) => serde_json::Value::Null,
+    }
+}
+
+/// Obtain a JSON representation of a resource.
+///
+/// The `type` key identifies the kind of resource. Values of `path` keys are
+/// `null` for resources not backed by a file.
+fn resource_json(r: &PythonResource) -> serde_json::Value {
+    match r {
+        PythonResource::ModuleSource(m) => serde_json::json!({
+            "type": "module-source",
+            "name": m.name,
+            "is_package": m.is_package,
+            "is_stdlib": m.is_stdlib,
+            "is_test": m.is_test,
+            "path": file_data_json(&m.source),
+        }),
+        PythonResource::ModuleBytecode(m) => serde_json::json!({
+            "type": "module-bytecode",
+            "name": m.name,
+            "is_package": m.is_package,
+            "is_stdlib": m.is_stdlib,
+            "is_test": m.is_test,
+            "optimize_level": i32::from(m.optimize_level),
+        }),
+        PythonResource::ModuleBytecodeRequest(m) => serde_json::json!({
+            "type": "module-bytecode-request",
+            "name": m.name,
+            "is_package": m.is_package,
+            "optimize_level": i32::from(m.optimize_level),
+        }),
+        PythonResource::PackageResource(r) => serde_json::json!({
+            "type": "package-resource",
+            "package": r.leaf_package,
+            "name": r.relative_name,
+            "is_stdlib": r.is_stdlib,
+            "is_test": r.is_test,
+            "path": file_data_json(&r.data),
+        }),
+        PythonResource::PackageDistributionResource(r) => serde_json::json!({
+            "type": "package-distribution-resource",
+            "package": r.package,
+            "version": r.version,
+            "name": r.name,
+            "path": file_data_json(&r.data),
+        }),
+        PythonResource::ExtensionModule(em) => serde_json::json!({
+            "type": "extension-module",
+            "name": em.name,
+            "is_package": em.is_package,
+            "is_stdlib": em.is_stdlib,
+            "is_builtin": em.builtin_default,
+            "path": em.shared_library.as_ref().map(file_data_json),
+            "has_object_files": !em.object_file_data.is_empty(),
+            "link_libraries": em.link_libraries.iter().map(|l| l.name.clone()).collect::<Vec<_>>(),
+        }),
+        PythonResource::EggFile(e) => serde_json::json!({
+            "type": "egg-file",
+            "path": file_data_json(&e.data),
+        }),
+        PythonResource::PathExtension(pe) => serde_json::json!({
+            "type": "path-extension",
+            "path": file_data_json(&pe.data),
+        }),
+        PythonResource::File(f) => serde_json::json!({
+            "type": "file",
+            "path": f.path.display().to_string(),
+            "is_executable": f.entry.executable,
+        }),
+    }
+}
+
 fn print_resource(r: &PythonResource) {
     match r {
         PythonResource::ModuleSource(m) => println!(
@@ -275,11 +535,30 @@ fn print_resource(r: &PythonResource) {
     }
 }
 
+/// Derive a project name from the name of its directory.
+fn project_name_from_dir(project_dir: &Path) -> Result<String> {
+    let path = project_dir.canonicalize()?;
+
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.to_string())
+        .ok_or_else(|| {
+            anyhow!(
+                "unable to derive a project name from {}; directory names must be valid UTF-8",
+                path.display()
+            )
+        })
+}
+
 /// Initialize a PyOxidizer configuration file in a given directory.
+///
+/// If `from_project` is set, the directory must contain a Python project
+/// and the configuration file is tailored to package it.
 pub fn init_config_file(
     project_dir: &Path,
     code: Option<&str>,
     pip_install: &[&str],
+    from_project: bool,
 ) -> Result<()> {
     if project_dir.exists() && !project_dir.is_dir() {
         return Err(anyhow!(
@@ -288,13 +567,58 @@ pub fn init_config_file(
         ));
     }
 
+    if from_project {
+        let metadata = read_project_metadata(project_dir)?.ok_or_else(|| {
+            anyhow!(
+                "no pyproject.toml, setup.cfg, or setup.py in {}",
+                project_dir.display()
+            )
+        })?;
+
+        let name = match &metadata.name {
+            Some(name) => name.clone(),
+            None => project_name_from_dir(project_dir)?,
+        };
+
+        write_project_pyoxidizer_config_file(project_dir, &name, &metadata, code, pip_install)?;
+
+        println!();
+        println!("A new PyOxidizer configuration file has been created.");
+        println!(
+            "It installs {} dependencies of {} and the project itself.",
+            metadata.requirements.len(),
+            name
+        );
+        if code.is_none() {
+            if let Some(script) = metadata.console_scripts.first() {
+                println!(
+                    "The built executable runs the {} console script.",
+                    script.name
+                );
+            } else {
+                println!("No console scripts were found. The built executable");
+                println!("invokes a Python REPL by default.");
+            }
+        }
+        println!();
+        println!("To build and run the application:");
+        println!();
+        println!("  $ cd {}", project_dir.display());
+        println!("  $ pyoxidizer run");
+        println!();
+        println!("Project metadata is detected on a best effort basis. You may");
+        println!("need to edit the configuration file to change behavior.");
+
+        return Ok(());
+    }
+
     if !project_dir.exists() {
         create_dir_all(project_dir)?;
     }
 
-    let name = project_dir.iter().last().unwrap().to_str().unwrap();
+    let name = project_name_from_dir(project_dir)?;
 
-    write_new_pyoxidizer_config_file(project_dir, name, code, pip_install)?;
+    write_new_pyoxidizer_config_file(project_dir, &name, code, pip_install)?;
 
     println!();
     println!("A new PyOxidizer configuration file has been created.");
@@ -349,7 +673,7 @@ pub fn python_distribution_extract(
         let location =
             default_distribution_location(&DistributionFlavor::Standalone, env!("HOST"), None)?;
 
-        resolve_python_distribution_archive(&location, Path::new(dest_path))?
+        resolve_python_distribution_archive(&location, Path::new(dest_path), &[])?
     } else {
         return Err(anyhow!("do not know what distribution to operate on"));
     };
@@ -531,3 +855,91 @@ pub fn python_distribution_licenses(path: &str) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        python_packaging::resource::{PythonModuleSource, PythonPackageResource},
+        std::borrow::Cow,
+    };
+
+    #[test]
+    fn test_virtualenv_site_packages() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let root = temp_dir.path();
+
+        assert_eq!(virtualenv_site_packages(root)?, None);
+
+        std::fs::write(root.join("pyvenv.cfg"), b"home = /usr/bin\n")?;
+        assert_eq!(virtualenv_site_packages(root)?, Some(vec![]));
+
+        let site_packages = root.join("lib").join("python3.8").join("site-packages");
+        create_dir_all(&site_packages)?;
+        create_dir_all(root.join("lib").join("other"))?;
+        assert_eq!(virtualenv_site_packages(root)?, Some(vec![site_packages]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_name_from_dir() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let project_dir = temp_dir.path().join("myapp");
+        std::fs::create_dir(&project_dir)?;
+
+        assert_eq!(project_name_from_dir(&project_dir)?, "myapp");
+        assert_eq!(project_name_from_dir(&project_dir.join("."))?, "myapp");
+        assert!(project_name_from_dir(&temp_dir.path().join("missing")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resource_json() {
+        let source = PythonResource::ModuleSource(Cow::Owned(PythonModuleSource {
+            name: "foo".to_string(),
+            source: FileData::Path(PathBuf::from("foo.py")),
+            is_package: false,
+            cache_tag: "cpython-38".to_string(),
+            is_stdlib: false,
+            is_test: false,
+        }));
+
+        assert_eq!(
+            resource_json(&source),
+            serde_json::json!({
+                "type": "module-source",
+                "name": "foo",
+                "is_package": false,
+                "is_stdlib": false,
+                "is_test": false,
+                "path": "foo.py",
+            })
+        );
+
+        let resource = PythonResource::PackageResource(Cow::Owned(PythonPackageResource {
+            leaf_package: "foo".to_string(),
+            relative_name: "data.txt".to_string(),
+            data: FileData::Memory(b"data".to_vec()),
+            is_stdlib: false,
+            is_test: false,
+        }));
+
+        assert_eq!(
+            resource_json(&resource),
+            serde_json::json!({
+                "type": "package-resource",
+                "package": "foo",
+                "name": "data.txt",
+                "is_stdlib": false,
+                "is_test": false,
+                "path": null,
+            })
+        );
+    }
+}
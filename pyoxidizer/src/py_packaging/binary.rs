@@ -7,31 +7,56 @@ Defining and manipulating binaries embedding Python.
 */
 
 use {
-    super::config::PyembedPythonInterpreterConfig,
+    super::{config::PyembedPythonInterpreterConfig, filtering::ResourceFilter},
     anyhow::{anyhow, Context, Result},
     python_packaging::{
+        bytecode::BytecodeCache,
+        import_graph::ImportGraph,
         policy::PythonPackagingPolicy,
         resource::{
-            PythonExtensionModule, PythonModuleSource, PythonPackageDistributionResource,
-            PythonPackageResource, PythonResource,
+            PythonExtensionModule, PythonModuleBytecode, PythonModuleSource,
+            PythonPackageDistributionResource, PythonPackageResource, PythonResource,
         },
         resource_collection::{
             CompiledResourcesCollection, PrePackagedResource, PythonResourceAddCollectionContext,
         },
     },
     std::{
-        collections::HashMap,
+        collections::{BTreeMap, BTreeSet, HashMap},
         convert::TryFrom,
         io::Write,
-        path::{Path, PathBuf},
-        sync::Arc,
+        path::{Component, Path, PathBuf},
+        sync::{Arc, Mutex},
     },
-    tugger_file_manifest::{File, FileManifest},
+    tugger_file_manifest::{File, FileEntry, FileManifest},
     tugger_windows::VcRedistributablePlatform,
 };
 
 include!("../pyembed-license.rs");
 
+/// Lexically resolve `.` and `..` components of a path.
+///
+/// Leading `..` components of relative paths are preserved.
+pub fn normalize_relative_path(path: &Path) -> PathBuf {
+    let mut components: Vec<Component> = vec![];
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match components.last() {
+                Some(Component::Normal(_)) => {
+                    components.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => components.push(component),
+            },
+            _ => components.push(component),
+        }
+    }
+
+    components.iter().collect()
+}
+
 /// How a binary should link against libpython.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum LibpythonLinkMode {
@@ -108,6 +133,99 @@ impl TryFrom<&str> for PackedResourcesLoadMode {
     }
 }
 
+/// A build system able to consume embedded Python artifacts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BuildSystem {
+    /// Bazel. A `BUILD.bazel` and `WORKSPACE` file are written.
+    Bazel,
+
+    /// Buck. A `BUCK` file is written.
+    Buck,
+}
+
+impl ToString for BuildSystem {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Bazel => "bazel",
+            Self::Buck => "buck",
+        }
+        .to_string()
+    }
+}
+
+impl TryFrom<&str> for BuildSystem {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "bazel" => Ok(Self::Bazel),
+            "buck" => Ok(Self::Buck),
+            _ => Err(format!(
+                "{} is not a valid build system; must be 'bazel' or 'buck'",
+                value
+            )),
+        }
+    }
+}
+
+/// Format a string as a Starlark string literal.
+fn starlark_string(value: &str) -> String {
+    let mut res = String::with_capacity(value.len() + 2);
+    res.push('"');
+
+    for c in value.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '"' => res.push_str("\\\""),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if c.is_ascii_control() => res.push_str(&format!("\\{:03o}", c as u32)),
+            c => res.push(c),
+        }
+    }
+
+    res.push('"');
+    res
+}
+
+/// Format strings as a Starlark list literal.
+fn starlark_list(values: &[String]) -> String {
+    format!(
+        "[{}]",
+        values
+            .iter()
+            .map(|v| starlark_string(v))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Obtain the file name of a path as a string.
+fn file_name_string(path: &Path) -> Result<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow!("unable to resolve file name of {}", path.display()))
+}
+
+/// A library imported by generated build files.
+struct BuildFileLibrary {
+    /// Name of the rule importing the library.
+    rule: String,
+
+    /// File name of a static library.
+    static_library: Option<String>,
+
+    /// File name of a shared library.
+    shared_library: Option<String>,
+
+    /// File name of the import library of a Windows shared library.
+    interface_library: Option<String>,
+
+    /// Whether all objects in the static library must be linked.
+    link_whole: bool,
+}
+
 /// Describes how Windows Runtime DLLs (e.g. vcruntime140.dll) should be handled during builds.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum WindowsRuntimeDllsMode {
@@ -147,6 +265,214 @@ impl TryFrom<&str> for WindowsRuntimeDllsMode {
     }
 }
 
+/// Version information embedded in Windows executables.
+///
+/// This becomes a `VERSIONINFO` resource, which is shown in the properties
+/// dialog of the executable and read by installers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WindowsVersionInfo {
+    /// Version of the file.
+    ///
+    /// Up to 4 dot delimited integers, e.g. `1.2.3`.
+    pub file_version: Option<String>,
+
+    /// Version of the product the file is distributed with.
+    ///
+    /// Same format as `file_version`, which it defaults to.
+    pub product_version: Option<String>,
+
+    /// Name of the company that produced the file.
+    pub company_name: Option<String>,
+
+    /// Description of the file.
+    pub file_description: Option<String>,
+
+    /// Name of the product the file is distributed with.
+    pub product_name: Option<String>,
+
+    /// Copyright notice.
+    pub copyright: Option<String>,
+}
+
+/// Describes how a Windows executable runs as a service.
+///
+/// The executable registers with the Service Control Manager and calls
+/// Python entry points to run and stop the service.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WindowsService {
+    /// Name of the service.
+    pub name: String,
+
+    /// Name of the service displayed by user interfaces.
+    pub display_name: String,
+
+    /// Description of the service.
+    pub description: Option<String>,
+
+    /// Python callable running the service, as `module:function`.
+    pub entry_point: String,
+
+    /// Python callable requesting the service to stop, as `module:function`.
+    pub stop_entry_point: Option<String>,
+}
+
+/// Ensure a string is a Python entry point of the form `module:function`.
+///
+/// Both the module and the attribute may be dotted, e.g. `pkg.mod:Class.run`.
+pub fn validate_entry_point(value: &str) -> Result<()> {
+    let is_dotted_identifier = |s: &str| {
+        s.split('.').all(|part| {
+            !part.is_empty()
+                && !part.starts_with(|c: char| c.is_ascii_digit())
+                && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+        })
+    };
+
+    match value.split_once(':') {
+        Some((module, attr)) if is_dotted_identifier(module) && is_dotted_identifier(attr) => {
+            Ok(())
+        }
+        _ => Err(anyhow!(
+            "{} is not a valid entry point; must be of the form module:function",
+            value
+        )),
+    }
+}
+
+/// Parse a version string into the integers of a `VERSIONINFO` resource.
+///
+/// The version consists of up to 4 dot delimited integers. Missing
+/// components are 0.
+pub fn parse_windows_version(value: &str) -> Result<[u16; 4]> {
+    let mut version = [0u16; 4];
+
+    let parts = value.split('.').collect::<Vec<_>>();
+    if parts.len() > 4 {
+        return Err(anyhow!(
+            "{} is not a valid version; must have at most 4 components",
+            value
+        ));
+    }
+
+    for (i, part) in parts.into_iter().enumerate() {
+        version[i] = part.parse::<u16>().map_err(|_| {
+            anyhow!(
+                "{} is not a valid version; components must be integers between 0 and 65535",
+                value
+            )
+        })?;
+    }
+
+    Ok(version)
+}
+
+/// Customizations to the Rust project used to build a binary.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RustProjectCustomizations {
+    /// Extra Cargo features to enable when building.
+    pub cargo_features: Vec<String>,
+
+    /// Rust source files to add to the project.
+    ///
+    /// Each file becomes a module of the crate named after its file stem.
+    pub sources: Vec<PathBuf>,
+
+    /// Path of a function to call with the interpreter config before the
+    /// interpreter is initialized.
+    ///
+    /// e.g. `hooks::pre_init`. The function receives a
+    /// `&mut OxidizedPythonInterpreterConfig`.
+    pub pre_init_hook: Option<String>,
+
+    /// Rust source file to use as the `main.rs` of the project.
+    pub main_path: Option<PathBuf>,
+
+    /// Extra arguments to pass to the linker.
+    pub link_args: Vec<String>,
+
+    /// Whether to link the C runtime statically.
+    ///
+    /// `None` uses the default of the target.
+    pub static_crt: Option<bool>,
+
+    /// Linker to use, keyed by target triple.
+    pub linkers: BTreeMap<String, PathBuf>,
+
+    /// Version information to embed in Windows executables.
+    pub windows_version_info: Option<WindowsVersionInfo>,
+
+    /// Run Windows executables as a service.
+    pub windows_service: Option<WindowsService>,
+}
+
+/// How a built executable is compressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExecutableCompression {
+    /// The executable is not compressed.
+    None,
+
+    /// The executable is compressed with UPX if it is available.
+    ///
+    /// UPX prepends a stub which decompresses the executable in memory when
+    /// it is run.
+    Upx,
+}
+
+impl Default for ExecutableCompression {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+impl ToString for ExecutableCompression {
+    fn to_string(&self) -> String {
+        match self {
+            Self::None => "none",
+            Self::Upx => "upx",
+        }
+        .to_string()
+    }
+}
+
+impl TryFrom<&str> for ExecutableCompression {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "none" => Ok(Self::None),
+            "upx" => Ok(Self::Upx),
+            _ => Err(format!(
+                "{} is not a valid compression; must be 'none' or 'upx'",
+                value
+            )),
+        }
+    }
+}
+
+/// How debug information in a built binary is processed after linking.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DebugInfoSettings {
+    /// Remove symbols and debug information from the binary.
+    pub strip: bool,
+
+    /// Move debug information to a separate file next to the binary.
+    pub split: bool,
+}
+
+impl DebugInfoSettings {
+    /// Describes the processing for recording in build metadata.
+    ///
+    /// Returns `None` if no processing is performed.
+    pub fn description(&self) -> Option<String> {
+        match (self.split, self.strip) {
+            (false, false) => None,
+            (false, true) => Some("stripped".to_string()),
+            (true, false) => Some("split".to_string()),
+            (true, true) => Some("split,stripped".to_string()),
+        }
+    }
+}
+
 /// A callable that can influence PythonResourceAddCollectionContext.
 pub type ResourceAddCollectionContextCallback<'a> = Box<
     dyn Fn(
@@ -172,6 +498,9 @@ pub trait PythonBinaryBuilder {
     /// The name of the binary.
     fn name(&self) -> String;
 
+    /// Set the name of the binary.
+    fn set_name(&mut self, name: &str);
+
     /// How the binary will link against libpython.
     fn libpython_link_mode(&self) -> LibpythonLinkMode;
 
@@ -201,6 +530,9 @@ pub trait PythonBinaryBuilder {
     // TODO this should not need to exist if we properly supported cross-compiling.
     fn target_python_exe_path(&self) -> &Path;
 
+    /// Names of top-level modules provided by the target Python distribution.
+    fn distribution_top_level_module_names(&self) -> BTreeSet<String>;
+
     /// Obtain how Windows runtime DLLs will be handled during builds.
     ///
     /// See the enum's documentation for behavior.
@@ -218,18 +550,67 @@ pub trait PythonBinaryBuilder {
     /// Set the directory to install tcl/tk files into.
     fn set_tcl_files_path(&mut self, value: Option<String>);
 
+    /// How debug information in the built binary is processed.
+    fn debug_info(&self) -> &DebugInfoSettings;
+
+    /// Set how debug information in the built binary is processed.
+    fn set_debug_info(&mut self, value: DebugInfoSettings);
+
+    /// How the built executable is compressed.
+    fn executable_compression(&self) -> ExecutableCompression;
+
+    /// Set how the built executable is compressed.
+    fn set_executable_compression(&mut self, value: ExecutableCompression);
+
+    /// Names of Python modules to freeze into the binary.
+    fn frozen_modules(&self) -> &[String];
+
+    /// Set the names of Python modules to freeze into the binary.
+    fn set_frozen_modules(&mut self, names: Vec<String>);
+
+    /// Directory holding scratch state and caches of the build, if any.
+    ///
+    /// Temporary directories used while building are created under it.
+    fn cache_path(&self) -> Option<&Path>;
+
+    /// Set the directory holding scratch state and caches of the build.
+    fn set_cache_path(&mut self, path: Option<PathBuf>);
+
+    /// Set the cache of compiled bytecode used when building.
+    ///
+    /// Builders sharing a cache only compile sources they have in common once.
+    fn set_bytecode_cache(&mut self, cache: Arc<Mutex<BytecodeCache>>);
+
+    /// Whether directories holding filesystem-relative modules are added to `sys.path`.
+    fn relative_path_module_search_paths(&self) -> bool;
+
+    /// Set whether directories holding filesystem-relative modules are added to `sys.path`.
+    fn set_relative_path_module_search_paths(&mut self, value: bool);
+
     /// The value of the `windows_subsystem` Rust attribute for the generated Rust project.
     fn windows_subsystem(&self) -> &str;
 
     /// Set the value of the `windows_subsystem` Rust attribute for generated Rust projects.
     fn set_windows_subsystem(&mut self, value: &str) -> Result<()>;
 
+    /// Customizations to the Rust project used to build the binary.
+    fn rust_project(&self) -> &RustProjectCustomizations;
+
+    /// Obtain a mutable reference to the Rust project customizations.
+    fn rust_project_mut(&mut self) -> &mut RustProjectCustomizations;
+
     /// How packed Python resources will be loaded by the binary.
     fn packed_resources_load_mode(&self) -> &PackedResourcesLoadMode;
 
     /// Set how packed Python resources will be loaded by the binary.
     fn set_packed_resources_load_mode(&mut self, load_mode: PackedResourcesLoadMode);
 
+    /// The configuration of the embedded Python interpreter.
+    fn python_interpreter_config(&self) -> &PyembedPythonInterpreterConfig;
+
+    /// Obtain a mutable reference to the embedded Python interpreter configuration.
+    fn python_interpreter_config_mut(&mut self) -> &mut PyembedPythonInterpreterConfig;
+
     /// Obtain an iterator over all resource entries that will be embedded in the binary.
     ///
     /// This likely does not return extension modules that are statically linked
@@ -270,6 +651,13 @@ pub trait PythonBinaryBuilder {
         extra_envs: &HashMap<String, String>,
     ) -> Result<Vec<PythonResource>>;
 
+    /// Read Python resources from a conda environment.
+    ///
+    /// Shared libraries in the environment that extension modules depend
+    /// on are attached to those extension modules.
+    fn read_conda_env(&mut self, logger: &slog::Logger, path: &Path)
+        -> Result<Vec<PythonResource>>;
+
     /// Reads Python resources from the filesystem.
     fn read_package_root(
         &mut self,
@@ -297,6 +685,24 @@ pub trait PythonBinaryBuilder {
         extra_global_arguments: &[String],
     ) -> Result<Vec<PythonResource>>;
 
+    /// Compile Cython sources into extension modules using the binary builder's settings.
+    ///
+    /// `sources` are glob patterns relative to `package_path`. Cython is
+    /// installed from `cython_requirement`.
+    ///
+    /// Returns resources discovered as part of performing an install.
+    #[allow(clippy::too_many_arguments)]
+    fn cythonize(
+        &mut self,
+        logger: &slog::Logger,
+        package_path: &Path,
+        sources: &[String],
+        language_level: &str,
+        cython_requirement: &str,
+        verbose: bool,
+        extra_envs: &HashMap<String, String>,
+    ) -> Result<Vec<PythonResource>>;
+
     /// Add resources from the Python distribution to the builder.
     ///
     /// This method should likely be called soon after object construction
@@ -330,6 +736,20 @@ pub trait PythonBinaryBuilder {
         add_context: Option<PythonResourceAddCollectionContext>,
     ) -> Result<()>;
 
+    /// Add a `PythonModuleBytecode` to the resources collection.
+    ///
+    /// This is used for modules without source code. Bytecode for modules
+    /// with source code is compiled from that source.
+    ///
+    /// The location to load the resource from is optional. If specified, it
+    /// will be used. If not, an appropriate location based on the resources
+    /// policy will be chosen.
+    fn add_python_module_bytecode(
+        &mut self,
+        module: &PythonModuleBytecode,
+        add_context: Option<PythonResourceAddCollectionContext>,
+    ) -> Result<()>;
+
     /// Add a `PythonPackageResource` to the resources collection.
     ///
     /// The location to load the resource from is optional. If specified, it will
@@ -385,6 +805,45 @@ pub trait PythonBinaryBuilder {
         glob_patterns: &[&str],
     ) -> Result<()>;
 
+    /// Filter embedded Python modules and package resources through a `ResourceFilter`.
+    ///
+    /// Modules are identified by their fully qualified name. Package resources
+    /// are identified by `<package>:<resource name>`.
+    ///
+    /// Returns the identifiers of removed resources. If `dry_run` is true,
+    /// nothing is removed and the identifiers of resources that would be
+    /// removed are returned.
+    fn filter_resources(
+        &mut self,
+        logger: &slog::Logger,
+        filter: &ResourceFilter,
+        dry_run: bool,
+    ) -> Result<Vec<String>>;
+
+    /// Resolve the imports between embedded Python modules.
+    ///
+    /// Imports are found in the bytecode of modules by the host Python
+    /// interpreter.
+    fn import_graph(&self) -> Result<ImportGraph>;
+
+    /// Remove embedded Python modules not reachable from `roots` through imports.
+    ///
+    /// Imports are found by `import_graph()`. `roots` are module names,
+    /// optionally ending in `.*` to denote a package and all its submodules.
+    /// Modules imported when the interpreter starts are always retained, as
+    /// are modules which may be imported by modules whose imports aren't
+    /// fully known. See `ImportGraph::unreachable()`.
+    ///
+    /// Returns the names of removed modules and why they were removed. If
+    /// `dry_run` is true, nothing is removed and the modules that would be
+    /// removed are returned.
+    fn prune_unreachable_resources(
+        &mut self,
+        logger: &slog::Logger,
+        roots: &[String],
+        dry_run: bool,
+    ) -> Result<BTreeMap<String, String>>;
+
     /// Whether the binary requires the jemalloc library.
     fn requires_jemalloc(&self) -> bool;
 
@@ -436,9 +895,17 @@ pub struct EmbeddedPythonContext<'a> {
     /// Python resources that need to be serialized to a file.
     pub pending_resources: Vec<(CompiledResourcesCollection<'a>, PathBuf)>,
 
+    /// Marshalled code of frozen modules that need to be written to a file.
+    pub pending_frozen_modules: Vec<(PathBuf, Vec<u8>)>,
+
     /// Extra files to install next to produced binary.
     pub extra_files: FileManifest,
 
+    /// Extra files to install outside the directory of the produced binary.
+    ///
+    /// Paths are relative to the directory of the binary and begin with `..`.
+    pub external_files: BTreeMap<PathBuf, FileEntry>,
+
     /// Rust target triple for the host we are running on.
     pub host_triple: String,
 
@@ -516,6 +983,60 @@ impl<'a> EmbeddedPythonContext<'a> {
         Ok(())
     }
 
+    /// The number of directories `external_files` reach above the binary's directory.
+    pub fn external_files_depth(&self) -> usize {
+        self.external_files
+            .keys()
+            .map(|path| {
+                path.components()
+                    .take_while(|c| *c == Component::ParentDir)
+                    .count()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Write files installed with the binary, relative to the directory it is in.
+    ///
+    /// This writes `extra_files` to `exe_dir` and `external_files` relative to it.
+    pub fn materialize_files(&self, exe_dir: impl AsRef<Path>) -> Result<()> {
+        let exe_dir = exe_dir.as_ref();
+
+        self.extra_files
+            .materialize_files(exe_dir)
+            .context("writing files installed next to executable")?;
+
+        for (path, entry) in &self.external_files {
+            let dest_path = normalize_relative_path(&exe_dir.join(path));
+            if let Some(parent) = dest_path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("creating {}", parent.display()))?;
+            }
+            let mut fh = std::fs::File::create(&dest_path)
+                .with_context(|| format!("creating {}", dest_path.display()))?;
+            fh.write_all(&entry.data.resolve()?)
+                .with_context(|| format!("writing {}", dest_path.display()))?;
+            if entry.executable {
+                tugger_file_manifest::set_executable(&mut fh)
+                    .with_context(|| format!("making {} executable", dest_path.display()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write marshalled code of frozen modules to files in the specified directory.
+    pub fn write_frozen_modules(&self, dest_dir: impl AsRef<Path>) -> Result<()> {
+        for (path, data) in &self.pending_frozen_modules {
+            let dest_path = dest_dir.as_ref().join(path);
+
+            std::fs::write(&dest_path, data)
+                .with_context(|| format!("writing {}", dest_path.display()))?;
+        }
+
+        Ok(())
+    }
+
     /// Ensure files required by libpython are written.
     pub fn write_libpython(&self, dest_dir: impl AsRef<Path>) -> Result<()> {
         let mut fh = std::fs::File::create(self.libpython_path(&dest_dir))?;
@@ -552,6 +1073,8 @@ impl<'a> EmbeddedPythonContext<'a> {
     pub fn write_files(&self, dest_dir: &Path) -> Result<()> {
         self.write_packed_resources(&dest_dir)
             .context("writing packed resources")?;
+        self.write_frozen_modules(&dest_dir)
+            .context("writing frozen modules")?;
         self.write_libpython(&dest_dir)
             .context("writing libpython")?;
         self.write_interpreter_config_rs(&dest_dir)
@@ -561,6 +1084,336 @@ impl<'a> EmbeddedPythonContext<'a> {
 
         Ok(())
     }
+
+    /// Obtain the libraries named by `cargo:rustc-link-lib` lines.
+    ///
+    /// Returns the kind of link, if any, and the name of the library.
+    fn link_libraries(&self) -> Vec<(Option<&str>, &str)> {
+        self.linking_info
+            .cargo_metadata
+            .iter()
+            .filter_map(|line| line.strip_prefix("cargo:rustc-link-lib="))
+            .map(|value| {
+                let (kind, name) = match value.splitn(2, '=').collect::<Vec<_>>()[..] {
+                    [kind, name] => (Some(kind), name),
+                    _ => (None, value),
+                };
+                // `name:rename` links against `rename`.
+                (kind, name.rsplit(':').next().unwrap_or(name))
+            })
+            .collect()
+    }
+
+    /// Obtain linker flags for system libraries libpython depends on.
+    ///
+    /// Static libraries are excluded since build files import them from
+    /// the artifacts directory, as are the search paths they are found in.
+    pub fn dependency_linker_flags(&self) -> Vec<String> {
+        let windows = self.target_triple.contains("-windows-");
+        let mut flags = vec![];
+
+        for (kind, name) in self.link_libraries() {
+            if kind == Some("static") {
+                continue;
+            }
+
+            if kind == Some("framework") {
+                flags.push("-framework".to_string());
+                flags.push(name.to_string());
+            } else if windows {
+                flags.push(format!("{}.lib", name));
+            } else {
+                flags.push(format!("-l{}", name));
+            }
+        }
+
+        for line in &self.linking_info.cargo_metadata {
+            if let Some(path) = line.strip_prefix("cargo:rustc-link-search=") {
+                if let Some(path) = path.strip_prefix("framework=") {
+                    flags.push(format!("-F{}", path));
+                } else if !path.starts_with("native=") {
+                    flags.push(if windows {
+                        format!("/LIBPATH:{}", path)
+                    } else {
+                        format!("-L{}", path)
+                    });
+                }
+            }
+        }
+
+        flags
+    }
+
+    /// Resolve the libraries build files import and the files to copy into the artifacts directory.
+    fn build_file_libraries(&self) -> Result<(Vec<BuildFileLibrary>, Vec<PathBuf>)> {
+        let windows = self.target_triple.contains("-windows-");
+        let mut libraries = vec![];
+        let mut copies = vec![];
+
+        if let Some(libpython) = &self.linking_info.libpython_filename {
+            // `pythonXY` is an empty placeholder when linking against a
+            // shared libpython.
+            let interface_library = if windows {
+                let filename = format!(
+                    "{}.lib",
+                    libpython
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                );
+                let dir = libpython.parent().unwrap_or_else(|| Path::new(""));
+                let path = [dir.join(&filename), dir.join("libs").join(&filename)]
+                    .iter()
+                    .find(|path| path.exists())
+                    .cloned()
+                    .ok_or_else(|| {
+                        anyhow!("unable to find import library of {}", libpython.display())
+                    })?;
+
+                copies.push(path);
+                Some(filename)
+            } else {
+                None
+            };
+
+            libraries.push(BuildFileLibrary {
+                rule: "libpython".to_string(),
+                static_library: None,
+                shared_library: Some(file_name_string(libpython)?),
+                interface_library,
+                link_whole: false,
+            });
+            copies.push(libpython.clone());
+        } else {
+            libraries.push(BuildFileLibrary {
+                rule: "pythonXY".to_string(),
+                static_library: Some(file_name_string(&self.linking_info.libpythonxy_filename)?),
+                shared_library: None,
+                interface_library: None,
+                link_whole: false,
+            });
+        }
+
+        if let Some(filename) = &self.linking_info.libpyembeddedconfig_filename {
+            libraries.push(BuildFileLibrary {
+                rule: "pyembeddedconfig".to_string(),
+                static_library: Some(file_name_string(filename)?),
+                shared_library: None,
+                interface_library: None,
+                link_whole: true,
+            });
+        }
+
+        let search_paths = self
+            .linking_info
+            .cargo_metadata
+            .iter()
+            .filter_map(|line| line.strip_prefix("cargo:rustc-link-search=native="))
+            .map(PathBuf::from)
+            .collect::<Vec<_>>();
+
+        for (kind, name) in self.link_libraries() {
+            if kind != Some("static") || name == "pythonXY" || name == "pyembeddedconfig" {
+                continue;
+            }
+
+            let filename = if windows {
+                format!("{}.lib", name)
+            } else {
+                format!("lib{}.a", name)
+            };
+            let path = search_paths
+                .iter()
+                .map(|dir| dir.join(&filename))
+                .find(|path| path.exists())
+                .ok_or_else(|| anyhow!("unable to find {} in library search paths", filename))?;
+
+            libraries.push(BuildFileLibrary {
+                rule: format!("lib_{}", name),
+                static_library: Some(filename),
+                shared_library: None,
+                interface_library: None,
+                link_whole: false,
+            });
+            copies.push(path);
+        }
+
+        Ok((libraries, copies))
+    }
+
+    /// Names of files holding Python resources, relative to the artifacts directory.
+    fn resources_files(&self) -> Vec<String> {
+        self.pending_resources
+            .iter()
+            .map(|(_, path)| path.display().to_string())
+            .chain(
+                self.pending_frozen_modules
+                    .iter()
+                    .map(|(path, _)| path.display().to_string()),
+            )
+            .collect()
+    }
+
+    /// Obtain the content of a Bazel `BUILD` file describing the artifacts.
+    ///
+    /// `:python` is a `cc_library` linking libpython and its dependencies.
+    /// `:resources` holds files the interpreter loads resources from and
+    /// `:config` is the generated Rust interpreter configuration.
+    pub fn bazel_build_file(&self) -> Result<String> {
+        let mut lines = vec![
+            "# Generated by PyOxidizer. Do not edit.".to_string(),
+            "".to_string(),
+            "package(default_visibility = [\"//visibility:public\"])".to_string(),
+            "".to_string(),
+        ];
+        let mut deps = vec![];
+
+        for library in self.build_file_libraries()?.0 {
+            deps.push(format!(":{}", library.rule));
+            lines.push("cc_import(".to_string());
+            lines.push(format!("    name = {},", starlark_string(&library.rule)));
+            for (attr, value) in [
+                ("static_library", &library.static_library),
+                ("shared_library", &library.shared_library),
+                ("interface_library", &library.interface_library),
+            ]
+            .iter()
+            {
+                if let Some(value) = value {
+                    lines.push(format!("    {} = {},", attr, starlark_string(value)));
+                }
+            }
+            if library.link_whole {
+                lines.push("    alwayslink = True,".to_string());
+            }
+            lines.push(")".to_string());
+            lines.push("".to_string());
+        }
+
+        lines.push("cc_library(".to_string());
+        lines.push("    name = \"python\",".to_string());
+        lines.push(format!(
+            "    linkopts = {},",
+            starlark_list(&self.dependency_linker_flags())
+        ));
+        lines.push(format!("    deps = {},", starlark_list(&deps)));
+        lines.push(")".to_string());
+        lines.push("".to_string());
+
+        lines.push("filegroup(".to_string());
+        lines.push("    name = \"resources\",".to_string());
+        lines.push(format!(
+            "    srcs = {},",
+            starlark_list(&self.resources_files())
+        ));
+        lines.push(")".to_string());
+        lines.push("".to_string());
+
+        lines.push("filegroup(".to_string());
+        lines.push("    name = \"config\",".to_string());
+        lines.push("    srcs = [\"default_python_config.rs\"],".to_string());
+        lines.push(")".to_string());
+
+        Ok(lines.join("\n") + "\n")
+    }
+
+    /// Obtain the content of a Buck `BUCK` file describing the artifacts.
+    ///
+    /// Rule names match those of [Self::bazel_build_file].
+    pub fn buck_build_file(&self) -> Result<String> {
+        let mut lines = vec![
+            "# Generated by PyOxidizer. Do not edit.".to_string(),
+            "".to_string(),
+        ];
+        let mut deps = vec![];
+
+        for library in self.build_file_libraries()?.0 {
+            deps.push(format!(":{}", library.rule));
+            lines.push("prebuilt_cxx_library(".to_string());
+            lines.push(format!("    name = {},", starlark_string(&library.rule)));
+            for (attr, value) in [
+                ("static_lib", &library.static_library),
+                ("shared_lib", &library.shared_library),
+                ("import_lib", &library.interface_library),
+            ]
+            .iter()
+            {
+                if let Some(value) = value {
+                    lines.push(format!("    {} = {},", attr, starlark_string(value)));
+                }
+            }
+            if library.shared_library.is_some() {
+                lines.push("    preferred_linkage = \"shared\",".to_string());
+            }
+            if library.link_whole {
+                lines.push("    link_whole = True,".to_string());
+            }
+            lines.push(")".to_string());
+            lines.push("".to_string());
+        }
+
+        lines.push("prebuilt_cxx_library(".to_string());
+        lines.push("    name = \"python\",".to_string());
+        lines.push("    header_only = True,".to_string());
+        lines.push(format!(
+            "    exported_linker_flags = {},",
+            starlark_list(&self.dependency_linker_flags())
+        ));
+        lines.push(format!("    exported_deps = {},", starlark_list(&deps)));
+        lines.push("    visibility = [\"PUBLIC\"],".to_string());
+        lines.push(")".to_string());
+        lines.push("".to_string());
+
+        lines.push("filegroup(".to_string());
+        lines.push("    name = \"resources\",".to_string());
+        lines.push(format!(
+            "    srcs = {},",
+            starlark_list(&self.resources_files())
+        ));
+        lines.push("    visibility = [\"PUBLIC\"],".to_string());
+        lines.push(")".to_string());
+        lines.push("".to_string());
+
+        lines.push("export_file(".to_string());
+        lines.push("    name = \"config\",".to_string());
+        lines.push("    src = \"default_python_config.rs\",".to_string());
+        lines.push("    visibility = [\"PUBLIC\"],".to_string());
+        lines.push(")".to_string());
+
+        Ok(lines.join("\n") + "\n")
+    }
+
+    /// Write files allowing a build system to consume the artifacts in `dest_dir`.
+    ///
+    /// Libraries the build files import are copied into `dest_dir`, so the
+    /// build files only reference files relative to it.
+    pub fn write_build_files(&self, dest_dir: &Path, build_system: BuildSystem) -> Result<()> {
+        for source in self.build_file_libraries()?.1 {
+            let dest_path = dest_dir.join(file_name_string(&source)?);
+            std::fs::copy(&source, &dest_path).with_context(|| {
+                format!("copying {} to {}", source.display(), dest_path.display())
+            })?;
+        }
+
+        let files = match build_system {
+            BuildSystem::Bazel => vec![
+                ("BUILD.bazel", self.bazel_build_file()?),
+                (
+                    "WORKSPACE",
+                    "# Generated by PyOxidizer. Do not edit.\n".to_string(),
+                ),
+            ],
+            BuildSystem::Buck => vec![("BUCK", self.buck_build_file()?)],
+        };
+
+        for (filename, content) in files {
+            let path = dest_dir.join(filename);
+            std::fs::write(&path, content)
+                .with_context(|| format!("writing {}", path.display()))?;
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -601,4 +1454,202 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_windows_version() -> Result<()> {
+        assert_eq!(parse_windows_version("1")?, [1, 0, 0, 0]);
+        assert_eq!(parse_windows_version("1.2.3")?, [1, 2, 3, 0]);
+        assert_eq!(parse_windows_version("1.2.3.4")?, [1, 2, 3, 4]);
+
+        assert!(parse_windows_version("").is_err());
+        assert!(parse_windows_version("1.2.3.4.5").is_err());
+        assert!(parse_windows_version("1.2-beta").is_err());
+        assert!(parse_windows_version("65536").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_entry_point() {
+        assert!(validate_entry_point("app:main").is_ok());
+        assert!(validate_entry_point("app.service:Service.run").is_ok());
+
+        assert!(validate_entry_point("app").is_err());
+        assert!(validate_entry_point("app:").is_err());
+        assert!(validate_entry_point(":main").is_err());
+        assert!(validate_entry_point("app:main()").is_err());
+        assert!(validate_entry_point("app..x:main").is_err());
+        assert!(validate_entry_point("1app:main").is_err());
+    }
+
+    fn build_files_context(deps_dir: &Path) -> EmbeddedPythonContext<'static> {
+        EmbeddedPythonContext {
+            config: PyembedPythonInterpreterConfig::default(),
+            linking_info: PythonLinkingInfo {
+                libpythonxy_filename: PathBuf::from("libpythonXY.a"),
+                libpythonxy_data: vec![],
+                libpython_filename: None,
+                libpyembeddedconfig_filename: Some(PathBuf::from("libpyembeddedconfig.a")),
+                libpyembeddedconfig_data: None,
+                cargo_metadata: vec![
+                    "cargo:rustc-link-lib=static=pyembeddedconfig".to_string(),
+                    "cargo:rustc-link-lib=framework=CoreFoundation".to_string(),
+                    "cargo:rustc-link-lib=dl".to_string(),
+                    "cargo:rustc-link-lib=static=ffi".to_string(),
+                    "cargo:rustc-link-lib=static=pythonXY".to_string(),
+                    format!("cargo:rustc-link-search=native={}", deps_dir.display()),
+                    "cargo:rustc-link-search=/toolchain".to_string(),
+                ],
+            },
+            pending_resources: vec![],
+            pending_frozen_modules: vec![(PathBuf::from("frozen-modules"), vec![])],
+            extra_files: FileManifest::default(),
+            external_files: BTreeMap::new(),
+            host_triple: "x86_64-apple-darwin".to_string(),
+            target_triple: "x86_64-apple-darwin".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_relative_path() {
+        assert_eq!(
+            normalize_relative_path(Path::new("lib/../share/./app")),
+            PathBuf::from("share/app")
+        );
+        assert_eq!(
+            normalize_relative_path(Path::new("../share/app")),
+            PathBuf::from("../share/app")
+        );
+        assert_eq!(
+            normalize_relative_path(Path::new("bin/../../share")),
+            PathBuf::from("../share")
+        );
+        assert_eq!(
+            normalize_relative_path(Path::new("/opt/app/bin/../share")),
+            PathBuf::from("/opt/app/share")
+        );
+    }
+
+    #[test]
+    fn test_build_system_parsing() {
+        assert_eq!(BuildSystem::try_from("bazel"), Ok(BuildSystem::Bazel));
+        assert_eq!(BuildSystem::try_from("buck"), Ok(BuildSystem::Buck));
+        assert!(BuildSystem::try_from("make").is_err());
+        assert_eq!(BuildSystem::Buck.to_string(), "buck");
+    }
+
+    #[test]
+    fn test_starlark_string() {
+        assert_eq!(starlark_string("lib.a"), "\"lib.a\"");
+        assert_eq!(
+            starlark_string("a\"b\\c\nd\u{1}\u{e9}"),
+            "\"a\\\"b\\\\c\\nd\\001\u{e9}\""
+        );
+    }
+
+    #[test]
+    fn test_dependency_linker_flags() {
+        let mut context = build_files_context(Path::new("/deps"));
+
+        assert_eq!(
+            context.dependency_linker_flags(),
+            vec!["-framework", "CoreFoundation", "-ldl", "-L/toolchain"]
+        );
+
+        context.target_triple = "x86_64-pc-windows-msvc".to_string();
+        context.linking_info.cargo_metadata = vec![
+            "cargo:rustc-link-lib=pythonXY:python39".to_string(),
+            "cargo:rustc-link-lib=ws2_32".to_string(),
+        ];
+        assert_eq!(
+            context.dependency_linker_flags(),
+            vec!["python39.lib", "ws2_32.lib"]
+        );
+    }
+
+    #[test]
+    fn test_build_files() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let deps_dir = temp_dir.path().join("deps");
+        let dest_dir = temp_dir.path().join("artifacts");
+        std::fs::create_dir_all(&deps_dir)?;
+        std::fs::create_dir_all(&dest_dir)?;
+
+        let context = build_files_context(&deps_dir);
+        assert!(context.bazel_build_file().is_err());
+
+        std::fs::write(deps_dir.join("libffi.a"), b"ffi")?;
+
+        let bazel = context.bazel_build_file()?;
+        assert!(bazel.contains("static_library = \"libpythonXY.a\","));
+        assert!(bazel.contains("static_library = \"libpyembeddedconfig.a\","));
+        assert!(bazel.contains("static_library = \"libffi.a\","));
+        assert!(bazel.contains("deps = [\":pythonXY\", \":pyembeddedconfig\", \":lib_ffi\"],"));
+        assert!(bazel.contains("srcs = [\"frozen-modules\"],"));
+        assert!(!bazel.contains(&deps_dir.display().to_string()));
+
+        let buck = context.buck_build_file()?;
+        assert!(buck.contains("static_lib = \"libpythonXY.a\","));
+        assert!(
+            buck.contains("exported_deps = [\":pythonXY\", \":pyembeddedconfig\", \":lib_ffi\"],")
+        );
+
+        context.write_build_files(&dest_dir, BuildSystem::Bazel)?;
+        assert_eq!(
+            std::fs::read_to_string(dest_dir.join("BUILD.bazel"))?,
+            bazel
+        );
+        assert!(dest_dir.join("WORKSPACE").exists());
+        assert_eq!(std::fs::read(dest_dir.join("libffi.a"))?, b"ffi");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_files_dynamic() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let install_dir = temp_dir.path().join("install");
+        let dest_dir = temp_dir.path().join("artifacts");
+        std::fs::create_dir_all(install_dir.join("libs"))?;
+        std::fs::create_dir_all(&dest_dir)?;
+
+        let mut context = build_files_context(&install_dir);
+        context.linking_info.libpythonxy_filename = PathBuf::from("pythonXY.lib");
+        context.linking_info.libpyembeddedconfig_filename = None;
+        context.linking_info.cargo_metadata = vec![];
+
+        std::fs::write(install_dir.join("libpython3.9.so.1.0"), b"so")?;
+        context.target_triple = "x86_64-unknown-linux-gnu".to_string();
+        context.linking_info.libpython_filename = Some(install_dir.join("libpython3.9.so.1.0"));
+
+        let bazel = context.bazel_build_file()?;
+        assert!(bazel.contains("shared_library = \"libpython3.9.so.1.0\","));
+        assert!(bazel.contains("deps = [\":libpython\"],"));
+        assert!(!bazel.contains("pythonXY"));
+
+        context.write_build_files(&dest_dir, BuildSystem::Buck)?;
+        let buck = std::fs::read_to_string(dest_dir.join("BUCK"))?;
+        assert!(buck.contains("shared_lib = \"libpython3.9.so.1.0\","));
+        assert!(buck.contains("preferred_linkage = \"shared\","));
+        assert_eq!(std::fs::read(dest_dir.join("libpython3.9.so.1.0"))?, b"so");
+
+        std::fs::write(install_dir.join("python39.dll"), b"dll")?;
+        context.target_triple = "x86_64-pc-windows-msvc".to_string();
+        context.linking_info.libpython_filename = Some(install_dir.join("python39.dll"));
+        assert!(context.bazel_build_file().is_err());
+
+        std::fs::write(install_dir.join("libs").join("python39.lib"), b"lib")?;
+        let bazel = context.bazel_build_file()?;
+        assert!(bazel.contains("shared_library = \"python39.dll\","));
+        assert!(bazel.contains("interface_library = \"python39.lib\","));
+
+        context.write_build_files(&dest_dir, BuildSystem::Bazel)?;
+        assert_eq!(std::fs::read(dest_dir.join("python39.lib"))?, b"lib");
+
+        Ok(())
+    }
 }
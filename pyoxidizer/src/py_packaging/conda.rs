@@ -0,0 +1,424 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Reading Python resources from conda environments.
+
+Python packages in a conda environment are installed into a `site-packages`
+directory, just like in a virtualenv. Unlike virtualenvs, conda packages
+commonly ship shared libraries (e.g. BLAS implementations) that extension
+modules link against. These live outside `site-packages`, in `lib/`
+(`Library/bin/` on Windows). Metadata for each installed conda package,
+including the files it installed and its license, is stored as JSON in
+`conda-meta/`.
+*/
+
+use {
+    super::{distribution::PythonDistribution, packaging_tool::find_resources},
+    anyhow::{anyhow, Context, Result},
+    python_packaging::{
+        policy::PythonPackagingPolicy,
+        resource::{LibraryDependency, PythonResource},
+    },
+    serde::Deserialize,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        path::{Path, PathBuf},
+    },
+    tugger_binary_analysis::find_library_dependencies,
+    tugger_file_manifest::FileData,
+    tugger_licensing::{ComponentFlavor, LicensedComponent},
+};
+
+/// Conda packages whose shared libraries are never packaged.
+///
+/// The Python distribution provides its own libpython.
+const IGNORED_PACKAGES: &[&str] = &["python"];
+
+/// Metadata of a package installed in a conda environment.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct CondaPackage {
+    /// Name of the conda package.
+    pub name: String,
+
+    /// Version of the conda package.
+    pub version: String,
+
+    /// License of the conda package.
+    #[serde(default)]
+    pub license: Option<String>,
+
+    /// Files installed by the package, relative to the environment root.
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+
+impl CondaPackage {
+    /// Obtain a [LicensedComponent] describing this package.
+    pub fn licensed_component(&self) -> LicensedComponent {
+        let mut component = match &self.license {
+            Some(license) => {
+                LicensedComponent::new_spdx(&self.name, license).unwrap_or_else(|_| {
+                    LicensedComponent::new_unknown(&self.name, vec![license.clone()])
+                })
+            }
+            None => LicensedComponent::new_none(&self.name),
+        };
+
+        component.set_flavor(ComponentFlavor::Library);
+
+        component
+    }
+}
+
+/// A shared library installed in a conda environment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CondaSharedLibrary {
+    /// Filename of the library.
+    pub filename: String,
+
+    /// Filesystem path of the library.
+    pub path: PathBuf,
+
+    /// Name of the conda package providing the library.
+    pub package: String,
+}
+
+/// Whether a filename looks like a shared library.
+///
+/// Versioned ELF libraries (e.g. `libz.so.1`) are recognized.
+fn is_shared_library_filename(filename: &str) -> bool {
+    let filename = filename.to_lowercase();
+
+    filename.ends_with(".so")
+        || filename.contains(".so.")
+        || filename.ends_with(".dylib")
+        || filename.ends_with(".dll")
+}
+
+/// Normalize the name of a library dependency for lookups.
+///
+/// Mach-O dependencies are install names (e.g. `@rpath/libfoo.dylib`) and
+/// PE dependencies are case insensitive. So we compare lowercased filenames.
+fn library_key(name: &str) -> String {
+    name.rsplit(|c| c == '/' || c == '\\')
+        .next()
+        .unwrap_or(name)
+        .to_lowercase()
+}
+
+/// A conda environment.
+#[derive(Clone, Debug)]
+pub struct CondaEnvironment {
+    /// Root directory of the environment.
+    pub path: PathBuf,
+
+    /// Packages installed in the environment.
+    pub packages: Vec<CondaPackage>,
+}
+
+impl CondaEnvironment {
+    /// Read a conda environment from its root directory.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let meta_dir = path.join("conda-meta");
+
+        if !meta_dir.is_dir() {
+            return Err(anyhow!(
+                "{} is not a conda environment: {} does not exist",
+                path.display(),
+                meta_dir.display()
+            ));
+        }
+
+        let mut packages = vec![];
+
+        for entry in std::fs::read_dir(&meta_dir)? {
+            let meta_path = entry?.path();
+
+            if meta_path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+
+            let data = std::fs::read(&meta_path)?;
+            let package: CondaPackage = serde_json::from_slice(&data)
+                .with_context(|| format!("parsing {}", meta_path.display()))?;
+
+            packages.push(package);
+        }
+
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            packages,
+        })
+    }
+
+    /// Resolve the `site-packages` directory of the environment.
+    pub fn site_packages(&self, python_major_minor_version: &str) -> PathBuf {
+        let windows = self.path.join("Lib").join("site-packages");
+
+        if windows.is_dir() {
+            windows
+        } else {
+            self.path
+                .join("lib")
+                .join(format!("python{}", python_major_minor_version))
+                .join("site-packages")
+        }
+    }
+
+    /// Obtain shared libraries installed into the environment's library directories.
+    ///
+    /// Keys are normalized filenames as returned by `library_key()`.
+    pub fn shared_libraries(&self) -> BTreeMap<String, CondaSharedLibrary> {
+        let mut res = BTreeMap::new();
+
+        for package in &self.packages {
+            if IGNORED_PACKAGES.contains(&package.name.as_str()) {
+                continue;
+            }
+
+            for file in &package.files {
+                let filename = if let Some(filename) = file.strip_prefix("lib/") {
+                    filename
+                } else if let Some(filename) = file.strip_prefix("Library/bin/") {
+                    filename
+                } else {
+                    continue;
+                };
+
+                // Only look at files directly in the library directory.
+                if filename.contains('/') || !is_shared_library_filename(filename) {
+                    continue;
+                }
+
+                let path = self.path.join(file);
+                if !path.exists() {
+                    continue;
+                }
+
+                res.insert(
+                    library_key(filename),
+                    CondaSharedLibrary {
+                        filename: filename.to_string(),
+                        path,
+                        package: package.name.clone(),
+                    },
+                );
+            }
+        }
+
+        res
+    }
+}
+
+/// Discover Python resources from a conda environment.
+///
+/// Resources are read from the environment's `site-packages` directory.
+/// Shared libraries in the environment that extension modules depend on
+/// (directly or indirectly) are attached to the extension modules as
+/// library dependencies.
+///
+/// Returns the resources and licensing info for the conda packages providing
+/// the attached shared libraries.
+pub fn read_conda_env<'a>(
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    path: &Path,
+) -> Result<(Vec<PythonResource<'a>>, Vec<LicensedComponent>)> {
+    let env = CondaEnvironment::from_path(path)?;
+    let libraries = env.shared_libraries();
+
+    let mut resources = find_resources(
+        dist,
+        policy,
+        &env.site_packages(&dist.python_major_minor_version()),
+        None,
+    )?;
+
+    let mut used_packages = BTreeSet::new();
+
+    for resource in resources.iter_mut() {
+        let module = if let PythonResource::ExtensionModule(module) = resource {
+            module
+        } else {
+            continue;
+        };
+
+        let data = if let Some(data) = &module.shared_library {
+            data.resolve()?
+        } else {
+            continue;
+        };
+
+        let mut pending = find_library_dependencies(&data)
+            .with_context(|| format!("finding library dependencies of {}", module.name))?;
+        let mut seen = BTreeSet::new();
+        let mut link_libraries = vec![];
+
+        while let Some(name) = pending.pop() {
+            let key = library_key(&name);
+
+            if !seen.insert(key.clone()) {
+                continue;
+            }
+
+            let library = if let Some(library) = libraries.get(&key) {
+                library
+            } else {
+                continue;
+            };
+
+            let library_data = std::fs::read(&library.path)
+                .with_context(|| format!("reading {}", library.path.display()))?;
+
+            pending.extend(find_library_dependencies(&library_data).with_context(|| {
+                format!("finding library dependencies of {}", library.path.display())
+            })?);
+
+            link_libraries.push(LibraryDependency {
+                name: library.filename.clone(),
+                static_library: None,
+                static_filename: None,
+                dynamic_library: Some(FileData::Memory(library_data)),
+                dynamic_filename: Some(PathBuf::from(&library.filename)),
+                framework: false,
+                system: false,
+            });
+
+            used_packages.insert(library.package.clone());
+        }
+
+        if !link_libraries.is_empty() {
+            module.to_mut().link_libraries.extend(link_libraries);
+        }
+    }
+
+    let components = env
+        .packages
+        .iter()
+        .filter(|package| used_packages.contains(&package.name))
+        .map(|package| package.licensed_component())
+        .collect::<Vec<_>>();
+
+    Ok((resources, components))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_package(env: &Path, name: &str, license: Option<&str>, files: &[&str]) -> Result<()> {
+        std::fs::create_dir_all(env.join("conda-meta"))?;
+
+        for file in files {
+            let path = env.join(file);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(&path, b"dummy")?;
+        }
+
+        let meta = serde_json::json!({
+            "name": name,
+            "version": "1.0",
+            "license": license,
+            "files": files,
+        });
+
+        std::fs::write(
+            env.join("conda-meta").join(format!("{}-1.0-0.json", name)),
+            serde_json::to_vec(&meta)?,
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_conda_environment() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        assert!(CondaEnvironment::from_path(temp_dir.path()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_libraries() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let env_path = temp_dir.path();
+
+        write_package(
+            env_path,
+            "python",
+            Some("Python-2.0"),
+            &["lib/libpython3.9.so.1.0", "lib/python3.9/os.py"],
+        )?;
+        write_package(
+            env_path,
+            "libopenblas",
+            Some("BSD-3-Clause"),
+            &[
+                "lib/libopenblas.so.0",
+                "lib/pkgconfig/openblas.pc",
+                "lib/cmake/openblas/libopenblas.so",
+            ],
+        )?;
+        write_package(
+            env_path,
+            "zlib",
+            Some("Zlib License"),
+            &["Library/bin/ZLIB.dll", "Library/include/zlib.h"],
+        )?;
+
+        let env = CondaEnvironment::from_path(env_path)?;
+        assert_eq!(env.packages.len(), 3);
+        assert_eq!(env.packages[0].name, "libopenblas");
+
+        let libraries = env.shared_libraries();
+        assert_eq!(
+            libraries.keys().collect::<Vec<_>>(),
+            vec!["libopenblas.so.0", "zlib.dll"]
+        );
+        assert_eq!(
+            libraries.get(&library_key("@rpath/libopenblas.so.0")),
+            Some(&CondaSharedLibrary {
+                filename: "libopenblas.so.0".to_string(),
+                path: env_path.join("lib/libopenblas.so.0"),
+                package: "libopenblas".to_string(),
+            })
+        );
+        assert_eq!(libraries.get("zlib.dll").unwrap().filename, "ZLIB.dll");
+
+        assert_eq!(
+            env.site_packages("3.9"),
+            env_path.join("lib").join("python3.9").join("site-packages")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_licensed_component() {
+        let package = CondaPackage {
+            name: "zlib".to_string(),
+            version: "1.2.11".to_string(),
+            license: Some("Zlib License".to_string()),
+            files: vec![],
+        };
+
+        let component = package.licensed_component();
+        assert_eq!(component.flavor(), &ComponentFlavor::Library);
+        assert!(!component.is_spdx());
+
+        let package = CondaPackage {
+            license: Some("Zlib".to_string()),
+            ..package
+        };
+        assert!(package.licensed_component().is_spdx());
+    }
+}
@@ -7,16 +7,19 @@ Configuring a Python interpreter.
 */
 
 use {
-    anyhow::Result,
+    anyhow::{anyhow, Result},
     itertools::Itertools,
     python_packaging::{
         interpreter::{
             Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, MemoryAllocatorBackend,
-            PythonInterpreterConfig, PythonInterpreterProfile, TerminfoResolution,
+            ModuleSearchPathsMode, PythonInterpreterConfig, PythonInterpreterProfile,
+            RemoteResourcesConfig, RestrictedFacility, RestrictedModeConfig, RunModuleMode,
+            RunReportSection, SelfUpdateConfig, TerminfoResolution,
         },
         resource::BytecodeOptimizationLevel,
     },
     std::{
+        collections::{BTreeMap, BTreeSet},
         io::Write,
         path::{Path, PathBuf},
     },
@@ -86,6 +89,10 @@ pub enum PyembedPackedResourcesSource {
     ///
     /// The string `$ORIGIN` is expanded at runtime.
     MemoryMappedPath(PathBuf),
+    /// Load from a file using memory mapped I/O if the file exists.
+    ///
+    /// The string `$ORIGIN` is expanded at runtime.
+    OptionalMemoryMappedPath(PathBuf),
 }
 
 impl ToString for PyembedPackedResourcesSource {
@@ -103,10 +110,38 @@ impl ToString for PyembedPackedResourcesSource {
                     path_to_string(&path)
                 )
             }
+            Self::OptionalMemoryMappedPath(path) => {
+                format!(
+                    "pyembed::PackedResourcesSource::OptionalMemoryMappedPath({})",
+                    path_to_string(&path)
+                )
+            }
         }
     }
 }
 
+/// Represents a frozen module whose marshalled code is loaded via `include_bytes!`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PyembedFrozenModule {
+    /// Name of the module.
+    pub name: String,
+    /// Whether the module is a package.
+    pub is_package: bool,
+    /// Path to the file holding the marshalled code object.
+    pub path: PathBuf,
+}
+
+impl ToString for PyembedFrozenModule {
+    fn to_string(&self) -> String {
+        format!(
+            "pyembed::FrozenModule {{ name: std::ffi::CString::new(\"{}\").unwrap(), code: include_bytes!(r#\"{}\"#), is_package: {} }}",
+            self.name.escape_default(),
+            self.path.display(),
+            self.is_package
+        )
+    }
+}
+
 /// Represents the run-time configuration of a Python interpreter.
 ///
 /// This type mirrors `pyembed::OxidizedPythonInterpreterConfig`. We can't
@@ -126,12 +161,39 @@ pub struct PyembedPythonInterpreterConfig {
     pub oxidized_importer: bool,
     pub filesystem_importer: bool,
     pub packed_resources: Vec<PyembedPackedResourcesSource>,
+    pub frozen_modules: Vec<PyembedFrozenModule>,
     pub argvb: bool,
+    pub sys_orig_argv: bool,
     pub sys_frozen: bool,
     pub sys_meipass: bool,
     pub terminfo_resolution: TerminfoResolution,
     pub tcl_library: Option<PathBuf>,
     pub write_modules_directory_env: Option<String>,
+    pub run_report_directory_env: Option<String>,
+    pub run_report_sections: BTreeSet<RunReportSection>,
+    pub origin_env: Option<String>,
+    pub multicall_run_modules: BTreeMap<String, String>,
+    pub multicall_subcommand: bool,
+    pub multicall_env: Option<String>,
+    pub run_command_env: Option<String>,
+    pub resource_payload_xor_key: Option<String>,
+    pub build_info: BTreeMap<String, String>,
+    pub line_editing: bool,
+    pub python_logging_to_rust: bool,
+    pub ctypes_libraries: BTreeMap<String, PathBuf>,
+    pub gettext_catalogs: bool,
+    pub gettext_cache_directory: Option<PathBuf>,
+    pub run_module_mode: RunModuleMode,
+    pub module_search_paths_mode: ModuleSearchPathsMode,
+    pub working_directory: Option<PathBuf>,
+    pub umask: Option<u32>,
+    pub process_title: Option<String>,
+    pub importer_debug: Option<String>,
+    pub self_update: Option<SelfUpdateConfig>,
+    pub remote_resources: Option<RemoteResourcesConfig>,
+    pub restricted_mode: Option<RestrictedModeConfig>,
+    pub audit_events: Vec<String>,
+    pub audit_log: Option<PathBuf>,
 }
 
 impl Default for PyembedPythonInterpreterConfig {
@@ -159,12 +221,39 @@ impl Default for PyembedPythonInterpreterConfig {
             oxidized_importer: true,
             filesystem_importer: false,
             packed_resources: vec![],
+            frozen_modules: vec![],
             argvb: false,
+            sys_orig_argv: false,
             sys_frozen: false,
             sys_meipass: false,
             terminfo_resolution: TerminfoResolution::None,
             tcl_library: None,
             write_modules_directory_env: None,
+            run_report_directory_env: None,
+            run_report_sections: RunReportSection::all().into_iter().collect(),
+            origin_env: None,
+            multicall_run_modules: BTreeMap::new(),
+            multicall_subcommand: false,
+            multicall_env: None,
+            run_command_env: None,
+            resource_payload_xor_key: None,
+            build_info: BTreeMap::new(),
+            line_editing: false,
+            python_logging_to_rust: false,
+            ctypes_libraries: BTreeMap::new(),
+            gettext_catalogs: false,
+            gettext_cache_directory: None,
+            run_module_mode: RunModuleMode::RunMain,
+            module_search_paths_mode: ModuleSearchPathsMode::Replace,
+            working_directory: None,
+            umask: None,
+            process_title: None,
+            importer_debug: None,
+            self_update: None,
+            remote_resources: None,
+            restricted_mode: None,
+            audit_events: vec![],
+            audit_log: None,
         }
     }
 }
@@ -172,6 +261,10 @@ impl Default for PyembedPythonInterpreterConfig {
 impl PyembedPythonInterpreterConfig {
     /// Convert the instance to Rust code that constructs a `pyembed::OxidizedPythonInterpreterConfig`.
     pub fn to_oxidized_python_interpreter_config_rs(&self) -> Result<String> {
+        self.config
+            .validate()
+            .map_err(|e| anyhow!("invalid Python interpreter configuration: {}", e))?;
+
         let code = format!(
             "pyembed::OxidizedPythonInterpreterConfig {{\n    \
             exe: None,\n    \
@@ -244,14 +337,43 @@ impl PyembedPythonInterpreterConfig {
             oxidized_importer: {},\n    \
             filesystem_importer: {},\n    \
             packed_resources: {},\n    \
+            resource_payload_decoder: {},\n    \
             extra_extension_modules: None,\n    \
+            extra_frozen_modules: {},\n    \
             argv: None,\n    \
+            argv_rewriter: None,\n    \
+            sys_orig_argv: {},\n    \
             argvb: {},\n    \
             sys_frozen: {},\n    \
             sys_meipass: {},\n    \
             terminfo_resolution: {},\n    \
             tcl_library: {},\n    \
             write_modules_directory_env: {},\n    \
+            run_report_directory_env: {},\n    \
+            run_report_sections: {},\n    \
+            origin_env: {},\n    \
+            multicall_run_modules: {},\n    \
+            multicall_subcommand: {},\n    \
+            multicall_env: {},\n    \
+            run_command_env: {},\n    \
+            build_info: {},\n    \
+            line_editing: {},\n    \
+            python_logging_to_rust: {},\n    \
+            ctypes_libraries: {},\n    \
+            gettext_catalogs: {},\n    \
+            gettext_cache_directory: {},\n    \
+            run_module_mode: {},\n    \
+            module_search_paths_mode: {},\n    \
+            working_directory: {},\n    \
+            umask: {},\n    \
+            process_title: {},\n    \
+            importer_debug: {},\n    \
+            self_update: {},\n    \
+            remote_resources: {},\n    \
+            restricted_mode: {},\n    \
+            audit_hooks: vec![],\n    \
+            audit_events: {},\n    \
+            audit_log: {},\n    \
             }}\n\
             ",
             match self.config.profile {
@@ -380,6 +502,24 @@ impl PyembedPythonInterpreterConfig {
                     .map(|e| e.to_string())
                     .join(", ")
             ),
+            match &self.resource_payload_xor_key {
+                Some(key) => format!(
+                    "Some((|data: Vec<u8>| -> Result<Vec<u8>, String> {{ \
+                    Ok(pyembed::xor_resource_payload(&data, &[{}])) \
+                    }}) as pyembed::ResourcePayloadDecoder)",
+                    key.as_bytes().iter().map(|b| b.to_string()).join(", ")
+                ),
+                None => "None".to_string(),
+            },
+            if self.frozen_modules.is_empty() {
+                "None".to_string()
+            } else {
+                format!(
+                    "Some(vec![{}])",
+                    self.frozen_modules.iter().map(|m| m.to_string()).join(", ")
+                )
+            },
+            self.sys_orig_argv,
             self.argvb,
             self.sys_frozen,
             self.sys_meipass,
@@ -392,6 +532,129 @@ impl PyembedPythonInterpreterConfig {
             },
             optional_pathbuf_to_string(&self.tcl_library),
             optional_string_to_string(&self.write_modules_directory_env),
+            optional_string_to_string(&self.run_report_directory_env),
+            format!(
+                "vec![{}].into_iter().collect()",
+                self.run_report_sections
+                    .iter()
+                    .map(|s| match s {
+                        RunReportSection::Modules => "pyembed::RunReportSection::Modules",
+                        RunReportSection::ImportGraph => "pyembed::RunReportSection::ImportGraph",
+                        RunReportSection::ImportTimes => "pyembed::RunReportSection::ImportTimes",
+                        RunReportSection::Resources => "pyembed::RunReportSection::Resources",
+                    })
+                    .join(", ")
+            ),
+            optional_string_to_string(&self.origin_env),
+            format!(
+                "vec![{}].into_iter().collect()",
+                self.multicall_run_modules
+                    .iter()
+                    .map(|(k, v)| format!(
+                        "(\"{}\".to_string(), \"{}\".to_string())",
+                        k.escape_default(),
+                        v.escape_default()
+                    ))
+                    .join(", ")
+            ),
+            self.multicall_subcommand,
+            optional_string_to_string(&self.multicall_env),
+            optional_string_to_string(&self.run_command_env),
+            format!(
+                "vec![{}].into_iter().collect()",
+                self.build_info
+                    .iter()
+                    .map(|(k, v)| format!(
+                        "(\"{}\".to_string(), \"{}\".to_string())",
+                        k.escape_default(),
+                        v.escape_default()
+                    ))
+                    .join(", ")
+            ),
+            self.line_editing,
+            self.python_logging_to_rust,
+            format!(
+                "vec![{}].into_iter().collect()",
+                self.ctypes_libraries
+                    .iter()
+                    .map(|(k, v)| format!(
+                        "(\"{}\".to_string(), {})",
+                        k.escape_default(),
+                        path_to_string(v)
+                    ))
+                    .join(", ")
+            ),
+            self.gettext_catalogs,
+            optional_pathbuf_to_string(&self.gettext_cache_directory),
+            match self.run_module_mode {
+                RunModuleMode::RunMain => "pyembed::RunModuleMode::RunMain",
+                RunModuleMode::Runpy => "pyembed::RunModuleMode::Runpy",
+            },
+            match self.module_search_paths_mode {
+                ModuleSearchPathsMode::Replace => "pyembed::ModuleSearchPathsMode::Replace",
+                ModuleSearchPathsMode::Extend => "pyembed::ModuleSearchPathsMode::Extend",
+            },
+            optional_pathbuf_to_string(&self.working_directory),
+            match self.umask {
+                Some(mask) => format!("Some(0o{:o})", mask),
+                None => "None".to_string(),
+            },
+            optional_string_to_string(&self.process_title),
+            optional_string_to_string(&self.importer_debug),
+            match &self.self_update {
+                Some(config) => format!(
+                    "Some(pyembed::SelfUpdateConfig {{ manifest_url: \"{}\".to_string(), public_key: \"{}\".to_string(), version: \"{}\".to_string(), staging_directory: {} }})",
+                    config.manifest_url.escape_default(),
+                    config.public_key.escape_default(),
+                    config.version.escape_default(),
+                    path_to_string(&config.staging_directory)
+                ),
+                None => "None".to_string(),
+            },
+            match &self.remote_resources {
+                Some(config) => format!(
+                    "Some(pyembed::RemoteResourcesConfig {{ index_url: \"{}\".to_string(), public_key: \"{}\".to_string(), packages: vec![{}], cache_directory: {} }})",
+                    config.index_url.escape_default(),
+                    config.public_key.escape_default(),
+                    config
+                        .packages
+                        .iter()
+                        .map(|p| format!("\"{}\".to_string()", p.escape_default()))
+                        .join(", "),
+                    path_to_string(&config.cache_directory)
+                ),
+                None => "None".to_string(),
+            },
+            match &self.restricted_mode {
+                Some(config) => format!(
+                    "Some(pyembed::RestrictedModeConfig {{ allowed_facilities: vec![{}].into_iter().collect(), denied_modules: vec![{}] }})",
+                    config
+                        .allowed_facilities
+                        .iter()
+                        .map(|f| match f {
+                            RestrictedFacility::Ctypes => "pyembed::RestrictedFacility::Ctypes",
+                            RestrictedFacility::Socket => "pyembed::RestrictedFacility::Socket",
+                            RestrictedFacility::Subprocess => {
+                                "pyembed::RestrictedFacility::Subprocess"
+                            }
+                        })
+                        .join(", "),
+                    config
+                        .denied_modules
+                        .iter()
+                        .map(|m| format!("\"{}\".to_string()", m.escape_default()))
+                        .join(", ")
+                ),
+                None => "None".to_string(),
+            },
+            format!(
+                "vec![{}]",
+                self.audit_events
+                    .iter()
+                    .map(|e| format!("\"{}\".to_string()", e.escape_default()))
+                    .join(", ")
+            ),
+            optional_pathbuf_to_string(&self.audit_log),
         );
 
         Ok(code)
@@ -475,6 +738,306 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_serialize_runtime_flags() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+        config.config.hash_seed = Some(42);
+        config.config.quiet = Some(true);
+        config.config.verbose = Some(false);
+        config.config.write_bytecode = Some(false);
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+
+        assert!(code.contains("hash_seed: Some(42),"));
+        assert!(code.contains("quiet: Some(true),"));
+        assert!(code.contains("verbose: Some(false),"));
+        assert!(code.contains("write_bytecode: Some(false),"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_invalid() {
+        let mut config = PyembedPythonInterpreterConfig::default();
+        config.config.isolated = Some(true);
+        config.config.use_environment = Some(true);
+
+        assert!(config.to_oxidized_python_interpreter_config_rs().is_err());
+    }
+
+    #[test]
+    fn test_serialize_multicall_run_modules() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+        config
+            .multicall_run_modules
+            .insert("mytool".to_string(), "mytool.main".to_string());
+        config
+            .multicall_run_modules
+            .insert("mytool-admin".to_string(), "mytool.admin".to_string());
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+
+        assert_contains(
+            &code,
+            "multicall_run_modules: vec![(\"mytool\".to_string(), \"mytool.main\".to_string()), (\"mytool-admin\".to_string(), \"mytool.admin\".to_string())].into_iter().collect(),",
+        )
+    }
+
+    #[test]
+    fn test_serialize_multicall_env() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "multicall_env: None,")?;
+
+        config.multicall_env = Some("MYAPP_ENTRYPOINT".to_string());
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "multicall_env: Some(\"MYAPP_ENTRYPOINT\".to_string()),",
+        )
+    }
+
+    #[test]
+    fn test_serialize_frozen_modules() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "extra_frozen_modules: None,")?;
+
+        config.frozen_modules.push(PyembedFrozenModule {
+            name: "encodings".to_string(),
+            is_package: true,
+            path: PathBuf::from("frozen-encodings"),
+        });
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+
+        assert_contains(
+            &code,
+            "extra_frozen_modules: Some(vec![pyembed::FrozenModule { name: std::ffi::CString::new(\"encodings\").unwrap(), code: include_bytes!(r#\"frozen-encodings\"#), is_package: true }]),",
+        )
+    }
+
+    #[test]
+    fn test_build_info() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "build_info: vec![].into_iter().collect(),")?;
+
+        config
+            .build_info
+            .insert("pyoxidizer_version".to_string(), "0.12.0".to_string());
+        config.build_info.insert(
+            "target_triple".to_string(),
+            "x86_64-unknown-linux-gnu".to_string(),
+        );
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+
+        assert_contains(
+            &code,
+            "build_info: vec![(\"pyoxidizer_version\".to_string(), \"0.12.0\".to_string()), (\"target_triple\".to_string(), \"x86_64-unknown-linux-gnu\".to_string())].into_iter().collect(),",
+        )
+    }
+
+    #[test]
+    fn test_serialize_ctypes_libraries() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "ctypes_libraries: vec![].into_iter().collect(),")?;
+
+        config.ctypes_libraries.insert(
+            "ssl".to_string(),
+            PathBuf::from("$ORIGIN/lib/libssl.so.1.1"),
+        );
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+
+        assert_contains(
+            &code,
+            "ctypes_libraries: vec![(\"ssl\".to_string(), std::path::PathBuf::from(\"$ORIGIN/lib/libssl.so.1.1\"))].into_iter().collect(),",
+        )
+    }
+
+    #[test]
+    fn test_serialize_run_module_mode() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "run_module_mode: pyembed::RunModuleMode::RunMain,")?;
+
+        config.run_module_mode = RunModuleMode::Runpy;
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "run_module_mode: pyembed::RunModuleMode::Runpy,")
+    }
+
+    #[test]
+    fn test_serialize_module_search_paths_mode() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "module_search_paths_mode: pyembed::ModuleSearchPathsMode::Replace,",
+        )?;
+
+        config.module_search_paths_mode = ModuleSearchPathsMode::Extend;
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "module_search_paths_mode: pyembed::ModuleSearchPathsMode::Extend,",
+        )
+    }
+
+    #[test]
+    fn test_serialize_process_setup() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "working_directory: None,")?;
+        assert_contains(&code, "umask: None,")?;
+        assert_contains(&code, "process_title: None,")?;
+
+        config.working_directory = Some(PathBuf::from("$ORIGIN/var"));
+        config.umask = Some(0o27);
+        config.process_title = Some("myapp".to_string());
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "working_directory: Some(std::path::PathBuf::from(\"$ORIGIN/var\")),",
+        )?;
+        assert_contains(&code, "umask: Some(0o27),")?;
+        assert_contains(&code, "process_title: Some(\"myapp\".to_string()),")
+    }
+
+    #[test]
+    fn test_serialize_importer_debug() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "importer_debug: None,")?;
+
+        config.importer_debug = Some("stderr".to_string());
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "importer_debug: Some(\"stderr\".to_string()),")
+    }
+
+    #[test]
+    fn test_serialize_self_update() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "self_update: None,")?;
+
+        config.self_update = Some(SelfUpdateConfig {
+            manifest_url: "https://example.com/manifest".to_string(),
+            public_key: "00ff".to_string(),
+            version: "1.0".to_string(),
+            staging_directory: PathBuf::from("$ORIGIN/.update"),
+        });
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "self_update: Some(pyembed::SelfUpdateConfig { manifest_url: \"https://example.com/manifest\".to_string(), public_key: \"00ff\".to_string(), version: \"1.0\".to_string(), staging_directory: std::path::PathBuf::from(\"$ORIGIN/.update\") }),",
+        )
+    }
+
+    #[test]
+    fn test_serialize_remote_resources() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "remote_resources: None,")?;
+
+        config.remote_resources = Some(RemoteResourcesConfig {
+            index_url: "https://example.com/index".to_string(),
+            public_key: "00ff".to_string(),
+            packages: vec!["foo".to_string(), "bar".to_string()],
+            cache_directory: PathBuf::from("$ORIGIN/.remote-resources"),
+        });
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "remote_resources: Some(pyembed::RemoteResourcesConfig { index_url: \"https://example.com/index\".to_string(), public_key: \"00ff\".to_string(), packages: vec![\"foo\".to_string(), \"bar\".to_string()], cache_directory: std::path::PathBuf::from(\"$ORIGIN/.remote-resources\") }),",
+        )
+    }
+
+    #[test]
+    fn test_serialize_restricted_mode() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "restricted_mode: None,")?;
+
+        let mut restricted = RestrictedModeConfig::default();
+        restricted
+            .allowed_facilities
+            .insert(RestrictedFacility::Socket);
+        restricted.denied_modules.push("http".to_string());
+        config.restricted_mode = Some(restricted);
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "restricted_mode: Some(pyembed::RestrictedModeConfig { allowed_facilities: vec![pyembed::RestrictedFacility::Socket].into_iter().collect(), denied_modules: vec![\"http\".to_string()] }),",
+        )
+    }
+
+    #[test]
+    fn test_serialize_audit() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "audit_hooks: vec![],")?;
+        assert_contains(&code, "audit_events: vec![],")?;
+        assert_contains(&code, "audit_log: None,")?;
+
+        config.audit_events = vec!["open".to_string(), "socket.".to_string()];
+        config.audit_log = Some(PathBuf::from("$ORIGIN/audit.log"));
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "audit_events: vec![\"open\".to_string(), \"socket.\".to_string()],",
+        )?;
+        assert_contains(
+            &code,
+            "audit_log: Some(std::path::PathBuf::from(\"$ORIGIN/audit.log\")),",
+        )
+    }
+
+    #[test]
+    fn test_run_report() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "run_report_directory_env: None,")?;
+        assert_contains(&code, "run_report_sections: vec![pyembed::RunReportSection::Modules, pyembed::RunReportSection::ImportGraph, pyembed::RunReportSection::ImportTimes, pyembed::RunReportSection::Resources].into_iter().collect(),")?;
+
+        config.run_report_directory_env = Some("RUN_REPORT_DIR".to_string());
+        config.run_report_sections = vec![RunReportSection::Resources].into_iter().collect();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "run_report_directory_env: Some(\"RUN_REPORT_DIR\".to_string()),",
+        )?;
+        assert_contains(
+            &code,
+            "run_report_sections: vec![pyembed::RunReportSection::Resources].into_iter().collect(),",
+        )
+    }
+
     #[test]
     fn test_backslash_in_path() -> Result<()> {
         let mut config = PyembedPythonInterpreterConfig::default();
@@ -569,13 +1132,59 @@ mod tests {
                 PyembedPackedResourcesSource::MemoryMappedPath(PathBuf::from(
                     "$ORIGIN/packed-resources",
                 )),
+                PyembedPackedResourcesSource::OptionalMemoryMappedPath(PathBuf::from(
+                    "$ORIGIN/packed-resources-stdlib",
+                )),
             ],
+            frozen_modules: vec![],
             argvb: true,
+            sys_orig_argv: true,
             sys_frozen: true,
             sys_meipass: true,
             terminfo_resolution: TerminfoResolution::Dynamic,
             tcl_library: Some("path".into()),
             write_modules_directory_env: Some("env".into()),
+            run_report_directory_env: Some("run_report_env".into()),
+            run_report_sections: vec![RunReportSection::Modules].into_iter().collect(),
+            origin_env: Some("origin_env".into()),
+            multicall_run_modules: vec![("name".to_string(), "module".to_string())]
+                .into_iter()
+                .collect(),
+            multicall_subcommand: true,
+            multicall_env: Some("MYAPP_ENTRYPOINT".into()),
+            run_command_env: Some("run_command_env".into()),
+            resource_payload_xor_key: Some("key".into()),
+            build_info: vec![("key".to_string(), "value".to_string())]
+                .into_iter()
+                .collect(),
+            line_editing: true,
+            python_logging_to_rust: true,
+            ctypes_libraries: vec![("foo".to_string(), PathBuf::from("$ORIGIN/lib/libfoo.so"))]
+                .into_iter()
+                .collect(),
+            gettext_catalogs: true,
+            gettext_cache_directory: Some("$ORIGIN/locale-cache".into()),
+            run_module_mode: RunModuleMode::Runpy,
+            module_search_paths_mode: ModuleSearchPathsMode::Extend,
+            working_directory: Some("$ORIGIN/var".into()),
+            umask: Some(0o22),
+            process_title: Some("all_config_fields".into()),
+            importer_debug: Some("stderr".into()),
+            self_update: Some(SelfUpdateConfig {
+                manifest_url: "https://example.com/manifest".to_string(),
+                public_key: "00".repeat(32),
+                version: "1.0".to_string(),
+                staging_directory: PathBuf::from("$ORIGIN/.update"),
+            }),
+            remote_resources: Some(RemoteResourcesConfig {
+                index_url: "https://example.com/index".to_string(),
+                public_key: "00".repeat(32),
+                packages: vec!["foo".to_string()],
+                cache_directory: PathBuf::from("$ORIGIN/.remote-resources"),
+            }),
+            restricted_mode: Some(RestrictedModeConfig::default()),
+            audit_events: vec!["open".to_string()],
+            audit_log: Some("$ORIGIN/audit.log".into()),
         };
 
         let builder = dist.as_python_executable_builder(
@@ -0,0 +1,162 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Generation of launchers for `console_scripts` entry points.
+
+Installers like pip materialize each `console_scripts` entry point as a
+small wrapper script that imports a module, resolves an attribute on it,
+calls it, and exits with its return value. Embedded executables can't
+rely on such scripts. Instead, we generate a single Python module which
+dispatches to the appropriate entry point based on the name the executable
+was invoked as (`sys.argv[0]`) or, failing that, the first argument.
+*/
+
+use {python_packaging::package_metadata::EntryPoint, std::collections::BTreeMap};
+
+/// Name of the Python module holding the generated console scripts dispatcher.
+pub const CONSOLE_SCRIPTS_MODULE: &str = "_pyoxidizer_console_scripts";
+
+/// Entry point group defining console scripts.
+pub const CONSOLE_SCRIPTS_GROUP: &str = "console_scripts";
+
+const DISPATCHER_SOURCE: &str = r#"
+def _resolve(name):
+    module, attr = SCRIPTS[name]
+    import importlib
+    obj = importlib.import_module(module)
+    if attr:
+        for part in attr.split("."):
+            obj = getattr(obj, part)
+    return obj
+
+
+def _script_name(argv0):
+    import os
+    name = os.path.basename(argv0)
+    for suffix in (".exe", "-script.pyw", "-script.py"):
+        if name.lower().endswith(suffix):
+            name = name[: -len(suffix)]
+            break
+    return name
+
+
+def main():
+    import sys
+    name = _script_name(sys.argv[0]) if sys.argv else ""
+    if name not in SCRIPTS:
+        if len(sys.argv) > 1 and sys.argv[1] in SCRIPTS:
+            name = sys.argv.pop(1)
+        elif len(SCRIPTS) == 1:
+            name = next(iter(SCRIPTS))
+        else:
+            sys.stderr.write(
+                "usage: %s <script> [args...]\navailable scripts: %s\n"
+                % (name or "<exe>", ", ".join(sorted(SCRIPTS)))
+            )
+            return 1
+    if sys.argv:
+        sys.argv[0] = name
+    return _resolve(name)()
+
+
+if __name__ == "__main__":
+    import sys
+    sys.exit(main())
+"#;
+
+/// Render a string as a Python string literal.
+///
+/// Rust's `Debug` formatting isn't a valid Python literal for all inputs
+/// (e.g. `\u{..}` escapes), so we emit escapes Python understands. Non-ASCII
+/// characters are escaped so the generated source is pure ASCII.
+fn python_string_literal(value: &str) -> String {
+    let mut res = String::with_capacity(value.len() + 2);
+    res.push('"');
+
+    for c in value.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '"' => res.push_str("\\\""),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            ' '..='~' => res.push(c),
+            c if (c as u32) < 0x100 => res.push_str(&format!("\\x{:02x}", c as u32)),
+            c if (c as u32) < 0x10000 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push_str(&format!("\\U{:08x}", c as u32)),
+        }
+    }
+
+    res.push('"');
+    res
+}
+
+/// Obtain Python source code for a module dispatching to console scripts.
+///
+/// Only entry points in the `console_scripts` group are considered. If
+/// multiple entry points share a name, the last one wins, mirroring how
+/// installers overwrite previously installed scripts.
+pub fn console_scripts_launcher_source<'a>(
+    entry_points: impl Iterator<Item = &'a EntryPoint>,
+) -> String {
+    let scripts = entry_points
+        .filter(|ep| ep.group == CONSOLE_SCRIPTS_GROUP)
+        .map(|ep| (ep.name.clone(), (ep.module.clone(), ep.attr.clone())))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut source = String::from("# Generated by PyOxidizer.\n\nSCRIPTS = {\n");
+
+    for (name, (module, attr)) in scripts {
+        source.push_str(&format!(
+            "    {}: ({}, {}),\n",
+            python_string_literal(&name),
+            python_string_literal(&module),
+            match attr {
+                Some(attr) => python_string_literal(&attr),
+                None => "None".to_string(),
+            }
+        ));
+    }
+
+    source.push_str("}\n");
+    source.push_str(DISPATCHER_SOURCE);
+
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, anyhow::Result, python_packaging::package_metadata::parse_entry_points};
+
+    #[test]
+    fn test_launcher_source() -> Result<()> {
+        let eps = parse_entry_points(
+            b"[console_scripts]\nblack = black:patched_main\nplain = plain\n[gui_scripts]\ngui = gui:main\n",
+        )?;
+
+        let source = console_scripts_launcher_source(eps.iter());
+
+        assert!(source.starts_with(concat!(
+            "# Generated by PyOxidizer.\n\n",
+            "SCRIPTS = {\n",
+            "    \"black\": (\"black\", \"patched_main\"),\n",
+            "    \"plain\": (\"plain\", None),\n",
+            "}\n",
+        )));
+        assert!(!source.contains("gui"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_python_string_literal() {
+        assert_eq!(python_string_literal("foo"), "\"foo\"");
+        assert_eq!(python_string_literal("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(python_string_literal("a\nb\u{7}"), "\"a\\nb\\x07\"");
+        assert_eq!(python_string_literal("caf\u{e9}"), "\"caf\\xe9\"");
+        assert_eq!(python_string_literal("\u{2603}"), "\"\\u2603\"");
+        assert_eq!(python_string_literal("\u{1f600}"), "\"\\U0001f600\"");
+    }
+}
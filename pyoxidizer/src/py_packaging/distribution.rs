@@ -52,6 +52,7 @@ pub enum BinaryLibpythonLinkMode {
 pub enum PythonDistributionLocation {
     Local { local_path: String, sha256: String },
     Url { url: String, sha256: String },
+    System { python_major_minor_version: String },
 }
 
 /// Describes an obtainable Python distribution.
@@ -68,6 +69,12 @@ pub struct PythonDistributionRecord {
 
     /// Whether the distribution can load prebuilt extension modules.
     pub supports_prebuilt_extension_modules: bool,
+
+    /// Whether the distribution was built with profile-guided optimization.
+    ///
+    /// These distributions are also built with link-time optimization when
+    /// the platform's toolchain supports it.
+    pub pgo: bool,
 }
 
 /// Describes a generic Python distribution.
@@ -159,7 +166,8 @@ pub trait PythonDistribution {
 
     /// Ensure pip is available to run in the distribution.
     ///
-    /// Returns the path to a `pip` executable.
+    /// Returns the path to an existing `pip` executable. Callers may instead
+    /// run pip as `python -m pip`, which is guaranteed to work after this.
     fn ensure_pip(&self, logger: &slog::Logger) -> Result<PathBuf>;
 
     /// Resolve a `distutils` installation used for building Python packages.
@@ -260,10 +268,82 @@ fn sha256_path(path: &Path) -> Vec<u8> {
     hasher.finalize().to_vec()
 }
 
+/// Environment variable defining mirrors to download Python distributions from.
+///
+/// The value is a whitespace delimited list of URL templates. See
+/// [distribution_download_urls] for the template syntax.
+pub const DISTRIBUTION_MIRROR_ENV: &str = "PYOXIDIZER_DISTRIBUTION_MIRROR";
+
+/// Resolve the URLs to try when downloading a Python distribution.
+///
+/// Each mirror is a URL template. `{filename}`, `{sha256}`, and `{url}` are
+/// replaced by the filename, SHA-256, and original URL of the distribution.
+/// A template without any of these is treated as a base URL and the filename
+/// is appended to it.
+///
+/// Mirrors passed via `mirrors` are tried first, followed by mirrors defined
+/// by the `PYOXIDIZER_DISTRIBUTION_MIRROR` environment variable, followed by
+/// the original URL.
+pub fn distribution_download_urls(
+    url: &str,
+    sha256: &str,
+    mirrors: &[String],
+) -> Result<Vec<String>> {
+    let u = Url::parse(url)?;
+
+    let filename = u
+        .path_segments()
+        .and_then(|segments| segments.last())
+        .ok_or_else(|| anyhow!("unable to resolve filename of {}", url))?;
+
+    let env_mirrors = std::env::var(DISTRIBUTION_MIRROR_ENV).unwrap_or_default();
+
+    let mut urls = vec![];
+
+    for template in mirrors
+        .iter()
+        .map(|s| s.as_str())
+        .chain(env_mirrors.split_whitespace())
+    {
+        let mirror_url = if template.contains("{filename}")
+            || template.contains("{sha256}")
+            || template.contains("{url}")
+        {
+            template
+                .replace("{filename}", filename)
+                .replace("{sha256}", sha256)
+                .replace("{url}", url)
+        } else {
+            format!("{}/{}", template.trim_end_matches('/'), filename)
+        };
+
+        Url::parse(&mirror_url)
+            .with_context(|| format!("parsing URL from distribution mirror {}", template))?;
+
+        if !urls.contains(&mirror_url) {
+            urls.push(mirror_url);
+        }
+    }
+
+    if !urls.iter().any(|u| u == url) {
+        urls.push(url.to_string());
+    }
+
+    Ok(urls)
+}
+
 /// Ensure a Python distribution at a URL is available in a local directory.
 ///
+/// The distribution is downloaded from the first of `mirrors` (see
+/// [distribution_download_urls]) having content with the expected hash.
+///
 /// The path to the downloaded and validated file is returned.
-pub fn download_distribution(url: &str, sha256: &str, cache_dir: &Path) -> Result<PathBuf> {
+pub fn download_distribution(
+    url: &str,
+    sha256: &str,
+    cache_dir: &Path,
+    mirrors: &[String],
+) -> Result<PathBuf> {
     let expected_hash = hex::decode(sha256)?;
     let u = Url::parse(url)?;
 
@@ -285,21 +365,49 @@ pub fn download_distribution(url: &str, sha256: &str, cache_dir: &Path) -> Resul
         }
     }
 
-    let mut data: Vec<u8> = Vec::new();
-
-    println!("downloading {}", u);
     let client = get_http_client()?;
-    let mut response = client.get(u.as_str()).send()?;
-    response.read_to_end(&mut data)?;
 
-    let mut hasher = Sha256::new();
-    hasher.update(&data);
+    let fetch = |url: &str| -> Result<Vec<u8>> {
+        let mut response = client.get(url).send()?.error_for_status()?;
 
-    let url_hash = hasher.finalize().to_vec();
-    if url_hash != expected_hash {
-        return Err(anyhow!("sha256 of Python distribution does not validate"));
+        let mut data: Vec<u8> = Vec::new();
+        response.read_to_end(&mut data)?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(&data);
+
+        let url_hash = hasher.finalize().to_vec();
+        if url_hash != expected_hash {
+            return Err(anyhow!("sha256 of Python distribution does not validate"));
+        }
+
+        Ok(data)
+    };
+
+    let mut errors = vec![];
+    let mut data = None;
+
+    for candidate in distribution_download_urls(url, sha256, mirrors)? {
+        println!("downloading {}", candidate);
+
+        match fetch(&candidate) {
+            Ok(res) => {
+                data = Some(res);
+                break;
+            }
+            Err(e) => {
+                errors.push(format!("{}: {}", candidate, e));
+            }
+        }
     }
 
+    let data = data.ok_or_else(|| {
+        anyhow!(
+            "unable to download Python distribution; attempted URLs:\n{}",
+            errors.join("\n")
+        )
+    })?;
+
     let mut temp_cache_path = cache_path.clone();
     temp_cache_path.set_file_name(format!("{}.tmp", Uuid::new_v4()));
 
@@ -311,7 +419,7 @@ pub fn download_distribution(url: &str, sha256: &str, cache_dir: &Path) -> Resul
                 .context("unable to remove temporary distribution file")?;
 
             if cache_path.exists() {
-                download_distribution(url, sha256, cache_dir)?;
+                download_distribution(url, sha256, cache_dir, mirrors)?;
                 return Ok(());
             }
 
@@ -363,6 +471,7 @@ pub fn copy_local_distribution(path: &Path, sha256: &str, cache_dir: &Path) -> R
 pub fn resolve_python_distribution_archive(
     dist: &PythonDistributionLocation,
     cache_dir: &Path,
+    mirrors: &[String],
 ) -> Result<PathBuf> {
     if !cache_dir.exists() {
         create_dir_all(cache_dir).unwrap();
@@ -374,8 +483,11 @@ pub fn resolve_python_distribution_archive(
             copy_local_distribution(&p, sha256, cache_dir)
         }
         PythonDistributionLocation::Url { url, sha256 } => {
-            download_distribution(url, sha256, cache_dir)
+            download_distribution(url, sha256, cache_dir, mirrors)
         }
+        PythonDistributionLocation::System { .. } => Err(anyhow!(
+            "system Python distributions do not have an archive"
+        )),
     }
 }
 
@@ -386,9 +498,10 @@ pub fn resolve_python_distribution_from_location(
     logger: &slog::Logger,
     location: &PythonDistributionLocation,
     distributions_dir: &Path,
+    mirrors: &[String],
 ) -> Result<(PathBuf, PathBuf)> {
     warn!(logger, "resolving Python distribution {:?}", location);
-    let path = resolve_python_distribution_archive(location, distributions_dir)?;
+    let path = resolve_python_distribution_archive(location, distributions_dir, mirrors)?;
     warn!(
         logger,
         "Python distribution available at {}",
@@ -398,6 +511,11 @@ pub fn resolve_python_distribution_from_location(
     let distribution_hash = match location {
         PythonDistributionLocation::Local { sha256, .. } => sha256,
         PythonDistributionLocation::Url { sha256, .. } => sha256,
+        PythonDistributionLocation::System { .. } => {
+            return Err(anyhow!(
+                "system Python distributions do not have an archive"
+            ))
+        }
     };
 
     let distribution_path = distributions_dir.join(format!("python.{}", &distribution_hash[0..12]));
@@ -416,6 +534,9 @@ pub enum DistributionFlavor {
 
     /// Dynamically linked distributions coming from the `python-build-standalone` project.
     StandaloneDynamic,
+
+    /// PGO+LTO optimized distributions coming from the `python-build-standalone` project.
+    StandalonePgo,
 }
 
 impl Default for DistributionFlavor {
@@ -432,6 +553,7 @@ impl TryFrom<&str> for DistributionFlavor {
             "standalone" => Ok(Self::Standalone),
             "standalone_static" | "standalone-static" => Ok(Self::StandaloneStatic),
             "standalone_dynamic" | "standalone-dynamic" => Ok(Self::StandaloneDynamic),
+            "pgo" | "standalone_pgo" | "standalone-pgo" => Ok(Self::StandalonePgo),
             _ => Err(format!("distribution flavor {} not recognized", value)),
         }
     }
@@ -456,11 +578,15 @@ impl DistributionCache {
     }
 
     /// Resolve a `PythonDistribution` given its source and storage locations.
+    ///
+    /// `mirrors` are URL templates of mirrors to try when downloading the
+    /// distribution. See [distribution_download_urls].
     pub fn resolve_distribution(
         &self,
         logger: &slog::Logger,
         location: &PythonDistributionLocation,
         dest_dir: Option<&Path>,
+        mirrors: &[String],
     ) -> Result<Arc<StandaloneDistribution>> {
         let dest_dir = if let Some(p) = dest_dir {
             p
@@ -530,7 +656,7 @@ impl DistributionCache {
             Ok(dist.clone())
         } else {
             let dist = Arc::new(StandaloneDistribution::from_location(
-                logger, location, &dest_dir,
+                logger, location, &dest_dir, mirrors,
             )?);
 
             lock.replace(dist.clone());
@@ -550,7 +676,10 @@ pub fn resolve_distribution(
     dest_dir: &Path,
 ) -> Result<Box<dyn PythonDistribution>> {
     Ok(Box::new(StandaloneDistribution::from_location(
-        logger, &location, dest_dir,
+        logger,
+        &location,
+        dest_dir,
+        &[],
     )?) as Box<dyn PythonDistribution>)
 }
 
@@ -562,7 +691,24 @@ pub fn default_distribution_location(
 ) -> Result<PythonDistributionLocation> {
     let dist = PYTHON_DISTRIBUTIONS
         .find_distribution(target, flavor, python_major_minor_version)
-        .ok_or_else(|| anyhow!("could not find default Python distribution for {}", target))?;
+        .ok_or_else(|| {
+            if PYTHON_DISTRIBUTIONS
+                .all_target_triples()
+                .all(|triple| triple != target)
+            {
+                anyhow!(
+                    "could not find default Python distribution for {}; there are no built-in distributions for this target, so one must be provided via PythonDistribution()",
+                    target
+                )
+            } else if flavor == &DistributionFlavor::StandalonePgo {
+                anyhow!(
+                    "could not find PGO optimized Python distribution for {}; PGO builds are not available for every target",
+                    target
+                )
+            } else {
+                anyhow!("could not find default Python distribution for {}", target)
+            }
+        })?;
 
     Ok(dist.location)
 }
@@ -571,10 +717,51 @@ pub fn default_distribution_location(
 mod tests {
     use {super::*, crate::testutil::*};
 
+    #[test]
+    fn test_default_distribution_location_missing_target() {
+        for target in &[
+            "aarch64-pc-windows-msvc",
+            "aarch64-unknown-linux-gnu",
+            "aarch64-unknown-linux-musl",
+            "armv7-unknown-linux-gnueabihf",
+        ] {
+            let err = default_distribution_location(&DistributionFlavor::Standalone, target, None)
+                .unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("there are no built-in distributions for this target"));
+        }
+    }
+
     #[test]
     fn test_all_standalone_distributions() -> Result<()> {
         assert!(!get_all_standalone_distributions()?.is_empty());
 
         Ok(())
     }
+
+    #[test]
+    fn test_distribution_download_urls() -> Result<()> {
+        let url = "https://example.com/releases/cpython-3.9.2.tar.zst";
+
+        assert_eq!(
+            distribution_download_urls(
+                url,
+                "deadbeef",
+                &[
+                    "https://mirror.example.com/python/".to_string(),
+                    "https://cache.example.com/{sha256}/{filename}".to_string(),
+                ],
+            )?,
+            vec![
+                "https://mirror.example.com/python/cpython-3.9.2.tar.zst".to_string(),
+                "https://cache.example.com/deadbeef/cpython-3.9.2.tar.zst".to_string(),
+                url.to_string(),
+            ]
+        );
+
+        assert!(distribution_download_urls(url, "deadbeef", &["not a url".to_string()]).is_err());
+
+        Ok(())
+    }
 }
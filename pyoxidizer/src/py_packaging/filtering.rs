@@ -7,7 +7,8 @@ Utility code for filtering.
 */
 
 use {
-    anyhow::{anyhow, Result},
+    anyhow::{anyhow, Context, Result},
+    regex::Regex,
     slog::warn,
     std::{
         collections::{BTreeMap, BTreeSet},
@@ -15,6 +16,7 @@ use {
         io::{BufRead, BufReader},
         path::Path,
     },
+    tugger_file_manifest::FileData,
 };
 
 pub fn read_resource_names_file(path: &Path) -> Result<BTreeSet<String>> {
@@ -79,3 +81,92 @@ pub fn filter_btreemap<V>(
         }
     }
 }
+
+/// Decides which resources to keep based on name patterns and sizes.
+///
+/// Patterns are regular expressions and are searched for anywhere in the
+/// resource identifier. Use `^` and `$` to anchor them.
+#[derive(Clone, Debug)]
+pub struct ResourceFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+    max_size: Option<u64>,
+}
+
+impl ResourceFilter {
+    /// Construct an instance from regular expression strings and an optional size limit.
+    ///
+    /// If `include_patterns` is non-empty, a resource must match one of them to be
+    /// kept. Resources matching any of `exclude_patterns` are never kept. Resources
+    /// whose data is larger than `max_size` bytes are not kept.
+    pub fn new(
+        include_patterns: &[&str],
+        exclude_patterns: &[&str],
+        max_size: Option<u64>,
+    ) -> Result<Self> {
+        let compile = |patterns: &[&str]| -> Result<Vec<Regex>> {
+            patterns
+                .iter()
+                .map(|p| Regex::new(p).with_context(|| format!("parsing regex {}", p)))
+                .collect()
+        };
+
+        Ok(Self {
+            include: compile(include_patterns)?,
+            exclude: compile(exclude_patterns)?,
+            max_size,
+        })
+    }
+
+    /// Whether a resource with the given identifier and size should be kept.
+    pub fn keep(&self, name: &str, size: Option<u64>) -> bool {
+        if !self.include.is_empty() && !self.include.iter().any(|re| re.is_match(name)) {
+            return false;
+        }
+
+        if self.exclude.iter().any(|re| re.is_match(name)) {
+            return false;
+        }
+
+        match (self.max_size, size) {
+            (Some(max_size), Some(size)) => size <= max_size,
+            _ => true,
+        }
+    }
+}
+
+/// Resolve the size in bytes of a `FileData`.
+pub fn file_data_size(data: &FileData) -> Result<u64> {
+    Ok(match data {
+        FileData::Memory(data) => data.len() as u64,
+        FileData::Path(path) => std::fs::metadata(path)
+            .with_context(|| format!("resolving size of {}", path.display()))?
+            .len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_filter() -> Result<()> {
+        let filter = ResourceFilter::new(&[], &[], None)?;
+        assert!(filter.keep("foo", None));
+        assert!(filter.keep("foo", Some(1000)));
+
+        let filter = ResourceFilter::new(&["^foo"], &[r"\.tests?(\.|$)", r"\.mo$"], Some(10))?;
+        assert!(filter.keep("foo", None));
+        assert!(filter.keep("foo.bar", Some(10)));
+        assert!(!filter.keep("foo.bar", Some(11)));
+        assert!(!filter.keep("bar", None));
+        assert!(!filter.keep("foo.tests", None));
+        assert!(!filter.keep("foo.test.test_bar", None));
+        assert!(filter.keep("foo.testing", None));
+        assert!(!filter.keep("foo:locale/de/LC_MESSAGES/foo.mo", None));
+
+        assert!(ResourceFilter::new(&["("], &[], None).is_err());
+
+        Ok(())
+    }
+}
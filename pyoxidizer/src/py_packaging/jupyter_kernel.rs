@@ -0,0 +1,152 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Packaging applications as Jupyter kernels.
+
+A Jupyter kernel is described by a *kernel spec*: a directory named after
+the kernel containing a `kernel.json` file. `kernel.json` defines the command
+line Jupyter runs to start the kernel. The `{connection_file}` argument is
+replaced by the path of a JSON file describing how to connect to Jupyter.
+
+Our kernels run a built executable whose interpreter runs `ipykernel`. The
+executable is installed into the kernel spec directory next to `kernel.json`.
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    python_packaging::resource_collection::PrePackagedResource,
+    std::path::{Path, PathBuf},
+};
+
+/// Module run by executables providing a Jupyter kernel.
+pub const KERNEL_MODULE: &str = "ipykernel_launcher";
+
+/// Package providing the kernel implementation.
+pub const KERNEL_PACKAGE: &str = "ipykernel";
+
+/// Ensure a string is a valid kernel name.
+///
+/// Jupyter only recognizes kernel spec directories whose name consists of
+/// ASCII letters, numbers, `.`, `-`, and `_`.
+pub fn validate_kernel_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
+    {
+        Err(anyhow!(
+            "invalid kernel name {:?}; names can only contain ASCII letters, numbers, '.', '-', and '_'",
+            name
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Ensure resources contain the package needed to run a kernel.
+pub fn ensure_kernel_package<'a>(
+    mut resources: impl Iterator<Item = (&'a String, &'a PrePackagedResource)>,
+) -> Result<()> {
+    if resources.any(|(name, _)| name == KERNEL_MODULE) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "the {} package is required to run a Jupyter kernel; add it to the executable (e.g. via pip_install([\"{}\"]))",
+            KERNEL_PACKAGE,
+            KERNEL_PACKAGE
+        ))
+    }
+}
+
+/// Derive the content of a `kernel.json` file.
+///
+/// `exe_path` is the path of the executable providing the kernel.
+pub fn kernel_spec(exe_path: &Path, display_name: &str) -> serde_json::Value {
+    serde_json::json!({
+        "argv": [
+            exe_path.display().to_string(),
+            "-f",
+            "{connection_file}",
+        ],
+        "display_name": display_name,
+        "language": "python",
+    })
+}
+
+/// Resolve the directory holding kernel specs of the current user.
+///
+/// This is where `jupyter kernelspec install --user` installs kernels.
+pub fn user_kernels_dir() -> Result<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+
+    let data_dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(|p| PathBuf::from(p).join("jupyter"))
+    } else if cfg!(target_os = "macos") {
+        home.map(|p| p.join("Library").join("Jupyter"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.map(|p| p.join(".local").join("share")))
+            .map(|p| p.join("jupyter"))
+    };
+
+    Ok(data_dir
+        .ok_or_else(|| anyhow!("unable to resolve Jupyter data directory"))?
+        .join("kernels"))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, tugger_file_manifest::FileData};
+
+    #[test]
+    fn test_validate_kernel_name() {
+        assert!(validate_kernel_name("myapp").is_ok());
+        assert!(validate_kernel_name("my_app-1.0").is_ok());
+        assert!(validate_kernel_name("").is_err());
+        assert!(validate_kernel_name("my app").is_err());
+        assert!(validate_kernel_name("../myapp").is_err());
+    }
+
+    #[test]
+    fn test_ensure_kernel_package() {
+        let mut resources = std::collections::BTreeMap::new();
+        resources.insert(
+            "myapp".to_string(),
+            PrePackagedResource {
+                name: "myapp".to_string(),
+                is_module: true,
+                in_memory_source: Some(FileData::Memory(vec![])),
+                ..PrePackagedResource::default()
+            },
+        );
+        assert!(ensure_kernel_package(resources.iter()).is_err());
+
+        resources.insert(
+            KERNEL_MODULE.to_string(),
+            PrePackagedResource {
+                name: KERNEL_MODULE.to_string(),
+                is_module: true,
+                in_memory_source: Some(FileData::Memory(vec![])),
+                ..PrePackagedResource::default()
+            },
+        );
+        assert!(ensure_kernel_package(resources.iter()).is_ok());
+    }
+
+    #[test]
+    fn test_kernel_spec() {
+        let spec = kernel_spec(Path::new("/kernels/myapp/myapp"), "My App");
+
+        assert_eq!(
+            spec,
+            serde_json::json!({
+                "argv": ["/kernels/myapp/myapp", "-f", "{connection_file}"],
+                "display_name": "My App",
+                "language": "python",
+            })
+        );
+    }
+}
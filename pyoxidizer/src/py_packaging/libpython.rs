@@ -58,6 +58,7 @@ pub struct LibpythonInfo {
 /// Returns a vector of cargo: lines that can be printed in build scripts.
 pub fn link_libpython(
     logger: &slog::Logger,
+    cache_path: Option<&Path>,
     context: &LibPythonBuildContext,
     out_dir: &Path,
     host_triple: &str,
@@ -66,7 +67,7 @@ pub fn link_libpython(
 ) -> Result<LibpythonInfo> {
     let mut cargo_metadata: Vec<String> = Vec::new();
 
-    let temp_dir = tempfile::Builder::new().prefix("libpython").tempdir()?;
+    let temp_dir = crate::environment::temp_dir(cache_path, "libpython")?;
     let temp_dir_path = temp_dir.path();
 
     let windows = crate::environment::WINDOWS_TARGET_TRIPLES.contains(&target_triple);
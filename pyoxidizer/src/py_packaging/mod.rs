@@ -9,12 +9,19 @@ This module tree holds functionality that is centered around Python.
 */
 
 pub mod binary;
+pub mod conda;
 pub mod config;
+pub mod console_scripts;
 pub mod distribution;
 pub mod distutils;
 pub mod filtering;
+pub mod jupyter_kernel;
 pub mod libpython;
 pub mod packaging_tool;
+pub mod project_metadata;
 pub mod resource;
+pub mod shell_completions;
 pub mod standalone_builder;
 pub mod standalone_distribution;
+pub mod system_python;
+pub mod zipapp;
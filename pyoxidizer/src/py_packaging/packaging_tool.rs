@@ -14,8 +14,10 @@ use {
     anyhow::{anyhow, Result},
     duct::cmd,
     python_packaging::{
-        filesystem_scanning::find_python_resources, policy::PythonPackagingPolicy,
-        resource::PythonResource, wheel::WheelArchive,
+        filesystem_scanning::{filter_redundant_bytecode, find_python_resources},
+        policy::PythonPackagingPolicy,
+        resource::PythonResource,
+        wheel::{WheelArchive, WheelTargetTags},
     },
     slog::warn,
     std::{
@@ -27,6 +29,8 @@ use {
 };
 
 /// Find resources installed as part of a packaging operation.
+///
+/// Bytecode is only returned for modules without source.
 pub fn find_resources<'a>(
     dist: &dyn PythonDistribution,
     policy: &PythonPackagingPolicy,
@@ -68,7 +72,78 @@ pub fn find_resources<'a>(
         }
     }
 
-    Ok(res)
+    Ok(filter_redundant_bytecode(res))
+}
+
+/// Obtain the PEP 425 tags of wheels compatible with a distribution.
+fn wheel_target_tags(dist: &dyn PythonDistribution) -> WheelTargetTags {
+    WheelTargetTags {
+        python_tag: dist.python_tag().to_string(),
+        abi_tag: dist.python_abi_tag().map(|tag| tag.to_string()),
+        platform_tag: dist.python_platform_compatibility_tag().to_string(),
+    }
+}
+
+/// Construct the error for a wheel incompatible with the target distribution.
+fn incompatible_wheel_error(
+    name: &str,
+    tags: &[String],
+    target: &WheelTargetTags,
+) -> anyhow::Error {
+    let hint = if target.platform_tag == "none" {
+        "the target distribution cannot load extension modules from wheels, so only pure Python wheels can be used".to_string()
+    } else {
+        format!(
+            "to install wheels for the target, pass --platform={} --only-binary=:all: to pip",
+            target.platform_tag
+        )
+    };
+
+    anyhow!(
+        "{} has tags {} which are incompatible with the target Python distribution ({}-{}-{}) and would fail at import time; {}",
+        name,
+        tags.join(", "),
+        target.python_tag,
+        target.abi_tag.as_deref().unwrap_or("none"),
+        target.platform_tag,
+        hint
+    )
+}
+
+/// Verify packages installed in a directory came from compatible wheels.
+///
+/// Packages not installed from wheels don't record tags and are ignored.
+fn verify_installed_wheel_tags(dist: &dyn PythonDistribution, path: &Path) -> Result<()> {
+    let target = wheel_target_tags(dist);
+
+    let mut dist_infos = std::fs::read_dir(path)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    dist_infos.retain(|p| p.extension().map_or(false, |ext| ext == "dist-info"));
+    dist_infos.sort();
+
+    for dist_info in dist_infos {
+        let wheel_path = dist_info.join("WHEEL");
+        if !wheel_path.exists() {
+            continue;
+        }
+
+        let tags = std::fs::read_to_string(&wheel_path)?
+            .lines()
+            .filter_map(|line| line.strip_prefix("Tag:"))
+            .map(|tag| tag.trim().to_string())
+            .collect::<Vec<_>>();
+
+        if !tags.is_empty() && !tags.iter().any(|tag| target.is_compatible(tag)) {
+            return Err(incompatible_wheel_error(
+                &dist_info.file_name().unwrap().to_string_lossy(),
+                &tags,
+                &target,
+            ));
+        }
+    }
+
+    Ok(())
 }
 
 /// Run `pip download` and collect resources found from downloaded packages.
@@ -84,15 +159,14 @@ pub fn find_resources<'a>(
 /// when cross-compiling.
 pub fn pip_download<'a>(
     logger: &slog::Logger,
+    cache_path: Option<&Path>,
     host_dist: &dyn PythonDistribution,
     taget_dist: &dyn PythonDistribution,
     policy: &PythonPackagingPolicy,
     verbose: bool,
     args: &[String],
 ) -> Result<Vec<PythonResource<'a>>> {
-    let temp_dir = tempfile::Builder::new()
-        .prefix("pyoxidizer-pip-download")
-        .tempdir()?;
+    let temp_dir = crate::environment::temp_dir(cache_path, "pyoxidizer-pip-download")?;
 
     host_dist.ensure_pip(logger)?;
 
@@ -164,12 +238,29 @@ pub fn pip_download<'a>(
         .collect::<Result<Vec<_>>>()?;
     files.sort();
 
+    let target = wheel_target_tags(taget_dist);
+
     // TODO there's probably a way to do this using iterators.
     let mut res = Vec::new();
 
     for path in &files {
         let wheel = WheelArchive::from_path(path)?;
 
+        // Additional pip arguments can override the tags we request.
+        if !wheel.is_compatible(&target)? {
+            let tags = wheel
+                .tags()?
+                .iter()
+                .map(|tag| tag.to_string())
+                .collect::<Vec<_>>();
+
+            return Err(incompatible_wheel_error(
+                &path.file_name().unwrap().to_string_lossy(),
+                &tags,
+                &target,
+            ));
+        }
+
         res.extend(wheel.python_resources(
             taget_dist.cache_tag(),
             &taget_dist.python_module_suffixes()?,
@@ -178,12 +269,13 @@ pub fn pip_download<'a>(
         )?);
     }
 
-    Ok(res)
+    Ok(filter_redundant_bytecode(res))
 }
 
 /// Run `pip install` and return found resources.
 pub fn pip_install<'a, S: BuildHasher>(
     logger: &slog::Logger,
+    cache_path: Option<&Path>,
     dist: &dyn PythonDistribution,
     policy: &PythonPackagingPolicy,
     libpython_link_mode: LibpythonLinkMode,
@@ -191,9 +283,7 @@ pub fn pip_install<'a, S: BuildHasher>(
     install_args: &[String],
     extra_envs: &HashMap<String, String, S>,
 ) -> Result<Vec<PythonResource<'a>>> {
-    let temp_dir = tempfile::Builder::new()
-        .prefix("pyoxidizer-pip-install")
-        .tempdir()?;
+    let temp_dir = crate::environment::temp_dir(cache_path, "pyoxidizer-pip-install")?;
 
     dist.ensure_pip(logger)?;
 
@@ -246,6 +336,8 @@ pub fn pip_install<'a, S: BuildHasher>(
         return Err(anyhow!("error running pip"));
     }
 
+    verify_installed_wheel_tags(dist, &target_dir)?;
+
     let state_dir = match env.get("PYOXIDIZER_DISTUTILS_STATE_DIR") {
         Some(p) => Some(PathBuf::from(p)),
         None => None,
@@ -269,10 +361,44 @@ pub fn read_virtualenv<'a>(
 #[allow(clippy::too_many_arguments)]
 pub fn setup_py_install<'a, S: BuildHasher>(
     logger: &slog::Logger,
+    cache_path: Option<&Path>,
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    libpython_link_mode: LibpythonLinkMode,
+    package_path: &Path,
+    verbose: bool,
+    extra_envs: &HashMap<String, String, S>,
+    extra_global_arguments: &[String],
+) -> Result<Vec<PythonResource<'a>>> {
+    run_setup_py_install(
+        logger,
+        cache_path,
+        dist,
+        policy,
+        libpython_link_mode,
+        package_path,
+        Path::new("setup.py"),
+        &[],
+        verbose,
+        extra_envs,
+        extra_global_arguments,
+    )
+}
+
+/// Run a setup script's `install` command in a directory and return found resources.
+///
+/// `setup_script` is resolved relative to `package_path`. `extra_python_paths`
+/// are prepended to `PYTHONPATH`.
+#[allow(clippy::too_many_arguments)]
+fn run_setup_py_install<'a, S: BuildHasher>(
+    logger: &slog::Logger,
+    cache_path: Option<&Path>,
     dist: &dyn PythonDistribution,
     policy: &PythonPackagingPolicy,
     libpython_link_mode: LibpythonLinkMode,
     package_path: &Path,
+    setup_script: &Path,
+    extra_python_paths: &[&Path],
     verbose: bool,
     extra_envs: &HashMap<String, String, S>,
     extra_global_arguments: &[String],
@@ -284,9 +410,7 @@ pub fn setup_py_install<'a, S: BuildHasher>(
         ));
     }
 
-    let temp_dir = tempfile::Builder::new()
-        .prefix("pyoxidizer-setup-py-install")
-        .tempdir()?;
+    let temp_dir = crate::environment::temp_dir(cache_path, "pyoxidizer-setup-py-install")?;
 
     let target_dir_path = temp_dir.path().join("install");
     let target_dir_s = target_dir_path.display().to_string();
@@ -309,14 +433,32 @@ pub fn setup_py_install<'a, S: BuildHasher>(
         envs.insert(key.clone(), value.clone());
     }
 
+    if !extra_python_paths.is_empty() {
+        let mut paths = extra_python_paths
+            .iter()
+            .map(|p| p.to_path_buf())
+            .collect::<Vec<_>>();
+
+        if let Some(existing) = envs.get("PYTHONPATH") {
+            paths.extend(std::env::split_paths(existing));
+        }
+
+        envs.insert(
+            "PYTHONPATH".to_string(),
+            std::env::join_paths(paths)?.to_string_lossy().to_string(),
+        );
+    }
+
     warn!(
         logger,
-        "python setup.py installing {} to {}",
+        "python {} installing {} to {}",
+        setup_script.display(),
         package_path.display(),
         target_dir_s
     );
 
-    let mut args = vec!["setup.py"];
+    let setup_script_s = setup_script.display().to_string();
+    let mut args = vec![setup_script_s.as_str()];
 
     if verbose {
         args.push("--verbose");
@@ -359,6 +501,134 @@ pub fn setup_py_install<'a, S: BuildHasher>(
     find_resources(dist, policy, &python_paths.site_packages, state_dir)
 }
 
+/// Setup script compiling Cython sources into extension modules.
+///
+/// Arguments are passed via environment variables because the script's
+/// command line is that of `setup.py install`.
+const CYTHONIZE_SETUP_PY: &str = r#"import json
+import os
+
+from setuptools import find_packages, setup
+from Cython.Build import cythonize
+
+setup(
+    name="pyoxidizer-cythonize",
+    packages=find_packages(),
+    ext_modules=cythonize(
+        json.loads(os.environ["PYOXIDIZER_CYTHON_SOURCES"]),
+        build_dir=os.environ["PYOXIDIZER_CYTHON_BUILD_DIR"],
+        compiler_directives={
+            "language_level": os.environ["PYOXIDIZER_CYTHON_LANGUAGE_LEVEL"],
+        },
+    ),
+    zip_safe=False,
+)
+"#;
+
+/// Compile Cython sources in a directory and return found resources.
+///
+/// `sources` are glob patterns of `.pyx` and `.py` files relative to
+/// `package_path` which are compiled to extension modules. Python packages
+/// in `package_path` are installed as well.
+///
+/// Cython is installed with `cython_requirement` into a temporary directory.
+/// Extensions are built by the distribution's Python, so they are compiled
+/// against its headers and linked like other extensions built for it.
+#[allow(clippy::too_many_arguments)]
+pub fn cythonize<'a, S: BuildHasher>(
+    logger: &slog::Logger,
+    cache_path: Option<&Path>,
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    libpython_link_mode: LibpythonLinkMode,
+    package_path: &Path,
+    sources: &[String],
+    language_level: &str,
+    cython_requirement: &str,
+    verbose: bool,
+    extra_envs: &HashMap<String, String, S>,
+) -> Result<Vec<PythonResource<'a>>> {
+    if sources.is_empty() {
+        return Err(anyhow!("no Cython sources specified"));
+    }
+
+    let temp_dir = crate::environment::temp_dir(cache_path, "pyoxidizer-cythonize")?;
+
+    dist.ensure_pip(logger)?;
+
+    let cython_dir = temp_dir.path().join("cython");
+    let build_dir = temp_dir.path().join("build");
+    let setup_script = temp_dir.path().join("setup.py");
+
+    warn!(
+        logger,
+        "installing {} to {}",
+        cython_requirement,
+        cython_dir.display()
+    );
+
+    let command = cmd(
+        dist.python_exe_path(),
+        &[
+            "-m",
+            "pip",
+            "--disable-pip-version-check",
+            "install",
+            "--target",
+            &cython_dir.display().to_string(),
+            cython_requirement,
+        ],
+    )
+    .stderr_to_stdout()
+    .reader()?;
+    {
+        let reader = BufReader::new(&command);
+        for line in reader.lines() {
+            warn!(logger, "{}", line?);
+        }
+    }
+
+    let output = command
+        .try_wait()?
+        .ok_or_else(|| anyhow!("unable to wait on command"))?;
+    if !output.status.success() {
+        return Err(anyhow!("error installing Cython"));
+    }
+
+    std::fs::write(&setup_script, CYTHONIZE_SETUP_PY)?;
+
+    let mut envs = extra_envs
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect::<HashMap<_, _>>();
+    envs.insert(
+        "PYOXIDIZER_CYTHON_SOURCES".to_string(),
+        serde_json::to_string(sources)?,
+    );
+    envs.insert(
+        "PYOXIDIZER_CYTHON_BUILD_DIR".to_string(),
+        build_dir.display().to_string(),
+    );
+    envs.insert(
+        "PYOXIDIZER_CYTHON_LANGUAGE_LEVEL".to_string(),
+        language_level.to_string(),
+    );
+
+    run_setup_py_install(
+        logger,
+        cache_path,
+        dist,
+        policy,
+        libpython_link_mode,
+        package_path,
+        &setup_script,
+        &[&cython_dir],
+        verbose,
+        &envs,
+        &[],
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use {
@@ -374,6 +644,7 @@ mod tests {
 
         let resources: Vec<PythonResource> = pip_install(
             &logger,
+            None,
             distribution.deref(),
             &distribution.create_packaging_policy()?,
             LibpythonLinkMode::Dynamic,
@@ -398,6 +669,7 @@ mod tests {
 
         let resources: Vec<PythonResource> = pip_install(
             &logger,
+            None,
             distribution.deref(),
             &policy,
             LibpythonLinkMode::Dynamic,
@@ -420,6 +692,77 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_verify_installed_wheel_tags() -> Result<()> {
+        let distribution = get_default_distribution()?;
+
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let root = temp_dir.path();
+
+        std::fs::create_dir(root.join("pure-1.0.dist-info"))?;
+        std::fs::write(
+            root.join("pure-1.0.dist-info").join("WHEEL"),
+            "Wheel-Version: 1.0\nTag: py2-none-any\nTag: py3-none-any\n",
+        )?;
+        std::fs::create_dir(root.join("sdist-1.0.dist-info"))?;
+        verify_installed_wheel_tags(distribution.deref(), root)?;
+
+        std::fs::create_dir(root.join("pypy-1.0.dist-info"))?;
+        std::fs::write(
+            root.join("pypy-1.0.dist-info").join("WHEEL"),
+            "Wheel-Version: 1.0\nTag: pp37-pypy37_pp73-any\n",
+        )?;
+        let err = verify_installed_wheel_tags(distribution.deref(), root).unwrap_err();
+        assert!(err.to_string().contains("pypy-1.0.dist-info"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cythonize() -> Result<()> {
+        let logger = get_logger()?;
+        let distribution = get_default_distribution()?;
+
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let root = temp_dir.path();
+        std::fs::create_dir(root.join("foo"))?;
+        std::fs::write(root.join("foo").join("__init__.py"), "")?;
+        std::fs::write(
+            root.join("foo").join("fast.pyx"),
+            "def add(int a, int b):\n    return a + b\n",
+        )?;
+
+        let resources: Vec<PythonResource> = cythonize(
+            &logger,
+            None,
+            distribution.deref(),
+            &distribution.create_packaging_policy()?,
+            LibpythonLinkMode::Dynamic,
+            root,
+            &["foo/*.pyx".to_string()],
+            "3",
+            "Cython",
+            false,
+            &HashMap::new(),
+        )?;
+
+        assert!(resources.iter().any(|r| match r {
+            PythonResource::ExtensionModule(em) => em.name == "foo.fast",
+            _ => false,
+        }));
+        assert!(resources.iter().any(|r| match r {
+            PythonResource::ModuleSource(m) => m.name == "foo",
+            _ => false,
+        }));
+
+        Ok(())
+    }
+
     #[test]
     fn test_pip_download_zstandard() -> Result<()> {
         let logger = get_logger()?;
@@ -443,6 +786,7 @@ mod tests {
 
             let resources = pip_download(
                 &logger,
+                None,
                 &*host_dist,
                 &*target_dist,
                 &policy,
@@ -543,6 +887,7 @@ mod tests {
 
             let res = pip_download(
                 &logger,
+                None,
                 &*host_dist,
                 &*target_dist,
                 &policy,
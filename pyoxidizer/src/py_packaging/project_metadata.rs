@@ -0,0 +1,593 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Extraction of metadata from Python project source trees.
+
+Python projects declare their name, dependencies, and entry points in a
+`pyproject.toml`, `setup.cfg`, and/or `setup.py` file. The first two are
+declarative and can be parsed. `setup.py` is arbitrary Python code and
+would need to be executed to obtain accurate metadata. Instead, we scan it
+for literal keyword arguments to `setup()`. This is best effort and is only
+used to seed generated configuration files.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    once_cell::sync::Lazy,
+    python_packaging::package_metadata::EntryPoint,
+    std::{collections::BTreeMap, path::Path},
+};
+
+/// Entry point group defining console scripts.
+const CONSOLE_SCRIPTS_GROUP: &str = "console_scripts";
+
+static SETUP_PY_NAME: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(r#"\bname\s*=\s*['"]([^'"]+)['"]"#).unwrap());
+
+static SETUP_PY_INSTALL_REQUIRES: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(r#"\binstall_requires\s*=\s*\[([^\]]*)\]"#).unwrap());
+
+static SETUP_PY_CONSOLE_SCRIPTS: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(r#"['"]console_scripts['"]\s*:\s*\[([^\]]*)\]"#).unwrap());
+
+static STRING_LITERAL: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(r#"'([^']*)'|"([^"]*)""#).unwrap());
+
+/// Metadata describing a Python project.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProjectMetadata {
+    /// Name of the project's distribution.
+    pub name: Option<String>,
+
+    /// Requirement specifiers of the project's dependencies.
+    pub requirements: Vec<String>,
+
+    /// `console_scripts` entry points defined by the project.
+    pub console_scripts: Vec<EntryPoint>,
+}
+
+impl ProjectMetadata {
+    /// Merge metadata from another source into this instance.
+    ///
+    /// Values already present in this instance take precedence.
+    fn merge(&mut self, other: ProjectMetadata) {
+        if self.name.is_none() {
+            self.name = other.name;
+        }
+
+        for requirement in other.requirements {
+            if !self.requirements.contains(&requirement) {
+                self.requirements.push(requirement);
+            }
+        }
+
+        for script in other.console_scripts {
+            if !self.console_scripts.iter().any(|s| s.name == script.name) {
+                self.console_scripts.push(script);
+            }
+        }
+    }
+}
+
+/// Parse a `console_scripts` entry point of the form `name = module:attr`.
+fn parse_console_script(value: &str) -> Result<EntryPoint> {
+    let pos = value
+        .find('=')
+        .ok_or_else(|| anyhow!("expected `name = value` in console script: {}", value))?;
+
+    EntryPoint::from_value(
+        CONSOLE_SCRIPTS_GROUP,
+        value[0..pos].trim(),
+        value[pos + 1..].trim(),
+    )
+}
+
+/// Parse the numeric release components of a version in a Poetry constraint.
+fn poetry_version_components(version: &str) -> Result<Vec<u64>> {
+    version
+        .split('.')
+        .map(|part| {
+            part.parse::<u64>().map_err(|_| {
+                anyhow!(
+                    "unsupported version in Poetry caret/tilde constraint: {}",
+                    version
+                )
+            })
+        })
+        .collect()
+}
+
+/// Format version components as a version string.
+fn format_version(components: &[u64]) -> String {
+    components
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Convert a single Poetry version constraint to a PEP 440 version specifier.
+///
+/// Caret constraints allow changes that don't modify the left-most non-zero
+/// component (e.g. `^1.2` means `>=1.2,<2.0`). Tilde constraints allow
+/// patch-level changes if a minor version is given and minor-level changes
+/// otherwise (e.g. `~1.2.3` means `>=1.2.3,<1.3.0`).
+fn poetry_specifier(constraint: &str) -> Result<String> {
+    let constraint = constraint.trim();
+
+    let (version, upper) = if let Some(version) = constraint.strip_prefix('^') {
+        let version = version.trim();
+        let mut upper = poetry_version_components(version)?;
+        let index = upper
+            .iter()
+            .position(|c| *c != 0)
+            .unwrap_or(upper.len() - 1);
+        upper.truncate(index + 1);
+        upper[index] += 1;
+
+        (version, upper)
+    } else if let Some(version) = constraint
+        .strip_prefix('~')
+        .filter(|_| !constraint.starts_with("~="))
+    {
+        let version = version.trim();
+        let mut upper = poetry_version_components(version)?;
+        let index = if upper.len() == 1 { 0 } else { 1 };
+        upper.truncate(index + 1);
+        upper[index] += 1;
+
+        (version, upper)
+    } else if constraint.starts_with(|c: char| c.is_ascii_digit()) {
+        return Ok(format!("=={}", constraint));
+    } else {
+        return Ok(constraint.to_string());
+    };
+
+    Ok(format!(">={},<{}", version, format_version(&upper)))
+}
+
+/// Convert a Poetry dependency constraint into a requirement specifier.
+///
+/// Caret (`^`) and tilde (`~`) constraints are translated to equivalent
+/// PEP 440 version ranges. Constraints that can't be expressed as a single
+/// requirement, such as alternatives joined with `||`, are an error.
+fn poetry_requirement(name: &str, constraint: &str) -> Result<String> {
+    let constraint = constraint.trim();
+
+    if constraint.is_empty() || constraint == "*" {
+        return Ok(name.to_string());
+    }
+
+    if constraint.contains('|') {
+        return Err(anyhow!(
+            "unsupported Poetry constraint for {}: {}",
+            name,
+            constraint
+        ));
+    }
+
+    let specifiers = constraint
+        .split(',')
+        .map(poetry_specifier)
+        .collect::<Result<Vec<_>>>()
+        .with_context(|| format!("converting Poetry constraint for {}", name))?;
+
+    Ok(format!("{}{}", name, specifiers.join(",")))
+}
+
+/// Parse the content of a `pyproject.toml` file.
+///
+/// Both PEP 621 `[project]` metadata and Poetry's `[tool.poetry]` metadata
+/// are recognized.
+pub fn parse_pyproject_toml(data: &str) -> Result<ProjectMetadata> {
+    let value = data.parse::<toml::Value>()?;
+
+    let mut metadata = ProjectMetadata::default();
+
+    let tables = [
+        value.get("project"),
+        value.get("tool").and_then(|tool| tool.get("poetry")),
+    ];
+
+    for (i, table) in tables.iter().enumerate() {
+        let table = match table {
+            Some(table) => table,
+            None => continue,
+        };
+        let poetry = i == 1;
+
+        let mut other = ProjectMetadata {
+            name: table
+                .get("name")
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string()),
+            ..ProjectMetadata::default()
+        };
+
+        match table.get("dependencies") {
+            Some(toml::Value::Array(values)) => {
+                other.requirements.extend(
+                    values
+                        .iter()
+                        .filter_map(|v| v.as_str())
+                        .map(|v| v.to_string()),
+                );
+            }
+            Some(toml::Value::Table(values)) if poetry => {
+                for (name, constraint) in values {
+                    // Poetry lists the Python version as a dependency.
+                    if name == "python" {
+                        continue;
+                    }
+
+                    // Dependencies defined by tables can point at version
+                    // control or local paths, which we can't express.
+                    let constraint = match constraint {
+                        toml::Value::String(s) => s.as_str(),
+                        toml::Value::Table(t) => match t.get("version").and_then(|v| v.as_str()) {
+                            Some(version) => version,
+                            None => continue,
+                        },
+                        _ => continue,
+                    };
+
+                    other
+                        .requirements
+                        .push(poetry_requirement(name, constraint)?);
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(toml::Value::Table(scripts)) = table.get("scripts") {
+            for (name, value) in scripts {
+                if let Some(value) = value.as_str() {
+                    other.console_scripts.push(EntryPoint::from_value(
+                        CONSOLE_SCRIPTS_GROUP,
+                        name,
+                        value,
+                    )?);
+                }
+            }
+        }
+
+        metadata.merge(other);
+    }
+
+    Ok(metadata)
+}
+
+/// Parse INI content into a mapping of section to key-value pairs.
+///
+/// Indented lines continue the value of the preceding key. Multi-line values
+/// are joined with newlines.
+fn parse_ini(data: &str) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut sections = BTreeMap::<String, BTreeMap<String, String>>::new();
+    let mut section = None;
+    let mut key = None;
+
+    for line in data.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with(char::is_whitespace) {
+            if let (Some(section), Some(key)) = (&section, &key) {
+                if let Some(value) = sections
+                    .get_mut(section)
+                    .and_then(|values| values.get_mut(key))
+                {
+                    value.push('\n');
+                    value.push_str(trimmed);
+                }
+            }
+
+            continue;
+        }
+
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            let name = trimmed[1..trimmed.len() - 1].trim().to_string();
+            sections.entry(name.clone()).or_default();
+            section = Some(name);
+            key = None;
+            continue;
+        }
+
+        if let (Some(section), Some(pos)) = (&section, trimmed.find(&['=', ':'][..])) {
+            let k = trimmed[0..pos].trim().to_string();
+            let v = trimmed[pos + 1..].trim().to_string();
+
+            sections.get_mut(section).unwrap().insert(k.clone(), v);
+            key = Some(k);
+        }
+    }
+
+    sections
+}
+
+/// Obtain the non-empty lines of a multi-line INI value.
+fn ini_list(value: &str) -> impl Iterator<Item = &str> {
+    value.lines().map(|l| l.trim()).filter(|l| !l.is_empty())
+}
+
+/// Parse the content of a `setup.cfg` file.
+pub fn parse_setup_cfg(data: &str) -> Result<ProjectMetadata> {
+    let sections = parse_ini(data);
+
+    let mut metadata = ProjectMetadata::default();
+
+    if let Some(name) = sections.get("metadata").and_then(|s| s.get("name")) {
+        metadata.name = Some(name.clone());
+    }
+
+    if let Some(requires) = sections
+        .get("options")
+        .and_then(|s| s.get("install_requires"))
+    {
+        metadata.requirements = ini_list(requires).map(|l| l.to_string()).collect();
+    }
+
+    if let Some(scripts) = sections
+        .get("options.entry_points")
+        .and_then(|s| s.get(CONSOLE_SCRIPTS_GROUP))
+    {
+        for line in ini_list(scripts) {
+            metadata.console_scripts.push(parse_console_script(line)?);
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// Obtain the values of string literals in Python source code.
+fn string_literals(source: &str) -> impl Iterator<Item = &str> {
+    STRING_LITERAL.captures_iter(source).filter_map(|caps| {
+        caps.get(1)
+            .or_else(|| caps.get(2))
+            .map(|m| m.as_str().trim())
+    })
+}
+
+/// Scan the content of a `setup.py` file for project metadata.
+///
+/// Only metadata defined by string literals passed directly to `setup()` is
+/// found.
+pub fn parse_setup_py(data: &str) -> Result<ProjectMetadata> {
+    let mut metadata = ProjectMetadata::default();
+
+    if let Some(caps) = SETUP_PY_NAME.captures(data) {
+        metadata.name = Some(caps[1].to_string());
+    }
+
+    if let Some(caps) = SETUP_PY_INSTALL_REQUIRES.captures(data) {
+        metadata.requirements = string_literals(&caps[1])
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+    }
+
+    if let Some(caps) = SETUP_PY_CONSOLE_SCRIPTS.captures(data) {
+        for value in string_literals(&caps[1]) {
+            metadata.console_scripts.push(parse_console_script(value)?);
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// Read metadata for the Python project in a directory.
+///
+/// `pyproject.toml`, `setup.cfg`, and `setup.py` are consulted, in that
+/// order. Values from earlier files take precedence. Returns `None` if none
+/// of these files exist.
+pub fn read_project_metadata(path: &Path) -> Result<Option<ProjectMetadata>> {
+    let mut res = None;
+
+    for filename in &["pyproject.toml", "setup.cfg", "setup.py"] {
+        let file_path = path.join(filename);
+        if !file_path.is_file() {
+            continue;
+        }
+
+        let data = std::fs::read_to_string(&file_path)
+            .with_context(|| format!("reading {}", file_path.display()))?;
+        let metadata = match *filename {
+            "pyproject.toml" => parse_pyproject_toml(&data),
+            "setup.cfg" => parse_setup_cfg(&data),
+            _ => parse_setup_py(&data),
+        }
+        .with_context(|| format!("parsing {}", file_path.display()))?;
+
+        res.get_or_insert_with(ProjectMetadata::default)
+            .merge(metadata);
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(name: &str, module: &str, attr: Option<&str>) -> EntryPoint {
+        EntryPoint {
+            group: CONSOLE_SCRIPTS_GROUP.to_string(),
+            name: name.to_string(),
+            module: module.to_string(),
+            attr: attr.map(|s| s.to_string()),
+            extras: vec![],
+        }
+    }
+
+    #[test]
+    fn test_parse_pyproject_toml() -> Result<()> {
+        let m = parse_pyproject_toml(
+            "[project]\nname = \"myapp\"\ndependencies = [\"click>=7\", \"attrs\"]\n\n\
+             [project.scripts]\nmyapp = \"myapp.cli:main\"\n",
+        )?;
+
+        assert_eq!(
+            m,
+            ProjectMetadata {
+                name: Some("myapp".to_string()),
+                requirements: vec!["click>=7".to_string(), "attrs".to_string()],
+                console_scripts: vec![script("myapp", "myapp.cli", Some("main"))],
+            }
+        );
+
+        let m = parse_pyproject_toml(
+            "[tool.poetry]\nname = \"poetic\"\n\n\
+             [tool.poetry.dependencies]\npython = \"^3.8\"\nrequests = \"^2.25\"\n\
+             six = \"1.15.0\"\ntoml = { version = \">=0.10\" }\nlocal = { path = \"../local\" }\n\n\
+             [tool.poetry.scripts]\npoetic = \"poetic:run\"\n",
+        )?;
+
+        assert_eq!(m.name, Some("poetic".to_string()));
+        assert_eq!(
+            m.requirements,
+            vec![
+                "requests>=2.25,<3".to_string(),
+                "six==1.15.0".to_string(),
+                "toml>=0.10".to_string()
+            ]
+        );
+        assert_eq!(
+            m.console_scripts,
+            vec![script("poetic", "poetic", Some("run"))]
+        );
+
+        assert_eq!(
+            parse_pyproject_toml("[build-system]\nrequires = [\"setuptools\"]\n")?,
+            ProjectMetadata::default()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_poetry_requirement() -> Result<()> {
+        assert_eq!(poetry_requirement("foo", "*")?, "foo");
+        assert_eq!(poetry_requirement("foo", "^1.2.3")?, "foo>=1.2.3,<2");
+        assert_eq!(poetry_requirement("foo", "^0.2.3")?, "foo>=0.2.3,<0.3");
+        assert_eq!(poetry_requirement("foo", "^0.0.3")?, "foo>=0.0.3,<0.0.4");
+        assert_eq!(poetry_requirement("foo", "^0.0")?, "foo>=0.0,<0.1");
+        assert_eq!(poetry_requirement("foo", "^0")?, "foo>=0,<1");
+        assert_eq!(poetry_requirement("foo", "~1.2.3")?, "foo>=1.2.3,<1.3");
+        assert_eq!(poetry_requirement("foo", "~1.2")?, "foo>=1.2,<1.3");
+        assert_eq!(poetry_requirement("foo", "~1")?, "foo>=1,<2");
+        assert_eq!(poetry_requirement("foo", "~=1.2")?, "foo~=1.2");
+        assert_eq!(poetry_requirement("foo", "1.2.*")?, "foo==1.2.*");
+        assert_eq!(poetry_requirement("foo", ">=1.2, <1.5")?, "foo>=1.2,<1.5");
+        assert_eq!(
+            poetry_requirement("foo", "^1.2, !=1.3.0")?,
+            "foo>=1.2,<2,!=1.3.0"
+        );
+
+        assert!(poetry_requirement("foo", "^1.0 || ^2.0").is_err());
+        assert!(poetry_requirement("foo", "^1.0b1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_setup_cfg() -> Result<()> {
+        let m = parse_setup_cfg(concat!(
+            "[metadata]\n",
+            "name = myapp\n",
+            "version = 1.0\n",
+            "\n",
+            "[options]\n",
+            "packages = find:\n",
+            "install_requires =\n",
+            "    click>=7\n",
+            "    # A comment.\n",
+            "    attrs; python_version < \"3.8\"\n",
+            "\n",
+            "[options.entry_points]\n",
+            "console_scripts =\n",
+            "    myapp = myapp.cli:main\n",
+            "    myapp-tool = myapp.tool\n",
+        ))?;
+
+        assert_eq!(
+            m,
+            ProjectMetadata {
+                name: Some("myapp".to_string()),
+                requirements: vec![
+                    "click>=7".to_string(),
+                    "attrs; python_version < \"3.8\"".to_string()
+                ],
+                console_scripts: vec![
+                    script("myapp", "myapp.cli", Some("main")),
+                    script("myapp-tool", "myapp.tool", None),
+                ],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_setup_py() -> Result<()> {
+        let m = parse_setup_py(concat!(
+            "from setuptools import setup\n",
+            "\n",
+            "setup(\n",
+            "    name='myapp',\n",
+            "    version='1.0',\n",
+            "    install_requires=[\n",
+            "        'click>=7',\n",
+            "        \"attrs\",\n",
+            "    ],\n",
+            "    entry_points={\n",
+            "        'console_scripts': ['myapp = myapp.cli:main'],\n",
+            "    },\n",
+            ")\n",
+        ))?;
+
+        assert_eq!(
+            m,
+            ProjectMetadata {
+                name: Some("myapp".to_string()),
+                requirements: vec!["click>=7".to_string(), "attrs".to_string()],
+                console_scripts: vec![script("myapp", "myapp.cli", Some("main"))],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_project_metadata() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let path = temp_dir.path();
+
+        assert_eq!(read_project_metadata(path)?, None);
+
+        std::fs::write(
+            path.join("setup.cfg"),
+            "[metadata]\nname = fromcfg\n[options]\ninstall_requires =\n    six\n",
+        )?;
+        std::fs::write(
+            path.join("setup.py"),
+            "setup(name='frompy', install_requires=['six', 'attrs'])\n",
+        )?;
+
+        assert_eq!(
+            read_project_metadata(path)?,
+            Some(ProjectMetadata {
+                name: Some("fromcfg".to_string()),
+                requirements: vec!["six".to_string(), "attrs".to_string()],
+                console_scripts: vec![],
+            })
+        );
+
+        Ok(())
+    }
+}
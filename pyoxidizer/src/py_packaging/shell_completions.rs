@@ -0,0 +1,280 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Generating shell completion scripts for command line applications.
+
+Completion scripts are generated by running a built executable and capturing
+what it prints. By default, the executable is run the way
+[click](https://click.palletsprojects.com/) applications generate their
+completion scripts: with a `_<PROG>_COMPLETE` environment variable set to
+`<shell>_source`. Other applications can be run with arbitrary arguments
+instead, e.g. `--print-completion {shell}` for applications using `shtab`
+with `argparse`.
+
+Scripts are installed where each shell finds completions by default,
+relative to a `share` directory.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    slog::warn,
+    std::{
+        convert::TryFrom,
+        path::{Path, PathBuf},
+    },
+    tugger_file_manifest::{FileEntry, FileManifest},
+};
+
+/// Placeholder in arguments replaced by the name of the shell.
+pub const SHELL_PLACEHOLDER: &str = "{shell}";
+
+/// A shell supporting completion scripts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl CompletionShell {
+    /// All supported shells.
+    pub fn all() -> Vec<Self> {
+        vec![Self::Bash, Self::Zsh, Self::Fish]
+    }
+
+    /// Path of the completion script for a program, relative to a `share` directory.
+    pub fn install_path(&self, prog_name: &str) -> PathBuf {
+        match self {
+            Self::Bash => PathBuf::from("bash-completion")
+                .join("completions")
+                .join(prog_name),
+            Self::Zsh => PathBuf::from("zsh")
+                .join("site-functions")
+                .join(format!("_{}", prog_name)),
+            Self::Fish => PathBuf::from("fish")
+                .join("vendor_completions.d")
+                .join(format!("{}.fish", prog_name)),
+        }
+    }
+}
+
+impl ToString for CompletionShell {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+        }
+        .to_string()
+    }
+}
+
+impl TryFrom<&str> for CompletionShell {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "bash" => Ok(Self::Bash),
+            "zsh" => Ok(Self::Zsh),
+            "fish" => Ok(Self::Fish),
+            _ => Err(format!(
+                "{} is not a valid shell; must be 'bash', 'zsh', or 'fish'",
+                value
+            )),
+        }
+    }
+}
+
+/// Name of the environment variable click uses to generate completions.
+pub fn click_complete_env_var(prog_name: &str) -> String {
+    format!("_{}_COMPLETE", prog_name.replace(&['-', '.'][..], "_")).to_uppercase()
+}
+
+/// Generate completion scripts by running an executable.
+///
+/// If `args` is `None`, the executable is run like click generates
+/// completions. Otherwise it is run with `args`, with [SHELL_PLACEHOLDER]
+/// replaced by the name of the shell. The standard output of the process is
+/// the completion script.
+///
+/// Returns a manifest with scripts at paths relative to `prefix`, which
+/// should be a `share` directory.
+pub fn generate_shell_completions(
+    logger: &slog::Logger,
+    exe_path: &Path,
+    prog_name: &str,
+    shells: &[CompletionShell],
+    args: Option<&[String]>,
+    prefix: &Path,
+) -> Result<FileManifest> {
+    let mut manifest = FileManifest::default();
+
+    for shell in shells {
+        let shell_name = shell.to_string();
+
+        let mut command = std::process::Command::new(exe_path);
+        if let Some(exe_dir) = exe_path.parent() {
+            command.current_dir(exe_dir);
+        }
+
+        if let Some(args) = args {
+            command.args(
+                args.iter()
+                    .map(|a| a.replace(SHELL_PLACEHOLDER, &shell_name)),
+            );
+        } else {
+            command.env(
+                click_complete_env_var(prog_name),
+                format!("{}_source", shell_name),
+            );
+        }
+
+        warn!(
+            logger,
+            "generating {} completions for {}", shell_name, prog_name
+        );
+
+        let output = command
+            .output()
+            .with_context(|| format!("running {}", exe_path.display()))?;
+
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            warn!(logger, "{}", line);
+        }
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "generating {} completions failed: {}",
+                shell_name,
+                output.status
+            ));
+        }
+        if output.stdout.iter().all(|c| c.is_ascii_whitespace()) {
+            return Err(anyhow!(
+                "generating {} completions produced no output",
+                shell_name
+            ));
+        }
+
+        manifest.add_file_entry(
+            prefix.join(shell.install_path(prog_name)),
+            FileEntry {
+                data: output.stdout.into(),
+                executable: false,
+            },
+        )?;
+    }
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::io::Write};
+
+    #[test]
+    fn test_shell_parsing() {
+        for shell in CompletionShell::all() {
+            assert_eq!(
+                CompletionShell::try_from(shell.to_string().as_str()),
+                Ok(shell)
+            );
+        }
+
+        assert!(CompletionShell::try_from("tcsh").is_err());
+    }
+
+    #[test]
+    fn test_install_path() {
+        assert_eq!(
+            CompletionShell::Bash.install_path("myapp"),
+            PathBuf::from("bash-completion/completions/myapp")
+        );
+        assert_eq!(
+            CompletionShell::Zsh.install_path("myapp"),
+            PathBuf::from("zsh/site-functions/_myapp")
+        );
+        assert_eq!(
+            CompletionShell::Fish.install_path("myapp"),
+            PathBuf::from("fish/vendor_completions.d/myapp.fish")
+        );
+    }
+
+    #[test]
+    fn test_click_complete_env_var() {
+        assert_eq!(click_complete_env_var("my-app"), "_MY_APP_COMPLETE");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_generate_shell_completions() -> Result<()> {
+        let logger = crate::testutil::get_logger()?;
+
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let exe_path = temp_dir.path().join("myapp");
+        let mut fh = std::fs::File::create(&exe_path)?;
+        fh.write_all(
+            b"#!/bin/sh\nif [ -n \"$_MYAPP_COMPLETE\" ]; then echo \"$_MYAPP_COMPLETE\"; else echo \"$1\"; fi\n",
+        )?;
+        tugger_file_manifest::set_executable(&mut fh)?;
+        drop(fh);
+
+        let manifest = generate_shell_completions(
+            &logger,
+            &exe_path,
+            "myapp",
+            &[CompletionShell::Bash, CompletionShell::Fish],
+            None,
+            Path::new("usr/share"),
+        )?;
+        assert_eq!(
+            manifest
+                .get("usr/share/bash-completion/completions/myapp")
+                .unwrap()
+                .data
+                .resolve()?,
+            b"bash_source\n".to_vec()
+        );
+        assert_eq!(
+            manifest
+                .get("usr/share/fish/vendor_completions.d/myapp.fish")
+                .unwrap()
+                .data
+                .resolve()?,
+            b"fish_source\n".to_vec()
+        );
+
+        let manifest = generate_shell_completions(
+            &logger,
+            &exe_path,
+            "myapp",
+            &[CompletionShell::Zsh],
+            Some(&["--completion={shell}".to_string()]),
+            Path::new("share"),
+        )?;
+        assert_eq!(
+            manifest
+                .get("share/zsh/site-functions/_myapp")
+                .unwrap()
+                .data
+                .resolve()?,
+            b"--completion=zsh\n".to_vec()
+        );
+
+        assert!(generate_shell_completions(
+            &logger,
+            &exe_path,
+            "myapp",
+            &[CompletionShell::Bash],
+            Some(&[]),
+            Path::new("share"),
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
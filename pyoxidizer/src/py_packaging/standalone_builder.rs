@@ -5,42 +5,55 @@
 use {
     super::{
         binary::{
-            pyembed_licenses, EmbeddedPythonContext, LibpythonLinkMode, PackedResourcesLoadMode,
-            PythonBinaryBuilder, PythonLinkingInfo, ResourceAddCollectionContextCallback,
+            normalize_relative_path, pyembed_licenses, DebugInfoSettings, EmbeddedPythonContext,
+            ExecutableCompression, LibpythonLinkMode, PackedResourcesLoadMode, PythonBinaryBuilder,
+            PythonLinkingInfo, ResourceAddCollectionContextCallback, RustProjectCustomizations,
             WindowsRuntimeDllsMode,
         },
-        config::{PyembedPackedResourcesSource, PyembedPythonInterpreterConfig},
+        conda::read_conda_env,
+        config::{
+            PyembedFrozenModule, PyembedPackedResourcesSource, PyembedPythonInterpreterConfig,
+        },
         distribution::{BinaryLibpythonLinkMode, PythonDistribution},
-        filtering::{filter_btreemap, resolve_resource_names_from_files},
+        filtering::{
+            file_data_size, filter_btreemap, resolve_resource_names_from_files, ResourceFilter,
+        },
         libpython::link_libpython,
         packaging_tool::{
-            find_resources, pip_download, pip_install, read_virtualenv, setup_py_install,
+            cythonize, find_resources, pip_download, pip_install, read_virtualenv, setup_py_install,
         },
         standalone_distribution::StandaloneDistribution,
     },
     anyhow::{anyhow, Context, Result},
     once_cell::sync::Lazy,
     python_packaging::{
-        bytecode::BytecodeCompiler,
+        bytecode::{
+            BytecodeCache, BytecodeCompiler, CachingBytecodeCompiler, CompileMode,
+            PythonBytecodeCompiler,
+        },
+        import_graph::{is_interpreter_startup_module, ImportGraph, INTERPRETER_STARTUP_MODULES},
         interpreter::MemoryAllocatorBackend,
         libpython::LibPythonBuildContext,
         licensing::derive_package_license_infos,
         location::AbstractResourceLocation,
-        policy::PythonPackagingPolicy,
+        policy::{PythonPackagingPolicy, StdlibPackedResourcesLocation},
         resource::{
-            PythonExtensionModule, PythonModuleSource, PythonPackageDistributionResource,
-            PythonPackageResource, PythonResource,
+            BytecodeOptimizationLevel, PythonExtensionModule, PythonModuleBytecode,
+            PythonModuleSource, PythonPackageDistributionResource, PythonPackageResource,
+            PythonResource,
         },
         resource_collection::{
-            PrePackagedResource, PythonResourceAddCollectionContext, PythonResourceCollector,
+            xor_resource_payload, CompiledResourcesCollection, PrePackagedResource,
+            PythonResourceAddCollectionContext, PythonResourceCollector,
         },
     },
+    python_packed_resources::data::METADATA_PYTHON_VERSION,
     slog::warn,
     std::{
         collections::{BTreeMap, BTreeSet, HashMap},
         convert::TryInto,
         path::{Path, PathBuf},
-        sync::Arc,
+        sync::{Arc, Mutex},
     },
     tugger_file_manifest::{File, FileData, FileEntry, FileManifest},
     tugger_licensing::{ComponentFlavor, LicensedComponent},
@@ -119,8 +132,30 @@ pub struct StandalonePythonExecutableBuilder {
     /// Path to install tcl/tk files into.
     tcl_files_path: Option<String>,
 
+    /// Names of Python modules to freeze into the binary.
+    frozen_modules: Vec<String>,
+
+    /// Directory holding scratch state and caches of the build.
+    cache_path: Option<PathBuf>,
+
+    /// Bytecode compiled by this and other builders sharing the cache.
+    bytecode_cache: Arc<Mutex<BytecodeCache>>,
+
+    /// Whether to register directories holding filesystem-relative modules
+    /// as module search paths.
+    relative_path_module_search_paths: bool,
+
     /// Describes how Windows runtime DLLs should be handled during builds.
     windows_runtime_dlls_mode: WindowsRuntimeDllsMode,
+
+    /// Customizations to the Rust project used to build the executable.
+    rust_project: RustProjectCustomizations,
+
+    /// How debug information in the built executable is processed.
+    debug_info: DebugInfoSettings,
+
+    /// How the built executable is compressed.
+    executable_compression: ExecutableCompression,
 }
 
 impl StandalonePythonExecutableBuilder {
@@ -153,6 +188,12 @@ impl StandalonePythonExecutableBuilder {
             }
             BinaryLibpythonLinkMode::Static => {
                 if !supports_static_libpython {
+                    if target_distribution.is_pgo_optimized() {
+                        return Err(anyhow!(
+                            "PGO optimized Python distribution does not support statically linking libpython; use a non-PGO distribution flavor (e.g. standalone_static) instead"
+                        ));
+                    }
+
                     return Err(anyhow!(
                         "Python distribution does not support statically linking libpython"
                     ));
@@ -220,7 +261,14 @@ impl StandalonePythonExecutableBuilder {
             host_python_exe,
             windows_subsystem: "console".to_string(),
             tcl_files_path: None,
+            frozen_modules: vec![],
+            cache_path: None,
+            bytecode_cache: Arc::new(Mutex::new(BytecodeCache::default())),
+            relative_path_module_search_paths: false,
             windows_runtime_dlls_mode: WindowsRuntimeDllsMode::WhenPresent,
+            rust_project: RustProjectCustomizations::default(),
+            debug_info: DebugInfoSettings::default(),
+            executable_compression: ExecutableCompression::default(),
         });
 
         builder.add_distribution_core_state()?;
@@ -228,6 +276,49 @@ impl StandalonePythonExecutableBuilder {
         Ok(builder)
     }
 
+    /// Compile Python modules registered for freezing to marshalled code objects.
+    ///
+    /// Returns tuples of module name, whether the module is a package, and
+    /// the marshalled code object.
+    fn compile_frozen_modules(
+        &self,
+        compiler: &mut dyn PythonBytecodeCompiler,
+    ) -> Result<Vec<(String, bool, Vec<u8>)>> {
+        let mut res = vec![];
+
+        for name in &self.frozen_modules {
+            let resource = self
+                .resources_collector
+                .iter_resources()
+                .find_map(|(k, v)| if k == name { Some(v) } else { None })
+                .ok_or_else(|| anyhow!("cannot freeze {}: module not present", name))?;
+
+            let source = if let Some(source) = &resource.in_memory_source {
+                source
+            } else if let Some((_, source)) = &resource.relative_path_module_source {
+                source
+            } else {
+                return Err(anyhow!(
+                    "cannot freeze {}: Python source code not available",
+                    name
+                ));
+            };
+
+            let code = compiler
+                .compile(
+                    &source.resolve()?,
+                    &format!("<frozen {}>", name),
+                    BytecodeOptimizationLevel::Zero,
+                    CompileMode::Bytecode,
+                )
+                .with_context(|| format!("compiling frozen module {}", name))?;
+
+            res.push((name.clone(), resource.is_package, code));
+        }
+
+        Ok(res)
+    }
+
     fn add_distribution_core_state(&mut self) -> Result<()> {
         for component in pyembed_licenses().context("deriving pyembed component licenses")? {
             self.resources_collector.add_licensed_component(component)?;
@@ -319,9 +410,10 @@ impl StandalonePythonExecutableBuilder {
 
         match self.link_mode {
             LibpythonLinkMode::Static => {
-                let temp_dir = tempfile::Builder::new()
-                    .prefix("pyoxidizer-build-exe-packaging")
-                    .tempdir()?;
+                let temp_dir = crate::environment::temp_dir(
+                    self.cache_path.as_deref(),
+                    "pyoxidizer-build-exe-packaging",
+                )?;
                 let temp_dir_path = temp_dir.path();
 
                 warn!(
@@ -336,6 +428,7 @@ impl StandalonePythonExecutableBuilder {
 
                 let library_info = link_libpython(
                     logger,
+                    self.cache_path.as_deref(),
                     &LibPythonBuildContext::merge(&link_contexts),
                     &temp_dir_path,
                     &self.host_triple,
@@ -430,6 +523,10 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         self.exe_name.clone()
     }
 
+    fn set_name(&mut self, name: &str) {
+        self.exe_name = name.to_string();
+    }
+
     fn libpython_link_mode(&self) -> LibpythonLinkMode {
         self.link_mode
     }
@@ -477,6 +574,16 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         &self.target_distribution.python_exe_path()
     }
 
+    fn distribution_top_level_module_names(&self) -> BTreeSet<String> {
+        self.target_distribution
+            .py_modules
+            .keys()
+            .chain(self.target_distribution.extension_modules.keys())
+            .chain(self.target_distribution.resources.keys())
+            .map(|name| name.split('.').next().unwrap_or(name).to_string())
+            .collect()
+    }
+
     fn windows_runtime_dlls_mode(&self) -> &WindowsRuntimeDllsMode {
         &self.windows_runtime_dlls_mode
     }
@@ -505,6 +612,60 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         };
     }
 
+    fn debug_info(&self) -> &DebugInfoSettings {
+        &self.debug_info
+    }
+
+    fn set_debug_info(&mut self, value: DebugInfoSettings) {
+        self.debug_info = value;
+
+        // Record the processing in build metadata so debug files for a
+        // binary can be located later.
+        if let Some(description) = self.debug_info.description() {
+            self.config
+                .build_info
+                .insert("debug_info".to_string(), description);
+        } else {
+            self.config.build_info.remove("debug_info");
+        }
+    }
+
+    fn executable_compression(&self) -> ExecutableCompression {
+        self.executable_compression
+    }
+
+    fn set_executable_compression(&mut self, value: ExecutableCompression) {
+        self.executable_compression = value;
+    }
+
+    fn frozen_modules(&self) -> &[String] {
+        &self.frozen_modules
+    }
+
+    fn set_frozen_modules(&mut self, names: Vec<String>) {
+        self.frozen_modules = names;
+    }
+
+    fn cache_path(&self) -> Option<&Path> {
+        self.cache_path.as_deref()
+    }
+
+    fn set_cache_path(&mut self, path: Option<PathBuf>) {
+        self.cache_path = path;
+    }
+
+    fn set_bytecode_cache(&mut self, cache: Arc<Mutex<BytecodeCache>>) {
+        self.bytecode_cache = cache;
+    }
+
+    fn relative_path_module_search_paths(&self) -> bool {
+        self.relative_path_module_search_paths
+    }
+
+    fn set_relative_path_module_search_paths(&mut self, value: bool) {
+        self.relative_path_module_search_paths = value;
+    }
+
     fn windows_subsystem(&self) -> &str {
         &self.windows_subsystem
     }
@@ -515,6 +676,14 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         Ok(())
     }
 
+    fn rust_project(&self) -> &RustProjectCustomizations {
+        &self.rust_project
+    }
+
+    fn rust_project_mut(&mut self) -> &mut RustProjectCustomizations {
+        &mut self.rust_project
+    }
+
     fn packed_resources_load_mode(&self) -> &PackedResourcesLoadMode {
         &self.resources_load_mode
     }
@@ -523,6 +692,14 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         self.resources_load_mode = load_mode;
     }
 
+    fn python_interpreter_config(&self) -> &PyembedPythonInterpreterConfig {
+        &self.config
+    }
+
+    fn python_interpreter_config_mut(&mut self) -> &mut PyembedPythonInterpreterConfig {
+        &mut self.config
+    }
+
     fn iter_resources<'a>(
         &'a self,
     ) -> Box<dyn Iterator<Item = (&'a String, &'a PrePackagedResource)> + 'a> {
@@ -549,6 +726,7 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
     ) -> Result<Vec<PythonResource>> {
         let resources = pip_download(
             logger,
+            self.cache_path.as_deref(),
             &*self.host_distribution,
             &*self.target_distribution,
             self.python_packaging_policy(),
@@ -572,6 +750,7 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
     ) -> Result<Vec<PythonResource>> {
         let resources = pip_install(
             logger,
+            self.cache_path.as_deref(),
             &*self.target_distribution,
             self.python_packaging_policy(),
             self.link_mode,
@@ -616,6 +795,28 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         Ok(resources)
     }
 
+    fn read_conda_env(
+        &mut self,
+        _logger: &slog::Logger,
+        path: &Path,
+    ) -> Result<Vec<PythonResource>> {
+        let (resources, components) = read_conda_env(
+            &*self.target_distribution,
+            self.python_packaging_policy(),
+            path,
+        )
+        .context("reading conda environment")?;
+
+        self.index_package_license_info_from_resources(&resources)
+            .context("indexing package license metadata")?;
+
+        for component in components {
+            self.resources_collector.add_licensed_component(component)?;
+        }
+
+        Ok(resources)
+    }
+
     fn read_virtualenv(
         &mut self,
         _logger: &slog::Logger,
@@ -644,6 +845,7 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
     ) -> Result<Vec<PythonResource>> {
         let resources = setup_py_install(
             logger,
+            self.cache_path.as_deref(),
             &*self.target_distribution,
             self.python_packaging_policy(),
             self.link_mode,
@@ -660,6 +862,37 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         Ok(resources)
     }
 
+    fn cythonize(
+        &mut self,
+        logger: &slog::Logger,
+        package_path: &Path,
+        sources: &[String],
+        language_level: &str,
+        cython_requirement: &str,
+        verbose: bool,
+        extra_envs: &HashMap<String, String>,
+    ) -> Result<Vec<PythonResource>> {
+        let resources = cythonize(
+            logger,
+            self.cache_path.as_deref(),
+            &*self.target_distribution,
+            self.python_packaging_policy(),
+            self.link_mode,
+            package_path,
+            sources,
+            language_level,
+            cython_requirement,
+            verbose,
+            extra_envs,
+        )
+        .context("running cythonize")?;
+
+        self.index_package_license_info_from_resources(&resources)
+            .context("indexing package license metadata")?;
+
+        Ok(resources)
+    }
+
     fn add_distribution_resources(
         &mut self,
         callback: Option<ResourceAddCollectionContextCallback>,
@@ -687,6 +920,22 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
                 callback(&self.packaging_policy, &resource, &mut add_context)?;
             }
 
+            // Record which variant was chosen when there was a choice, as it
+            // determines backends like the TLS library in the binary.
+            let has_choice = self
+                .target_distribution
+                .extension_modules
+                .get(&ext.name)
+                .map_or(false, |variants| variants.iter().count() > 1);
+            if add_context.include && has_choice {
+                if let Some(variant) = &ext.variant {
+                    self.config.build_info.insert(
+                        format!("extension_module_variant.{}", ext.name),
+                        variant.clone(),
+                    );
+                }
+            }
+
             if let Some(component) = &ext.license {
                 self.resources_collector
                     .add_licensed_component(component.clone())?;
@@ -757,6 +1006,20 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             .add_python_module_source_with_context(module, &add_context)
     }
 
+    fn add_python_module_bytecode(
+        &mut self,
+        module: &PythonModuleBytecode,
+        add_context: Option<PythonResourceAddCollectionContext>,
+    ) -> Result<()> {
+        let add_context = add_context.unwrap_or_else(|| {
+            self.packaging_policy
+                .derive_add_collection_context(&module.into())
+        });
+
+        self.resources_collector
+            .add_python_module_bytecode_with_context(module, &add_context)
+    }
+
     fn add_python_package_resource(
         &mut self,
         resource: &PythonPackageResource,
@@ -864,6 +1127,160 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         Ok(())
     }
 
+    fn filter_resources(
+        &mut self,
+        logger: &slog::Logger,
+        filter: &ResourceFilter,
+        dry_run: bool,
+    ) -> Result<Vec<String>> {
+        let mut removed_modules = BTreeSet::new();
+        let mut removed_resources = BTreeSet::new();
+
+        for (name, resource) in self.resources_collector.iter_resources() {
+            if resource.is_module {
+                let source = resource.in_memory_source.as_ref().or_else(|| {
+                    resource
+                        .relative_path_module_source
+                        .as_ref()
+                        .map(|(_, data)| data)
+                });
+
+                let size = if let Some(data) = source {
+                    Some(file_data_size(data)?)
+                } else {
+                    None
+                };
+
+                if !filter.keep(name, size) {
+                    removed_modules.insert(name.clone());
+                    continue;
+                }
+            }
+
+            let package_resources = resource
+                .in_memory_resources
+                .iter()
+                .flat_map(|m| m.iter())
+                .chain(
+                    resource
+                        .relative_path_package_resources
+                        .iter()
+                        .flat_map(|m| m.iter().map(|(k, (_, data))| (k, data))),
+                );
+
+            for (resource_name, data) in package_resources {
+                if !filter.keep(
+                    &format!("{}:{}", name, resource_name),
+                    Some(file_data_size(data)?),
+                ) {
+                    removed_resources.insert((name.clone(), resource_name.clone()));
+                }
+            }
+        }
+
+        let removed = removed_modules
+            .iter()
+            .cloned()
+            .chain(
+                removed_resources
+                    .iter()
+                    .map(|(package, name)| format!("{}:{}", package, name)),
+            )
+            .collect::<Vec<_>>();
+
+        for name in &removed {
+            if dry_run {
+                warn!(logger, "would remove {}", name);
+            } else {
+                warn!(logger, "removing {}", name);
+            }
+        }
+
+        if !dry_run {
+            self.resources_collector
+                .filter_resources_mut(|resource| !removed_modules.contains(&resource.name))?;
+            self.resources_collector
+                .filter_package_resources_mut(|package, name| {
+                    !removed_resources.contains(&(package.to_string(), name.to_string()))
+                })?;
+        }
+
+        Ok(removed)
+    }
+
+    fn import_graph(&self) -> Result<ImportGraph> {
+        let temp_dir =
+            crate::environment::temp_dir(self.cache_path.as_deref(), "pyoxidizer-import-graph")?;
+        let mut compiler = BytecodeCompiler::new(self.host_python_exe_path(), temp_dir.path())?;
+
+        ImportGraph::from_resources(self.resources_collector.iter_resources(), &mut compiler)
+    }
+
+    fn prune_unreachable_resources(
+        &mut self,
+        logger: &slog::Logger,
+        roots: &[String],
+        dry_run: bool,
+    ) -> Result<BTreeMap<String, String>> {
+        let graph = self.import_graph()?;
+
+        let roots = roots
+            .iter()
+            .cloned()
+            .chain(INTERPRETER_STARTUP_MODULES.iter().map(|s| s.to_string()))
+            .collect::<Vec<_>>();
+
+        let unresolved = graph.unresolved_importers(&roots);
+        let unreachable = graph.unreachable(&roots);
+
+        let mut removed = BTreeMap::new();
+
+        for (name, resource) in self.resources_collector.iter_resources() {
+            // Modules imported when the interpreter starts dynamically import
+            // the module being run, which is a root.
+            if unresolved.contains(name)
+                && !resource.is_builtin_extension_module
+                && !resource.is_frozen_module
+                && !is_interpreter_startup_module(name)
+            {
+                if graph.is_analyzed(name) {
+                    warn!(
+                        logger,
+                        "{} imports modules by names computed at run-time; modules it imports from other packages may be removed",
+                        name
+                    );
+                } else {
+                    warn!(
+                        logger,
+                        "unable to determine imports of {}; modules it imports from other packages may be removed",
+                        name
+                    );
+                }
+            }
+
+            if !resource.is_module {
+                continue;
+            }
+
+            if let Some(reason) = unreachable.get(name) {
+                if dry_run {
+                    warn!(logger, "would remove {}: {}", name, reason);
+                } else {
+                    warn!(logger, "removing {}: {}", name, reason);
+                }
+
+                removed.insert(name.clone(), reason.clone());
+            }
+        }
+
+        if !dry_run {
+            self.resources_collector
+                .filter_resources_mut(|resource| !removed.contains_key(&resource.name))?;
+        }
+
+        Ok(removed)
+    }
+
     fn requires_jemalloc(&self) -> bool {
         self.config.allocator_backend == MemoryAllocatorBackend::Jemalloc
     }
@@ -933,27 +1350,193 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             warn!(logger, "license: {}; packages: {:?}", license, packages);
         }
 
-        let compiled_resources = {
-            let temp_dir = tempfile::TempDir::new()?;
+        let optimization_level = self
+            .config
+            .config
+            .optimization_level
+            .unwrap_or(BytecodeOptimizationLevel::Zero);
+        let substitutions = self
+            .packaging_policy
+            .bytecode_optimization_level_substitutions(
+                optimization_level,
+                self.resources_collector
+                    .iter_resources()
+                    .filter(|(_, resource)| resource.is_module)
+                    .map(|(name, _)| name.as_str()),
+            );
+
+        let (mut compiled_resources, frozen_modules) = {
+            let temp_dir = crate::environment::temp_dir(
+                self.cache_path.as_deref(),
+                "pyoxidizer-bytecode-compiler",
+            )?;
             let mut compiler = BytecodeCompiler::new(self.host_python_exe_path(), temp_dir.path())?;
-            self.resources_collector.compile_resources(&mut compiler)?
+            let mut compiler = CachingBytecodeCompiler::new(&mut compiler, &self.bytecode_cache);
+
+            // Modules registered for other optimization levels are loaded from
+            // bytecode compiled at these levels.
+            let mut substituted_collector;
+            let resources_collector = if substitutions.is_empty() {
+                &self.resources_collector
+            } else {
+                substituted_collector = self.resources_collector.clone();
+                for (name, compile_level) in substitutions {
+                    warn!(
+                        logger,
+                        "loading {} from bytecode compiled at optimization level {}",
+                        name,
+                        i32::from(compile_level)
+                    );
+                    substituted_collector.load_bytecode_at_optimization_level(
+                        &name,
+                        optimization_level,
+                        compile_level,
+                        &mut compiler,
+                    )?;
+                }
+                &substituted_collector
+            };
+
+            (
+                resources_collector.compile_resources(&mut compiler)?,
+                self.compile_frozen_modules(&mut compiler)?,
+            )
         };
 
+        if let Some(key) = &self.config.resource_payload_xor_key {
+            compiled_resources
+                .transform_module_payloads(|data| xor_resource_payload(data, key.as_bytes()));
+        }
+
+        // Record the Python version resources were compiled for so the
+        // interpreter can refuse resources built for another version.
+        compiled_resources.metadata.insert(
+            METADATA_PYTHON_VERSION.to_string(),
+            self.target_distribution.python_major_minor_version(),
+        );
+
         let mut pending_resources = vec![];
         let mut extra_files = FileManifest::default();
+        let mut external_files = BTreeMap::new();
 
         for (path, location, executable) in &compiled_resources.extra_files {
-            extra_files.add_file_entry(
-                path,
-                FileEntry {
-                    data: location.resolve()?.into(),
-                    executable: *executable,
-                },
-            )?;
+            let entry = FileEntry {
+                data: location.resolve()?.into(),
+                executable: *executable,
+            };
+
+            // Paths are relative to the binary's directory, which they may
+            // reference as `$ORIGIN`. The importer expands environment
+            // variables in them at run-time, so we can't know where such
+            // files go: whoever installs the binary has to put them there.
+            let install_path = path.strip_prefix("$ORIGIN").unwrap_or(path);
+            if install_path.to_string_lossy().contains('$') {
+                warn!(
+                    logger,
+                    "not installing {}: its path references environment variables and must be installed separately",
+                    path.display()
+                );
+                continue;
+            }
+
+            let install_path = normalize_relative_path(install_path);
+            if install_path.starts_with("..") {
+                external_files.insert(install_path, entry);
+            } else {
+                extra_files.add_file_entry(&install_path, entry)?;
+            }
         }
 
         let mut config = self.config.clone();
 
+        let mut pending_frozen_modules = vec![];
+        for (name, is_package, code) in frozen_modules {
+            let path = PathBuf::from(format!("frozen-{}", name));
+
+            config.frozen_modules.push(PyembedFrozenModule {
+                name,
+                is_package,
+                path: path.clone(),
+            });
+            pending_frozen_modules.push((path, code));
+        }
+
+        // If requested, make Python modules materialized next to the binary
+        // importable by filesystem-based importers too by registering the
+        // directories they are installed in with `sys.path`.
+        let module_prefixes = if self.relative_path_module_search_paths {
+            self.resources_collector
+                .iter_resources()
+                .flat_map(|(_, r)| r.relative_path_module_prefixes())
+                .collect::<BTreeSet<_>>()
+        } else {
+            BTreeSet::new()
+        };
+        if !module_prefixes.is_empty() {
+            let search_paths = config
+                .config
+                .module_search_paths
+                .get_or_insert_with(Vec::new);
+
+            for prefix in module_prefixes {
+                // Prefixes may reference `$ORIGIN` themselves. Other variables
+                // are only expanded by the importer, so we can't register them.
+                let path = if prefix.starts_with("$ORIGIN") {
+                    PathBuf::from(prefix)
+                } else if prefix.contains('$') {
+                    continue;
+                } else {
+                    PathBuf::from("$ORIGIN").join(prefix)
+                };
+                if !search_paths.contains(&path) {
+                    search_paths.push(path);
+                }
+            }
+        }
+
+        // Search paths are only consulted by the filesystem importer.
+        if !config.filesystem_importer {
+            if let Some(paths) = &config.config.module_search_paths {
+                if !paths.is_empty() {
+                    warn!(
+                        logger,
+                        "module_search_paths is set but filesystem_importer is disabled; \
+                        modules in {} will not be importable",
+                        paths
+                            .iter()
+                            .map(|p| p.display().to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                }
+            }
+        }
+
+        // Standard library resources can be stored separately from other
+        // resources so they can be shipped and updated independently.
+        let stdlib_resources = if self.packaging_policy.stdlib_packed_resources()
+            == &StdlibPackedResourcesLocation::Default
+        {
+            None
+        } else {
+            let (stdlib, other) = std::mem::take(&mut compiled_resources.resources)
+                .into_iter()
+                .partition::<BTreeMap<_, _>, _>(|(name, _)| {
+                    self.target_distribution.py_modules.contains_key(name)
+                        || self
+                            .target_distribution
+                            .extension_modules
+                            .contains_key(name)
+                });
+            compiled_resources.resources = other;
+
+            Some(CompiledResourcesCollection {
+                resources: stdlib,
+                extra_files: vec![],
+                metadata: compiled_resources.metadata.clone(),
+            })
+        };
+
         match &self.resources_load_mode {
             PackedResourcesLoadMode::None => {}
             PackedResourcesLoadMode::EmbeddedInBinary(filename) => {
@@ -986,9 +1569,54 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             }
         }
 
+        if let Some(stdlib_resources) = stdlib_resources {
+            let (embedded, external) = match self.packaging_policy.stdlib_packed_resources() {
+                StdlibPackedResourcesLocation::Default => (false, None),
+                StdlibPackedResourcesLocation::Embedded => (true, None),
+                StdlibPackedResourcesLocation::External(filename) => (false, Some(filename)),
+                StdlibPackedResourcesLocation::EmbeddedAndExternal(filename) => {
+                    (true, Some(filename))
+                }
+            };
+
+            // Resources from later sources take precedence. So the external
+            // file comes last and overrides the embedded copy if present.
+            if embedded {
+                let filename = PathBuf::from("packed-resources-stdlib");
+                pending_resources.push((stdlib_resources.clone(), filename.clone()));
+                config
+                    .packed_resources
+                    .push(PyembedPackedResourcesSource::MemoryIncludeBytes(filename));
+            }
+
+            if let Some(filename) = external {
+                let mut buffer = vec![];
+                stdlib_resources
+                    .write_packed_resources(&mut buffer)
+                    .context("serializing standard library packed resources")?;
+                extra_files.add_file_entry(
+                    Path::new(filename),
+                    FileEntry {
+                        data: FileData::Memory(buffer),
+                        executable: false,
+                    },
+                )?;
+
+                let path = PathBuf::from("$ORIGIN").join(filename);
+                config.packed_resources.push(if embedded {
+                    PyembedPackedResourcesSource::OptionalMemoryMappedPath(path)
+                } else {
+                    PyembedPackedResourcesSource::MemoryMappedPath(path)
+                });
+            }
+        }
+
         let linking_info = self.resolve_python_linking_info(logger, opt_level)?;
 
-        if self.link_mode == LibpythonLinkMode::Dynamic {
+        // A system libpython is provided by the operating system and isn't distributed.
+        if self.link_mode == LibpythonLinkMode::Dynamic
+            && !self.target_distribution.is_system_python()
+        {
             if let Some(p) = &self.target_distribution.libpython_shared_library {
                 let manifest_path = Path::new(p.file_name().unwrap());
                 let content = FileEntry {
@@ -1035,7 +1663,9 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             config,
             linking_info,
             pending_resources,
+            pending_frozen_modules,
             extra_files,
+            external_files,
             host_triple: self.host_triple.clone(),
             target_triple: self.target_triple.clone(),
         })
@@ -1051,7 +1681,7 @@ pub mod tests {
         crate::testutil::*,
         once_cell::sync::Lazy,
         python_packaging::{location::ConcreteResourceLocation, policy::ExtensionModuleFilter},
-        std::ops::DerefMut,
+        std::{borrow::Cow, convert::TryFrom, ops::DerefMut},
         tugger_licensing::LicensedComponents,
     };
 
@@ -1348,6 +1978,208 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_stdlib_packed_resources() -> Result<()> {
+        let logger = get_logger()?;
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+        exe.packaging_policy.set_stdlib_packed_resources(
+            StdlibPackedResourcesLocation::EmbeddedAndExternal("stdlib".to_string()),
+        );
+
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+
+        assert_eq!(
+            &embedded.config.packed_resources,
+            &vec![
+                PyembedPackedResourcesSource::MemoryIncludeBytes("packed-resources".into()),
+                PyembedPackedResourcesSource::MemoryIncludeBytes("packed-resources-stdlib".into()),
+                PyembedPackedResourcesSource::OptionalMemoryMappedPath("$ORIGIN/stdlib".into()),
+            ]
+        );
+        assert!(embedded.extra_files.has_path(Path::new("stdlib")));
+
+        let (stdlib, other) = (
+            &embedded.pending_resources[1].0.resources,
+            &embedded.pending_resources[0].0.resources,
+        );
+        assert!(stdlib.contains_key("json"));
+        assert!(!other.contains_key("json"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frozen_modules() -> Result<()> {
+        let logger = get_logger()?;
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+
+        exe.set_frozen_modules(vec!["encodings".to_string()]);
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+
+        assert_eq!(
+            embedded.config.frozen_modules,
+            vec![PyembedFrozenModule {
+                name: "encodings".to_string(),
+                is_package: true,
+                path: PathBuf::from("frozen-encodings"),
+            }]
+        );
+        assert_eq!(embedded.pending_frozen_modules.len(), 1);
+        assert!(!embedded.pending_frozen_modules[0].1.is_empty());
+
+        exe.set_frozen_modules(vec!["does_not_exist".to_string()]);
+        assert!(exe.to_embedded_python_context(&logger, "0").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_path_module_search_paths() -> Result<()> {
+        let logger = get_logger()?;
+        let options = StandalonePythonExecutableBuilderOptions {
+            resources_location: Some(ConcreteResourceLocation::InMemory),
+            resources_location_fallback: Some(Some(ConcreteResourceLocation::RelativePath(
+                "lib".to_string(),
+            ))),
+            ..StandalonePythonExecutableBuilderOptions::default()
+        };
+        let mut exe = options.new_builder()?;
+
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+        assert_eq!(embedded.config.config.module_search_paths, None);
+
+        exe.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: FileData::Memory(vec![]),
+                is_package: false,
+                cache_tag: exe.cache_tag().to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            Some(PythonResourceAddCollectionContext {
+                include: true,
+                location: ConcreteResourceLocation::RelativePath("lib/python3.9".to_string()),
+                location_fallback: None,
+                store_source: true,
+                strip_record_hashes: false,
+                optimize_level_zero: true,
+                optimize_level_one: false,
+                optimize_level_two: false,
+            }),
+        )?;
+
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+        assert_eq!(
+            embedded.config.config.module_search_paths, None,
+            "search paths should only be registered when requested"
+        );
+
+        exe.set_relative_path_module_search_paths(true);
+        exe.config.config.module_search_paths = Some(vec![PathBuf::from("$ORIGIN/lib/python3.9")]);
+
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+        assert_eq!(
+            embedded.config.config.module_search_paths,
+            Some(vec![PathBuf::from("$ORIGIN/lib/python3.9")]),
+            "existing search path should not be duplicated"
+        );
+
+        exe.config.config.module_search_paths = None;
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+        assert_eq!(
+            embedded.config.config.module_search_paths,
+            Some(vec![PathBuf::from("$ORIGIN/lib/python3.9")])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_path_origin_references() -> Result<()> {
+        let logger = get_logger()?;
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+        exe.set_relative_path_module_search_paths(true);
+
+        for (name, prefix) in &[
+            ("foo", "$ORIGIN/../share/app"),
+            ("bar", "${APP_DATA}/lib"),
+            ("baz", "lib"),
+        ] {
+            exe.add_python_module_source(
+                &PythonModuleSource {
+                    name: name.to_string(),
+                    source: FileData::Memory(b"import sys".to_vec()),
+                    is_package: false,
+                    cache_tag: exe.cache_tag().to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+                Some(PythonResourceAddCollectionContext {
+                    include: true,
+                    location: ConcreteResourceLocation::try_from(
+                        format!("filesystem-relative:{}", prefix).as_str(),
+                    )
+                    .map_err(|e| anyhow!(e))?,
+                    location_fallback: None,
+                    store_source: true,
+                    strip_record_hashes: false,
+                    optimize_level_zero: false,
+                    optimize_level_one: false,
+                    optimize_level_two: false,
+                }),
+            )?;
+        }
+
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+
+        // Paths are recorded as given, for the importer to expand.
+        let resources = &embedded.pending_resources[0].0.resources;
+        assert_eq!(
+            resources["foo"].relative_path_module_source,
+            Some(Cow::Borrowed(Path::new("$ORIGIN/../share/app/foo.py")))
+        );
+        assert_eq!(
+            resources["bar"].relative_path_module_source,
+            Some(Cow::Borrowed(Path::new("${APP_DATA}/lib/bar.py")))
+        );
+
+        assert_eq!(
+            embedded.config.config.module_search_paths,
+            Some(vec![
+                PathBuf::from("$ORIGIN/../share/app"),
+                PathBuf::from("$ORIGIN/lib"),
+            ])
+        );
+
+        // Files are installed relative to the binary, except for files whose
+        // location depends on the environment.
+        assert!(embedded.extra_files.has_path(Path::new("lib/baz.py")));
+        assert_eq!(
+            embedded.external_files.keys().collect::<Vec<_>>(),
+            vec![Path::new("../share/app/foo.py")]
+        );
+        assert_eq!(embedded.external_files_depth(), 1);
+
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let exe_dir = temp_dir.path().join("bin");
+        embedded.materialize_files(&exe_dir)?;
+
+        assert!(exe_dir.join("lib").join("baz.py").exists());
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("share").join("app").join("foo.py"))?,
+            b"import sys"
+        );
+        assert!(!temp_dir.path().join("lib").exists());
+
+        Ok(())
+    }
+
     #[test]
     fn test_minimal_extensions_present() -> Result<()> {
         let options = StandalonePythonExecutableBuilderOptions::default();
@@ -1379,6 +2211,24 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_extension_module_variants_build_info() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let builder = options.new_builder()?;
+
+        for (name, variants) in &builder.target_distribution.extension_modules {
+            let key = format!("extension_module_variant.{}", name);
+
+            if variants.iter().count() > 1 && builder.iter_resources().any(|(x, _)| x == name) {
+                assert!(builder.config.build_info.contains_key(&key));
+            } else {
+                assert!(!builder.config.build_info.contains_key(&key));
+            }
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_linux_distribution_extensions() -> Result<()> {
         for libpython_link_mode in vec![
@@ -14,6 +14,7 @@ use {
         },
         distutils::prepare_hacked_distutils,
         standalone_builder::StandalonePythonExecutableBuilder,
+        system_python::SystemPython,
     },
     crate::environment::{LINUX_TARGET_TRIPLES, MACOS_TARGET_TRIPLES},
     anyhow::{anyhow, Context, Result},
@@ -328,6 +329,8 @@ pub fn invoke_python(python_paths: &PythonPaths, logger: &slog::Logger, args: &[
 pub enum StandaloneDistributionLinkMode {
     Static,
     Dynamic,
+    /// libpython is a shared library provided by the operating system.
+    System,
 }
 
 /// Represents a standalone Python distribution.
@@ -359,6 +362,11 @@ pub struct StandaloneDistribution {
     /// Python version string.
     pub version: String,
 
+    /// Compiler optimizations the distribution was built with.
+    ///
+    /// e.g. `noopt`, `pgo`, or `pgo+lto`.
+    pub optimizations: String,
+
     /// Path to Python interpreter executable.
     pub python_exe: PathBuf,
 
@@ -406,7 +414,8 @@ pub struct StandaloneDistribution {
 
     /// Filesystem location of pythonXY shared library for this distribution.
     ///
-    /// Only set if `link_mode` is `StandaloneDistributionLinkMode::Dynamic`.
+    /// Only set if `link_mode` is `StandaloneDistributionLinkMode::Dynamic` or
+    /// `StandaloneDistributionLinkMode::System`.
     pub libpython_shared_library: Option<PathBuf>,
 
     /// Extension modules available to this distribution.
@@ -452,6 +461,9 @@ pub struct StandaloneDistribution {
 
     /// List of strings denoting C Runtime requirements.
     pub crt_features: Vec<String>,
+
+    /// PEP 425 platform compatibility tag, if not derived from `python_platform_tag`.
+    python_platform_compatibility_tag: Option<String>,
 }
 
 impl StandaloneDistribution {
@@ -459,9 +471,25 @@ impl StandaloneDistribution {
         logger: &slog::Logger,
         location: &PythonDistributionLocation,
         distributions_dir: &Path,
+        mirrors: &[String],
     ) -> Result<Self> {
-        let (archive_path, extract_path) =
-            resolve_python_distribution_from_location(logger, location, distributions_dir)?;
+        if let PythonDistributionLocation::System {
+            python_major_minor_version,
+        } = location
+        {
+            warn!(
+                logger,
+                "resolving system Python {}", python_major_minor_version
+            );
+            return Self::from_system_python(&SystemPython::find(python_major_minor_version)?);
+        }
+
+        let (archive_path, extract_path) = resolve_python_distribution_from_location(
+            logger,
+            location,
+            distributions_dir,
+            mirrors,
+        )?;
 
         Self::from_tar_zst_file(logger, &archive_path, &extract_path)
     }
@@ -919,17 +947,89 @@ impl StandaloneDistribution {
             inittab_object,
             inittab_cflags: pi.build_info.inittab_cflags,
             cache_tag: pi.python_implementation_cache_tag,
+            optimizations: pi.optimizations,
             module_suffixes,
             crt_features: pi.crt_features,
+            python_platform_compatibility_tag: None,
+        })
+    }
+
+    /// Obtain an instance linking against the libpython of a system Python.
+    ///
+    /// The distribution has no object files, extension modules, or standard
+    /// library resources: binaries use the standard library and extension
+    /// modules installed alongside the system Python.
+    pub fn from_system_python(python: &SystemPython) -> Result<Self> {
+        let mut includes = BTreeMap::new();
+
+        for entry in walk_tree_files(&python.include_dir) {
+            let full_path = entry.path();
+            let rel_path = full_path
+                .strip_prefix(&python.include_dir)
+                .expect("unable to strip prefix");
+            includes.insert(
+                String::from(rel_path.to_str().expect("path to string")),
+                full_path.to_path_buf(),
+            );
+        }
+
+        Ok(Self {
+            base_dir: python
+                .stdlib_path
+                .parent()
+                .ok_or_else(|| anyhow!("unable to resolve parent of stdlib path"))?
+                .to_path_buf(),
+            target_triple: env!("HOST").to_string(),
+            python_implementation: python.implementation_name.clone(),
+            python_tag: python.python_tag(),
+            python_abi_tag: Some(python.python_abi_tag()),
+            python_platform_tag: python.platform.clone(),
+            version: python.version.clone(),
+            optimizations: "system".to_string(),
+            python_exe: python.python_exe.clone(),
+            stdlib_path: python.stdlib_path.clone(),
+            stdlib_test_packages: vec!["test".to_string()],
+            link_mode: StandaloneDistributionLinkMode::System,
+            python_symbol_visibility: "global-default".to_string(),
+            extension_module_loading: vec!["shared-library".to_string()],
+            core_license: None,
+            licenses: None,
+            license_path: None,
+            tcl_library_path: None,
+            tcl_library_paths: None,
+            objs_core: BTreeMap::new(),
+            links_core: vec![],
+            libpython_shared_library: Some(python.libpython_path()),
+            extension_modules: BTreeMap::new(),
+            frozen_c: vec![],
+            includes,
+            libraries: BTreeMap::new(),
+            py_modules: BTreeMap::new(),
+            resources: BTreeMap::new(),
+            venv_base: PathBuf::new(),
+            inittab_object: PathBuf::new(),
+            inittab_cflags: vec![],
+            cache_tag: python.cache_tag.clone(),
+            module_suffixes: python.module_suffixes.clone(),
+            crt_features: vec![],
+            python_platform_compatibility_tag: Some(python.python_platform_compatibility_tag()),
         })
     }
 
+    /// Whether this distribution links against a system Python.
+    pub fn is_system_python(&self) -> bool {
+        self.link_mode == StandaloneDistributionLinkMode::System
+    }
+
     /// Determines support for building a libpython from this distribution.
     ///
     /// Returns a tuple of bools indicating whether this distribution can
     /// build a static libpython and a dynamically linked libpython.
     pub fn libpython_link_support(&self) -> (bool, bool) {
-        if self.target_triple.contains("pc-windows") {
+        if self.is_system_python() {
+            // There are no object files to link a libpython from.
+            (false, true)
+        } else if self.target_triple.contains("pc-windows") {
             // On Windows, support for libpython linkage is determined
             // by presence of a shared library in the distribution. This
             // isn't entirely semantically correct. Since we use `dllexport`
@@ -1039,6 +1139,11 @@ impl StandaloneDistribution {
         Ok((python_paths, extra_envs))
     }
 
+    /// Whether the distribution was built with profile-guided optimization.
+    pub fn is_pgo_optimized(&self) -> bool {
+        self.optimizations.split('+').any(|o| o == "pgo")
+    }
+
     /// Whether the distribution is capable of loading filed-based Python extension modules.
     pub fn is_extension_module_file_loadable(&self) -> bool {
         self.extension_module_loading
@@ -1063,6 +1168,9 @@ impl PythonDistribution for StandaloneDistribution {
                 "x86_64-unknown-linux-gnu" => vec![],
                 // musl libc linked distributions run on GNU Linux.
                 "x86_64-unknown-linux-musl" => vec!["x86_64-unknown-linux-gnu"],
+                "aarch64-unknown-linux-gnu" => vec![],
+                "aarch64-unknown-linux-musl" => vec!["aarch64-unknown-linux-gnu"],
+                "armv7-unknown-linux-gnueabihf" => vec![],
                 "aarch64-apple-darwin" => vec![],
                 "x86_64-apple-darwin" => vec![],
                 // 32-bit Windows GNU on 32-bit Windows MSVC and 64-bit Windows.
@@ -1072,14 +1180,20 @@ impl PythonDistribution for StandaloneDistribution {
                     "x86_64-pc-windows-msvc",
                 ],
                 // 32-bit Windows MSVC runs on 32-bit Windows MSVC and 64-bit Windows.
+                // ARM64 Windows emulates x86.
                 "i686-pc-windows-msvc" => vec![
+                    "aarch64-pc-windows-msvc",
                     "i686-pc-windows-gnu",
                     "x86_64-pc-windows-gnu",
                     "x86_64-pc-windows-msvc",
                 ],
                 // 64-bit Windows GNU/MSVC runs on the other.
                 "x86_64-pc-windows-gnu" => vec!["x86_64-pc-windows-msvc"],
-                "x86_64-pc-windows-msvc" => vec!["x86_64-pc-windows-gnu"],
+                // ARM64 Windows 11 emulates x64.
+                "x86_64-pc-windows-msvc" => {
+                    vec!["aarch64-pc-windows-msvc", "x86_64-pc-windows-gnu"]
+                }
+                "aarch64-pc-windows-msvc" => vec![],
                 _ => vec![],
             }
             .iter()
@@ -1139,6 +1253,10 @@ impl PythonDistribution for StandaloneDistribution {
     }
 
     fn python_platform_compatibility_tag(&self) -> &str {
+        if let Some(tag) = &self.python_platform_compatibility_tag {
+            return tag;
+        }
+
         // TODO capture this in distribution metadata.
         if !self.is_extension_module_file_loadable() {
             return "none";
@@ -1147,10 +1265,13 @@ impl PythonDistribution for StandaloneDistribution {
         match self.python_platform_tag.as_str() {
             "linux-x86_64" => "manylinux2014_x86_64",
             "linux-i686" => "manylinux2014_i686",
+            "linux-aarch64" => "manylinux2014_aarch64",
+            "linux-armv7l" => "manylinux2014_armv7l",
             "macosx-10.9-x86_64" => "macosx_10_9_x86_64",
             "macosx-11.0-arm64" => "macosx_11_0_arm64",
             "win-amd64" => "win_amd64",
             "win32" => "win32",
+            "win-arm64" => "win_arm64",
             p => panic!("unsupported Python platform: {}", p),
         }
     }
@@ -1168,7 +1289,7 @@ impl PythonDistribution for StandaloneDistribution {
     }
 
     fn create_bytecode_compiler(&self) -> Result<Box<dyn PythonBytecodeCompiler>> {
-        let temp_dir = tempfile::TempDir::new()?;
+        let temp_dir = crate::environment::temp_dir(None, "pyoxidizer-bytecode-compiler")?;
         Ok(Box::new(BytecodeCompiler::new(
             &self.python_exe,
             temp_dir.path(),
@@ -1213,7 +1334,8 @@ impl PythonDistribution for StandaloneDistribution {
             allocator_backend: default_memory_allocator(self.target_triple()),
             allocator_raw: true,
             oxidized_importer: true,
-            filesystem_importer: false,
+            // The standard library of a system Python is only on the filesystem.
+            filesystem_importer: self.is_system_python(),
             terminfo_resolution: TerminfoResolution::Dynamic,
             ..embedded_default
         })
@@ -1291,6 +1413,40 @@ impl PythonDistribution for StandaloneDistribution {
 
     /// Ensure pip is available to run in the distribution.
     fn ensure_pip(&self, logger: &slog::Logger) -> Result<PathBuf> {
+        if self.is_system_python() {
+            // We shouldn't modify the system Python. So require pip to be installed.
+            let status = std::process::Command::new(&self.python_exe)
+                .args(&["-m", "pip", "--version"])
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()?;
+
+            if !status.success() {
+                return Err(anyhow!(
+                    "pip is not installed for {}; install it with your system's package manager (e.g. python3-pip)",
+                    self.python_exe.display()
+                ));
+            }
+
+            // Only return a pip script that exists and is tied to this Python's
+            // version, as an unversioned pip may belong to another interpreter.
+            let major_minor = self.python_major_minor_version();
+            let major = major_minor.split('.').next().unwrap_or_default();
+
+            return [format!("pip{}", major_minor), format!("pip{}", major)]
+                .iter()
+                .map(|name| self.python_exe.with_file_name(name))
+                .find(|path| path.exists())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "pip is installed for {} but no pip{} or pip{} executable exists next to it",
+                        self.python_exe.display(),
+                        major_minor,
+                        major
+                    )
+                });
+        }
+
         let dist_prefix = self.base_dir.join("python").join("install");
         let python_paths = resolve_python_paths(&dist_prefix, &self.version);
 
@@ -1299,6 +1455,13 @@ impl PythonDistribution for StandaloneDistribution {
         if !pip_path.exists() {
             warn!(logger, "{} doesnt exist", pip_path.display().to_string());
             invoke_python(&python_paths, &logger, &["-m", "ensurepip"]);
+
+            if !pip_path.exists() {
+                return Err(anyhow!(
+                    "{} doesn't exist after running ensurepip",
+                    pip_path.display()
+                ));
+            }
         }
 
         Ok(pip_path)
@@ -1376,7 +1539,8 @@ pub mod tests {
         super::*,
         crate::testutil::*,
         python_packaging::{
-            bytecode::CompileMode, policy::ExtensionModuleFilter,
+            bytecode::{CodeImports, CompileMode, ImportsInput},
+            policy::ExtensionModuleFilter,
             resource::BytecodeOptimizationLevel,
         },
         std::collections::BTreeSet,
@@ -1521,4 +1685,68 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn find_imports() -> Result<()> {
+        let dist = get_default_distribution()?;
+
+        let temp_dir = tempfile::TempDir::new()?;
+
+        let mut compiler = BytecodeCompiler::new(dist.python_exe_path(), temp_dir.path())?;
+
+        let source = b"import os, sys as system\n\
+            from json import loads, dumps as d\n\
+            from . import sibling\n\
+            from .. import top\n\
+            from .child import *\n\
+            x = 'import quoted'\n\
+            def f():\n    import importlib\n    return importlib.import_module('plugin')\n";
+
+        assert_eq!(
+            compiler.find_imports("pkg.sub.mod", false, ImportsInput::Source(source))?,
+            Some(CodeImports {
+                imports: vec![
+                    "importlib",
+                    "json",
+                    "json.dumps",
+                    "json.loads",
+                    "os",
+                    "pkg",
+                    "pkg.sub",
+                    "pkg.sub.child",
+                    "pkg.sub.sibling",
+                    "pkg.top",
+                    "plugin",
+                    "sys",
+                ]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+                dynamic: false,
+            })
+        );
+
+        let bytecode = compiler.compile(
+            b"from . import a\ndef f(name):\n    return __import__(name)\n",
+            "pkg/__init__.py",
+            BytecodeOptimizationLevel::Zero,
+            CompileMode::Bytecode,
+        )?;
+        assert_eq!(
+            compiler.find_imports("pkg", true, ImportsInput::Bytecode(&bytecode))?,
+            Some(CodeImports {
+                imports: vec!["pkg".to_string(), "pkg.a".to_string()]
+                    .into_iter()
+                    .collect(),
+                dynamic: true,
+            })
+        );
+
+        assert_eq!(
+            compiler.find_imports("mod", false, ImportsInput::Source(b"invalid syntax"))?,
+            None
+        );
+
+        Ok(())
+    }
 }
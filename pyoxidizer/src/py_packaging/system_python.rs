@@ -0,0 +1,264 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Discovering Python installations provided by the operating system.
+
+Executables can link against the `libpython` shared library of a system
+Python instead of embedding a Python distribution. The interpreter is found
+on `PATH` as `pythonX.Y` and introspected to derive the metadata normally
+found in a distribution's `PYTHON.json`. The development files (the shared
+library and headers) are located with `pkg-config`, via the
+`python-X.Y-embed` package installed by e.g. Debian's `pythonX.Y-dev`.
+*/
+
+use {
+    crate::environment::find_executable,
+    anyhow::{anyhow, Context, Result},
+    python_packaging::module_util::PythonModuleSuffixes,
+    serde::Deserialize,
+    std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Python code printing information about the running interpreter as JSON.
+const INTROSPECT_SOURCE: &str = "import importlib.machinery, json, sys, sysconfig
+m = importlib.machinery
+print(json.dumps({
+    'version': '%d.%d.%d' % sys.version_info[:3],
+    'implementation_name': sys.implementation.name,
+    'cache_tag': sys.implementation.cache_tag,
+    'abiflags': getattr(sys, 'abiflags', ''),
+    'platform': sysconfig.get_platform(),
+    'stdlib': sysconfig.get_paths()['stdlib'],
+    'enable_shared': bool(sysconfig.get_config_var('Py_ENABLE_SHARED')),
+    'suffixes': {
+        'source': m.SOURCE_SUFFIXES,
+        'bytecode': m.BYTECODE_SUFFIXES,
+        'debug_bytecode': m.DEBUG_BYTECODE_SUFFIXES,
+        'optimized_bytecode': m.OPTIMIZED_BYTECODE_SUFFIXES,
+        'extension': m.EXTENSION_SUFFIXES,
+    },
+}))
+";
+
+#[derive(Debug, Deserialize)]
+struct Introspection {
+    version: String,
+    implementation_name: String,
+    cache_tag: String,
+    abiflags: String,
+    platform: String,
+    stdlib: PathBuf,
+    enable_shared: bool,
+    suffixes: HashMap<String, Vec<String>>,
+}
+
+/// Run `pkg-config` to obtain the value of a variable of a package.
+fn pkg_config_variable(package: &str, variable: &str) -> Result<String> {
+    let output = std::process::Command::new("pkg-config")
+        .arg(format!("--variable={}", variable))
+        .arg(package)
+        .output()
+        .context("running pkg-config")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "pkg-config could not find {}: {}",
+            package,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// A Python installation provided by the operating system.
+#[derive(Clone, Debug)]
+pub struct SystemPython {
+    /// Path to the `python` executable.
+    pub python_exe: PathBuf,
+
+    /// Full Python version string. e.g. `3.9.2`.
+    pub version: String,
+
+    /// X.Y Python version. e.g. `3.9`.
+    pub python_major_minor_version: String,
+
+    /// Python implementation name. e.g. `cpython`.
+    pub implementation_name: String,
+
+    /// Tag applied to bytecode files. e.g. `cpython-39`.
+    pub cache_tag: String,
+
+    /// ABI flags of the interpreter. e.g. `d` for debug builds.
+    pub abiflags: String,
+
+    /// Python platform tag. e.g. `linux-x86_64`.
+    pub platform: String,
+
+    /// Directory holding the standard library.
+    pub stdlib_path: PathBuf,
+
+    /// Directory holding the C headers.
+    pub include_dir: PathBuf,
+
+    /// Directory holding the libpython shared library.
+    pub lib_dir: PathBuf,
+
+    /// Filename of the libpython shared library. e.g. `libpython3.9.so`.
+    pub libpython_filename: String,
+
+    /// Suffixes for Python module types.
+    pub module_suffixes: PythonModuleSuffixes,
+}
+
+impl SystemPython {
+    /// Find the system Python of a given `X.Y` version.
+    pub fn find(python_major_minor_version: &str) -> Result<Self> {
+        if cfg!(windows) {
+            return Err(anyhow!(
+                "linking against a system Python is not supported on Windows"
+            ));
+        }
+
+        let exe_name = format!("python{}", python_major_minor_version);
+        let python_exe = find_executable(&exe_name)
+            .ok_or_else(|| anyhow!("unable to find {} on PATH", exe_name))?;
+
+        Self::from_python_exe(&python_exe, python_major_minor_version)
+    }
+
+    /// Introspect the system Python of a given `X.Y` version at an executable path.
+    pub fn from_python_exe(python_exe: &Path, python_major_minor_version: &str) -> Result<Self> {
+        let output = std::process::Command::new(python_exe)
+            .args(&["-I", "-c", INTROSPECT_SOURCE])
+            .output()
+            .with_context(|| format!("running {}", python_exe.display()))?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "unable to introspect {}: {}",
+                python_exe.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let info: Introspection = serde_json::from_slice(&output.stdout)
+            .with_context(|| format!("parsing introspection of {}", python_exe.display()))?;
+
+        if !info
+            .version
+            .starts_with(&format!("{}.", python_major_minor_version))
+        {
+            return Err(anyhow!(
+                "{} is Python {}; expected {}",
+                python_exe.display(),
+                info.version,
+                python_major_minor_version
+            ));
+        }
+
+        if !info.enable_shared {
+            return Err(anyhow!(
+                "{} was not built with a shared libpython and cannot be linked against",
+                python_exe.display()
+            ));
+        }
+
+        // The -embed package links libpython. The regular package doesn't
+        // since Python 3.8, as extension modules shouldn't link it.
+        let package = format!("python-{}-embed", python_major_minor_version);
+        let lib_dir = PathBuf::from(pkg_config_variable(&package, "libdir").with_context(|| {
+            format!(
+                "resolving Python {} development files; install them with your system's package manager (e.g. python{}-dev)",
+                python_major_minor_version, python_major_minor_version
+            )
+        })?);
+        let include_dir = PathBuf::from(pkg_config_variable(&package, "includedir")?).join(
+            format!("python{}{}", python_major_minor_version, info.abiflags),
+        );
+
+        let libpython_filename = format!(
+            "libpython{}{}.{}",
+            python_major_minor_version,
+            info.abiflags,
+            if cfg!(target_os = "macos") {
+                "dylib"
+            } else {
+                "so"
+            }
+        );
+
+        if !lib_dir.join(&libpython_filename).exists() {
+            return Err(anyhow!(
+                "{} does not exist; are the Python {} development files installed?",
+                lib_dir.join(&libpython_filename).display(),
+                python_major_minor_version
+            ));
+        }
+
+        let suffixes = |key: &str| -> Result<Vec<String>> {
+            info.suffixes
+                .get(key)
+                .cloned()
+                .ok_or_else(|| anyhow!("{} suffixes not defined", key))
+        };
+
+        let module_suffixes = PythonModuleSuffixes {
+            source: suffixes("source")?,
+            bytecode: suffixes("bytecode")?,
+            debug_bytecode: suffixes("debug_bytecode")?,
+            optimized_bytecode: suffixes("optimized_bytecode")?,
+            extension: suffixes("extension")?,
+        };
+
+        Ok(Self {
+            python_exe: python_exe.to_path_buf(),
+            version: info.version,
+            python_major_minor_version: python_major_minor_version.to_string(),
+            implementation_name: info.implementation_name,
+            cache_tag: info.cache_tag,
+            abiflags: info.abiflags,
+            platform: info.platform,
+            stdlib_path: info.stdlib,
+            include_dir,
+            lib_dir,
+            libpython_filename,
+            module_suffixes,
+        })
+    }
+
+    /// PEP 425 Python tag. e.g. `cp39`.
+    pub fn python_tag(&self) -> String {
+        format!("cp{}", self.python_major_minor_version.replace('.', ""))
+    }
+
+    /// PEP 425 ABI tag. e.g. `cp39`.
+    pub fn python_abi_tag(&self) -> String {
+        format!("{}{}", self.python_tag(), self.abiflags)
+    }
+
+    /// PEP 425 platform tag. e.g. `linux_x86_64`.
+    pub fn python_platform_compatibility_tag(&self) -> String {
+        self.platform.replace('-', "_").replace('.', "_")
+    }
+
+    /// Path to the libpython shared library.
+    pub fn libpython_path(&self) -> PathBuf {
+        self.lib_dir.join(&self.libpython_filename)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_missing() {
+        assert!(SystemPython::find("2.1").is_err());
+    }
+}
@@ -0,0 +1,395 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Producing Python zip applications.
+
+A zip application (see PEP 441) is a zip archive containing Python modules
+and a `__main__.py` which is run when the archive is executed by a Python
+interpreter. It can be prefixed with a shebang line so it can be executed
+directly.
+
+Zip applications are run by a Python interpreter installed on the machine.
+So they can't contain extension modules or shared libraries and they don't
+contain the Python standard library.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    python_packaging::{
+        interpreter::PythonInterpreterConfig,
+        package_metadata::PythonPackageMetadata,
+        resource_collection::{PrePackagedResource, PythonModuleBytecodeProvider},
+    },
+    slog::warn,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        path::{Component, Path, PathBuf},
+    },
+    tugger_file_manifest::FileData,
+};
+
+/// Default interpreter used to run zip applications.
+pub const DEFAULT_INTERPRETER: &str = "/usr/bin/env python3";
+
+/// Derive the source code of `__main__.py` from an interpreter config.
+pub fn main_source(config: &PythonInterpreterConfig) -> Result<String> {
+    if let Some(module) = &config.run_module {
+        Ok(format!(
+            "import runpy\nrunpy.run_module({}, run_name=\"__main__\", alter_sys=True)\n",
+            serde_json::to_string(module)?
+        ))
+    } else if let Some(command) = &config.run_command {
+        Ok(format!("{}\n", command))
+    } else if config.run_filename.is_some() {
+        Err(anyhow!(
+            "zip applications can't run a filename; define run_module or run_command"
+        ))
+    } else {
+        Err(anyhow!(
+            "zip applications need something to run; define run_module or run_command"
+        ))
+    }
+}
+
+/// Resolve the path of a module's source file in a zip application.
+fn module_path(name: &str, is_package: bool) -> String {
+    let path = name.replace('.', "/");
+
+    if is_package {
+        format!("{}/__init__.py", path)
+    } else {
+        format!("{}.py", path)
+    }
+}
+
+/// Resolve the path of a package resource in a zip application.
+fn package_resource_path(package: &str, name: &str) -> String {
+    format!("{}/{}", package.replace('.', "/"), name)
+}
+
+/// Strip the install prefix from the path of a distribution resource.
+fn strip_distribution_prefix(path: &Path) -> Option<String> {
+    let components = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(c) => Some(c.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let index = components
+        .iter()
+        .position(|c| c.ends_with(".dist-info") || c.ends_with(".egg-info"))?;
+
+    Some(components[index..].join("/"))
+}
+
+/// Resolve the `.dist-info` directory name of in-memory distribution resources.
+fn dist_info_dir(package: &str, resources: &BTreeMap<String, FileData>) -> Result<String> {
+    let version = if let Some(data) = resources.get("METADATA") {
+        PythonPackageMetadata::from_metadata(&data.resolve()?)?
+            .version()
+            .map(|v| v.to_string())
+    } else {
+        None
+    };
+
+    Ok(format!(
+        "{}-{}.dist-info",
+        package,
+        version.unwrap_or_else(|| "0.0.0".to_string())
+    ))
+}
+
+/// Resolve the files of a zip application from collected resources.
+///
+/// Resources whose top-level module is in `exclude` (typically the Python
+/// standard library) are ignored. Extension modules and shared libraries
+/// can't be loaded from zip applications and are ignored with a warning.
+///
+/// Returns a mapping of archive member name to content. `__main__.py` is not
+/// added.
+pub fn zipapp_files<'a>(
+    logger: &slog::Logger,
+    resources: impl Iterator<Item = (&'a String, &'a PrePackagedResource)>,
+    exclude: &BTreeSet<String>,
+) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+
+    for (name, resource) in resources {
+        let top_level = name.split('.').next().unwrap_or(name);
+        if exclude.contains(top_level) {
+            continue;
+        }
+
+        if resource.is_builtin_extension_module || resource.is_frozen_module {
+            continue;
+        }
+
+        if resource.is_extension_module || resource.is_shared_library {
+            warn!(
+                logger,
+                "{} can't be loaded from a zip application; ignoring", name
+            );
+            continue;
+        }
+
+        if resource.file_data_embedded.is_some() || resource.file_data_utf8_relative_path.is_some()
+        {
+            warn!(
+                logger,
+                "file {} is not supported by zip applications; ignoring", name
+            );
+            continue;
+        }
+
+        // Bytecode derived from source (e.g. when the packaging policy
+        // excludes source) still gives us the source.
+        let bytecode = [
+            resource.in_memory_bytecode.as_ref(),
+            resource.in_memory_bytecode_opt1.as_ref(),
+            resource.in_memory_bytecode_opt2.as_ref(),
+            resource.relative_path_bytecode.as_ref().map(|(_, _, b)| b),
+            resource
+                .relative_path_bytecode_opt1
+                .as_ref()
+                .map(|(_, _, b)| b),
+            resource
+                .relative_path_bytecode_opt2
+                .as_ref()
+                .map(|(_, _, b)| b),
+        ];
+
+        let source = if let Some(source) = &resource.in_memory_source {
+            Some(source)
+        } else if let Some((_, source)) = &resource.relative_path_module_source {
+            Some(source)
+        } else {
+            bytecode.iter().flatten().find_map(|b| match b {
+                PythonModuleBytecodeProvider::FromSource(source) => Some(source),
+                PythonModuleBytecodeProvider::Provided(_) => None,
+            })
+        };
+
+        if let Some(source) = source {
+            files.insert(
+                module_path(name, resource.is_package),
+                source
+                    .resolve()
+                    .with_context(|| format!("resolving source of {}", name))?,
+            );
+        } else if bytecode.iter().any(|b| b.is_some()) {
+            return Err(anyhow!(
+                "module {} has no source code; zip applications require module source",
+                name
+            ));
+        }
+
+        if let Some(resources) = &resource.in_memory_resources {
+            for (key, data) in resources {
+                files.insert(package_resource_path(name, key), data.resolve()?);
+            }
+        }
+
+        if let Some(resources) = &resource.relative_path_package_resources {
+            for (key, (_, data)) in resources {
+                files.insert(package_resource_path(name, key), data.resolve()?);
+            }
+        }
+
+        if let Some(resources) = &resource.in_memory_distribution_resources {
+            let dir = dist_info_dir(name, resources)?;
+
+            for (key, data) in resources {
+                files.insert(format!("{}/{}", dir, key), data.resolve()?);
+            }
+        }
+
+        if let Some(resources) = &resource.relative_path_distribution_resources {
+            for (path, data) in resources.values() {
+                let member = strip_distribution_prefix(path).ok_or_else(|| {
+                    anyhow!(
+                        "unable to resolve distribution resource path {}",
+                        path.display()
+                    )
+                })?;
+
+                files.insert(member, data.resolve()?);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Write a zip application to a file.
+///
+/// If `interpreter` is defined, a shebang line running it is prepended and
+/// the file is made executable.
+pub fn write_zipapp(
+    path: &Path,
+    files: BTreeMap<String, Vec<u8>>,
+    interpreter: Option<&str>,
+    compress: bool,
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let prefix = if let Some(interpreter) = interpreter {
+        format!("#!{}\n", interpreter).into_bytes()
+    } else {
+        vec![]
+    };
+
+    let mut fh =
+        std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?;
+
+    tugger_common::zipfile::write_zip(
+        &mut fh,
+        &prefix,
+        files.into_iter().map(|(name, data)| (name, data, false)),
+        compress,
+    )
+    .with_context(|| format!("writing {}", path.display()))?;
+
+    if interpreter.is_some() {
+        tugger_file_manifest::set_executable(&mut fh)
+            .context("making zip application executable")?;
+    }
+
+    Ok(())
+}
+
+/// Resolve the filename of a zip application for an application name.
+pub fn zipapp_filename(name: &str) -> PathBuf {
+    PathBuf::from(format!("{}.pyz", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::*};
+
+    #[test]
+    fn test_main_source() -> Result<()> {
+        let mut config = PythonInterpreterConfig::default();
+        assert!(main_source(&config).is_err());
+
+        config.run_module = Some("myapp".to_string());
+        assert_eq!(
+            main_source(&config)?,
+            "import runpy\nrunpy.run_module(\"myapp\", run_name=\"__main__\", alter_sys=True)\n"
+        );
+
+        config.run_module = None;
+        config.run_command = Some("print('hello')".to_string());
+        assert_eq!(main_source(&config)?, "print('hello')\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zipapp_files() -> Result<()> {
+        let logger = get_logger()?;
+
+        let mut resources = BTreeMap::new();
+        resources.insert(
+            "myapp".to_string(),
+            PrePackagedResource {
+                name: "myapp".to_string(),
+                is_package: true,
+                is_module: true,
+                in_memory_source: Some(FileData::Memory(b"# init".to_vec())),
+                in_memory_resources: Some(
+                    vec![(
+                        "data/config.json".to_string(),
+                        FileData::Memory(b"{}".to_vec()),
+                    )]
+                    .into_iter()
+                    .collect(),
+                ),
+                in_memory_distribution_resources: Some(
+                    vec![(
+                        "METADATA".to_string(),
+                        FileData::Memory(b"Name: myapp\nVersion: 1.0\n".to_vec()),
+                    )]
+                    .into_iter()
+                    .collect(),
+                ),
+                ..PrePackagedResource::default()
+            },
+        );
+        resources.insert(
+            "myapp.cli".to_string(),
+            PrePackagedResource {
+                name: "myapp.cli".to_string(),
+                is_module: true,
+                relative_path_module_source: Some((
+                    "lib".to_string(),
+                    FileData::Memory(b"# cli".to_vec()),
+                )),
+                ..PrePackagedResource::default()
+            },
+        );
+        resources.insert(
+            "json".to_string(),
+            PrePackagedResource {
+                name: "json".to_string(),
+                is_package: true,
+                is_module: true,
+                in_memory_source: Some(FileData::Memory(vec![])),
+                ..PrePackagedResource::default()
+            },
+        );
+        resources.insert(
+            "myext".to_string(),
+            PrePackagedResource {
+                name: "myext".to_string(),
+                is_module: true,
+                is_extension_module: true,
+                ..PrePackagedResource::default()
+            },
+        );
+
+        let exclude = vec!["json".to_string()].into_iter().collect();
+        let files = zipapp_files(&logger, resources.iter(), &exclude)?;
+
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            vec![
+                "myapp-1.0.dist-info/METADATA",
+                "myapp/__init__.py",
+                "myapp/cli.py",
+                "myapp/data/config.json",
+            ]
+        );
+
+        resources.insert(
+            "bytecode".to_string(),
+            PrePackagedResource {
+                name: "bytecode".to_string(),
+                is_module: true,
+                in_memory_bytecode: Some(
+                    python_packaging::resource_collection::PythonModuleBytecodeProvider::Provided(
+                        FileData::Memory(vec![]),
+                    ),
+                ),
+                ..PrePackagedResource::default()
+            },
+        );
+        assert!(zipapp_files(&logger, resources.iter(), &exclude).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_distribution_prefix() {
+        assert_eq!(
+            strip_distribution_prefix(Path::new("lib/foo-1.0.dist-info/METADATA")),
+            Some("foo-1.0.dist-info/METADATA".to_string())
+        );
+        assert_eq!(strip_distribution_prefix(Path::new("lib/foo/bar")), None);
+    }
+}
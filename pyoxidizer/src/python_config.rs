@@ -0,0 +1,383 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Configuration files written in Python.
+
+A Python configuration file is a script run by the host Python
+distribution. The script imports a `pyoxidizer` module (defined by
+`python_config/pyoxidizer.py`) to define build targets. The recorded
+targets are serialized to JSON and converted to an equivalent Starlark
+configuration, which is evaluated like any other configuration file.
+*/
+
+use {
+    crate::environment::temp_dir,
+    anyhow::{anyhow, Context, Result},
+    serde::Deserialize,
+    slog::warn,
+    std::{collections::BTreeMap, io::Write, path::Path},
+};
+
+/// Source code of the `pyoxidizer` module available to Python configuration files.
+const PYOXIDIZER_MODULE: &str = include_str!("python_config/pyoxidizer.py");
+
+/// Whether a configuration file is a Python script.
+pub fn is_python_config(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some("py")
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum Action {
+    PipInstall {
+        args: Vec<String>,
+        extra_envs: BTreeMap<String, String>,
+    },
+    ReadPackageRoot {
+        path: String,
+        packages: Vec<String>,
+    },
+    ReadVirtualenv {
+        path: String,
+    },
+    SetupPyInstall {
+        package_path: String,
+        extra_envs: BTreeMap<String, String>,
+        extra_global_arguments: Vec<String>,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TargetValue {
+    Executable {
+        name: String,
+        run_module: Option<String>,
+        run_command: Option<String>,
+        run_filename: Option<String>,
+        python_version: Option<String>,
+        flavor: String,
+        actions: Vec<Action>,
+    },
+    Install {
+        executables: Vec<String>,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct Target {
+    name: String,
+    default: bool,
+    value: TargetValue,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct PythonConfig {
+    targets: Vec<Target>,
+}
+
+/// Format a string as a Starlark string literal.
+fn starlark_str(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c => res.push(c),
+        }
+    }
+
+    res.push('"');
+    res
+}
+
+fn starlark_list(values: &[String]) -> String {
+    format!(
+        "[{}]",
+        values
+            .iter()
+            .map(|v| starlark_str(v))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+fn starlark_dict(values: &BTreeMap<String, String>) -> String {
+    format!(
+        "{{{}}}",
+        values
+            .iter()
+            .map(|(k, v)| format!("{}: {}", starlark_str(k), starlark_str(v)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+impl PythonConfig {
+    /// Convert to the source code of an equivalent Starlark configuration.
+    fn to_starlark(&self) -> Result<String> {
+        let mut lines = vec![
+            "# Generated by PyOxidizer from a Python configuration file.".to_string(),
+            "".to_string(),
+        ];
+
+        for (i, target) in self.targets.iter().enumerate() {
+            let mut depends = vec![];
+
+            match &target.value {
+                TargetValue::Executable {
+                    name,
+                    run_module,
+                    run_command,
+                    run_filename,
+                    python_version,
+                    flavor,
+                    actions,
+                } => {
+                    lines.push(format!("def make_target_{}():", i));
+                    lines.push(format!(
+                        "    dist = default_python_distribution(flavor={}, python_version={})",
+                        starlark_str(flavor),
+                        match python_version {
+                            Some(v) => starlark_str(v),
+                            None => "None".to_string(),
+                        }
+                    ));
+                    lines.push("    policy = dist.make_python_packaging_policy()".to_string());
+                    lines.push(
+                        "    python_config = dist.make_python_interpreter_config()".to_string(),
+                    );
+
+                    for (attr, value) in &[
+                        ("run_module", run_module),
+                        ("run_command", run_command),
+                        ("run_filename", run_filename),
+                    ] {
+                        if let Some(value) = value {
+                            lines.push(format!(
+                                "    python_config.{} = {}",
+                                attr,
+                                starlark_str(value)
+                            ));
+                        }
+                    }
+
+                    lines.push(format!(
+                        "    exe = dist.to_python_executable(name={}, packaging_policy=policy, config=python_config)",
+                        starlark_str(name)
+                    ));
+
+                    for action in actions {
+                        let call = match action {
+                            Action::PipInstall { args, extra_envs } => format!(
+                                "exe.pip_install({}, {})",
+                                starlark_list(args),
+                                starlark_dict(extra_envs)
+                            ),
+                            Action::ReadPackageRoot { path, packages } => format!(
+                                "exe.read_package_root({}, {})",
+                                starlark_str(path),
+                                starlark_list(packages)
+                            ),
+                            Action::ReadVirtualenv { path } => {
+                                format!("exe.read_virtualenv({})", starlark_str(path))
+                            }
+                            Action::SetupPyInstall {
+                                package_path,
+                                extra_envs,
+                                extra_global_arguments,
+                            } => format!(
+                                "exe.setup_py_install({}, {}, {})",
+                                starlark_str(package_path),
+                                starlark_dict(extra_envs),
+                                starlark_list(extra_global_arguments)
+                            ),
+                        };
+
+                        lines.push(format!("    exe.add_python_resources({})", call));
+                    }
+
+                    lines.push("    return exe".to_string());
+                }
+                TargetValue::Install { executables } => {
+                    let args = (0..executables.len())
+                        .map(|i| format!("exe{}", i))
+                        .collect::<Vec<_>>();
+
+                    lines.push(format!("def make_target_{}({}):", i, args.join(", ")));
+                    lines.push("    files = FileManifest()".to_string());
+                    for arg in &args {
+                        lines.push(format!("    files.add_python_resource(\".\", {})", arg));
+                    }
+                    lines.push("    return files".to_string());
+
+                    for exe in executables {
+                        if !self.targets.iter().any(|t| &t.name == exe) {
+                            return Err(anyhow!(
+                                "target {} installs unknown target {}",
+                                target.name,
+                                exe
+                            ));
+                        }
+                    }
+
+                    depends = executables.clone();
+                }
+            }
+
+            lines.push("".to_string());
+            lines.push(format!(
+                "register_target({}, make_target_{}, depends={}, default={})",
+                starlark_str(&target.name),
+                i,
+                starlark_list(&depends),
+                if target.default { "True" } else { "False" }
+            ));
+            lines.push("".to_string());
+        }
+
+        lines.push("resolve_targets()".to_string());
+        lines.push("".to_string());
+
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Run a Python configuration file and convert it to Starlark source code.
+///
+/// `python_exe` is the Python interpreter used to run the script. Scratch
+/// files are written to a temporary directory under `cache_path`, so
+/// concurrent evaluations don't interfere. The generated Starlark is
+/// written to `python_config/python-config.bzl` in `cache_path`.
+pub fn python_config_to_starlark(
+    logger: &slog::Logger,
+    python_exe: &Path,
+    config_path: &Path,
+    cache_path: &Path,
+) -> Result<String> {
+    let temp_dir = temp_dir(Some(cache_path), "pyoxidizer-python-config")?;
+    let scratch_dir = temp_dir.path();
+
+    let module_dir = scratch_dir.join("python-config-api");
+    std::fs::create_dir_all(&module_dir)?;
+    std::fs::write(module_dir.join("pyoxidizer.py"), PYOXIDIZER_MODULE)?;
+
+    let targets_path = scratch_dir.join("python-config-targets.json");
+
+    warn!(
+        logger,
+        "running Python configuration file {}",
+        config_path.display()
+    );
+
+    let status = std::process::Command::new(python_exe)
+        .arg("-B")
+        .arg("-c")
+        .arg("import sys; sys.path.insert(0, sys.argv[1]); import pyoxidizer; pyoxidizer._run(sys.argv[2], sys.argv[3])")
+        .arg(&module_dir)
+        .arg(config_path)
+        .arg(&targets_path)
+        .current_dir(config_path.parent().unwrap_or_else(|| Path::new(".")))
+        .status()
+        .with_context(|| format!("running {}", python_exe.display()))?;
+
+    if !status.success() {
+        return Err(anyhow!(
+            "error running Python configuration file {}",
+            config_path.display()
+        ));
+    }
+
+    let data = std::fs::read(&targets_path)
+        .with_context(|| format!("reading {}", targets_path.display()))?;
+    let config: PythonConfig =
+        serde_json::from_slice(&data).context("parsing targets of Python configuration file")?;
+
+    let code = config.to_starlark()?;
+
+    // Replace the file atomically, as other evaluations may write it.
+    let starlark_dir = cache_path.join("python_config");
+    std::fs::create_dir_all(&starlark_dir)
+        .with_context(|| format!("creating {}", starlark_dir.display()))?;
+    let starlark_path = starlark_dir.join("python-config.bzl");
+    let mut starlark_file = tempfile::NamedTempFile::new_in(&starlark_dir)?;
+    starlark_file.write_all(code.as_bytes())?;
+    starlark_file
+        .persist(&starlark_path)
+        .with_context(|| format!("writing {}", starlark_path.display()))?;
+    warn!(
+        logger,
+        "evaluating Starlark generated from Python configuration file: {}",
+        starlark_path.display()
+    );
+
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starlark_str() {
+        assert_eq!(starlark_str("foo"), "\"foo\"");
+        assert_eq!(starlark_str("a \"b\"\\\n"), "\"a \\\"b\\\"\\\\\\n\"");
+    }
+
+    #[test]
+    fn test_to_starlark() -> Result<()> {
+        let config: PythonConfig = serde_json::from_str(
+            r#"{"targets": [
+                {"name": "exe", "default": false, "value": {
+                    "type": "executable", "name": "myapp", "run_module": "myapp",
+                    "run_command": null, "run_filename": null, "python_version": "3.8",
+                    "flavor": "standalone", "actions": [
+                        {"action": "pip_install", "args": ["requests"], "extra_envs": {}},
+                        {"action": "read_package_root", "path": "/src", "packages": ["myapp"]}
+                    ]}},
+                {"name": "install", "default": true, "value": {
+                    "type": "install", "executables": ["exe"]}}
+            ]}"#,
+        )?;
+
+        let code = config.to_starlark()?;
+
+        assert!(code.contains(
+            "    dist = default_python_distribution(flavor=\"standalone\", python_version=\"3.8\")"
+        ));
+        assert!(code.contains("    python_config.run_module = \"myapp\""));
+        assert!(code.contains("    exe.add_python_resources(exe.pip_install([\"requests\"], {}))"));
+        assert!(code.contains(
+            "    exe.add_python_resources(exe.read_package_root(\"/src\", [\"myapp\"]))"
+        ));
+        assert!(code.contains("register_target(\"exe\", make_target_0, depends=[], default=False)"));
+        assert!(code.contains("def make_target_1(exe0):"));
+        assert!(code.contains(
+            "register_target(\"install\", make_target_1, depends=[\"exe\"], default=True)"
+        ));
+        assert!(code.ends_with("resolve_targets()\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_starlark_unknown_install_target() -> Result<()> {
+        let config: PythonConfig = serde_json::from_str(
+            r#"{"targets": [
+                {"name": "install", "default": true, "value": {
+                    "type": "install", "executables": ["missing"]}}
+            ]}"#,
+        )?;
+
+        assert!(config.to_starlark().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+"""Define PyOxidizer build targets from a Python script.
+
+This module is made importable when PyOxidizer runs a Python configuration
+script. Calls to this module record build targets. Once the script
+finishes, the recorded targets are handed to PyOxidizer, which builds them
+the same way it builds targets defined by a Starlark configuration file.
+"""
+
+import json
+import os
+import runpy
+import sys
+
+_SCRIPT_DIR = os.getcwd()
+_TARGETS = []
+
+
+def _abspath(path):
+    return os.path.normpath(os.path.join(_SCRIPT_DIR, path))
+
+
+def _string_dict(value, name):
+    value = dict(value or {})
+    for k, v in value.items():
+        if not isinstance(k, str) or not isinstance(v, str):
+            raise TypeError("%s must map strings to strings" % name)
+    return value
+
+
+def _string_list(value, name):
+    value = list(value or [])
+    for v in value:
+        if not isinstance(v, str):
+            raise TypeError("%s must be a list of strings" % name)
+    return value
+
+
+class PythonExecutable:
+    """An executable embedding Python.
+
+    Equivalent to the ``PythonExecutable`` Starlark type.
+    """
+
+    def __init__(
+        self,
+        name,
+        run_module=None,
+        run_command=None,
+        run_filename=None,
+        python_version=None,
+        flavor="standalone",
+    ):
+        if sum(x is not None for x in (run_module, run_command, run_filename)) > 1:
+            raise ValueError(
+                "only one of run_module, run_command, and run_filename can be set"
+            )
+
+        self.name = name
+        self.run_module = run_module
+        self.run_command = run_command
+        self.run_filename = run_filename
+        self.python_version = python_version
+        self.flavor = flavor
+        self._actions = []
+
+    def pip_install(self, args, extra_envs=None):
+        """Add resources installed by ``pip install``."""
+        self._actions.append(
+            {
+                "action": "pip_install",
+                "args": _string_list(args, "args"),
+                "extra_envs": _string_dict(extra_envs, "extra_envs"),
+            }
+        )
+
+    def read_package_root(self, path, packages):
+        """Add resources of packages in a directory."""
+        self._actions.append(
+            {
+                "action": "read_package_root",
+                "path": _abspath(path),
+                "packages": _string_list(packages, "packages"),
+            }
+        )
+
+    def read_virtualenv(self, path):
+        """Add resources installed in a virtualenv."""
+        self._actions.append(
+            {
+                "action": "read_virtualenv",
+                "path": _abspath(path),
+            }
+        )
+
+    def setup_py_install(self, package_path, extra_envs=None, extra_global_arguments=None):
+        """Add resources installed by running ``setup.py install``."""
+        self._actions.append(
+            {
+                "action": "setup_py_install",
+                "package_path": _abspath(package_path),
+                "extra_envs": _string_dict(extra_envs, "extra_envs"),
+                "extra_global_arguments": _string_list(
+                    extra_global_arguments, "extra_global_arguments"
+                ),
+            }
+        )
+
+    def _serialize(self):
+        return {
+            "type": "executable",
+            "name": self.name,
+            "run_module": self.run_module,
+            "run_command": self.run_command,
+            "run_filename": self.run_filename,
+            "python_version": self.python_version,
+            "flavor": self.flavor,
+            "actions": self._actions,
+        }
+
+
+class Install:
+    """Files installed to a directory.
+
+    Equivalent to a ``FileManifest`` holding executables.
+    """
+
+    def __init__(self, executables):
+        self.executables = list(executables)
+
+    def _serialize(self):
+        names = []
+        for exe in self.executables:
+            target = _target_of(exe)
+            if target is None:
+                raise ValueError(
+                    "executable %s must be registered as a target before being installed"
+                    % exe.name
+                )
+            names.append(target)
+
+        return {
+            "type": "install",
+            "executables": names,
+        }
+
+
+def _target_of(value):
+    for target in _TARGETS:
+        if target["value"] is value:
+            return target["name"]
+
+    return None
+
+
+def register_target(name, value, default=False):
+    """Register a named build target.
+
+    ``value`` is a ``PythonExecutable`` or ``Install`` instance.
+    """
+    if not isinstance(value, (PythonExecutable, Install)):
+        raise TypeError("target value must be a PythonExecutable or Install")
+
+    if any(t["name"] == name for t in _TARGETS):
+        raise ValueError("target %s is already registered" % name)
+
+    _TARGETS.append({"name": name, "value": value, "default": bool(default)})
+
+
+def _run(script_path, output_path):
+    global _SCRIPT_DIR
+
+    _SCRIPT_DIR = os.path.dirname(os.path.abspath(script_path))
+    sys.path.insert(0, _SCRIPT_DIR)
+
+    runpy.run_path(script_path, run_name="__main__")
+
+    targets = [
+        {
+            "name": t["name"],
+            "default": t["default"],
+            "value": t["value"]._serialize(),
+        }
+        for t in _TARGETS
+    ]
+
+    with open(output_path, "w", encoding="utf-8") as fh:
+        json.dump({"targets": targets}, fh)
@@ -39,6 +39,7 @@ impl PythonDistributionCollection {
                 DistributionFlavor::Standalone => true,
                 DistributionFlavor::StandaloneStatic => !dist.supports_prebuilt_extension_modules,
                 DistributionFlavor::StandaloneDynamic => dist.supports_prebuilt_extension_modules,
+                DistributionFlavor::StandalonePgo => dist.pgo,
             })
             .cloned()
             .next()
@@ -51,7 +52,6 @@ impl PythonDistributionCollection {
     }
 
     /// All target triples of distributions in this collection.
-    #[allow(unused)]
     pub fn all_target_triples(&self) -> impl Iterator<Item = &str> {
         self.dists
             .iter()
@@ -61,6 +61,9 @@ impl PythonDistributionCollection {
     }
 }
 
+// There are no records for aarch64-pc-windows-msvc or aarch64 and armv7
+// Linux because no upstream builds exist for them yet. Those targets require
+// an explicitly provided distribution.
 pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(|| {
     let dists = vec![
 		// Linux glibc linked.
@@ -72,6 +75,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "x86_64-unknown-linux-gnu".to_string(),
 			supports_prebuilt_extension_modules: true,
+			pgo: true,
 		},
 		PythonDistributionRecord {
 			python_major_minor_version: "3.9".to_string(),
@@ -81,6 +85,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "x86_64-unknown-linux-gnu".to_string(),
 			supports_prebuilt_extension_modules: true,
+			pgo: true,
 		},
 
 		// Linux musl.
@@ -92,6 +97,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "x86_64-unknown-linux-musl".to_string(),
 			supports_prebuilt_extension_modules: false,
+			pgo: false,
 		},
 		PythonDistributionRecord {
 			python_major_minor_version: "3.9".to_string(),
@@ -101,6 +107,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "x86_64-unknown-linux-musl".to_string(),
 			supports_prebuilt_extension_modules: false,
+			pgo: false,
 		},
 
 		// The order here is important because we will choose the
@@ -119,6 +126,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "i686-pc-windows-msvc".to_string(),
 			supports_prebuilt_extension_modules: true,
+			pgo: true,
 		},
 		PythonDistributionRecord {
 			python_major_minor_version: "3.9".to_string(),
@@ -128,6 +136,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "i686-pc-windows-msvc".to_string(),
 			supports_prebuilt_extension_modules: true,
+			pgo: true,
 		},
 		PythonDistributionRecord {
 			python_major_minor_version: "3.8".to_string(),
@@ -137,6 +146,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "x86_64-pc-windows-msvc".to_string(),
 			supports_prebuilt_extension_modules: true,
+			pgo: true,
 		},
 		PythonDistributionRecord {
 			python_major_minor_version: "3.9".to_string(),
@@ -146,6 +156,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "x86_64-pc-windows-msvc".to_string(),
 			supports_prebuilt_extension_modules: true,
+			pgo: true,
 		},
 
 		// Windows static.
@@ -157,6 +168,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "i686-pc-windows-msvc".to_string(),
 			supports_prebuilt_extension_modules: false,
+			pgo: false,
 		},
 		PythonDistributionRecord {
 			python_major_minor_version: "3.9".to_string(),
@@ -166,6 +178,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "i686-pc-windows-msvc".to_string(),
 			supports_prebuilt_extension_modules: false,
+			pgo: false,
 		},
 		PythonDistributionRecord {
 			python_major_minor_version: "3.8".to_string(),
@@ -175,6 +188,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "x86_64-pc-windows-msvc".to_string(),
 			supports_prebuilt_extension_modules: false,
+			pgo: false,
 		},
 		PythonDistributionRecord {
 			python_major_minor_version: "3.9".to_string(),
@@ -184,6 +198,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "x86_64-pc-windows-msvc".to_string(),
 			supports_prebuilt_extension_modules: false,
+			pgo: false,
 		},
 
 		// macOS.
@@ -195,6 +210,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "aarch64-apple-darwin".to_string(),
 			supports_prebuilt_extension_modules: true,
+			pgo: true,
 		},
 		PythonDistributionRecord {
 			python_major_minor_version: "3.8".to_string(),
@@ -204,6 +220,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "x86_64-apple-darwin".to_string(),
 			supports_prebuilt_extension_modules: true,
+			pgo: true,
 		},
 		PythonDistributionRecord {
 			python_major_minor_version: "3.9".to_string(),
@@ -213,6 +230,7 @@ pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(
 			},
 			target_triple: "x86_64-apple-darwin".to_string(),
 			supports_prebuilt_extension_modules: true,
+			pgo: true,
 		},
 	];
 
@@ -5,6 +5,8 @@
 use {
     crate::py_packaging::distribution::DistributionCache,
     anyhow::{Context, Result},
+    python_packaging::bytecode::BytecodeCache,
+    sha2::{Digest, Sha256},
     starlark::{
         environment::{Environment, EnvironmentError, TypeValues},
         values::{
@@ -14,8 +16,10 @@ use {
     },
     starlark_dialect_build_targets::{get_context_value, EnvironmentContext},
     std::{
+        collections::{BTreeMap, BTreeSet},
         path::{Path, PathBuf},
-        sync::Arc,
+        sync::{Arc, Mutex},
+        time::{SystemTime, UNIX_EPOCH},
     },
 };
 
@@ -52,6 +56,23 @@ pub struct PyOxidizerEnvironmentContext {
     /// This exists because constructing a new instance can take a
     /// few seconds in debug builds. And this adds up, especially in tests!
     pub distribution_cache: Arc<DistributionCache>,
+
+    /// URL templates of mirrors to download Python distributions from.
+    ///
+    /// Registered via `set_distribution_mirror()`.
+    pub distribution_mirrors: Vec<String>,
+
+    /// Bytecode compiled when building executables.
+    ///
+    /// Shared by all executables so sources they have in common, such as
+    /// resources from a `SharedPythonResources`, are only compiled once.
+    pub bytecode_cache: Arc<Mutex<BytecodeCache>>,
+
+    /// Files and directories read while evaluating the configuration.
+    ///
+    /// Paths are keyed by the target whose function read them, or `None`
+    /// if they were read outside of target functions.
+    pub input_paths: BTreeMap<Option<String>, BTreeSet<PathBuf>>,
 }
 
 impl PyOxidizerEnvironmentContext {
@@ -89,6 +110,9 @@ impl PyOxidizerEnvironmentContext {
             build_release,
             build_opt_level: build_opt_level.to_string(),
             distribution_cache,
+            distribution_mirrors: vec![],
+            bytecode_cache: Arc::new(Mutex::new(BytecodeCache::default())),
+            input_paths: BTreeMap::new(),
         })
     }
 
@@ -96,6 +120,57 @@ impl PyOxidizerEnvironmentContext {
         &self.logger
     }
 
+    /// Metadata describing the build to embed in built binaries.
+    ///
+    /// The build timestamp is seconds since the UNIX epoch. It honors
+    /// `SOURCE_DATE_EPOCH` so reproducible builds remain reproducible.
+    /// `config_hash` and `git_commit` are omitted if the configuration file
+    /// can't be read or it isn't in a Git checkout.
+    pub fn build_info(&self) -> BTreeMap<String, String> {
+        let mut info = BTreeMap::new();
+
+        info.insert(
+            "pyoxidizer_version".to_string(),
+            crate::environment::PYOXIDIZER_VERSION.to_string(),
+        );
+        info.insert(
+            "target_triple".to_string(),
+            self.build_target_triple.clone(),
+        );
+
+        let timestamp = match std::env::var("SOURCE_DATE_EPOCH") {
+            Ok(value) => value,
+            Err(_) => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+                .to_string(),
+        };
+        info.insert("build_timestamp".to_string(), timestamp);
+
+        if let Ok(data) = std::fs::read(&self.config_path) {
+            info.insert(
+                "config_hash".to_string(),
+                hex::encode(Sha256::digest(&data)),
+            );
+        }
+
+        if let Ok(output) = std::process::Command::new("git")
+            .args(&["rev-parse", "HEAD"])
+            .current_dir(&self.cwd)
+            .output()
+        {
+            if output.status.success() {
+                info.insert(
+                    "git_commit".to_string(),
+                    String::from_utf8_lossy(&output.stdout).trim().to_string(),
+                );
+            }
+        }
+
+        info
+    }
+
     pub fn build_path(&self, type_values: &TypeValues) -> Result<PathBuf, ValueError> {
         let build_targets_context_value = get_context_value(type_values)?;
         let context = build_targets_context_value
@@ -105,11 +180,21 @@ impl PyOxidizerEnvironmentContext {
         Ok(context.build_path().to_path_buf())
     }
 
+    /// Directory holding scratch state and caches of the build.
+    pub fn cache_path(&self, type_values: &TypeValues) -> Result<PathBuf, ValueError> {
+        let build_targets_context_value = get_context_value(type_values)?;
+        let context = build_targets_context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        Ok(context.cache_path())
+    }
+
     pub fn python_distributions_path(
         &self,
         type_values: &TypeValues,
     ) -> Result<PathBuf, ValueError> {
-        Ok(self.build_path(type_values)?.join("python_distributions"))
+        Ok(self.cache_path(type_values)?.join("python_distributions"))
     }
 
     pub fn get_output_path(
@@ -166,6 +251,35 @@ pub fn get_context(type_values: &TypeValues) -> ValueResult {
         })
 }
 
+/// Record a file or directory read while evaluating the configuration.
+///
+/// Relative paths are relative to the directory of the configuration file.
+/// The path is attributed to the target being resolved, if any.
+pub fn record_input_path(type_values: &TypeValues, path: &Path) -> Result<(), ValueError> {
+    let target = {
+        let build_targets_context_value = get_context_value(type_values)?;
+        let context = build_targets_context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        context.resolving_target().map(|t| t.to_string())
+    };
+
+    let pyoxidizer_context_value = get_context(type_values)?;
+    let mut pyoxidizer_context = pyoxidizer_context_value
+        .downcast_mut::<PyOxidizerEnvironmentContext>()?
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    let path = pyoxidizer_context.cwd.join(path);
+    pyoxidizer_context
+        .input_paths
+        .entry(target)
+        .or_default()
+        .insert(path);
+
+    Ok(())
+}
+
 /// Obtain a Starlark environment for evaluating PyOxidizer configurations.
 pub fn register_starlark_dialect(
     env: &mut Environment,
@@ -174,10 +288,16 @@ pub fn register_starlark_dialect(
     starlark_dialect_build_targets::register_starlark_dialect(env, type_values)?;
     tugger::starlark::register_starlark_dialect(env, type_values)?;
     super::file_resource::file_resource_env(env, type_values);
+    super::jupyter_kernel::jupyter_kernel_module(env, type_values);
+    super::platform_conditions::platform_conditions_module(env, type_values);
     super::python_distribution::python_distribution_module(env, type_values);
     super::python_embedded_resources::python_embedded_resources_module(env, type_values);
     super::python_executable::python_executable_env(env, type_values);
+    super::python_executable_tests::python_executable_tests_module(env, type_values);
+    super::python_interpreter_config::python_interpreter_config_module(env, type_values);
     super::python_packaging_policy::python_packaging_policy_module(env, type_values);
+    super::python_zipapp::python_zipapp_module(env, type_values);
+    super::shared_resources::shared_resources_module(env, type_values);
 
     Ok(())
 }
@@ -205,6 +325,8 @@ pub fn populate_environment(
         }),
     ));
 
+    build_targets_context.set_cache_path(crate::environment::cache_dir_from_env());
+
     starlark_dialect_build_targets::populate_environment(env, type_values, build_targets_context)?;
     tugger::starlark::populate_environment(env, type_values)?;
 
@@ -4,7 +4,10 @@
 
 use {
     crate::{
-        py_packaging::distribution::DistributionCache,
+        py_packaging::distribution::{
+            default_distribution_location, DistributionCache, DistributionFlavor,
+            PythonDistribution,
+        },
         starlark::env::{
             populate_environment, register_starlark_dialect, PyOxidizerContext,
             PyOxidizerEnvironmentContext,
@@ -26,6 +29,7 @@ use {
         build_target, run_target, EnvironmentContext, ResolvedTarget,
     },
     std::{
+        collections::BTreeSet,
         convert::TryFrom,
         path::{Path, PathBuf},
         sync::{Arc, Mutex},
@@ -232,11 +236,61 @@ impl EvaluationContext {
     }
 
     /// Evaluate a Starlark configuration file, returning an anyhow Result.
+    ///
+    /// Python configuration files are converted to Starlark and evaluated.
     pub fn evaluate_file(&mut self, config_path: &Path) -> Result<()> {
+        if crate::python_config::is_python_config(config_path) {
+            return self.evaluate_python_file(config_path);
+        }
+
         self.evaluate_file_diagnostic(config_path)
             .map_err(|d| anyhow!(d.message))
     }
 
+    /// Evaluate a Python configuration file.
+    ///
+    /// The file is run by the default Python distribution of the host.
+    fn evaluate_python_file(&mut self, config_path: &Path) -> Result<()> {
+        let code = {
+            let pyoxidizer_context_value = self
+                .pyoxidizer_context_value()
+                .map_err(|e| anyhow!("{:?}", e))?;
+            let pyoxidizer_context = pyoxidizer_context_value
+                .downcast_ref::<PyOxidizerEnvironmentContext>()
+                .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+            let location = default_distribution_location(
+                &DistributionFlavor::Standalone,
+                crate::project_building::HOST,
+                None,
+            )?;
+            let dest_dir = pyoxidizer_context
+                .python_distributions_path(&self.type_values)
+                .map_err(|e| anyhow!("{:?}", e))?;
+            let dist = pyoxidizer_context.distribution_cache.resolve_distribution(
+                pyoxidizer_context.logger(),
+                &location,
+                Some(&dest_dir),
+                &pyoxidizer_context.distribution_mirrors,
+            )?;
+
+            let cache_path = pyoxidizer_context
+                .cache_path(&self.type_values)
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+            crate::python_config::python_config_to_starlark(
+                pyoxidizer_context.logger(),
+                dist.python_exe_path(),
+                config_path,
+                &cache_path,
+            )?
+        };
+
+        self.eval_code_with_path(&config_path.display().to_string(), &code)?;
+
+        Ok(())
+    }
+
     /// Evaluate code, returning a `Diagnostic` on error.
     pub fn eval_diagnostic(
         &mut self,
@@ -349,6 +403,73 @@ impl EvaluationContext {
         Ok(context.targets_to_resolve())
     }
 
+    /// Obtain the names of targets a target depends on.
+    pub fn target_depends(&self, target: &str) -> Result<Vec<String>> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        Ok(context
+            .get_target(target)
+            .ok_or_else(|| anyhow!("target {} is not defined", target))?
+            .depends
+            .clone())
+    }
+
+    /// Obtain the Starlark type of the value a target resolved to.
+    ///
+    /// Returns `None` if the target hasn't been resolved.
+    pub fn target_value_type(&self, target: &str) -> Result<Option<String>> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        Ok(context
+            .get_target(target)
+            .ok_or_else(|| anyhow!("target {} is not defined", target))?
+            .resolved_value
+            .as_ref()
+            .map(|v| v.get_type().to_string()))
+    }
+
+    /// Obtain files and directories read while resolving a target.
+    ///
+    /// Paths read while resolving the targets it depends on are included. If
+    /// `target` is `None`, paths read outside of target functions are obtained.
+    pub fn input_paths(&self, target: Option<&str>) -> Result<BTreeSet<PathBuf>> {
+        let mut targets = target
+            .map(|t| t.to_string())
+            .into_iter()
+            .collect::<Vec<_>>();
+        let mut seen = BTreeSet::new();
+
+        while let Some(target) = targets.pop() {
+            if seen.insert(target.clone()) {
+                targets.extend(self.target_depends(&target)?);
+            }
+        }
+
+        let pyoxidizer_context_value = self
+            .pyoxidizer_context_value()
+            .map_err(|e| anyhow!("{:?}", e))?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        Ok(pyoxidizer_context
+            .input_paths
+            .iter()
+            .filter(|(key, _)| match (key, target) {
+                (Some(key), Some(_)) => seen.contains(key),
+                (None, None) => true,
+                _ => false,
+            })
+            .flat_map(|(_, paths)| paths.iter().cloned())
+            .collect())
+    }
+
     pub fn build_resolved_target(&mut self, target: &str) -> Result<ResolvedTarget> {
         let mut call_stack = CallStack::default();
 
@@ -426,4 +547,77 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_input_paths() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let logger = get_logger()?;
+
+        for name in &["shared", "exe", "other"] {
+            let package = temp_dir.path().join(name).join(format!("pkg_{}", name));
+            std::fs::create_dir_all(&package)?;
+            std::fs::write(package.join("__init__.py"), b"")?;
+        }
+
+        let root = temp_dir
+            .path()
+            .display()
+            .to_string()
+            .escape_default()
+            .to_string();
+        let config_path = temp_dir.path().join("pyoxidizer.bzl");
+        std::fs::write(
+            &config_path,
+            format!(
+                "root = '{}'\n\
+                 dist = default_python_distribution()\n\
+                 dist.to_python_executable('shared').read_package_root(root + '/shared', ['pkg_shared'])\n\
+                 def make_exe():\n    \
+                     exe = dist.to_python_executable('exe')\n    \
+                     exe.add_python_resources(exe.read_package_root(root + '/exe', ['pkg_exe']))\n    \
+                     return exe\n\
+                 def make_other():\n    \
+                     exe = dist.to_python_executable('other')\n    \
+                     exe.read_package_root(root + '/other', ['pkg_other'])\n    \
+                     return exe\n\
+                 def make_install(exe):\n    \
+                     return FileManifest()\n\
+                 register_target('exe', make_exe)\n\
+                 register_target('other', make_other)\n\
+                 register_target('install', make_install, depends=['exe'])\n\
+                 resolve_targets()\n",
+                root
+            )
+            .as_bytes(),
+        )?;
+
+        let mut context: EvaluationContext =
+            EvaluationContextBuilder::new(logger, config_path.clone(), env!("HOST").to_string())
+                .resolve_targets(vec!["install".to_string(), "other".to_string()])
+                .into_context()?;
+        context.evaluate_file(&config_path)?;
+
+        let cwd = temp_dir.path();
+        assert_eq!(
+            context.input_paths(None)?,
+            vec![cwd.join("shared")].into_iter().collect()
+        );
+        assert_eq!(
+            context.input_paths(Some("exe"))?,
+            vec![cwd.join("exe")].into_iter().collect()
+        );
+        assert_eq!(
+            context.input_paths(Some("install"))?,
+            vec![cwd.join("exe")].into_iter().collect(),
+            "paths read by dependencies should be included"
+        );
+        assert_eq!(
+            context.input_paths(Some("other"))?,
+            vec![cwd.join("other")].into_iter().collect()
+        );
+
+        Ok(())
+    }
 }
@@ -13,9 +13,12 @@ use {
     },
     crate::{
         project_building::build_python_executable,
-        py_packaging::{binary::PythonBinaryBuilder, resource::AddToFileManifest},
+        py_packaging::{
+            binary::{normalize_relative_path, PythonBinaryBuilder},
+            resource::AddToFileManifest,
+        },
     },
-    anyhow::Result,
+    anyhow::{anyhow, Result},
     slog::warn,
     starlark::{
         environment::TypeValues,
@@ -61,6 +64,47 @@ pub fn file_manifest_add_python_executable(
 
     for (path, entry) in build.binary_data.extra_files.iter_entries() {
         warn!(logger, "adding extra file {} to {}", path.display(), prefix);
+        let dest_path = Path::new(use_prefix).join(path);
+        extra_files.add_file_entry(&dest_path, entry.clone())?;
+
+        if let Some(mode) = build.binary_data.extra_files.get_mode(path) {
+            extra_files.set_mode(&dest_path, mode)?;
+        }
+    }
+
+    for (path, target) in build.binary_data.extra_files.iter_symlinks() {
+        warn!(
+            logger,
+            "adding extra symlink {} to {}",
+            path.display(),
+            prefix
+        );
+        extra_files.add_symlink(&Path::new(use_prefix).join(path), target)?;
+    }
+
+    // Files installed outside the executable's directory are placed relative
+    // to it, so they must stay within the manifest.
+    for (path, entry) in &build.binary_data.external_files {
+        let dest_path = normalize_relative_path(&Path::new(use_prefix).join(path));
+        if dest_path.starts_with("..") {
+            return Err(anyhow!(
+                "{} is installed outside of the manifest; add the executable under a deeper prefix than {}",
+                path.display(),
+                prefix
+            ));
+        }
+
+        warn!(
+            logger,
+            "adding extra file {} to {}",
+            dest_path.display(),
+            prefix
+        );
+        extra_files.add_file_entry(&dest_path, entry.clone())?;
+    }
+
+    for (path, entry) in build.debug_files.iter_entries() {
+        warn!(logger, "adding debug file {} to {}", path.display(), prefix);
         extra_files.add_file_entry(&Path::new(use_prefix).join(path), entry.clone())?;
     }
 
@@ -165,6 +209,18 @@ pub fn file_manifest_add_python_resource(
                 })
         }
 
+        "PythonModuleBytecode" => {
+            // We don't know the bytecode header of the target interpreter here.
+            // So these resources can only be added to executables.
+            warn!(
+                pyoxidizer_context.logger(),
+                "ignoring {}: bytecode without source can't be added to a FileManifest",
+                resource.to_repr()
+            );
+
+            Ok(())
+        }
+
         "PythonExecutable" => match resource.downcast_ref::<PythonExecutableValue>() {
             Some(exe) => {
                 warn!(
@@ -405,4 +461,36 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_install_origin_relative_resources() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        env.eval("module = exe.make_python_module_source('foo', 'import sys')")?;
+        env.eval("module.add_location = 'filesystem-relative:$ORIGIN/../share/myapp'")?;
+        env.eval("module.add_include = True")?;
+        env.eval("exe.add_python_resource(module)")?;
+
+        let m = Value::new(FileManifestValue {
+            manifest: FileManifest::default(),
+            run_path: None,
+        });
+        env.set_var("m", m).unwrap();
+
+        // Files outside the executable's directory must stay in the manifest.
+        assert!(env.eval("m.add_python_resource('.', exe)").is_err());
+
+        env.eval("m.add_python_resource('bin', exe)")?;
+        env.eval("m.install('myapp')")?;
+
+        let dest_path = env.build_path().unwrap().join("myapp");
+        assert_eq!(
+            std::fs::read(dest_path.join("share").join("myapp").join("foo.py"))?,
+            b"import sys"
+        );
+        assert!(!dest_path.join("bin").join("share").exists());
+
+        Ok(())
+    }
 }
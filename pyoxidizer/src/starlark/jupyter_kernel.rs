@@ -0,0 +1,177 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::{
+        environment::canonicalize_path,
+        project_building::build_python_executable,
+        py_packaging::{
+            binary::PythonBinaryBuilder,
+            jupyter_kernel::{ensure_kernel_package, kernel_spec, user_kernels_dir, KERNEL_MODULE},
+        },
+        starlark::env::{get_context, PyOxidizerEnvironmentContext},
+    },
+    anyhow::{anyhow, Context, Result},
+    slog::warn,
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{ResolvedTarget, ResolvedTargetValue, RunMode},
+    std::{io::Write, path::PathBuf, sync::Arc},
+};
+
+/// A Jupyter kernel backed by a Python executable.
+pub struct JupyterKernelValue {
+    pub exe: Arc<dyn PythonBinaryBuilder>,
+
+    /// Name of the kernel, which is also the name of its directory.
+    pub kernel_name: String,
+
+    /// Name of the kernel shown in Jupyter.
+    pub display_name: String,
+
+    /// Directory to install the kernel into.
+    ///
+    /// If not defined, the kernel is installed into the build directory of
+    /// the target.
+    pub kernels_dir: Option<PathBuf>,
+
+    /// Whether to install the kernel into the current user's kernels directory.
+    pub user: bool,
+}
+
+impl TypedValue for JupyterKernelValue {
+    type Holder = Mutable<JupyterKernelValue>;
+    const TYPE: &'static str = "JupyterKernel";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl JupyterKernelValue {
+    fn build(
+        &self,
+        type_values: &TypeValues,
+        target: &str,
+        context: &PyOxidizerEnvironmentContext,
+    ) -> Result<ResolvedTarget> {
+        let output_path = context
+            .get_output_path(type_values, target)
+            .map_err(|_| anyhow!("unable to resolve output path"))?;
+
+        ensure_kernel_package(self.exe.iter_resources())?;
+
+        let mut exe = self.exe.clone_trait();
+
+        let config = Arc::get_mut(&mut exe)
+            .expect("cloned executable is uniquely owned")
+            .python_interpreter_config_mut();
+        config.multicall_run_modules.clear();
+        config.multicall_subcommand = false;
+        config.multicall_env = None;
+        config.config.run_command = None;
+        config.config.run_filename = None;
+        config.config.run_module = Some(KERNEL_MODULE.to_string());
+
+        let build = build_python_executable(
+            context.logger(),
+            &exe.name(),
+            exe.as_ref(),
+            &context.build_target_triple,
+            &context.build_opt_level,
+            context.build_release,
+        )?;
+
+        let kernels_dir = if self.user {
+            user_kernels_dir()?
+        } else if let Some(path) = &self.kernels_dir {
+            path.clone()
+        } else {
+            output_path.join("kernels")
+        };
+        let kernel_dir = kernels_dir.join(&self.kernel_name);
+
+        // Kernels are self-contained directories.
+        if let Some(path) = build.binary_data.external_files.keys().next() {
+            return Err(anyhow!(
+                "unable to install {} outside the kernel directory; use a filesystem-relative location within the executable's directory",
+                path.display()
+            ));
+        }
+
+        warn!(
+            context.logger(),
+            "installing Jupyter kernel to {}",
+            kernel_dir.display()
+        );
+
+        if kernel_dir.exists() {
+            std::fs::remove_dir_all(&kernel_dir)
+                .with_context(|| format!("removing {}", kernel_dir.display()))?;
+        }
+        std::fs::create_dir_all(&kernel_dir)
+            .with_context(|| format!("creating {}", kernel_dir.display()))?;
+        let kernel_dir = canonicalize_path(&kernel_dir)?;
+
+        build.binary_data.materialize_files(&kernel_dir)?;
+
+        let exe_path = kernel_dir.join(&build.exe_name);
+        let mut fh = std::fs::File::create(&exe_path)
+            .with_context(|| format!("creating {}", exe_path.display()))?;
+        fh.write_all(&build.exe_data)
+            .with_context(|| format!("writing {}", exe_path.display()))?;
+        tugger_file_manifest::set_executable(&mut fh).context("making binary executable")?;
+
+        let spec_path = kernel_dir.join("kernel.json");
+        std::fs::write(
+            &spec_path,
+            serde_json::to_vec_pretty(&kernel_spec(&exe_path, &self.display_name))?,
+        )
+        .with_context(|| format!("writing {}", spec_path.display()))?;
+
+        Ok(ResolvedTarget {
+            run_mode: RunMode::None,
+            output_path,
+        })
+    }
+
+    fn build_starlark(&self, type_values: &TypeValues, target: String) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        Ok(Value::new(ResolvedTargetValue {
+            inner: self
+                .build(type_values, &target, &pyoxidizer_context)
+                .map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER",
+                        message: e.to_string(),
+                        label: "build()".to_string(),
+                    })
+                })?,
+        }))
+    }
+}
+
+starlark_module! { jupyter_kernel_module =>
+    JupyterKernel.build(
+        env env,
+        this,
+        target: String
+    ) {
+        let this = this.downcast_ref::<JupyterKernelValue>().unwrap();
+        this.build_starlark(env, target)
+    }
+}
@@ -11,12 +11,17 @@ define Oxidized Python binaries.
 pub mod env;
 pub mod eval;
 pub mod file_resource;
+pub mod jupyter_kernel;
+pub mod platform_conditions;
 pub mod python_distribution;
 pub mod python_embedded_resources;
 pub mod python_executable;
+pub mod python_executable_tests;
 pub mod python_interpreter_config;
 pub mod python_packaging_policy;
 pub mod python_resource;
+pub mod python_zipapp;
+pub mod shared_resources;
 #[cfg(test)]
 mod testutil;
 pub mod util;
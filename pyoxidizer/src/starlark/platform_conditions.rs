@@ -0,0 +1,199 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Starlark functions selecting values based on the target being built for.
+
+These allow a single configuration file to describe differences between
+platforms. e.g. `exe.add_python_resources(if_windows(windows_resources))`,
+where `windows_resources` is a function returning resources.
+*/
+
+use {
+    super::env::{get_context, PyOxidizerEnvironmentContext},
+    linked_hash_map::LinkedHashMap,
+    starlark::{
+        environment::TypeValues,
+        eval::call_stack::CallStack,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            Value, ValueResult,
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+};
+
+/// Whether a target triple matches a glob pattern like `*-pc-windows-*`.
+pub fn target_triple_matches(pattern: &str, target_triple: &str) -> Result<bool, String> {
+    Ok(glob::Pattern::new(pattern)
+        .map_err(|e| format!("invalid pattern {}: {}", pattern, e))?
+        .matches(target_triple))
+}
+
+fn build_target_triple(type_values: &TypeValues) -> Result<String, ValueError> {
+    let pyoxidizer_context_value = get_context(type_values)?;
+    let pyoxidizer_context = pyoxidizer_context_value
+        .downcast_ref::<PyOxidizerEnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    Ok(pyoxidizer_context.build_target_triple.clone())
+}
+
+/// Resolve the value to return from a condition.
+///
+/// Functions are called so work like running `pip` only happens for
+/// matching targets. `None` resolves to an empty list so the result can
+/// always be iterated or passed to `add_python_resources()`.
+fn resolve_value(
+    type_values: &TypeValues,
+    call_stack: &mut CallStack,
+    value: Value,
+) -> ValueResult {
+    match value.get_type() {
+        "function" => value.call(
+            call_stack,
+            type_values,
+            vec![],
+            LinkedHashMap::new(),
+            None,
+            None,
+        ),
+        "NoneType" => Ok(Value::from(Vec::<Value>::new())),
+        _ => Ok(value),
+    }
+}
+
+fn select(
+    type_values: &TypeValues,
+    call_stack: &mut CallStack,
+    matches: bool,
+    value: Value,
+    otherwise: Value,
+) -> ValueResult {
+    resolve_value(
+        type_values,
+        call_stack,
+        if matches { value } else { otherwise },
+    )
+}
+
+/// if_target(pattern, value, otherwise=None)
+fn starlark_if_target(
+    type_values: &TypeValues,
+    call_stack: &mut CallStack,
+    pattern: String,
+    value: Value,
+    otherwise: Value,
+) -> ValueResult {
+    let matches =
+        target_triple_matches(&pattern, &build_target_triple(type_values)?).map_err(|message| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_CONDITION",
+                message,
+                label: "if_target()".to_string(),
+            })
+        })?;
+
+    select(type_values, call_stack, matches, value, otherwise)
+}
+
+fn starlark_if_triple_contains(
+    type_values: &TypeValues,
+    call_stack: &mut CallStack,
+    needle: &str,
+    value: Value,
+    otherwise: Value,
+) -> ValueResult {
+    let matches = build_target_triple(type_values)?.contains(needle);
+
+    select(type_values, call_stack, matches, value, otherwise)
+}
+
+starlark_module! { platform_conditions_module =>
+    if_target(
+        env env,
+        call_stack cs,
+        pattern: String,
+        value,
+        otherwise = NoneType::None
+    ) {
+        starlark_if_target(env, cs, pattern, value, otherwise)
+    }
+
+    if_linux(env env, call_stack cs, value, otherwise = NoneType::None) {
+        starlark_if_triple_contains(env, cs, "-linux-", value, otherwise)
+    }
+
+    if_macos(env env, call_stack cs, value, otherwise = NoneType::None) {
+        starlark_if_triple_contains(env, cs, "-apple-darwin", value, otherwise)
+    }
+
+    if_windows(env env, call_stack cs, value, otherwise = NoneType::None) {
+        starlark_if_triple_contains(env, cs, "-pc-windows-", value, otherwise)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::starlark::testutil::*, anyhow::Result};
+
+    #[test]
+    fn test_target_triple_matches() -> Result<()> {
+        assert!(target_triple_matches("*", "x86_64-unknown-linux-gnu").unwrap());
+        assert!(target_triple_matches("*-linux-*", "x86_64-unknown-linux-gnu").unwrap());
+        assert!(target_triple_matches("x86_64-*", "x86_64-apple-darwin").unwrap());
+        assert!(!target_triple_matches("*-musl", "x86_64-unknown-linux-gnu").unwrap());
+        assert!(target_triple_matches("[", "x86_64-unknown-linux-gnu").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_if_windows() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?
+            .build_target_triple("x86_64-pc-windows-msvc")
+            .into_context()?;
+
+        eval_assert(&mut env, "if_windows(['a']) == ['a']")?;
+        eval_assert(&mut env, "if_windows(['a'], ['b']) == ['a']")?;
+        eval_assert(&mut env, "if_linux(['a']) == []")?;
+        eval_assert(&mut env, "if_linux(['a'], ['b']) == ['b']")?;
+        eval_assert(&mut env, "if_macos('a', otherwise = 'b') == 'b'")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_if_target() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?
+            .build_target_triple("x86_64-unknown-linux-musl")
+            .into_context()?;
+
+        eval_assert(&mut env, "if_target('*-musl', ['a']) == ['a']")?;
+        eval_assert(&mut env, "if_target('*-gnu', ['a']) == []")?;
+        eval_assert(&mut env, "if_linux(['a']) == ['a']")?;
+        assert!(env.eval("if_target('[', ['a'])").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_function_value() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?
+            .build_target_triple("x86_64-apple-darwin")
+            .into_context()?;
+
+        env.eval("def mac():\n    return ['mac']\n")?;
+        env.eval("def fail_if_called():\n    return [][1]\n")?;
+
+        eval_assert(&mut env, "if_macos(mac, fail_if_called) == ['mac']")?;
+        eval_assert(&mut env, "if_windows(fail_if_called) == []")?;
+
+        Ok(())
+    }
+}
@@ -13,8 +13,8 @@ use {
     crate::py_packaging::{
         distribution::BinaryLibpythonLinkMode,
         distribution::{
-            default_distribution_location, DistributionFlavor, PythonDistribution,
-            PythonDistributionLocation,
+            default_distribution_location, distribution_download_urls, DistributionFlavor,
+            PythonDistribution, PythonDistributionLocation,
         },
     },
     anyhow::{anyhow, Result},
@@ -35,8 +35,8 @@ use {
             starlark_signature_extraction, starlark_signatures,
         },
     },
-    starlark_dialect_build_targets::{optional_str_arg, optional_type_arg},
-    std::{convert::TryFrom, sync::Arc},
+    starlark_dialect_build_targets::{optional_str_arg, optional_type_arg, required_type_arg},
+    std::{collections::HashMap, convert::TryFrom, sync::Arc},
 };
 
 /// A Starlark Value wrapper for `PythonDistribution` traits.
@@ -78,6 +78,7 @@ impl PythonDistributionValue {
                         pyoxidizer_context.logger(),
                         &self.source,
                         Some(&dest_dir),
+                        &pyoxidizer_context.distribution_mirrors,
                     )
                     .map_err(|e| {
                         ValueError::from(RuntimeError {
@@ -154,6 +155,50 @@ impl PythonDistributionValue {
         Ok(Value::new(PythonDistributionValue::from_location(location)))
     }
 
+    /// system_python(version)
+    fn system_python(version: String) -> ValueResult {
+        if version.split('.').count() != 2 || version.split('.').any(|x| x.parse::<u32>().is_err())
+        {
+            return Err(ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("invalid Python version {}; expected X.Y", version),
+                label: "system_python()".to_string(),
+            }));
+        }
+
+        Ok(Value::new(PythonDistributionValue::from_location(
+            PythonDistributionLocation::System {
+                python_major_minor_version: version,
+            },
+        )))
+    }
+
+    /// set_distribution_mirror(url_template)
+    fn set_distribution_mirror(type_values: &TypeValues, url_template: String) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let mut pyoxidizer_context = pyoxidizer_context_value
+            .downcast_mut::<PyOxidizerEnvironmentContext>()?
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        // Validate the template by expanding it against a dummy URL.
+        distribution_download_urls(
+            "https://example.com/python.tar.zst",
+            "0",
+            &[url_template.clone()],
+        )
+        .map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:?}", e),
+                label: "set_distribution_mirror()".to_string(),
+            })
+        })?;
+
+        pyoxidizer_context.distribution_mirrors.push(url_template);
+
+        Ok(Value::new(NoneType::None))
+    }
+
     /// PythonDistribution()
     fn from_args(sha256: String, local_path: &Value, url: &Value, flavor: String) -> ValueResult {
         optional_str_arg("local_path", local_path)?;
@@ -229,6 +274,7 @@ impl PythonDistributionValue {
     ///     name,
     ///     packaging_policy=None,
     ///     config=None,
+    ///     host_distribution=None,
     /// )
     #[allow(
         clippy::ptr_arg,
@@ -242,6 +288,7 @@ impl PythonDistributionValue {
         name: String,
         packaging_policy: &Value,
         config: &Value,
+        host_distribution: Option<Arc<dyn PythonDistribution>>,
     ) -> ValueResult {
         optional_type_arg(
             "packaging_policy",
@@ -294,7 +341,39 @@ impl PythonDistributionValue {
         let python_distributions_path =
             pyoxidizer_context.python_distributions_path(type_values)?;
 
-        let host_distribution = if dist
+        let host_distribution = if let Some(host_dist) = host_distribution {
+            // The host distribution runs on the build machine and compiles
+            // bytecode for the target. So it must be runnable and produce
+            // bytecode the target interpreter can load.
+            if !host_dist
+                .compatible_host_triples()
+                .contains(&pyoxidizer_context.build_host_triple)
+            {
+                return Err(ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!(
+                        "host distribution targeting {} cannot run on the build host {}",
+                        host_dist.target_triple(),
+                        pyoxidizer_context.build_host_triple
+                    ),
+                    label: "to_python_executable()".to_string(),
+                }));
+            }
+
+            if host_dist.python_major_minor_version() != dist.python_major_minor_version() {
+                return Err(ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!(
+                        "host distribution is Python {} but target distribution is Python {}",
+                        host_dist.python_major_minor_version(),
+                        dist.python_major_minor_version()
+                    ),
+                    label: "to_python_executable()".to_string(),
+                }));
+            }
+
+            Some(host_dist)
+        } else if dist
             .compatible_host_triples()
             .contains(&pyoxidizer_context.build_host_triple)
         {
@@ -321,6 +400,7 @@ impl PythonDistributionValue {
                         pyoxidizer_context.logger(),
                         &location,
                         Some(&python_distributions_path),
+                        &pyoxidizer_context.distribution_mirrors,
                     )
                     .map_err(|e| {
                         ValueError::from(RuntimeError {
@@ -353,6 +433,10 @@ impl PythonDistributionValue {
                 })
             })?;
 
+        builder.python_interpreter_config_mut().build_info = pyoxidizer_context.build_info();
+        builder.set_cache_path(Some(pyoxidizer_context.cache_path(type_values)?));
+        builder.set_bytecode_cache(pyoxidizer_context.bytecode_cache.clone());
+
         let callback = Box::new(
             |_policy: &PythonPackagingPolicy,
              resource: &PythonResource,
@@ -419,6 +503,25 @@ impl PythonDistributionValue {
 
         Ok(Value::from(values))
     }
+
+    /// PythonDistribution.extension_module_variants()
+    pub fn extension_module_variants_starlark(&mut self, type_values: &TypeValues) -> ValueResult {
+        let dist = self.resolve_distribution(type_values, "extension_module_variants()")?;
+
+        let mut variants: HashMap<String, Vec<String>> = HashMap::new();
+        for resource in dist.python_resources() {
+            if let PythonResource::ExtensionModule(em) = resource {
+                if let Some(variant) = &em.variant {
+                    variants
+                        .entry(em.name.clone())
+                        .or_default()
+                        .push(variant.clone());
+                }
+            }
+        }
+
+        Value::try_from(variants)
+    }
 }
 
 starlark_module! { python_distribution_module =>
@@ -432,6 +535,11 @@ starlark_module! { python_distribution_module =>
         this.make_python_packaging_policy_starlark(&env)
     }
 
+    PythonDistribution.extension_module_variants(env env, this) {
+        let mut this = this.downcast_mut::<PythonDistributionValue>().unwrap().unwrap();
+        this.extension_module_variants_starlark(&env)
+    }
+
     PythonDistribution.make_python_interpreter_config(env env, this) {
         let mut this = this.downcast_mut::<PythonDistributionValue>().unwrap().unwrap();
         this.make_python_interpreter_config_starlark(&env)
@@ -449,8 +557,22 @@ starlark_module! { python_distribution_module =>
         this,
         name: String,
         packaging_policy=NoneType::None,
-        config=NoneType::None
+        config=NoneType::None,
+        host_distribution=NoneType::None
     ) {
+        // Resolved before borrowing `this` because it may be the same value.
+        let host_distribution = if host_distribution.get_type() == "NoneType" {
+            None
+        } else {
+            required_type_arg("host_distribution", "PythonDistribution", &host_distribution)?;
+            Some(
+                host_distribution
+                    .downcast_mut::<PythonDistributionValue>()?
+                    .ok_or(ValueError::IncorrectParameterType)?
+                    .resolve_distribution(&env, "to_python_executable()")?,
+            )
+        };
+
         let mut this = this.downcast_mut::<PythonDistributionValue>().unwrap().unwrap();
         this.to_python_executable_starlark(
             &env,
@@ -458,6 +580,7 @@ starlark_module! { python_distribution_module =>
             name,
             &packaging_policy,
             &config,
+            host_distribution,
         )
     }
 
@@ -470,6 +593,14 @@ starlark_module! { python_distribution_module =>
     ) {
         PythonDistributionValue::default_python_distribution(&env, flavor, &build_target, &python_version)
     }
+
+    set_distribution_mirror(env env, url_template: String) {
+        PythonDistributionValue::set_distribution_mirror(&env, url_template)
+    }
+
+    system_python(version: String) {
+        PythonDistributionValue::system_python(version)
+    }
 }
 
 #[cfg(test)]
@@ -545,6 +676,25 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[cfg(not(target_env = "musl"))]
+    fn test_default_python_distribution_pgo() {
+        let dist = starlark_ok("default_python_distribution(flavor='pgo')");
+        assert_eq!(dist.get_type(), "PythonDistribution");
+
+        let host_distribution = PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                crate::project_building::HOST,
+                &DistributionFlavor::StandalonePgo,
+                None,
+            )
+            .unwrap();
+        assert!(host_distribution.pgo);
+
+        let x = dist.downcast_ref::<PythonDistributionValue>().unwrap();
+        assert_eq!(x.source, host_distribution.location)
+    }
+
     #[test]
     #[cfg(windows)]
     fn test_default_python_distribution_dynamic_windows() {
@@ -563,6 +713,72 @@ mod tests {
         assert_eq!(x.source, host_distribution.location)
     }
 
+    #[test]
+    fn test_set_distribution_mirror() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("set_distribution_mirror('https://mirror.example.com/{filename}')")?;
+        env.eval("set_distribution_mirror('https://other.example.com/python')")?;
+
+        let context_value = env.pyoxidizer_context_value().unwrap();
+        let context = context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .unwrap();
+        assert_eq!(
+            context.distribution_mirrors,
+            vec![
+                "https://mirror.example.com/{filename}".to_string(),
+                "https://other.example.com/python".to_string(),
+            ]
+        );
+
+        assert!(env.eval("set_distribution_mirror('not a url')").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    // Python 3.8 not supported on aarch64.
+    #[cfg(not(target_arch = "aarch64"))]
+    fn test_to_python_executable_host_distribution() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("dist = default_python_distribution(python_version='3.9')")?;
+        let exe = env.eval("dist.to_python_executable('myapp', host_distribution = dist)")?;
+        assert_eq!(exe.get_type(), "PythonExecutable");
+
+        env.eval("host = default_python_distribution(python_version='3.8')")?;
+        assert!(env
+            .eval("dist.to_python_executable('myapp', host_distribution = host)")
+            .is_err());
+        assert!(env
+            .eval("dist.to_python_executable('myapp', host_distribution = 'host')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_python() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        let dist = env.eval("system_python('3.9')")?;
+        assert_eq!(dist.get_type(), "PythonDistribution");
+
+        let x = dist.downcast_ref::<PythonDistributionValue>().unwrap();
+        assert_eq!(
+            x.source,
+            PythonDistributionLocation::System {
+                python_major_minor_version: "3.9".to_string(),
+            }
+        );
+
+        assert!(env.eval("system_python('3')").is_err());
+        assert!(env.eval("system_python('3.x')").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_python_distribution_no_args() {
         let err = starlark_nok("PythonDistribution()");
@@ -613,6 +829,17 @@ mod tests {
         assert_eq!(config.get_type(), "PythonInterpreterConfig");
     }
 
+    #[test]
+    fn test_extension_module_variants() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("variants = default_python_distribution().extension_module_variants()")?;
+        eval_assert(&mut env, "type(variants) == 'dict'")?;
+        eval_assert(&mut env, "len(variants['_ssl']) >= 1")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_python_resources() {
         let resources = starlark_ok("default_python_distribution().python_resources()");
@@ -4,7 +4,7 @@
 
 use {
     crate::{
-        py_packaging::binary::PythonBinaryBuilder,
+        py_packaging::binary::{BuildSystem, PythonBinaryBuilder},
         starlark::env::{get_context, PyOxidizerEnvironmentContext},
     },
     anyhow::{anyhow, Result},
@@ -12,7 +12,10 @@ use {
     starlark::{
         environment::TypeValues,
         values::{
-            error::{RuntimeError, ValueError},
+            error::{
+                RuntimeError, UnsupportedOperation, ValueError, INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+            },
+            none::NoneType,
             {Mutable, TypedValue, Value, ValueResult},
         },
         {
@@ -21,11 +24,14 @@ use {
         },
     },
     starlark_dialect_build_targets::{ResolvedTarget, ResolvedTargetValue, RunMode},
-    std::sync::Arc,
+    std::{convert::TryFrom, sync::Arc},
 };
 
 pub struct PythonEmbeddedResourcesValue {
     pub exe: Arc<dyn PythonBinaryBuilder>,
+
+    /// Build system to write build files for.
+    pub build_system: Option<BuildSystem>,
 }
 
 impl TypedValue for PythonEmbeddedResourcesValue {
@@ -35,6 +41,51 @@ impl TypedValue for PythonEmbeddedResourcesValue {
     fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
         Box::new(std::iter::empty())
     }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        match attribute {
+            "build_system" => match &self.build_system {
+                Some(value) => Ok(Value::from(value.to_string())),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            _ => Err(ValueError::OperationNotSupported {
+                op: UnsupportedOperation::GetAttr(attribute.to_string()),
+                left: Self::TYPE.to_string(),
+                right: None,
+            }),
+        }
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(matches!(attribute, "build_system"))
+    }
+
+    fn set_attr(&mut self, attribute: &str, value: Value) -> Result<(), ValueError> {
+        match attribute {
+            "build_system" => {
+                self.build_system = if value.get_type() == "NoneType" {
+                    None
+                } else {
+                    Some(
+                        BuildSystem::try_from(value.to_string().as_str()).map_err(|e| {
+                            ValueError::from(RuntimeError {
+                                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                                message: e,
+                                label: format!("{}.{}", Self::TYPE, attribute),
+                            })
+                        })?,
+                    )
+                };
+
+                Ok(())
+            }
+            _ => Err(ValueError::OperationNotSupported {
+                op: UnsupportedOperation::SetAttr(attribute.to_string()),
+                left: Self::TYPE.to_string(),
+                right: None,
+            }),
+        }
+    }
 }
 
 impl PythonEmbeddedResourcesValue {
@@ -60,6 +111,10 @@ impl PythonEmbeddedResourcesValue {
 
         embedded.write_files(&output_path)?;
 
+        if let Some(build_system) = self.build_system {
+            embedded.write_build_files(&output_path, build_system)?;
+        }
+
         Ok(ResolvedTarget {
             run_mode: RunMode::None,
             output_path,
@@ -96,3 +151,36 @@ starlark_module! { python_embedded_resources_module =>
         this.build_starlark(env, target)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::super::testutil::*, super::*};
+
+    #[test]
+    fn test_build_system() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+        env.eval("resources = exe.to_embedded_resources()")?;
+
+        let value = env.eval("resources.build_system")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        env.eval("resources.build_system = 'bazel'")?;
+        let value = env.eval("resources.build_system")?;
+        assert_eq!(value.to_string(), "bazel");
+
+        let value = env.eval("resources")?;
+        let resources = value
+            .downcast_ref::<PythonEmbeddedResourcesValue>()
+            .unwrap();
+        assert_eq!(resources.build_system, Some(BuildSystem::Bazel));
+
+        assert!(env.eval("resources.build_system = 'make'").is_err());
+
+        env.eval("resources.build_system = None")?;
+        let value = env.eval("resources.build_system")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        Ok(())
+    }
+}
@@ -4,25 +4,45 @@
 
 use {
     super::{
-        env::{get_context, PyOxidizerEnvironmentContext},
+        env::{get_context, record_input_path, PyOxidizerEnvironmentContext},
         file_resource::file_manifest_add_python_executable,
+        jupyter_kernel::JupyterKernelValue,
         python_embedded_resources::PythonEmbeddedResourcesValue,
+        python_executable_tests::PythonExecutableTestsValue,
         python_packaging_policy::PythonPackagingPolicyValue,
         python_resource::{
             is_resource_starlark_compatible, python_resource_to_value, FileValue,
-            PythonExtensionModuleValue, PythonModuleSourceValue,
+            PythonExtensionModuleValue, PythonModuleBytecodeValue, PythonModuleSourceValue,
             PythonPackageDistributionResourceValue, PythonPackageResourceValue,
             ResourceCollectionContext,
         },
+        python_zipapp::PythonZipAppValue,
+        shared_resources::SharedPythonResourcesValue,
     },
     crate::{
+        import_verification::{compat_packages_in_executable, verify_imports},
         project_building::build_python_executable,
         py_packaging::binary::PythonBinaryBuilder,
-        py_packaging::binary::{PackedResourcesLoadMode, WindowsRuntimeDllsMode},
+        py_packaging::binary::{
+            parse_windows_version, validate_entry_point, EmbeddedPythonContext,
+            ExecutableCompression, PackedResourcesLoadMode, WindowsRuntimeDllsMode, WindowsService,
+            WindowsVersionInfo,
+        },
+        py_packaging::console_scripts::{
+            console_scripts_launcher_source, CONSOLE_SCRIPTS_GROUP, CONSOLE_SCRIPTS_MODULE,
+        },
+        py_packaging::filtering::ResourceFilter,
+        py_packaging::jupyter_kernel::validate_kernel_name,
+        py_packaging::shell_completions::{generate_shell_completions, CompletionShell},
+        py_packaging::zipapp::DEFAULT_INTERPRETER,
     },
     anyhow::{anyhow, Context, Result},
     linked_hash_map::LinkedHashMap,
-    python_packaging::resource::PythonModuleSource,
+    python_packaging::{
+        interpreter::{RemoteResourcesConfig, SelfUpdateConfig},
+        package_metadata::parse_entry_points,
+        resource::PythonModuleSource,
+    },
     slog::{info, warn},
     starlark::{
         environment::TypeValues,
@@ -40,11 +60,12 @@ use {
         },
     },
     starlark_dialect_build_targets::{
-        optional_dict_arg, optional_list_arg, optional_type_arg, required_list_arg, ResolvedTarget,
-        ResolvedTargetValue, RunMode, ToOptional,
+        optional_dict_arg, optional_int_arg, optional_list_arg, optional_str_arg,
+        optional_type_arg, required_list_arg, ResolvedTarget, ResolvedTargetValue, RunMode,
+        ToOptional,
     },
     std::{
-        collections::HashMap,
+        collections::{HashMap, HashSet},
         convert::TryFrom,
         io::Write,
         ops::Deref,
@@ -54,7 +75,7 @@ use {
         file_resource::FileManifestValue, wix_bundle_builder::WiXBundleBuilderValue,
         wix_msi_builder::WiXMsiBuilderValue,
     },
-    tugger_file_manifest::FileData,
+    tugger_file_manifest::{FileData, FileManifest},
 };
 
 /// Represents a builder for a Python executable.
@@ -66,6 +87,21 @@ pub struct PythonExecutableValue {
     // values_for_descendant_check_and_freeze() without the borrow checker
     // complaining due to a temporary vec/array.
     policy: Vec<Value>,
+
+    /// Modules to verify can be imported after the executable is built.
+    verify_imports: Vec<String>,
+
+    /// Whether imports must be satisfied by packed resources after the executable is built.
+    require_packed_imports: bool,
+
+    /// Template of the path to copy the built executable to.
+    artifact_name_template: Option<String>,
+
+    /// Version substituted into `artifact_name_template`.
+    artifact_version: Option<String>,
+
+    /// Keys of resources added from `SharedPythonResources`.
+    shared_resources: HashSet<String>,
 }
 
 impl PythonExecutableValue {
@@ -73,6 +109,11 @@ impl PythonExecutableValue {
         Self {
             exe,
             policy: vec![Value::new(policy)],
+            verify_imports: vec![],
+            require_packed_imports: false,
+            artifact_name_template: None,
+            artifact_version: None,
+            shared_resources: HashSet::new(),
         }
     }
 
@@ -90,38 +131,266 @@ impl PythonExecutableValue {
         target: &str,
         context: &PyOxidizerEnvironmentContext,
     ) -> Result<ResolvedTarget> {
-        // Build an executable by writing out a temporary Rust project
-        // and building it.
-        let build = build_python_executable(
-            context.logger(),
-            &self.exe.name(),
-            self.exe.deref(),
-            &context.build_target_triple,
-            &context.build_opt_level,
-            context.build_release,
-        )?;
+        let mut modules = compat_packages_in_executable(self.exe.deref());
+        modules.extend(self.verify_imports.iter().cloned());
+
+        // Modules the executable runs are its entry points and must also be packed.
+        if self.require_packed_imports {
+            let config = self.exe.python_interpreter_config();
+            modules.extend(config.config.run_module.iter().cloned());
+            modules.extend(config.multicall_run_modules.values().cloned());
+        }
+
+        let (built, binary_data) =
+            build_python_executable_target(type_values, target, context, self.exe.deref())?;
+
+        if let RunMode::Path { path } = &built.run_mode {
+            verify_imports(
+                context.logger(),
+                self.exe.deref(),
+                path,
+                &binary_data,
+                &modules,
+                self.require_packed_imports,
+                &built.output_path.join("verify-imports"),
+                &context.build_opt_level,
+                context.build_release,
+            )?;
+        }
+
+        if let (Some(template), RunMode::Path { path }) =
+            (&self.artifact_name_template, &built.run_mode)
+        {
+            let artifact_path = context.cwd.join(expand_artifact_name_template(
+                template,
+                &self.exe.name(),
+                &context.build_target_triple,
+                self.artifact_version.as_deref(),
+                context.build_release,
+            )?);
+
+            warn!(
+                context.logger(),
+                "copying executable to {}",
+                artifact_path.display()
+            );
+            if let Some(parent) = artifact_path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("creating {}", parent.display()))?;
+            }
+            std::fs::copy(path, &artifact_path)
+                .with_context(|| format!("copying executable to {}", artifact_path.display()))?;
+        }
+
+        Ok(built)
+    }
+}
+
+/// Whether a string is usable as the file name of a built executable.
+///
+/// Names can't be empty, contain path separators, refer to the current or
+/// parent directory, or be a reserved device name on Windows.
+fn is_valid_executable_name(name: &str) -> bool {
+    if name.is_empty() || name == "." || name == ".." || name.contains(&['/', '\\'][..]) {
+        return false;
+    }
+
+    // Windows reserves device names regardless of case or extension.
+    let stem = name.split('.').next().unwrap_or_default().to_uppercase();
+
+    !(matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && matches!(stem.as_bytes()[3], b'1'..=b'9')))
+}
+
+/// Expand a template of the path of an executable artifact.
+///
+/// Recognized placeholders are `{name}`, `{target}`, `{version}`, `{profile}`,
+/// and `{ext}`. If `version` is `None`, `{version}` is an error.
+pub fn expand_artifact_name_template(
+    template: &str,
+    name: &str,
+    target_triple: &str,
+    version: Option<&str>,
+    release: bool,
+) -> Result<String> {
+    let mut res = String::new();
+    let mut remaining = template;
+
+    while let Some(start) = remaining.find('{') {
+        res.push_str(&remaining[..start]);
+
+        let end = remaining[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("unterminated placeholder in {}", template))?
+            + start;
+
+        res.push_str(match &remaining[start + 1..end] {
+            "name" => name,
+            "target" => target_triple,
+            "version" => version.ok_or_else(|| {
+                anyhow!(
+                    "artifact_version must be set to use {{version}} in {}",
+                    template
+                )
+            })?,
+            "profile" => {
+                if release {
+                    "release"
+                } else {
+                    "debug"
+                }
+            }
+            "ext" => {
+                if target_triple.contains("pc-windows") {
+                    ".exe"
+                } else {
+                    ""
+                }
+            }
+            placeholder => {
+                return Err(anyhow!(
+                    "unknown placeholder {{{}}} in {}",
+                    placeholder,
+                    template
+                ))
+            }
+        });
 
-        let output_path = context
-            .get_output_path(type_values, target)
-            .map_err(|_| anyhow!("unable to resolve output path"))?;
-        let dest_path = output_path.join(build.exe_name);
+        remaining = &remaining[end + 1..];
+    }
+
+    res.push_str(remaining);
+
+    Ok(res)
+}
+
+/// Generate shell completion scripts by running a Python executable.
+///
+/// The executable is built and installed into a temporary directory, along
+/// with the files installed next to it, and then run for each shell.
+fn build_shell_completions(
+    context: &PyOxidizerEnvironmentContext,
+    exe: &dyn PythonBinaryBuilder,
+    prog_name: &str,
+    shells: &[CompletionShell],
+    args: Option<&[String]>,
+    prefix: &Path,
+) -> Result<FileManifest> {
+    if context.build_target_triple != env!("HOST") {
+        return Err(anyhow!(
+            "unable to generate shell completions when cross-compiling"
+        ));
+    }
+
+    let build = build_python_executable(
+        context.logger(),
+        &exe.name(),
+        exe,
+        &context.build_target_triple,
+        &context.build_opt_level,
+        context.build_release,
+    )?;
+
+    let temp_dir = crate::environment::temp_dir(exe.cache_path(), "pyoxidizer-completions")?;
+
+    // Files installed above the executable's directory must stay in the
+    // temporary directory too.
+    let exe_dir = (0..build.binary_data.external_files_depth())
+        .fold(temp_dir.path().to_path_buf(), |path, _| path.join("bin"));
+
+    build.binary_data.materialize_files(&exe_dir)?;
+
+    let exe_path = exe_dir.join(&build.exe_name);
+    let mut fh = std::fs::File::create(&exe_path)
+        .with_context(|| format!("creating {}", exe_path.display()))?;
+    fh.write_all(&build.exe_data)
+        .with_context(|| format!("writing {}", exe_path.display()))?;
+    tugger_file_manifest::set_executable(&mut fh).context("making binary executable")?;
+    drop(fh);
+
+    generate_shell_completions(context.logger(), &exe_path, prog_name, shells, args, prefix)
+}
+
+/// Record arguments to pip naming existing files or directories as inputs.
+///
+/// These are local projects and requirements files.
+fn record_pip_input_paths(type_values: &TypeValues, args: &[String]) -> Result<(), ValueError> {
+    let cwd = {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        pyoxidizer_context.cwd.clone()
+    };
+
+    for arg in args {
+        if !arg.starts_with('-') && cwd.join(arg).exists() {
+            record_input_path(type_values, Path::new(arg))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Build a Python executable and write it to the build path of a target.
+///
+/// Returns the resolved target and the data embedded in the executable. The
+/// files to install with the executable aren't written.
+pub fn build_python_executable_target<'a>(
+    type_values: &TypeValues,
+    target: &str,
+    context: &PyOxidizerEnvironmentContext,
+    exe: &'a (dyn PythonBinaryBuilder + 'a),
+) -> Result<(ResolvedTarget, EmbeddedPythonContext<'a>)> {
+    // Build an executable by writing out a temporary Rust project
+    // and building it.
+    let build = build_python_executable(
+        context.logger(),
+        &exe.name(),
+        exe,
+        &context.build_target_triple,
+        &context.build_opt_level,
+        context.build_release,
+    )?;
+
+    let output_path = context
+        .get_output_path(type_values, target)
+        .map_err(|_| anyhow!("unable to resolve output path"))?;
+    let dest_path = output_path.join(build.exe_name);
+    warn!(
+        context.logger(),
+        "writing executable to {}",
+        dest_path.display()
+    );
+    let mut fh =
+        std::fs::File::create(&dest_path).context(format!("creating {}", dest_path.display()))?;
+    fh.write_all(&build.exe_data)
+        .context(format!("writing {}", dest_path.display()))?;
+
+    tugger_file_manifest::set_executable(&mut fh).context("making binary executable")?;
+
+    if !build.debug_files.is_empty() {
         warn!(
             context.logger(),
-            "writing executable to {}",
-            dest_path.display()
+            "writing debug information to {}",
+            output_path.display()
         );
-        let mut fh = std::fs::File::create(&dest_path)
-            .context(format!("creating {}", dest_path.display()))?;
-        fh.write_all(&build.exe_data)
-            .context(format!("writing {}", dest_path.display()))?;
-
-        tugger_file_manifest::set_executable(&mut fh).context("making binary executable")?;
+        build
+            .debug_files
+            .materialize_files(&output_path)
+            .context("writing debug information")?;
+    }
 
-        Ok(ResolvedTarget {
+    Ok((
+        ResolvedTarget {
             run_mode: RunMode::Path { path: dest_path },
             output_path,
-        })
-    }
+        },
+        build.binary_data,
+    ))
 }
 
 impl TypedValue for PythonExecutableValue {
@@ -136,9 +405,38 @@ impl TypedValue for PythonExecutableValue {
 
     fn get_attr(&self, attribute: &str) -> ValueResult {
         match attribute {
+            "artifact_name_template" => match &self.artifact_name_template {
+                Some(value) => Ok(Value::from(value.to_string())),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "artifact_version" => match &self.artifact_version {
+                Some(value) => Ok(Value::from(value.to_string())),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "executable_compression" => {
+                Ok(Value::from(self.exe.executable_compression().to_string()))
+            }
+            "frozen_modules" => Ok(Value::from(
+                self.exe
+                    .frozen_modules()
+                    .iter()
+                    .map(|x| Value::from(x.as_str()))
+                    .collect::<Vec<_>>(),
+            )),
+            "name" => Ok(Value::from(self.exe.name())),
             "packed_resources_load_mode" => Ok(Value::from(
                 self.exe.packed_resources_load_mode().to_string(),
             )),
+            "relative_path_module_search_paths" => {
+                Ok(Value::from(self.exe.relative_path_module_search_paths()))
+            }
+            "require_packed_imports" => Ok(Value::from(self.require_packed_imports)),
+            "rust_pre_init_hook" => match &self.exe.rust_project().pre_init_hook {
+                Some(value) => Ok(Value::from(value.to_string())),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "split_debug_info" => Ok(Value::from(self.exe.debug_info().split)),
+            "strip_symbols" => Ok(Value::from(self.exe.debug_info().strip)),
             "tcl_files_path" => match self.exe.tcl_files_path() {
                 Some(value) => Ok(Value::from(value.to_string())),
                 None => Ok(Value::from(NoneType::None)),
@@ -158,7 +456,17 @@ impl TypedValue for PythonExecutableValue {
     fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
         Ok(matches!(
             attribute,
-            "packed_resources_load_mode"
+            "artifact_name_template"
+                | "artifact_version"
+                | "executable_compression"
+                | "frozen_modules"
+                | "name"
+                | "packed_resources_load_mode"
+                | "relative_path_module_search_paths"
+                | "require_packed_imports"
+                | "rust_pre_init_hook"
+                | "split_debug_info"
+                | "strip_symbols"
                 | "tcl_files_path"
                 | "windows_runtime_dlls_mode"
                 | "windows_subsystem"
@@ -167,6 +475,70 @@ impl TypedValue for PythonExecutableValue {
 
     fn set_attr(&mut self, attribute: &str, value: Value) -> Result<(), ValueError> {
         match attribute {
+            "artifact_name_template" => {
+                let template: Option<String> = value.to_optional();
+
+                if let Some(template) = &template {
+                    // Validate placeholders by expanding with dummy values.
+                    expand_artifact_name_template(template, "", "", Some(""), false).map_err(
+                        |e| {
+                            ValueError::from(RuntimeError {
+                                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                                message: e.to_string(),
+                                label: format!("{}.{}", Self::TYPE, attribute),
+                            })
+                        },
+                    )?;
+                }
+
+                self.artifact_name_template = template;
+
+                Ok(())
+            }
+            "artifact_version" => {
+                self.artifact_version = value.to_optional();
+
+                Ok(())
+            }
+            "executable_compression" => {
+                self.exe.set_executable_compression(
+                    ExecutableCompression::try_from(value.to_string().as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e,
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?,
+                );
+
+                Ok(())
+            }
+            "frozen_modules" => {
+                optional_list_arg(attribute, "string", &value)?;
+
+                self.exe.set_frozen_modules(if value.get_type() == "list" {
+                    value.iter()?.iter().map(|x| x.to_string()).collect()
+                } else {
+                    vec![]
+                });
+
+                Ok(())
+            }
+            "name" => {
+                let name = value.to_string();
+
+                if !is_valid_executable_name(&name) {
+                    return Err(ValueError::from(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: format!("invalid executable name: {:?}", name),
+                        label: format!("{}.{}", Self::TYPE, attribute),
+                    }));
+                }
+
+                self.exe.set_name(&name);
+
+                Ok(())
+            }
             "packed_resources_load_mode" => {
                 self.exe.set_packed_resources_load_mode(
                     PackedResourcesLoadMode::try_from(value.to_string().as_str()).map_err(|e| {
@@ -180,6 +552,36 @@ impl TypedValue for PythonExecutableValue {
 
                 Ok(())
             }
+            "relative_path_module_search_paths" => {
+                self.exe
+                    .set_relative_path_module_search_paths(value.to_bool());
+
+                Ok(())
+            }
+            "require_packed_imports" => {
+                self.require_packed_imports = value.to_bool();
+
+                Ok(())
+            }
+            "rust_pre_init_hook" => {
+                self.exe.rust_project_mut().pre_init_hook = value.to_optional();
+
+                Ok(())
+            }
+            "split_debug_info" => {
+                let mut debug_info = *self.exe.debug_info();
+                debug_info.split = value.to_bool();
+                self.exe.set_debug_info(debug_info);
+
+                Ok(())
+            }
+            "strip_symbols" => {
+                let mut debug_info = *self.exe.debug_info();
+                debug_info.strip = value.to_bool();
+                self.exe.set_debug_info(debug_info);
+
+                Ok(())
+            }
             "tcl_files_path" => {
                 self.exe.set_tcl_files_path(value.to_optional());
 
@@ -276,6 +678,7 @@ impl PythonExecutableValue {
         required_list_arg("args", "string", &args)?;
 
         let args: Vec<String> = args.iter()?.iter().map(|x| x.to_string()).collect();
+        record_pip_input_paths(type_values, &args)?;
 
         let pyoxidizer_context_value = get_context(type_values)?;
         let pyoxidizer_context = pyoxidizer_context_value
@@ -333,6 +736,8 @@ impl PythonExecutableValue {
             _ => panic!("should have validated type above"),
         };
 
+        record_pip_input_paths(type_values, &args)?;
+
         let pyoxidizer_context_value = get_context(type_values)?;
         let pyoxidizer_context = pyoxidizer_context_value
             .downcast_ref::<PyOxidizerEnvironmentContext>()
@@ -379,6 +784,8 @@ impl PythonExecutableValue {
             .map(|x| x.to_string())
             .collect::<Vec<String>>();
 
+        record_input_path(type_values, Path::new(&path))?;
+
         let pyoxidizer_context_value = get_context(type_values)?;
         let pyoxidizer_context = pyoxidizer_context_value
             .downcast_ref::<PyOxidizerEnvironmentContext>()
@@ -404,6 +811,40 @@ impl PythonExecutableValue {
         Ok(Value::from(resources))
     }
 
+    /// PythonExecutable.read_conda_env(path)
+    pub fn read_conda_env(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        path: String,
+    ) -> ValueResult {
+        record_input_path(type_values, Path::new(&path))?;
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let python_packaging_policy = self.python_packaging_policy();
+
+        let resources = self
+            .exe
+            .read_conda_env(pyoxidizer_context.logger(), &Path::new(&path))
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "CONDA_ERROR",
+                    message: format!("could not find resources: {:?}", e),
+                    label: "read_conda_env()".to_string(),
+                })
+            })?
+            .iter()
+            .filter(|r| is_resource_starlark_compatible(r))
+            .map(|r| python_resource_to_value(type_values, call_stack, r, &python_packaging_policy))
+            .collect::<Result<Vec<Value>, ValueError>>()?;
+
+        Ok(Value::from(resources))
+    }
+
     /// PythonExecutable.read_virtualenv(path)
     pub fn read_virtualenv(
         &mut self,
@@ -411,6 +852,8 @@ impl PythonExecutableValue {
         call_stack: &mut CallStack,
         path: String,
     ) -> ValueResult {
+        record_input_path(type_values, Path::new(&path))?;
+
         let pyoxidizer_context_value = get_context(type_values)?;
         let pyoxidizer_context = pyoxidizer_context_value
             .downcast_ref::<PyOxidizerEnvironmentContext>()
@@ -472,6 +915,7 @@ impl PythonExecutableValue {
         };
 
         let package_path = PathBuf::from(package_path);
+        record_input_path(type_values, &package_path)?;
 
         let pyoxidizer_context_value = get_context(type_values)?;
         let pyoxidizer_context = pyoxidizer_context_value
@@ -516,6 +960,88 @@ impl PythonExecutableValue {
         Ok(Value::from(resources))
     }
 
+    /// PythonExecutable.cythonize(package_path, sources, language_level="3", cython_requirement="Cython", extra_envs=None)
+    #[allow(clippy::too_many_arguments)]
+    pub fn cythonize(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        package_path: String,
+        sources: &Value,
+        language_level: String,
+        cython_requirement: String,
+        extra_envs: &Value,
+    ) -> ValueResult {
+        required_list_arg("sources", "string", &sources)?;
+        optional_dict_arg("extra_envs", "string", "string", &extra_envs)?;
+
+        let sources = sources
+            .iter()?
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        let extra_envs = match extra_envs.get_type() {
+            "dict" => extra_envs
+                .iter()?
+                .iter()
+                .map(|key| {
+                    let k = key.to_string();
+                    let v = extra_envs.at(key).unwrap().to_string();
+                    (k, v)
+                })
+                .collect(),
+            "NoneType" => HashMap::new(),
+            _ => panic!("should have validated type above"),
+        };
+
+        let package_path = PathBuf::from(package_path);
+        record_input_path(type_values, &package_path)?;
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let package_path = if package_path.is_absolute() {
+            package_path
+        } else {
+            PathBuf::from(&pyoxidizer_context.cwd).join(package_path)
+        };
+
+        let python_packaging_policy = self.python_packaging_policy();
+
+        let resources = self
+            .exe
+            .cythonize(
+                pyoxidizer_context.logger(),
+                &package_path,
+                &sources,
+                &language_level,
+                &cython_requirement,
+                pyoxidizer_context.verbose,
+                &extra_envs,
+            )
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "CYTHONIZE_ERROR",
+                    message: format!("{:?}", e),
+                    label: "cythonize()".to_string(),
+                })
+            })?
+            .iter()
+            .filter(|r| is_resource_starlark_compatible(r))
+            .map(|r| python_resource_to_value(type_values, call_stack, r, &python_packaging_policy))
+            .collect::<Result<Vec<Value>, ValueError>>()?;
+
+        warn!(
+            pyoxidizer_context.logger(),
+            "collected {} resources from cythonize",
+            resources.len()
+        );
+
+        Ok(Value::from(resources))
+    }
+
     pub fn add_python_module_source(
         &mut self,
         context: &PyOxidizerEnvironmentContext,
@@ -540,6 +1066,30 @@ impl PythonExecutableValue {
         Ok(Value::new(NoneType::None))
     }
 
+    pub fn add_python_module_bytecode(
+        &mut self,
+        context: &PyOxidizerEnvironmentContext,
+        label: &str,
+        module: &PythonModuleBytecodeValue,
+    ) -> ValueResult {
+        info!(
+            context.logger(),
+            "adding Python bytecode module {}", module.inner.name;
+        );
+        self.exe
+            .add_python_module_bytecode(&module.inner, module.add_collection_context().clone())
+            .with_context(|| format!("adding {}", module.to_repr()))
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:?}", e),
+                    label: label.to_string(),
+                })
+            })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
     pub fn add_python_package_resource(
         &mut self,
         context: &PyOxidizerEnvironmentContext,
@@ -663,6 +1213,12 @@ impl PythonExecutableValue {
                 let module = resource.downcast_ref::<PythonModuleSourceValue>().unwrap();
                 self.add_python_module_source(pyoxidizer_context.deref(), label, module.deref())
             }
+            PythonModuleBytecodeValue::TYPE => {
+                let module = resource
+                    .downcast_ref::<PythonModuleBytecodeValue>()
+                    .unwrap();
+                self.add_python_module_bytecode(pyoxidizer_context.deref(), label, module.deref())
+            }
             PythonPackageResourceValue::TYPE => {
                 let r = resource
                     .downcast_ref::<PythonPackageResourceValue>()
@@ -706,541 +1262,2140 @@ impl PythonExecutableValue {
         Ok(Value::new(NoneType::None))
     }
 
-    /// PythonExecutable.to_embedded_resources()
-    pub fn to_embedded_resources(&self) -> ValueResult {
-        Ok(Value::new(PythonEmbeddedResourcesValue {
-            exe: self.exe.clone_trait(),
-        }))
-    }
+    /// PythonExecutable.add_shared_resources(resources)
+    pub fn add_shared_resources(
+        &mut self,
+        type_values: &TypeValues,
+        resources: &Value,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.add_shared_resources()";
 
-    /// PythonExecutable.to_file_manifest(prefix)
-    pub fn to_file_manifest(&self, type_values: &TypeValues, prefix: String) -> ValueResult {
-        let pyoxidizer_context_value = get_context(type_values)?;
-        let pyoxidizer_context = pyoxidizer_context_value
-            .downcast_ref::<PyOxidizerEnvironmentContext>()
-            .ok_or(ValueError::IncorrectParameterType)?;
+        let resources = match resources.get_type() {
+            SharedPythonResourcesValue::TYPE => resources
+                .downcast_ref::<SharedPythonResourcesValue>()
+                .unwrap()
+                .resources
+                .clone(),
+            _ => {
+                return Err(ValueError::from(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: "resources argument must be a SharedPythonResources".to_string(),
+                    label: LABEL.to_string(),
+                }))
+            }
+        };
 
-        let manifest_value = FileManifestValue::new_from_args()?;
-        let mut manifest = manifest_value
-            .downcast_mut::<FileManifestValue>()
-            .unwrap()
-            .unwrap();
+        // Sets are typically added by every target that uses them. Only add
+        // resources that haven't already been added from a set.
+        for resource in &resources {
+            let key = SharedPythonResourcesValue::resource_key(resource);
 
-        file_manifest_add_python_executable(
-            &mut manifest,
-            pyoxidizer_context.logger(),
-            &prefix,
-            self.exe.deref(),
-            &pyoxidizer_context.build_target_triple,
-            pyoxidizer_context.build_release,
-            &pyoxidizer_context.build_opt_level,
-        )
-        .map_err(|e| {
-            ValueError::from(RuntimeError {
-                code: "PYOXIDIZER_PYTHON_EXECUTABLE",
-                message: format!("{:?}", e),
-                label: "to_file_manifest()".to_string(),
-            })
-        })?;
+            if !self.shared_resources.contains(&key) {
+                self.add_python_resource(type_values, resource, LABEL)?;
+                self.shared_resources.insert(key);
+            }
+        }
 
-        Ok(manifest_value.clone())
+        Ok(Value::new(NoneType::None))
     }
 
-    /// PythonExecutable.to_wix_bundle_builder(id_prefix, name, version, manufacturer, msi_builder_callback)
-    #[allow(clippy::too_many_arguments)]
-    pub fn to_wix_bundle_builder(
-        &self,
+    /// PythonExecutable.add_file_manifest(manifest, location=None)
+    pub fn add_file_manifest(
+        &mut self,
         type_values: &TypeValues,
         call_stack: &mut CallStack,
-        id_prefix: String,
-        product_name: String,
-        product_version: String,
-        product_manufacturer: String,
-        msi_builder_callback: Value,
+        manifest: &Value,
+        location: &Value,
     ) -> ValueResult {
-        optional_type_arg("msi_builder_callback", "function", &msi_builder_callback)?;
+        const LABEL: &str = "PythonExecutable.add_file_manifest()";
+
+        let manifest = match manifest.get_type() {
+            FileManifestValue::TYPE => manifest.downcast_ref::<FileManifestValue>().unwrap(),
+            _ => {
+                return Err(ValueError::from(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: "manifest argument must be a FileManifest".to_string(),
+                    label: LABEL.to_string(),
+                }))
+            }
+        };
+        let location = optional_str_arg("location", &location)?;
 
-        let msi_builder_value = self.to_wix_msi_builder(
-            type_values,
-            id_prefix.clone(),
-            product_name.clone(),
-            product_version.clone(),
-            product_manufacturer.clone(),
-        )?;
+        let python_packaging_policy = self.python_packaging_policy();
 
-        if msi_builder_callback.get_type() == "function" {
-            msi_builder_callback.call(
-                call_stack,
-                type_values,
-                vec![msi_builder_value.clone()],
-                LinkedHashMap::new(),
-                None,
-                None,
-            )?;
+        let files = manifest.manifest.iter_files().collect::<Vec<_>>();
+
+        for file in files {
+            let mut value = FileValue::new(file);
+            python_packaging_policy.apply_to_resource(type_values, call_stack, &mut value)?;
+            value.set_attr("add_include", Value::from(true))?;
+            if let Some(location) = &location {
+                value.set_attr("add_location", Value::from(location.clone()))?;
+            }
+
+            self.add_python_resource(type_values, &Value::new(value), LABEL)?;
         }
 
-        let msi_builder = msi_builder_value
-            .downcast_ref::<WiXMsiBuilderValue>()
-            .unwrap();
+        Ok(Value::new(NoneType::None))
+    }
 
-        let bundle_builder_value = WiXBundleBuilderValue::new_from_args(
-            id_prefix,
-            product_name,
-            product_version,
-            product_manufacturer,
-        )?;
-        let mut bundle_builder = bundle_builder_value
-            .downcast_mut::<WiXBundleBuilderValue>()
-            .unwrap()
-            .unwrap();
+    /// PythonExecutable.add_console_scripts(resources)
+    pub fn add_console_scripts(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        resources: &Value,
+    ) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
 
-        // Add the VC++ Redistributable for the target platform.
-        match self.exe.target_triple() {
-            "i686-pc-windows-msvc" => {
-                bundle_builder.add_vc_redistributable(type_values, "x86".to_string())?;
+        let values = match resources.get_type() {
+            "list" => resources.iter()?.iter().collect::<Vec<_>>(),
+            PythonPackageDistributionResourceValue::TYPE => vec![resources.clone()],
+            t => {
+                return Err(ValueError::from(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: format!(
+                        "resources must be a list or PythonPackageDistributionResource; got {}",
+                        t
+                    ),
+                    label: "add_console_scripts()".to_string(),
+                }))
             }
-            "x86_64-pc-windows-msvc" => {
-                bundle_builder.add_vc_redistributable(type_values, "x64".to_string())?;
+        };
+
+        let mut entry_points = vec![];
+
+        // Non-distribution resources are ignored so the result of e.g.
+        // pip_install() can be passed in directly.
+        for value in values {
+            if value.get_type() != PythonPackageDistributionResourceValue::TYPE {
+                continue;
             }
-            _ => {}
+
+            let resource = value
+                .downcast_ref::<PythonPackageDistributionResourceValue>()
+                .unwrap();
+
+            if resource.inner.name != "entry_points.txt" {
+                continue;
+            }
+
+            let data = resource.inner.data.resolve().map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:?}", e),
+                    label: "add_console_scripts()".to_string(),
+                })
+            })?;
+
+            entry_points.extend(
+                parse_entry_points(&data)
+                    .with_context(|| format!("parsing entry points of {}", resource.inner.package))
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: format!("{:?}", e),
+                            label: "add_console_scripts()".to_string(),
+                        })
+                    })?,
+            );
         }
 
-        bundle_builder.add_wix_msi_builder(
-            msi_builder.deref().clone(),
+        let mut names = entry_points
+            .iter()
+            .filter(|ep| ep.group == CONSOLE_SCRIPTS_GROUP)
+            .map(|ep| ep.name.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+
+        if names.is_empty() {
+            warn!(
+                pyoxidizer_context.logger(),
+                "no console_scripts entry points found; not adding launcher"
+            );
+            return Ok(Value::from(Vec::<Value>::new()));
+        }
+
+        info!(
+            pyoxidizer_context.logger(),
+            "adding console scripts launcher for {}",
+            names.join(", ")
+        );
+
+        let module = self.make_python_module_source(
+            type_values,
+            call_stack,
+            CONSOLE_SCRIPTS_MODULE.to_string(),
+            console_scripts_launcher_source(entry_points.iter()),
             false,
-            Value::new(NoneType::None),
         )?;
+        self.add_python_resource(type_values, &module, "add_console_scripts()")?;
 
-        Ok(bundle_builder_value.clone())
+        let config = &mut self.exe.python_interpreter_config_mut().config;
+        if config.run_command.is_none()
+            && config.run_module.is_none()
+            && config.run_filename.is_none()
+        {
+            config.run_module = Some(CONSOLE_SCRIPTS_MODULE.to_string());
+        }
+
+        Ok(Value::from(
+            names.into_iter().map(Value::from).collect::<Vec<_>>(),
+        ))
     }
 
-    /// PythonExecutable.to_wix_msi_builder(id_prefix, product_name, product_version, product_manufacturer)
-    pub fn to_wix_msi_builder(
-        &self,
+    /// PythonExecutable.add_cargo_feature(feature)
+    pub fn add_cargo_feature(&mut self, feature: String) -> ValueResult {
+        let features = &mut self.exe.rust_project_mut().cargo_features;
+
+        if !features.contains(&feature) {
+            features.push(feature);
+        }
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// Resolve the path to an existing Rust source file.
+    fn resolve_rust_source(
         type_values: &TypeValues,
-        id_prefix: String,
-        product_name: String,
-        product_version: String,
-        product_manufacturer: String,
-    ) -> ValueResult {
-        let manifest_value = self.to_file_manifest(type_values, ".".to_string())?;
-        let manifest = manifest_value.downcast_ref::<FileManifestValue>().unwrap();
+        path: String,
+        label: &str,
+    ) -> Result<PathBuf, ValueError> {
+        let path = {
+            let pyoxidizer_context_value = get_context(type_values)?;
+            let pyoxidizer_context = pyoxidizer_context_value
+                .downcast_ref::<PyOxidizerEnvironmentContext>()
+                .ok_or(ValueError::IncorrectParameterType)?;
+
+            PathBuf::from(&pyoxidizer_context.cwd).join(path)
+        };
 
-        let builder_value = WiXMsiBuilderValue::new_from_args(
-            id_prefix,
-            product_name,
-            product_version,
-            product_manufacturer,
-        )?;
-        let mut builder = builder_value
-            .downcast_mut::<WiXMsiBuilderValue>()
-            .unwrap()
-            .unwrap();
+        record_input_path(type_values, &path)?;
 
-        builder.add_program_files_manifest(manifest.deref().clone())?;
+        if path.extension().and_then(|ext| ext.to_str()) != Some("rs") || !path.is_file() {
+            return Err(ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: format!("{} is not a Rust source file", path.display()),
+                label: label.to_string(),
+            }));
+        }
 
-        Ok(builder_value.clone())
+        Ok(path)
     }
 
-    /// PythonExecutable.filter_resources_from_files(files=None, glob_files=None)
-    pub fn filter_resources_from_files(
-        &mut self,
-        type_values: &TypeValues,
-        files: &Value,
-        glob_files: &Value,
-    ) -> ValueResult {
-        optional_list_arg("files", "string", &files)?;
-        optional_list_arg("glob_files", "string", &glob_files)?;
+    /// PythonExecutable.add_rust_source(path)
+    pub fn add_rust_source(&mut self, type_values: &TypeValues, path: String) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.add_rust_source()";
 
-        let files = match files.get_type() {
-            "list" => files
-                .iter()?
+        let path = Self::resolve_rust_source(type_values, path, LABEL)?;
+
+        // The file becomes a module named after it, so the name must be a
+        // valid identifier that doesn't collide with the crate root.
+        let module = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+
+        let valid = module
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !module.starts_with(|c: char| c.is_ascii_digit())
+            && !module.is_empty()
+            && module != "main";
+
+        let sources = &mut self.exe.rust_project_mut().sources;
+
+        if !valid
+            || sources
                 .iter()
-                .map(|x| PathBuf::from(x.to_string()))
-                .collect(),
-            "NoneType" => Vec::new(),
-            _ => panic!("type should have been validated above"),
-        };
+                .any(|source| source.file_stem() == path.file_stem())
+        {
+            return Err(ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: format!("{} is not a valid or unique Rust module name", module),
+                label: LABEL.to_string(),
+            }));
+        }
 
-        let glob_files = match glob_files.get_type() {
-            "list" => glob_files.iter()?.iter().map(|x| x.to_string()).collect(),
-            "NoneType" => Vec::new(),
-            _ => panic!("type should have been validated above"),
-        };
+        sources.push(path);
 
-        let files_refs = files.iter().map(|x| x.as_ref()).collect::<Vec<&Path>>();
-        let glob_files_refs = glob_files.iter().map(|x| x.as_ref()).collect::<Vec<&str>>();
+        Ok(Value::new(NoneType::None))
+    }
 
-        let pyoxidizer_context_value = get_context(type_values)?;
-        let pyoxidizer_context = pyoxidizer_context_value
-            .downcast_ref::<PyOxidizerEnvironmentContext>()
-            .ok_or(ValueError::IncorrectParameterType)?;
+    /// PythonExecutable.set_rust_main(path)
+    pub fn set_rust_main(&mut self, type_values: &TypeValues, path: String) -> ValueResult {
+        let path =
+            Self::resolve_rust_source(type_values, path, "PythonExecutable.set_rust_main()")?;
+
+        self.exe.rust_project_mut().main_path = Some(path);
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.link_args(args)
+    pub fn link_args(&mut self, args: &Value) -> ValueResult {
+        required_list_arg("args", "string", args)?;
+
+        let args = args
+            .iter()?
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+
+        // Arguments are passed to rustc via RUSTFLAGS, which is split on
+        // whitespace.
+        if let Some(arg) = args.iter().find(|arg| arg.contains(char::is_whitespace)) {
+            return Err(ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: format!("linker argument cannot contain whitespace: {}", arg),
+                label: "PythonExecutable.link_args()".to_string(),
+            }));
+        }
+
+        self.exe.rust_project_mut().link_args.extend(args);
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.linker(path, target_triple=None)
+    pub fn linker(&mut self, path: String, target_triple: &Value) -> ValueResult {
+        let target_triple = optional_str_arg("target_triple", target_triple)?
+            .unwrap_or_else(|| self.exe.target_triple().to_string());
 
         self.exe
-            .filter_resources_from_files(pyoxidizer_context.logger(), &files_refs, &glob_files_refs)
-            .map_err(|e| {
+            .rust_project_mut()
+            .linkers
+            .insert(target_triple, PathBuf::from(path));
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.static_crt(enabled)
+    pub fn static_crt(&mut self, enabled: bool) -> ValueResult {
+        self.exe.rust_project_mut().static_crt = Some(enabled);
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.run_tests(module="pytest", args=None)
+    pub fn run_tests(&self, module: String, args: &Value) -> ValueResult {
+        optional_list_arg("args", "string", args)?;
+
+        let args = if args.get_type() == "list" {
+            args.iter()?.iter().map(|x| x.to_string()).collect()
+        } else {
+            vec![]
+        };
+
+        Ok(Value::new(PythonExecutableTestsValue::new(
+            self.exe.deref(),
+            module,
+            args,
+        )))
+    }
+
+    /// PythonExecutable.verify_imports(modules)
+    pub fn verify_imports(&mut self, modules: &Value) -> ValueResult {
+        required_list_arg("modules", "string", modules)?;
+
+        for module in modules.iter()?.iter() {
+            let module = module.to_string();
+
+            if !self.verify_imports.contains(&module) {
+                self.verify_imports.push(module);
+            }
+        }
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.windows_version_info(file_version=None, product_version=None, company_name=None, file_description=None, product_name=None, copyright=None)
+    pub fn windows_version_info(
+        &mut self,
+        file_version: &Value,
+        product_version: &Value,
+        company_name: &Value,
+        file_description: &Value,
+        product_name: &Value,
+        copyright: &Value,
+    ) -> ValueResult {
+        let version_info = WindowsVersionInfo {
+            file_version: optional_str_arg("file_version", file_version)?,
+            product_version: optional_str_arg("product_version", product_version)?,
+            company_name: optional_str_arg("company_name", company_name)?,
+            file_description: optional_str_arg("file_description", file_description)?,
+            product_name: optional_str_arg("product_name", product_name)?,
+            copyright: optional_str_arg("copyright", copyright)?,
+        };
+
+        for version in version_info
+            .file_version
+            .iter()
+            .chain(version_info.product_version.iter())
+        {
+            parse_windows_version(version).map_err(|e| {
                 ValueError::from(RuntimeError {
-                    code: "RUNTIME_ERROR",
-                    message: format!("{:?}", e),
-                    label: "filter_from_files()".to_string(),
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: e.to_string(),
+                    label: "PythonExecutable.windows_version_info()".to_string(),
                 })
             })?;
+        }
+
+        self.exe.rust_project_mut().windows_version_info = Some(version_info);
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.self_update(manifest_url, public_key, version, staging_directory="$ORIGIN/.update")
+    pub fn self_update(
+        &mut self,
+        manifest_url: String,
+        public_key: String,
+        version: String,
+        staging_directory: String,
+    ) -> ValueResult {
+        let error = |message: String| {
+            ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message,
+                label: "PythonExecutable.self_update()".to_string(),
+            })
+        };
+
+        if !manifest_url.contains("://") {
+            return Err(error(format!("{} is not a URL", manifest_url)));
+        }
+
+        match hex::decode(&public_key) {
+            Ok(key) if key.len() == 32 => {}
+            _ => {
+                return Err(error(
+                    "public_key must be a hex encoded Ed25519 public key".to_string(),
+                ))
+            }
+        }
+
+        // Versions are compared by pyembed::parse_version().
+        if !version
+            .split('.')
+            .all(|c| !c.is_empty() && c.chars().all(|c| c.is_ascii_digit()))
+        {
+            return Err(error(format!(
+                "{:?} is not a version of dot delimited integers",
+                version
+            )));
+        }
+
+        self.exe.python_interpreter_config_mut().self_update = Some(SelfUpdateConfig {
+            manifest_url,
+            public_key: public_key.to_ascii_lowercase(),
+            version,
+            staging_directory: PathBuf::from(staging_directory),
+        });
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.remote_resources(index_url, public_key, packages, cache_directory="$ORIGIN/.remote-resources")
+    pub fn remote_resources(
+        &mut self,
+        index_url: String,
+        public_key: String,
+        packages: &Value,
+        cache_directory: String,
+    ) -> ValueResult {
+        required_list_arg("packages", "string", packages)?;
+
+        let error = |message: String| {
+            ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message,
+                label: "PythonExecutable.remote_resources()".to_string(),
+            })
+        };
+
+        if !index_url.starts_with("https://") {
+            return Err(error(format!("{} is not an https:// URL", index_url)));
+        }
+
+        match hex::decode(&public_key) {
+            Ok(key) if key.len() == 32 => {}
+            _ => {
+                return Err(error(
+                    "public_key must be a hex encoded Ed25519 public key".to_string(),
+                ))
+            }
+        }
+
+        let packages = packages
+            .iter()?
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+
+        if packages.is_empty() {
+            return Err(error("packages must name at least one package".to_string()));
+        }
+
+        for package in &packages {
+            if package.split('.').any(|part| part.is_empty()) {
+                return Err(error(format!("{} is not a valid package name", package)));
+            }
+        }
+
+        self.exe.python_interpreter_config_mut().remote_resources = Some(RemoteResourcesConfig {
+            index_url,
+            public_key: public_key.to_ascii_lowercase(),
+            packages,
+            cache_directory: PathBuf::from(cache_directory),
+        });
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.windows_service(name, entry_point, display_name=None, description=None, stop_entry_point=None)
+    pub fn windows_service(
+        &mut self,
+        name: String,
+        entry_point: String,
+        display_name: &Value,
+        description: &Value,
+        stop_entry_point: &Value,
+    ) -> ValueResult {
+        let display_name = optional_str_arg("display_name", display_name)?;
+        let description = optional_str_arg("description", description)?;
+        let stop_entry_point = optional_str_arg("stop_entry_point", stop_entry_point)?;
+
+        let error = |message: String| {
+            ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message,
+                label: "PythonExecutable.windows_service()".to_string(),
+            })
+        };
+
+        if name.is_empty() || name.contains(|c: char| c == '/' || c == '\\') {
+            return Err(error(format!("{:?} is not a valid service name", name)));
+        }
+
+        for entry_point in std::iter::once(&entry_point).chain(stop_entry_point.iter()) {
+            validate_entry_point(entry_point).map_err(|e| error(e.to_string()))?;
+        }
+
+        self.exe.rust_project_mut().windows_service = Some(WindowsService {
+            display_name: display_name.unwrap_or_else(|| name.clone()),
+            name,
+            description,
+            entry_point,
+            stop_entry_point,
+        });
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.to_embedded_resources()
+    pub fn to_embedded_resources(&self) -> ValueResult {
+        Ok(Value::new(PythonEmbeddedResourcesValue {
+            exe: self.exe.clone_trait(),
+            build_system: None,
+        }))
+    }
+
+    /// PythonExecutable.to_zipapp(interpreter="/usr/bin/env python3", compress=True)
+    pub fn to_zipapp(&self, interpreter: &Value, compress: bool) -> ValueResult {
+        let interpreter = optional_str_arg("interpreter", interpreter)?;
+
+        Ok(Value::new(PythonZipAppValue {
+            exe: self.exe.clone_trait(),
+            interpreter,
+            compress,
+        }))
+    }
+
+    /// PythonExecutable.to_jupyter_kernel(kernel_name=None, display_name=None, kernels_dir=None, user=False)
+    pub fn to_jupyter_kernel(
+        &self,
+        kernel_name: &Value,
+        display_name: &Value,
+        kernels_dir: &Value,
+        user: bool,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.to_jupyter_kernel()";
+
+        let kernel_name =
+            optional_str_arg("kernel_name", kernel_name)?.unwrap_or_else(|| self.exe.name());
+        let display_name =
+            optional_str_arg("display_name", display_name)?.unwrap_or_else(|| kernel_name.clone());
+        let kernels_dir = optional_str_arg("kernels_dir", kernels_dir)?;
+
+        validate_kernel_name(&kernel_name).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: e.to_string(),
+                label: LABEL.to_string(),
+            })
+        })?;
+
+        if user && kernels_dir.is_some() {
+            return Err(ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: "kernels_dir cannot be used with user=True".to_string(),
+                label: LABEL.to_string(),
+            }));
+        }
+
+        Ok(Value::new(JupyterKernelValue {
+            exe: self.exe.clone_trait(),
+            kernel_name,
+            display_name,
+            kernels_dir: kernels_dir.map(PathBuf::from),
+            user,
+        }))
+    }
+
+    /// PythonExecutable.to_shell_completions(prefix="usr/share", shells=None, prog_name=None, args=None)
+    pub fn to_shell_completions(
+        &self,
+        type_values: &TypeValues,
+        prefix: String,
+        shells: &Value,
+        prog_name: &Value,
+        args: &Value,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.to_shell_completions()";
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        optional_list_arg("shells", "string", shells)?;
+        optional_list_arg("args", "string", args)?;
+
+        let shells = if shells.get_type() == "list" {
+            shells
+                .iter()?
+                .iter()
+                .map(|x| {
+                    CompletionShell::try_from(x.to_string().as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e,
+                            label: LABEL.to_string(),
+                        })
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            CompletionShell::all()
+        };
+
+        let prog_name =
+            optional_str_arg("prog_name", prog_name)?.unwrap_or_else(|| self.exe.name());
+
+        let args = if args.get_type() == "list" {
+            Some(
+                args.iter()?
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>(),
+            )
+        } else {
+            None
+        };
+
+        let manifest = build_shell_completions(
+            pyoxidizer_context,
+            self.exe.deref(),
+            &prog_name,
+            &shells,
+            args.as_deref(),
+            Path::new(&prefix),
+        )
+        .map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_PYTHON_EXECUTABLE",
+                message: format!("{:?}", e),
+                label: LABEL.to_string(),
+            })
+        })?;
+
+        Ok(Value::new(FileManifestValue {
+            manifest,
+            run_path: None,
+        }))
+    }
+
+    /// PythonExecutable.to_file_manifest(prefix)
+    pub fn to_file_manifest(&self, type_values: &TypeValues, prefix: String) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let manifest_value = FileManifestValue::new_from_args()?;
+        let mut manifest = manifest_value
+            .downcast_mut::<FileManifestValue>()
+            .unwrap()
+            .unwrap();
+
+        file_manifest_add_python_executable(
+            &mut manifest,
+            pyoxidizer_context.logger(),
+            &prefix,
+            self.exe.deref(),
+            &pyoxidizer_context.build_target_triple,
+            pyoxidizer_context.build_release,
+            &pyoxidizer_context.build_opt_level,
+        )
+        .map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_PYTHON_EXECUTABLE",
+                message: format!("{:?}", e),
+                label: "to_file_manifest()".to_string(),
+            })
+        })?;
+
+        Ok(manifest_value.clone())
+    }
+
+    /// PythonExecutable.to_wix_bundle_builder(id_prefix, name, version, manufacturer, msi_builder_callback)
+    #[allow(clippy::too_many_arguments)]
+    pub fn to_wix_bundle_builder(
+        &self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        id_prefix: String,
+        product_name: String,
+        product_version: String,
+        product_manufacturer: String,
+        msi_builder_callback: Value,
+    ) -> ValueResult {
+        optional_type_arg("msi_builder_callback", "function", &msi_builder_callback)?;
+
+        let msi_builder_value = self.to_wix_msi_builder(
+            type_values,
+            id_prefix.clone(),
+            product_name.clone(),
+            product_version.clone(),
+            product_manufacturer.clone(),
+        )?;
+
+        if msi_builder_callback.get_type() == "function" {
+            msi_builder_callback.call(
+                call_stack,
+                type_values,
+                vec![msi_builder_value.clone()],
+                LinkedHashMap::new(),
+                None,
+                None,
+            )?;
+        }
+
+        let msi_builder = msi_builder_value
+            .downcast_ref::<WiXMsiBuilderValue>()
+            .unwrap();
+
+        let bundle_builder_value = WiXBundleBuilderValue::new_from_args(
+            id_prefix,
+            product_name,
+            product_version,
+            product_manufacturer,
+        )?;
+        let mut bundle_builder = bundle_builder_value
+            .downcast_mut::<WiXBundleBuilderValue>()
+            .unwrap()
+            .unwrap();
+
+        // Add the VC++ Redistributable for the target platform.
+        match self.exe.target_triple() {
+            "i686-pc-windows-msvc" => {
+                bundle_builder.add_vc_redistributable(type_values, "x86".to_string())?;
+            }
+            "x86_64-pc-windows-msvc" => {
+                bundle_builder.add_vc_redistributable(type_values, "x64".to_string())?;
+            }
+            "aarch64-pc-windows-msvc" => {
+                bundle_builder.add_vc_redistributable(type_values, "arm64".to_string())?;
+            }
+            _ => {}
+        }
+
+        bundle_builder.add_wix_msi_builder(
+            msi_builder.deref().clone(),
+            false,
+            Value::new(NoneType::None),
+        )?;
+
+        Ok(bundle_builder_value.clone())
+    }
+
+    /// PythonExecutable.to_wix_msi_builder(id_prefix, product_name, product_version, product_manufacturer)
+    pub fn to_wix_msi_builder(
+        &self,
+        type_values: &TypeValues,
+        id_prefix: String,
+        product_name: String,
+        product_version: String,
+        product_manufacturer: String,
+    ) -> ValueResult {
+        let manifest_value = self.to_file_manifest(type_values, ".".to_string())?;
+        let manifest = manifest_value.downcast_ref::<FileManifestValue>().unwrap();
+
+        let builder_value = WiXMsiBuilderValue::new_from_args(
+            id_prefix,
+            product_name,
+            product_version,
+            product_manufacturer,
+        )?;
+        let mut builder = builder_value
+            .downcast_mut::<WiXMsiBuilderValue>()
+            .unwrap()
+            .unwrap();
+
+        builder.add_program_files_manifest(manifest.deref().clone())?;
+
+        Ok(builder_value.clone())
+    }
+
+    /// PythonExecutable.filter_resources_from_files(files=None, glob_files=None)
+    pub fn filter_resources_from_files(
+        &mut self,
+        type_values: &TypeValues,
+        files: &Value,
+        glob_files: &Value,
+    ) -> ValueResult {
+        optional_list_arg("files", "string", &files)?;
+        optional_list_arg("glob_files", "string", &glob_files)?;
+
+        let files = match files.get_type() {
+            "list" => files
+                .iter()?
+                .iter()
+                .map(|x| PathBuf::from(x.to_string()))
+                .collect(),
+            "NoneType" => Vec::new(),
+            _ => panic!("type should have been validated above"),
+        };
+
+        let glob_files = match glob_files.get_type() {
+            "list" => glob_files.iter()?.iter().map(|x| x.to_string()).collect(),
+            "NoneType" => Vec::new(),
+            _ => panic!("type should have been validated above"),
+        };
+
+        let files_refs = files.iter().map(|x| x.as_ref()).collect::<Vec<&Path>>();
+        let glob_files_refs = glob_files.iter().map(|x| x.as_ref()).collect::<Vec<&str>>();
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        self.exe
+            .filter_resources_from_files(pyoxidizer_context.logger(), &files_refs, &glob_files_refs)
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "RUNTIME_ERROR",
+                    message: format!("{:?}", e),
+                    label: "filter_from_files()".to_string(),
+                })
+            })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.filter_resources(include_patterns=None, exclude_patterns=None, max_size=None, dry_run=False)
+    pub fn filter_resources(
+        &mut self,
+        type_values: &TypeValues,
+        include_patterns: &Value,
+        exclude_patterns: &Value,
+        max_size: &Value,
+        dry_run: bool,
+    ) -> ValueResult {
+        optional_list_arg("include_patterns", "string", &include_patterns)?;
+        optional_list_arg("exclude_patterns", "string", &exclude_patterns)?;
+        let max_size = optional_int_arg("max_size", &max_size)?;
+
+        let max_size = match max_size {
+            Some(size) if size < 0 => {
+                return Err(ValueError::from(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: format!("max_size must be non-negative; got {}", size),
+                    label: "filter_resources()".to_string(),
+                }));
+            }
+            Some(size) => Some(size as u64),
+            None => None,
+        };
+
+        let include_patterns = match include_patterns.get_type() {
+            "list" => include_patterns
+                .iter()?
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+            "NoneType" => Vec::new(),
+            _ => panic!("type should have been validated above"),
+        };
+
+        let exclude_patterns = match exclude_patterns.get_type() {
+            "list" => exclude_patterns
+                .iter()?
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+            "NoneType" => Vec::new(),
+            _ => panic!("type should have been validated above"),
+        };
+
+        let include_refs = include_patterns
+            .iter()
+            .map(|x| x.as_ref())
+            .collect::<Vec<&str>>();
+        let exclude_refs = exclude_patterns
+            .iter()
+            .map(|x| x.as_ref())
+            .collect::<Vec<&str>>();
+
+        let filter = ResourceFilter::new(&include_refs, &exclude_refs, max_size).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: format!("{:?}", e),
+                label: "filter_resources()".to_string(),
+            })
+        })?;
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let removed = self
+            .exe
+            .filter_resources(pyoxidizer_context.logger(), &filter, dry_run)
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "RUNTIME_ERROR",
+                    message: format!("{:?}", e),
+                    label: "filter_resources()".to_string(),
+                })
+            })?;
+
+        Ok(Value::from(
+            removed.into_iter().map(Value::from).collect::<Vec<_>>(),
+        ))
+    }
+
+    /// PythonExecutable.import_graph()
+    pub fn import_graph(&self) -> ValueResult {
+        let graph = self.exe.import_graph().map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "RUNTIME_ERROR",
+                message: format!("{:?}", e),
+                label: "import_graph()".to_string(),
+            })
+        })?;
+
+        let mut res = Value::try_from(HashMap::<String, String>::new())?;
+
+        for name in graph.modules() {
+            res.set_at(
+                Value::from(name.as_str()),
+                Value::from(
+                    graph
+                        .imports(name)
+                        .into_iter()
+                        .flatten()
+                        .map(|import| Value::from(import.as_str()))
+                        .collect::<Vec<_>>(),
+                ),
+            )?;
+        }
+
+        Ok(res)
+    }
+
+    /// PythonExecutable.prune_unreachable_resources(roots=None, dry_run=False)
+    pub fn prune_unreachable_resources(
+        &mut self,
+        type_values: &TypeValues,
+        roots: &Value,
+        dry_run: bool,
+    ) -> ValueResult {
+        const LABEL: &str = "prune_unreachable_resources()";
+
+        optional_list_arg("roots", "string", &roots)?;
+
+        let roots = match roots.get_type() {
+            "list" => roots.iter()?.iter().map(|x| x.to_string()).collect(),
+            "NoneType" => {
+                // Default to the modules the interpreter runs.
+                let config = self.exe.python_interpreter_config();
+
+                config
+                    .config
+                    .run_module
+                    .iter()
+                    .chain(config.multicall_run_modules.values())
+                    .cloned()
+                    .collect::<Vec<_>>()
+            }
+            _ => panic!("type should have been validated above"),
+        };
+
+        if roots.is_empty() {
+            return Err(ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: "unable to determine the modules the executable runs; specify roots"
+                    .to_string(),
+                label: LABEL.to_string(),
+            }));
+        }
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let removed = self
+            .exe
+            .prune_unreachable_resources(pyoxidizer_context.logger(), &roots, dry_run)
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "RUNTIME_ERROR",
+                    message: format!("{:?}", e),
+                    label: LABEL.to_string(),
+                })
+            })?;
+
+        Value::try_from(removed.into_iter().collect::<HashMap<_, _>>())
+    }
+}
+
+starlark_module! { python_executable_env =>
+    PythonExecutable.build(env env, this, target: String) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.build(env, target)
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.make_python_module_source(
+        env env,
+        call_stack cs,
+        this,
+        name: String,
+        source: String,
+        is_package: bool = false
+    ) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.make_python_module_source(&env, cs, name, source, is_package)
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.pip_download(
+        env env,
+        call_stack cs,
+        this,
+        args
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.pip_download(&env, cs, &args)
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.pip_install(
+        env env,
+        call_stack cs,
+        this,
+        args,
+        extra_envs=NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.pip_install(&env, cs, &args, &extra_envs)
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.read_package_root(
+        env env,
+        call_stack cs,
+        this,
+        path: String,
+        packages
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.read_package_root(&env, cs, path, &packages)
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.read_conda_env(
+        env env,
+        call_stack cs,
+        this,
+        path: String
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.read_conda_env(&env, cs, path)
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.read_virtualenv(
+        env env,
+        call_stack cs,
+        this,
+        path: String
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.read_virtualenv(&env, cs, path)
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.setup_py_install(
+        env env,
+        call_stack cs,
+        this,
+        package_path: String,
+        extra_envs=NoneType::None,
+        extra_global_arguments=NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.setup_py_install(&env, cs, package_path, &extra_envs, &extra_global_arguments)
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.cythonize(
+        env env,
+        call_stack cs,
+        this,
+        package_path: String,
+        sources,
+        language_level: String = "3".to_string(),
+        cython_requirement: String = "Cython".to_string(),
+        extra_envs=NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.cythonize(
+            &env,
+            cs,
+            package_path,
+            &sources,
+            language_level,
+            cython_requirement,
+            &extra_envs,
+        )
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.add_python_resource(
+        env env,
+        this,
+        resource
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.add_python_resource(
+            &env,
+            &resource,
+            "add_python_resource",
+        )
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.add_python_resources(
+        env env,
+        this,
+        resources
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.add_python_resources(
+            &env,
+            &resources,
+        )
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.add_shared_resources(
+        env env,
+        this,
+        resources
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.add_shared_resources(
+            &env,
+            &resources,
+        )
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.add_file_manifest(
+        env env,
+        call_stack cs,
+        this,
+        manifest,
+        location = NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.add_file_manifest(&env, cs, &manifest, &location)
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.add_console_scripts(
+        env env,
+        call_stack cs,
+        this,
+        resources
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.add_console_scripts(&env, cs, &resources)
+    }
+
+    PythonExecutable.add_cargo_feature(this, feature: String) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.add_cargo_feature(feature)
+    }
+
+    PythonExecutable.add_rust_source(env env, this, path: String) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.add_rust_source(&env, path)
+    }
+
+    #[allow(clippy::ptr_arg)]
+    PythonExecutable.filter_resources(
+        env env,
+        this,
+        include_patterns=NoneType::None,
+        exclude_patterns=NoneType::None,
+        max_size=NoneType::None,
+        dry_run: bool = false)
+    {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.filter_resources(&env, &include_patterns, &exclude_patterns, &max_size, dry_run)
+    }
+
+    #[allow(clippy::ptr_arg)]
+    PythonExecutable.filter_resources_from_files(
+        env env,
+        this,
+        files=NoneType::None,
+        glob_files=NoneType::None)
+    {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.filter_resources_from_files(&env, &files, &glob_files)
+    }
+
+    PythonExecutable.import_graph(this) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.import_graph()
+    }
+
+    PythonExecutable.link_args(this, args) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.link_args(&args)
+    }
+
+    PythonExecutable.linker(this, path: String, target_triple = NoneType::None) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.linker(path, &target_triple)
+    }
+
+    PythonExecutable.prune_unreachable_resources(
+        env env,
+        this,
+        roots=NoneType::None,
+        dry_run: bool = false)
+    {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.prune_unreachable_resources(&env, &roots, dry_run)
+    }
+
+    PythonExecutable.run_tests(
+        this,
+        module: String = "pytest".to_string(),
+        args = NoneType::None
+    ) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.run_tests(module, &args)
+    }
+
+    PythonExecutable.self_update(
+        this,
+        manifest_url: String,
+        public_key: String,
+        version: String,
+        staging_directory: String = "$ORIGIN/.update".to_string()
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.self_update(manifest_url, public_key, version, staging_directory)
+    }
+
+    PythonExecutable.remote_resources(
+        this,
+        index_url: String,
+        public_key: String,
+        packages,
+        cache_directory: String = "$ORIGIN/.remote-resources".to_string()
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.remote_resources(index_url, public_key, &packages, cache_directory)
+    }
+
+    PythonExecutable.set_rust_main(env env, this, path: String) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.set_rust_main(&env, path)
+    }
+
+    PythonExecutable.static_crt(this, enabled: bool) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.static_crt(enabled)
+    }
+
+    #[allow(clippy::ptr_arg)]
+    PythonExecutable.to_embedded_resources(this) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.to_embedded_resources()
+    }
+
+    PythonExecutable.to_jupyter_kernel(
+        this,
+        kernel_name = NoneType::None,
+        display_name = NoneType::None,
+        kernels_dir = NoneType::None,
+        user: bool = false
+    ) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.to_jupyter_kernel(&kernel_name, &display_name, &kernels_dir, user)
+    }
+
+    PythonExecutable.to_file_manifest(env env, this, prefix: String) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.to_file_manifest(&env, prefix)
+    }
+
+    PythonExecutable.to_shell_completions(
+        env env,
+        this,
+        prefix: String = "usr/share".to_string(),
+        shells = NoneType::None,
+        prog_name = NoneType::None,
+        args = NoneType::None
+    ) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.to_shell_completions(&env, prefix, &shells, &prog_name, &args)
+    }
+
+    PythonExecutable.to_zipapp(
+        this,
+        interpreter = Value::from(DEFAULT_INTERPRETER),
+        compress: bool = true
+    ) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.to_zipapp(&interpreter, compress)
+    }
+
+    PythonExecutable.to_wix_bundle_builder(
+        env env,
+        call_stack cs,
+        this,
+        id_prefix: String,
+        product_name: String,
+        product_version: String,
+        product_manufacturer: String,
+        msi_builder_callback = NoneType::None
+    ) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.to_wix_bundle_builder(
+            env,
+            cs,
+            id_prefix,
+            product_name,
+            product_version,
+            product_manufacturer,
+            msi_builder_callback
+        )
+    }
+
+    PythonExecutable.to_wix_msi_builder(
+        env env,
+        this,
+        id_prefix: String,
+        product_name: String,
+        product_version: String,
+        product_manufacturer: String
+    ) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.to_wix_msi_builder(&env, id_prefix, product_name, product_version, product_manufacturer)
+    }
+
+    PythonExecutable.verify_imports(this, modules) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.verify_imports(&modules)
+    }
+
+    PythonExecutable.windows_service(
+        this,
+        name: String,
+        entry_point: String,
+        display_name = NoneType::None,
+        description = NoneType::None,
+        stop_entry_point = NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.windows_service(name, entry_point, &display_name, &description, &stop_entry_point)
+    }
+
+    PythonExecutable.windows_version_info(
+        this,
+        file_version = NoneType::None,
+        product_version = NoneType::None,
+        company_name = NoneType::None,
+        file_description = NoneType::None,
+        product_name = NoneType::None,
+        copyright = NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.windows_version_info(
+            &file_version,
+            &product_version,
+            &company_name,
+            &file_description,
+            &product_name,
+            &copyright
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::super::{eval::EvaluationContext, testutil::*},
+        super::*,
+        crate::{
+            py_packaging::binary::{DebugInfoSettings, RustProjectCustomizations},
+            python_distributions::PYTHON_DISTRIBUTIONS,
+        },
+        python_packaging::resource::{
+            PythonPackageDistributionResource, PythonPackageDistributionResourceFlavor,
+        },
+        tugger_file_manifest::{FileEntry, FileManifest},
+    };
+
+    #[test]
+    fn test_default_values() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+        let exe = env.eval("exe")?;
+
+        assert_eq!(exe.get_type(), "PythonExecutable");
+
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+        assert!(exe
+            .exe
+            .iter_resources()
+            .any(|(_, r)| r.in_memory_source.is_some()));
+        assert!(exe
+            .exe
+            .iter_resources()
+            .all(|(_, r)| r.in_memory_resources.is_none()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_info() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+        let exe = env.eval("exe")?;
+
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+        let info = &exe.exe.python_interpreter_config().build_info;
+
+        assert_eq!(
+            info.get("pyoxidizer_version"),
+            Some(&crate::environment::PYOXIDIZER_VERSION.to_string())
+        );
+        assert_eq!(
+            info.get("target_triple"),
+            Some(&crate::project_building::HOST.to_string())
+        );
+        assert!(info.contains_key("build_timestamp"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_sources() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+        env.eval("policy.include_distribution_sources = False")?;
+
+        let exe = env.eval("dist.to_python_executable('testapp', packaging_policy=policy)")?;
+
+        assert_eq!(exe.get_type(), "PythonExecutable");
+
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+        assert!(exe
+            .exe
+            .iter_resources()
+            .all(|(_, r)| r.in_memory_source.is_none()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_make_python_module_source() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+        let m = env.eval("exe.make_python_module_source('foo', 'import bar')")?;
+
+        assert_eq!(m.get_type(), PythonModuleSourceValue::TYPE);
+        assert_eq!(m.get_attr("name").unwrap().to_str(), "foo");
+        assert_eq!(m.get_attr("source").unwrap().to_str(), "import bar");
+        assert_eq!(m.get_attr("is_package").unwrap().to_bool(), false);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_make_python_module_source_callback() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+        env.eval(
+            "def my_func(policy, resource):\n    resource.add_source = True\n    resource.add_bytecode_optimization_level_two = True\n",
+        )?;
+        env.eval("policy.register_resource_callback(my_func)")?;
+        env.eval("exe = dist.to_python_executable('testapp', packaging_policy = policy)")?;
+
+        let m = env.eval("exe.make_python_module_source('foo', 'import bar')")?;
+
+        assert_eq!(m.get_type(), PythonModuleSourceValue::TYPE);
+        assert_eq!(m.get_attr("name").unwrap().to_str(), "foo");
+        assert_eq!(m.get_attr("source").unwrap().to_str(), "import bar");
+        assert_eq!(m.get_attr("is_package").unwrap().to_bool(), false);
+        assert_eq!(m.get_attr("add_source").unwrap().to_bool(), true);
+        assert_eq!(
+            m.get_attr("add_bytecode_optimization_level_two")
+                .unwrap()
+                .to_bool(),
+            true
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_console_scripts() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let resource = PythonPackageDistributionResource {
+            location: PythonPackageDistributionResourceFlavor::DistInfo,
+            package: "black".to_string(),
+            version: "19.10b0".to_string(),
+            name: "entry_points.txt".to_string(),
+            data: FileData::Memory(
+                b"[console_scripts]\nblack = black:patched_main\nblackd = blackd:patched_main\n"
+                    .to_vec(),
+            ),
+        };
+        env.set_var(
+            "resource",
+            Value::new(PythonPackageDistributionResourceValue::new(resource)),
+        )?;
+
+        let names = env.eval("exe.add_console_scripts([resource])")?;
+        assert_eq!(names.get_type(), "list");
+        assert_eq!(
+            names
+                .iter()?
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>(),
+            vec!["black", "blackd"]
+        );
+
+        let exe = env.get_var("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+        assert!(exe
+            .exe
+            .iter_resources()
+            .any(|(name, _)| name == CONSOLE_SCRIPTS_MODULE));
+        assert_eq!(
+            exe.exe.python_interpreter_config().config.run_module,
+            Some(CONSOLE_SCRIPTS_MODULE.to_string())
+        );
+
+        let names = env.eval("exe.add_console_scripts([])")?;
+        assert_eq!(names.length().unwrap(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_file_manifest() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+        env.eval("policy.allow_files = True")?;
+        env.eval("policy.resources_location_fallback = 'filesystem-relative:lib'")?;
+        env.eval("exe = dist.to_python_executable('testapp', packaging_policy=policy)")?;
+
+        let mut manifest = FileManifest::default();
+        manifest.add_file_entry(
+            "assets/logo.png",
+            FileEntry {
+                data: vec![42].into(),
+                executable: false,
+            },
+        )?;
+        env.set_var(
+            "manifest",
+            Value::new(FileManifestValue {
+                manifest,
+                run_path: None,
+            }),
+        )?;
+
+        env.eval("exe.add_file_manifest(manifest)")?;
+        {
+            let exe = env.get_var("exe")?;
+            let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+            let resource = exe
+                .exe
+                .iter_resources()
+                .find(|(name, _)| *name == "assets/logo.png")
+                .unwrap()
+                .1;
+            assert!(resource.is_utf8_filename_data);
+            assert_eq!(resource.file_data_embedded, Some(vec![42].into()));
+        }
+
+        env.eval("exe.add_file_manifest(manifest, location='filesystem-relative:lib')")?;
+        let exe = env.get_var("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+        let resource = exe
+            .exe
+            .iter_resources()
+            .find(|(name, _)| *name == "assets/logo.png")
+            .unwrap()
+            .1;
+        assert_eq!(
+            resource.file_data_utf8_relative_path,
+            Some((PathBuf::from("lib/assets/logo.png"), vec![42].into()))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_resources() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let count_resources = |env: &mut EvaluationContext| -> Result<usize> {
+            let exe = env.get_var("exe")?;
+            let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+            Ok(exe
+                .exe
+                .iter_resources()
+                .filter(|(name, _)| name.starts_with("email."))
+                .count())
+        };
+
+        assert!(count_resources(&mut env)? > 0);
+
+        let removed =
+            env.eval("exe.filter_resources(exclude_patterns = ['^email\\\\.'], dry_run = True)")?;
+        assert!(removed.length().unwrap() > 0);
+        assert!(count_resources(&mut env)? > 0);
+
+        let removed = env.eval("exe.filter_resources(exclude_patterns = ['^email\\\\.'])")?;
+        assert!(removed.length().unwrap() > 0);
+        assert_eq!(count_resources(&mut env)?, 0);
+
+        assert!(env
+            .eval("exe.filter_resources(include_patterns = ['('])")
+            .is_err());
+        assert!(env.eval("exe.filter_resources(max_size = -1)").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_graph() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        env.eval("graph = exe.import_graph()")?;
+        eval_assert(&mut env, "'json.decoder' in graph['json']")?;
+        eval_assert(&mut env, "'json' in graph['json.decoder']")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_unreachable_resources_dynamic_imports() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let source = "import importlib\n\
+            importlib.import_module('xmlrpc.client')\n\
+            def load(name):\n    \
+                return importlib.import_module('myapp.plugins.' + name)\n";
+
+        env.eval(&format!(
+            "exe.add_python_resource(exe.make_python_module_source('myapp', {:?}, True))",
+            source
+        ))?;
+        env.eval(
+            "exe.add_python_resource(exe.make_python_module_source('myapp.plugins', '', True))",
+        )?;
+        env.eval("exe.add_python_resource(exe.make_python_module_source('myapp.plugins.a', ''))")?;
+
+        env.eval("removed = exe.prune_unreachable_resources(roots = ['myapp'])")?;
+        // Modules imported by constant names are found.
+        eval_assert(&mut env, "'xmlrpc.client' not in removed")?;
+        // Modules the package may import by computed names are retained.
+        eval_assert(&mut env, "'myapp.plugins.a' not in removed")?;
+        eval_assert(&mut env, "'xml.dom' in removed")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_unreachable_resources() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let has_resource = |env: &mut EvaluationContext, name: &str| -> Result<bool> {
+            let exe = env.get_var("exe")?;
+            let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+            Ok(exe.exe.iter_resources().any(|(n, _)| n == name))
+        };
+
+        // The default configuration runs a REPL, so there's no module to
+        // start from.
+        assert!(env.eval("exe.prune_unreachable_resources()").is_err());
+
+        let removed =
+            env.eval("exe.prune_unreachable_resources(roots = ['json'], dry_run = True)")?;
+        assert!(removed.length().unwrap() > 0);
+        assert!(has_resource(&mut env, "xmlrpc.client")?);
+
+        env.eval("removed = exe.prune_unreachable_resources(roots = ['json'])")?;
+        eval_assert(&mut env, "'xmlrpc.client' in removed")?;
+        eval_assert(&mut env, "'json.decoder' not in removed")?;
+        eval_assert(&mut env, "'encodings.utf_8' not in removed")?;
+        assert!(!has_resource(&mut env, "xmlrpc.client")?);
+        assert!(has_resource(&mut env, "json.decoder")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_unreachable_resources_run_module() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("config = dist.make_python_interpreter_config()")?;
+        env.eval("config.run_module = 'json.tool'")?;
+        env.eval("exe = dist.to_python_executable('testapp', config = config)")?;
+
+        env.eval("removed = exe.prune_unreachable_resources()")?;
+        eval_assert(&mut env, "'xmlrpc.client' in removed")?;
+
+        // Running a module goes through runpy.
+        for name in &["runpy", "importlib.util", "pkgutil", "types", "json.tool"] {
+            eval_assert(&mut env, &format!("'{}' not in removed", name))?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pip_download_pyflakes() -> Result<()> {
+        for target_triple in PYTHON_DISTRIBUTIONS.all_target_triples() {
+            let mut env = test_evaluation_context_builder()?
+                .build_target_triple(target_triple)
+                .into_context()?;
+
+            env.eval("dist = default_python_distribution()")?;
+            env.eval("exe = dist.to_python_executable('testapp')")?;
+
+            let resources = env.eval("exe.pip_download(['pyflakes==2.2.0'])")?;
+
+            assert_eq!(resources.get_type(), "list");
+
+            let raw_it = resources.iter().unwrap();
+            let mut it = raw_it.iter();
+
+            let v = it.next().unwrap();
+            assert_eq!(v.get_type(), PythonModuleSourceValue::TYPE);
+            let x = v.downcast_ref::<PythonModuleSourceValue>().unwrap();
+            assert!(x.inner.package().starts_with("pyflakes"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pip_install_simple() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+        env.eval("policy.include_distribution_sources = False")?;
+        env.eval("exe = dist.to_python_executable('testapp', packaging_policy = policy)")?;
+
+        let resources = env.eval("exe.pip_install(['pyflakes==2.1.1'])")?;
+        assert_eq!(resources.get_type(), "list");
+
+        let raw_it = resources.iter().unwrap();
+        let mut it = raw_it.iter();
+
+        let v = it.next().unwrap();
+        assert_eq!(v.get_type(), PythonModuleSourceValue::TYPE);
+        let x = v.downcast_ref::<PythonModuleSourceValue>().unwrap();
+        assert_eq!(x.inner.name, "pyflakes");
+        assert!(x.inner.is_package);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_package_root_simple() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let root = temp_dir.path();
+        std::fs::create_dir(root.join("bar"))?;
+        let bar_init = root.join("bar").join("__init__.py");
+        std::fs::write(&bar_init, "# bar")?;
+
+        let foo_path = root.join("foo.py");
+        std::fs::write(&foo_path, "# foo")?;
+
+        let baz_path = root.join("baz.py");
+        std::fs::write(&baz_path, "# baz")?;
+
+        std::fs::create_dir(root.join("extra"))?;
+        let extra_path = root.join("extra").join("__init__.py");
+        std::fs::write(&extra_path, "# extra")?;
+
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+        env.eval("policy.include_distribution_sources = False")?;
+        env.eval("exe = dist.to_python_executable('testapp', packaging_policy = policy)")?;
+
+        let resources = env.eval(&format!(
+            "exe.read_package_root(\"{}\", packages=['foo', 'bar'])",
+            root.display()
+        ))?;
 
-        Ok(Value::new(NoneType::None))
-    }
-}
+        assert_eq!(resources.get_type(), "list");
+        assert_eq!(resources.length().unwrap(), 2);
 
-starlark_module! { python_executable_env =>
-    PythonExecutable.build(env env, this, target: String) {
-        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
-        this.build(env, target)
-    }
+        let raw_it = resources.iter().unwrap();
+        let mut it = raw_it.iter();
 
-    #[allow(non_snake_case, clippy::ptr_arg)]
-    PythonExecutable.make_python_module_source(
-        env env,
-        call_stack cs,
-        this,
-        name: String,
-        source: String,
-        is_package: bool = false
-    ) {
-        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
-        this.make_python_module_source(&env, cs, name, source, is_package)
-    }
+        let v = it.next().unwrap();
+        assert_eq!(v.get_type(), PythonModuleSourceValue::TYPE);
+        let x = v.downcast_ref::<PythonModuleSourceValue>().unwrap();
+        assert_eq!(x.inner.name, "bar");
+        assert!(x.inner.is_package);
+        assert_eq!(x.inner.source.resolve().unwrap(), b"# bar");
 
-    #[allow(non_snake_case, clippy::ptr_arg)]
-    PythonExecutable.pip_download(
-        env env,
-        call_stack cs,
-        this,
-        args
-    ) {
-        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
-        this.pip_download(&env, cs, &args)
-    }
+        let v = it.next().unwrap();
+        assert_eq!(v.get_type(), PythonModuleSourceValue::TYPE);
+        let x = v.downcast_ref::<PythonModuleSourceValue>().unwrap();
+        assert_eq!(x.inner.name, "foo");
+        assert!(!x.inner.is_package);
+        assert_eq!(x.inner.source.resolve().unwrap(), b"# foo");
 
-    #[allow(non_snake_case, clippy::ptr_arg)]
-    PythonExecutable.pip_install(
-        env env,
-        call_stack cs,
-        this,
-        args,
-        extra_envs=NoneType::None
-    ) {
-        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
-        this.pip_install(&env, cs, &args, &extra_envs)
+        Ok(())
     }
 
-    #[allow(non_snake_case, clippy::ptr_arg)]
-    PythonExecutable.read_package_root(
-        env env,
-        call_stack cs,
-        this,
-        path: String,
-        packages
-    ) {
-        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
-        this.read_package_root(&env, cs, path, &packages)
-    }
+    #[test]
+    fn test_run_tests() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
 
-    #[allow(non_snake_case, clippy::ptr_arg)]
-    PythonExecutable.read_virtualenv(
-        env env,
-        call_stack cs,
-        this,
-        path: String
-    ) {
-        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
-        this.read_virtualenv(&env, cs, path)
-    }
+        let tests = env.eval("exe.run_tests(args = ['--pyargs', 'myapp.tests'])")?;
+        assert_eq!(tests.get_type(), "PythonExecutableTests");
 
-    #[allow(non_snake_case, clippy::ptr_arg)]
-    PythonExecutable.setup_py_install(
-        env env,
-        call_stack cs,
-        this,
-        package_path: String,
-        extra_envs=NoneType::None,
-        extra_global_arguments=NoneType::None
-    ) {
-        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
-        this.setup_py_install(&env, cs, package_path, &extra_envs, &extra_global_arguments)
-    }
+        let tests = tests.downcast_ref::<PythonExecutableTestsValue>().unwrap();
+        assert_eq!(
+            tests.args,
+            vec!["--pyargs".to_string(), "myapp.tests".to_string()]
+        );
 
-    #[allow(non_snake_case, clippy::ptr_arg)]
-    PythonExecutable.add_python_resource(
-        env env,
-        this,
-        resource
-    ) {
-        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
-        this.add_python_resource(
-            &env,
-            &resource,
-            "add_python_resource",
-        )
-    }
+        let config = tests.exe.python_interpreter_config();
+        assert_eq!(config.config.run_module, Some("pytest".to_string()));
+        assert_eq!(config.config.use_environment, Some(false));
+        assert!(config.oxidized_importer);
+        assert!(!config.filesystem_importer);
 
-    #[allow(non_snake_case, clippy::ptr_arg)]
-    PythonExecutable.add_python_resources(
-        env env,
-        this,
-        resources
-    ) {
-        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
-        this.add_python_resources(
-            &env,
-            &resources,
-        )
-    }
+        let tests = env.eval("exe.run_tests(module = 'unittest')")?;
+        let tests = tests.downcast_ref::<PythonExecutableTestsValue>().unwrap();
+        assert!(tests.args.is_empty());
+        assert_eq!(
+            tests.exe.python_interpreter_config().config.run_module,
+            Some("unittest".to_string())
+        );
 
-    #[allow(clippy::ptr_arg)]
-    PythonExecutable.filter_resources_from_files(
-        env env,
-        this,
-        files=NoneType::None,
-        glob_files=NoneType::None)
-    {
-        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
-        this.filter_resources_from_files(&env, &files, &glob_files)
-    }
+        // The tested executable is not modified.
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+        assert_ne!(
+            exe.exe.python_interpreter_config().config.run_module,
+            Some("pytest".to_string())
+        );
 
-    #[allow(clippy::ptr_arg)]
-    PythonExecutable.to_embedded_resources(this) {
-        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
-        this.to_embedded_resources()
+        Ok(())
     }
 
-    PythonExecutable.to_file_manifest(env env, this, prefix: String) {
-        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
-        this.to_file_manifest(&env, prefix)
-    }
+    #[test]
+    fn test_to_jupyter_kernel() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
 
-    PythonExecutable.to_wix_bundle_builder(
-        env env,
-        call_stack cs,
-        this,
-        id_prefix: String,
-        product_name: String,
-        product_version: String,
-        product_manufacturer: String,
-        msi_builder_callback = NoneType::None
-    ) {
-        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
-        this.to_wix_bundle_builder(
-            env,
-            cs,
-            id_prefix,
-            product_name,
-            product_version,
-            product_manufacturer,
-            msi_builder_callback
-        )
-    }
+        let kernel = env.eval("exe.to_jupyter_kernel()")?;
+        assert_eq!(kernel.get_type(), "JupyterKernel");
 
-    PythonExecutable.to_wix_msi_builder(
-        env env,
-        this,
-        id_prefix: String,
-        product_name: String,
-        product_version: String,
-        product_manufacturer: String
-    ) {
-        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
-        this.to_wix_msi_builder(&env, id_prefix, product_name, product_version, product_manufacturer)
-    }
-}
+        let kernel = kernel.downcast_ref::<JupyterKernelValue>().unwrap();
+        assert_eq!(kernel.kernel_name, "testapp");
+        assert_eq!(kernel.display_name, "testapp");
+        assert_eq!(kernel.kernels_dir, None);
+        assert!(!kernel.user);
 
-#[cfg(test)]
-mod tests {
-    use {super::super::testutil::*, super::*, crate::python_distributions::PYTHON_DISTRIBUTIONS};
+        let kernel = env.eval(
+            "exe.to_jupyter_kernel(kernel_name = 'myapp', display_name = 'My App', kernels_dir = '/kernels')",
+        )?;
+        let kernel = kernel.downcast_ref::<JupyterKernelValue>().unwrap();
+        assert_eq!(kernel.kernel_name, "myapp");
+        assert_eq!(kernel.display_name, "My App");
+        assert_eq!(kernel.kernels_dir, Some(PathBuf::from("/kernels")));
+
+        assert!(env
+            .eval("exe.to_jupyter_kernel(kernel_name = 'my app')")
+            .is_err());
+        assert!(env
+            .eval("exe.to_jupyter_kernel(kernels_dir = '/kernels', user = True)")
+            .is_err());
+
+        Ok(())
+    }
 
     #[test]
-    fn test_default_values() -> Result<()> {
+    fn test_to_zipapp() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
         add_exe(&mut env)?;
-        let exe = env.eval("exe")?;
 
-        assert_eq!(exe.get_type(), "PythonExecutable");
+        let zipapp = env.eval("exe.to_zipapp()")?;
+        assert_eq!(zipapp.get_type(), "PythonZipApp");
 
-        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
-        assert!(exe
-            .exe
-            .iter_resources()
-            .any(|(_, r)| r.in_memory_source.is_some()));
-        assert!(exe
-            .exe
-            .iter_resources()
-            .all(|(_, r)| r.in_memory_resources.is_none()));
+        let zipapp = zipapp.downcast_ref::<PythonZipAppValue>().unwrap();
+        assert_eq!(zipapp.interpreter, Some(DEFAULT_INTERPRETER.to_string()));
+        assert!(zipapp.compress);
+
+        let zipapp = env.eval("exe.to_zipapp(interpreter = None, compress = False)")?;
+        let zipapp = zipapp.downcast_ref::<PythonZipAppValue>().unwrap();
+        assert_eq!(zipapp.interpreter, None);
+        assert!(!zipapp.compress);
 
         Ok(())
     }
 
     #[test]
-    fn test_no_sources() -> Result<()> {
+    fn test_verify_imports() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
 
-        env.eval("dist = default_python_distribution()")?;
-        env.eval("policy = dist.make_python_packaging_policy()")?;
-        env.eval("policy.include_distribution_sources = False")?;
-
-        let exe = env.eval("dist.to_python_executable('testapp', packaging_policy=policy)")?;
+        assert!(env.eval("exe.verify_imports('foo')").is_err());
 
-        assert_eq!(exe.get_type(), "PythonExecutable");
+        env.eval("exe.verify_imports(['numpy', 'myapp'])")?;
+        env.eval("exe.verify_imports(['myapp', 'myapp.cli'])")?;
 
+        let exe = env.eval("exe")?;
         let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
-        assert!(exe
-            .exe
-            .iter_resources()
-            .all(|(_, r)| r.in_memory_source.is_none()));
+        assert_eq!(
+            exe.verify_imports,
+            vec![
+                "numpy".to_string(),
+                "myapp".to_string(),
+                "myapp.cli".to_string()
+            ]
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_make_python_module_source() -> Result<()> {
+    fn test_name() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
         add_exe(&mut env)?;
-        let m = env.eval("exe.make_python_module_source('foo', 'import bar')")?;
 
-        assert_eq!(m.get_type(), PythonModuleSourceValue::TYPE);
-        assert_eq!(m.get_attr("name").unwrap().to_str(), "foo");
-        assert_eq!(m.get_attr("source").unwrap().to_str(), "import bar");
-        assert_eq!(m.get_attr("is_package").unwrap().to_bool(), false);
+        let value = env.eval("exe.name")?;
+        assert_eq!(value.get_type(), "string");
+        assert_eq!(value.to_string(), "testapp");
+
+        let value = env.eval("exe.name = 'myapp'; exe.name")?;
+        assert_eq!(value.to_string(), "myapp");
 
         Ok(())
     }
 
     #[test]
-    fn test_make_python_module_source_callback() -> Result<()> {
+    fn test_name_rejects_path_separators() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
-        env.eval("dist = default_python_distribution()")?;
-        env.eval("policy = dist.make_python_packaging_policy()")?;
-        env.eval(
-            "def my_func(policy, resource):\n    resource.add_source = True\n    resource.add_bytecode_optimization_level_two = True\n",
-        )?;
-        env.eval("policy.register_resource_callback(my_func)")?;
-        env.eval("exe = dist.to_python_executable('testapp', packaging_policy = policy)")?;
-
-        let m = env.eval("exe.make_python_module_source('foo', 'import bar')")?;
+        add_exe(&mut env)?;
 
-        assert_eq!(m.get_type(), PythonModuleSourceValue::TYPE);
-        assert_eq!(m.get_attr("name").unwrap().to_str(), "foo");
-        assert_eq!(m.get_attr("source").unwrap().to_str(), "import bar");
-        assert_eq!(m.get_attr("is_package").unwrap().to_bool(), false);
-        assert_eq!(m.get_attr("add_source").unwrap().to_bool(), true);
-        assert_eq!(
-            m.get_attr("add_bytecode_optimization_level_two")
-                .unwrap()
-                .to_bool(),
-            true
-        );
+        assert!(env.eval("exe.name = 'bin/myapp'").is_err());
+        assert!(env.eval("exe.name = 'bin\\\\myapp'").is_err());
+        assert!(env.eval("exe.name = '..'").is_err());
+        assert!(env.eval("exe.name = '.'").is_err());
+        assert_eq!(env.eval("exe.name")?.to_string(), "testapp");
 
         Ok(())
     }
 
     #[test]
-    fn test_pip_download_pyflakes() -> Result<()> {
-        for target_triple in PYTHON_DISTRIBUTIONS.all_target_triples() {
-            let mut env = test_evaluation_context_builder()?
-                .build_target_triple(target_triple)
-                .into_context()?;
-
-            env.eval("dist = default_python_distribution()")?;
-            env.eval("exe = dist.to_python_executable('testapp')")?;
+    fn test_name_rejects_empty() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
 
-            let resources = env.eval("exe.pip_download(['pyflakes==2.2.0'])")?;
+        assert!(env.eval("exe.name = ''").is_err());
+        assert_eq!(env.eval("exe.name")?.to_string(), "testapp");
 
-            assert_eq!(resources.get_type(), "list");
+        Ok(())
+    }
 
-            let raw_it = resources.iter().unwrap();
-            let mut it = raw_it.iter();
+    #[test]
+    fn test_name_rejects_windows_reserved() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
 
-            let v = it.next().unwrap();
-            assert_eq!(v.get_type(), PythonModuleSourceValue::TYPE);
-            let x = v.downcast_ref::<PythonModuleSourceValue>().unwrap();
-            assert!(x.inner.package().starts_with("pyflakes"));
+        for name in &[
+            "CON", "prn", "Aux", "NUL", "COM1", "com9", "LPT1", "lpt9", "nul.exe",
+        ] {
+            assert!(env.eval(&format!("exe.name = '{}'", name)).is_err());
         }
+        assert_eq!(env.eval("exe.name")?.to_string(), "testapp");
+
+        let value = env.eval("exe.name = 'console'; exe.name")?;
+        assert_eq!(value.to_string(), "console");
+        let value = env.eval("exe.name = 'COM10'; exe.name")?;
+        assert_eq!(value.to_string(), "COM10");
 
         Ok(())
     }
 
     #[test]
-    fn test_pip_install_simple() -> Result<()> {
+    fn test_artifact_name_template() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
 
-        env.eval("dist = default_python_distribution()")?;
-        env.eval("policy = dist.make_python_packaging_policy()")?;
-        env.eval("policy.include_distribution_sources = False")?;
-        env.eval("exe = dist.to_python_executable('testapp', packaging_policy = policy)")?;
+        let value = env.eval("exe.artifact_name_template")?;
+        assert_eq!(value.get_type(), "NoneType");
+        let value = env.eval("exe.artifact_version")?;
+        assert_eq!(value.get_type(), "NoneType");
 
-        let resources = env.eval("exe.pip_install(['pyflakes==2.1.1'])")?;
-        assert_eq!(resources.get_type(), "list");
+        let value = env.eval(
+            "exe.artifact_name_template = 'dist/{target}/{name}-{version}{ext}'; exe.artifact_name_template",
+        )?;
+        assert_eq!(value.to_string(), "dist/{target}/{name}-{version}{ext}");
 
-        let raw_it = resources.iter().unwrap();
-        let mut it = raw_it.iter();
+        let value = env.eval("exe.artifact_version = '1.0'; exe.artifact_version")?;
+        assert_eq!(value.to_string(), "1.0");
 
-        let v = it.next().unwrap();
-        assert_eq!(v.get_type(), PythonModuleSourceValue::TYPE);
-        let x = v.downcast_ref::<PythonModuleSourceValue>().unwrap();
-        assert_eq!(x.inner.name, "pyflakes");
-        assert!(x.inner.is_package);
+        assert!(env
+            .eval("exe.artifact_name_template = '{name}-{unknown}'")
+            .is_err());
+        assert!(env.eval("exe.artifact_name_template = '{name'").is_err());
 
         Ok(())
     }
 
     #[test]
-    fn test_read_package_root_simple() -> Result<()> {
-        let temp_dir = tempfile::Builder::new()
-            .prefix("pyoxidizer-test")
-            .tempdir()?;
+    fn test_expand_artifact_name_template() -> Result<()> {
+        assert_eq!(
+            expand_artifact_name_template(
+                "dist/{target}/{name}-{version}{ext}",
+                "myapp",
+                "x86_64-pc-windows-msvc",
+                Some("1.0"),
+                true,
+            )?,
+            "dist/x86_64-pc-windows-msvc/myapp-1.0.exe"
+        );
+        assert_eq!(
+            expand_artifact_name_template(
+                "{profile}/{name}{ext}",
+                "myapp",
+                "x86_64-unknown-linux-gnu",
+                None,
+                false,
+            )?,
+            "debug/myapp"
+        );
+        assert!(expand_artifact_name_template(
+            "{name}-{version}",
+            "myapp",
+            "x86_64-unknown-linux-gnu",
+            None,
+            false
+        )
+        .is_err());
 
-        let root = temp_dir.path();
-        std::fs::create_dir(root.join("bar"))?;
-        let bar_init = root.join("bar").join("__init__.py");
-        std::fs::write(&bar_init, "# bar")?;
+        Ok(())
+    }
 
-        let foo_path = root.join("foo.py");
-        std::fs::write(&foo_path, "# foo")?;
+    #[test]
+    fn test_relative_path_module_search_paths() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
 
-        let baz_path = root.join("baz.py");
-        std::fs::write(&baz_path, "# baz")?;
+        let value = env.eval("exe.relative_path_module_search_paths")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
 
-        std::fs::create_dir(root.join("extra"))?;
-        let extra_path = root.join("extra").join("__init__.py");
-        std::fs::write(&extra_path, "# extra")?;
+        let value = env.eval(
+            "exe.relative_path_module_search_paths = True; exe.relative_path_module_search_paths",
+        )?;
+        assert!(value.to_bool());
 
-        let mut env = test_evaluation_context_builder()?.into_context()?;
-        env.eval("dist = default_python_distribution()")?;
-        env.eval("policy = dist.make_python_packaging_policy()")?;
-        env.eval("policy.include_distribution_sources = False")?;
-        env.eval("exe = dist.to_python_executable('testapp', packaging_policy = policy)")?;
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+        assert!(exe.exe.relative_path_module_search_paths());
 
-        let resources = env.eval(&format!(
-            "exe.read_package_root(\"{}\", packages=['foo', 'bar'])",
-            root.display()
-        ))?;
+        Ok(())
+    }
 
-        assert_eq!(resources.get_type(), "list");
-        assert_eq!(resources.length().unwrap(), 2);
+    #[test]
+    fn test_require_packed_imports() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
 
-        let raw_it = resources.iter().unwrap();
-        let mut it = raw_it.iter();
+        let value = env.eval("exe.require_packed_imports")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
 
-        let v = it.next().unwrap();
-        assert_eq!(v.get_type(), PythonModuleSourceValue::TYPE);
-        let x = v.downcast_ref::<PythonModuleSourceValue>().unwrap();
-        assert_eq!(x.inner.name, "bar");
-        assert!(x.inner.is_package);
-        assert_eq!(x.inner.source.resolve().unwrap(), b"# bar");
+        let value = env.eval("exe.require_packed_imports = True; exe.require_packed_imports")?;
+        assert!(value.to_bool());
 
-        let v = it.next().unwrap();
-        assert_eq!(v.get_type(), PythonModuleSourceValue::TYPE);
-        let x = v.downcast_ref::<PythonModuleSourceValue>().unwrap();
-        assert_eq!(x.inner.name, "foo");
-        assert!(!x.inner.is_package);
-        assert_eq!(x.inner.source.resolve().unwrap(), b"# foo");
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+        assert!(exe.require_packed_imports);
 
         Ok(())
     }
@@ -1289,6 +3444,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_executable_compression() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.executable_compression")?;
+        assert_eq!(value.get_type(), "string");
+        assert_eq!(value.to_string(), "none");
+
+        let value = env.eval("exe.executable_compression = 'upx'; exe.executable_compression")?;
+        assert_eq!(value.get_type(), "string");
+        assert_eq!(value.to_string(), "upx");
+
+        assert!(env.eval("exe.executable_compression = 'zip'").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_windows_subsystem() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
@@ -1305,6 +3478,22 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_frozen_modules() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        eval_assert(&mut env, "exe.frozen_modules == []")?;
+
+        env.eval("exe.frozen_modules = ['codecs', 'encodings']")?;
+        eval_assert(&mut env, "exe.frozen_modules == ['codecs', 'encodings']")?;
+
+        env.eval("exe.frozen_modules = None")?;
+        eval_assert(&mut env, "exe.frozen_modules == []")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_tcl_files_path() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
@@ -1323,6 +3512,312 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_rust_project() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let hooks_path = temp_dir.path().join("hooks.rs");
+        std::fs::write(&hooks_path, "// hooks")?;
+        let main_path = temp_dir.path().join("custom_main.rs");
+        std::fs::write(&main_path, "fn main() {}")?;
+        let invalid_path = temp_dir.path().join("main.rs");
+        std::fs::write(&invalid_path, "fn main() {}")?;
+
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        env.eval("exe.add_cargo_feature('pyembed/serialization')")?;
+        env.eval("exe.add_cargo_feature('pyembed/serialization')")?;
+        env.eval(&format!(
+            "exe.add_rust_source(\"{}\")",
+            hooks_path.display()
+        ))?;
+        env.eval(&format!("exe.set_rust_main(\"{}\")", main_path.display()))?;
+
+        assert!(env
+            .eval(&format!(
+                "exe.add_rust_source(\"{}\")",
+                hooks_path.display()
+            ))
+            .is_err());
+        assert!(env
+            .eval(&format!(
+                "exe.add_rust_source(\"{}\")",
+                invalid_path.display()
+            ))
+            .is_err());
+        assert!(env.eval("exe.add_rust_source('missing.rs')").is_err());
+
+        let value = env.eval("exe.rust_pre_init_hook")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value =
+            env.eval("exe.rust_pre_init_hook = 'hooks::pre_init'; exe.rust_pre_init_hook")?;
+        assert_eq!(value.get_type(), "string");
+        assert_eq!(value.to_string(), "hooks::pre_init");
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+
+        assert_eq!(
+            exe.exe.rust_project(),
+            &RustProjectCustomizations {
+                cargo_features: vec!["pyembed/serialization".to_string()],
+                sources: vec![hooks_path],
+                pre_init_hook: Some("hooks::pre_init".to_string()),
+                main_path: Some(main_path),
+                ..RustProjectCustomizations::default()
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_shell_completions_invalid() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        assert!(env
+            .eval("exe.to_shell_completions(shells = ['tcsh'])")
+            .is_err());
+        assert!(env
+            .eval("exe.to_shell_completions(args = '--help')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_version_info() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        assert!(env
+            .eval("exe.windows_version_info(file_version = '1.2.3-beta')")
+            .is_err());
+        assert!(env
+            .eval("exe.windows_version_info(product_version = '1.2.3.4.5')")
+            .is_err());
+
+        env.eval("exe.windows_version_info(file_version = '1.2.3', product_name = 'My App', copyright = 'Copyright 2020 Me')")?;
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+
+        assert_eq!(
+            exe.exe.rust_project().windows_version_info,
+            Some(WindowsVersionInfo {
+                file_version: Some("1.2.3".to_string()),
+                product_name: Some("My App".to_string()),
+                copyright: Some("Copyright 2020 Me".to_string()),
+                ..WindowsVersionInfo::default()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_service() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        assert!(env.eval("exe.windows_service('', 'app:run')").is_err());
+        assert!(env.eval("exe.windows_service('app', 'app')").is_err());
+        assert!(env
+            .eval("exe.windows_service('app', 'app:run', stop_entry_point = 'app.stop()')")
+            .is_err());
+
+        env.eval("exe.windows_service('app', 'app.service:run', description = 'Runs the app', stop_entry_point = 'app.service:stop')")?;
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+
+        assert_eq!(
+            exe.exe.rust_project().windows_service,
+            Some(WindowsService {
+                name: "app".to_string(),
+                display_name: "app".to_string(),
+                description: Some("Runs the app".to_string()),
+                entry_point: "app.service:run".to_string(),
+                stop_entry_point: Some("app.service:stop".to_string()),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_self_update() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let key = "ab".repeat(32);
+
+        assert!(env
+            .eval(&format!("exe.self_update('manifest', '{}', '1.0')", key))
+            .is_err());
+        assert!(env
+            .eval("exe.self_update('https://example.com/manifest', 'abcd', '1.0')")
+            .is_err());
+        assert!(env
+            .eval(&format!(
+                "exe.self_update('https://example.com/manifest', '{}', '')",
+                key
+            ))
+            .is_err());
+        assert!(env
+            .eval(&format!(
+                "exe.self_update('https://example.com/manifest', '{}', '1.0-beta')",
+                key
+            ))
+            .is_err());
+
+        env.eval(&format!(
+            "exe.self_update('https://example.com/manifest', '{}', '1.0')",
+            key.to_uppercase()
+        ))?;
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+
+        assert_eq!(
+            exe.exe.python_interpreter_config().self_update,
+            Some(SelfUpdateConfig {
+                manifest_url: "https://example.com/manifest".to_string(),
+                public_key: key,
+                version: "1.0".to_string(),
+                staging_directory: PathBuf::from("$ORIGIN/.update"),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remote_resources() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let key = "ab".repeat(32);
+
+        assert!(env
+            .eval(&format!(
+                "exe.remote_resources('http://example.com/index', '{}', ['foo'])",
+                key
+            ))
+            .is_err());
+        assert!(env
+            .eval("exe.remote_resources('https://example.com/index', 'abcd', ['foo'])")
+            .is_err());
+        for packages in &["[]", "['foo..bar']", "['']", "'foo'"] {
+            assert!(env
+                .eval(&format!(
+                    "exe.remote_resources('https://example.com/index', '{}', {})",
+                    key, packages
+                ))
+                .is_err());
+        }
+
+        env.eval(&format!(
+            "exe.remote_resources('https://example.com/index', '{}', ['foo', 'bar.baz'])",
+            key.to_uppercase()
+        ))?;
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+
+        assert_eq!(
+            exe.exe.python_interpreter_config().remote_resources,
+            Some(RemoteResourcesConfig {
+                index_url: "https://example.com/index".to_string(),
+                public_key: key,
+                packages: vec!["foo".to_string(), "bar.baz".to_string()],
+                cache_directory: PathBuf::from("$ORIGIN/.remote-resources"),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_linking() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        env.eval("exe.link_args(['-static-libstdc++', '-Wl,-rpath,$ORIGIN/lib'])")?;
+        env.eval("exe.static_crt(True)")?;
+        env.eval("exe.linker('clang')")?;
+        env.eval(
+            "exe.linker('riscv64-linux-gnu-gcc', target_triple = 'riscv64gc-unknown-linux-gnu')",
+        )?;
+
+        assert!(env.eval("exe.link_args(['-L /opt'])").is_err());
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+
+        let rust_project = exe.exe.rust_project();
+        assert_eq!(
+            rust_project.link_args,
+            vec![
+                "-static-libstdc++".to_string(),
+                "-Wl,-rpath,$ORIGIN/lib".to_string()
+            ]
+        );
+        assert_eq!(rust_project.static_crt, Some(true));
+        assert_eq!(
+            rust_project.linkers.get(exe.exe.target_triple()),
+            Some(&PathBuf::from("clang"))
+        );
+        assert_eq!(
+            rust_project.linkers.get("riscv64gc-unknown-linux-gnu"),
+            Some(&PathBuf::from("riscv64-linux-gnu-gcc"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_info() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.strip_symbols")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
+
+        let value = env.eval("exe.split_debug_info")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
+
+        env.eval("exe.strip_symbols = True")?;
+        env.eval("exe.split_debug_info = True")?;
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+
+        assert_eq!(
+            exe.exe.debug_info(),
+            &DebugInfoSettings {
+                strip: true,
+                split: true
+            }
+        );
+        assert_eq!(
+            exe.exe
+                .python_interpreter_config()
+                .build_info
+                .get("debug_info"),
+            Some(&"split,stripped".to_string())
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_to_wix_bundle_builder_callback() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
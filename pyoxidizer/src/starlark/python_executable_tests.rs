@@ -0,0 +1,171 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::{
+        environment::target_runner,
+        import_verification::PYTHON_PATH_ENV_VARS,
+        py_packaging::{binary::PythonBinaryBuilder, config::PyembedPythonInterpreterConfig},
+        starlark::{
+            env::{get_context, PyOxidizerEnvironmentContext},
+            python_executable::build_python_executable_target,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    slog::warn,
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{ResolvedTarget, ResolvedTargetValue, RunMode},
+    std::sync::Arc,
+};
+
+/// Configure an interpreter to only import resources packaged with the executable.
+///
+/// What the interpreter runs is reset and must be defined by the caller.
+pub fn isolate_interpreter_config(config: &mut PyembedPythonInterpreterConfig) {
+    config.oxidized_importer = true;
+    config.filesystem_importer = false;
+    config.multicall_run_modules.clear();
+    config.multicall_subcommand = false;
+    config.multicall_env = None;
+    config.config.use_environment = Some(false);
+    config.config.module_search_paths = None;
+    config.config.parse_argv = Some(false);
+    config.config.run_command = None;
+    config.config.run_filename = None;
+    config.config.run_module = None;
+}
+
+/// Runs a Python module in a built executable to test it.
+///
+/// The executable is a variant of the application's executable, built
+/// separately, whose interpreter runs the test module and can only import
+/// resources packed in the executable. The application's executable itself
+/// isn't run, so behavior depending on the interpreter configuration it
+/// ships with isn't tested.
+pub struct PythonExecutableTestsValue {
+    pub exe: Arc<dyn PythonBinaryBuilder>,
+    pub args: Vec<String>,
+}
+
+impl TypedValue for PythonExecutableTestsValue {
+    type Holder = Mutable<PythonExecutableTestsValue>;
+    const TYPE: &'static str = "PythonExecutableTests";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl PythonExecutableTestsValue {
+    /// Derive an instance from the executable being tested.
+    ///
+    /// `exe` is cloned and the clone's interpreter configuration is altered
+    /// by `isolate_interpreter_config()`. `exe` isn't modified.
+    pub fn new(exe: &dyn PythonBinaryBuilder, module: String, args: Vec<String>) -> Self {
+        let mut exe = exe.clone_trait();
+
+        // We just cloned the instance, so we have the only reference.
+        let config = Arc::get_mut(&mut exe)
+            .expect("cloned executable is uniquely owned")
+            .python_interpreter_config_mut();
+
+        isolate_interpreter_config(config);
+        config.config.run_module = Some(module);
+
+        Self { exe, args }
+    }
+
+    fn build(
+        &self,
+        type_values: &TypeValues,
+        target: &str,
+        context: &PyOxidizerEnvironmentContext,
+    ) -> Result<ResolvedTarget> {
+        let (built, _) =
+            build_python_executable_target(type_values, target, context, self.exe.as_ref())?;
+
+        let exe_path = match &built.run_mode {
+            RunMode::Path { path } => path.clone(),
+            _ => return Err(anyhow!("built executable does not have a path")),
+        };
+
+        let runner = target_runner(self.exe.target_triple()).ok_or_else(|| {
+            anyhow!(
+                "unable to run {} executables on this machine",
+                self.exe.target_triple()
+            )
+        })?;
+
+        warn!(
+            context.logger(),
+            "running tests: {} {}",
+            exe_path.display(),
+            self.args.join(" ")
+        );
+
+        // Tests are run from the build directory so files in the project
+        // directory can't influence them.
+        let mut command = std::process::Command::new(&exe_path);
+        command.args(&self.args).current_dir(&built.output_path);
+        for key in PYTHON_PATH_ENV_VARS {
+            command.env_remove(key);
+        }
+        runner.configure(&mut command);
+
+        let status = command
+            .status()
+            .with_context(|| format!("running {}", exe_path.display()))?;
+
+        if !status.success() {
+            return Err(anyhow!("tests failed: {}", status));
+        }
+
+        warn!(context.logger(), "tests passed");
+
+        Ok(ResolvedTarget {
+            run_mode: RunMode::None,
+            output_path: built.output_path,
+        })
+    }
+
+    fn build_starlark(&self, type_values: &TypeValues, target: String) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        Ok(Value::new(ResolvedTargetValue {
+            inner: self
+                .build(type_values, &target, &pyoxidizer_context)
+                .map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER",
+                        message: e.to_string(),
+                        label: "build()".to_string(),
+                    })
+                })?,
+        }))
+    }
+}
+
+starlark_module! { python_executable_tests_module =>
+    PythonExecutableTests.build(
+        env env,
+        this,
+        target: String
+    ) {
+        let this = this.downcast_ref::<PythonExecutableTestsValue>().unwrap();
+        this.build_starlark(env, target)
+    }
+}
@@ -8,19 +8,31 @@ use {
     python_packaging::{
         interpreter::{
             Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, MemoryAllocatorBackend,
-            PythonInterpreterProfile, TerminfoResolution,
+            ModuleSearchPathsMode, PythonInterpreterProfile, RestrictedFacility,
+            RestrictedModeConfig, RunModuleMode, RunReportSection, TerminfoResolution,
         },
         resource::BytecodeOptimizationLevel,
     },
-    starlark::values::{
-        error::{
-            RuntimeError, UnsupportedOperation, ValueError, INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+    starlark::{
+        environment::TypeValues,
+        starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+        starlark_signature_extraction, starlark_signatures,
+        values::{
+            error::{
+                RuntimeError, UnsupportedOperation, ValueError, INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+            },
+            none::NoneType,
+            {Mutable, TypedValue, Value, ValueResult},
         },
-        none::NoneType,
-        {Mutable, TypedValue, Value, ValueResult},
     },
-    starlark_dialect_build_targets::{ToOptional, TryToOptional},
-    std::convert::TryFrom,
+    starlark_dialect_build_targets::{
+        optional_dict_arg, optional_list_arg, ToOptional, TryToOptional,
+    },
+    std::{
+        collections::{BTreeMap, HashMap},
+        convert::TryFrom,
+        path::PathBuf,
+    },
 };
 
 impl ToValue for PythonInterpreterProfile {
@@ -86,6 +98,18 @@ impl ToValue for MemoryAllocatorBackend {
     }
 }
 
+impl ToValue for RunModuleMode {
+    fn to_value(&self) -> Value {
+        Value::from(self.to_string())
+    }
+}
+
+impl ToValue for ModuleSearchPathsMode {
+    fn to_value(&self) -> Value {
+        Value::from(self.to_string())
+    }
+}
+
 fn bytecode_optimization_level_try_to_optional(
     v: Value,
 ) -> Result<Option<BytecodeOptimizationLevel>, ValueError> {
@@ -114,6 +138,66 @@ impl PythonInterpreterConfigValue {
     pub fn new(inner: PyembedPythonInterpreterConfig) -> Self {
         Self { inner }
     }
+
+    /// Obtain the restricted mode config, which must be enabled.
+    fn restricted_mode_mut(
+        &mut self,
+        attribute: &str,
+    ) -> Result<&mut RestrictedModeConfig, ValueError> {
+        self.inner.restricted_mode.as_mut().ok_or_else(|| {
+            ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: "restricted_mode must be enabled first".to_string(),
+                label: format!("{}.{}", Self::TYPE, attribute),
+            })
+        })
+    }
+
+    /// Set a bundle of settings from a named preset.
+    ///
+    /// `isolated-app` shields the application from the environment it runs
+    /// in. `python-compatible` behaves like a regular `python` executable.
+    /// Both presets set the same attributes, so applying one after the other
+    /// leaves nothing behind.
+    #[allow(clippy::unnecessary_wraps)]
+    fn starlark_apply_profile(&mut self, name: String) -> ValueResult {
+        let config = &mut self.inner.config;
+
+        match name.as_str() {
+            "isolated-app" => {
+                config.profile = PythonInterpreterProfile::Isolated;
+                config.isolated = Some(true);
+                config.use_environment = Some(false);
+                config.user_site_directory = Some(false);
+                config.parse_argv = Some(false);
+                config.site_import = Some(false);
+                config.write_bytecode = Some(false);
+                self.inner.filesystem_importer = false;
+            }
+            "python-compatible" => {
+                config.profile = PythonInterpreterProfile::Python;
+                config.isolated = Some(false);
+                config.use_environment = Some(true);
+                config.user_site_directory = Some(true);
+                config.parse_argv = Some(true);
+                config.site_import = Some(true);
+                config.write_bytecode = Some(true);
+                self.inner.filesystem_importer = true;
+            }
+            _ => {
+                return Err(ValueError::from(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: format!(
+                        "unknown profile: {}; must be isolated-app or python-compatible",
+                        name
+                    ),
+                    label: format!("{}.apply_profile()", Self::TYPE),
+                }))
+            }
+        }
+
+        Ok(Value::from(NoneType::None))
+    }
 }
 
 impl TypedValue for PythonInterpreterConfigValue {
@@ -198,10 +282,95 @@ impl TypedValue for PythonInterpreterConfigValue {
             "oxidized_importer" => Value::from(self.inner.oxidized_importer),
             "filesystem_importer" => Value::from(self.inner.filesystem_importer),
             "argvb" => Value::from(self.inner.argvb),
+            "sys_orig_argv" => Value::from(self.inner.sys_orig_argv),
             "sys_frozen" => Value::from(self.inner.sys_frozen),
             "sys_meipass" => Value::from(self.inner.sys_meipass),
             "terminfo_resolution" => self.inner.terminfo_resolution.to_value(),
             "write_modules_directory_env" => self.inner.write_modules_directory_env.to_value(),
+            "run_report_directory_env" => self.inner.run_report_directory_env.to_value(),
+            "run_report_sections" => Value::from(
+                self.inner
+                    .run_report_sections
+                    .iter()
+                    .map(|s| Value::from(s.to_string()))
+                    .collect::<Vec<_>>(),
+            ),
+            "origin_env" => self.inner.origin_env.to_value(),
+            "resource_payload_xor_key" => self.inner.resource_payload_xor_key.to_value(),
+            "multicall_run_modules" => Value::try_from(
+                self.inner
+                    .multicall_run_modules
+                    .clone()
+                    .into_iter()
+                    .collect::<HashMap<_, _>>(),
+            )?,
+            "multicall_subcommand" => Value::from(self.inner.multicall_subcommand),
+            "multicall_env" => self.inner.multicall_env.to_value(),
+            "run_command_env" => self.inner.run_command_env.to_value(),
+            "line_editing" => Value::from(self.inner.line_editing),
+            "python_logging_to_rust" => Value::from(self.inner.python_logging_to_rust),
+            "ctypes_libraries" => Value::try_from(
+                self.inner
+                    .ctypes_libraries
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.display().to_string()))
+                    .collect::<HashMap<_, _>>(),
+            )?,
+            "gettext_catalogs" => Value::from(self.inner.gettext_catalogs),
+            "gettext_cache_directory" => self
+                .inner
+                .gettext_cache_directory
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .to_value(),
+            "run_module_mode" => self.inner.run_module_mode.to_value(),
+            "module_search_paths_mode" => self.inner.module_search_paths_mode.to_value(),
+            "working_directory" => self
+                .inner
+                .working_directory
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .to_value(),
+            "umask" => match self.inner.umask {
+                Some(mask) => Value::from(mask as i64),
+                None => Value::from(NoneType::None),
+            },
+            "process_title" => self.inner.process_title.to_value(),
+            "importer_debug" => self.inner.importer_debug.to_value(),
+            "restricted_mode" => Value::from(self.inner.restricted_mode.is_some()),
+            "restricted_mode_allowed_facilities" => match &self.inner.restricted_mode {
+                Some(config) => Value::from(
+                    config
+                        .allowed_facilities
+                        .iter()
+                        .map(|f| Value::from(f.to_string()))
+                        .collect::<Vec<_>>(),
+                ),
+                None => Value::from(NoneType::None),
+            },
+            "restricted_mode_denied_modules" => match &self.inner.restricted_mode {
+                Some(config) => Value::from(
+                    config
+                        .denied_modules
+                        .iter()
+                        .map(|m| Value::from(m.as_str()))
+                        .collect::<Vec<_>>(),
+                ),
+                None => Value::from(NoneType::None),
+            },
+            "audit_events" => Value::from(
+                self.inner
+                    .audit_events
+                    .iter()
+                    .map(|e| Value::from(e.as_str()))
+                    .collect::<Vec<_>>(),
+            ),
+            "audit_log" => self
+                .inner
+                .audit_log
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .to_value(),
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: UnsupportedOperation::GetAttr(attr.to_string()),
@@ -281,10 +450,35 @@ impl TypedValue for PythonInterpreterConfigValue {
                 | "oxidized_importer"
                 | "filesystem_importer"
                 | "argvb"
+                | "sys_orig_argv"
                 | "sys_frozen"
                 | "sys_meipass"
                 | "terminfo_resolution"
                 | "write_modules_directory_env"
+                | "run_report_directory_env"
+                | "run_report_sections"
+                | "origin_env"
+                | "resource_payload_xor_key"
+                | "multicall_run_modules"
+                | "multicall_subcommand"
+                | "multicall_env"
+                | "run_command_env"
+                | "line_editing"
+                | "python_logging_to_rust"
+                | "ctypes_libraries"
+                | "gettext_catalogs"
+                | "gettext_cache_directory"
+                | "run_module_mode"
+                | "module_search_paths_mode"
+                | "working_directory"
+                | "umask"
+                | "process_title"
+                | "importer_debug"
+                | "restricted_mode"
+                | "restricted_mode_allowed_facilities"
+                | "restricted_mode_denied_modules"
+                | "audit_events"
+                | "audit_log"
         ))
     }
 
@@ -510,7 +704,7 @@ impl TypedValue for PythonInterpreterConfigValue {
                 self.inner.config.user_site_directory = value.to_optional();
             }
             "verbose" => {
-                self.inner.config.configure_locale = value.to_optional();
+                self.inner.config.verbose = value.to_optional();
             }
             "warn_options" => {
                 self.inner.config.warn_options = value.try_to_optional()?;
@@ -555,6 +749,9 @@ impl TypedValue for PythonInterpreterConfigValue {
             "argvb" => {
                 self.inner.argvb = value.to_bool();
             }
+            "sys_orig_argv" => {
+                self.inner.sys_orig_argv = value.to_bool();
+            }
             "sys_frozen" => {
                 self.inner.sys_frozen = value.to_bool();
             }
@@ -574,6 +771,183 @@ impl TypedValue for PythonInterpreterConfigValue {
             "write_modules_directory_env" => {
                 self.inner.write_modules_directory_env = value.to_optional();
             }
+            "run_report_directory_env" => {
+                self.inner.run_report_directory_env = value.to_optional();
+            }
+            "run_report_sections" => {
+                optional_list_arg(attribute, "string", &value)?;
+
+                self.inner.run_report_sections = if value.get_type() == "list" {
+                    value
+                        .iter()?
+                        .iter()
+                        .map(|v| RunReportSection::try_from(v.to_string().as_str()))
+                        .collect::<Result<_, _>>()
+                        .map_err(|e| {
+                            ValueError::from(RuntimeError {
+                                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                                message: e,
+                                label: format!("{}.{}", Self::TYPE, attribute),
+                            })
+                        })?
+                } else {
+                    RunReportSection::all().into_iter().collect()
+                };
+            }
+            "origin_env" => {
+                self.inner.origin_env = value.to_optional();
+            }
+            "resource_payload_xor_key" => {
+                self.inner.resource_payload_xor_key = value.to_optional();
+            }
+            "multicall_run_modules" => {
+                optional_dict_arg(attribute, "string", "string", &value)?;
+
+                let mut run_modules = BTreeMap::new();
+
+                if value.get_type() == "dict" {
+                    for k in &value.iter()? {
+                        let v = value.at(k.clone())?;
+                        run_modules.insert(k.to_string(), v.to_string());
+                    }
+                }
+
+                self.inner.multicall_run_modules = run_modules;
+            }
+            "multicall_subcommand" => {
+                self.inner.multicall_subcommand = value.to_bool();
+            }
+            "multicall_env" => {
+                self.inner.multicall_env = value.to_optional();
+            }
+            "run_command_env" => {
+                self.inner.run_command_env = value.to_optional();
+            }
+            "line_editing" => {
+                self.inner.line_editing = value.to_bool();
+            }
+            "python_logging_to_rust" => {
+                self.inner.python_logging_to_rust = value.to_bool();
+            }
+            "ctypes_libraries" => {
+                optional_dict_arg(attribute, "string", "string", &value)?;
+
+                let mut libraries = BTreeMap::new();
+
+                if value.get_type() == "dict" {
+                    for k in &value.iter()? {
+                        let v = value.at(k.clone())?;
+                        libraries.insert(k.to_string(), PathBuf::from(v.to_string()));
+                    }
+                }
+
+                self.inner.ctypes_libraries = libraries;
+            }
+            "gettext_catalogs" => {
+                self.inner.gettext_catalogs = value.to_bool();
+            }
+            "gettext_cache_directory" => {
+                let path: Option<String> = value.to_optional();
+                self.inner.gettext_cache_directory = path.map(PathBuf::from);
+            }
+            "run_module_mode" => {
+                self.inner.run_module_mode = RunModuleMode::try_from(value.to_string().as_str())
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e,
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?;
+            }
+            "module_search_paths_mode" => {
+                self.inner.module_search_paths_mode =
+                    ModuleSearchPathsMode::try_from(value.to_string().as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e,
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?;
+            }
+            "working_directory" => {
+                let path: Option<String> = value.to_optional();
+                self.inner.working_directory = path.map(PathBuf::from);
+            }
+            "umask" => {
+                let mask: Option<i64> = value.try_to_optional()?;
+
+                self.inner.umask = match mask {
+                    Some(mask) if (0..=0o777).contains(&mask) => Some(mask as u32),
+                    Some(mask) => {
+                        return Err(ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: format!("umask must be between 0 and 0o777; got {:#o}", mask),
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        }));
+                    }
+                    None => None,
+                };
+            }
+            "process_title" => {
+                self.inner.process_title = value.to_optional();
+            }
+            "importer_debug" => {
+                self.inner.importer_debug = value.to_optional();
+            }
+            "restricted_mode" => {
+                if !value.to_bool() {
+                    self.inner.restricted_mode = None;
+                } else if self.inner.restricted_mode.is_none() {
+                    self.inner.restricted_mode = Some(RestrictedModeConfig::default());
+                }
+            }
+            "restricted_mode_allowed_facilities" => {
+                optional_list_arg(attribute, "string", &value)?;
+
+                let allowed_facilities = if value.get_type() == "list" {
+                    value
+                        .iter()?
+                        .iter()
+                        .map(|v| RestrictedFacility::try_from(v.to_string().as_str()))
+                        .collect::<Result<_, _>>()
+                        .map_err(|e| {
+                            ValueError::from(RuntimeError {
+                                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                                message: e,
+                                label: format!("{}.{}", Self::TYPE, attribute),
+                            })
+                        })?
+                } else {
+                    Default::default()
+                };
+
+                self.restricted_mode_mut(attribute)?.allowed_facilities = allowed_facilities;
+            }
+            "restricted_mode_denied_modules" => {
+                optional_list_arg(attribute, "string", &value)?;
+
+                let denied_modules = if value.get_type() == "list" {
+                    value.iter()?.iter().map(|v| v.to_string()).collect()
+                } else {
+                    vec![]
+                };
+
+                self.restricted_mode_mut(attribute)?.denied_modules = denied_modules;
+            }
+            "audit_events" => {
+                optional_list_arg(attribute, "string", &value)?;
+
+                self.inner.audit_events = if value.get_type() == "list" {
+                    value.iter()?.iter().map(|v| v.to_string()).collect()
+                } else {
+                    vec![]
+                };
+            }
+            "audit_log" => {
+                let path: Option<String> = value.to_optional();
+                self.inner.audit_log = path.map(PathBuf::from);
+            }
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: UnsupportedOperation::SetAttr(attr.to_string()),
@@ -587,6 +961,13 @@ impl TypedValue for PythonInterpreterConfigValue {
     }
 }
 
+starlark_module! { python_interpreter_config_module =>
+    PythonInterpreterConfig.apply_profile(this, name: String) {
+        let mut this = this.downcast_mut::<PythonInterpreterConfigValue>().unwrap().unwrap();
+        this.starlark_apply_profile(name)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::starlark::eval::EvaluationContext;
@@ -616,6 +997,42 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_apply_profile() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval("config.apply_profile('python-compatible')")?;
+        eval_assert(&mut env, "config.config_profile == 'python'")?;
+        eval_assert(&mut env, "config.isolated == False")?;
+        eval_assert(&mut env, "config.use_environment == True")?;
+        eval_assert(&mut env, "config.user_site_directory == True")?;
+        eval_assert(&mut env, "config.parse_argv == True")?;
+        eval_assert(&mut env, "config.filesystem_importer == True")?;
+
+        eval_assert(&mut env, "config.site_import == True")?;
+        eval_assert(&mut env, "config.write_bytecode == True")?;
+
+        // Nothing from python-compatible remains.
+        env.eval("config.apply_profile('isolated-app')")?;
+        eval_assert(&mut env, "config.config_profile == 'isolated'")?;
+        eval_assert(&mut env, "config.isolated == True")?;
+        eval_assert(&mut env, "config.use_environment == False")?;
+        eval_assert(&mut env, "config.user_site_directory == False")?;
+        eval_assert(&mut env, "config.parse_argv == False")?;
+        eval_assert(&mut env, "config.site_import == False")?;
+        eval_assert(&mut env, "config.write_bytecode == False")?;
+        eval_assert(&mut env, "config.filesystem_importer == False")?;
+
+        env.eval("config.apply_profile('python-compatible')")?;
+        eval_assert(&mut env, "config.config_profile == 'python'")?;
+        eval_assert(&mut env, "config.site_import == True")?;
+        eval_assert(&mut env, "config.filesystem_importer == True")?;
+
+        assert!(env.eval("config.apply_profile('unknown')").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_allocator() -> Result<()> {
         let mut env = get_env()?;
@@ -853,6 +1270,11 @@ mod tests {
 
         eval_assert(&mut env, "config.hash_seed == None")?;
 
+        env.eval("config.hash_seed = 42")?;
+        eval_assert(&mut env, "config.hash_seed == 42")?;
+        env.eval("config.hash_seed = None")?;
+        eval_assert(&mut env, "config.hash_seed == None")?;
+
         Ok(())
     }
 
@@ -880,6 +1302,9 @@ mod tests {
 
         eval_assert(&mut env, "config.inspect == None")?;
 
+        env.eval("config.inspect = True")?;
+        eval_assert(&mut env, "config.inspect == True")?;
+
         Ok(())
     }
 
@@ -898,6 +1323,9 @@ mod tests {
 
         eval_assert(&mut env, "config.interactive == None")?;
 
+        env.eval("config.interactive = True")?;
+        eval_assert(&mut env, "config.interactive == True")?;
+
         Ok(())
     }
 
@@ -1016,6 +1444,9 @@ mod tests {
 
         eval_assert(&mut env, "config.quiet == None")?;
 
+        env.eval("config.quiet = True")?;
+        eval_assert(&mut env, "config.quiet == True")?;
+
         Ok(())
     }
 
@@ -1115,6 +1546,10 @@ mod tests {
 
         eval_assert(&mut env, "config.verbose == None")?;
 
+        env.eval("config.verbose = True")?;
+        eval_assert(&mut env, "config.verbose == True")?;
+        eval_assert(&mut env, "config.configure_locale == None")?;
+
         Ok(())
     }
 
@@ -1133,6 +1568,9 @@ mod tests {
 
         eval_assert(&mut env, "config.write_bytecode == None")?;
 
+        env.eval("config.write_bytecode = False")?;
+        eval_assert(&mut env, "config.write_bytecode == False")?;
+
         Ok(())
     }
 
@@ -1256,6 +1694,18 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_sys_orig_argv() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.sys_orig_argv == False")?;
+
+        env.eval("config.sys_orig_argv = True")?;
+        eval_assert(&mut env, "config.sys_orig_argv == True")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_sys_frozen() -> Result<()> {
         let mut env = get_env()?;
@@ -1297,4 +1747,343 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_run_report_directory_env() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.run_report_directory_env == None")?;
+
+        env.eval("config.run_report_directory_env = 'RUN_REPORT_DIR'")?;
+        eval_assert(
+            &mut env,
+            "config.run_report_directory_env == 'RUN_REPORT_DIR'",
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_report_sections() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(
+            &mut env,
+            "config.run_report_sections == ['modules', 'import_graph', 'import_times', 'resources']",
+        )?;
+
+        env.eval("config.run_report_sections = ['resources', 'modules']")?;
+        eval_assert(
+            &mut env,
+            "config.run_report_sections == ['modules', 'resources']",
+        )?;
+
+        env.eval("config.run_report_sections = None")?;
+        eval_assert(
+            &mut env,
+            "config.run_report_sections == ['modules', 'import_graph', 'import_times', 'resources']",
+        )?;
+
+        assert!(env.eval("config.run_report_sections = ['bogus']").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_origin_env() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.origin_env == None")?;
+
+        env.eval("config.origin_env = 'MYAPP_RESOURCES'")?;
+        eval_assert(&mut env, "config.origin_env == 'MYAPP_RESOURCES'")?;
+
+        env.eval("config.origin_env = None")?;
+        eval_assert(&mut env, "config.origin_env == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resource_payload_xor_key() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.resource_payload_xor_key == None")?;
+
+        env.eval("config.resource_payload_xor_key = 'secret'")?;
+        eval_assert(&mut env, "config.resource_payload_xor_key == 'secret'")?;
+
+        env.eval("config.resource_payload_xor_key = None")?;
+        eval_assert(&mut env, "config.resource_payload_xor_key == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multicall_run_modules() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.multicall_run_modules == {}")?;
+
+        env.eval("config.multicall_run_modules = {'mytool': 'mytool.main', 'mytool-admin': 'mytool.admin'}")?;
+        eval_assert(
+            &mut env,
+            "config.multicall_run_modules == {'mytool': 'mytool.main', 'mytool-admin': 'mytool.admin'}",
+        )?;
+
+        env.eval("config.multicall_run_modules = None")?;
+        eval_assert(&mut env, "config.multicall_run_modules == {}")?;
+
+        assert!(env
+            .eval("config.multicall_run_modules = {'foo': 42}")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multicall_subcommand() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.multicall_subcommand == False")?;
+
+        env.eval("config.multicall_subcommand = True")?;
+        eval_assert(&mut env, "config.multicall_subcommand == True")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multicall_env() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.multicall_env == None")?;
+
+        env.eval("config.multicall_env = 'MYAPP_ENTRYPOINT'")?;
+        eval_assert(&mut env, "config.multicall_env == 'MYAPP_ENTRYPOINT'")?;
+
+        env.eval("config.multicall_env = None")?;
+        eval_assert(&mut env, "config.multicall_env == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_command_env() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.run_command_env == None")?;
+
+        env.eval("config.run_command_env = 'MYAPP_RUN_COMMAND'")?;
+        eval_assert(&mut env, "config.run_command_env == 'MYAPP_RUN_COMMAND'")?;
+
+        env.eval("config.run_command_env = None")?;
+        eval_assert(&mut env, "config.run_command_env == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_editing() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.line_editing == False")?;
+
+        env.eval("config.line_editing = True")?;
+        eval_assert(&mut env, "config.line_editing == True")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_python_logging_to_rust() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.python_logging_to_rust == False")?;
+
+        env.eval("config.python_logging_to_rust = True")?;
+        eval_assert(&mut env, "config.python_logging_to_rust == True")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ctypes_libraries() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.ctypes_libraries == {}")?;
+
+        env.eval("config.ctypes_libraries = {'ssl': '$ORIGIN/lib/libssl.so.1.1'}")?;
+        eval_assert(
+            &mut env,
+            "config.ctypes_libraries == {'ssl': '$ORIGIN/lib/libssl.so.1.1'}",
+        )?;
+
+        env.eval("config.ctypes_libraries = None")?;
+        eval_assert(&mut env, "config.ctypes_libraries == {}")?;
+
+        assert!(env.eval("config.ctypes_libraries = {'foo': 42}").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gettext_catalogs() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.gettext_catalogs == False")?;
+        eval_assert(&mut env, "config.gettext_cache_directory == None")?;
+
+        env.eval("config.gettext_catalogs = True")?;
+        eval_assert(&mut env, "config.gettext_catalogs == True")?;
+
+        env.eval("config.gettext_cache_directory = '$ORIGIN/locale-cache'")?;
+        eval_assert(
+            &mut env,
+            "config.gettext_cache_directory == '$ORIGIN/locale-cache'",
+        )?;
+
+        env.eval("config.gettext_cache_directory = None")?;
+        eval_assert(&mut env, "config.gettext_cache_directory == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_module_mode() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.run_module_mode == 'run_main'")?;
+
+        env.eval("config.run_module_mode = 'runpy'")?;
+        eval_assert(&mut env, "config.run_module_mode == 'runpy'")?;
+
+        assert!(env.eval("config.run_module_mode = 'invalid'").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_module_search_paths_mode() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.module_search_paths_mode == 'replace'")?;
+
+        env.eval("config.module_search_paths_mode = 'extend'")?;
+        eval_assert(&mut env, "config.module_search_paths_mode == 'extend'")?;
+
+        assert!(env
+            .eval("config.module_search_paths_mode = 'invalid'")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_setup() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.working_directory == None")?;
+        eval_assert(&mut env, "config.umask == None")?;
+        eval_assert(&mut env, "config.process_title == None")?;
+
+        env.eval("config.working_directory = '$ORIGIN/var'")?;
+        eval_assert(&mut env, "config.working_directory == '$ORIGIN/var'")?;
+
+        env.eval("config.umask = 23")?;
+        eval_assert(&mut env, "config.umask == 23")?;
+        assert!(env.eval("config.umask = -1").is_err());
+        assert!(env.eval("config.umask = 512").is_err());
+
+        env.eval("config.process_title = 'myapp'")?;
+        eval_assert(&mut env, "config.process_title == 'myapp'")?;
+
+        env.eval("config.working_directory = None")?;
+        env.eval("config.umask = None")?;
+        env.eval("config.process_title = None")?;
+        eval_assert(&mut env, "config.working_directory == None")?;
+        eval_assert(&mut env, "config.umask == None")?;
+        eval_assert(&mut env, "config.process_title == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_importer_debug() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.importer_debug == None")?;
+
+        env.eval("config.importer_debug = 'stderr'")?;
+        eval_assert(&mut env, "config.importer_debug == 'stderr'")?;
+
+        env.eval("config.importer_debug = None")?;
+        eval_assert(&mut env, "config.importer_debug == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restricted_mode() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.restricted_mode == False")?;
+        eval_assert(
+            &mut env,
+            "config.restricted_mode_allowed_facilities == None",
+        )?;
+        assert!(env
+            .eval("config.restricted_mode_denied_modules = ['http']")
+            .is_err());
+
+        env.eval("config.restricted_mode = True")?;
+        eval_assert(&mut env, "config.restricted_mode == True")?;
+        eval_assert(&mut env, "config.restricted_mode_allowed_facilities == []")?;
+        eval_assert(&mut env, "config.restricted_mode_denied_modules == []")?;
+
+        env.eval("config.restricted_mode_allowed_facilities = ['subprocess', 'ctypes']")?;
+        eval_assert(
+            &mut env,
+            "config.restricted_mode_allowed_facilities == ['ctypes', 'subprocess']",
+        )?;
+        assert!(env
+            .eval("config.restricted_mode_allowed_facilities = ['filesystem']")
+            .is_err());
+
+        env.eval("config.restricted_mode_denied_modules = ['http']")?;
+        eval_assert(
+            &mut env,
+            "config.restricted_mode_denied_modules == ['http']",
+        )?;
+
+        // Enabling again retains settings.
+        env.eval("config.restricted_mode = True")?;
+        eval_assert(
+            &mut env,
+            "config.restricted_mode_denied_modules == ['http']",
+        )?;
+
+        env.eval("config.restricted_mode = False")?;
+        eval_assert(&mut env, "config.restricted_mode_denied_modules == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.audit_events == []")?;
+        eval_assert(&mut env, "config.audit_log == None")?;
+
+        env.eval("config.audit_events = ['open', 'socket.']")?;
+        eval_assert(&mut env, "config.audit_events == ['open', 'socket.']")?;
+        env.eval("config.audit_events = None")?;
+        eval_assert(&mut env, "config.audit_events == []")?;
+
+        env.eval("config.audit_log = '$ORIGIN/audit.log'")?;
+        eval_assert(&mut env, "config.audit_log == '$ORIGIN/audit.log'")?;
+
+        Ok(())
+    }
 }
@@ -7,7 +7,11 @@ use {
     linked_hash_map::LinkedHashMap,
     python_packaging::{
         location::ConcreteResourceLocation,
-        policy::{ExtensionModuleFilter, PythonPackagingPolicy, ResourceHandlingMode},
+        policy::{
+            DistributionMetadataMode, ExtensionModuleFilter, PythonPackagingPolicy,
+            ResourceHandlingMode, StdlibPackedResourcesLocation,
+        },
+        resource::BytecodeOptimizationLevel,
     },
     starlark::{
         environment::TypeValues,
@@ -115,6 +119,16 @@ impl TypedValue for PythonPackagingPolicyValue {
             }
             "bytecode_optimize_level_one" => Value::from(self.inner.bytecode_optimize_level_one()),
             "bytecode_optimize_level_two" => Value::from(self.inner.bytecode_optimize_level_two()),
+            "compat_packages" => Value::from(
+                self.inner
+                    .compat_packages()
+                    .iter()
+                    .map(|x| Value::from(x.as_str()))
+                    .collect::<Vec<_>>(),
+            ),
+            "distribution_metadata_mode" => {
+                Value::from(self.inner.distribution_metadata_mode().as_ref())
+            }
             "extension_module_filter" => Value::from(self.inner.extension_module_filter().as_ref()),
             "file_scanner_classify_files" => Value::from(self.inner.file_scanner_classify_files()),
             "file_scanner_emit_files" => Value::from(self.inner.file_scanner_emit_files()),
@@ -132,6 +146,7 @@ impl TypedValue for PythonPackagingPolicyValue {
                 Value::from(self.inner.include_non_distribution_sources())
             }
             "include_test" => Value::from(self.inner.include_test()),
+            "include_gettext_catalogs" => Value::from(self.inner.include_gettext_catalogs()),
             "preferred_extension_module_variants" => {
                 Value::try_from(self.inner.preferred_extension_module_variants().clone())?
             }
@@ -140,6 +155,9 @@ impl TypedValue for PythonPackagingPolicyValue {
                 Some(location) => Value::from(location.to_string()),
                 None => Value::from(NoneType::None),
             },
+            "stdlib_packed_resources" => {
+                Value::from(self.inner.stdlib_packed_resources().to_string())
+            }
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: UnsupportedOperation::GetAttr(attr.to_string()),
@@ -160,6 +178,8 @@ impl TypedValue for PythonPackagingPolicyValue {
                 | "bytecode_optimize_level_zero"
                 | "bytecode_optimize_level_one"
                 | "bytecode_optimize_level_two"
+                | "compat_packages"
+                | "distribution_metadata_mode"
                 | "extension_module_filter"
                 | "file_scanner_classify_files"
                 | "file_scanner_emit_files"
@@ -169,9 +189,11 @@ impl TypedValue for PythonPackagingPolicyValue {
                 | "include_file_resources"
                 | "include_non_distribution_sources"
                 | "include_test"
+                | "include_gettext_catalogs"
                 | "preferred_extension_module_variants"
                 | "resources_location"
                 | "resources_location_fallback"
+                | "stdlib_packed_resources"
         ))
     }
 
@@ -193,6 +215,19 @@ impl TypedValue for PythonPackagingPolicyValue {
             "bytecode_optimize_level_two" => {
                 self.inner.set_bytecode_optimize_level_two(value.to_bool());
             }
+            "distribution_metadata_mode" => {
+                let mode = DistributionMetadataMode::try_from(value.to_string().as_str()).map_err(
+                    |e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: e,
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value.to_string()),
+                        })
+                    },
+                )?;
+
+                self.inner.set_distribution_metadata_mode(mode);
+            }
             "extension_module_filter" => {
                 let filter =
                     ExtensionModuleFilter::try_from(value.to_string().as_str()).map_err(|e| {
@@ -231,6 +266,9 @@ impl TypedValue for PythonPackagingPolicyValue {
             "include_test" => {
                 self.inner.set_include_test(value.to_bool());
             }
+            "include_gettext_catalogs" => {
+                self.inner.set_include_gettext_catalogs(value.to_bool());
+            }
             "resources_location" => {
                 self.inner.set_resources_location(
                     ConcreteResourceLocation::try_from(value.to_string().as_str()).map_err(
@@ -271,6 +309,24 @@ impl TypedValue for PythonPackagingPolicyValue {
                     ));
                 }
             }
+            "stdlib_packed_resources" => {
+                self.inner.set_stdlib_packed_resources(
+                    StdlibPackedResourcesLocation::try_from(value.to_string().as_str()).map_err(
+                        |e| {
+                            ValueError::from(RuntimeError {
+                                code: "PYOXIDIZER_BUILD",
+                                message: e,
+                                label: format!(
+                                    "{}.{} = {}",
+                                    Self::TYPE,
+                                    attribute,
+                                    value.to_string()
+                                ),
+                            })
+                        },
+                    )?,
+                );
+            }
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: UnsupportedOperation::SetAttr(attr.to_string()),
@@ -294,6 +350,46 @@ impl PythonPackagingPolicyValue {
         Ok(Value::from(NoneType::None))
     }
 
+    #[allow(clippy::unnecessary_wraps)]
+    fn starlark_enable_known_compat_fixes(&mut self) -> ValueResult {
+        self.inner.enable_known_compat_fixes();
+
+        Ok(Value::from(NoneType::None))
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn starlark_register_compat_package(&mut self, name: String) -> ValueResult {
+        self.inner.register_compat_package(&name);
+
+        Ok(Value::from(NoneType::None))
+    }
+
+    fn starlark_register_bytecode_optimization_levels(
+        &mut self,
+        pattern: String,
+        levels: &Value,
+    ) -> ValueResult {
+        let levels = levels
+            .iter()?
+            .iter()
+            .map(|level| match level.to_int()? {
+                0 => Ok(BytecodeOptimizationLevel::Zero),
+                1 => Ok(BytecodeOptimizationLevel::One),
+                2 => Ok(BytecodeOptimizationLevel::Two),
+                i => Err(ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("invalid bytecode optimization level: {}", i),
+                    label: "register_bytecode_optimization_levels()".to_string(),
+                })),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.inner
+            .register_bytecode_optimization_levels(&pattern, &levels);
+
+        Ok(Value::from(NoneType::None))
+    }
+
     #[allow(clippy::unnecessary_wraps)]
     fn starlark_set_preferred_extension_module_variant(
         &mut self,
@@ -322,6 +418,25 @@ impl PythonPackagingPolicyValue {
 }
 
 starlark_module! { python_packaging_policy_module =>
+    PythonPackagingPolicy.enable_known_compat_fixes(this) {
+        let mut this = this.downcast_mut::<PythonPackagingPolicyValue>().unwrap().unwrap();
+        this.starlark_enable_known_compat_fixes()
+    }
+
+    PythonPackagingPolicy.register_compat_package(this, name: String) {
+        let mut this = this.downcast_mut::<PythonPackagingPolicyValue>().unwrap().unwrap();
+        this.starlark_register_compat_package(name)
+    }
+
+    PythonPackagingPolicy.register_bytecode_optimization_levels(
+        this,
+        pattern: String,
+        levels
+    ) {
+        let mut this = this.downcast_mut::<PythonPackagingPolicyValue>().unwrap().unwrap();
+        this.starlark_register_bytecode_optimization_levels(pattern, &levels)
+    }
+
     PythonPackagingPolicy.register_resource_callback(this, func) {
         let mut this = this.downcast_mut::<PythonPackagingPolicyValue>().unwrap().unwrap();
         this.starlark_register_resource_callback(&func)
@@ -476,6 +591,14 @@ mod tests {
         let value = env.eval("policy.include_test = True; policy.include_test")?;
         assert!(value.to_bool());
 
+        let value = env.eval("policy.include_gettext_catalogs")?;
+        assert_eq!(value.get_type(), "bool");
+        assert_eq!(value.to_bool(), policy.include_gettext_catalogs());
+
+        let value =
+            env.eval("policy.include_gettext_catalogs = False; policy.include_gettext_catalogs")?;
+        assert!(!value.to_bool());
+
         let value = env.eval("policy.resources_location")?;
         assert_eq!(value.get_type(), "string");
         assert_eq!(value.to_string(), "in-memory");
@@ -565,6 +688,55 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_stdlib_packed_resources() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        let value = env.eval("policy.stdlib_packed_resources")?;
+        assert_eq!(value.get_type(), "string");
+        assert_eq!(value.to_string(), "default");
+
+        let value = env.eval(
+            "policy.stdlib_packed_resources = 'embedded-and-external:stdlib'; policy.stdlib_packed_resources",
+        )?;
+        assert_eq!(value.to_string(), "embedded-and-external:stdlib");
+
+        assert!(env
+            .eval("policy.stdlib_packed_resources = 'external'")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_distribution_metadata_mode() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        let value = env.eval("policy.distribution_metadata_mode")?;
+        assert_eq!(value.get_type(), "string");
+        assert_eq!(value.to_string(), "include");
+
+        let value = env.eval(
+            "policy.distribution_metadata_mode = 'strip-record-hashes'; policy.distribution_metadata_mode",
+        )?;
+        assert_eq!(value.to_string(), "strip-record-hashes");
+
+        let value = env.eval(
+            "policy.distribution_metadata_mode = 'exclude'; policy.distribution_metadata_mode",
+        )?;
+        assert_eq!(value.to_string(), "exclude");
+
+        assert!(env
+            .eval("policy.distribution_metadata_mode = 'bogus'")
+            .is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_preferred_extension_module_variants() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
@@ -623,6 +795,81 @@ mod tests {
         env.eval("policy.set_resource_handling_mode('classify')")?;
         env.eval("policy.set_resource_handling_mode('files')")?;
 
+        env.eval("policy.set_resource_handling_mode('bytecode-only')")?;
+        assert!(!env.eval("policy.include_distribution_sources")?.to_bool());
+        assert!(!env
+            .eval("policy.include_non_distribution_sources")?
+            .to_bool());
+        assert!(!env.eval("policy.allow_files")?.to_bool());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compat_packages() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        let value = env.eval("policy.compat_packages")?;
+        assert_eq!(value.get_type(), "list");
+        assert_eq!(value.length().unwrap(), 0);
+
+        env.eval("policy.register_compat_package('foo')")?;
+        let value = env.eval("policy.compat_packages")?;
+        assert_eq!(value.length().unwrap(), 1);
+        assert_eq!(value.at(Value::from(0)).unwrap(), Value::from("foo"));
+
+        env.eval("policy.enable_known_compat_fixes()")?;
+        let value = env.eval("policy.compat_packages")?;
+        assert_eq!(
+            value.length().unwrap() as usize,
+            python_packaging::policy::KNOWN_COMPAT_PACKAGES.len() + 1
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_bytecode_optimization_levels() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        env.eval("policy.register_bytecode_optimization_levels('foo.*', [1, 2])")?;
+        assert!(env
+            .eval("policy.register_bytecode_optimization_levels('foo', [3])")
+            .is_err());
+
+        let value = env.eval("policy")?;
+        let policy = value.downcast_ref::<PythonPackagingPolicyValue>().unwrap();
+        let overrides = policy.inner.bytecode_optimization_overrides();
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(overrides[0].pattern, "foo.*");
+        assert_eq!(
+            overrides[0].levels,
+            vec![
+                BytecodeOptimizationLevel::One,
+                BytecodeOptimizationLevel::Two
+            ]
+        );
+
+        env.eval("exe = dist.to_python_executable('testapp', packaging_policy = policy)")?;
+        env.eval("m = exe.make_python_module_source('foo.bar', '')")?;
+        assert!(!env
+            .eval("m.add_bytecode_optimization_level_zero")?
+            .to_bool());
+        assert!(env.eval("m.add_bytecode_optimization_level_one")?.to_bool());
+        assert!(env.eval("m.add_bytecode_optimization_level_two")?.to_bool());
+
+        env.eval("m = exe.make_python_module_source('other', '')")?;
+        assert!(env
+            .eval("m.add_bytecode_optimization_level_zero")?
+            .to_bool());
+        assert!(!env.eval("m.add_bytecode_optimization_level_two")?.to_bool());
+
         Ok(())
     }
 }
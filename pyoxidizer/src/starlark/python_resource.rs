@@ -7,8 +7,8 @@ use {
     python_packaging::{
         location::ConcreteResourceLocation,
         resource::{
-            PythonExtensionModule, PythonModuleSource, PythonPackageDistributionResource,
-            PythonPackageResource, PythonResource,
+            PythonExtensionModule, PythonModuleBytecode, PythonModuleSource,
+            PythonPackageDistributionResource, PythonPackageResource, PythonResource,
         },
         resource_collection::PythonResourceAddCollectionContext,
     },
@@ -55,9 +55,15 @@ impl TryFrom<&str> for OptionalResourceLocation {
                 inner: Some(ConcreteResourceLocation::InMemory),
             })
         } else if s.starts_with("filesystem-relative:") {
-            let prefix = s.split_at("filesystem-relative:".len()).1;
+            let location = ConcreteResourceLocation::try_from(s).map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: e,
+                    label: "resource location".to_string(),
+                })
+            })?;
             Ok(OptionalResourceLocation {
-                inner: Some(ConcreteResourceLocation::RelativePath(prefix.to_string())),
+                inner: Some(location),
             })
         } else {
             Err(ValueError::from(RuntimeError {
@@ -336,6 +342,104 @@ impl TypedValue for PythonModuleSourceValue {
     }
 }
 
+/// Starlark value wrapper for `PythonModuleBytecode`.
+///
+/// Only modules without source code are represented by this type.
+#[derive(Debug, Clone)]
+pub struct PythonModuleBytecodeValue {
+    pub inner: PythonModuleBytecode,
+    pub add_context: Option<PythonResourceAddCollectionContext>,
+}
+
+impl PythonModuleBytecodeValue {
+    pub fn new(module: PythonModuleBytecode) -> Self {
+        Self {
+            inner: module,
+            add_context: None,
+        }
+    }
+}
+
+impl ResourceCollectionContext for PythonModuleBytecodeValue {
+    fn add_collection_context(&self) -> &Option<PythonResourceAddCollectionContext> {
+        &self.add_context
+    }
+
+    fn add_collection_context_mut(&mut self) -> &mut Option<PythonResourceAddCollectionContext> {
+        &mut self.add_context
+    }
+
+    fn as_python_resource(&self) -> PythonResource<'_> {
+        PythonResource::from(&self.inner)
+    }
+}
+
+impl TypedValue for PythonModuleBytecodeValue {
+    type Holder = Mutable<PythonModuleBytecodeValue>;
+    const TYPE: &'static str = "PythonModuleBytecode";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+
+    fn to_str(&self) -> String {
+        format!(
+            "{}<name={}, optimize_level={}>",
+            Self::TYPE,
+            self.inner.name,
+            i32::from(self.inner.optimize_level)
+        )
+    }
+
+    fn to_repr(&self) -> String {
+        self.to_str()
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        let v = match attribute {
+            "is_stdlib" => Value::from(self.inner.is_stdlib),
+            "name" => Value::new(self.inner.name.clone()),
+            "is_package" => Value::new(self.inner.is_package),
+            "optimize_level" => Value::from(i64::from(i32::from(self.inner.optimize_level))),
+            attr => {
+                return if self.add_collection_context_attrs().contains(&attr) {
+                    self.get_attr_add_collection_context(attr)
+                } else {
+                    Err(ValueError::OperationNotSupported {
+                        op: UnsupportedOperation::GetAttr(attr.to_string()),
+                        left: Self::TYPE.to_string(),
+                        right: None,
+                    })
+                };
+            }
+        };
+
+        Ok(v)
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(match attribute {
+            "name" => true,
+            "is_package" => true,
+            "is_stdlib" => true,
+            "optimize_level" => true,
+            attr => self.add_collection_context_attrs().contains(&attr),
+        })
+    }
+
+    fn set_attr(&mut self, attribute: &str, value: Value) -> Result<(), ValueError> {
+        if self.add_collection_context_attrs().contains(&attribute) {
+            self.set_attr_add_collection_context(attribute, value)
+        } else {
+            Err(ValueError::OperationNotSupported {
+                op: UnsupportedOperation::SetAttr(attribute.to_string()),
+                left: Self::TYPE.to_owned(),
+                right: None,
+            })
+        }
+    }
+}
+
 /// Starlark `Value` wrapper for `PythonPackageResource`.
 #[derive(Debug, Clone)]
 pub struct PythonPackageResourceValue {
@@ -483,6 +587,7 @@ impl TypedValue for PythonPackageDistributionResourceValue {
         let v = match attribute {
             "is_stdlib" => Value::from(false),
             "package" => Value::new(self.inner.package.clone()),
+            "version" => Value::new(self.inner.version.clone()),
             "name" => Value::new(self.inner.name.clone()),
             // TODO expose raw data
             attr => {
@@ -505,6 +610,7 @@ impl TypedValue for PythonPackageDistributionResourceValue {
         Ok(match attribute {
             "is_stdlib" => true,
             "package" => true,
+            "version" => true,
             "name" => true,
             // TODO expose raw data
             attr => self.add_collection_context_attrs().contains(&attr),
@@ -566,6 +672,10 @@ impl TypedValue for PythonExtensionModuleValue {
         let v = match attribute {
             "is_stdlib" => Value::from(self.inner.is_stdlib),
             "name" => Value::new(self.inner.name.clone()),
+            "variant" => match &self.inner.variant {
+                Some(variant) => Value::from(variant.clone()),
+                None => Value::from(NoneType::None),
+            },
             attr => {
                 return if self.add_collection_context_attrs().contains(&attr) {
                     self.get_attr_add_collection_context(attr)
@@ -586,6 +696,7 @@ impl TypedValue for PythonExtensionModuleValue {
         Ok(match attribute {
             "is_stdlib" => true,
             "name" => true,
+            "variant" => true,
             attr => self.add_collection_context_attrs().contains(&attr),
         })
     }
@@ -686,7 +797,7 @@ pub fn is_resource_starlark_compatible(resource: &PythonResource) -> bool {
         PythonResource::PackageResource(_) => true,
         PythonResource::PackageDistributionResource(_) => true,
         PythonResource::ExtensionModule(_) => true,
-        PythonResource::ModuleBytecode(_) => false,
+        PythonResource::ModuleBytecode(_) => true,
         PythonResource::ModuleBytecodeRequest(_) => false,
         PythonResource::EggFile(_) => false,
         PythonResource::PathExtension(_) => false,
@@ -708,6 +819,13 @@ pub fn python_resource_to_value(
             Ok(Value::new(m))
         }
 
+        PythonResource::ModuleBytecode(module) => {
+            let mut m = PythonModuleBytecodeValue::new(module.clone().into_owned());
+            policy.apply_to_resource(type_values, call_stack, &mut m)?;
+
+            Ok(Value::new(m))
+        }
+
         PythonResource::PackageResource(data) => {
             let mut r = PythonPackageResourceValue::new(data.clone().into_owned());
             policy.apply_to_resource(type_values, call_stack, &mut r)?;
@@ -753,6 +871,11 @@ pub fn add_context_for_value(
             .unwrap()
             .add_collection_context()
             .clone()),
+        "PythonModuleBytecode" => Ok(value
+            .downcast_ref::<PythonModuleBytecodeValue>()
+            .unwrap()
+            .add_collection_context()
+            .clone()),
         "PythonPackageResource" => Ok(value
             .downcast_ref::<PythonPackageResourceValue>()
             .unwrap()
@@ -0,0 +1,123 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::{
+        py_packaging::{
+            binary::PythonBinaryBuilder,
+            zipapp::{main_source, write_zipapp, zipapp_filename, zipapp_files},
+        },
+        starlark::env::{get_context, PyOxidizerEnvironmentContext},
+    },
+    anyhow::{anyhow, Result},
+    slog::warn,
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{ResolvedTarget, ResolvedTargetValue, RunMode},
+    std::sync::Arc,
+};
+
+/// A Python zip application derived from an executable's resources.
+pub struct PythonZipAppValue {
+    pub exe: Arc<dyn PythonBinaryBuilder>,
+
+    /// Interpreter to run the zip application with, written as a shebang line.
+    pub interpreter: Option<String>,
+
+    /// Whether to compress members of the zip archive.
+    pub compress: bool,
+}
+
+impl TypedValue for PythonZipAppValue {
+    type Holder = Mutable<PythonZipAppValue>;
+    const TYPE: &'static str = "PythonZipApp";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl PythonZipAppValue {
+    fn build(
+        &self,
+        type_values: &TypeValues,
+        target: &str,
+        context: &PyOxidizerEnvironmentContext,
+    ) -> Result<ResolvedTarget> {
+        let output_path = context
+            .get_output_path(type_values, target)
+            .map_err(|_| anyhow!("unable to resolve output path"))?;
+
+        let mut files = zipapp_files(
+            context.logger(),
+            self.exe.iter_resources(),
+            &self.exe.distribution_top_level_module_names(),
+        )?;
+
+        if !files.contains_key("__main__.py") {
+            files.insert(
+                "__main__.py".to_string(),
+                main_source(&self.exe.python_interpreter_config().config)?.into_bytes(),
+            );
+        }
+
+        let dest_path = output_path.join(zipapp_filename(&self.exe.name()));
+        warn!(
+            context.logger(),
+            "writing zip application to {}",
+            dest_path.display()
+        );
+
+        write_zipapp(
+            &dest_path,
+            files,
+            self.interpreter.as_deref(),
+            self.compress,
+        )?;
+
+        Ok(ResolvedTarget {
+            run_mode: RunMode::Path { path: dest_path },
+            output_path,
+        })
+    }
+
+    fn build_starlark(&self, type_values: &TypeValues, target: String) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        Ok(Value::new(ResolvedTargetValue {
+            inner: self
+                .build(type_values, &target, &pyoxidizer_context)
+                .map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER",
+                        message: e.to_string(),
+                        label: "build()".to_string(),
+                    })
+                })?,
+        }))
+    }
+}
+
+starlark_module! { python_zipapp_module =>
+    PythonZipApp.build(
+        env env,
+        this,
+        target: String
+    ) {
+        let this = this.downcast_ref::<PythonZipAppValue>().unwrap();
+        this.build_starlark(env, target)
+    }
+}
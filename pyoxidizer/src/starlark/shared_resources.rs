@@ -0,0 +1,209 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Python resources collected once and added to multiple executables.
+*/
+
+use {
+    super::python_resource::{
+        FileValue, PythonExtensionModuleValue, PythonModuleBytecodeValue, PythonModuleSourceValue,
+        PythonPackageDistributionResourceValue, PythonPackageResourceValue,
+    },
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{
+                RuntimeError, UnsupportedOperation, ValueError, INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+            },
+            none::NoneType,
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    std::collections::HashMap,
+};
+
+/// A set of Python resources that can be added to multiple executables.
+///
+/// Resources are stored as the values they were added as, so attributes
+/// controlling how they are added to an executable are preserved.
+#[derive(Default)]
+pub struct SharedPythonResourcesValue {
+    pub resources: Vec<Value>,
+
+    /// Index into `resources` of each resource key.
+    indices: HashMap<String, usize>,
+}
+
+impl SharedPythonResourcesValue {
+    /// A key identifying a resource.
+    ///
+    /// Resources with the same key occupy the same slot in an executable.
+    pub fn resource_key(resource: &Value) -> String {
+        resource.to_str()
+    }
+}
+
+impl TypedValue for SharedPythonResourcesValue {
+    type Holder = Mutable<SharedPythonResourcesValue>;
+    const TYPE: &'static str = "SharedPythonResources";
+
+    fn values_for_descendant_check_and_freeze<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = Value> + 'a> {
+        Box::new(self.resources.iter().cloned())
+    }
+
+    fn to_str(&self) -> String {
+        format!("{}<resources={}>", Self::TYPE, self.resources.len())
+    }
+
+    fn to_repr(&self) -> String {
+        self.to_str()
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        match attribute {
+            "resources" => Ok(Value::from(self.resources.clone())),
+            _ => Err(ValueError::OperationNotSupported {
+                op: UnsupportedOperation::GetAttr(attribute.to_string()),
+                left: Self::TYPE.to_string(),
+                right: None,
+            }),
+        }
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(matches!(attribute, "resources"))
+    }
+}
+
+// Starlark functions.
+impl SharedPythonResourcesValue {
+    /// shared_resources()
+    fn new_from_args() -> ValueResult {
+        Ok(Value::new(SharedPythonResourcesValue::default()))
+    }
+
+    /// SharedPythonResources.add_python_resource(resource)
+    pub fn add_python_resource(&mut self, resource: &Value, label: &str) -> ValueResult {
+        match resource.get_type() {
+            FileValue::TYPE
+            | PythonModuleSourceValue::TYPE
+            | PythonModuleBytecodeValue::TYPE
+            | PythonPackageResourceValue::TYPE
+            | PythonPackageDistributionResourceValue::TYPE
+            | PythonExtensionModuleValue::TYPE => {
+                // A resource added again replaces the earlier one, like it
+                // would when added to an executable.
+                let key = Self::resource_key(resource);
+
+                if let Some(index) = self.indices.get(&key) {
+                    self.resources[*index] = resource.clone();
+                } else {
+                    self.indices.insert(key, self.resources.len());
+                    self.resources.push(resource.clone());
+                }
+
+                Ok(Value::new(NoneType::None))
+            }
+            t => Err(ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: format!("unable to add {} to shared resources", t),
+                label: label.to_string(),
+            })),
+        }
+    }
+
+    /// SharedPythonResources.add_python_resources(resources)
+    pub fn add_python_resources(&mut self, resources: &Value) -> ValueResult {
+        for resource in &resources.iter()? {
+            self.add_python_resource(&resource, "add_python_resources()")?;
+        }
+
+        Ok(Value::new(NoneType::None))
+    }
+}
+
+starlark_module! { shared_resources_module =>
+    shared_resources() {
+        SharedPythonResourcesValue::new_from_args()
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    SharedPythonResources.add_python_resource(this, resource) {
+        let mut this = this.downcast_mut::<SharedPythonResourcesValue>().unwrap().unwrap();
+        this.add_python_resource(&resource, "add_python_resource()")
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    SharedPythonResources.add_python_resources(this, resources) {
+        let mut this = this.downcast_mut::<SharedPythonResourcesValue>().unwrap().unwrap();
+        this.add_python_resources(&resources)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::super::{python_executable::PythonExecutableValue, testutil::*},
+        super::*,
+        anyhow::Result,
+    };
+
+    #[test]
+    fn test_shared_resources() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let shared = env.eval("shared_resources()")?;
+        assert_eq!(shared.get_type(), SharedPythonResourcesValue::TYPE);
+
+        env.eval("shared = shared_resources()")?;
+        env.eval("shared.add_python_resource(exe.make_python_module_source('shared_mod', ''))")?;
+        env.eval("shared.add_python_resources([exe.make_python_module_source('other_mod', '')])")?;
+        assert!(env.eval("shared.add_python_resource(True)").is_err());
+
+        let resources = env.eval("shared.resources")?;
+        assert_eq!(resources.length().unwrap(), 2);
+
+        // Adding a resource again replaces it.
+        env.eval(
+            "shared.add_python_resource(exe.make_python_module_source('shared_mod', 'foo = 1'))",
+        )?;
+        let resources = env.eval("shared.resources")?;
+        assert_eq!(resources.length().unwrap(), 2);
+        assert_eq!(
+            env.eval("shared.resources[0].source")?.to_string(),
+            "foo = 1"
+        );
+
+        env.eval("app1 = dist.to_python_executable('app1')")?;
+        env.eval("app1.add_shared_resources(shared)")?;
+        env.eval("app1.add_python_resource(app1.make_python_module_source('app1', ''))")?;
+        env.eval("app1.add_shared_resources(shared)")?;
+        env.eval("app2 = dist.to_python_executable('app2')")?;
+        env.eval("app2.add_shared_resources(shared)")?;
+
+        for (name, mods) in &[
+            ("app1", vec!["shared_mod", "other_mod", "app1"]),
+            ("app2", vec!["shared_mod", "other_mod"]),
+        ] {
+            let exe = env.eval(name)?;
+            let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+
+            for m in mods {
+                assert!(exe.exe.iter_resources().any(|(n, _)| n == *m));
+            }
+        }
+
+        assert!(env.eval("app2.add_shared_resources([])").is_err());
+
+        Ok(())
+    }
+}
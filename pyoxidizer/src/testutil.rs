@@ -55,7 +55,7 @@ pub fn get_distribution(
 
     let logger = get_logger()?;
 
-    DISTRIBUTION_CACHE.resolve_distribution(&logger, &location, Some(&dest_path))
+    DISTRIBUTION_CACHE.resolve_distribution(&logger, &location, Some(&dest_path), &[])
 }
 
 pub fn get_default_distribution() -> Result<Arc<StandaloneDistribution>> {
@@ -0,0 +1,552 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Rebuild projects when files change.
+
+Watching is implemented by periodically polling the modification times
+and sizes of files under a set of directories. This is less efficient
+than platform-specific filesystem notification APIs. But it behaves
+identically everywhere, including on network filesystems and in
+containers, and project trees are small enough for polling to be cheap.
+
+Besides the directory containing the configuration file, the files and
+directories targets read while being resolved (package roots, virtualenvs,
+projects installed by pip, etc) are watched. When only files read by some
+targets change, only those targets are rebuilt. Other changes, such as to
+the configuration file, rebuild every requested target.
+
+Each rebuild re-evaluates the configuration file from scratch so changes
+to it are honored. Work that hasn't changed, such as compiling Rust code
+or downloading Python distributions, is cached by the underlying build
+machinery, making rebuilds incremental.
+*/
+
+use {
+    crate::{
+        project_building::resolve_pyoxidizer_config_file, projectmgmt::resolve_target,
+        starlark::eval::EvaluationContextBuilder,
+    },
+    anyhow::{anyhow, Result},
+    slog::{error, warn},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        path::{Path, PathBuf},
+        time::{Duration, Instant, SystemTime},
+    },
+};
+
+/// Names of directories whose content is never watched.
+///
+/// These hold version control state or build artifacts, which change as a
+/// side-effect of building and would trigger endless rebuilds.
+const IGNORED_DIRECTORIES: &[&str] = &[".eggs", ".git", ".hg", "__pycache__", "target"];
+
+/// Suffixes of directory names whose content is never watched.
+///
+/// `pip install` of a project directory writes `<name>.egg-info` into it.
+const IGNORED_DIRECTORY_SUFFIXES: &[&str] = &[".egg-info"];
+
+/// Suffixes of file names that are never watched.
+const IGNORED_FILE_SUFFIXES: &[&str] = &[".pyc", ".pyo"];
+
+/// How long watched files must remain unchanged before a rebuild starts.
+///
+/// This coalesces bursts of changes, such as an editor or `git checkout`
+/// writing several files, into a single rebuild.
+const DEBOUNCE: Duration = Duration::from_millis(1000);
+
+/// Whether a directory entry should be ignored by name.
+fn is_ignored_entry(entry: &walkdir::DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy();
+
+    if entry.file_type().is_dir() {
+        entry.depth() > 0
+            && (IGNORED_DIRECTORIES.iter().any(|n| name == *n)
+                || IGNORED_DIRECTORY_SUFFIXES
+                    .iter()
+                    .any(|suffix| name.ends_with(suffix)))
+    } else {
+        IGNORED_FILE_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+    }
+}
+
+/// Modification state of files under a set of directories.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FileSnapshot {
+    files: BTreeMap<PathBuf, (Option<SystemTime>, u64)>,
+}
+
+impl FileSnapshot {
+    /// Record the state of files under the given paths.
+    ///
+    /// Paths under any of the `exclude` paths are ignored. Files that
+    /// disappear while walking are silently skipped.
+    pub fn capture(paths: &[PathBuf], exclude: &[PathBuf]) -> Self {
+        let mut files = BTreeMap::new();
+
+        for path in paths {
+            let walk = walkdir::WalkDir::new(path)
+                .into_iter()
+                .filter_entry(|entry| {
+                    !is_ignored_entry(entry) && !exclude.iter().any(|p| entry.path().starts_with(p))
+                });
+
+            for entry in walk.filter_map(|entry| entry.ok()) {
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+
+                if let Ok(metadata) = entry.metadata() {
+                    files.insert(
+                        entry.path().to_path_buf(),
+                        (metadata.modified().ok(), metadata.len()),
+                    );
+                }
+            }
+        }
+
+        Self { files }
+    }
+
+    /// Add the files of another snapshot to this one.
+    pub fn extend(&mut self, other: Self) {
+        self.files.extend(other.files);
+    }
+
+    /// Obtain a copy of this snapshot without files under any of the given paths.
+    pub fn without(&self, exclude: &[PathBuf]) -> Self {
+        Self {
+            files: self
+                .files
+                .iter()
+                .filter(|(path, _)| !exclude.iter().any(|p| path.starts_with(p)))
+                .map(|(path, state)| (path.clone(), *state))
+                .collect(),
+        }
+    }
+
+    /// Obtain paths that were added, removed, or modified relative to another snapshot.
+    pub fn changed_paths(&self, other: &Self) -> Vec<PathBuf> {
+        let mut changed = self
+            .files
+            .iter()
+            .filter(|(path, state)| other.files.get(*path) != Some(state))
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+
+        changed.extend(
+            other
+                .files
+                .keys()
+                .filter(|path| !self.files.contains_key(*path))
+                .cloned(),
+        );
+
+        changed.sort();
+
+        changed
+    }
+}
+
+/// Files and directories read by a build.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct BuildInputs {
+    /// Paths read outside of target functions.
+    global: BTreeSet<PathBuf>,
+
+    /// Paths read by each built target, including by targets it depends on.
+    targets: BTreeMap<String, BTreeSet<PathBuf>>,
+}
+
+impl BuildInputs {
+    /// Obtain all paths read by the build.
+    fn paths(&self) -> BTreeSet<PathBuf> {
+        self.global
+            .iter()
+            .chain(self.targets.values().flatten())
+            .cloned()
+            .collect()
+    }
+
+    /// Obtain the targets affected by changes to paths.
+    ///
+    /// Returns `None` if every target is affected. This is the case when a
+    /// changed path was read outside of target functions or isn't known to
+    /// be read by any target, like the configuration file.
+    fn affected_targets(&self, changed: &[PathBuf]) -> Option<Vec<String>> {
+        let mut affected = BTreeSet::new();
+
+        for path in changed {
+            if self.global.iter().any(|p| path.starts_with(p)) {
+                return None;
+            }
+
+            let targets = self
+                .targets
+                .iter()
+                .filter(|(_, paths)| paths.iter().any(|p| path.starts_with(p)))
+                .map(|(target, _)| target.clone())
+                .collect::<Vec<_>>();
+
+            if targets.is_empty() {
+                return None;
+            }
+
+            affected.extend(targets);
+        }
+
+        Some(affected.into_iter().collect())
+    }
+}
+
+/// Canonicalize a path, leaving it as is if it doesn't exist.
+fn canonicalize_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Obtain the paths to watch, omitting paths under other paths.
+fn watched_paths(paths: impl IntoIterator<Item = PathBuf>) -> Vec<PathBuf> {
+    let paths = paths.into_iter().collect::<BTreeSet<_>>();
+
+    paths
+        .iter()
+        .filter(|path| {
+            !paths
+                .iter()
+                .any(|other| other != *path && path.starts_with(other))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Build targets once.
+///
+/// `build_path` is set to the build path of the evaluation context as soon
+/// as it is known, even if building fails. Paths read by the resolved
+/// targets are recorded in `inputs` once the configuration is evaluated.
+#[allow(clippy::too_many_arguments)]
+fn build_targets(
+    logger: &slog::Logger,
+    config_path: &Path,
+    target_triple: &str,
+    resolve_targets: Option<Vec<String>>,
+    release: bool,
+    verbose: bool,
+    build_path: &mut Option<PathBuf>,
+    inputs: &mut BuildInputs,
+) -> Result<()> {
+    let mut context =
+        EvaluationContextBuilder::new(logger.clone(), config_path.to_path_buf(), target_triple)
+            .release(release)
+            .verbose(verbose)
+            .resolve_targets_optional(resolve_targets)
+            .into_context()?;
+
+    build_path.replace(
+        context
+            .build_path()
+            .map_err(|e| anyhow!("resolving build path: {:?}", e))?,
+    );
+
+    context.evaluate_file(config_path)?;
+
+    let targets = context.targets_to_resolve()?;
+
+    inputs.global = context
+        .input_paths(None)?
+        .into_iter()
+        .map(|p| canonicalize_path(&p))
+        .collect();
+    for target in &targets {
+        inputs.targets.insert(
+            target.clone(),
+            context
+                .input_paths(Some(target))?
+                .into_iter()
+                .map(|p| canonicalize_path(&p))
+                .collect(),
+        );
+    }
+
+    for target in targets {
+        context.build_resolved_target(&target)?;
+    }
+
+    Ok(())
+}
+
+/// Wait until watched files differ from `baseline` and then stop changing.
+///
+/// Returns the settled snapshot and the paths that changed relative to
+/// `baseline`.
+fn wait_for_changes(
+    paths: &[PathBuf],
+    exclude: &[PathBuf],
+    baseline: &FileSnapshot,
+    interval: Duration,
+) -> (FileSnapshot, Vec<PathBuf>) {
+    let mut current = FileSnapshot::capture(paths, exclude);
+
+    while baseline.changed_paths(&current).is_empty() {
+        std::thread::sleep(interval);
+        current = FileSnapshot::capture(paths, exclude);
+    }
+
+    // Debounce: only proceed once nothing has changed for a while.
+    let mut quiet_since = Instant::now();
+    while quiet_since.elapsed() < DEBOUNCE {
+        std::thread::sleep(interval.min(DEBOUNCE));
+
+        let next = FileSnapshot::capture(paths, exclude);
+        if next != current {
+            current = next;
+            quiet_since = Instant::now();
+        }
+    }
+
+    let changed = baseline.changed_paths(&current);
+
+    (current, changed)
+}
+
+/// Build a project and rebuild it whenever watched files change.
+///
+/// The directory containing the configuration file and the paths read by
+/// targets are always watched. `watch_paths` defines additional files or
+/// directories to watch. Changes to paths read by targets only rebuild
+/// those targets. This function never returns unless an error occurs before
+/// the first build.
+#[allow(clippy::too_many_arguments)]
+pub fn build_watch(
+    logger: &slog::Logger,
+    project_path: &Path,
+    config_path: Option<&Path>,
+    target_triple: Option<&str>,
+    resolve_targets: Option<Vec<String>>,
+    release: bool,
+    verbose: bool,
+    watch_paths: &[&Path],
+    interval: Duration,
+) -> Result<()> {
+    let config_path = resolve_pyoxidizer_config_file(logger, project_path, config_path)?;
+    let target_triple = resolve_target(target_triple)?;
+
+    let config_dir = std::fs::canonicalize(&config_path)?
+        .parent()
+        .ok_or_else(|| anyhow!("unable to resolve directory of config file"))?
+        .to_path_buf();
+
+    // Paths are canonicalized so they can be compared with the build path.
+    let base_paths = std::iter::once(config_dir.clone())
+        .chain(watch_paths.iter().map(|path| canonicalize_path(path)))
+        .collect::<Vec<_>>();
+    let mut paths = watched_paths(base_paths.clone());
+
+    // The default build path lives next to the configuration file. Exclude it
+    // up front in case the build fails before resolving the actual path.
+    let mut exclude = vec![config_dir.join("build")];
+    let mut build_path = None;
+    let mut inputs = BuildInputs::default();
+    let mut targets = resolve_targets.clone();
+
+    // Files are snapshotted before building so changes made while a build is
+    // running trigger another build.
+    let mut snapshot = FileSnapshot::capture(&paths, &exclude);
+
+    loop {
+        let start = Instant::now();
+
+        let built = build_targets(
+            logger,
+            &config_path,
+            &target_triple,
+            targets.clone(),
+            release,
+            verbose,
+            &mut build_path,
+            &mut inputs,
+        );
+        match &built {
+            Ok(()) => warn!(
+                logger,
+                "build finished in {:.2}s",
+                start.elapsed().as_secs_f64()
+            ),
+            Err(e) => error!(
+                logger,
+                "build failed after {:.2}s: {:?}",
+                start.elapsed().as_secs_f64(),
+                e
+            ),
+        }
+
+        if let Some(path) = &build_path {
+            let path = canonicalize_path(path);
+            if !exclude.contains(&path) {
+                exclude.push(path);
+            }
+        }
+
+        // Start watching paths targets read for the first time. Changes made
+        // to them during this build can't be detected.
+        let new_paths = watched_paths(base_paths.iter().cloned().chain(inputs.paths()))
+            .into_iter()
+            .filter(|path| !paths.iter().any(|p| path.starts_with(p)))
+            .collect::<Vec<_>>();
+        if !new_paths.is_empty() {
+            snapshot.extend(FileSnapshot::capture(&new_paths, &exclude));
+        }
+        paths = watched_paths(paths.into_iter().chain(new_paths));
+
+        warn!(logger, "watching for changes; press CTRL+C to stop");
+
+        let (current, changed) =
+            wait_for_changes(&paths, &exclude, &snapshot.without(&exclude), interval);
+        snapshot = current;
+
+        for path in changed.iter().take(5) {
+            warn!(logger, "changed: {}", path.display());
+        }
+        if changed.len() > 5 {
+            warn!(logger, "... and {} more", changed.len() - 5);
+        }
+
+        // Targets that failed to build are built again regardless of what
+        // changed.
+        targets = match (built, inputs.affected_targets(&changed)) {
+            (Ok(()), Some(affected)) => {
+                warn!(
+                    logger,
+                    "rebuilding affected targets: {}",
+                    affected.join(", ")
+                );
+                Some(affected)
+            }
+            _ => resolve_targets.clone(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_snapshot() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let root = temp_dir.path().to_path_buf();
+
+        std::fs::write(root.join("pyoxidizer.bzl"), b"")?;
+        std::fs::create_dir_all(root.join("build"))?;
+        std::fs::write(root.join("build").join("artifact"), b"")?;
+        std::fs::create_dir_all(root.join("pkg").join("__pycache__"))?;
+        std::fs::write(root.join("pkg").join("__init__.py"), b"")?;
+        std::fs::write(root.join("pkg").join("__pycache__").join("x.pyc"), b"")?;
+        std::fs::write(root.join("pkg").join("stale.pyc"), b"")?;
+        std::fs::create_dir_all(root.join("pkg.egg-info"))?;
+        std::fs::write(root.join("pkg.egg-info").join("PKG-INFO"), b"")?;
+
+        let paths = vec![root.clone()];
+        let exclude = vec![root.join("build")];
+
+        let snapshot = FileSnapshot::capture(&paths, &exclude);
+        assert_eq!(
+            snapshot.files.keys().cloned().collect::<Vec<_>>(),
+            vec![
+                root.join("pkg").join("__init__.py"),
+                root.join("pyoxidizer.bzl")
+            ]
+        );
+        assert!(snapshot
+            .changed_paths(&FileSnapshot::capture(&paths, &exclude))
+            .is_empty());
+
+        std::fs::write(root.join("build").join("artifact"), b"changed")?;
+        std::fs::write(root.join("pkg").join("__init__.py"), b"import os\n")?;
+        std::fs::write(root.join("pkg").join("new.py"), b"")?;
+        std::fs::remove_file(root.join("pyoxidizer.bzl"))?;
+        std::fs::write(root.join("pkg.egg-info").join("PKG-INFO"), b"changed")?;
+
+        assert_eq!(
+            snapshot.changed_paths(&FileSnapshot::capture(&paths, &exclude)),
+            vec![
+                root.join("pkg").join("__init__.py"),
+                root.join("pkg").join("new.py"),
+                root.join("pyoxidizer.bzl"),
+            ]
+        );
+
+        let unfiltered = FileSnapshot::capture(&paths, &[]);
+        assert!(unfiltered
+            .files
+            .contains_key(&root.join("build").join("artifact")));
+        assert_eq!(
+            unfiltered.without(&exclude),
+            FileSnapshot::capture(&paths, &exclude)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_affected_targets() {
+        let inputs = BuildInputs {
+            global: vec![PathBuf::from("/project/shared")].into_iter().collect(),
+            targets: vec![
+                (
+                    "exe".to_string(),
+                    vec![PathBuf::from("/project/app")].into_iter().collect(),
+                ),
+                (
+                    "install".to_string(),
+                    vec![PathBuf::from("/project/app"), PathBuf::from("/data")]
+                        .into_iter()
+                        .collect(),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        };
+
+        assert_eq!(
+            inputs.affected_targets(&[PathBuf::from("/project/app/__init__.py")]),
+            Some(vec!["exe".to_string(), "install".to_string()])
+        );
+        assert_eq!(
+            inputs.affected_targets(&[PathBuf::from("/data/file.txt")]),
+            Some(vec!["install".to_string()])
+        );
+        assert_eq!(
+            inputs.affected_targets(&[
+                PathBuf::from("/data/file.txt"),
+                PathBuf::from("/project/shared/mod.py")
+            ]),
+            None,
+            "paths read outside of targets affect every target"
+        );
+        assert_eq!(
+            inputs.affected_targets(&[PathBuf::from("/project/pyoxidizer.bzl")]),
+            None,
+            "paths not read by targets affect every target"
+        );
+    }
+
+    #[test]
+    fn test_watched_paths() {
+        assert_eq!(
+            watched_paths(vec![
+                PathBuf::from("/project/app"),
+                PathBuf::from("/project"),
+                PathBuf::from("/project-data"),
+                PathBuf::from("/project"),
+            ]),
+            vec![PathBuf::from("/project"), PathBuf::from("/project-data")]
+        );
+    }
+}
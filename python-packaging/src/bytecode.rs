@@ -8,10 +8,13 @@ use {
     super::resource::BytecodeOptimizationLevel,
     anyhow::{anyhow, Context, Result},
     byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt},
+    sha2::{Digest, Sha256},
     std::{
+        collections::{BTreeSet, HashMap},
         io::{BufRead, BufReader, Read, Write},
         path::Path,
         process,
+        sync::Mutex,
     },
 };
 
@@ -42,6 +45,7 @@ pub struct BytecodeCompiler {
 }
 
 /// Output mode for BytecodeCompiler.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum CompileMode {
     /// Emit just Python bytecode.
     Bytecode,
@@ -51,6 +55,32 @@ pub enum CompileMode {
     PycUncheckedHash,
 }
 
+/// Python code to find imports in.
+#[derive(Clone, Copy, Debug)]
+pub enum ImportsInput<'a> {
+    /// Python source code.
+    Source(&'a [u8]),
+    /// A marshalled code object, without a `.pyc` header.
+    Bytecode(&'a [u8]),
+}
+
+/// Modules imported by Python code.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CodeImports {
+    /// Absolute names of modules imported by the code.
+    ///
+    /// For `from foo import bar`, both `foo` and `foo.bar` are present, as
+    /// `bar` could be a module. Calls to `__import__()` and `import_module()`
+    /// with a constant module name are included.
+    pub imports: BTreeSet<String>,
+
+    /// Whether the code may import other modules.
+    ///
+    /// This is true if the code refers to `__import__()` or `import_module()`
+    /// other than to call them with a constant module name.
+    pub dynamic: bool,
+}
+
 impl BytecodeCompiler {
     /// Create a bytecode compiler using a Python executable.
     ///
@@ -106,6 +136,94 @@ impl BytecodeCompiler {
     }
 }
 
+impl BytecodeCompiler {
+    /// Find the modules imported by Python code.
+    ///
+    /// Imports are found in the bytecode of `IMPORT_NAME` instructions and
+    /// calls to import functions. Source code is compiled by the Python
+    /// interpreter and bytecode is loaded by it, so bytecode must be for the
+    /// interpreter's Python version.
+    ///
+    /// `module` is the fully qualified name of the module the code belongs to
+    /// and `is_package` whether it is a package. These are used to resolve
+    /// relative imports.
+    ///
+    /// Returns `None` if the interpreter is unable to load the code, such as
+    /// source with syntax errors.
+    pub fn find_imports(
+        &mut self,
+        module: &str,
+        is_package: bool,
+        input: ImportsInput,
+    ) -> Result<Option<CodeImports>> {
+        let stdin = self.command.stdin.as_mut().expect("failed to get stdin");
+        let stdout = self.command.stdout.as_mut().expect("failed to get stdout");
+
+        let mut reader = BufReader::new(stdout);
+
+        let (mode, data): (&[u8], _) = match input {
+            ImportsInput::Source(data) => (b"source", data),
+            ImportsInput::Bytecode(data) => (b"bytecode", data),
+        };
+
+        stdin
+            .write_all(b"imports\n")
+            .context("writing imports command")?;
+        stdin
+            .write_all(format!("{}\n{}\n", module.len(), data.len()).as_bytes())
+            .context("writing lengths")?;
+        stdin.write_all(if is_package { b"1\n" } else { b"0\n" })?;
+        stdin.write_all(mode).context("writing input mode")?;
+        stdin.write_all(b"\n")?;
+        stdin
+            .write_all(module.as_bytes())
+            .context("writing module name")?;
+        stdin.write_all(data).context("writing code")?;
+        stdin.flush().context("flushing")?;
+
+        let mut code_s = String::new();
+        reader
+            .read_line(&mut code_s)
+            .context("reading result code")?;
+        let code = code_s
+            .trim_end()
+            .parse::<u8>()
+            .context("parsing result code")?;
+
+        let mut len_s = String::new();
+        reader
+            .read_line(&mut len_s)
+            .context("reading output size line")?;
+        let len = len_s
+            .trim_end()
+            .parse::<u64>()
+            .context("parsing output size")?;
+
+        let mut output = vec![];
+        reader
+            .take(len)
+            .read_to_end(&mut output)
+            .context("reading imports result")?;
+
+        match code {
+            0 => {
+                let output = String::from_utf8(output)?;
+                let mut lines = output.lines();
+
+                Ok(Some(CodeImports {
+                    dynamic: lines.next() == Some("1"),
+                    imports: lines.map(|s| s.to_string()).collect(),
+                }))
+            }
+            2 => Ok(None),
+            _ => Err(anyhow!(
+                "unexpected result code from imports command: {}",
+                code
+            )),
+        }
+    }
+}
+
 impl PythonBytecodeCompiler for BytecodeCompiler {
     fn get_magic_number(&self) -> u32 {
         self.magic_number
@@ -212,6 +330,99 @@ impl Drop for BytecodeCompiler {
     }
 }
 
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct BytecodeCacheKey {
+    magic_number: u32,
+    /// SHA-256 digest of the source.
+    source_hash: [u8; 32],
+    filename: String,
+    optimize: i32,
+    output_mode: CompileMode,
+}
+
+/// Bytecode previously produced by a `PythonBytecodeCompiler`.
+///
+/// Entries are keyed by the compiler's magic number and every compilation
+/// input, so a cache can be shared by compilers for different Python versions.
+/// Sources are identified by their SHA-256 digest, so they aren't retained.
+#[derive(Clone, Debug, Default)]
+pub struct BytecodeCache {
+    entries: HashMap<BytecodeCacheKey, Vec<u8>>,
+}
+
+impl BytecodeCache {
+    /// The number of cached compilation results.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// A `PythonBytecodeCompiler` that reuses results from a `BytecodeCache`.
+///
+/// Sources not in the cache are compiled by the wrapped compiler and the
+/// result is added to the cache. The cache is only locked to look up and
+/// add entries, so compilers sharing it compile concurrently.
+pub struct CachingBytecodeCompiler<'a> {
+    compiler: &'a mut dyn PythonBytecodeCompiler,
+    cache: &'a Mutex<BytecodeCache>,
+}
+
+impl<'a> CachingBytecodeCompiler<'a> {
+    pub fn new(
+        compiler: &'a mut dyn PythonBytecodeCompiler,
+        cache: &'a Mutex<BytecodeCache>,
+    ) -> Self {
+        Self { compiler, cache }
+    }
+
+    fn lock_cache(&self) -> Result<std::sync::MutexGuard<'a, BytecodeCache>> {
+        self.cache
+            .lock()
+            .map_err(|_| anyhow!("unable to lock bytecode cache"))
+    }
+}
+
+impl<'a> PythonBytecodeCompiler for CachingBytecodeCompiler<'a> {
+    fn get_magic_number(&self) -> u32 {
+        self.compiler.get_magic_number()
+    }
+
+    fn compile(
+        &mut self,
+        source: &[u8],
+        filename: &str,
+        optimize: BytecodeOptimizationLevel,
+        output_mode: CompileMode,
+    ) -> Result<Vec<u8>> {
+        let mut source_hash = [0; 32];
+        source_hash.copy_from_slice(&Sha256::digest(source));
+
+        let key = BytecodeCacheKey {
+            magic_number: self.compiler.get_magic_number(),
+            source_hash,
+            filename: filename.to_string(),
+            optimize: i32::from(optimize),
+            output_mode,
+        };
+
+        if let Some(bytecode) = self.lock_cache()?.entries.get(&key) {
+            return Ok(bytecode.clone());
+        }
+
+        let bytecode = self
+            .compiler
+            .compile(source, filename, optimize, output_mode)?;
+        self.lock_cache()?.entries.insert(key, bytecode.clone());
+
+        Ok(bytecode)
+    }
+}
+
 /// How to write out a .pyc bytecode header.
 #[derive(Debug, Clone, Copy)]
 pub enum BytecodeHeaderMode {
@@ -275,4 +486,95 @@ mod tests {
 
         Ok(())
     }
+
+    struct CountingBytecodeCompiler {
+        magic_number: u32,
+        compiles: usize,
+    }
+
+    impl PythonBytecodeCompiler for CountingBytecodeCompiler {
+        fn get_magic_number(&self) -> u32 {
+            self.magic_number
+        }
+
+        fn compile(
+            &mut self,
+            source: &[u8],
+            _filename: &str,
+            _optimize: BytecodeOptimizationLevel,
+            _output_mode: CompileMode,
+        ) -> Result<Vec<u8>> {
+            self.compiles += 1;
+
+            let mut bytecode = self.magic_number.to_le_bytes().to_vec();
+            bytecode.extend(source);
+
+            Ok(bytecode)
+        }
+    }
+
+    #[test]
+    fn test_caching_compiler() -> Result<()> {
+        let cache = Mutex::new(BytecodeCache::default());
+        let mut compiler = CountingBytecodeCompiler {
+            magic_number: 42,
+            compiles: 0,
+        };
+
+        for _ in 0..2 {
+            let mut caching = CachingBytecodeCompiler::new(&mut compiler, &cache);
+            assert_eq!(
+                caching.compile(
+                    b"foo",
+                    "foo.py",
+                    BytecodeOptimizationLevel::Zero,
+                    CompileMode::Bytecode
+                )?,
+                b"\x2a\x00\x00\x00foo"
+            );
+        }
+        assert_eq!(compiler.compiles, 1);
+        assert_eq!(cache.lock().unwrap().len(), 1);
+
+        // Any differing input is a cache miss.
+        let mut caching = CachingBytecodeCompiler::new(&mut compiler, &cache);
+        caching.compile(
+            b"foo",
+            "foo.py",
+            BytecodeOptimizationLevel::One,
+            CompileMode::Bytecode,
+        )?;
+        caching.compile(
+            b"foo",
+            "foo.py",
+            BytecodeOptimizationLevel::Zero,
+            CompileMode::PycCheckedHash,
+        )?;
+        caching.compile(
+            b"foo",
+            "bar.py",
+            BytecodeOptimizationLevel::Zero,
+            CompileMode::Bytecode,
+        )?;
+        assert_eq!(compiler.compiles, 4);
+
+        let mut other = CountingBytecodeCompiler {
+            magic_number: 43,
+            compiles: 0,
+        };
+        let mut caching = CachingBytecodeCompiler::new(&mut other, &cache);
+        assert_eq!(
+            caching.compile(
+                b"foo",
+                "foo.py",
+                BytecodeOptimizationLevel::Zero,
+                CompileMode::Bytecode
+            )?,
+            b"\x2b\x00\x00\x00foo"
+        );
+        assert_eq!(other.compiles, 1);
+        assert_eq!(cache.lock().unwrap().len(), 5);
+
+        Ok(())
+    }
 }
@@ -7,6 +7,7 @@
 # When invoked, we start a server that listens for commands. We then
 # react to those commands and send results to the caller.
 
+import dis
 import importlib._bootstrap_external
 import importlib.util
 import marshal
@@ -21,10 +22,127 @@ RE_CODING = re.compile(b"^[ \t\f]*#.*?coding[:=][ \t]*([-_.a-zA-Z0-9]+)")
 if marshal.version != 4:
     raise Exception("unexpected marshal version: %d" % marshal.version)
 
+# Names of functions which import the module named by their first argument.
+IMPORT_FUNCTIONS = {"__import__", "import_module"}
+
+# Instructions which load a name that may be one of IMPORT_FUNCTIONS.
+LOAD_NAME_OPS = {"LOAD_ATTR", "LOAD_GLOBAL", "LOAD_METHOD", "LOAD_NAME"}
+
+# Instructions which load a constant.
+LOAD_CONST_OPS = {"LOAD_CONST", "LOAD_SMALL_INT"}
+
 stdin = sys.__stdin__.buffer
 stdout = sys.__stdout__.buffer
 
 
+def decode_source(source):
+    """Decode source bytes to str.
+
+    Returns the source bytes without a byte order mark and the decoded source.
+    """
+    # Default source encoding is UTF-8. But per PEP 263, the first or second
+    # line of source can match a regular expression to define a custom
+    # encoding. We need to detect custom encodings and use it to decode
+    # the passed bytes to str.
+    encoding = "utf-8"
+
+    for line in source.splitlines()[0:2]:
+        m = RE_CODING.match(line)
+        if m:
+            encoding = m.group(1).decode("ascii")
+            break
+
+    # Someone has set us up the BOM! According to PEP 263 the file should
+    # be interpreted as UTF-8.
+    if source.startswith(b"\xef\xbb\xbf"):
+        encoding = "utf-8"
+        source = source[3:]
+
+    return source, source.decode(encoding)
+
+
+def find_imports(code, name, is_package):
+    """Find modules imported by a code object and the code objects it contains.
+
+    Returns a set of absolute module names and whether the code calls an
+    import function with a name that isn't a constant.
+    """
+    package = name if is_package else name.rpartition(".")[0]
+
+    imports = set()
+    dynamic = False
+
+    def add(target, level):
+        try:
+            imports.add(importlib.util.resolve_name("." * level + target, package))
+        except (ImportError, ValueError):
+            # Relative imports beyond the top-level package fail at run-time.
+            pass
+
+    def constant(instruction):
+        if instruction.opname in LOAD_CONST_OPS:
+            return instruction.argval
+
+        return None
+
+    pending = [code]
+
+    while pending:
+        code = pending.pop()
+        instructions = list(dis.get_instructions(code))
+
+        for i, instruction in enumerate(instructions):
+            if instruction.opname == "IMPORT_NAME":
+                # `import` statements load the relative import level and the
+                # names to import from the module before importing it.
+                level = constant(instructions[i - 2]) if i >= 2 else None
+                fromlist = constant(instructions[i - 1]) if i >= 1 else None
+
+                add(instruction.argval, level or 0)
+
+                # Names imported from a module may be submodules.
+                for attr in fromlist or ():
+                    if attr != "*":
+                        if instruction.argval:
+                            add("%s.%s" % (instruction.argval, attr), level or 0)
+                        else:
+                            add(attr, level or 0)
+
+            elif (
+                instruction.opname in LOAD_NAME_OPS
+                and instruction.argval in IMPORT_FUNCTIONS
+            ):
+                # Calls like `importlib.import_module("foo")` import a known
+                # module. Any other use of the function may import anything.
+                # Version-specific instructions like PUSH_NULL and PRECALL may
+                # occur between the function and its arguments.
+                j = i + 1
+                while j < len(instructions) and instructions[j].opname in (
+                    "PUSH_NULL",
+                    "CACHE",
+                ):
+                    j += 1
+
+                target = constant(instructions[j]) if j < len(instructions) else None
+                call = instructions[j + 1].opname if j + 1 < len(instructions) else ""
+
+                if (
+                    isinstance(target, str)
+                    and not target.startswith(".")
+                    and call.startswith(("CALL", "PRECALL"))
+                ):
+                    imports.add(target)
+                else:
+                    dynamic = True
+
+        for const in code.co_consts:
+            if isinstance(const, type(code)):
+                pending.append(const)
+
+    return imports, dynamic
+
+
+
 while True:
     command = stdin.readline().rstrip()
 
@@ -49,26 +167,7 @@ while True:
 
             name = os.fsdecode(name)
 
-            # Default source encoding is UTF-8. But per PEP 263, the first or second
-            # line of source can match a regular expression to define a custom
-            # encoding. We need to detect custom encodings and use it to decode
-            # the passed bytes to str.
-            encoding = "utf-8"
-
-            for line in source.splitlines()[0:2]:
-                m = RE_CODING.match(line)
-                if m:
-                    encoding = m.group(1).decode("ascii")
-                    break
-
-            # Someone has set us up the BOM! According to PEP 263 the file should
-            # be interpreted as UTF-8.
-            if source.startswith(b"\xef\xbb\xbf"):
-                encoding = "utf-8"
-                source = source[3:]
-
-            source_bytes = source
-            source = source.decode(encoding)
+            source_bytes, source = decode_source(source)
 
             code = compile(source, name, "exec", optimize=optimize_level)
             bytecode = marshal.dumps(code)
@@ -98,6 +197,46 @@ while True:
             stdout.write(b"%d\n" % len(error_bytes))
             stdout.write(error_bytes)
 
+        stdout.flush()
+    elif command == b"imports":
+        name_len = int(stdin.readline().rstrip())
+        data_len = int(stdin.readline().rstrip())
+        is_package = stdin.readline().rstrip() == b"1"
+        input_mode = stdin.readline().rstrip()
+
+        name = stdin.read(name_len).decode("utf-8")
+        data = stdin.read(data_len)
+
+        try:
+            if input_mode == b"source":
+                code = compile(decode_source(data)[1], name, "exec")
+            elif input_mode == b"bytecode":
+                code = marshal.loads(data)
+            else:
+                raise Exception("unknown input mode: %s" % input_mode)
+
+            if not isinstance(code, type(find_imports.__code__)):
+                raise Exception("data is not a code object")
+
+            imports, dynamic = find_imports(code, name, is_package)
+
+            # Code 0 means success. The first line denotes whether the code
+            # imports modules by computed names and the others are the
+            # imported modules.
+            out = "\n".join(
+                ["1" if dynamic else "0"] + sorted(imports)
+            ).encode("utf-8")
+
+            stdout.write(b"0\n")
+            stdout.write(b"%d\n" % len(out))
+            stdout.write(out)
+        except Exception as e:
+            # Code 2 means the code could not be loaded.
+            stdout.write(b"2\n")
+            error_bytes = str(e).encode("utf-8", errors="replace")
+            stdout.write(b"%d\n" % len(error_bytes))
+            stdout.write(error_bytes)
+
         stdout.flush()
     else:
         raise Exception("invalid command: %s" % command)
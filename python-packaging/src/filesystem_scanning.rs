@@ -201,6 +201,85 @@ impl<'a> PythonResourceIterator<'a> {
         }
     }
 
+    /// Resolve a `PythonModuleBytecode` for a `.pyc` file.
+    ///
+    /// Returns `None` if the file is too short to contain a `.pyc` header.
+    fn resolve_module_bytecode(
+        &self,
+        name: &str,
+        optimize_level: BytecodeOptimizationLevel,
+        path: &Path,
+    ) -> Option<PathItem<'a>> {
+        let module = match self.path_content_overrides.get(path) {
+            // In-memory content includes the 16 byte header, which
+            // `PythonModuleBytecode` doesn't store.
+            Some(file) => {
+                let data = file.data.resolve().ok()?;
+
+                if data.len() < 16 {
+                    return None;
+                }
+
+                PythonModuleBytecode::new(
+                    name,
+                    optimize_level,
+                    is_package_from_path(path),
+                    &self.cache_tag,
+                    &data[16..],
+                )
+            }
+            None => PythonModuleBytecode::from_path(name, optimize_level, &self.cache_tag, path),
+        };
+
+        Some(PathItem::PythonResource(module.into()))
+    }
+
+    /// Resolve a `.pyc` file using the legacy `<package>/<module>.pyc` layout.
+    ///
+    /// Python 3 only imports bytecode from outside a `__pycache__` directory
+    /// if there is no source file. Distributions shipping only bytecode (e.g.
+    /// produced by `compileall -b`) use this layout. The file name doesn't
+    /// carry an optimization level, so bytecode is assumed to be for level 0.
+    fn resolve_legacy_bytecode(
+        &mut self,
+        path: &Path,
+        components: &[&str],
+    ) -> Option<PathItem<'a>> {
+        let file_name = components[components.len() - 1];
+
+        let suffix = self
+            .suffixes
+            .bytecode
+            .iter()
+            .find(|suffix| file_name.ends_with(suffix.as_str()))?;
+
+        let module_name = &file_name[0..file_name.len() - suffix.len()];
+
+        // e.g. `foo.cpython-39.pyc`, which Python doesn't import from here.
+        if module_name.is_empty() || module_name.contains('.') {
+            return None;
+        }
+
+        let package_parts = &components[0..components.len() - 1];
+        let mut package = itertools::join(package_parts, ".");
+
+        let mut full_module_name: Vec<&str> = package_parts.to_vec();
+
+        if module_name != "__init__" {
+            full_module_name.push(module_name);
+        }
+
+        let full_module_name = itertools::join(full_module_name, ".");
+
+        if package.is_empty() {
+            package = full_module_name.clone();
+        }
+
+        self.seen_packages.insert(package);
+
+        self.resolve_module_bytecode(&full_module_name, BytecodeOptimizationLevel::Zero, path)
+    }
+
     fn resolve_path(&mut self, path: &Path) -> Option<PathItem<'a>> {
         let mut rel_path = path
             .strip_prefix(&self.root_path)
@@ -419,14 +498,10 @@ impl<'a> PythonResourceIterator<'a> {
             .iter()
             .any(|ext| rel_str.ends_with(ext))
         {
-            // .pyc files should be in a __pycache__ directory.
-            if components.len() < 2 {
-                return None;
-            }
-
-            // Possibly from Python 2?
-            if components[components.len() - 2] != "__pycache__" {
-                return None;
+            // .pyc files should be in a __pycache__ directory. Otherwise they
+            // are from a sourceless distribution.
+            if components.len() < 2 || components[components.len() - 2] != "__pycache__" {
+                return self.resolve_legacy_bytecode(path, &components);
             }
 
             let package_parts = &components[0..components.len() - 2];
@@ -490,15 +565,7 @@ impl<'a> PythonResourceIterator<'a> {
 
             self.seen_packages.insert(package);
 
-            return Some(PathItem::PythonResource(
-                PythonModuleBytecode::from_path(
-                    &full_module_name,
-                    optimization_level,
-                    &self.cache_tag,
-                    path,
-                )
-                .into(),
-            ));
+            return self.resolve_module_bytecode(&full_module_name, optimization_level, path);
         }
 
         let resource = match rel_path.extension().and_then(OsStr::to_str) {
@@ -679,6 +746,34 @@ impl<'a> Iterator for PythonResourceIterator<'a> {
     }
 }
 
+/// Remove bytecode of modules whose source is also present.
+///
+/// Scanning emits bytecode found in `__pycache__` directories next to
+/// source files. Bytecode is normally compiled from source when packaging,
+/// so this bytecode is redundant and may be stale. Bytecode of modules
+/// without source, as found in sourceless distributions, is retained.
+pub fn filter_redundant_bytecode<'a>(
+    resources: impl IntoIterator<Item = PythonResource<'a>>,
+) -> Vec<PythonResource<'a>> {
+    let resources = resources.into_iter().collect::<Vec<_>>();
+
+    let sources = resources
+        .iter()
+        .filter_map(|r| match r {
+            PythonResource::ModuleSource(module) => Some(module.name.clone()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    resources
+        .into_iter()
+        .filter(|r| match r {
+            PythonResource::ModuleBytecode(module) => !sources.contains(&module.name),
+            _ => true,
+        })
+        .collect()
+}
+
 /// Find Python resources in a directory.
 ///
 /// Given a root directory path, walk the directory and find all Python
@@ -1830,4 +1925,93 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_sourceless_resources() -> Result<()> {
+        let mut pyc = vec![0; 16];
+        pyc.push(42);
+
+        let inputs = vec![
+            File {
+                path: PathBuf::from("foo/__init__.pyc"),
+                entry: FileEntry {
+                    executable: false,
+                    data: pyc.clone().into(),
+                },
+            },
+            File {
+                path: PathBuf::from("foo/bar.pyc"),
+                entry: FileEntry {
+                    executable: false,
+                    data: pyc.clone().into(),
+                },
+            },
+            File {
+                path: PathBuf::from("foo/baz.py"),
+                entry: FileEntry {
+                    executable: false,
+                    data: vec![1].into(),
+                },
+            },
+            File {
+                path: PathBuf::from("foo/__pycache__/baz.cpython-37.pyc"),
+                entry: FileEntry {
+                    executable: false,
+                    data: pyc.clone().into(),
+                },
+            },
+            // Bytecode for another interpreter isn't imported from here.
+            File {
+                path: PathBuf::from("foo/other.cpython-37.pyc"),
+                entry: FileEntry {
+                    executable: false,
+                    data: pyc.into(),
+                },
+            },
+        ];
+
+        let resources = PythonResourceIterator::from_data_locations(
+            &inputs,
+            DEFAULT_CACHE_TAG,
+            &DEFAULT_SUFFIXES,
+            false,
+            true,
+        )?
+        .collect::<Result<Vec<_>>>()?;
+
+        let foo = PythonResource::from(PythonModuleBytecode::new(
+            "foo",
+            BytecodeOptimizationLevel::Zero,
+            true,
+            DEFAULT_CACHE_TAG,
+            &[42],
+        ));
+        let foo_bar = PythonResource::from(PythonModuleBytecode::new(
+            "foo.bar",
+            BytecodeOptimizationLevel::Zero,
+            false,
+            DEFAULT_CACHE_TAG,
+            &[42],
+        ));
+        let foo_baz = PythonResource::from(PythonModuleBytecode::new(
+            "foo.baz",
+            BytecodeOptimizationLevel::Zero,
+            false,
+            DEFAULT_CACHE_TAG,
+            &[42],
+        ));
+
+        assert_eq!(resources.len(), 4);
+        assert!(resources.contains(&foo));
+        assert!(resources.contains(&foo_bar));
+        assert!(resources.contains(&foo_baz));
+
+        let resources = filter_redundant_bytecode(resources);
+        assert_eq!(resources.len(), 3);
+        assert!(resources.contains(&foo));
+        assert!(resources.contains(&foo_bar));
+        assert!(!resources.contains(&foo_baz));
+
+        Ok(())
+    }
 }
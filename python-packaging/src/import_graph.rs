@@ -0,0 +1,486 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Analyze which Python modules import which. */
+
+use {
+    crate::{
+        bytecode::{BytecodeCompiler, CodeImports, ImportsInput},
+        module_util::packages_from_module_name,
+        resource_collection::{PrePackagedResource, PythonModuleBytecodeProvider},
+    },
+    anyhow::Result,
+    std::collections::{BTreeMap, BTreeSet, VecDeque},
+    tugger_file_manifest::FileData,
+};
+
+/// Modules the interpreter imports when it starts, regardless of the code it runs.
+///
+/// This includes `runpy` and its imports, which the embedded interpreter uses
+/// to run a configured module or command.
+///
+/// A trailing `.*` denotes a package and all its submodules.
+pub const INTERPRETER_STARTUP_MODULES: &[&str] = &[
+    "_collections_abc",
+    "_sitebuiltins",
+    "abc",
+    "codecs",
+    "encodings.*",
+    "genericpath",
+    "importlib",
+    "importlib._bootstrap",
+    "importlib._bootstrap_external",
+    "importlib.abc",
+    "importlib.machinery",
+    "importlib.util",
+    "io",
+    "linecache",
+    "ntpath",
+    "os",
+    "pkgutil",
+    "posixpath",
+    "runpy",
+    "site",
+    "stat",
+    "tokenize",
+    "traceback",
+    "types",
+    "warnings",
+    "zipimport",
+];
+
+/// Whether a module is imported when the interpreter starts.
+pub fn is_interpreter_startup_module(name: &str) -> bool {
+    INTERPRETER_STARTUP_MODULES
+        .iter()
+        .any(|pattern| module_matches(pattern, name))
+}
+
+/// Whether a module name matches a name that may end with `.*`.
+fn module_matches(pattern: &str, name: &str) -> bool {
+    if let Some(package) = pattern.strip_suffix(".*") {
+        name == package || name.starts_with(&format!("{}.", package))
+    } else {
+        name == pattern
+    }
+}
+
+fn bytecode_source(provider: &Option<PythonModuleBytecodeProvider>) -> Option<&FileData> {
+    match provider {
+        Some(PythonModuleBytecodeProvider::FromSource(data)) => Some(data),
+        _ => None,
+    }
+}
+
+fn provided_bytecode(provider: &Option<PythonModuleBytecodeProvider>) -> Option<&FileData> {
+    match provider {
+        Some(PythonModuleBytecodeProvider::Provided(data)) => Some(data),
+        _ => None,
+    }
+}
+
+/// Resolve the source code of a module, if available.
+fn module_source(resource: &PrePackagedResource) -> Result<Option<Vec<u8>>> {
+    let data = resource
+        .in_memory_source
+        .as_ref()
+        .or(resource
+            .relative_path_module_source
+            .as_ref()
+            .map(|(_, data)| data))
+        .or(bytecode_source(&resource.in_memory_bytecode))
+        .or(bytecode_source(&resource.in_memory_bytecode_opt1))
+        .or(bytecode_source(&resource.in_memory_bytecode_opt2))
+        .or(match &resource.relative_path_bytecode {
+            Some((_, _, PythonModuleBytecodeProvider::FromSource(data))) => Some(data),
+            _ => None,
+        });
+
+    Ok(if let Some(data) = data {
+        Some(data.resolve()?)
+    } else {
+        None
+    })
+}
+
+/// Resolve the bytecode of a module, if available.
+fn module_bytecode(resource: &PrePackagedResource) -> Result<Option<Vec<u8>>> {
+    let data = provided_bytecode(&resource.in_memory_bytecode)
+        .or(provided_bytecode(&resource.in_memory_bytecode_opt1))
+        .or(provided_bytecode(&resource.in_memory_bytecode_opt2))
+        .or(match &resource.relative_path_bytecode {
+            Some((_, _, PythonModuleBytecodeProvider::Provided(data))) => Some(data),
+            _ => None,
+        });
+
+    Ok(if let Some(data) = data {
+        Some(data.resolve()?)
+    } else {
+        None
+    })
+}
+
+/// The top-level package of a module.
+fn top_level_package(name: &str) -> &str {
+    name.split('.').next().unwrap_or(name)
+}
+
+/// A graph of imports between Python modules.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportGraph {
+    /// Modules and the modules they import.
+    imports: BTreeMap<String, BTreeSet<String>>,
+
+    /// Modules whose imports could not be determined.
+    ///
+    /// This includes extension modules and modules whose code the
+    /// interpreter was unable to load.
+    unanalyzed: BTreeSet<String>,
+
+    /// Modules which may import modules not in their imports.
+    ///
+    /// These call `__import__()` or `importlib.import_module()` with names
+    /// computed at run-time.
+    dynamic: BTreeSet<String>,
+}
+
+impl ImportGraph {
+    /// Construct an instance from modules and the modules they import.
+    ///
+    /// A value of `None` denotes a module whose imports are unknown. Imported
+    /// names not in `modules` are ignored. Modules import their parent
+    /// packages, as importing a module imports its parents.
+    pub fn new(modules: BTreeMap<String, Option<CodeImports>>) -> Self {
+        let mut res = Self::default();
+
+        for (name, imports) in &modules {
+            let mut edges = packages_from_module_name(name);
+
+            if let Some(imports) = imports {
+                for import in &imports.imports {
+                    edges.extend(packages_from_module_name(import));
+                    edges.insert(import.clone());
+                }
+
+                if imports.dynamic {
+                    res.dynamic.insert(name.clone());
+                }
+            } else {
+                res.unanalyzed.insert(name.clone());
+            }
+
+            edges.retain(|import| import != name && modules.contains_key(import));
+
+            res.imports.insert(name.clone(), edges);
+        }
+
+        res
+    }
+
+    /// Construct an instance from Python resources.
+    ///
+    /// Imports are found in the bytecode of modules by `compiler`. Modules
+    /// without source are analyzed using their bytecode, which must be for
+    /// the compiler's Python version.
+    pub fn from_resources<'a>(
+        resources: impl Iterator<Item = (&'a String, &'a PrePackagedResource)>,
+        compiler: &mut BytecodeCompiler,
+    ) -> Result<Self> {
+        let mut modules = BTreeMap::new();
+
+        for (name, resource) in resources {
+            if resource.is_module {
+                let imports = if let Some(source) = module_source(resource)? {
+                    compiler.find_imports(
+                        name,
+                        resource.is_package,
+                        ImportsInput::Source(&source),
+                    )?
+                } else if let Some(bytecode) = module_bytecode(resource)? {
+                    compiler.find_imports(
+                        name,
+                        resource.is_package,
+                        ImportsInput::Bytecode(&bytecode),
+                    )?
+                } else {
+                    None
+                };
+
+                modules.insert(name.clone(), imports);
+            } else if resource.is_extension_module
+                || resource.is_builtin_extension_module
+                || resource.is_frozen_module
+            {
+                modules.insert(name.clone(), None);
+            }
+        }
+
+        Ok(Self::new(modules))
+    }
+
+    /// Obtain the names of all modules in the graph.
+    pub fn modules(&self) -> impl Iterator<Item = &String> {
+        self.imports.keys()
+    }
+
+    /// Obtain the modules imported by a module.
+    pub fn imports(&self, name: &str) -> Option<&BTreeSet<String>> {
+        self.imports.get(name)
+    }
+
+    /// Whether the imports of a module were determined.
+    pub fn is_analyzed(&self, name: &str) -> bool {
+        !self.unanalyzed.contains(name)
+    }
+
+    /// Whether a module in the graph has submodules.
+    fn is_package(&self, name: &str) -> bool {
+        let prefix = format!("{}.", name);
+
+        self.modules().any(|module| module.starts_with(&prefix))
+    }
+
+    /// Whether a module may import modules with names computed at run-time.
+    pub fn imports_dynamically(&self, name: &str) -> bool {
+        self.dynamic.contains(name)
+    }
+
+    /// Resolve reachable modules which may import modules not in the graph's edges.
+    ///
+    /// These are modules whose imports could not be determined and modules
+    /// which import modules with names computed at run-time.
+    pub fn unresolved_importers(&self, roots: &[String]) -> BTreeSet<String> {
+        self.reachable(roots)
+            .keys()
+            .filter(|name| !self.is_analyzed(name) || self.imports_dynamically(name))
+            .cloned()
+            .collect()
+    }
+
+    /// Resolve modules reachable from roots by following imports.
+    ///
+    /// Roots ending with `.*` match a package and all its submodules.
+    ///
+    /// Returns reachable modules and the module which first led to them.
+    /// Roots map to `None`.
+    pub fn reachable(&self, roots: &[String]) -> BTreeMap<String, Option<String>> {
+        let mut res = BTreeMap::new();
+        let mut queue = VecDeque::new();
+
+        for name in self.modules() {
+            if roots.iter().any(|root| module_matches(root, name)) {
+                res.insert(name.clone(), None);
+                queue.push_back(name.clone());
+            }
+        }
+
+        while let Some(name) = queue.pop_front() {
+            for import in &self.imports[&name] {
+                if !res.contains_key(import) {
+                    res.insert(import.clone(), Some(name.clone()));
+                    queue.push_back(import.clone());
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Resolve modules not reachable from roots and why.
+    ///
+    /// Modules reachable from roots whose imports aren't fully known could
+    /// import any module. Modules in the same top-level package as these
+    /// are assumed to be reachable, as packages commonly import their own
+    /// modules dynamically. Modules they import from other packages can't be
+    /// detected: see `unresolved_importers()`.
+    ///
+    /// Returns unreachable modules and a description of why they are
+    /// unreachable.
+    pub fn unreachable(&self, roots: &[String]) -> BTreeMap<String, String> {
+        let reachable = self.reachable(roots);
+
+        let unresolved_packages = self
+            .unresolved_importers(roots)
+            .iter()
+            .filter(|name| name.contains('.') || self.is_package(name))
+            .map(|name| top_level_package(name).to_string())
+            .collect::<BTreeSet<_>>();
+
+        let mut importers: BTreeMap<&String, BTreeSet<&String>> = BTreeMap::new();
+        for (name, imports) in &self.imports {
+            for import in imports {
+                importers.entry(import).or_default().insert(name);
+            }
+        }
+
+        self.modules()
+            .filter(|name| {
+                !reachable.contains_key(*name)
+                    && !unresolved_packages.contains(top_level_package(name))
+            })
+            .map(|name| {
+                let reason = match importers.get(name) {
+                    Some(importers) => format!(
+                        "only imported by unreachable modules: {}",
+                        importers
+                            .iter()
+                            .map(|s| s.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    None => "not imported by any module".to_string(),
+                };
+
+                (name.clone(), reason)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Modules, the modules they import and whether they import dynamically.
+    type Modules<'a> = Vec<(&'a str, Option<(Vec<&'a str>, bool)>)>;
+
+    fn new_graph(modules: Modules) -> ImportGraph {
+        ImportGraph::new(
+            modules
+                .into_iter()
+                .map(|(name, imports)| {
+                    (
+                        name.to_string(),
+                        imports.map(|(imports, dynamic)| CodeImports {
+                            imports: imports.into_iter().map(String::from).collect(),
+                            dynamic,
+                        }),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn graph() -> ImportGraph {
+        let modules = vec![
+            ("app", Some(vec!["app.util", "json", "missing"])),
+            ("app.util", Some(vec!["os"])),
+            ("app.unused", Some(vec!["email"])),
+            ("email", Some(vec![])),
+            ("json", Some(vec!["json.decoder"])),
+            ("json.decoder", Some(vec![])),
+            ("os", Some(vec![])),
+            ("_ssl", None),
+        ];
+
+        new_graph(
+            modules
+                .into_iter()
+                .map(|(name, imports)| (name, imports.map(|imports| (imports, false))))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_imports() {
+        let graph = graph();
+
+        assert_eq!(
+            graph.imports("app"),
+            Some(
+                &vec!["app.util".to_string(), "json".to_string()]
+                    .into_iter()
+                    .collect()
+            )
+        );
+        // Modules import their parent packages.
+        assert_eq!(
+            graph.imports("app.util"),
+            Some(
+                &vec!["app".to_string(), "os".to_string()]
+                    .into_iter()
+                    .collect()
+            )
+        );
+        assert!(graph.is_analyzed("app"));
+        assert!(!graph.is_analyzed("_ssl"));
+    }
+
+    #[test]
+    fn test_reachable() {
+        let graph = graph();
+
+        let reachable = graph.reachable(&["app".to_string()]);
+        assert_eq!(
+            reachable.keys().cloned().collect::<Vec<_>>(),
+            vec!["app", "app.util", "json", "json.decoder", "os"]
+        );
+        assert_eq!(reachable["app"], None);
+        assert_eq!(reachable["json.decoder"], Some("json".to_string()));
+
+        assert_eq!(graph.reachable(&["json.*".to_string()]).len(), 2);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let graph = graph();
+
+        let unreachable = graph.unreachable(&["app".to_string()]);
+        assert_eq!(
+            unreachable,
+            vec![
+                ("_ssl".to_string(), "not imported by any module".to_string()),
+                (
+                    "app.unused".to_string(),
+                    "not imported by any module".to_string()
+                ),
+                (
+                    "email".to_string(),
+                    "only imported by unreachable modules: app.unused".to_string()
+                ),
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
+
+    #[test]
+    fn test_unreachable_unresolved() {
+        let graph = new_graph(vec![
+            (
+                "app",
+                Some((vec!["plugins.loader", "speedups", "pkgutil"], false)),
+            ),
+            ("pkgutil", Some((vec![], true))),
+            ("plugins", Some((vec![], false))),
+            ("plugins.a", Some((vec![], false))),
+            ("plugins.loader", Some((vec![], true))),
+            ("speedups", Some((vec!["speedups._native"], false))),
+            ("speedups._native", None),
+            ("speedups.extra", Some((vec![], false))),
+            ("unused", Some((vec![], false))),
+        ]);
+
+        assert!(graph.imports_dynamically("plugins.loader"));
+        assert_eq!(
+            graph.unresolved_importers(&["app".to_string()]),
+            vec!["pkgutil", "plugins.loader", "speedups._native"]
+                .into_iter()
+                .map(String::from)
+                .collect()
+        );
+
+        // Modules in packages of modules which may import anything are
+        // retained. Top-level modules don't retain other modules.
+        assert_eq!(
+            graph.unreachable(&["app".to_string()]),
+            vec![(
+                "unused".to_string(),
+                "not imported by any module".to_string()
+            )]
+            .into_iter()
+            .collect()
+        );
+    }
+}
@@ -6,7 +6,9 @@
 
 use {
     crate::resource::BytecodeOptimizationLevel,
-    std::{convert::TryFrom, ffi::OsString, os::raw::c_ulong, path::PathBuf},
+    std::{
+        collections::BTreeSet, convert::TryFrom, ffi::OsString, os::raw::c_ulong, path::PathBuf,
+    },
 };
 
 /// Defines the profile to use to configure a Python interpreter.
@@ -302,6 +304,241 @@ impl TryFrom<&str> for Allocator {
     }
 }
 
+/// A section of the report describing what an interpreter did at run-time.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum RunReportSection {
+    /// Names of modules in `sys.modules` at shutdown.
+    Modules,
+    /// Modules imported by our importer and the module that imported them.
+    ImportGraph,
+    /// Time spent executing each module imported by our importer.
+    ImportTimes,
+    /// Names of resources read through our importer.
+    Resources,
+}
+
+impl RunReportSection {
+    /// All sections, in the order they appear in reports.
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::Modules,
+            Self::ImportGraph,
+            Self::ImportTimes,
+            Self::Resources,
+        ]
+    }
+}
+
+impl ToString for RunReportSection {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Modules => "modules",
+            Self::ImportGraph => "import_graph",
+            Self::ImportTimes => "import_times",
+            Self::Resources => "resources",
+        }
+        .to_string()
+    }
+}
+
+impl TryFrom<&str> for RunReportSection {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "modules" => Ok(Self::Modules),
+            "import_graph" => Ok(Self::ImportGraph),
+            "import_times" => Ok(Self::ImportTimes),
+            "resources" => Ok(Self::Resources),
+            _ => Err(format!("{} is not a valid run report section", value)),
+        }
+    }
+}
+
+/// How a module configured by `run_module` is run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunModuleMode {
+    /// Let `Py_RunMain()` run the module, like the `python` executable does.
+    RunMain,
+    /// Run the module with `runpy`, like `python -m`, from Rust.
+    ///
+    /// Unlike `Py_RunMain()`, `sys.argv[0]` is retained for modules without a
+    /// filesystem origin and `sys.path` isn't modified.
+    Runpy,
+}
+
+impl Default for RunModuleMode {
+    fn default() -> Self {
+        Self::RunMain
+    }
+}
+
+impl ToString for RunModuleMode {
+    fn to_string(&self) -> String {
+        match self {
+            Self::RunMain => "run_main",
+            Self::Runpy => "runpy",
+        }
+        .to_string()
+    }
+}
+
+impl TryFrom<&str> for RunModuleMode {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "run_main" => Ok(Self::RunMain),
+            "runpy" => Ok(Self::Runpy),
+            _ => Err(format!("{} is not a valid run module mode", value)),
+        }
+    }
+}
+
+/// How configured module search paths relate to Python's default search paths.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ModuleSearchPathsMode {
+    /// The configured paths replace the default search paths.
+    Replace,
+    /// The configured paths are added before the default search paths, like
+    /// `PYTHONPATH` entries.
+    Extend,
+}
+
+impl Default for ModuleSearchPathsMode {
+    fn default() -> Self {
+        Self::Replace
+    }
+}
+
+impl ToString for ModuleSearchPathsMode {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Replace => "replace",
+            Self::Extend => "extend",
+        }
+        .to_string()
+    }
+}
+
+impl TryFrom<&str> for ModuleSearchPathsMode {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "replace" => Ok(Self::Replace),
+            "extend" => Ok(Self::Extend),
+            _ => Err(format!("{} is not a valid module search paths mode", value)),
+        }
+    }
+}
+
+/// A Python facility able to affect the host system.
+///
+/// Restricted mode denies access to facilities not explicitly allowed.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum RestrictedFacility {
+    /// Loading and calling native code with `ctypes`.
+    Ctypes,
+    /// Network access with `socket` and `ssl`.
+    Socket,
+    /// Running processes with `subprocess` and the `os` module.
+    Subprocess,
+}
+
+impl RestrictedFacility {
+    /// All facilities.
+    pub fn all() -> Vec<Self> {
+        vec![Self::Ctypes, Self::Socket, Self::Subprocess]
+    }
+}
+
+impl ToString for RestrictedFacility {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Ctypes => "ctypes",
+            Self::Socket => "socket",
+            Self::Subprocess => "subprocess",
+        }
+        .to_string()
+    }
+}
+
+impl TryFrom<&str> for RestrictedFacility {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "ctypes" => Ok(Self::Ctypes),
+            "socket" => Ok(Self::Socket),
+            "subprocess" => Ok(Self::Subprocess),
+            _ => Err(format!("{} is not a valid restricted facility", value)),
+        }
+    }
+}
+
+/// Configures restricted mode.
+///
+/// Restricted mode denies Python code access to facilities able to affect
+/// the host system. It is intended for applications embedding Python as an
+/// extension language.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RestrictedModeConfig {
+    /// Facilities that remain available.
+    pub allowed_facilities: BTreeSet<RestrictedFacility>,
+
+    /// Additional modules that can't be imported.
+    ///
+    /// Submodules of these modules can't be imported either.
+    pub denied_modules: Vec<String>,
+}
+
+/// Configures updating an application from a signed update manifest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelfUpdateConfig {
+    /// URL of the update manifest.
+    ///
+    /// The detached signature of the manifest is fetched from this URL with
+    /// `.sig` appended.
+    pub manifest_url: String,
+
+    /// Hex encoded Ed25519 public key the manifest must be signed with.
+    pub public_key: String,
+
+    /// Version of the running application.
+    pub version: String,
+
+    /// Directory downloaded updates are staged in until the next start.
+    ///
+    /// `$ORIGIN` is expanded to the directory of the current executable.
+    /// Relative paths are evaluated relative to `$ORIGIN`.
+    pub staging_directory: PathBuf,
+}
+
+/// Configures importing modules from a remote resource server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemoteResourcesConfig {
+    /// URL of the resource index.
+    ///
+    /// The detached signature of the index is fetched from this URL with
+    /// `.sig` appended.
+    pub index_url: String,
+
+    /// Hex encoded Ed25519 public key the index must be signed with.
+    pub public_key: String,
+
+    /// Names of packages whose modules may be imported from the server.
+    ///
+    /// Only modules named by an entry or beneath one are looked up.
+    pub packages: Vec<String>,
+
+    /// Directory fetched module sources are cached in.
+    ///
+    /// `$ORIGIN` is expanded to the directory of the current executable.
+    /// Relative paths are evaluated relative to `$ORIGIN`.
+    pub cache_directory: PathBuf,
+}
+
 /// Holds configuration of a Python interpreter.
 ///
 /// This struct holds fields that are exposed by `PyPreConfig` and
@@ -481,3 +718,71 @@ pub struct PythonInterpreterConfig {
     /// See https://docs.python.org/3/c-api/init_config.html#c.PyConfig.xoptions.
     pub x_options: Option<Vec<String>>,
 }
+
+impl PythonInterpreterConfig {
+    /// Verify the values of fields are compatible with each other.
+    ///
+    /// Python silently resolves some conflicting settings. e.g. isolated mode
+    /// ignores environment variables even if `use_environment` is set. We
+    /// reject these combinations so a configuration does what it says.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(hash_seed) = self.hash_seed {
+            // Python only accepts seeds fitting in 32 bits, even where
+            // c_ulong is 64 bits.
+            if u32::try_from(hash_seed).is_err() {
+                return Err(format!(
+                    "hash_seed must be between 0 and {}; got {}",
+                    u32::MAX,
+                    hash_seed
+                ));
+            }
+        }
+
+        if self.isolated == Some(true) {
+            if self.use_environment == Some(true) {
+                return Err("use_environment cannot be enabled in isolated mode".to_string());
+            }
+
+            if self.user_site_directory == Some(true) {
+                return Err("user_site_directory cannot be enabled in isolated mode".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let mut config = PythonInterpreterConfig::default();
+        assert!(config.validate().is_ok());
+
+        config.hash_seed = Some(u32::MAX.into());
+        assert!(config.validate().is_ok());
+        #[cfg(not(windows))]
+        {
+            config.hash_seed = Some(c_ulong::from(u32::MAX) + 1);
+            assert!(config.validate().is_err());
+        }
+        config.hash_seed = None;
+
+        config.isolated = Some(true);
+        config.use_environment = Some(false);
+        config.user_site_directory = Some(false);
+        assert!(config.validate().is_ok());
+
+        config.use_environment = Some(true);
+        assert!(config.validate().is_err());
+        config.use_environment = None;
+
+        config.user_site_directory = Some(true);
+        assert!(config.validate().is_err());
+
+        config.isolated = Some(false);
+        assert!(config.validate().is_ok());
+    }
+}
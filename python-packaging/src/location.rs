@@ -4,7 +4,7 @@
 
 /*! Functionality related to resource locations. */
 
-use std::convert::TryFrom;
+use std::{convert::TryFrom, path::Path};
 
 /// Describes the location of a Python resource.
 ///
@@ -90,7 +90,17 @@ impl TryFrom<&str> for ConcreteResourceLocation {
                 let suffix = parts[1];
 
                 if prefix == "filesystem-relative" {
-                    Ok(Self::RelativePath(suffix.to_string()))
+                    // Prefixes are relative to the binary's directory. They may
+                    // reference it as `$ORIGIN`, leave it with `..`, and
+                    // reference environment variables.
+                    if Path::new(suffix).is_absolute() {
+                        Err(format!(
+                            "{} is not a valid resource location: filesystem-relative prefixes must be relative paths",
+                            value
+                        ))
+                    } else {
+                        Ok(Self::RelativePath(suffix.to_string()))
+                    }
                 } else {
                     Err(format!("{} is not a valid resource location", value))
                 }
@@ -127,6 +137,23 @@ mod tests {
             ConcreteResourceLocation::try_from("filesystem-relative:lib"),
             Ok(ConcreteResourceLocation::RelativePath("lib".to_string()))
         );
+        assert_eq!(
+            ConcreteResourceLocation::try_from("filesystem-relative:$ORIGIN/../share/app"),
+            Ok(ConcreteResourceLocation::RelativePath(
+                "$ORIGIN/../share/app".to_string()
+            ))
+        );
+        assert_eq!(
+            ConcreteResourceLocation::try_from("filesystem-relative:../lib"),
+            Ok(ConcreteResourceLocation::RelativePath("../lib".to_string()))
+        );
+        assert_eq!(
+            ConcreteResourceLocation::try_from("filesystem-relative:${APP_DATA}/lib"),
+            Ok(ConcreteResourceLocation::RelativePath(
+                "${APP_DATA}/lib".to_string()
+            ))
+        );
+        assert!(ConcreteResourceLocation::try_from("filesystem-relative:/usr/lib").is_err());
 
         Ok(())
     }
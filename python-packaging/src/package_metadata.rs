@@ -5,7 +5,7 @@
 /*! Working with Python package metadata (i.e. .pkg-info directories) */
 
 use {
-    anyhow::{Context, Result},
+    anyhow::{anyhow, Context, Result},
     mailparse::parse_mail,
 };
 
@@ -62,6 +62,111 @@ impl PythonPackageMetadata {
     }
 }
 
+/// Represents an entry point defined in an `entry_points.txt` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntryPoint {
+    /// The group the entry point belongs to. e.g. `console_scripts`.
+    pub group: String,
+
+    /// The name of the entry point.
+    pub name: String,
+
+    /// The Python module the entry point resolves to.
+    pub module: String,
+
+    /// The attribute within the module the entry point resolves to.
+    ///
+    /// May contain dots to reference nested attributes.
+    pub attr: Option<String>,
+
+    /// Extras that must be installed for the entry point to work.
+    pub extras: Vec<String>,
+}
+
+impl EntryPoint {
+    /// Parse an entry point value of the form `module:attr [extra1, extra2]`.
+    pub fn from_value(group: &str, name: &str, value: &str) -> Result<Self> {
+        let (target, extras) = match value.find('[') {
+            Some(pos) => {
+                let extras = value[pos + 1..]
+                    .trim_end()
+                    .strip_suffix(']')
+                    .ok_or_else(|| anyhow!("unterminated extras in entry point: {}", value))?;
+
+                (
+                    &value[0..pos],
+                    extras
+                        .split(',')
+                        .map(|s| s.trim())
+                        .filter(|s| !s.is_empty())
+                        .map(|s| s.to_string())
+                        .collect::<Vec<_>>(),
+                )
+            }
+            None => (value, vec![]),
+        };
+
+        let (module, attr) = match target.find(':') {
+            Some(pos) => (
+                target[0..pos].trim(),
+                Some(target[pos + 1..].trim().to_string()),
+            ),
+            None => (target.trim(), None),
+        };
+
+        if module.is_empty() {
+            return Err(anyhow!("entry point {} does not define a module", name));
+        }
+
+        Ok(Self {
+            group: group.to_string(),
+            name: name.to_string(),
+            module: module.to_string(),
+            attr,
+            extras,
+        })
+    }
+}
+
+/// Parse the content of an `entry_points.txt` file into entry points.
+///
+/// The file is in the INI format, with each section denoting an entry point
+/// group and each key-value pair defining an entry point within that group.
+pub fn parse_entry_points(data: &[u8]) -> Result<Vec<EntryPoint>> {
+    let text = String::from_utf8_lossy(data);
+
+    let mut group = None;
+    let mut res = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            group = Some(line[1..line.len() - 1].trim().to_string());
+            continue;
+        }
+
+        let group = group
+            .as_ref()
+            .ok_or_else(|| anyhow!("line {}: entry point defined outside of a group", i + 1))?;
+
+        let pos = line
+            .find('=')
+            .ok_or_else(|| anyhow!("line {}: expected `name = value`", i + 1))?;
+
+        res.push(
+            EntryPoint::from_value(group, line[0..pos].trim(), line[pos + 1..].trim())
+                .with_context(|| format!("parsing line {}", i + 1))?,
+        );
+    }
+
+    Ok(res)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,4 +204,61 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_entry_points() -> Result<()> {
+        let data = concat!(
+            "[console_scripts]\n",
+            "black = black:patched_main\n",
+            "blackd = blackd:patched_main [d]\n",
+            "\n",
+            "# comment\n",
+            "[gui_scripts]\n",
+            "viewer=viewer.app:Main.run\n",
+            "[other]\n",
+            "plain = plain\n",
+        )
+        .as_bytes();
+
+        let eps = parse_entry_points(data)?;
+
+        assert_eq!(
+            eps,
+            vec![
+                EntryPoint {
+                    group: "console_scripts".to_string(),
+                    name: "black".to_string(),
+                    module: "black".to_string(),
+                    attr: Some("patched_main".to_string()),
+                    extras: vec![],
+                },
+                EntryPoint {
+                    group: "console_scripts".to_string(),
+                    name: "blackd".to_string(),
+                    module: "blackd".to_string(),
+                    attr: Some("patched_main".to_string()),
+                    extras: vec!["d".to_string()],
+                },
+                EntryPoint {
+                    group: "gui_scripts".to_string(),
+                    name: "viewer".to_string(),
+                    module: "viewer.app".to_string(),
+                    attr: Some("Main.run".to_string()),
+                    extras: vec![],
+                },
+                EntryPoint {
+                    group: "other".to_string(),
+                    name: "plain".to_string(),
+                    module: "plain".to_string(),
+                    attr: None,
+                    extras: vec![],
+                },
+            ]
+        );
+
+        assert!(parse_entry_points(b"foo = bar:baz\n").is_err());
+        assert!(parse_entry_points(b"[console_scripts]\nfoo\n").is_err());
+
+        Ok(())
+    }
 }
@@ -10,14 +10,82 @@ use {
     crate::{
         licensing::SAFE_SYSTEM_LIBRARIES,
         location::ConcreteResourceLocation,
-        resource::{PythonExtensionModule, PythonExtensionModuleVariants, PythonResource},
+        resource::{
+            BytecodeOptimizationLevel, PythonExtensionModule, PythonExtensionModuleVariants,
+            PythonResource,
+        },
         resource_collection::PythonResourceAddCollectionContext,
     },
     anyhow::Result,
-    std::{collections::HashMap, convert::TryFrom},
+    std::{
+        collections::{BTreeSet, HashMap},
+        convert::TryFrom,
+    },
     tugger_licensing::LicenseFlavor,
 };
 
+/// Packages known to not work when imported from memory.
+///
+/// These packages rely on `__file__` or load data files and shared libraries
+/// relative to their own location. They need to be installed on the
+/// filesystem to work.
+pub const KNOWN_COMPAT_PACKAGES: &[&str] = &["cryptography", "numpy", "pandas", "scipy"];
+
+/// Relative path that resources of compatibility mode packages are installed to.
+pub const COMPAT_PACKAGES_PATH: &str = "lib";
+
+/// Whether a module name matches a module name pattern.
+///
+/// `*` in the pattern matches any sequence of characters. A pattern without
+/// `*` matches the named module and all modules beneath it.
+fn module_name_matches(pattern: &str, name: &str) -> bool {
+    if !pattern.contains('*') {
+        return name == pattern
+            || (name.starts_with(pattern) && name[pattern.len()..].starts_with('.'));
+    }
+
+    let mut parts = pattern.split('*');
+    // split() always yields at least one element.
+    let first = parts.next().unwrap();
+
+    if !name.starts_with(first) {
+        return false;
+    }
+
+    let mut remaining = &name[first.len()..];
+    let mut parts = parts.collect::<Vec<_>>();
+    let last = parts.pop().unwrap_or("");
+
+    for part in parts {
+        match remaining.find(part) {
+            Some(pos) => remaining = &remaining[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    remaining.ends_with(last)
+}
+
+/// Bytecode optimization levels to write for modules matching a pattern.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BytecodeOptimizationOverride {
+    /// Module name pattern.
+    ///
+    /// `*` matches any sequence of characters. A pattern without `*` matches
+    /// the named module and all modules beneath it.
+    pub pattern: String,
+
+    /// Optimization levels to write bytecode for.
+    pub levels: Vec<BytecodeOptimizationLevel>,
+}
+
+impl BytecodeOptimizationOverride {
+    /// Whether this override applies to a module.
+    pub fn matches(&self, name: &str) -> bool {
+        module_name_matches(&self.pattern, name)
+    }
+}
+
 /// Denotes methods to filter extension modules.
 #[derive(Clone, Debug, PartialEq)]
 pub enum ExtensionModuleFilter {
@@ -63,6 +131,11 @@ pub enum ResourceHandlingMode {
 
     /// Files should be handled as files.
     Files,
+
+    /// Files should be classified and Python module source should be dropped.
+    ///
+    /// Only Python module bytecode is retained.
+    BytecodeOnly,
 }
 
 impl TryFrom<&str> for ResourceHandlingMode {
@@ -72,8 +145,9 @@ impl TryFrom<&str> for ResourceHandlingMode {
         match value {
             "classify" => Ok(Self::Classify),
             "files" => Ok(Self::Files),
+            "bytecode-only" => Ok(Self::BytecodeOnly),
             _ => Err(format!(
-                "{} is not a valid resource handling mode; use \"classify\" or \"files\"",
+                "{} is not a valid resource handling mode; use \"classify\", \"files\", or \"bytecode-only\"",
                 value
             )),
         }
@@ -85,6 +159,116 @@ impl AsRef<str> for ResourceHandlingMode {
         match self {
             Self::Classify => "classify",
             Self::Files => "files",
+            Self::BytecodeOnly => "bytecode-only",
+        }
+    }
+}
+
+/// Describes how package distribution metadata (`.dist-info` files) is packaged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DistributionMetadataMode {
+    /// Metadata files are packaged as is.
+    Include,
+
+    /// Metadata files are packaged with hashes and sizes removed from `RECORD`.
+    StripRecordHashes,
+
+    /// Metadata files are not packaged.
+    ///
+    /// `importlib.metadata` won't find distributions for packaged packages.
+    Exclude,
+}
+
+impl Default for DistributionMetadataMode {
+    fn default() -> Self {
+        Self::Include
+    }
+}
+
+impl TryFrom<&str> for DistributionMetadataMode {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "include" => Ok(Self::Include),
+            "strip-record-hashes" => Ok(Self::StripRecordHashes),
+            "exclude" => Ok(Self::Exclude),
+            _ => Err(format!(
+                "{} is not a valid distribution metadata mode; use \"include\", \"strip-record-hashes\", or \"exclude\"",
+                value
+            )),
+        }
+    }
+}
+
+impl AsRef<str> for DistributionMetadataMode {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Include => "include",
+            Self::StripRecordHashes => "strip-record-hashes",
+            Self::Exclude => "exclude",
+        }
+    }
+}
+
+/// Describes where packed resources for the Python standard library are stored.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StdlibPackedResourcesLocation {
+    /// Standard library resources are stored with other packed resources.
+    Default,
+
+    /// Standard library resources are stored in their own packed resources
+    /// embedded in the binary.
+    Embedded,
+
+    /// Standard library resources are stored in a packed resources file with
+    /// the given name next to the binary.
+    External(String),
+
+    /// Standard library resources are embedded in the binary and stored in a
+    /// file with the given name next to the binary.
+    ///
+    /// Resources from the file are preferred if it exists at run-time.
+    /// Otherwise the embedded copy is used.
+    EmbeddedAndExternal(String),
+}
+
+impl Default for StdlibPackedResourcesLocation {
+    fn default() -> Self {
+        Self::Default
+    }
+}
+
+impl ToString for StdlibPackedResourcesLocation {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Default => "default".to_string(),
+            Self::Embedded => "embedded".to_string(),
+            Self::External(filename) => format!("external:{}", filename),
+            Self::EmbeddedAndExternal(filename) => format!("embedded-and-external:{}", filename),
+        }
+    }
+}
+
+impl TryFrom<&str> for StdlibPackedResourcesLocation {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "default" => Ok(Self::Default),
+            "embedded" => Ok(Self::Embedded),
+            _ => match value.splitn(2, ':').collect::<Vec<_>>().as_slice() {
+                ["external", filename] if !filename.is_empty() => {
+                    Ok(Self::External(filename.to_string()))
+                }
+                ["embedded-and-external", filename] if !filename.is_empty() => {
+                    Ok(Self::EmbeddedAndExternal(filename.to_string()))
+                }
+                _ => Err(format!(
+                    "{} is not a valid standard library packed resources location; use \"default\", \"embedded\", \"external:<filename>\", or \"embedded-and-external:<filename>\"",
+                    value
+                )),
+            },
         }
     }
 }
@@ -155,6 +339,12 @@ pub struct PythonPackagingPolicy {
     /// Whether to include test files.
     include_test: bool,
 
+    /// Whether to include gettext message catalogs in packages.
+    include_gettext_catalogs: bool,
+
+    /// How package distribution metadata is packaged.
+    distribution_metadata_mode: DistributionMetadataMode,
+
     /// Whether to classify `File` resources as `include = True` by default.
     include_file_resources: bool,
 
@@ -172,6 +362,21 @@ pub struct PythonPackagingPolicy {
 
     /// Whether to write Python bytecode at optimization level 2.
     bytecode_optimize_level_two: bool,
+
+    /// Bytecode optimization levels for modules matching a pattern.
+    ///
+    /// These take precedence over the `bytecode_optimize_level_*` fields.
+    /// Later entries take precedence over earlier ones.
+    bytecode_optimization_overrides: Vec<BytecodeOptimizationOverride>,
+
+    /// Where packed resources for the standard library are stored.
+    stdlib_packed_resources: StdlibPackedResourcesLocation,
+
+    /// Packages needing filesystem-relative placement to work.
+    ///
+    /// Resources in these packages are always installed next to the binary
+    /// and their import is verified after the binary is built.
+    compat_packages: BTreeSet<String>,
 }
 
 impl Default for PythonPackagingPolicy {
@@ -190,11 +395,16 @@ impl Default for PythonPackagingPolicy {
             include_non_distribution_sources: true,
             include_distribution_resources: false,
             include_test: false,
+            include_gettext_catalogs: true,
+            distribution_metadata_mode: DistributionMetadataMode::Include,
             include_file_resources: false,
             broken_extensions: HashMap::new(),
             bytecode_optimize_level_zero: true,
             bytecode_optimize_level_one: false,
             bytecode_optimize_level_two: false,
+            bytecode_optimization_overrides: vec![],
+            stdlib_packed_resources: StdlibPackedResourcesLocation::Default,
+            compat_packages: BTreeSet::new(),
         }
     }
 }
@@ -246,6 +456,16 @@ impl PythonPackagingPolicy {
         self.resources_location_fallback = location;
     }
 
+    /// Obtain where packed resources for the standard library are stored.
+    pub fn stdlib_packed_resources(&self) -> &StdlibPackedResourcesLocation {
+        &self.stdlib_packed_resources
+    }
+
+    /// Set where packed resources for the standard library are stored.
+    pub fn set_stdlib_packed_resources(&mut self, location: StdlibPackedResourcesLocation) {
+        self.stdlib_packed_resources = location;
+    }
+
     /// Whether to allow untyped `File` resources.
     pub fn allow_files(&self) -> bool {
         self.allow_files
@@ -326,6 +546,26 @@ impl PythonPackagingPolicy {
         self.include_test = include;
     }
 
+    /// Get whether to include gettext message catalogs in packages.
+    pub fn include_gettext_catalogs(&self) -> bool {
+        self.include_gettext_catalogs
+    }
+
+    /// Set whether to include gettext message catalogs in packages.
+    pub fn set_include_gettext_catalogs(&mut self, include: bool) {
+        self.include_gettext_catalogs = include;
+    }
+
+    /// Obtain how package distribution metadata is packaged.
+    pub fn distribution_metadata_mode(&self) -> DistributionMetadataMode {
+        self.distribution_metadata_mode
+    }
+
+    /// Set how package distribution metadata is packaged.
+    pub fn set_distribution_metadata_mode(&mut self, mode: DistributionMetadataMode) {
+        self.distribution_metadata_mode = mode;
+    }
+
     /// Get whether to classify `File` resources as include by default.
     pub fn include_file_resources(&self) -> bool {
         self.include_file_resources
@@ -376,6 +616,90 @@ impl PythonPackagingPolicy {
         self.bytecode_optimize_level_two = value;
     }
 
+    /// Obtain bytecode optimization levels registered for module name patterns.
+    pub fn bytecode_optimization_overrides(&self) -> &[BytecodeOptimizationOverride] {
+        &self.bytecode_optimization_overrides
+    }
+
+    /// Define the bytecode optimization levels to write for matching modules.
+    ///
+    /// Matching modules have bytecode written for exactly `levels`, regardless
+    /// of the `bytecode_optimize_level_*` settings. If several patterns match
+    /// a module, the last registered one wins.
+    pub fn register_bytecode_optimization_levels(
+        &mut self,
+        pattern: &str,
+        levels: &[BytecodeOptimizationLevel],
+    ) {
+        self.bytecode_optimization_overrides
+            .push(BytecodeOptimizationOverride {
+                pattern: pattern.to_string(),
+                levels: levels.to_vec(),
+            });
+    }
+
+    /// Obtain the registered bytecode optimization levels applying to a module.
+    pub fn bytecode_optimization_override(
+        &self,
+        name: &str,
+    ) -> Option<&BytecodeOptimizationOverride> {
+        self.bytecode_optimization_overrides
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(name))
+    }
+
+    /// Resolve modules whose loaded bytecode must be compiled at another level.
+    ///
+    /// The interpreter only loads bytecode for its configured optimization
+    /// `level`. Modules whose registered levels don't include it are loaded
+    /// from bytecode compiled at the highest registered level instead. This
+    /// returns the names of these modules and the level to compile them at.
+    pub fn bytecode_optimization_level_substitutions<'a>(
+        &self,
+        level: BytecodeOptimizationLevel,
+        module_names: impl Iterator<Item = &'a str>,
+    ) -> Vec<(String, BytecodeOptimizationLevel)> {
+        module_names
+            .filter_map(|name| {
+                let rule = self.bytecode_optimization_override(name)?;
+
+                if rule.levels.contains(&level) {
+                    None
+                } else {
+                    rule.levels
+                        .iter()
+                        .max_by_key(|level| i32::from(**level))
+                        .map(|compile_level| (name.to_string(), *compile_level))
+                }
+            })
+            .collect()
+    }
+
+    /// Resolve which bytecode optimization levels to write for a resource.
+    fn bytecode_optimize_levels(&self, resource: &PythonResource) -> (bool, bool, bool) {
+        let rule = match resource {
+            PythonResource::ModuleSource(module) => {
+                self.bytecode_optimization_override(&module.name)
+            }
+            _ => None,
+        };
+
+        if let Some(rule) = rule {
+            (
+                rule.levels.contains(&BytecodeOptimizationLevel::Zero),
+                rule.levels.contains(&BytecodeOptimizationLevel::One),
+                rule.levels.contains(&BytecodeOptimizationLevel::Two),
+            )
+        } else {
+            (
+                self.bytecode_optimize_level_zero,
+                self.bytecode_optimize_level_one,
+                self.bytecode_optimize_level_two,
+            )
+        }
+    }
+
     /// Set the resource handling mode of the policy.
     ///
     /// This is a convenience function for mapping a `ResourceHandlingMode`
@@ -396,6 +720,11 @@ impl PythonPackagingPolicy {
                 self.include_file_resources = true;
                 self.include_classified_resources = true;
             }
+            ResourceHandlingMode::BytecodeOnly => {
+                self.set_resource_handling_mode(ResourceHandlingMode::Classify);
+                self.include_distribution_sources = false;
+                self.include_non_distribution_sources = false;
+            }
         }
     }
 
@@ -417,6 +746,46 @@ impl PythonPackagingPolicy {
             .push(extension.to_string());
     }
 
+    /// Obtain packages packaged in compatibility mode.
+    pub fn compat_packages(&self) -> &BTreeSet<String> {
+        &self.compat_packages
+    }
+
+    /// Package a Python package in compatibility mode.
+    ///
+    /// Resources in the package are installed to the filesystem relative to
+    /// the binary, regardless of `resources_location`. This is needed by
+    /// packages which don't work when imported from memory.
+    pub fn register_compat_package(&mut self, package: &str) {
+        self.compat_packages.insert(package.to_string());
+    }
+
+    /// Package all packages known to need it in compatibility mode.
+    ///
+    /// See `KNOWN_COMPAT_PACKAGES` for the list of packages.
+    pub fn enable_known_compat_fixes(&mut self) {
+        for package in KNOWN_COMPAT_PACKAGES {
+            self.register_compat_package(package);
+        }
+    }
+
+    /// Whether a resource belongs to a package packaged in compatibility mode.
+    fn is_compat_resource(&self, resource: &PythonResource) -> bool {
+        let name = match resource {
+            PythonResource::PackageDistributionResource(resource) => resource.package.clone(),
+            PythonResource::EggFile(_)
+            | PythonResource::PathExtension(_)
+            | PythonResource::File(_) => return false,
+            _ => resource.full_name(),
+        };
+
+        let top_level = name.split('.').next().unwrap_or(&name);
+
+        self.compat_packages
+            .iter()
+            .any(|package| package.eq_ignore_ascii_case(top_level))
+    }
+
     /// Derive a `PythonResourceAddCollectionContext` for a resource using current settings.
     ///
     /// The returned object essentially says how the resource should be added
@@ -438,17 +807,47 @@ impl PythonPackagingPolicy {
             _ => false,
         };
 
-        let location = self.resources_location.clone();
-        let location_fallback = self.resources_location_fallback.clone();
+        let strip_record_hashes = match resource {
+            PythonResource::PackageDistributionResource(_) => {
+                self.distribution_metadata_mode == DistributionMetadataMode::StripRecordHashes
+            }
+            _ => false,
+        };
+
+        let (include, location, location_fallback) = if self.is_compat_resource(resource) {
+            // Data files and metadata are needed for the package to work. So
+            // they are always included, even if metadata is otherwise excluded.
+            let include = match resource {
+                PythonResource::PackageResource(_)
+                | PythonResource::PackageDistributionResource(_) => true,
+                _ => include,
+            };
+
+            (
+                include,
+                ConcreteResourceLocation::RelativePath(COMPAT_PACKAGES_PATH.to_string()),
+                None,
+            )
+        } else {
+            (
+                include,
+                self.resources_location.clone(),
+                self.resources_location_fallback.clone(),
+            )
+        };
+
+        let (optimize_level_zero, optimize_level_one, optimize_level_two) =
+            self.bytecode_optimize_levels(resource);
 
         PythonResourceAddCollectionContext {
             include,
             location,
             location_fallback,
             store_source,
-            optimize_level_zero: self.bytecode_optimize_level_zero,
-            optimize_level_one: self.bytecode_optimize_level_one,
-            optimize_level_two: self.bytecode_optimize_level_two,
+            strip_record_hashes,
+            optimize_level_zero,
+            optimize_level_one,
+            optimize_level_two,
         }
     }
 
@@ -473,15 +872,13 @@ impl PythonPackagingPolicy {
         }
 
         match resource {
-            PythonResource::ModuleSource(module) => {
-                if !self.include_test && module.is_test {
-                    false
-                } else {
-                    self.include_distribution_sources
-                }
-            }
+            // Whether source is retained is controlled by `store_source`. The
+            // module is still needed to derive bytecode.
+            PythonResource::ModuleSource(module) => self.include_test || !module.is_test,
             PythonResource::ModuleBytecodeRequest(module) => self.include_test || !module.is_test,
-            PythonResource::ModuleBytecode(_) => false,
+            // Bytecode is normally derived from source. So bytecode resources
+            // are only seen for modules without source.
+            PythonResource::ModuleBytecode(module) => self.include_test || !module.is_test,
             PythonResource::PackageResource(resource) => {
                 if resource.is_stdlib {
                     if self.include_distribution_resources {
@@ -490,10 +887,12 @@ impl PythonPackagingPolicy {
                         false
                     }
                 } else {
-                    true
+                    self.include_gettext_catalogs || resource.gettext_catalog().is_none()
                 }
             }
-            PythonResource::PackageDistributionResource(_) => true,
+            PythonResource::PackageDistributionResource(_) => {
+                self.distribution_metadata_mode != DistributionMetadataMode::Exclude
+            }
             PythonResource::ExtensionModule(_) => false,
             PythonResource::PathExtension(_) => false,
             PythonResource::EggFile(_) => false,
@@ -636,10 +1035,35 @@ impl PythonPackagingPolicy {
 mod tests {
     use {
         super::*,
+        crate::resource::{
+            BytecodeOptimizationLevel, PythonModuleBytecode, PythonModuleSource,
+            PythonPackageDistributionResource, PythonPackageDistributionResourceFlavor,
+            PythonPackageResource,
+        },
         std::path::PathBuf,
-        tugger_file_manifest::{File, FileEntry},
+        tugger_file_manifest::{File, FileData, FileEntry},
     };
 
+    #[test]
+    fn test_stdlib_packed_resources_location() {
+        for value in &[
+            "default",
+            "embedded",
+            "external:stdlib",
+            "embedded-and-external:lib/stdlib",
+        ] {
+            assert_eq!(
+                StdlibPackedResourcesLocation::try_from(*value)
+                    .unwrap()
+                    .to_string(),
+                *value
+            );
+        }
+
+        assert!(StdlibPackedResourcesLocation::try_from("external:").is_err());
+        assert!(StdlibPackedResourcesLocation::try_from("bogus:stdlib").is_err());
+    }
+
     #[test]
     fn test_add_collection_context_file() -> Result<()> {
         let mut policy = PythonPackagingPolicy::default();
@@ -662,4 +1086,290 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_bytecode_only() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_resource_handling_mode(ResourceHandlingMode::BytecodeOnly);
+
+        for is_stdlib in &[false, true] {
+            let module = PythonModuleSource {
+                name: "foo".to_string(),
+                source: FileData::Memory(vec![]),
+                is_package: false,
+                cache_tag: "cpython-39".to_string(),
+                is_stdlib: *is_stdlib,
+                is_test: false,
+            };
+
+            let add_context = policy.derive_add_collection_context(&module.into());
+            assert!(add_context.include);
+            assert!(!add_context.store_source);
+            assert!(add_context.optimize_level_zero);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sourceless_bytecode() -> Result<()> {
+        let policy = PythonPackagingPolicy::default();
+
+        let module = PythonModuleBytecode::new(
+            "foo",
+            BytecodeOptimizationLevel::Zero,
+            false,
+            "cpython-39",
+            &[42],
+        );
+
+        let add_context = policy.derive_add_collection_context(&module.into());
+        assert!(add_context.include);
+        assert!(add_context.optimize_level_zero);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compat_packages() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+
+        let module = PythonModuleSource {
+            name: "numpy.core".to_string(),
+            source: FileData::Memory(vec![]),
+            is_package: true,
+            cache_tag: "cpython-39".to_string(),
+            is_stdlib: false,
+            is_test: false,
+        };
+
+        let resource = PythonPackageResource {
+            leaf_package: "numpy.core".to_string(),
+            relative_name: "include/numpy/ndarrayobject.h".to_string(),
+            data: FileData::Memory(vec![]),
+            is_stdlib: false,
+            is_test: false,
+        };
+
+        let other = PythonModuleSource {
+            name: "numpyx".to_string(),
+            ..module.clone()
+        };
+
+        let add_context = policy.derive_add_collection_context(&module.clone().into());
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        policy.enable_known_compat_fixes();
+        assert!(policy.compat_packages().contains("numpy"));
+
+        policy.set_resources_location_fallback(Some(ConcreteResourceLocation::RelativePath(
+            "other".to_string(),
+        )));
+        policy.set_include_classified_resources(false);
+
+        let add_context = policy.derive_add_collection_context(&module.into());
+        assert!(!add_context.include);
+        assert_eq!(
+            add_context.location,
+            ConcreteResourceLocation::RelativePath("lib".to_string())
+        );
+        assert_eq!(add_context.location_fallback, None);
+
+        let add_context = policy.derive_add_collection_context(&resource.into());
+        assert!(add_context.include);
+        assert_eq!(
+            add_context.location,
+            ConcreteResourceLocation::RelativePath("lib".to_string())
+        );
+
+        let add_context = policy.derive_add_collection_context(&other.into());
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_distribution_metadata_mode() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        assert_eq!(
+            policy.distribution_metadata_mode(),
+            DistributionMetadataMode::Include
+        );
+
+        let resource = PythonPackageDistributionResource {
+            location: PythonPackageDistributionResourceFlavor::DistInfo,
+            package: "foo".to_string(),
+            version: "1.0".to_string(),
+            name: "RECORD".to_string(),
+            data: FileData::Memory(vec![]),
+        };
+
+        let add_context = policy.derive_add_collection_context(&resource.clone().into());
+        assert!(add_context.include);
+        assert!(!add_context.strip_record_hashes);
+
+        policy.set_distribution_metadata_mode(DistributionMetadataMode::StripRecordHashes);
+        let add_context = policy.derive_add_collection_context(&resource.clone().into());
+        assert!(add_context.include);
+        assert!(add_context.strip_record_hashes);
+
+        policy.set_distribution_metadata_mode(DistributionMetadataMode::Exclude);
+        let add_context = policy.derive_add_collection_context(&resource.into());
+        assert!(!add_context.include);
+
+        for value in &["include", "strip-record-hashes", "exclude"] {
+            assert_eq!(
+                DistributionMetadataMode::try_from(*value).unwrap().as_ref(),
+                *value
+            );
+        }
+        assert!(DistributionMetadataMode::try_from("bogus").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_gettext_catalogs() {
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(policy.include_gettext_catalogs());
+
+        let catalog = PythonPackageResource {
+            leaf_package: "foo".to_string(),
+            relative_name: "locale/de/LC_MESSAGES/foo.mo".to_string(),
+            data: FileData::Memory(vec![]),
+            is_stdlib: false,
+            is_test: false,
+        };
+        let other = PythonPackageResource {
+            relative_name: "data.txt".to_string(),
+            ..catalog.clone()
+        };
+
+        let add_context = policy.derive_add_collection_context(&catalog.clone().into());
+        assert!(add_context.include);
+
+        policy.set_include_gettext_catalogs(false);
+        let add_context = policy.derive_add_collection_context(&catalog.into());
+        assert!(!add_context.include);
+        let add_context = policy.derive_add_collection_context(&other.into());
+        assert!(add_context.include);
+    }
+
+    #[test]
+    fn test_module_name_matches() {
+        assert!(module_name_matches("foo", "foo"));
+        assert!(module_name_matches("foo", "foo.bar"));
+        assert!(!module_name_matches("foo", "foobar"));
+        assert!(!module_name_matches("foo.bar", "foo"));
+        assert!(module_name_matches("*", "foo"));
+        assert!(module_name_matches("foo.*", "foo.bar.baz"));
+        assert!(!module_name_matches("foo.*", "foo"));
+        assert!(module_name_matches("*.tests", "foo.tests"));
+        assert!(!module_name_matches("*.tests", "foo.tests.bar"));
+        assert!(module_name_matches("foo.*.tests.*", "foo.bar.tests.baz"));
+        assert!(!module_name_matches("foo.*.tests.*", "foo.bar.baz"));
+    }
+
+    #[test]
+    fn test_bytecode_optimization_overrides() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+
+        let module = PythonModuleSource {
+            name: "foo.bar".to_string(),
+            source: FileData::Memory(vec![]),
+            is_package: false,
+            cache_tag: "cpython-39".to_string(),
+            is_stdlib: false,
+            is_test: false,
+        };
+        let other = PythonModuleSource {
+            name: "other".to_string(),
+            ..module.clone()
+        };
+
+        policy.register_bytecode_optimization_levels(
+            "foo",
+            &[
+                BytecodeOptimizationLevel::One,
+                BytecodeOptimizationLevel::Two,
+            ],
+        );
+        assert_eq!(policy.bytecode_optimization_overrides().len(), 1);
+
+        let add_context = policy.derive_add_collection_context(&module.clone().into());
+        assert!(!add_context.optimize_level_zero);
+        assert!(add_context.optimize_level_one);
+        assert!(add_context.optimize_level_two);
+
+        let add_context = policy.derive_add_collection_context(&other.into());
+        assert!(add_context.optimize_level_zero);
+        assert!(!add_context.optimize_level_one);
+        assert!(!add_context.optimize_level_two);
+
+        // The last matching pattern wins.
+        policy.register_bytecode_optimization_levels("foo.*", &[BytecodeOptimizationLevel::Two]);
+
+        let add_context = policy.derive_add_collection_context(&module.into());
+        assert!(!add_context.optimize_level_zero);
+        assert!(!add_context.optimize_level_one);
+        assert!(add_context.optimize_level_two);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytecode_optimization_level_substitutions() {
+        let mut policy = PythonPackagingPolicy::default();
+        let names = vec!["foo", "foo.bar", "foo.debug", "other"];
+
+        assert!(policy
+            .bytecode_optimization_level_substitutions(
+                BytecodeOptimizationLevel::Zero,
+                names.iter().copied()
+            )
+            .is_empty());
+
+        // Strip docstrings from `foo` but not from `foo.debug`.
+        policy.register_bytecode_optimization_levels(
+            "foo",
+            &[
+                BytecodeOptimizationLevel::One,
+                BytecodeOptimizationLevel::Two,
+            ],
+        );
+        policy
+            .register_bytecode_optimization_levels("foo.debug", &[BytecodeOptimizationLevel::Zero]);
+
+        assert_eq!(
+            policy.bytecode_optimization_level_substitutions(
+                BytecodeOptimizationLevel::Zero,
+                names.iter().copied()
+            ),
+            vec![
+                ("foo".to_string(), BytecodeOptimizationLevel::Two),
+                ("foo.bar".to_string(), BytecodeOptimizationLevel::Two),
+            ]
+        );
+
+        // Modules with bytecode for the interpreter's level are loaded as is.
+        assert_eq!(
+            policy.bytecode_optimization_level_substitutions(
+                BytecodeOptimizationLevel::Two,
+                names.iter().copied()
+            ),
+            vec![("foo.debug".to_string(), BytecodeOptimizationLevel::Zero)]
+        );
+
+        // Modules without any bytecode have nothing to substitute.
+        policy.register_bytecode_optimization_levels("other", &[]);
+        assert_eq!(
+            policy
+                .bytecode_optimization_level_substitutions(
+                    BytecodeOptimizationLevel::Zero,
+                    vec!["other"].into_iter()
+                )
+                .len(),
+            0
+        );
+    }
 }
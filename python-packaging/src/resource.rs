@@ -366,6 +366,32 @@ impl PythonPackageResource {
 
         path
     }
+
+    /// Obtain the language and domain of this resource if it is a gettext message catalog.
+    pub fn gettext_catalog(&self) -> Option<(&str, &str)> {
+        parse_gettext_catalog_path(&self.relative_name)
+    }
+}
+
+/// Parse the path of a gettext message catalog.
+///
+/// Catalogs are named `<language>/LC_MESSAGES/<domain>.mo`, optionally
+/// under a directory (conventionally `locale`). Returns the language and
+/// domain if `path` names a catalog.
+pub fn parse_gettext_catalog_path(path: &str) -> Option<(&str, &str)> {
+    let mut parts = path.rsplit(|c| c == '/' || c == '\\');
+
+    let domain = parts.next()?.strip_suffix(".mo")?;
+    if parts.next()? != "LC_MESSAGES" {
+        return None;
+    }
+    let language = parts.next()?;
+
+    if domain.is_empty() || language.is_empty() {
+        None
+    } else {
+        Some((language, domain))
+    }
 }
 
 /// Represents where a Python package distribution resource is materialized.
@@ -430,6 +456,49 @@ impl PythonPackageDistributionResource {
 
         PathBuf::from(prefix).join(p).join(&self.name)
     }
+
+    /// Whether this resource is the `RECORD` file of a `.dist-info` directory.
+    pub fn is_record(&self) -> bool {
+        self.location == PythonPackageDistributionResourceFlavor::DistInfo && self.name == "RECORD"
+    }
+
+    /// Obtain a copy of this resource with hashes and sizes removed from `RECORD`.
+    ///
+    /// `RECORD` lists every file installed by a distribution along with its
+    /// hash and size. Since packaged files may be relocated or transformed,
+    /// these values are often stale and only add to the binary size. Each
+    /// line is reduced to `<path>,,`, which is what `RECORD` contains for
+    /// files not having a hash, such as `RECORD` itself.
+    ///
+    /// Resources other than `RECORD` are returned unmodified.
+    pub fn strip_record_hashes(&self) -> Result<Self> {
+        if !self.is_record() {
+            return Ok(self.clone());
+        }
+
+        let data = self.data.resolve()?;
+        let data = String::from_utf8(data)
+            .map_err(|_| anyhow!("RECORD of {} is not valid UTF-8", self.package))?;
+
+        let mut stripped = String::with_capacity(data.len());
+
+        for line in data.lines() {
+            if line.is_empty() {
+                continue;
+            }
+
+            // The hash and size never contain commas. But the path may, if quoted.
+            let path = line.rsplitn(3, ',').last().unwrap_or(line);
+
+            stripped.push_str(path);
+            stripped.push_str(",,\n");
+        }
+
+        Ok(Self {
+            data: FileData::Memory(stripped.into_bytes()),
+            ..self.clone()
+        })
+    }
 }
 
 /// Represents a dependency on a library.
@@ -962,4 +1031,53 @@ mod tests {
         assert!(!bytecode.is_in_packages(&[]));
         assert!(!bytecode.is_in_packages(&["bar".to_string()]));
     }
+
+    #[test]
+    fn test_parse_gettext_catalog_path() {
+        assert_eq!(
+            parse_gettext_catalog_path("locale/de/LC_MESSAGES/myapp.mo"),
+            Some(("de", "myapp"))
+        );
+        assert_eq!(
+            parse_gettext_catalog_path("pt_BR/LC_MESSAGES/myapp.mo"),
+            Some(("pt_BR", "myapp"))
+        );
+        assert_eq!(
+            parse_gettext_catalog_path("locale\\de\\LC_MESSAGES\\myapp.mo"),
+            Some(("de", "myapp"))
+        );
+        assert_eq!(parse_gettext_catalog_path("LC_MESSAGES/myapp.mo"), None);
+        assert_eq!(
+            parse_gettext_catalog_path("locale/de/LC_MESSAGES/myapp.po"),
+            None
+        );
+        assert_eq!(parse_gettext_catalog_path("locale/de/myapp.mo"), None);
+    }
+
+    #[test]
+    fn test_strip_record_hashes() -> Result<()> {
+        let record = PythonPackageDistributionResource {
+            location: PythonPackageDistributionResourceFlavor::DistInfo,
+            package: "foo".to_string(),
+            version: "1.0".to_string(),
+            name: "RECORD".to_string(),
+            data: FileData::Memory(
+                b"foo/__init__.py,sha256=47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU,0\n\"foo/a,b.py\",sha256=47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU,0\nfoo-1.0.dist-info/RECORD,,\n".to_vec(),
+            ),
+        };
+
+        let stripped = record.strip_record_hashes()?;
+        assert_eq!(
+            stripped.data.resolve()?,
+            b"foo/__init__.py,,\n\"foo/a,b.py\",,\nfoo-1.0.dist-info/RECORD,,\n".to_vec()
+        );
+
+        let metadata = PythonPackageDistributionResource {
+            name: "METADATA".to_string(),
+            ..record
+        };
+        assert_eq!(metadata.strip_record_hashes()?, metadata);
+
+        Ok(())
+    }
 }
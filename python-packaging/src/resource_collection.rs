@@ -20,7 +20,10 @@ use {
         },
     },
     anyhow::{anyhow, Context, Result},
-    python_packed_resources::data::{Resource, ResourceFlavor},
+    python_packed_resources::{
+        data::{Resource, ResourceFlavor, SectionKind},
+        writer::PackedSection,
+    },
     std::{
         borrow::Cow,
         collections::{BTreeMap, BTreeSet, HashMap},
@@ -97,6 +100,111 @@ impl PrePackagedResource {
             || self.is_extension_module
     }
 
+    /// Obtain the relative path prefixes Python modules of this resource are installed under.
+    ///
+    /// These are the directories that would need to be on `sys.path` for
+    /// a filesystem-based importer to find the module.
+    pub fn relative_path_module_prefixes(&self) -> BTreeSet<String> {
+        let mut prefixes = BTreeSet::new();
+
+        if let Some((prefix, _)) = &self.relative_path_module_source {
+            prefixes.insert(prefix.clone());
+        }
+
+        for (prefix, _, _) in [
+            &self.relative_path_bytecode,
+            &self.relative_path_bytecode_opt1,
+            &self.relative_path_bytecode_opt2,
+        ]
+        .iter()
+        .filter_map(|x| x.as_ref())
+        {
+            prefixes.insert(prefix.clone());
+        }
+
+        if let Some((path, _)) = &self.relative_path_extension_module_shared_library {
+            // The path has a directory per package component plus the file name.
+            if let Some(prefix) = path.ancestors().nth(self.name.split('.').count()) {
+                prefixes.insert(prefix.display().to_string().replace('\\', "/"));
+            }
+        }
+
+        prefixes
+    }
+
+    /// Load bytecode compiled at `compile_level` at optimization level `level`.
+    ///
+    /// The interpreter only loads bytecode for its optimization level. This
+    /// stores the bytecode for `compile_level` in the place bytecode for
+    /// `level` is loaded from, so the module is loaded with the optimizations
+    /// of `compile_level`, e.g. without docstrings. Bytecode derived from
+    /// source is compiled with `compiler`. Bytecode for `compile_level` is
+    /// retained. Has no effect if there is no bytecode for `compile_level`.
+    pub fn load_bytecode_at_optimization_level(
+        &mut self,
+        level: BytecodeOptimizationLevel,
+        compile_level: BytecodeOptimizationLevel,
+        compiler: &mut dyn PythonBytecodeCompiler,
+    ) -> Result<()> {
+        let mut compile = |provider: &PythonModuleBytecodeProvider| -> Result<_> {
+            Ok(match provider {
+                PythonModuleBytecodeProvider::Provided(data) => {
+                    PythonModuleBytecodeProvider::Provided(data.clone())
+                }
+                PythonModuleBytecodeProvider::FromSource(source) => {
+                    PythonModuleBytecodeProvider::Provided(FileData::Memory(
+                        compiler
+                            .compile(
+                                &source.resolve()?,
+                                &self.name,
+                                compile_level,
+                                CompileMode::Bytecode,
+                            )
+                            .with_context(|| format!("compiling bytecode for {}", self.name))?,
+                    ))
+                }
+            })
+        };
+
+        let in_memory = match compile_level {
+            BytecodeOptimizationLevel::Zero => &self.in_memory_bytecode,
+            BytecodeOptimizationLevel::One => &self.in_memory_bytecode_opt1,
+            BytecodeOptimizationLevel::Two => &self.in_memory_bytecode_opt2,
+        }
+        .as_ref()
+        .map(&mut compile)
+        .transpose()?;
+
+        let relative_path = match compile_level {
+            BytecodeOptimizationLevel::Zero => &self.relative_path_bytecode,
+            BytecodeOptimizationLevel::One => &self.relative_path_bytecode_opt1,
+            BytecodeOptimizationLevel::Two => &self.relative_path_bytecode_opt2,
+        }
+        .as_ref()
+        .map(|(prefix, cache_tag, provider)| -> Result<_> {
+            Ok((prefix.clone(), cache_tag.clone(), compile(provider)?))
+        })
+        .transpose()?;
+
+        if let Some(provider) = in_memory {
+            *match level {
+                BytecodeOptimizationLevel::Zero => &mut self.in_memory_bytecode,
+                BytecodeOptimizationLevel::One => &mut self.in_memory_bytecode_opt1,
+                BytecodeOptimizationLevel::Two => &mut self.in_memory_bytecode_opt2,
+            } = Some(provider);
+        }
+
+        if let Some(entry) = relative_path {
+            *match level {
+                BytecodeOptimizationLevel::Zero => &mut self.relative_path_bytecode,
+                BytecodeOptimizationLevel::One => &mut self.relative_path_bytecode_opt1,
+                BytecodeOptimizationLevel::Two => &mut self.relative_path_bytecode_opt2,
+            } = Some(entry);
+        }
+
+        Ok(())
+    }
+
     /// Convert the instance to a `Resource`.
     ///
     /// This will compile bytecode from source code using the specified compiler.
@@ -571,6 +679,11 @@ pub struct PythonResourceAddCollectionContext {
     /// controlling this behavior.
     pub store_source: bool,
 
+    /// Whether to remove hashes and sizes from a distribution's `RECORD` file.
+    ///
+    /// Only has an effect when handling a `PythonPackageDistributionResource`.
+    pub strip_record_hashes: bool,
+
     /// Whether to store Python bytecode for optimization level 0.
     pub optimize_level_zero: bool,
 
@@ -588,6 +701,7 @@ impl PythonResourceAddCollectionContext {
         self.location = other.location.clone();
         self.location_fallback = other.location_fallback.clone();
         self.store_source = other.store_source;
+        self.strip_record_hashes = other.strip_record_hashes;
         self.optimize_level_zero = other.optimize_level_zero;
         self.optimize_level_one = other.optimize_level_one;
         self.optimize_level_two = other.optimize_level_two;
@@ -618,12 +732,48 @@ pub struct CompiledResourcesCollection<'a> {
 
     /// Extra file installs that must be performed so referenced files are available.
     pub extra_files: Vec<FileInstall>,
+
+    /// Metadata key-value pairs recorded in packed resources data.
+    pub metadata: BTreeMap<String, String>,
 }
 
 impl<'a> CompiledResourcesCollection<'a> {
-    /// Write resources to packed resources data, version 1.
+    /// Transform in-memory Python module source and bytecode payloads.
+    ///
+    /// This can be used to obfuscate or encrypt module payloads. Whatever
+    /// loads the resources must reverse the transform. Payloads referenced
+    /// via filesystem paths are not transformed.
+    pub fn transform_module_payloads(&mut self, transform: impl Fn(&[u8]) -> Vec<u8>) {
+        for resource in self.resources.values_mut().filter(|r| r.is_module) {
+            for payload in vec![
+                &mut resource.in_memory_source,
+                &mut resource.in_memory_bytecode,
+                &mut resource.in_memory_bytecode_opt1,
+                &mut resource.in_memory_bytecode_opt2,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                *payload = Cow::Owned(transform(payload));
+            }
+        }
+    }
+
+    /// Write resources to packed resources data, version 4.
     pub fn write_packed_resources<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
-        python_packed_resources::writer::write_packed_resources_v3(
+        let metadata = python_packed_resources::writer::encode_metadata(&self.metadata)?;
+
+        let extra_sections = if self.metadata.is_empty() {
+            vec![]
+        } else {
+            vec![PackedSection {
+                kind: SectionKind::Metadata.into(),
+                optional: true,
+                data: &metadata,
+            }]
+        };
+
+        python_packed_resources::writer::write_packed_resources_v4(
             &self
                 .resources
                 .values()
@@ -631,10 +781,27 @@ impl<'a> CompiledResourcesCollection<'a> {
                 .collect::<Vec<Resource<'a, u8>>>(),
             writer,
             None,
+            0,
+            &extra_sections,
         )
     }
 }
 
+/// XOR data with a repeating key.
+///
+/// The transform is its own inverse. It is a deterrent against trivial
+/// extraction of resource payloads, not encryption.
+pub fn xor_resource_payload(data: &[u8], key: &[u8]) -> Vec<u8> {
+    if key.is_empty() {
+        return data.to_vec();
+    }
+
+    data.iter()
+        .zip(key.iter().cycle())
+        .map(|(a, b)| a ^ b)
+        .collect()
+}
+
 /// Type used to collect Python resources so they can be serialized.
 ///
 /// We often want to turn Python resource primitives (module source,
@@ -767,6 +934,29 @@ impl PythonResourceCollector {
         Ok(())
     }
 
+    /// Apply a filter function on package resources in this collection and mutate in place.
+    ///
+    /// The filter function receives the name of the package and the name of
+    /// the resource within that package. If it returns true, the package
+    /// resource will be preserved. Both in-memory and relative path package
+    /// resources are filtered.
+    pub fn filter_package_resources_mut<F>(&mut self, filter: F) -> Result<()>
+    where
+        F: Fn(&str, &str) -> bool,
+    {
+        for (package, resource) in self.resources.iter_mut() {
+            if let Some(resources) = resource.in_memory_resources.as_mut() {
+                resources.retain(|name, _| filter(package, name));
+            }
+
+            if let Some(resources) = resource.relative_path_package_resources.as_mut() {
+                resources.retain(|name, _| filter(package, name));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Obtain an iterator over the resources in this collector.
     pub fn iter_resources(&self) -> impl Iterator<Item = (&String, &PrePackagedResource)> {
         Box::new(self.resources.iter())
@@ -1271,8 +1461,14 @@ impl PythonResourceCollector {
             return Ok(());
         }
 
+        let resource = if add_context.strip_record_hashes {
+            Cow::Owned(resource.strip_record_hashes()?)
+        } else {
+            Cow::Borrowed(resource)
+        };
+
         self.add_python_resource_with_locations(
-            &resource.into(),
+            &resource.as_ref().into(),
             &add_context.location,
             &add_context.location_fallback,
         )
@@ -1792,6 +1988,23 @@ impl PythonResourceCollector {
         Ok(res)
     }
 
+    /// Load bytecode of a module compiled at another optimization level.
+    ///
+    /// See `PrePackagedResource::load_bytecode_at_optimization_level()`.
+    pub fn load_bytecode_at_optimization_level(
+        &mut self,
+        name: &str,
+        level: BytecodeOptimizationLevel,
+        compile_level: BytecodeOptimizationLevel,
+        compiler: &mut dyn PythonBytecodeCompiler,
+    ) -> Result<()> {
+        if let Some(resource) = self.resources.get_mut(name) {
+            resource.load_bytecode_at_optimization_level(level, compile_level, compiler)?;
+        }
+
+        Ok(())
+    }
+
     /// Compiles resources into a finalized collection.
     ///
     /// This will take all resources collected so far and convert them into
@@ -1823,6 +2036,7 @@ impl PythonResourceCollector {
         Ok(CompiledResourcesCollection {
             resources,
             extra_files,
+            metadata: BTreeMap::new(),
         })
     }
 }
@@ -2521,6 +2735,73 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_load_bytecode_at_optimization_level() -> Result<()> {
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+
+        let mut pre = PrePackagedResource {
+            is_module: true,
+            name: "foo.bar".to_string(),
+            in_memory_bytecode_opt2: Some(PythonModuleBytecodeProvider::FromSource(
+                FileData::Memory(b"source".to_vec()),
+            )),
+            relative_path_bytecode_opt2: Some((
+                "prefix".to_string(),
+                "tag".to_string(),
+                PythonModuleBytecodeProvider::FromSource(FileData::Memory(b"source".to_vec())),
+            )),
+            ..PrePackagedResource::default()
+        };
+
+        // There is no bytecode for level 1 to load.
+        pre.load_bytecode_at_optimization_level(
+            BytecodeOptimizationLevel::Zero,
+            BytecodeOptimizationLevel::One,
+            &mut compiler,
+        )?;
+        assert!(pre.in_memory_bytecode.is_none());
+        assert!(pre.relative_path_bytecode.is_none());
+
+        pre.load_bytecode_at_optimization_level(
+            BytecodeOptimizationLevel::Zero,
+            BytecodeOptimizationLevel::Two,
+            &mut compiler,
+        )?;
+
+        let (resource, installs) = pre.to_resource(&mut compiler)?;
+
+        assert_eq!(
+            resource,
+            Resource {
+                is_module: true,
+                name: Cow::Owned("foo.bar".to_string()),
+                in_memory_bytecode: Some(Cow::Owned(b"bc2source".to_vec())),
+                in_memory_bytecode_opt2: Some(Cow::Owned(b"bc2source".to_vec())),
+                relative_path_module_bytecode: Some(Cow::Owned(PathBuf::from(
+                    "prefix/foo/__pycache__/bar.tag.pyc"
+                ))),
+                relative_path_module_bytecode_opt2: Some(Cow::Owned(PathBuf::from(
+                    "prefix/foo/__pycache__/bar.tag.opt-2.pyc"
+                ))),
+                ..Resource::default()
+            }
+        );
+
+        assert_eq!(
+            installs[0],
+            (
+                PathBuf::from("prefix/foo/__pycache__/bar.tag.pyc"),
+                FileData::Memory(
+                    b"\x2a\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00bc2source"
+                        .to_vec()
+                ),
+                false
+            )
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_resource_conversion_relative_path_extension_module_shared_library() -> Result<()> {
         let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
@@ -3143,6 +3424,7 @@ mod tests {
             location: ConcreteResourceLocation::InMemory,
             location_fallback: None,
             store_source: false,
+            strip_record_hashes: false,
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
@@ -3371,6 +3653,7 @@ mod tests {
             location: ConcreteResourceLocation::InMemory,
             location_fallback: None,
             store_source: false,
+            strip_record_hashes: false,
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
@@ -3569,6 +3852,7 @@ mod tests {
             location: ConcreteResourceLocation::InMemory,
             location_fallback: None,
             store_source: false,
+            strip_record_hashes: false,
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
@@ -3806,6 +4090,52 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_filter_package_resources() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+            DEFAULT_CACHE_TAG,
+        );
+
+        for name in &["resource.txt", "locale/de.mo"] {
+            r.add_python_package_resource(
+                &PythonPackageResource {
+                    leaf_package: "foo".to_string(),
+                    relative_name: name.to_string(),
+                    data: FileData::Memory(vec![42]),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+                &ConcreteResourceLocation::InMemory,
+            )?;
+        }
+
+        r.filter_package_resources_mut(|package, name| {
+            !(package == "foo" && name.starts_with("locale/"))
+        })?;
+
+        assert_eq!(
+            r.resources.get("foo"),
+            Some(&PrePackagedResource {
+                is_module: true,
+                name: "foo".to_string(),
+                is_package: true,
+                in_memory_resources: Some(
+                    [("resource.txt".to_string(), FileData::Memory(vec![42]))]
+                        .iter()
+                        .cloned()
+                        .collect()
+                ),
+                ..PrePackagedResource::default()
+            })
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_package_resource_with_context() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -3829,6 +4159,7 @@ mod tests {
             location: ConcreteResourceLocation::InMemory,
             location_fallback: None,
             store_source: false,
+            strip_record_hashes: false,
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
@@ -4057,6 +4388,7 @@ mod tests {
             location: ConcreteResourceLocation::InMemory,
             location_fallback: None,
             store_source: false,
+            strip_record_hashes: false,
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
@@ -4114,6 +4446,27 @@ mod tests {
 
         r.resources.clear();
 
+        // strip_record_hashes rewrites RECORD.
+        let record = PythonPackageDistributionResource {
+            name: "RECORD".to_string(),
+            data: FileData::Memory(b"foo/__init__.py,sha256=abc,0\n".to_vec()),
+            ..resource
+        };
+
+        r.allowed_locations = vec![AbstractResourceLocation::InMemory];
+        add_context.strip_record_hashes = true;
+        r.add_python_package_distribution_resource_with_context(&record, &add_context)?;
+        assert_eq!(
+            r.resources
+                .get(&record.package)
+                .unwrap()
+                .in_memory_distribution_resources
+                .as_ref()
+                .unwrap()
+                .get("RECORD"),
+            Some(&FileData::Memory(b"foo/__init__.py,,\n".to_vec()))
+        );
+
         Ok(())
     }
 
@@ -4469,6 +4822,51 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_relative_path_module_prefixes() -> Result<()> {
+        let resource = PrePackagedResource {
+            name: "foo.bar".to_string(),
+            is_module: true,
+            relative_path_module_source: Some(("lib".to_string(), FileData::Memory(vec![42]))),
+            relative_path_bytecode_opt1: Some((
+                "lib/python3.9".to_string(),
+                DEFAULT_CACHE_TAG.to_string(),
+                PythonModuleBytecodeProvider::FromSource(FileData::Memory(vec![42])),
+            )),
+            ..PrePackagedResource::default()
+        };
+
+        assert_eq!(
+            resource.relative_path_module_prefixes(),
+            vec!["lib".to_string(), "lib/python3.9".to_string()]
+                .into_iter()
+                .collect::<BTreeSet<_>>()
+        );
+
+        let resource = PrePackagedResource {
+            name: "foo.bar".to_string(),
+            is_extension_module: true,
+            relative_path_extension_module_shared_library: Some((
+                PathBuf::from("packages/foo/bar.so"),
+                FileData::Memory(vec![42]),
+            )),
+            ..PrePackagedResource::default()
+        };
+
+        assert_eq!(
+            resource.relative_path_module_prefixes(),
+            vec!["packages".to_string()]
+                .into_iter()
+                .collect::<BTreeSet<_>>()
+        );
+
+        assert!(PrePackagedResource::default()
+            .relative_path_module_prefixes()
+            .is_empty());
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_in_memory_file_data() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -4616,6 +5014,7 @@ mod tests {
             location: ConcreteResourceLocation::InMemory,
             location_fallback: None,
             store_source: false,
+            strip_record_hashes: false,
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
@@ -4718,4 +5117,87 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_transform_module_payloads() -> Result<()> {
+        let mut compiled = CompiledResourcesCollection::default();
+        compiled.resources.insert(
+            "module".to_string(),
+            Resource {
+                is_module: true,
+                name: Cow::Owned("module".to_string()),
+                in_memory_source: Some(Cow::Owned(b"source".to_vec())),
+                in_memory_bytecode_opt2: Some(Cow::Owned(b"bytecode".to_vec())),
+                relative_path_module_bytecode: Some(Cow::Owned("module.pyc".into())),
+                ..Resource::default()
+            },
+        );
+        compiled.resources.insert(
+            "file.txt".to_string(),
+            Resource {
+                is_utf8_filename_data: true,
+                name: Cow::Owned("file.txt".to_string()),
+                file_data_embedded: Some(Cow::Owned(b"file".to_vec())),
+                ..Resource::default()
+            },
+        );
+
+        compiled.transform_module_payloads(|data| xor_resource_payload(data, b"key"));
+
+        let module = &compiled.resources["module"];
+        assert_ne!(
+            module.in_memory_source,
+            Some(Cow::Owned(b"source".to_vec()))
+        );
+        assert_eq!(
+            xor_resource_payload(module.in_memory_source.as_ref().unwrap(), b"key"),
+            b"source".to_vec()
+        );
+        assert_eq!(
+            xor_resource_payload(module.in_memory_bytecode_opt2.as_ref().unwrap(), b"key"),
+            b"bytecode".to_vec()
+        );
+        assert!(module.in_memory_bytecode.is_none());
+        assert_eq!(
+            module.relative_path_module_bytecode,
+            Some(Cow::Owned("module.pyc".into()))
+        );
+        assert_eq!(
+            compiled.resources["file.txt"].file_data_embedded,
+            Some(Cow::Owned(b"file".to_vec()))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_packed_resources_metadata() -> Result<()> {
+        let mut compiled = CompiledResourcesCollection::default();
+        compiled.resources.insert(
+            "module".to_string(),
+            Resource {
+                is_module: true,
+                name: Cow::Owned("module".to_string()),
+                in_memory_source: Some(Cow::Owned(b"source".to_vec())),
+                ..Resource::default()
+            },
+        );
+
+        let mut data = vec![];
+        compiled.write_packed_resources(&mut data)?;
+        let resources = python_packed_resources::parser::load_resources_detailed(&data)?;
+        assert!(resources.metadata().is_empty());
+
+        compiled
+            .metadata
+            .insert("python_version".to_string(), "3.9".to_string());
+
+        let mut data = vec![];
+        compiled.write_packed_resources(&mut data)?;
+        let resources = python_packed_resources::parser::load_resources_detailed(&data)?;
+        assert_eq!(resources.metadata().get("python_version"), Some(&"3.9"));
+        assert_eq!(resources.count(), 1);
+
+        Ok(())
+    }
 }
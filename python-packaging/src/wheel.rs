@@ -30,6 +30,173 @@ static RE_WHEEL_INFO: Lazy<regex::Regex> = Lazy::new(|| {
 
 const S_IXUSR: u32 = 64;
 
+/// Family of a PEP 425 platform tag.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PlatformFamily {
+    Glibc,
+    Musl,
+    MacOs,
+    Windows,
+}
+
+/// A parsed PEP 425 platform tag.
+#[derive(Clone, Debug, PartialEq)]
+struct PlatformTag<'a> {
+    family: PlatformFamily,
+    arch: &'a str,
+    /// Minimum glibc, musl, or macOS version required, if known.
+    version: Option<(u32, u32)>,
+}
+
+impl<'a> PlatformTag<'a> {
+    fn parse(tag: &'a str) -> Option<Self> {
+        // Tags like manylinux_2_17_x86_64 and macosx_10_9_x86_64.
+        let versioned = |rest: &'a str| -> Option<((u32, u32), &'a str)> {
+            let mut parts = rest.splitn(3, '_');
+            let major = parts.next()?.parse().ok()?;
+            let minor = parts.next()?.parse().ok()?;
+
+            Some(((major, minor), parts.next()?))
+        };
+
+        let (family, version, arch) = if let Some(arch) = tag.strip_prefix("linux_") {
+            (PlatformFamily::Glibc, None, arch)
+        } else if let Some(arch) = tag.strip_prefix("manylinux1_") {
+            (PlatformFamily::Glibc, Some((2, 5)), arch)
+        } else if let Some(arch) = tag.strip_prefix("manylinux2010_") {
+            (PlatformFamily::Glibc, Some((2, 12)), arch)
+        } else if let Some(arch) = tag.strip_prefix("manylinux2014_") {
+            (PlatformFamily::Glibc, Some((2, 17)), arch)
+        } else if let Some(rest) = tag.strip_prefix("manylinux_") {
+            let (version, arch) = versioned(rest)?;
+            (PlatformFamily::Glibc, Some(version), arch)
+        } else if let Some(rest) = tag.strip_prefix("musllinux_") {
+            let (version, arch) = versioned(rest)?;
+            (PlatformFamily::Musl, Some(version), arch)
+        } else if let Some(rest) = tag.strip_prefix("macosx_") {
+            let (version, arch) = versioned(rest)?;
+            (PlatformFamily::MacOs, Some(version), arch)
+        } else if tag == "win32" {
+            (PlatformFamily::Windows, None, "x86")
+        } else if let Some(arch) = tag.strip_prefix("win_") {
+            (PlatformFamily::Windows, None, arch)
+        } else {
+            return None;
+        };
+
+        Some(Self {
+            family,
+            arch,
+            version,
+        })
+    }
+
+    /// Whether a wheel having this platform tag runs on `target`.
+    fn runs_on(&self, target: &PlatformTag) -> bool {
+        let arch_matches = self.arch == target.arch
+            || (self.family == PlatformFamily::MacOs
+                && match self.arch {
+                    "universal2" => matches!(target.arch, "x86_64" | "arm64"),
+                    "intel" | "universal" => target.arch == "x86_64",
+                    _ => false,
+                });
+
+        // Wheels requiring a newer OS or libc than the target supports may
+        // fail to load at run-time.
+        let version_matches = match (self.version, target.version) {
+            (Some(wheel), Some(target)) => wheel <= target,
+            _ => true,
+        };
+
+        self.family == target.family && arch_matches && version_matches
+    }
+}
+
+/// The PEP 425 tags of the interpreter that wheels are installed for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WheelTargetTags {
+    /// Python tag. e.g. `cp39`.
+    pub python_tag: String,
+
+    /// ABI tag. e.g. `cp39`.
+    pub abi_tag: Option<String>,
+
+    /// Platform tag. e.g. `manylinux2014_x86_64`.
+    ///
+    /// `none` means the interpreter can't load extension modules from
+    /// wheels and only pure Python wheels are compatible.
+    pub platform_tag: String,
+}
+
+impl WheelTargetTags {
+    /// Whether a `{python tag}-{abi tag}-{platform tag}` tag is compatible.
+    ///
+    /// Compressed tag sets like `py2.py3-none-any` are accepted.
+    pub fn is_compatible(&self, tag: &str) -> bool {
+        let parts = tag.split('-').collect::<Vec<_>>();
+        if parts.len() != 3 {
+            return false;
+        }
+
+        parts[0].split('.').any(|python| {
+            parts[1].split('.').any(|abi| {
+                self.is_python_abi_compatible(python, abi)
+                    && parts[2]
+                        .split('.')
+                        .any(|platform| self.is_platform_compatible(platform))
+            })
+        })
+    }
+
+    fn is_python_abi_compatible(&self, python: &str, abi: &str) -> bool {
+        // e.g. cp39 -> (cp, 3, 9).
+        let (implementation, version) = self.python_tag.split_at(2.min(self.python_tag.len()));
+        let (major, minor) = version.split_at(1.min(version.len()));
+
+        // Wheel tags for major.minor versions not newer than ours.
+        let not_newer = |tag_version: &str| {
+            tag_version == major
+                || (tag_version.starts_with(major)
+                    && tag_version.len() > major.len()
+                    && matches!(
+                        (tag_version[major.len()..].parse::<u32>(), minor.parse::<u32>()),
+                        (Ok(theirs), Ok(ours)) if theirs <= ours
+                    ))
+        };
+
+        match abi {
+            "none" => {
+                python == self.python_tag || python.strip_prefix("py").map_or(false, not_newer)
+            }
+            "abi3" => {
+                implementation == "cp"
+                    && python
+                        .strip_prefix("cp")
+                        .map_or(false, |v| v.len() > major.len() && not_newer(v))
+            }
+            abi => python == self.python_tag && Some(abi) == self.abi_tag.as_deref(),
+        }
+    }
+
+    fn is_platform_compatible(&self, platform: &str) -> bool {
+        if platform == "any" {
+            true
+        } else if self.platform_tag == "none" {
+            false
+        } else if platform == self.platform_tag {
+            true
+        } else {
+            match (
+                PlatformTag::parse(platform),
+                PlatformTag::parse(&self.platform_tag),
+            ) {
+                (Some(wheel), Some(target)) => wheel.runs_on(&target),
+                _ => false,
+            }
+        }
+    }
+}
+
 /// Represents a Python wheel archive.
 pub struct WheelArchive {
     files: FileManifest,
@@ -168,6 +335,11 @@ impl WheelArchive {
         self.archive_metadata_headers("Tag")
     }
 
+    /// Whether any `Tag` of the wheel archive is compatible with an interpreter.
+    pub fn is_compatible(&self, target: &WheelTargetTags) -> Result<bool> {
+        Ok(self.tags()?.iter().any(|tag| target.is_compatible(tag)))
+    }
+
     /// `Build` identifier for the wheel archive.
     pub fn build(&self) -> Result<Cow<str>> {
         self.archive_metadata_header("Build")
@@ -301,3 +473,60 @@ impl WheelArchive {
         .collect::<Result<Vec<_>>>()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(platform_tag: &str) -> WheelTargetTags {
+        WheelTargetTags {
+            python_tag: "cp39".to_string(),
+            abi_tag: Some("cp39".to_string()),
+            platform_tag: platform_tag.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_python_abi_compatible() {
+        let target = target("manylinux2014_x86_64");
+
+        assert!(target.is_compatible("py3-none-any"));
+        assert!(target.is_compatible("py2.py3-none-any"));
+        assert!(target.is_compatible("py38-none-any"));
+        assert!(target.is_compatible("cp39-none-any"));
+        assert!(target.is_compatible("cp39-cp39-manylinux2014_x86_64"));
+        assert!(target.is_compatible("cp36-abi3-manylinux2014_x86_64"));
+        assert!(!target.is_compatible("py2-none-any"));
+        assert!(!target.is_compatible("py310-none-any"));
+        assert!(!target.is_compatible("cp38-cp38-manylinux2014_x86_64"));
+        assert!(!target.is_compatible("cp310-abi3-manylinux2014_x86_64"));
+        assert!(!target.is_compatible("pp39-pypy39_pp73-manylinux2014_x86_64"));
+        assert!(!target.is_compatible("not-a-tag"));
+    }
+
+    #[test]
+    fn test_platform_compatible() {
+        let linux = target("manylinux2014_x86_64");
+        assert!(linux.is_compatible("cp39-cp39-manylinux1_x86_64"));
+        assert!(linux.is_compatible("cp39-cp39-manylinux_2_17_x86_64.manylinux2014_x86_64"));
+        assert!(linux.is_compatible("cp39-cp39-linux_x86_64"));
+        assert!(!linux.is_compatible("cp39-cp39-manylinux_2_28_x86_64"));
+        assert!(!linux.is_compatible("cp39-cp39-manylinux2014_aarch64"));
+        assert!(!linux.is_compatible("cp39-cp39-musllinux_1_1_x86_64"));
+        assert!(!linux.is_compatible("cp39-cp39-win_amd64"));
+
+        let macos = target("macosx_10_9_x86_64");
+        assert!(macos.is_compatible("cp39-cp39-macosx_10_9_universal2"));
+        assert!(macos.is_compatible("cp39-cp39-macosx_10_6_intel"));
+        assert!(!macos.is_compatible("cp39-cp39-macosx_11_0_x86_64"));
+        assert!(!macos.is_compatible("cp39-cp39-macosx_10_9_arm64"));
+
+        let windows = target("win32");
+        assert!(windows.is_compatible("cp39-cp39-win32"));
+        assert!(!windows.is_compatible("cp39-cp39-win_amd64"));
+
+        let pure = target("none");
+        assert!(pure.is_compatible("py3-none-any"));
+        assert!(!pure.is_compatible("cp39-cp39-manylinux2014_x86_64"));
+    }
+}
@@ -9,6 +9,49 @@ use std::{borrow::Cow, collections::HashMap, convert::TryFrom, path::Path};
 /// Header value for version 2 of resources payload.
 pub const HEADER_V3: &[u8] = b"pyembed\x03";
 
+/// Header value for version 4 of resources payload.
+pub const HEADER_V4: &[u8] = b"pyembed\x04";
+
+/// Minimum format version a reader must support to read the version 4
+/// payloads we write.
+pub const MIN_READER_VERSION_V4: u8 = 4;
+
+/// Section flag denoting a section readers may skip if they don't recognize it.
+pub const SECTION_FLAG_OPTIONAL: u8 = 0x01;
+
+/// Metadata key holding the `X.Y` version of Python resources were built for.
+pub const METADATA_PYTHON_VERSION: &str = "python_version";
+
+/// Describes the kind of a section in version 4 payloads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SectionKind {
+    /// Resources data, laid out like version 3 payloads after the header.
+    Resources = 0x01,
+    /// Metadata describing the data, as key-value pairs.
+    Metadata = 0x02,
+}
+
+impl From<SectionKind> for u8 {
+    fn from(source: SectionKind) -> Self {
+        match source {
+            SectionKind::Resources => 0x01,
+            SectionKind::Metadata => 0x02,
+        }
+    }
+}
+
+impl TryFrom<u8> for SectionKind {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(SectionKind::Resources),
+            0x02 => Ok(SectionKind::Metadata),
+            _ => Err("unrecognized section kind"),
+        }
+    }
+}
+
 /// Defines the type of a resource.
 ///
 /// This is deprecated in favor of individual boolean fields on resources
@@ -0,0 +1,320 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Deltas between versions of packed resources data.
+
+A delta describes how to produce new data from old data. It consists of
+instructions copying ranges of the old data and inserting literal bytes.
+Since most resources are unchanged between releases of an application,
+a delta is typically much smaller than the new data, making it suitable
+for shipping updates of packed resources files.
+
+Deltas operate on bytes and don't interpret the data. Applying a delta
+produces output byte-identical to the data the delta was created from.
+The lengths and checksums of both the old and new data are recorded so
+applying a delta to the wrong data or applying a corrupted delta fails
+instead of producing bad output. The checksums are not cryptographic and
+don't protect against tampering.
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt},
+    std::{
+        collections::HashMap,
+        io::{Cursor, Read, Write},
+    },
+};
+
+/// Header identifying version 1 of the delta format.
+pub const DELTA_HEADER_V1: &[u8] = b"pydelta\x01";
+
+/// Size of blocks of the old data which are indexed for matching.
+const BLOCK_SIZE: usize = 32;
+
+/// Base of the polynomial rolling hash of blocks.
+const HASH_BASE: u32 = 257;
+
+const OP_END: u8 = 0x00;
+const OP_COPY: u8 = 0x01;
+const OP_INSERT: u8 = 0x02;
+
+/// Compute the 64-bit FNV-1a hash of data.
+fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn block_hash(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |hash, b| {
+        hash.wrapping_mul(HASH_BASE).wrapping_add(*b as u32)
+    })
+}
+
+fn write_insert<W: Write>(dest: &mut W, data: &[u8]) -> Result<()> {
+    if !data.is_empty() {
+        dest.write_u8(OP_INSERT)?;
+        dest.write_u64::<LittleEndian>(data.len() as u64)?;
+        dest.write_all(data)?;
+    }
+
+    Ok(())
+}
+
+fn write_copy<W: Write>(dest: &mut W, offset: usize, length: usize) -> Result<()> {
+    dest.write_u8(OP_COPY)?;
+    dest.write_u64::<LittleEndian>(offset as u64)?;
+    dest.write_u64::<LittleEndian>(length as u64)?;
+
+    Ok(())
+}
+
+/// Create a delta producing `new` from `old`.
+pub fn create_delta(old: &[u8], new: &[u8]) -> Result<Vec<u8>> {
+    let mut dest = Vec::new();
+
+    dest.write_all(DELTA_HEADER_V1)?;
+    dest.write_u64::<LittleEndian>(old.len() as u64)?;
+    dest.write_u64::<LittleEndian>(checksum(old))?;
+    dest.write_u64::<LittleEndian>(new.len() as u64)?;
+    dest.write_u64::<LittleEndian>(checksum(new))?;
+
+    // Index the hash of every aligned block of the old data. The first
+    // block having a given hash wins.
+    let mut blocks = HashMap::new();
+    for (i, block) in old.chunks_exact(BLOCK_SIZE).enumerate() {
+        blocks.entry(block_hash(block)).or_insert(i * BLOCK_SIZE);
+    }
+
+    // Factor removing the leading byte from a rolling hash.
+    let leading_factor = (1..BLOCK_SIZE).fold(1u32, |f, _| f.wrapping_mul(HASH_BASE));
+
+    // Start of new data not yet emitted.
+    let mut literal_start = 0;
+    let mut i = 0;
+    let mut hash = None;
+
+    while i + BLOCK_SIZE <= new.len() {
+        let current = match hash {
+            Some(hash) => hash,
+            None => block_hash(&new[i..i + BLOCK_SIZE]),
+        };
+
+        let matched = blocks
+            .get(&current)
+            .copied()
+            .filter(|offset| old[*offset..*offset + BLOCK_SIZE] == new[i..i + BLOCK_SIZE]);
+
+        if let Some(mut offset) = matched {
+            // Extend the match backwards into pending literal data and
+            // forwards as far as the data agrees.
+            let mut start = i;
+            while start > literal_start && offset > 0 && old[offset - 1] == new[start - 1] {
+                start -= 1;
+                offset -= 1;
+            }
+
+            let mut end = i + BLOCK_SIZE;
+            while end < new.len()
+                && offset + end - start < old.len()
+                && old[offset + end - start] == new[end]
+            {
+                end += 1;
+            }
+
+            write_insert(&mut dest, &new[literal_start..start])?;
+            write_copy(&mut dest, offset, end - start)?;
+
+            literal_start = end;
+            i = end;
+            hash = None;
+        } else {
+            hash = if i + BLOCK_SIZE < new.len() {
+                Some(
+                    current
+                        .wrapping_sub((new[i] as u32).wrapping_mul(leading_factor))
+                        .wrapping_mul(HASH_BASE)
+                        .wrapping_add(new[i + BLOCK_SIZE] as u32),
+                )
+            } else {
+                None
+            };
+            i += 1;
+        }
+    }
+
+    write_insert(&mut dest, &new[literal_start..])?;
+    dest.write_u8(OP_END)?;
+
+    Ok(dest)
+}
+
+/// Apply a delta to old data, producing new data.
+///
+/// Errors if `old` isn't the data the delta was created from or the delta
+/// is malformed.
+pub fn apply_delta(old: &[u8], delta: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = Cursor::new(delta);
+
+    let mut header = [0; 8];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| anyhow!("error reading 8 byte header"))?;
+    if header != DELTA_HEADER_V1 {
+        return Err(anyhow!("unrecognized delta format"));
+    }
+
+    let mut read_u64 = |what: &str| {
+        reader
+            .read_u64::<LittleEndian>()
+            .map_err(|_| anyhow!("failed reading {}", what))
+    };
+
+    let old_length = read_u64("old data length")?;
+    let old_checksum = read_u64("old data checksum")?;
+    let new_length = read_u64("new data length")?;
+    let new_checksum = read_u64("new data checksum")?;
+
+    if old_length != old.len() as u64 || old_checksum != checksum(old) {
+        return Err(anyhow!("delta was not created from this data"));
+    }
+
+    let mut new = Vec::with_capacity(new_length.min(delta.len() as u64 + old_length) as usize);
+
+    loop {
+        let op = reader
+            .read_u8()
+            .map_err(|_| anyhow!("failed reading delta instruction"))?;
+
+        match op {
+            OP_END => break,
+            OP_COPY => {
+                let offset = reader
+                    .read_u64::<LittleEndian>()
+                    .map_err(|_| anyhow!("failed reading copy offset"))?;
+                let length = reader
+                    .read_u64::<LittleEndian>()
+                    .map_err(|_| anyhow!("failed reading copy length"))?;
+
+                let end = offset
+                    .checked_add(length)
+                    .filter(|end| *end <= old.len() as u64)
+                    .ok_or_else(|| anyhow!("copy extends beyond end of old data"))?;
+
+                new.extend_from_slice(&old[offset as usize..end as usize]);
+            }
+            OP_INSERT => {
+                let length = reader
+                    .read_u64::<LittleEndian>()
+                    .map_err(|_| anyhow!("failed reading insert length"))?;
+
+                let start = reader.position();
+                let end = start
+                    .checked_add(length)
+                    .filter(|end| *end <= delta.len() as u64)
+                    .ok_or_else(|| anyhow!("insert extends beyond end of delta"))?;
+
+                new.extend_from_slice(&delta[start as usize..end as usize]);
+                reader.set_position(end);
+            }
+            _ => return Err(anyhow!("unrecognized delta instruction: {}", op)),
+        }
+
+        if new.len() as u64 > new_length {
+            return Err(anyhow!("delta produces more data than expected"));
+        }
+    }
+
+    if reader.position() != delta.len() as u64 {
+        return Err(anyhow!("unexpected data after end of delta"));
+    }
+
+    if new.len() as u64 != new_length || checksum(&new) != new_checksum {
+        return Err(anyhow!(
+            "delta produced data not matching the expected checksum"
+        ));
+    }
+
+    Ok(new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random data.
+    fn sample_data(seed: u64, length: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..length)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 33) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let old = sample_data(1, 10000);
+
+        let mut new = old.clone();
+        // Replace a range, insert data which shifts offsets, and remove a range.
+        new[1000..1010].copy_from_slice(b"0123456789");
+        new.splice(4000..4000, sample_data(2, 500));
+        new.drain(8000..8200);
+        new.extend_from_slice(b"trailer");
+
+        let delta = create_delta(&old, &new)?;
+        assert!(delta.len() < 1000, "delta is {} bytes", delta.len());
+        assert_eq!(apply_delta(&old, &delta)?, new);
+
+        for (a, b) in &[
+            (vec![], vec![]),
+            (vec![], b"new".to_vec()),
+            (b"old".to_vec(), vec![]),
+            (old.clone(), old.clone()),
+            (sample_data(3, 100), sample_data(4, 50)),
+        ] {
+            assert_eq!(&apply_delta(a, &create_delta(a, b)?)?, b);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_errors() -> Result<()> {
+        let old = sample_data(1, 1000);
+        let mut new = old.clone();
+        new[500] ^= 0xff;
+        let delta = create_delta(&old, &new)?;
+
+        let err = |data: &[u8], delta: &[u8]| apply_delta(data, delta).unwrap_err().to_string();
+
+        assert_eq!(err(&old, b"pydelta"), "error reading 8 byte header");
+        assert_eq!(err(&old, b"pyembed\x04"), "unrecognized delta format");
+        assert_eq!(err(&new, &delta), "delta was not created from this data");
+        assert_eq!(
+            err(&old, &delta[0..delta.len() - 1]),
+            "failed reading delta instruction"
+        );
+
+        let mut trailing = delta.clone();
+        trailing.push(0);
+        assert_eq!(err(&old, &trailing), "unexpected data after end of delta");
+
+        // Header, copy, insert of the modified byte, copy, end.
+        assert_eq!(delta.len(), 40 + 17 + 10 + 17 + 1);
+        let mut corrupted = delta.clone();
+        corrupted[40 + 17 + 9] ^= 0x01;
+        assert_eq!(
+            err(&old, &corrupted),
+            "delta produced data not matching the expected checksum"
+        );
+
+        Ok(())
+    }
+}
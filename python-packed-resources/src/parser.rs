@@ -6,14 +6,16 @@
 
 use {
     super::data::{
-        BlobInteriorPadding, BlobSectionField, Resource, ResourceField, ResourceFlavor, HEADER_V3,
+        BlobInteriorPadding, BlobSectionField, Resource, ResourceField, ResourceFlavor,
+        SectionKind, HEADER_V3, SECTION_FLAG_OPTIONAL,
     },
     byteorder::{LittleEndian, ReadBytesExt},
     std::{
         borrow::Cow,
-        collections::{HashMap, HashSet},
+        collections::{BTreeMap, HashMap, HashSet},
         convert::TryFrom,
         ffi::OsStr,
+        fmt::{Display, Formatter},
         io::{Cursor, Read},
         path::Path,
     },
@@ -41,8 +43,91 @@ struct BlobSectionReadState {
 
 pub type PythonPackageResources<'a> = HashMap<&'a str, &'a [u8]>;
 
+/// Newest format version of packed resources data this parser can read.
+///
+/// Version 3 data can also be read. Data written by a newer version can be read
+/// if it declares a minimum reader version no newer than this one.
+pub const SUPPORTED_FORMAT_VERSION: u8 = 4;
+
+/// Describes why packed resources data could not be loaded.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LoadError {
+    /// The data is too short to hold the 8 byte header.
+    TruncatedHeader,
+
+    /// The data doesn't begin with a packed resources header.
+    UnrecognizedFormat,
+
+    /// The data is packed resources of a format version we can't read.
+    UnsupportedVersion {
+        /// Format version required to read the data.
+        found: u8,
+        /// Format version this parser can read.
+        supported: u8,
+    },
+
+    /// The data is malformed.
+    Malformed {
+        /// Offset from the start of the data where the problem was detected.
+        offset: usize,
+        message: &'static str,
+    },
+
+    /// The data was built for a different Python version than the running one.
+    PythonVersionMismatch {
+        /// Python version recorded in the data's metadata.
+        built: String,
+        /// Python version of the running interpreter.
+        running: String,
+    },
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TruncatedHeader => f.write_str("error reading 8 byte header"),
+            Self::UnrecognizedFormat => f.write_str("unrecognized file format"),
+            Self::UnsupportedVersion { found, supported } => write!(
+                f,
+                "format version {} is not supported (supported version: {})",
+                found, supported
+            ),
+            Self::Malformed { offset, message } => {
+                write!(f, "malformed data at offset {}: {}", offset, message)
+            }
+            Self::PythonVersionMismatch { built, running } => write!(
+                f,
+                "data was built for Python {} but the running Python is {}",
+                built, running
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl LoadError {
+    /// Obtain the short message describing this error.
+    ///
+    /// These are the messages returned by [load_resources()].
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::TruncatedHeader => "error reading 8 byte header",
+            Self::UnrecognizedFormat | Self::UnsupportedVersion { .. } => {
+                "unrecognized file format"
+            }
+            Self::Malformed { message, .. } => message,
+            Self::PythonVersionMismatch { .. } => "data was built for a different Python version",
+        }
+    }
+}
+
 pub struct ResourceParserIterator<'a> {
     done: bool,
+    format_version: u8,
+    optional_features: u32,
+    metadata: BTreeMap<&'a str, &'a str>,
+    base_offset: usize,
     data: &'a [u8],
     reader: Cursor<&'a [u8]>,
     blob_sections: [Option<BlobSectionReadState>; 256],
@@ -56,17 +141,60 @@ impl<'a> ResourceParserIterator<'a> {
         self.claimed_resources_count
     }
 
+    /// The format version recorded in the header of the data.
+    pub fn format_version(&self) -> u8 {
+        self.format_version
+    }
+
+    /// Optional feature flags recorded by the writer.
+    ///
+    /// These denote features readers may ignore. Version 3 data has none.
+    pub fn optional_features(&self) -> u32 {
+        self.optional_features
+    }
+
+    /// Metadata key-value pairs recorded by the writer.
+    ///
+    /// Version 3 data has none.
+    pub fn metadata(&self) -> &BTreeMap<&'a str, &'a str> {
+        &self.metadata
+    }
+
+    /// Offset from the start of the data of the next index entry to read.
+    ///
+    /// After a parse error, this is the offset at which the error was detected.
+    pub fn offset(&self) -> usize {
+        self.base_offset + self.reader.position() as usize
+    }
+
+    /// Describe a parse error as a [LoadError] at the current offset.
+    pub fn error(&self, message: &'static str) -> LoadError {
+        LoadError::Malformed {
+            offset: self.offset(),
+            message,
+        }
+    }
+
     /// Resolve a slice to an individual blob's data.
     ///
     /// This accepts a reference to the original blobs payload, an array of
     /// current blob section offsets, the resource field being accessed, and the
     /// length of the blob and returns a slice to that blob.
-    fn resolve_blob_data(&mut self, resource_field: ResourceField, length: usize) -> &'a [u8] {
+    fn resolve_blob_data(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<&'a [u8], &'static str> {
         let mut state = self.blob_sections[resource_field as usize]
             .as_mut()
-            .expect("blob state not found");
+            .ok_or("no blob section for resource field")?;
 
-        let blob = &self.data[state.offset..state.offset + length];
+        let data = self.data;
+        let blob = state
+            .offset
+            .checked_add(length)
+            .and_then(|end| data.get(state.offset..end))
+            .ok_or("blob data extends beyond end of data")?;
 
         let increment = match &state.interior_padding {
             BlobInteriorPadding::None => length,
@@ -75,25 +203,33 @@ impl<'a> ResourceParserIterator<'a> {
 
         state.offset += increment;
 
-        blob
+        Ok(blob)
     }
 
     #[cfg(unix)]
-    fn resolve_path(&mut self, resource_field: ResourceField, length: usize) -> Cow<'a, Path> {
-        let path_str = OsStr::from_bytes(self.resolve_blob_data(resource_field, length));
-        Cow::Borrowed(Path::new(path_str))
+    fn resolve_path(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<Cow<'a, Path>, &'static str> {
+        let path_str = OsStr::from_bytes(self.resolve_blob_data(resource_field, length)?);
+        Ok(Cow::Borrowed(Path::new(path_str)))
     }
 
     #[cfg(windows)]
-    fn resolve_path(&mut self, resource_field: ResourceField, length: usize) -> Cow<'a, Path> {
-        let raw = self.resolve_blob_data(resource_field, length);
+    fn resolve_path(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<Cow<'a, Path>, &'static str> {
+        let raw = self.resolve_blob_data(resource_field, length)?;
         let raw = unsafe { std::slice::from_raw_parts(raw.as_ptr() as *const u16, raw.len() / 2) };
 
         // There isn't an API that lets us get a OsStr from &[u16]. So we need to use
         // owned types.
         let path_string = OsString::from_wide(raw);
 
-        Cow::Owned(PathBuf::from(path_string))
+        Ok(Cow::Owned(PathBuf::from(path_string)))
     }
 
     fn parse_next(&mut self) -> Result<Option<Resource<'a, u8>>, &'static str> {
@@ -153,7 +289,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     let name = unsafe {
-                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l))
+                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l)?)
                     };
 
                     current_resource_name = Some(name);
@@ -173,7 +309,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_source =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
                 ResourceField::InMemoryBytecode => {
                     let l = self
@@ -183,7 +319,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_bytecode =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
                 ResourceField::InMemoryBytecodeOpt1 => {
                     let l = self
@@ -193,7 +329,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_bytecode_opt1 =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
                 ResourceField::InMemoryBytecodeOpt2 => {
                     let l = self
@@ -203,7 +339,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_bytecode_opt2 =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
                 ResourceField::InMemoryExtensionModuleSharedLibrary => {
                     let l = self
@@ -213,7 +349,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_extension_module_shared_library =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
 
                 ResourceField::InMemoryResourcesData => {
@@ -234,7 +370,7 @@ impl<'a> ResourceParserIterator<'a> {
 
                         let resource_name = unsafe {
                             std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, resource_name_length),
+                                self.resolve_blob_data(field_type, resource_name_length)?,
                             )
                         };
 
@@ -244,7 +380,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading resource length")?
                             as usize;
 
-                        let resource_data = self.resolve_blob_data(field_type, resource_length);
+                        let resource_data = self.resolve_blob_data(field_type, resource_length)?;
 
                         resources
                             .insert(Cow::Borrowed(resource_name), Cow::Borrowed(resource_data));
@@ -271,7 +407,7 @@ impl<'a> ResourceParserIterator<'a> {
 
                         let name = unsafe {
                             std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
+                                self.resolve_blob_data(field_type, name_length)?,
                             )
                         };
 
@@ -280,7 +416,7 @@ impl<'a> ResourceParserIterator<'a> {
                                 "failed reading package distribution resource length"
                             })? as usize;
 
-                        let resource_data = self.resolve_blob_data(field_type, resource_length);
+                        let resource_data = self.resolve_blob_data(field_type, resource_length)?;
 
                         resources.insert(Cow::Borrowed(name), Cow::Borrowed(resource_data));
                     }
@@ -296,7 +432,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_shared_library =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
 
                 ResourceField::SharedLibraryDependencyNames => {
@@ -316,7 +452,7 @@ impl<'a> ResourceParserIterator<'a> {
 
                         let name = unsafe {
                             std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
+                                self.resolve_blob_data(field_type, name_length)?,
                             )
                         };
 
@@ -333,7 +469,7 @@ impl<'a> ResourceParserIterator<'a> {
                         .map_err(|_| "failed reading Python module relative path length")?
                         as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_source = Some(path);
                 }
@@ -344,7 +480,7 @@ impl<'a> ResourceParserIterator<'a> {
                             "failed reading Python module bytecode relative path length"
                         })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode = Some(path);
                 }
@@ -354,7 +490,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading Python module bytecode opt 1 relative path length"
                     })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode_opt1 = Some(path);
                 }
@@ -364,7 +500,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading Python module bytecode opt 2 relative path length"
                     })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode_opt2 = Some(path);
                 }
@@ -374,7 +510,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading Python extension module shared library relative path length"
                     })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_extension_module_shared_library = Some(path);
                 }
@@ -396,7 +532,7 @@ impl<'a> ResourceParserIterator<'a> {
 
                         let resource_name = unsafe {
                             std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, resource_name_length),
+                                self.resolve_blob_data(field_type, resource_name_length)?,
                             )
                         };
 
@@ -406,7 +542,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading resource path length")?
                             as usize;
 
-                        let path = self.resolve_path(field_type, path_length);
+                        let path = self.resolve_path(field_type, path_length)?;
 
                         resources.insert(Cow::Borrowed(resource_name), path);
                     }
@@ -430,7 +566,7 @@ impl<'a> ResourceParserIterator<'a> {
 
                         let name = unsafe {
                             std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
+                                self.resolve_blob_data(field_type, name_length)?,
                             )
                         };
 
@@ -440,7 +576,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading package distribution path length")?
                             as usize;
 
-                        let path = self.resolve_path(field_type, path_length);
+                        let path = self.resolve_path(field_type, path_length)?;
 
                         resources.insert(Cow::Borrowed(name), path);
                     }
@@ -484,7 +620,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.file_data_embedded =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
 
                 ResourceField::FileDataUtf8RelativePath => {
@@ -495,7 +631,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.file_data_utf8_relative_path = Some(Cow::Borrowed(unsafe {
-                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l))
+                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l)?)
                     }));
                 }
             }
@@ -522,21 +658,221 @@ impl<'a> Iterator for ResourceParserIterator<'a> {
 }
 
 pub fn load_resources<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &'static str> {
+    load_resources_detailed(data).map_err(|e| e.message())
+}
+
+/// Obtain the format version recorded in the header of packed resources data.
+///
+/// Errors if the data doesn't begin with a packed resources header.
+pub fn format_version(data: &[u8]) -> Result<u8, LoadError> {
     if data.len() < HEADER_V3.len() {
-        return Err("error reading 8 byte header");
+        return Err(LoadError::TruncatedHeader);
     }
 
-    let header = &data[0..8];
+    // Headers consist of a fixed prefix followed by a version byte.
+    let prefix = &HEADER_V3[0..HEADER_V3.len() - 1];
 
-    if header == HEADER_V3 {
-        load_resources_v3(&data[8..])
+    if data.starts_with(prefix) {
+        Ok(data[prefix.len()])
     } else {
-        Err("unrecognized file format")
+        Err(LoadError::UnrecognizedFormat)
     }
 }
 
-fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &'static str> {
+/// Like [load_resources()] but describing failures with a [LoadError].
+pub fn load_resources_detailed<'a>(
+    data: &'a [u8],
+) -> Result<ResourceParserIterator<'a>, LoadError> {
+    let version = format_version(data)?;
+
+    let sections = match version {
+        3 => SectionsV4 {
+            resources: (HEADER_V3.len(), data.len()),
+            optional_features: 0,
+            metadata: BTreeMap::new(),
+        },
+        // Version 4 and newer share a layout allowing readers to determine
+        // whether they can read the data.
+        v if v >= 4 => {
+            let mut reader = Cursor::new(data);
+            reader.set_position(HEADER_V3.len() as u64);
+
+            let malformed = |reader: &Cursor<&[u8]>, message| LoadError::Malformed {
+                offset: reader.position() as usize,
+                message,
+            };
+
+            let min_reader_version = reader
+                .read_u8()
+                .map_err(|_| malformed(&reader, "failed reading minimum reader version"))?;
+
+            if min_reader_version > SUPPORTED_FORMAT_VERSION {
+                return Err(LoadError::UnsupportedVersion {
+                    found: min_reader_version,
+                    supported: SUPPORTED_FORMAT_VERSION,
+                });
+            }
+
+            read_sections_v4(&mut reader).map_err(|message| malformed(&reader, message))?
+        }
+        v => {
+            return Err(LoadError::UnsupportedVersion {
+                found: v,
+                supported: SUPPORTED_FORMAT_VERSION,
+            })
+        }
+    };
+
+    let (base_offset, end_offset) = sections.resources;
+    let mut reader = Cursor::new(&data[base_offset..end_offset]);
+
+    let mut resources = load_resources_v3(&mut reader).map_err(|message| LoadError::Malformed {
+        offset: base_offset + reader.position() as usize,
+        message,
+    })?;
+
+    resources.format_version = version;
+    resources.optional_features = sections.optional_features;
+    resources.metadata = sections.metadata;
+    resources.base_offset = base_offset;
+
+    Ok(resources)
+}
+
+/// Content of the section table of version 4 data.
+struct SectionsV4<'a> {
+    /// Start and end offsets of the resources section.
+    resources: (usize, usize),
+    optional_features: u32,
+    metadata: BTreeMap<&'a str, &'a str>,
+}
+
+/// Parse the data of a metadata section.
+fn parse_metadata(data: &[u8]) -> Result<BTreeMap<&str, &str>, &'static str> {
     let mut reader = Cursor::new(data);
+    let mut metadata = BTreeMap::new();
+
+    let read_str = |reader: &mut Cursor<&[u8]>, length: usize| {
+        let start = reader.position() as usize;
+        let value = start
+            .checked_add(length)
+            .and_then(|end| data.get(start..end))
+            .ok_or("metadata extends beyond end of section")?;
+        reader.set_position((start + length) as u64);
+
+        std::str::from_utf8(value).map_err(|_| "metadata is not valid UTF-8")
+    };
+
+    let count = reader
+        .read_u32::<LittleEndian>()
+        .map_err(|_| "failed reading metadata count")?;
+
+    for _ in 0..count {
+        let key_length = reader
+            .read_u16::<LittleEndian>()
+            .map_err(|_| "failed reading metadata key length")?;
+        let key = read_str(&mut reader, key_length as usize)?;
+
+        let value_length = reader
+            .read_u32::<LittleEndian>()
+            .map_err(|_| "failed reading metadata value length")?;
+        let value = read_str(&mut reader, value_length as usize)?;
+
+        metadata.insert(key, value);
+    }
+
+    Ok(metadata)
+}
+
+/// Read the section table of version 4 data.
+///
+/// The reader must be positioned after the minimum reader version.
+///
+/// Sections we don't recognize are skipped if they are optional.
+fn read_sections_v4<'a>(reader: &mut Cursor<&'a [u8]>) -> Result<SectionsV4<'a>, &'static str> {
+    let data: &'a [u8] = reader.get_ref();
+    let data_len = data.len();
+
+    // Optional features are informational. Readers ignore flags they don't
+    // recognize.
+    let optional_features = reader
+        .read_u32::<LittleEndian>()
+        .map_err(|_| "failed reading optional features")?;
+
+    let section_count = reader
+        .read_u8()
+        .map_err(|_| "failed reading section count")?;
+
+    let mut resources = None;
+    let mut metadata = None;
+
+    for _ in 0..section_count {
+        let kind = reader
+            .read_u8()
+            .map_err(|_| "failed reading section kind")?;
+        let flags = reader
+            .read_u8()
+            .map_err(|_| "failed reading section flags")?;
+        let length = reader
+            .read_u64::<LittleEndian>()
+            .map_err(|_| "failed reading section length")? as usize;
+
+        let start = reader.position() as usize;
+        let end = start
+            .checked_add(length)
+            .filter(|end| *end <= data_len)
+            .ok_or("section extends beyond end of data")?;
+
+        match SectionKind::try_from(kind) {
+            Ok(SectionKind::Resources) => {
+                if resources.is_some() {
+                    return Err("multiple resources sections");
+                }
+
+                resources = Some((start, end));
+            }
+            Ok(SectionKind::Metadata) => {
+                if metadata.is_some() {
+                    return Err("multiple metadata sections");
+                }
+
+                metadata = Some(parse_metadata(&data[start..end])?);
+            }
+            Err(_) if flags & SECTION_FLAG_OPTIONAL != 0 => {}
+            Err(_) => {
+                return Err("unrecognized required section");
+            }
+        }
+
+        reader.set_position(end as u64);
+    }
+
+    Ok(SectionsV4 {
+        resources: resources.ok_or("resources section not found")?,
+        optional_features,
+        metadata: metadata.unwrap_or_default(),
+    })
+}
+
+/// Validate packed resources data by parsing every resource in it.
+///
+/// Returns the number of resources.
+pub fn verify_resources(data: &[u8]) -> Result<usize, LoadError> {
+    let mut resources = load_resources_detailed(data)?;
+    let mut count = 0;
+
+    while let Some(resource) = resources.next() {
+        resource.map_err(|message| resources.error(message))?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+fn load_resources_v3<'a>(
+    reader: &mut Cursor<&'a [u8]>,
+) -> Result<ResourceParserIterator<'a>, &'static str> {
+    let data = *reader.get_ref();
 
     let blob_section_count = reader
         .read_u8()
@@ -650,8 +986,12 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
 
     Ok(ResourceParserIterator {
         done: resources_index_length == 0 || resources_count == 0,
+        format_version: 3,
+        optional_features: 0,
+        metadata: BTreeMap::new(),
+        base_offset: HEADER_V3.len(),
         data,
-        reader,
+        reader: reader.clone(),
         blob_sections: blob_offsets,
         claimed_resources_count: resources_count,
         read_resources_count: 0,
@@ -663,7 +1003,13 @@ mod tests {
     use {
         super::*,
         crate::data::{BlobInteriorPadding, Resource},
-        crate::writer::write_packed_resources_v3,
+        crate::{
+            data::SectionKind,
+            writer::{
+                encode_metadata, write_packed_resources_v3, write_packed_resources_v4,
+                PackedSection,
+            },
+        },
         std::collections::BTreeMap,
     };
 
@@ -681,9 +1027,13 @@ mod tests {
         let res = load_resources(data);
         assert_eq!(res.err(), Some("unrecognized file format"));
 
-        let data = b"pyembed\x04";
+        let data = b"pyembed\x02";
         let res = load_resources(data);
         assert_eq!(res.err(), Some("unrecognized file format"));
+
+        let data = b"pyembed\x04";
+        let res = load_resources(data);
+        assert_eq!(res.err(), Some("failed reading minimum reader version"));
     }
 
     #[test]
@@ -1556,4 +1906,193 @@ mod tests {
 
         assert_eq!(resources, loaded);
     }
+
+    #[test]
+    fn test_verify_resources() {
+        assert_eq!(verify_resources(b"foo"), Err(LoadError::TruncatedHeader));
+        assert_eq!(
+            verify_resources(b"garbage!"),
+            Err(LoadError::UnrecognizedFormat)
+        );
+        assert_eq!(
+            verify_resources(b"pyembed\x02"),
+            Err(LoadError::UnsupportedVersion {
+                found: 2,
+                supported: 4
+            })
+        );
+        assert_eq!(
+            verify_resources(b"pyembed\x03\x00"),
+            Err(LoadError::Malformed {
+                offset: 9,
+                message: "failed reading blob index length"
+            })
+        );
+
+        let data = b"pyembed\x03\x00\x00\x00\x00\x00\x01\x00\x00\x00\x01\x00\x00\x00\x00";
+        assert_eq!(
+            verify_resources(data),
+            Err(LoadError::Malformed {
+                offset: 22,
+                message: "mismatch between advertised index count and actual"
+            })
+        );
+
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"source".to_vec())),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[resource], &mut data, None).unwrap();
+        assert_eq!(verify_resources(&data), Ok(1));
+
+        // Truncating the data leaves the source blob incomplete.
+        let err = verify_resources(&data[0..data.len() - 1]).unwrap_err();
+        assert!(matches!(
+            err,
+            LoadError::Malformed {
+                message: "blob data extends beyond end of data",
+                ..
+            }
+        ));
+        assert_eq!(
+            load_resources(&data[0..data.len() - 1])
+                .unwrap()
+                .next()
+                .unwrap()
+                .err(),
+            Some(err.message())
+        );
+        assert!(err.to_string().starts_with("malformed data at offset "));
+    }
+
+    #[test]
+    fn test_v4() -> Result<(), &'static str> {
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"source".to_vec())),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v4(
+            std::slice::from_ref(&resource),
+            &mut data,
+            None,
+            0x8000_0001,
+            &[PackedSection {
+                kind: 0x80,
+                optional: true,
+                data: b"ignored",
+            }],
+        )
+        .unwrap();
+
+        let mut resources = load_resources(&data)?;
+        assert_eq!(resources.format_version(), 4);
+        assert_eq!(resources.optional_features(), 0x8000_0001);
+        assert_eq!(resources.next(), Some(Ok(resource.clone())));
+        assert_eq!(resources.next(), None);
+
+        // Data from newer writers can be read if it allows it.
+        let mut newer = data.clone();
+        newer[7] = 5;
+        assert_eq!(load_resources(&newer)?.format_version(), 5);
+        assert_eq!(verify_resources(&newer), Ok(1));
+
+        newer[8] = 5;
+        assert_eq!(
+            verify_resources(&newer),
+            Err(LoadError::UnsupportedVersion {
+                found: 5,
+                supported: 4
+            })
+        );
+
+        // Unrecognized sections which aren't optional can't be skipped.
+        let mut data = Vec::new();
+        write_packed_resources_v4(
+            &[resource],
+            &mut data,
+            None,
+            0,
+            &[PackedSection {
+                kind: 0x80,
+                optional: false,
+                data: b"required",
+            }],
+        )
+        .unwrap();
+
+        let err = verify_resources(&data).unwrap_err();
+        assert_eq!(err.message(), "unrecognized required section");
+        assert!(matches!(err, LoadError::Malformed { .. }));
+
+        // Errors in the section table are reported at their offset.
+        let end = data.len() - b"required".len() - 10;
+        assert_eq!(
+            verify_resources(&data[0..end]),
+            Err(LoadError::Malformed {
+                offset: end,
+                message: "failed reading section kind",
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_v4_metadata() -> Result<(), &'static str> {
+        let resource = Resource {
+            name: Cow::from("foo"),
+            ..Resource::default()
+        };
+
+        let metadata = vec![
+            ("python_version".to_string(), "3.8".to_string()),
+            ("content".to_string(), "stdlib".to_string()),
+        ]
+        .into_iter()
+        .collect::<std::collections::BTreeMap<_, _>>();
+        let metadata_data = encode_metadata(&metadata).unwrap();
+
+        let mut data = Vec::new();
+        write_packed_resources_v4(
+            &[resource],
+            &mut data,
+            None,
+            0,
+            &[PackedSection {
+                kind: SectionKind::Metadata.into(),
+                optional: true,
+                data: &metadata_data,
+            }],
+        )
+        .unwrap();
+
+        let resources = load_resources(&data)?;
+        assert_eq!(resources.metadata().len(), 2);
+        assert_eq!(resources.metadata().get("python_version"), Some(&"3.8"));
+        assert_eq!(resources.metadata().get("content"), Some(&"stdlib"));
+        assert_eq!(verify_resources(&data), Ok(1));
+
+        assert!(load_resources(
+            b"pyembed\x03\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00"
+        )?
+        .metadata()
+        .is_empty());
+
+        assert_eq!(
+            parse_metadata(b"\x01\x00\x00\x00\x03\x00ab"),
+            Err("metadata extends beyond end of section")
+        );
+        assert_eq!(
+            parse_metadata(b"\x01\x00\x00\x00\x01\x00\xff\x00\x00\x00\x00"),
+            Err("metadata is not valid UTF-8")
+        );
+
+        Ok(())
+    }
 }
@@ -6,7 +6,8 @@
 
 use {
     super::data::{
-        BlobInteriorPadding, BlobSectionField, Resource, ResourceField, ResourceFlavor, HEADER_V3,
+        BlobInteriorPadding, BlobSectionField, Resource, ResourceField, ResourceFlavor,
+        SectionKind, HEADER_V3, HEADER_V4, MIN_READER_VERSION_V4, SECTION_FLAG_OPTIONAL,
     },
     anyhow::{anyhow, Context, Result},
     byteorder::{LittleEndian, WriteBytesExt},
@@ -1180,6 +1181,94 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     Ok(())
 }
 
+/// An additional section of version 4 packed resources data.
+#[derive(Clone, Debug)]
+pub struct PackedSection<'a> {
+    /// The kind of section. Must not be [SectionKind::Resources].
+    pub kind: u8,
+    /// Whether readers not recognizing the section may skip it.
+    pub optional: bool,
+    pub data: &'a [u8],
+}
+
+/// Encode key-value pairs as the data of a [SectionKind::Metadata] section.
+pub fn encode_metadata(metadata: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+
+    data.write_u32::<LittleEndian>(metadata.len() as u32)?;
+    for (key, value) in metadata {
+        let key_length =
+            u16::try_from(key.len()).map_err(|_| anyhow!("metadata key {} is too long", key))?;
+
+        data.write_u16::<LittleEndian>(key_length)?;
+        data.write_all(key.as_bytes())?;
+        data.write_u32::<LittleEndian>(value.len() as u32)?;
+        data.write_all(value.as_bytes())?;
+    }
+
+    Ok(data)
+}
+
+fn write_section_v4<W: Write>(dest: &mut W, kind: u8, flags: u8, data: &[u8]) -> Result<()> {
+    dest.write_u8(kind)?;
+    dest.write_u8(flags)?;
+    dest.write_u64::<LittleEndian>(data.len() as u64)?;
+    dest.write_all(data)?;
+
+    Ok(())
+}
+
+/// Write packed resources data, version 4.
+///
+/// Resources are written like version 3 data, in a section following a
+/// header recording the minimum format version readers must support and
+/// `optional_features` flags readers may ignore. `extra_sections` are written
+/// after the resources section.
+pub fn write_packed_resources_v4<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
+    modules: &[T],
+    dest: &mut W,
+    interior_padding: Option<BlobInteriorPadding>,
+    optional_features: u32,
+    extra_sections: &[PackedSection],
+) -> Result<()> {
+    if extra_sections
+        .iter()
+        .any(|s| s.kind == SectionKind::Resources.into())
+    {
+        return Err(anyhow!("extra sections cannot be resources sections"));
+    }
+
+    let section_count =
+        u8::try_from(extra_sections.len() + 1).map_err(|_| anyhow!("too many sections"))?;
+
+    let mut resources = Vec::new();
+    write_packed_resources_v3(modules, &mut resources, interior_padding)?;
+
+    dest.write_all(HEADER_V4)?;
+    dest.write_u8(MIN_READER_VERSION_V4)?;
+    dest.write_u32::<LittleEndian>(optional_features)?;
+    dest.write_u8(section_count)?;
+
+    write_section_v4(
+        dest,
+        SectionKind::Resources.into(),
+        0,
+        &resources[HEADER_V3.len()..],
+    )?;
+
+    for section in extra_sections {
+        let flags = if section.optional {
+            SECTION_FLAG_OPTIONAL
+        } else {
+            0
+        };
+
+        write_section_v4(dest, section.kind, flags, section.data)?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use {super::*, crate::data::ResourceFlavor, std::borrow::Cow};
@@ -1251,4 +1340,59 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_write_v4() -> Result<()> {
+        let mut data = Vec::new();
+        let resources: Vec<Resource<u8>> = Vec::new();
+        write_packed_resources_v4(
+            &resources,
+            &mut data,
+            None,
+            0x02,
+            &[PackedSection {
+                kind: 0x80,
+                optional: true,
+                data: b"extra",
+            }],
+        )?;
+
+        let mut v3 = Vec::new();
+        write_packed_resources_v3(&resources, &mut v3, None)?;
+
+        let mut expected: Vec<u8> = b"pyembed\x04".to_vec();
+        // Minimum reader version.
+        expected.write_u8(4)?;
+        // Optional features.
+        expected.write_u32::<LittleEndian>(0x02)?;
+        // Number of sections.
+        expected.write_u8(2)?;
+        // Resources section.
+        expected.write_u8(SectionKind::Resources.into())?;
+        expected.write_u8(0)?;
+        expected.write_u64::<LittleEndian>((v3.len() - HEADER_V3.len()) as u64)?;
+        expected.write_all(&v3[HEADER_V3.len()..])?;
+        // Extra section.
+        expected.write_u8(0x80)?;
+        expected.write_u8(SECTION_FLAG_OPTIONAL)?;
+        expected.write_u64::<LittleEndian>(5)?;
+        expected.write_all(b"extra")?;
+
+        assert_eq!(data, expected);
+
+        assert!(write_packed_resources_v4(
+            &resources,
+            &mut Vec::new(),
+            None,
+            0,
+            &[PackedSection {
+                kind: SectionKind::Resources.into(),
+                optional: true,
+                data: b"",
+            }],
+        )
+        .is_err());
+
+        Ok(())
+    }
 }
@@ -21,6 +21,7 @@ PythonDistributionRecord {{
     }},
     target_triple: "{target_triple}".to_string(),
     supports_prebuilt_extension_modules: {supports_prebuilt_extension_modules},
+    pgo: {pgo},
 }},
 """.strip()
 
@@ -82,6 +83,8 @@ def main():
         supports_prebuilt_extension_modules = target_triple != "x86_64-unknown-linux-musl" and \
             flavor != "static-noopt"
 
+        pgo = "pgo" in flavor.split("-")[-1].split("+")
+
         key = "%s-%s-%s" % (major_minor, target_triple, flavor)
 
         records[key] = {
@@ -91,12 +94,17 @@ def main():
             "major_minor": major_minor,
             "target_triple": target_triple,
             "supports_prebuilt_extension_modules": "true" if supports_prebuilt_extension_modules else "false",
+            "pgo": "true" if pgo else "false",
         }
 
+    def optimized(key):
+        """Resolve the record for a PGO build, preferring PGO+LTO builds."""
+        return records.get("%s+lto" % key, records[key])
+
     lines = [
         "// Linux glibc linked.",
-        ENTRY.format(**records["3.8-x86_64-unknown-linux-gnu-pgo"]),
-        ENTRY.format(**records["3.9-x86_64-unknown-linux-gnu-pgo"]),
+        ENTRY.format(**optimized("3.8-x86_64-unknown-linux-gnu-pgo")),
+        ENTRY.format(**optimized("3.9-x86_64-unknown-linux-gnu-pgo")),
         "",
         "// Linux musl.",
         ENTRY.format(**records["3.8-x86_64-unknown-linux-musl-noopt"]),
@@ -110,10 +118,10 @@ def main():
         "// with.",
         "",
         "// Windows shared.",
-        ENTRY.format(**records["3.8-i686-pc-windows-msvc-shared-pgo"]),
-        ENTRY.format(**records["3.9-i686-pc-windows-msvc-shared-pgo"]),
-        ENTRY.format(**records["3.8-x86_64-pc-windows-msvc-shared-pgo"]),
-        ENTRY.format(**records["3.9-x86_64-pc-windows-msvc-shared-pgo"]),
+        ENTRY.format(**optimized("3.8-i686-pc-windows-msvc-shared-pgo")),
+        ENTRY.format(**optimized("3.9-i686-pc-windows-msvc-shared-pgo")),
+        ENTRY.format(**optimized("3.8-x86_64-pc-windows-msvc-shared-pgo")),
+        ENTRY.format(**optimized("3.9-x86_64-pc-windows-msvc-shared-pgo")),
         "",
         "// Windows static.",
         ENTRY.format(**records["3.8-i686-pc-windows-msvc-static-noopt"]),
@@ -122,9 +130,9 @@ def main():
         ENTRY.format(**records["3.9-x86_64-pc-windows-msvc-static-noopt"]),
         "",
         "// macOS.",
-        ENTRY.format(**records["3.9-aarch64-apple-darwin-pgo"]),
-        ENTRY.format(**records["3.8-x86_64-apple-darwin-pgo"]),
-        ENTRY.format(**records["3.9-x86_64-apple-darwin-pgo"]),
+        ENTRY.format(**optimized("3.9-aarch64-apple-darwin-pgo")),
+        ENTRY.format(**optimized("3.8-x86_64-apple-darwin-pgo")),
+        ENTRY.format(**optimized("3.9-x86_64-apple-darwin-pgo")),
     ]
 
     print("\n".join(lines))
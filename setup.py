@@ -54,7 +54,12 @@ class RustExtension(distutils.extension.Extension):
         else:
             rust_lib_filename = "lib%s.so" % self.name
 
-        rust_lib = build_dir / "release" / rust_lib_filename
+        # Cargo puts artifacts in a per-target directory when cross-compiling.
+        target = os.environ.get("CARGO_BUILD_TARGET")
+        if target:
+            rust_lib = build_dir / target / "release" / rust_lib_filename
+        else:
+            rust_lib = build_dir / "release" / rust_lib_filename
 
         dest_path.parent.mkdir(parents=True, exist_ok=True)
         shutil.copy2(rust_lib, dest_path)
@@ -25,6 +25,7 @@ use {
     std::{
         borrow::Cow,
         collections::{BTreeMap, HashMap},
+        convert::TryFrom,
         os::raw::c_ulong,
         path::{Path, PathBuf},
     },
@@ -105,6 +106,117 @@ pub struct Target {
     pub built_target: Option<ResolvedTarget>,
 }
 
+/// When a build hook runs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BuildHookStage {
+    /// Before a target is built.
+    PreBuild,
+    /// After a target is built.
+    PostBuild,
+}
+
+impl ToString for BuildHookStage {
+    fn to_string(&self) -> String {
+        match self {
+            Self::PreBuild => "pre-build",
+            Self::PostBuild => "post-build",
+        }
+        .to_string()
+    }
+}
+
+impl TryFrom<&str> for BuildHookStage {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "pre-build" => Ok(Self::PreBuild),
+            "post-build" => Ok(Self::PostBuild),
+            _ => Err(format!(
+                "{} is not a valid build hook stage; expected pre-build or post-build",
+                value
+            )),
+        }
+    }
+}
+
+/// An external command to run when building targets.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildHook {
+    /// When the command runs.
+    pub stage: BuildHookStage,
+
+    /// Program and arguments to run.
+    pub command: Vec<String>,
+
+    /// Extra environment variables to define.
+    pub env: BTreeMap<String, String>,
+
+    /// Name of the target this hook is limited to.
+    ///
+    /// If not defined, the hook runs for every target.
+    pub target: Option<String>,
+}
+
+impl BuildHook {
+    /// Run the command of this hook for a target.
+    ///
+    /// The command runs from `cwd`. `BUILD_STAGE`, `BUILD_TARGET`,
+    /// `BUILD_PATH`, and `BUILD_OUTPUT_PATH` environment variables describe
+    /// the build. Fails if the command exits with an error.
+    pub fn run(
+        &self,
+        logger: &slog::Logger,
+        cwd: &Path,
+        target: &str,
+        build_path: &Path,
+        output_path: &Path,
+    ) -> Result<()> {
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or_else(|| anyhow!("build hook has an empty command"))?;
+
+        warn!(
+            logger,
+            "running {} hook for {}: {}",
+            self.stage.to_string(),
+            target,
+            self.command.join(" ")
+        );
+
+        let output = std::process::Command::new(program)
+            .args(args)
+            .current_dir(cwd)
+            .envs(&self.env)
+            .env("BUILD_STAGE", self.stage.to_string())
+            .env("BUILD_TARGET", target)
+            .env("BUILD_PATH", build_path)
+            .env("BUILD_OUTPUT_PATH", output_path)
+            .output()
+            .map_err(|e| anyhow!("error running {}: {}", program, e))?;
+
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            warn!(logger, "{}", line);
+        }
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            warn!(logger, "{}", line);
+        }
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "{} hook for {} failed ({}): {}",
+                self.stage.to_string(),
+                target,
+                output.status,
+                self.command.join(" ")
+            ))
+        }
+    }
+}
+
 /// Holds execution context for a Starlark environment.
 #[derive(Debug)]
 pub struct EnvironmentContext {
@@ -119,6 +231,9 @@ pub struct EnvironmentContext {
     /// Optional path prefix to insert between the build path and the target name.
     target_build_path_prefix: Option<PathBuf>,
 
+    /// Explicit directory to hold cached state, such as downloads.
+    cache_path: Option<PathBuf>,
+
     /// Registered targets.
     ///
     /// A target is a name and a Starlark callable.
@@ -133,10 +248,16 @@ pub struct EnvironmentContext {
     /// List of targets to resolve.
     resolve_targets: Option<Vec<String>>,
 
+    /// Targets whose functions are being called, innermost last.
+    resolving_targets: Vec<String>,
+
     // TODO figure out a generic way to express build script mode.
     /// Name of default target to resolve in build script mode.
     pub default_build_script_target: Option<String>,
 
+    /// Registered build hooks, in registration order.
+    build_hooks: Vec<BuildHook>,
+
     /// Whether we are operating in Rust build script mode.
     ///
     /// This will change the default target to resolve.
@@ -152,11 +273,14 @@ impl EnvironmentContext {
             cwd,
             build_path,
             target_build_path_prefix: None,
+            cache_path: None,
             targets: BTreeMap::new(),
             targets_order: vec![],
             default_target: None,
             resolve_targets: None,
+            resolving_targets: vec![],
             default_build_script_target: None,
+            build_hooks: vec![],
             build_script_mode: false,
         }
     }
@@ -208,6 +332,20 @@ impl EnvironmentContext {
         }
     }
 
+    /// Directory to use for cached state, such as downloads.
+    ///
+    /// Defaults to the `cache` directory under the build path.
+    pub fn cache_path(&self) -> PathBuf {
+        self.cache_path
+            .clone()
+            .unwrap_or_else(|| self.build_path.join("cache"))
+    }
+
+    /// Update the directory to use for cached state.
+    pub fn set_cache_path<P: AsRef<Path>>(&mut self, path: Option<P>) {
+        self.cache_path = path.map(|p| p.as_ref().to_path_buf());
+    }
+
     /// Obtain all registered targets.
     pub fn targets(&self) -> &BTreeMap<String, Target> {
         &self.targets
@@ -233,6 +371,14 @@ impl EnvironmentContext {
         self.resolve_targets = Some(targets);
     }
 
+    /// Obtain the target whose function is being called.
+    ///
+    /// If the function of a target resolves another target, this is the
+    /// innermost target.
+    pub fn resolving_target(&self) -> Option<&str> {
+        self.resolving_targets.last().map(|t| t.as_str())
+    }
+
     /// Obtain the order that targets were registered in.
     pub fn targets_order(&self) -> &Vec<String> {
         &self.targets_order
@@ -270,6 +416,22 @@ impl EnvironmentContext {
         }
     }
 
+    /// Register a build hook.
+    pub fn register_build_hook(&mut self, hook: BuildHook) {
+        self.build_hooks.push(hook);
+    }
+
+    /// Obtain build hooks that run at a stage when building a target.
+    pub fn build_hooks(&self, stage: BuildHookStage, target: &str) -> Vec<BuildHook> {
+        self.build_hooks
+            .iter()
+            .filter(|hook| {
+                hook.stage == stage && hook.target.as_ref().map_or(true, |t| t == target)
+            })
+            .cloned()
+            .collect()
+    }
+
     /// Determine what targets should be resolved.
     ///
     /// This isn't the full list of targets that will be resolved, only the main
@@ -788,6 +950,67 @@ fn starlark_register_target(
     Ok(Value::new(NoneType::None))
 }
 
+/// register_build_hook(stage, command, env=None, target=None)
+fn starlark_register_build_hook(
+    type_values: &TypeValues,
+    stage: String,
+    command: Value,
+    env: Value,
+    target: Value,
+) -> ValueResult {
+    required_list_arg("command", "string", &command)?;
+    optional_dict_arg("env", "string", "string", &env)?;
+    let target = optional_str_arg("target", &target)?;
+
+    let stage = BuildHookStage::try_from(stage.as_str()).map_err(|e| {
+        ValueError::from(RuntimeError {
+            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+            message: e,
+            label: "register_build_hook()".to_string(),
+        })
+    })?;
+
+    let command = command
+        .iter()?
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+
+    if command.is_empty() {
+        return Err(ValueError::from(RuntimeError {
+            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+            message: "command cannot be empty".to_string(),
+            label: "register_build_hook()".to_string(),
+        }));
+    }
+
+    let env = match env.get_type() {
+        "dict" => {
+            let mut res = BTreeMap::new();
+            for key in &env.iter()? {
+                let value = env.at(key.clone())?;
+                res.insert(key.to_string(), value.to_string());
+            }
+            res
+        }
+        _ => BTreeMap::new(),
+    };
+
+    let raw_context = get_context_value(type_values)?;
+    let mut context = raw_context
+        .downcast_mut::<EnvironmentContext>()?
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    context.register_build_hook(BuildHook {
+        stage,
+        command,
+        env,
+        target,
+    });
+
+    Ok(Value::new(NoneType::None))
+}
+
 /// resolve_target(target)
 ///
 /// This will return a Value returned from the called function.
@@ -847,6 +1070,14 @@ fn starlark_resolve_target(
         )?);
     }
 
+    {
+        let raw_context = get_context_value(type_values)?;
+        let mut context = raw_context
+            .downcast_mut::<EnvironmentContext>()?
+            .ok_or(ValueError::IncorrectParameterType)?;
+        context.resolving_targets.push(target.clone());
+    }
+
     let res = target_entry.callable.call(
         call_stack,
         type_values,
@@ -854,7 +1085,7 @@ fn starlark_resolve_target(
         LinkedHashMap::new(),
         None,
         None,
-    )?;
+    );
 
     // TODO consider replacing the target's callable with a new function that returns the
     // resolved value. This will ensure a target function is only ever called once.
@@ -866,6 +1097,9 @@ fn starlark_resolve_target(
         .downcast_mut::<EnvironmentContext>()?
         .ok_or(ValueError::IncorrectParameterType)?;
 
+    context.resolving_targets.pop();
+    let res = res?;
+
     if let Some(target_entry) = context.get_target_mut(&target) {
         target_entry.resolved_value = Some(res.clone());
     }
@@ -937,6 +1171,16 @@ starlark_module! { build_targets_module =>
         starlark_print(&env, &args)
     }
 
+    register_build_hook(
+        env type_values,
+        stage: String,
+        command,
+        env = NoneType::None,
+        target = NoneType::None
+    ) {
+        starlark_register_build_hook(type_values, stage, command, env, target)
+    }
+
     register_target(
         env env,
         target: String,
@@ -987,6 +1231,7 @@ pub fn populate_environment(
     // Rust code with only access to the TypeValues dictionary to retrieve
     // these symbols.
     for f in &[
+        "register_build_hook",
         "register_target",
         "resolve_target",
         "resolve_targets",
@@ -1034,6 +1279,8 @@ pub fn build_target(
         .get_type_value(&resolved_value, "build")
         .ok_or_else(|| anyhow!("{} does not implement build()", resolved_value.get_type()))?;
 
+    run_build_hooks(type_values, BuildHookStage::PreBuild, target, None)?;
+
     let resolved_target_value = build
         .call(
             call_stack,
@@ -1058,9 +1305,52 @@ pub fn build_target(
 
     context.get_target_mut(target).unwrap().built_target = Some(resolved_target.inner.clone());
 
+    // Release the mutable borrow of the context before hooks access it.
+    drop(context);
+
+    run_build_hooks(
+        type_values,
+        BuildHookStage::PostBuild,
+        target,
+        Some(&resolved_target.inner.output_path),
+    )?;
+
     Ok(resolved_target.inner.clone())
 }
 
+/// Run build hooks registered for a stage of building a target.
+///
+/// `output_path` defaults to the build path of the target.
+fn run_build_hooks(
+    type_values: &TypeValues,
+    stage: BuildHookStage,
+    target: &str,
+    output_path: Option<&Path>,
+) -> Result<()> {
+    let context_value = get_context_value(type_values)
+        .map_err(|e| anyhow!("unable to resolve context: {:?}", e))?;
+    let context = context_value
+        .downcast_ref::<EnvironmentContext>()
+        .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+    let output_path = match output_path {
+        Some(p) => p.to_path_buf(),
+        None => context.target_build_path(target),
+    };
+
+    for hook in context.build_hooks(stage, target) {
+        hook.run(
+            context.logger(),
+            context.cwd(),
+            target,
+            context.build_path(),
+            &output_path,
+        )?;
+    }
+
+    Ok(())
+}
+
 /// Runs a named target.
 ///
 /// Runs the default target is a target name is not specified.
@@ -1140,6 +1430,51 @@ mod test {
             &vec!["foo".to_string()],
         );
 
+        Ok(())
+    }
+    #[test]
+    fn test_register_build_hook() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("register_build_hook('pre-build', ['true'])")?;
+        env.eval(
+            "register_build_hook('post-build', ['sign', 'app'], env={'KEY': 'value'}, target='exe')",
+        )?;
+
+        assert!(env
+            .eval("register_build_hook('during-build', ['true'])")
+            .is_err());
+        assert!(env.eval("register_build_hook('pre-build', [])").is_err());
+
+        let context_value = get_context_value(&env.type_values).unwrap();
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)
+            .unwrap();
+
+        assert_eq!(
+            context.build_hooks(BuildHookStage::PreBuild, "exe"),
+            vec![BuildHook {
+                stage: BuildHookStage::PreBuild,
+                command: vec!["true".to_string()],
+                env: BTreeMap::new(),
+                target: None,
+            }]
+        );
+        assert_eq!(
+            context.build_hooks(BuildHookStage::PostBuild, "exe").len(),
+            1
+        );
+        assert_eq!(
+            context.build_hooks(BuildHookStage::PostBuild, "exe")[0].env,
+            [("KEY".to_string(), "value".to_string())]
+                .iter()
+                .cloned()
+                .collect::<BTreeMap<_, _>>()
+        );
+        assert!(context
+            .build_hooks(BuildHookStage::PostBuild, "install")
+            .is_empty());
+
         Ok(())
     }
 }
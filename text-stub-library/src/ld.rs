@@ -0,0 +1,360 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Linker directives encoded as `$ld$` symbols.
+
+Libraries export specially named symbols to change how the linker treats
+them depending on the deployment target being linked for:
+
+* `$ld$install_name$os<version>$<path>` uses `<path>` as the install name of
+  the library when linking for deployment target `<version>`.
+* `$ld$previous$<path>$<compatibility_version>$<platform>$<start>$<end>$<symbol>$`
+  uses `<path>` as the install name of `<symbol>` when linking for a
+  deployment target in `[<start>, <end>)` on Mach-O platform `<platform>`. If
+  `<symbol>` is empty, this applies to the entire library.
+* `$ld$hide$os<version>$<symbol>` hides `<symbol>` when linking for
+  deployment target `<version>`.
+
+See `ld64`'s `macho_dylib_file.cpp` for the reference implementation.
+*/
+
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+/// An operating system version. e.g. `10.15` or `11.0.1`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct OsVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl OsVersion {
+    pub fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl Display for OsVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.patch == 0 {
+            write!(f, "{}.{}", self.major, self.minor)
+        } else {
+            write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+        }
+    }
+}
+
+impl FromStr for OsVersion {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, '.');
+
+        let major = parts.next().unwrap_or_default().parse()?;
+        let minor = parts.next().map(u32::from_str).transpose()?.unwrap_or(0);
+        let patch = parts.next().map(u32::from_str).transpose()?.unwrap_or(0);
+
+        Ok(Self::new(major, minor, patch))
+    }
+}
+
+/// A linker directive parsed from a `$ld$` symbol.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LdSymbol {
+    /// `$ld$install_name$os<version>$<path>`.
+    InstallName {
+        os_version: OsVersion,
+        install_name: String,
+    },
+
+    /// `$ld$previous$<path>$<compatibility_version>$<platform>$<start>$<end>$<symbol>$`.
+    Previous {
+        install_name: String,
+        compatibility_version: Option<String>,
+        platform: u32,
+        start_version: OsVersion,
+        end_version: OsVersion,
+        symbol: Option<String>,
+    },
+
+    /// `$ld$hide$os<version>$<symbol>`.
+    Hide {
+        os_version: OsVersion,
+        symbol: String,
+    },
+}
+
+/// Parse the `os<version>$<value>` form of `$ld$` symbols.
+fn parse_os_version_value(s: &str) -> Option<(OsVersion, String)> {
+    let mut parts = s.splitn(2, '$');
+
+    let version = parts.next()?.strip_prefix("os")?.parse().ok()?;
+    let value = parts.next()?;
+
+    if value.is_empty() {
+        None
+    } else {
+        Some((version, value.to_string()))
+    }
+}
+
+impl LdSymbol {
+    /// Parse a symbol name into a linker directive.
+    ///
+    /// Returns `None` if the symbol isn't a well-formed `$ld$install_name`,
+    /// `$ld$previous`, or `$ld$hide` symbol. The linker ignores these.
+    pub fn parse(symbol: &str) -> Option<Self> {
+        if let Some(rest) = symbol.strip_prefix("$ld$install_name$") {
+            let (os_version, install_name) = parse_os_version_value(rest)?;
+
+            Some(Self::InstallName {
+                os_version,
+                install_name,
+            })
+        } else if let Some(rest) = symbol.strip_prefix("$ld$hide$") {
+            let (os_version, symbol) = parse_os_version_value(rest)?;
+
+            Some(Self::Hide { os_version, symbol })
+        } else if let Some(rest) = symbol.strip_prefix("$ld$previous$") {
+            let parts = rest.trim_end_matches('$').split('$').collect::<Vec<_>>();
+
+            if parts.len() < 5 || parts.len() > 6 || parts[0].is_empty() {
+                return None;
+            }
+
+            let optional = |s: &str| {
+                if s.is_empty() {
+                    None
+                } else {
+                    Some(s.to_string())
+                }
+            };
+
+            Some(Self::Previous {
+                install_name: parts[0].to_string(),
+                compatibility_version: optional(parts[1]),
+                platform: parts[2].parse().ok()?,
+                start_version: parts[3].parse().ok()?,
+                end_version: parts[4].parse().ok()?,
+                symbol: parts.get(5).and_then(|s| optional(s)),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// The linker directives exported by a library.
+#[derive(Clone, Debug, Default)]
+pub struct LdSymbols {
+    symbols: Vec<LdSymbol>,
+}
+
+impl LdSymbols {
+    /// Collect linker directives from exported symbol names.
+    ///
+    /// Names that aren't linker directives are ignored.
+    pub fn from_symbols<'a>(symbols: impl IntoIterator<Item = &'a str>) -> Self {
+        Self {
+            symbols: symbols.into_iter().filter_map(LdSymbol::parse).collect(),
+        }
+    }
+
+    /// Iterate over parsed linker directives.
+    pub fn iter(&self) -> impl Iterator<Item = &LdSymbol> {
+        self.symbols.iter()
+    }
+
+    /// Find a `$ld$previous` directive applying to a deployment target.
+    fn previous(
+        &self,
+        platform: u32,
+        deployment_target: &OsVersion,
+        wanted_symbol: Option<&str>,
+    ) -> Option<&str> {
+        self.symbols.iter().find_map(|s| match s {
+            LdSymbol::Previous {
+                install_name,
+                platform: p,
+                start_version,
+                end_version,
+                symbol,
+                ..
+            } if *p == platform
+                && start_version <= deployment_target
+                && deployment_target < end_version
+                && symbol.as_deref() == wanted_symbol =>
+            {
+                Some(install_name.as_str())
+            }
+            _ => None,
+        })
+    }
+
+    /// Compute the install name of the library when linking for a deployment target.
+    ///
+    /// `platform` is the Mach-O platform number being linked for. e.g. `1`
+    /// for macOS.
+    pub fn effective_install_name<'a>(
+        &'a self,
+        install_name: &'a str,
+        platform: u32,
+        deployment_target: &OsVersion,
+    ) -> &'a str {
+        if let Some(name) = self.symbols.iter().find_map(|s| match s {
+            LdSymbol::InstallName {
+                os_version,
+                install_name,
+            } if os_version == deployment_target => Some(install_name.as_str()),
+            _ => None,
+        }) {
+            return name;
+        }
+
+        self.previous(platform, deployment_target, None)
+            .unwrap_or(install_name)
+    }
+
+    /// Compute the install name a symbol is linked against for a deployment target.
+    ///
+    /// This is the library's effective install name unless a `$ld$previous`
+    /// directive moves the symbol to another library.
+    pub fn symbol_install_name<'a>(
+        &'a self,
+        symbol: &str,
+        install_name: &'a str,
+        platform: u32,
+        deployment_target: &OsVersion,
+    ) -> &'a str {
+        self.previous(platform, deployment_target, Some(symbol))
+            .unwrap_or_else(|| {
+                self.effective_install_name(install_name, platform, deployment_target)
+            })
+    }
+
+    /// Whether a symbol is hidden when linking for a deployment target.
+    pub fn is_hidden(&self, symbol: &str, deployment_target: &OsVersion) -> bool {
+        self.symbols.iter().any(|s| match s {
+            LdSymbol::Hide {
+                os_version,
+                symbol: hidden,
+            } => os_version == deployment_target && hidden == symbol,
+            _ => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_os_version() {
+        assert_eq!("10.4".parse(), Ok(OsVersion::new(10, 4, 0)));
+        assert_eq!("11".parse(), Ok(OsVersion::new(11, 0, 0)));
+        assert_eq!("10.15.1".parse(), Ok(OsVersion::new(10, 15, 1)));
+        assert!("10.x".parse::<OsVersion>().is_err());
+        assert!(OsVersion::new(10, 15, 0) < OsVersion::new(11, 0, 0));
+        assert_eq!(OsVersion::new(10, 15, 0).to_string(), "10.15");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            LdSymbol::parse("$ld$install_name$os10.4$/usr/lib/libold.dylib"),
+            Some(LdSymbol::InstallName {
+                os_version: OsVersion::new(10, 4, 0),
+                install_name: "/usr/lib/libold.dylib".to_string(),
+            })
+        );
+        assert_eq!(
+            LdSymbol::parse("$ld$hide$os10.5$_foo"),
+            Some(LdSymbol::Hide {
+                os_version: OsVersion::new(10, 5, 0),
+                symbol: "_foo".to_string(),
+            })
+        );
+        assert_eq!(
+            LdSymbol::parse("$ld$previous$/usr/lib/libold.dylib$$1$10.4$10.6$$"),
+            Some(LdSymbol::Previous {
+                install_name: "/usr/lib/libold.dylib".to_string(),
+                compatibility_version: None,
+                platform: 1,
+                start_version: OsVersion::new(10, 4, 0),
+                end_version: OsVersion::new(10, 6, 0),
+                symbol: None,
+            })
+        );
+        assert_eq!(
+            LdSymbol::parse("$ld$previous$/usr/lib/libold.dylib$1.0.0$2$8.0$9.0$_bar$"),
+            Some(LdSymbol::Previous {
+                install_name: "/usr/lib/libold.dylib".to_string(),
+                compatibility_version: Some("1.0.0".to_string()),
+                platform: 2,
+                start_version: OsVersion::new(8, 0, 0),
+                end_version: OsVersion::new(9, 0, 0),
+                symbol: Some("_bar".to_string()),
+            })
+        );
+
+        assert_eq!(LdSymbol::parse("_foo"), None);
+        assert_eq!(LdSymbol::parse("$ld$hide$10.5$_foo"), None);
+        assert_eq!(LdSymbol::parse("$ld$install_name$os10.4$"), None);
+        assert_eq!(
+            LdSymbol::parse("$ld$previous$/usr/lib/libold.dylib$$1$10.4$"),
+            None
+        );
+        assert_eq!(LdSymbol::parse("$ld$add$os10.4$_foo"), None);
+    }
+
+    #[test]
+    fn test_effective() {
+        let symbols = LdSymbols::from_symbols(vec![
+            "_foo",
+            "$ld$install_name$os10.4$/usr/lib/libold4.dylib",
+            "$ld$previous$/usr/lib/libold.dylib$$1$10.5$10.7$$",
+            "$ld$previous$/usr/lib/libbar.dylib$$1$10.5$10.9$_bar$",
+            "$ld$hide$os10.6$_foo",
+        ]);
+        assert_eq!(symbols.iter().count(), 4);
+
+        let name = "/usr/lib/libnew.dylib";
+        let v = |s: &str| s.parse::<OsVersion>().unwrap();
+
+        assert_eq!(
+            symbols.effective_install_name(name, 1, &v("10.4")),
+            "/usr/lib/libold4.dylib"
+        );
+        assert_eq!(
+            symbols.effective_install_name(name, 1, &v("10.6")),
+            "/usr/lib/libold.dylib"
+        );
+        assert_eq!(symbols.effective_install_name(name, 1, &v("10.7")), name);
+        assert_eq!(symbols.effective_install_name(name, 2, &v("10.6")), name);
+
+        assert_eq!(
+            symbols.symbol_install_name("_bar", name, 1, &v("10.8")),
+            "/usr/lib/libbar.dylib"
+        );
+        assert_eq!(
+            symbols.symbol_install_name("_foo", name, 1, &v("10.6")),
+            "/usr/lib/libold.dylib"
+        );
+        assert_eq!(
+            symbols.symbol_install_name("_bar", name, 1, &v("10.9")),
+            name
+        );
+
+        assert!(symbols.is_hidden("_foo", &v("10.6")));
+        assert!(!symbols.is_hidden("_foo", &v("10.7")));
+        assert!(!symbols.is_hidden("_bar", &v("10.6")));
+    }
+}
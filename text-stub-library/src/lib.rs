@@ -2,10 +2,12 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+pub mod ld;
+pub mod target;
 pub mod yaml;
 
-use yaml::*;
 use yaml_rust::ScanError;
+use {ld::*, target::*, yaml::*};
 
 /// Version of a TBD document.
 #[derive(Copy, Clone, Debug)]
@@ -26,10 +28,89 @@ pub enum TbdVersionedRecord {
     V4(TbdVersion4),
 }
 
+impl TbdVersionedRecord {
+    /// The path of the installed library.
+    pub fn install_name(&self) -> &str {
+        match self {
+            Self::V1(v) => &v.install_name,
+            Self::V2(v) => &v.install_name,
+            Self::V3(v) => &v.install_name,
+            Self::V4(v) => &v.install_name,
+        }
+    }
+
+    /// The architectures this record applies to.
+    pub fn architectures(&self) -> ArchitectureSet {
+        match self {
+            Self::V1(v) => v.archs,
+            Self::V2(v) => v.archs,
+            Self::V3(v) => v.archs,
+            Self::V4(v) => target_architectures(&v.targets),
+        }
+    }
+
+    /// Obtain names of symbols exported for an architecture.
+    ///
+    /// This includes regular, weak, and thread local symbols. Objective-C
+    /// classes and instance variables are not included.
+    pub fn exported_symbols(&self, arch: Architecture) -> Vec<&str> {
+        let mut res = vec![];
+
+        match self {
+            Self::V1(TbdVersion1 { exports, .. }) | Self::V2(TbdVersion2 { exports, .. }) => {
+                for section in exports.iter().filter(|s| s.archs.contains(arch)) {
+                    res.extend(section.symbols.iter().map(|s| s.as_str()));
+                    res.extend(section.weak_def_symbols.iter().map(|s| s.as_str()));
+                    res.extend(section.thread_local_symbols.iter().map(|s| s.as_str()));
+                }
+            }
+            Self::V3(v) => {
+                for section in v.exports.iter().filter(|s| s.archs.contains(arch)) {
+                    res.extend(section.symbols.iter().map(|s| s.as_str()));
+                    res.extend(section.weak_def_symbols.iter().map(|s| s.as_str()));
+                    res.extend(section.thread_local_symbols.iter().map(|s| s.as_str()));
+                }
+            }
+            Self::V4(v) => {
+                for section in v
+                    .exports
+                    .iter()
+                    .filter(|s| target_architectures(&s.targets).contains(arch))
+                {
+                    res.extend(section.symbols.iter().map(|s| s.as_str()));
+                    res.extend(section.weak_symbols.iter().map(|s| s.as_str()));
+                    res.extend(section.thread_local_symbols.iter().map(|s| s.as_str()));
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Obtain the linker directives exported for an architecture.
+    pub fn ld_symbols(&self, arch: Architecture) -> LdSymbols {
+        LdSymbols::from_symbols(self.exported_symbols(arch))
+    }
+
+    /// Compute the install name of the library when linking for a target and deployment target.
+    ///
+    /// This honors `$ld$install_name` and `$ld$previous` symbols.
+    pub fn effective_install_name(&self, target: &Target, deployment_target: &OsVersion) -> String {
+        self.ld_symbols(target.arch)
+            .effective_install_name(
+                self.install_name(),
+                target.macho_platform(),
+                deployment_target,
+            )
+            .to_string()
+    }
+}
+
 /// Represents an error when parsing TBD YAML.
 #[derive(Debug)]
 pub enum ParseError {
     YamlError(yaml_rust::ScanError),
+    YamlEmit(yaml_rust::EmitError),
     DocumentCountMismatch,
     Serde(serde_yaml::Error),
 }
@@ -38,6 +119,7 @@ impl std::fmt::Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::YamlError(e) => e.fmt(f),
+            Self::YamlEmit(e) => write!(f, "error emitting YAML: {:?}", e),
             Self::DocumentCountMismatch => {
                 f.write_str("mismatch in expected document count when parsing YAML")
             }
@@ -54,6 +136,12 @@ impl From<yaml_rust::ScanError> for ParseError {
     }
 }
 
+impl From<yaml_rust::EmitError> for ParseError {
+    fn from(e: yaml_rust::EmitError) -> Self {
+        Self::YamlEmit(e)
+    }
+}
+
 impl From<serde_yaml::Error> for ParseError {
     fn from(e: serde_yaml::Error) -> Self {
         Self::Serde(e)
@@ -64,6 +152,37 @@ const TBD_V2_DOCUMENT_START: &str = "--- !tapi-tbd-v2";
 const TBD_V3_DOCUMENT_START: &str = "--- !tapi-tbd-v3";
 const TBD_V4_DOCUMENT_START: &str = "--- !tapi-tbd";
 
+/// Resolve the TBD version of a document from its `---` start marker line.
+fn document_version(line: &str) -> TbdVersion {
+    if line.starts_with(TBD_V2_DOCUMENT_START) {
+        TbdVersion::V2
+    } else if line.starts_with(TBD_V3_DOCUMENT_START) {
+        TbdVersion::V3
+    } else if line.starts_with(TBD_V4_DOCUMENT_START) {
+        TbdVersion::V4
+    } else {
+        // Version 1 has no document tag.
+        TbdVersion::V1
+    }
+}
+
+/// Convert a parsed YAML document to a TBD record.
+fn parse_yaml_document(
+    version: TbdVersion,
+    value: &yaml_rust::Yaml,
+) -> Result<TbdVersionedRecord, ParseError> {
+    // TODO We could almost certainly avoid the YAML parsing round trip
+    let mut s = String::new();
+    yaml_rust::YamlEmitter::new(&mut s).dump(value)?;
+
+    Ok(match version {
+        TbdVersion::V1 => TbdVersionedRecord::V1(serde_yaml::from_str(&s)?),
+        TbdVersion::V2 => TbdVersionedRecord::V2(serde_yaml::from_str(&s)?),
+        TbdVersion::V3 => TbdVersionedRecord::V3(serde_yaml::from_str(&s)?),
+        TbdVersion::V4 => TbdVersionedRecord::V4(serde_yaml::from_str(&s)?),
+    })
+}
+
 /// Parse TBD records from a YAML stream.
 ///
 /// Returns a series of parsed records contained in the stream.
@@ -86,46 +205,125 @@ pub fn parse_str(data: &str) -> Result<Vec<TbdVersionedRecord>, ParseError> {
     for line in data.lines() {
         // Start of new YAML document.
         if line.starts_with("---") {
-            let version = if line.starts_with(TBD_V2_DOCUMENT_START) {
-                TbdVersion::V2
-            } else if line.starts_with(TBD_V3_DOCUMENT_START) {
-                TbdVersion::V3
-            } else if line.starts_with(TBD_V4_DOCUMENT_START) {
-                TbdVersion::V4
-            } else {
-                // Version 1 has no document tag.
-                TbdVersion::V1
-            };
-
-            document_versions.push(version);
+            document_versions.push(document_version(line));
         }
     }
 
     // The initial document marker in a YAML file is optional. And the
     // `---` marker is a version 1 TBD. So if there is a count mismatch,
     // insert a version 1 at the beginning of the versions list.
-    if document_versions.len() == yamls.len() - 1 {
+    if document_versions.len() + 1 == yamls.len() {
         document_versions.insert(0, TbdVersion::V1);
     } else if document_versions.len() != yamls.len() {
         return Err(ParseError::DocumentCountMismatch);
     }
 
-    let mut res = vec![];
+    yamls
+        .iter()
+        .zip(document_versions)
+        .map(|(value, version)| parse_yaml_document(version, value))
+        .collect()
+}
+
+/// An error parsing a single document in a YAML stream.
+#[derive(Debug)]
+pub struct DocumentError {
+    /// Index of the document in the stream.
+    pub index: usize,
+
+    /// Line number the document starts on, starting at 1.
+    pub line: usize,
+
+    /// The error parsing the document.
+    pub error: ParseError,
+}
+
+impl std::fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "document {} at line {}: {}",
+            self.index, self.line, self.error
+        )
+    }
+}
 
-    for (index, value) in yamls.iter().enumerate() {
-        // TODO We could almost certainly avoid the YAML parsing round trip
-        let mut s = String::new();
-        yaml_rust::YamlEmitter::new(&mut s).dump(value).unwrap();
+impl std::error::Error for DocumentError {}
 
-        res.push(match document_versions[index] {
-            TbdVersion::V1 => TbdVersionedRecord::V1(serde_yaml::from_str(&s)?),
-            TbdVersion::V2 => TbdVersionedRecord::V2(serde_yaml::from_str(&s)?),
-            TbdVersion::V3 => TbdVersionedRecord::V3(serde_yaml::from_str(&s)?),
-            TbdVersion::V4 => TbdVersionedRecord::V4(serde_yaml::from_str(&s)?),
+/// The result of [parse_str_lossy].
+#[derive(Default)]
+pub struct LossyParseResult {
+    /// Records of documents that parsed successfully.
+    pub records: Vec<TbdVersionedRecord>,
+
+    /// Errors for documents that failed to parse.
+    pub errors: Vec<DocumentError>,
+}
+
+/// Split a YAML stream into documents.
+///
+/// Returns the starting line number, TBD version, and source of each document
+/// having content.
+fn split_documents(data: &str) -> Vec<(usize, TbdVersion, String)> {
+    let mut documents = vec![];
+    let mut current: Option<(usize, TbdVersion, String)> = None;
+
+    for (i, line) in data.lines().enumerate() {
+        if line.starts_with("---") {
+            documents.extend(current.take());
+            current = Some((i + 1, document_version(line), String::new()));
+        } else if line.starts_with("...") {
+            documents.extend(current.take());
+            continue;
+        }
+
+        // The initial document marker is optional.
+        let (_, _, source) = current.get_or_insert_with(|| (i + 1, TbdVersion::V1, String::new()));
+        source.push_str(line);
+        source.push('\n');
+    }
+
+    documents.extend(current);
+
+    // Text between an end marker and the next start marker is usually only
+    // comments and whitespace.
+    documents
+        .into_iter()
+        .filter(|(_, _, source)| {
+            source.lines().any(|line| {
+                let line = line.trim();
+                !line.is_empty() && !line.starts_with('#') && !line.starts_with("---")
+            })
         })
+        .collect()
+}
+
+/// Parse TBD records from a YAML stream, tolerating malformed documents.
+///
+/// Unlike [parse_str], which fails if any document in the stream is invalid,
+/// each document is parsed independently. Successfully parsed records are
+/// returned alongside errors for documents that failed to parse.
+pub fn parse_str_lossy(data: &str) -> LossyParseResult {
+    let mut res = LossyParseResult::default();
+
+    for (index, (line, version, source)) in split_documents(data).into_iter().enumerate() {
+        let record = yaml_rust::YamlLoader::load_from_str(&source)
+            .map_err(ParseError::from)
+            .and_then(|yamls| {
+                if yamls.len() == 1 {
+                    parse_yaml_document(version, &yamls[0])
+                } else {
+                    Err(ParseError::DocumentCountMismatch)
+                }
+            });
+
+        match record {
+            Ok(record) => res.records.push(record),
+            Err(error) => res.errors.push(DocumentError { index, line, error }),
+        }
     }
 
-    Ok(res)
+    res
 }
 
 #[cfg(test)]
@@ -140,6 +338,179 @@ mod tests {
         },
     };
 
+    #[test]
+    fn test_exported_symbols() -> Result<(), ParseError> {
+        let records = parse_str(
+            "--- !tapi-tbd
+tbd-version: 4
+targets: [ x86_64-macos, arm64-macos, arm64-ios-simulator ]
+install-name: /usr/lib/libfoo.dylib
+exports:
+  - targets: [ x86_64-macos, arm64-macos ]
+    symbols: [ _foo ]
+    weak-symbols: [ _weak ]
+  - targets: [ x86_64-macos ]
+    symbols: [ _x86_only ]
+...
+",
+        )?;
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+
+        assert_eq!(record.install_name(), "/usr/lib/libfoo.dylib");
+        assert_eq!(
+            record.architectures(),
+            vec![Architecture::X86_64, Architecture::Arm64]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(
+            record.exported_symbols(Architecture::X86_64),
+            vec!["_foo", "_weak", "_x86_only"]
+        );
+        assert_eq!(
+            record.exported_symbols(Architecture::Arm64),
+            vec!["_foo", "_weak"]
+        );
+        assert!(record.exported_symbols(Architecture::I386).is_empty());
+
+        if let TbdVersionedRecord::V4(v) = record {
+            assert!(v.targets[2].is_simulator());
+        } else {
+            panic!("expected version 4 record");
+        }
+
+        let records = parse_str(
+            "---
+archs: [ i386, x86_64 ]
+platform: macosx
+install-name: /usr/lib/libbar.dylib
+exports:
+  - archs: [ x86_64 ]
+    symbols: [ _bar ]
+...
+",
+        )?;
+        assert_eq!(
+            records[0].exported_symbols(Architecture::X86_64),
+            vec!["_bar"]
+        );
+        assert!(records[0].exported_symbols(Architecture::I386).is_empty());
+
+        assert!(parse_str(
+            "---
+archs: [ sparc ]
+platform: macosx
+install-name: /usr/lib/libbar.dylib
+exports: []
+...
+"
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_effective_install_name() -> Result<(), ParseError> {
+        let records = parse_str(
+            "--- !tapi-tbd
+tbd-version: 4
+targets: [ x86_64-macos, arm64-macos ]
+install-name: /usr/lib/libnew.dylib
+exports:
+  - targets: [ x86_64-macos ]
+    symbols: [ '$ld$previous$/usr/lib/libold.dylib$$1$10.4$10.6$$', _foo ]
+...
+",
+        )?;
+
+        let x86 = "x86_64-macos".parse::<Target>().unwrap();
+        let arm = "arm64-macos".parse::<Target>().unwrap();
+
+        assert_eq!(
+            records[0].effective_install_name(&x86, &OsVersion::new(10, 5, 0)),
+            "/usr/lib/libold.dylib"
+        );
+        assert_eq!(
+            records[0].effective_install_name(&x86, &OsVersion::new(10, 6, 0)),
+            "/usr/lib/libnew.dylib"
+        );
+        assert_eq!(
+            records[0].effective_install_name(&arm, &OsVersion::new(10, 5, 0)),
+            "/usr/lib/libnew.dylib"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_str_lossy() {
+        let data = "--- !tapi-tbd
+tbd-version: 4
+targets: [ x86_64-macos ]
+install-name: /usr/lib/libone.dylib
+...
+# A comment between documents.
+--- !tapi-tbd
+tbd-version: 4
+targets: [ x86_64-macos ]
+install-name: [ unclosed
+...
+--- !tapi-tbd
+tbd-version: 4
+targets: [ sparc-macos ]
+install-name: /usr/lib/libthree.dylib
+...
+--- !tapi-tbd-v3
+archs: [ x86_64 ]
+platform: macosx
+install-name: /usr/lib/libfour.dylib
+...
+";
+
+        assert!(parse_str(data).is_err());
+
+        let res = parse_str_lossy(data);
+        assert_eq!(
+            res.records
+                .iter()
+                .map(|r| r.install_name())
+                .collect::<Vec<_>>(),
+            vec!["/usr/lib/libone.dylib", "/usr/lib/libfour.dylib"]
+        );
+        assert_eq!(
+            res.errors
+                .iter()
+                .map(|e| (e.index, e.line))
+                .collect::<Vec<_>>(),
+            vec![(1, 7), (2, 12)]
+        );
+        assert!(matches!(res.errors[0].error, ParseError::YamlError(_)));
+        assert!(matches!(res.errors[1].error, ParseError::Serde(_)));
+
+        // Documents without an initial marker are version 1.
+        let res = parse_str_lossy(
+            "archs: [ x86_64 ]
+platform: macosx
+install-name: /usr/lib/libfoo.dylib
+exports: []
+",
+        );
+        assert!(res.errors.is_empty());
+        assert!(matches!(res.records[0], TbdVersionedRecord::V1(_)));
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        assert!(parse_str("").unwrap().is_empty());
+        assert!(parse_str("# comment\n").unwrap().is_empty());
+
+        let res = parse_str_lossy("");
+        assert!(res.records.is_empty());
+        assert!(res.errors.is_empty());
+    }
+
     #[test]
     fn test_parse_apple_sdk_tbds() {
         // This will find older Xcode versions and their SDKs when run in GitHub
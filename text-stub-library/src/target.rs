@@ -0,0 +1,461 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Architectures and targets in text stub files.
+
+TBD versions 1 through 3 scope symbols to a list of architectures. Version 4
+scopes them to a list of targets, which are an architecture plus a platform.
+This module defines typed representations of these values so consumers can
+filter by architecture without string matching.
+
+See https://github.com/llvm/llvm-project/blob/main/llvm/include/llvm/TextAPI/MachO/Architecture.def
+for the list of known architectures.
+*/
+
+use {
+    serde::{Deserialize, Serialize},
+    std::{
+        convert::TryFrom,
+        fmt::{Display, Formatter},
+        iter::FromIterator,
+        str::FromStr,
+    },
+};
+
+/// Represents an error converting a string to a typed value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TargetError {
+    UnknownArchitecture(String),
+    UnknownPlatform(String),
+    InvalidTarget(String),
+}
+
+impl Display for TargetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownArchitecture(s) => write!(f, "unknown architecture: {}", s),
+            Self::UnknownPlatform(s) => write!(f, "unknown platform: {}", s),
+            Self::InvalidTarget(s) => write!(f, "invalid target: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for TargetError {}
+
+/// A Mach-O architecture.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Architecture {
+    I386,
+    X86_64,
+    X86_64h,
+    Armv4t,
+    Armv6,
+    Armv5,
+    Armv7,
+    Armv7s,
+    Armv7k,
+    Armv6m,
+    Armv7m,
+    Armv7em,
+    Arm64,
+    Arm64e,
+    Arm64_32,
+}
+
+impl Architecture {
+    /// All known architectures.
+    pub const ALL: [Architecture; 15] = [
+        Self::I386,
+        Self::X86_64,
+        Self::X86_64h,
+        Self::Armv4t,
+        Self::Armv6,
+        Self::Armv5,
+        Self::Armv7,
+        Self::Armv7s,
+        Self::Armv7k,
+        Self::Armv6m,
+        Self::Armv7m,
+        Self::Armv7em,
+        Self::Arm64,
+        Self::Arm64e,
+        Self::Arm64_32,
+    ];
+
+    /// The name of this architecture in TBD files.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::I386 => "i386",
+            Self::X86_64 => "x86_64",
+            Self::X86_64h => "x86_64h",
+            Self::Armv4t => "armv4t",
+            Self::Armv6 => "armv6",
+            Self::Armv5 => "armv5",
+            Self::Armv7 => "armv7",
+            Self::Armv7s => "armv7s",
+            Self::Armv7k => "armv7k",
+            Self::Armv6m => "armv6m",
+            Self::Armv7m => "armv7m",
+            Self::Armv7em => "armv7em",
+            Self::Arm64 => "arm64",
+            Self::Arm64e => "arm64e",
+            Self::Arm64_32 => "arm64_32",
+        }
+    }
+
+    fn bit(self) -> u32 {
+        1 << (self as u32)
+    }
+}
+
+impl Display for Architecture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Architecture {
+    type Err = TargetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|arch| arch.as_str() == s)
+            .copied()
+            .ok_or_else(|| TargetError::UnknownArchitecture(s.to_string()))
+    }
+}
+
+/// A set of architectures.
+///
+/// Serializes as a list of architecture names.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct ArchitectureSet(u32);
+
+impl ArchitectureSet {
+    /// Construct an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the set contains an architecture.
+    pub fn contains(&self, arch: Architecture) -> bool {
+        self.0 & arch.bit() != 0
+    }
+
+    /// Add an architecture to the set.
+    pub fn insert(&mut self, arch: Architecture) {
+        self.0 |= arch.bit();
+    }
+
+    /// Whether the set contains no architectures.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// The number of architectures in the set.
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Whether this set has any architecture in common with another set.
+    pub fn intersects(&self, other: ArchitectureSet) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Iterate over architectures in the set.
+    pub fn iter(&self) -> impl Iterator<Item = Architecture> + '_ {
+        Architecture::ALL
+            .iter()
+            .copied()
+            .filter(move |arch| self.contains(*arch))
+    }
+}
+
+impl std::ops::BitOr for ArchitectureSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for ArchitectureSet {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl From<Architecture> for ArchitectureSet {
+    fn from(arch: Architecture) -> Self {
+        Self(arch.bit())
+    }
+}
+
+impl FromIterator<Architecture> for ArchitectureSet {
+    fn from_iter<I: IntoIterator<Item = Architecture>>(iter: I) -> Self {
+        let mut set = Self::new();
+
+        for arch in iter {
+            set.insert(arch);
+        }
+
+        set
+    }
+}
+
+impl TryFrom<Vec<String>> for ArchitectureSet {
+    type Error = TargetError;
+
+    fn try_from(value: Vec<String>) -> Result<Self, Self::Error> {
+        value
+            .iter()
+            .map(|s| Architecture::from_str(s))
+            .collect::<Result<_, _>>()
+    }
+}
+
+impl From<ArchitectureSet> for Vec<String> {
+    fn from(value: ArchitectureSet) -> Self {
+        value.iter().map(|arch| arch.as_str().to_string()).collect()
+    }
+}
+
+/// An Apple platform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Platform {
+    MacOs,
+    Ios,
+    TvOs,
+    WatchOs,
+    BridgeOs,
+    DriverKit,
+}
+
+impl Platform {
+    /// The name of this platform in TBD targets.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MacOs => "macos",
+            Self::Ios => "ios",
+            Self::TvOs => "tvos",
+            Self::WatchOs => "watchos",
+            Self::BridgeOs => "bridgeos",
+            Self::DriverKit => "driverkit",
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = TargetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "macos" => Ok(Self::MacOs),
+            "ios" => Ok(Self::Ios),
+            "tvos" => Ok(Self::TvOs),
+            "watchos" => Ok(Self::WatchOs),
+            "bridgeos" => Ok(Self::BridgeOs),
+            "driverkit" => Ok(Self::DriverKit),
+            _ => Err(TargetError::UnknownPlatform(s.to_string())),
+        }
+    }
+}
+
+/// The environment a platform runs in.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PlatformEnvironment {
+    /// Physical devices.
+    Device,
+    /// The simulator for the platform.
+    Simulator,
+    /// Mac Catalyst, which runs iOS applications on macOS.
+    MacAbi,
+}
+
+/// A target triple in a TBD version 4 structure.
+///
+/// e.g. `arm64-macos`, `x86_64-ios-simulator`, or `x86_64-maccatalyst`.
+/// Serializes as a string.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Target {
+    pub arch: Architecture,
+    pub platform: Platform,
+    pub environment: PlatformEnvironment,
+}
+
+impl Target {
+    /// Whether this target is a simulator.
+    pub fn is_simulator(&self) -> bool {
+        self.environment == PlatformEnvironment::Simulator
+    }
+
+    /// Whether this target is Mac Catalyst.
+    pub fn is_macabi(&self) -> bool {
+        self.environment == PlatformEnvironment::MacAbi
+    }
+
+    /// The Mach-O platform number of this target.
+    ///
+    /// This is the `PLATFORM_*` value from `<mach-o/loader.h>`, as used by
+    /// `$ld$previous` symbols.
+    pub fn macho_platform(&self) -> u32 {
+        match (self.platform, self.environment) {
+            (_, PlatformEnvironment::MacAbi) => 6,
+            (Platform::MacOs, _) => 1,
+            (Platform::Ios, PlatformEnvironment::Device) => 2,
+            (Platform::TvOs, PlatformEnvironment::Device) => 3,
+            (Platform::WatchOs, PlatformEnvironment::Device) => 4,
+            (Platform::BridgeOs, _) => 5,
+            (Platform::Ios, PlatformEnvironment::Simulator) => 7,
+            (Platform::TvOs, PlatformEnvironment::Simulator) => 8,
+            (Platform::WatchOs, PlatformEnvironment::Simulator) => 9,
+            (Platform::DriverKit, _) => 10,
+        }
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.environment {
+            PlatformEnvironment::Device => write!(f, "{}-{}", self.arch, self.platform.as_str()),
+            PlatformEnvironment::Simulator => {
+                write!(f, "{}-{}-simulator", self.arch, self.platform.as_str())
+            }
+            PlatformEnvironment::MacAbi => write!(f, "{}-maccatalyst", self.arch),
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = TargetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '-');
+
+        let arch = parts.next().unwrap_or_default();
+        let platform = parts
+            .next()
+            .ok_or_else(|| TargetError::InvalidTarget(s.to_string()))?;
+
+        let arch = Architecture::from_str(arch)?;
+
+        let (platform, environment) = if platform == "maccatalyst" {
+            (Platform::Ios, PlatformEnvironment::MacAbi)
+        } else if let Some(platform) = platform.strip_suffix("-simulator") {
+            (
+                Platform::from_str(platform)?,
+                PlatformEnvironment::Simulator,
+            )
+        } else {
+            (Platform::from_str(platform)?, PlatformEnvironment::Device)
+        };
+
+        Ok(Self {
+            arch,
+            platform,
+            environment,
+        })
+    }
+}
+
+impl TryFrom<String> for Target {
+    type Error = TargetError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str(&value)
+    }
+}
+
+impl From<Target> for String {
+    fn from(value: Target) -> Self {
+        value.to_string()
+    }
+}
+
+/// Obtain the set of architectures of a list of targets.
+pub fn target_architectures(targets: &[Target]) -> ArchitectureSet {
+    targets.iter().map(|target| target.arch).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_architecture_set() -> Result<(), TargetError> {
+        let archs = ArchitectureSet::try_from(vec!["arm64".to_string(), "x86_64".to_string()])?;
+
+        assert_eq!(archs.len(), 2);
+        assert!(archs.contains(Architecture::Arm64));
+        assert!(archs.contains(Architecture::X86_64));
+        assert!(!archs.contains(Architecture::Arm64e));
+        assert!(archs.intersects(Architecture::Arm64.into()));
+        assert_eq!(
+            Vec::<String>::from(archs),
+            vec!["x86_64".to_string(), "arm64".to_string()]
+        );
+
+        assert_eq!(
+            ArchitectureSet::try_from(vec!["sparc".to_string()]),
+            Err(TargetError::UnknownArchitecture("sparc".to_string()))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_target() -> Result<(), TargetError> {
+        for (s, arch, platform, environment) in &[
+            (
+                "arm64-macos",
+                Architecture::Arm64,
+                Platform::MacOs,
+                PlatformEnvironment::Device,
+            ),
+            (
+                "x86_64-ios-simulator",
+                Architecture::X86_64,
+                Platform::Ios,
+                PlatformEnvironment::Simulator,
+            ),
+            (
+                "arm64e-maccatalyst",
+                Architecture::Arm64e,
+                Platform::Ios,
+                PlatformEnvironment::MacAbi,
+            ),
+            (
+                "arm64_32-watchos",
+                Architecture::Arm64_32,
+                Platform::WatchOs,
+                PlatformEnvironment::Device,
+            ),
+        ] {
+            let target = Target::from_str(s)?;
+            assert_eq!(target.arch, *arch);
+            assert_eq!(target.platform, *platform);
+            assert_eq!(target.environment, *environment);
+            assert_eq!(&target.to_string(), s);
+        }
+
+        assert!(Target::from_str("x86_64-ios-simulator")?.is_simulator());
+        assert!(Target::from_str("x86_64-maccatalyst")?.is_macabi());
+        assert_eq!(Target::from_str("arm64-macos")?.macho_platform(), 1);
+        assert_eq!(Target::from_str("x86_64-maccatalyst")?.macho_platform(), 6);
+        assert_eq!(Target::from_str("arm64-ios-simulator")?.macho_platform(), 7);
+        assert_eq!(
+            Target::from_str("arm64"),
+            Err(TargetError::InvalidTarget("arm64".to_string()))
+        );
+        assert_eq!(
+            Target::from_str("arm64-linux"),
+            Err(TargetError::UnknownPlatform("linux".to_string()))
+        );
+
+        Ok(())
+    }
+}
@@ -11,7 +11,10 @@ See https://github.com/llvm/llvm-project/blob/main/llvm/lib/TextAPI/MachO/TextSt
 for specifications of the YAML files.
 */
 
-use serde::{Deserialize, Serialize};
+use {
+    crate::target::{ArchitectureSet, Target},
+    serde::{Deserialize, Serialize},
+};
 
 /*
 The TBD v1 format only support two level address libraries and is per
@@ -47,36 +50,36 @@ pub struct TbdVersion1 {
     /// The list of architecture slices that are supported by this file.
     ///
     /// armv7, arm64, etc.
-    archs: Vec<String>,
+    pub archs: ArchitectureSet,
 
     /// Specifies the platform (macosx, ios, etc).
-    platform: String,
+    pub platform: String,
 
     /// Path of installed library.
-    install_name: String,
+    pub install_name: String,
 
     /// Current version of library.
     ///
     /// Defaults to `1.0`.
-    current_version: Option<String>,
+    pub current_version: Option<String>,
 
     /// Compatibility version of library.
     ///
     /// Defaults to `1.0`.
-    compatibility_version: Option<String>,
+    pub compatibility_version: Option<String>,
 
     /// Swift version of library.
     ///
     /// Defaults to `0`.
-    swift_version: Option<String>,
+    pub swift_version: Option<String>,
 
     /// Objective-C constraint.
     ///
     /// Defaults to `none`.
-    objc_constraint: Option<String>,
+    pub objc_constraint: Option<String>,
 
     /// Export sections.
-    exports: Vec<TbdVersion12ExportSection>,
+    pub exports: Vec<TbdVersion12ExportSection>,
 }
 
 /// Export section in a TBD version 1 or 2 structure.
@@ -84,7 +87,7 @@ pub struct TbdVersion1 {
 #[serde(rename_all = "kebab-case")]
 pub struct TbdVersion12ExportSection {
     /// List of architecture slices.
-    pub archs: Vec<String>,
+    pub archs: ArchitectureSet,
 
     /// List of clients.
     #[serde(default)]
@@ -155,7 +158,7 @@ Each undefineds section is defined as following:
 #[serde(rename_all = "kebab-case")]
 pub struct TbdVersion2 {
     /// The list of architecture slices that are supported by this file.
-    pub archs: Vec<String>,
+    pub archs: ArchitectureSet,
 
     /// List of architecture and UUID pairs.
     #[serde(default)]
@@ -199,7 +202,7 @@ pub struct TbdVersion2 {
 #[serde(rename_all = "kebab-case")]
 pub struct TbdVersion2UndefinedsSection {
     /// The list of architecture slices.
-    pub archs: Vec<String>,
+    pub archs: ArchitectureSet,
 
     /// List of symbols.
     #[serde(default)]
@@ -222,7 +225,7 @@ pub struct TbdVersion2UndefinedsSection {
 #[serde(rename_all = "kebab-case")]
 pub struct TbdUmbrellaSection {
     #[serde(default)]
-    pub targets: Vec<String>,
+    pub targets: Vec<Target>,
 
     pub umbrella: String,
 }
@@ -271,7 +274,7 @@ Each undefineds section is defined as following:
 #[serde(rename_all = "kebab-case")]
 pub struct TbdVersion3 {
     /// The list of architecture slices that are supported by this file.
-    pub archs: Vec<String>,
+    pub archs: ArchitectureSet,
 
     /// List of architecture and UUID pairs.
     #[serde(default)]
@@ -319,7 +322,7 @@ pub struct TbdVersion3 {
 #[serde(rename_all = "kebab-case")]
 pub struct TbdVersion3ExportSection {
     /// List of architecture slices.
-    pub archs: Vec<String>,
+    pub archs: ArchitectureSet,
 
     /// List of clients.
     #[serde(default)]
@@ -359,7 +362,7 @@ pub struct TbdVersion3ExportSection {
 #[serde(rename_all = "kebab-case")]
 pub struct TbdVersion3UndefinedsSection {
     /// The list of architecture slices.
-    pub archs: Vec<String>,
+    pub archs: ArchitectureSet,
 
     /// List of symbols.
     #[serde(default)]
@@ -436,7 +439,7 @@ pub struct TbdVersion4 {
     pub tbd_version: usize,
 
     /// The list of applicable tapi supported target triples.
-    pub targets: Vec<String>,
+    pub targets: Vec<Target>,
 
     /// List of architecture and UUID pairs.
     #[serde(default)]
@@ -482,7 +485,7 @@ pub struct TbdVersion4 {
 /// A UUID value in a TBD version 4 data structure.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TbdVersion4Uuid {
-    pub target: String,
+    pub target: Target,
 
     pub value: String,
 }
@@ -491,8 +494,8 @@ pub struct TbdVersion4Uuid {
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TbdVersion4AllowableClient {
     #[serde(default)]
-    targets: Vec<String>,
-    clients: Vec<String>,
+    pub targets: Vec<Target>,
+    pub clients: Vec<String>,
 }
 
 /// (Re)export section in a TBD version 4 structure.
@@ -500,7 +503,7 @@ pub struct TbdVersion4AllowableClient {
 #[serde(rename_all = "kebab-case")]
 pub struct TbdVersion4ExportSection {
     /// Target triples associated with symbols.
-    pub targets: Vec<String>,
+    pub targets: Vec<Target>,
 
     /// List of symbols.
     #[serde(default)]
@@ -532,7 +535,7 @@ pub struct TbdVersion4ExportSection {
 #[serde(rename_all = "kebab-case")]
 pub struct TbdVersion4UndefinedsSection {
     /// The list of target triples associated with symbols.
-    pub targets: Vec<String>,
+    pub targets: Vec<Target>,
 
     /// List of symbols.
     #[serde(default)]
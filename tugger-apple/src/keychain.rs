@@ -0,0 +1,131 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Sign files with identities in the macOS Keychain. */
+
+use {
+    anyhow::{anyhow, Context, Result},
+    slog::warn,
+    std::{
+        io::{BufRead, BufReader},
+        path::{Path, PathBuf},
+    },
+    tugger_common::signing::SigningCallback,
+};
+
+/// Signs files with `codesign` using an identity in the Keychain.
+///
+/// The private key stays in the Keychain. The Keychain must be unlocked,
+/// e.g. via `security unlock-keychain`, for signing to work unattended.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeychainSigner {
+    /// Name or SHA-1 hash of the signing identity.
+    pub identity: String,
+
+    /// Keychain to search for the identity instead of the search list.
+    pub keychain: Option<PathBuf>,
+
+    /// Whether to request a secure timestamp from Apple's server.
+    pub timestamp: bool,
+}
+
+impl KeychainSigner {
+    /// Create a new instance signing with an identity.
+    pub fn new(identity: impl ToString) -> Self {
+        Self {
+            identity: identity.to_string(),
+            keychain: None,
+            timestamp: true,
+        }
+    }
+
+    /// Obtain the arguments to `codesign` to sign a path.
+    pub fn args(&self, path: &Path) -> Vec<String> {
+        let mut args = vec![
+            "--force".to_string(),
+            "--sign".to_string(),
+            self.identity.clone(),
+        ];
+
+        if let Some(keychain) = &self.keychain {
+            args.push("--keychain".to_string());
+            args.push(keychain.display().to_string());
+        }
+
+        args.push(if self.timestamp {
+            "--timestamp".to_string()
+        } else {
+            "--timestamp=none".to_string()
+        });
+
+        args.push(path.display().to_string());
+
+        args
+    }
+}
+
+impl SigningCallback for KeychainSigner {
+    fn sign(&self, logger: &slog::Logger, path: &Path) -> Result<()> {
+        if !cfg!(target_os = "macos") {
+            return Err(anyhow!(
+                "signing with the Keychain is only supported on macOS"
+            ));
+        }
+
+        let command = duct::cmd("codesign", self.args(path))
+            .stderr_to_stdout()
+            .reader()
+            .context("running codesign")?;
+        {
+            let reader = BufReader::new(&command);
+            for line in reader.lines() {
+                warn!(logger, "{}", line?);
+            }
+        }
+
+        let output = command
+            .try_wait()?
+            .ok_or_else(|| anyhow!("unable to wait on command"))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!("error running codesign"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args() {
+        let mut signer = KeychainSigner::new("Developer ID Application: Example (ABCDE12345)");
+        assert_eq!(
+            signer.args(Path::new("app")),
+            vec![
+                "--force",
+                "--sign",
+                "Developer ID Application: Example (ABCDE12345)",
+                "--timestamp",
+                "app"
+            ]
+        );
+
+        signer.keychain = Some(PathBuf::from("build.keychain"));
+        signer.timestamp = false;
+        assert_eq!(
+            signer.args(Path::new("app")),
+            vec![
+                "--force",
+                "--sign",
+                "Developer ID Application: Example (ABCDE12345)",
+                "--keychain",
+                "build.keychain",
+                "--timestamp=none",
+                "app"
+            ]
+        );
+    }
+}
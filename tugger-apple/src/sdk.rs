@@ -7,7 +7,7 @@ use {
     duct::cmd,
     once_cell::sync::Lazy,
     semver::Version,
-    serde::Deserialize,
+    serde::{Deserialize, Deserializer},
     std::{
         collections::HashMap,
         path::{Path, PathBuf},
@@ -28,14 +28,49 @@ pub static XCODE_APP_DEFAULT_PATH: Lazy<PathBuf> =
 pub static XCODE_APP_RELATIVE_PATH_DEVELOPER: Lazy<PathBuf> =
     Lazy::new(|| PathBuf::from("Contents/Developer"));
 
-/// Represents the DefaultProperties key in a SDKSettings.json file.
+/// Parse a version string of the form `X.Y` or `X.Y.Z` to a `semver::Version`.
+fn parse_version(version: &str) -> Result<Version> {
+    match version.split('.').count() {
+        2 => Ok(Version::parse(&format!("{}.0", version))?),
+        3 => Ok(Version::parse(version)?),
+        _ => Err(anyhow!(
+            "version string {} is not of form X.Y or X.Y.Z",
+            version
+        )),
+    }
+}
+
+/// Deserialize build settings, joining list values with spaces.
+///
+/// Xcode treats list build settings as space delimited strings.
+fn deserialize_build_settings<'de, D>(deserializer: D) -> Result<HashMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BuildSetting {
+        String(String),
+        List(Vec<String>),
+    }
+
+    Ok(HashMap::<String, BuildSetting>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(k, v)| match v {
+            BuildSetting::String(v) => (k, v),
+            BuildSetting::List(v) => (k, v.join(" ")),
+        })
+        .collect())
+}
+
+/// Represents the DefaultProperties key in a SDKSettings file.
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-struct SdkSettingsJsonDefaultProperties {
+struct SdkSettingsDefaultProperties {
     platform_name: String,
 }
 
-/// Represents a SupportedTargets value in a SDKSettings.json file.
+/// Represents a SupportedTargets value in a SDKSettings file.
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct AppleSdkSupportedTarget {
@@ -45,21 +80,68 @@ pub struct AppleSdkSupportedTarget {
     pub deployment_target_setting_name: Option<String>,
     pub minimum_deployment_target: String,
     pub platform_family_name: Option<String>,
+    #[serde(default)]
     pub valid_deployment_targets: Vec<String>,
 }
 
-/// Used for deserializing a SDKSettings.json file in an SDK directory.
+impl AppleSdkSupportedTarget {
+    /// Whether this target supports building for a named architecture. e.g. `arm64`.
+    pub fn supports_arch(&self, arch: &str) -> bool {
+        self.archs.iter().any(|a| a == arch)
+    }
+
+    /// Whether this target supports a deployment target version. e.g. `10.15`.
+    ///
+    /// The version must not be older than the minimum deployment target. If the
+    /// target enumerates valid deployment targets, the version must be one of them.
+    pub fn supports_deployment_target(&self, version: &str) -> Result<bool> {
+        let wanted = parse_version(version)?;
+
+        if wanted < parse_version(&self.minimum_deployment_target)? {
+            return Ok(false);
+        }
+
+        if self.valid_deployment_targets.is_empty() {
+            return Ok(true);
+        }
+
+        for valid in &self.valid_deployment_targets {
+            if parse_version(valid)? == wanted {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+/// Represents a Variants entry in a SDKSettings file.
+///
+/// Variants define alternate build settings for an SDK. e.g. the macOS SDK
+/// has a `iosmac` variant for Mac Catalyst.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AppleSdkVariant {
+    pub name: String,
+    #[serde(default, deserialize_with = "deserialize_build_settings")]
+    pub build_settings: HashMap<String, String>,
+}
+
+/// Used for deserializing a SDKSettings.json or SDKSettings.plist file in an SDK directory.
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
-struct SdkSettingsJson {
+struct SdkSettings {
     canonical_name: String,
     default_deployment_target: String,
-    default_properties: SdkSettingsJsonDefaultProperties,
+    default_properties: SdkSettingsDefaultProperties,
     default_variant: Option<String>,
     display_name: String,
     maximum_deployment_target: String,
     minimal_display_name: String,
+    #[serde(default)]
     supported_targets: HashMap<String, AppleSdkSupportedTarget>,
+    #[serde(default)]
+    variants: Vec<AppleSdkVariant>,
     version: String,
 }
 
@@ -96,6 +178,9 @@ pub struct AppleSdk {
     /// Describes named target configurations this SDK supports.
     pub supported_targets: HashMap<String, AppleSdkSupportedTarget>,
 
+    /// Alternate build settings this SDK defines.
+    pub variants: Vec<AppleSdkVariant>,
+
     /// Version of this SDK. e.g. `11.1`.
     pub version: String,
 }
@@ -109,23 +194,31 @@ impl AppleSdk {
 
         let is_symlink = metadata.file_type().is_symlink();
 
-        let settings_path = path.join("SDKSettings.json");
+        // Modern SDKs have a SDKSettings.json. Older SDKs only have the
+        // equivalent SDKSettings.plist.
+        let json_path = path.join("SDKSettings.json");
+        let plist_path = path.join("SDKSettings.plist");
 
-        let json_data = std::fs::read(&settings_path)
-            .with_context(|| format!("reading {}", settings_path.display()))?;
+        let settings: SdkSettings = if json_path.exists() {
+            let json_data = std::fs::read(&json_path)
+                .with_context(|| format!("reading {}", json_path.display()))?;
 
-        let settings_json: SdkSettingsJson = serde_json::from_slice(&json_data)
-            .with_context(|| format!("parsing {}", settings_path.display()))?;
+            serde_json::from_slice(&json_data)
+                .with_context(|| format!("parsing {}", json_path.display()))?
+        } else {
+            plist::from_file(&plist_path)
+                .with_context(|| format!("parsing {}", plist_path.display()))?
+        };
 
-        Ok(Self::from_json(
+        Ok(Self::from_settings(
             path.to_path_buf(),
             is_symlink,
-            settings_json,
+            settings,
         ))
     }
 
-    /// Attempt to create a new instance from deserialized JSON.
-    fn from_json(path: PathBuf, is_symlink: bool, value: SdkSettingsJson) -> Self {
+    /// Attempt to create a new instance from deserialized settings.
+    fn from_settings(path: PathBuf, is_symlink: bool, value: SdkSettings) -> Self {
         Self {
             path,
             is_symlink,
@@ -137,20 +230,72 @@ impl AppleSdk {
             maximum_deployment_target: value.maximum_deployment_target,
             minimal_display_name: value.minimal_display_name,
             supported_targets: value.supported_targets,
+            variants: value.variants,
             version: value.version,
         }
     }
 
     /// Convert the version string to a `semver::Version`.
     pub fn version_as_semver(&self) -> Result<Version> {
-        match self.version.split('.').count() {
-            2 => Ok(Version::parse(&format!("{}.0", self.version))?),
-            3 => Ok(Version::parse(&self.version)?),
-            _ => Err(anyhow!(
-                "version string {} is not of form X.Y or X.Y.Z",
-                self.version
-            )),
+        parse_version(&self.version)
+    }
+
+    /// Obtain the named target configuration. e.g. `macosx` or `iosmac`.
+    pub fn supported_target(&self, name: &str) -> Option<&AppleSdkSupportedTarget> {
+        self.supported_targets.get(name)
+    }
+
+    /// Obtain the named settings variant.
+    pub fn variant(&self, name: &str) -> Option<&AppleSdkVariant> {
+        self.variants.iter().find(|v| v.name == name)
+    }
+
+    /// Verify this SDK can build for a target with an architecture and deployment target.
+    ///
+    /// Returns `Err` describing the first unsupported value.
+    pub fn validate_target(
+        &self,
+        target: &str,
+        arch: Option<&str>,
+        deployment_target: Option<&str>,
+    ) -> Result<()> {
+        let supported = self.supported_target(target).ok_or_else(|| {
+            let mut names = self.supported_targets.keys().collect::<Vec<_>>();
+            names.sort();
+
+            anyhow!(
+                "SDK {} does not support target {}; supported targets: {:?}",
+                self.name,
+                target,
+                names
+            )
+        })?;
+
+        if let Some(arch) = arch {
+            if !supported.supports_arch(arch) {
+                return Err(anyhow!(
+                    "SDK {} target {} does not support architecture {}; supported architectures: {:?}",
+                    self.name,
+                    target,
+                    arch,
+                    supported.archs
+                ));
+            }
+        }
+
+        if let Some(version) = deployment_target {
+            if !supported.supports_deployment_target(version)? {
+                return Err(anyhow!(
+                    "SDK {} target {} does not support deployment target {}; minimum is {}",
+                    self.name,
+                    target,
+                    version,
+                    supported.minimum_deployment_target
+                ));
+            }
         }
+
+        Ok(())
     }
 }
 
@@ -341,9 +486,9 @@ pub fn find_sdks_in_directory(root: &Path) -> Result<Vec<AppleSdk>> {
     for entry in dir {
         let entry = entry.context("reading directory entry")?;
 
-        let settings_path = entry.path().join("SDKSettings.json");
-
-        if !settings_path.exists() {
+        if !entry.path().join("SDKSettings.json").exists()
+            && !entry.path().join("SDKSettings.plist").exists()
+        {
             continue;
         }
 
@@ -413,6 +558,103 @@ pub fn find_command_line_tools_sdks() -> Result<Option<Vec<AppleSdk>>> {
 mod tests {
     use super::*;
 
+    const SDK_SETTINGS_JSON: &str = r#"{
+        "CanonicalName": "macosx11.1",
+        "DefaultDeploymentTarget": "11.1",
+        "DefaultProperties": {"PLATFORM_NAME": "macosx"},
+        "DefaultVariant": "macos",
+        "DisplayName": "macOS 11.1",
+        "MaximumDeploymentTarget": "11.1.99",
+        "MinimalDisplayName": "11.1",
+        "SupportedTargets": {
+            "macosx": {
+                "Archs": ["x86_64", "x86_64h", "arm64", "arm64e"],
+                "DefaultDeploymentTarget": "11.1",
+                "DefaultVariant": "macos",
+                "DeploymentTargetSettingName": "MACOSX_DEPLOYMENT_TARGET",
+                "MinimumDeploymentTarget": "10.9",
+                "PlatformFamilyName": "macOS",
+                "ValidDeploymentTargets": ["10.9", "10.10", "10.15", "11.0", "11.1"]
+            }
+        },
+        "Variants": [
+            {"Name": "macos", "BuildSettings": {"SWIFT_PLATFORM_TARGET_PREFIX": "macos"}},
+            {"Name": "iosmac", "BuildSettings": {"RESOURCES_UI_FRAMEWORK_FAMILY": ["uikit", "appkit"]}}
+        ],
+        "Version": "11.1"
+    }"#;
+
+    #[test]
+    fn test_sdk_settings_json() -> Result<()> {
+        let settings: SdkSettings = serde_json::from_str(SDK_SETTINGS_JSON)?;
+        let sdk = AppleSdk::from_settings(PathBuf::from("MacOSX11.1.sdk"), false, settings);
+
+        assert_eq!(sdk.platform_name, "macosx");
+        assert_eq!(sdk.version_as_semver()?, Version::new(11, 1, 0));
+
+        let target = sdk.supported_target("macosx").unwrap();
+        assert!(target.supports_arch("arm64"));
+        assert!(!target.supports_arch("i386"));
+        assert!(target.supports_deployment_target("10.15")?);
+        assert!(target.supports_deployment_target("11.0.0")?);
+        assert!(!target.supports_deployment_target("10.8")?);
+        assert!(!target.supports_deployment_target("10.11")?);
+        assert!(target.supports_deployment_target("11").is_err());
+
+        assert_eq!(
+            sdk.variant("iosmac").unwrap().build_settings["RESOURCES_UI_FRAMEWORK_FAMILY"],
+            "uikit appkit"
+        );
+        assert!(sdk.variant("watchos").is_none());
+
+        sdk.validate_target("macosx", Some("arm64"), Some("11.0"))?;
+        sdk.validate_target("macosx", None, None)?;
+        assert!(sdk.validate_target("iphoneos", None, None).is_err());
+        assert!(sdk.validate_target("macosx", Some("armv7"), None).is_err());
+        assert!(sdk.validate_target("macosx", None, Some("10.4")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sdk_settings_plist() -> Result<()> {
+        let plist_data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>CanonicalName</key>
+    <string>macosx10.13</string>
+    <key>DefaultDeploymentTarget</key>
+    <string>10.13</string>
+    <key>DefaultProperties</key>
+    <dict>
+        <key>PLATFORM_NAME</key>
+        <string>macosx</string>
+    </dict>
+    <key>DisplayName</key>
+    <string>macOS 10.13</string>
+    <key>MaximumDeploymentTarget</key>
+    <string>10.13</string>
+    <key>MinimalDisplayName</key>
+    <string>10.13</string>
+    <key>Version</key>
+    <string>10.13</string>
+</dict>
+</plist>
+"#;
+
+        let settings: SdkSettings =
+            plist::from_reader(std::io::Cursor::new(plist_data.as_bytes()))?;
+        let sdk = AppleSdk::from_settings(PathBuf::from("MacOSX10.13.sdk"), false, settings);
+
+        assert_eq!(sdk.name, "macosx10.13");
+        assert!(sdk.supported_targets.is_empty());
+        assert!(sdk.variants.is_empty());
+        assert!(sdk.validate_target("macosx", None, None).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_find_system_xcode_applications() -> Result<()> {
         let res = find_system_xcode_applications()?;
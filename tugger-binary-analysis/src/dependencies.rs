@@ -0,0 +1,42 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    anyhow::{anyhow, Result},
+    std::path::Path,
+};
+
+/// Find the names of shared libraries a binary depends on.
+///
+/// ELF, PE, and Mach-O binaries are supported. For Mach-O binaries, the
+/// names are the install names of the libraries, which may contain path
+/// components and `@rpath` style prefixes.
+pub fn find_library_dependencies(data: &[u8]) -> Result<Vec<String>> {
+    match goblin::Object::parse(data)? {
+        goblin::Object::Elf(elf) => Ok(elf.libraries.iter().map(|l| (*l).to_string()).collect()),
+        goblin::Object::PE(pe) => Ok(pe.libraries.iter().map(|l| (*l).to_string()).collect()),
+        goblin::Object::Mach(mach) => {
+            let macho = match mach {
+                goblin::mach::Mach::Binary(macho) => macho,
+                goblin::mach::Mach::Fat(multi) => multi.get(0)?,
+            };
+
+            // goblin records the binary itself as the first library.
+            Ok(macho
+                .libs
+                .iter()
+                .filter(|l| **l != "self")
+                .map(|l| (*l).to_string())
+                .collect())
+        }
+        goblin::Object::Archive(_) => Err(anyhow!("archives do not have library dependencies")),
+        goblin::Object::Unknown(magic) => Err(anyhow!("unknown binary magic: {:#x}", magic)),
+    }
+}
+
+/// Find the names of shared libraries a binary file depends on.
+pub fn find_library_dependencies_path(path: &Path) -> Result<Vec<String>> {
+    let data = std::fs::read(path)?;
+    find_library_dependencies(&data)
+}
@@ -6,6 +6,8 @@
 
 mod audit;
 pub use audit::{analyze_data, analyze_elf_libraries, analyze_file};
+mod dependencies;
+pub use dependencies::{find_library_dependencies, find_library_dependencies_path};
 mod elf;
 pub use elf::find_undefined_elf_symbols;
 mod linux_distro_versions;
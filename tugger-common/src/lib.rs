@@ -4,5 +4,7 @@
 
 pub mod glob;
 pub mod http;
+pub mod publish;
+pub mod signing;
 pub mod testutil;
 pub mod zipfile;
@@ -0,0 +1,292 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Upload build artifacts to remote services. */
+
+use {
+    crate::http::get_http_client,
+    anyhow::{anyhow, Context, Result},
+    slog::warn,
+    std::path::{Path, PathBuf},
+    url::Url,
+};
+
+/// Default base URL of the GitHub REST API.
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// Upload files as assets of a GitHub release.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GitHubReleaseUpload {
+    /// Repository to publish to, as `owner/name`.
+    pub repo: String,
+
+    /// Tag of the release. The release is created if it doesn't exist.
+    pub tag: String,
+
+    /// Files to upload.
+    pub files: Vec<PathBuf>,
+
+    /// API token to authenticate with.
+    pub token: String,
+
+    /// Whether a created release should be a draft.
+    pub draft: bool,
+
+    /// Base URL of the GitHub REST API.
+    pub api_url: String,
+}
+
+/// Obtain the URL to upload a release asset to.
+///
+/// `upload_url` is the URI template advertised by the GitHub API, e.g.
+/// `https://uploads.github.com/repos/o/r/releases/1/assets{?name,label}`.
+pub fn github_asset_upload_url(upload_url: &str, name: &str) -> Result<Url> {
+    let base = match upload_url.find('{') {
+        Some(pos) => &upload_url[0..pos],
+        None => upload_url,
+    };
+
+    Ok(Url::parse_with_params(base, &[("name", name)])?)
+}
+
+fn file_name(path: &Path) -> Result<String> {
+    Ok(path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} does not have a filename", path.display()))?
+        .to_string_lossy()
+        .to_string())
+}
+
+fn response_json(response: reqwest::blocking::Response) -> Result<serde_json::Value> {
+    let status = response.status();
+    let body = response.bytes()?;
+
+    if !status.is_success() {
+        return Err(anyhow!(
+            "GitHub API request failed with {}: {}",
+            status,
+            String::from_utf8_lossy(&body)
+        ));
+    }
+
+    serde_json::from_slice(&body).context("parsing GitHub API response")
+}
+
+impl GitHubReleaseUpload {
+    fn request(
+        &self,
+        client: &reqwest::blocking::Client,
+        method: reqwest::Method,
+        url: &str,
+    ) -> reqwest::blocking::RequestBuilder {
+        client
+            .request(method, url)
+            .header("Accept", "application/vnd.github.v3+json")
+            .header("Authorization", format!("token {}", self.token))
+            .header("User-Agent", "tugger")
+    }
+
+    /// Resolve the release to upload to, creating it if necessary.
+    fn resolve_release(
+        &self,
+        logger: &slog::Logger,
+        client: &reqwest::blocking::Client,
+    ) -> Result<serde_json::Value> {
+        let url = format!(
+            "{}/repos/{}/releases/tags/{}",
+            self.api_url, self.repo, self.tag
+        );
+
+        let response = self.request(client, reqwest::Method::GET, &url).send()?;
+
+        if response.status() != reqwest::StatusCode::NOT_FOUND {
+            return response_json(response);
+        }
+
+        warn!(
+            logger,
+            "creating GitHub release {} in {}", self.tag, self.repo
+        );
+
+        response_json(
+            self.request(
+                client,
+                reqwest::Method::POST,
+                &format!("{}/repos/{}/releases", self.api_url, self.repo),
+            )
+            .body(
+                serde_json::json!({
+                    "tag_name": self.tag,
+                    "name": self.tag,
+                    "draft": self.draft,
+                })
+                .to_string(),
+            )
+            .send()?,
+        )
+    }
+
+    /// Perform the upload, returning the download URLs of uploaded files.
+    ///
+    /// Existing release assets having the same name as an uploaded file are
+    /// replaced.
+    pub fn upload(&self, logger: &slog::Logger) -> Result<Vec<String>> {
+        let client = get_http_client()?;
+
+        let release = self.resolve_release(logger, &client)?;
+
+        let upload_url = release["upload_url"]
+            .as_str()
+            .ok_or_else(|| anyhow!("GitHub release does not define an upload URL"))?;
+
+        let mut urls = vec![];
+
+        for path in &self.files {
+            let name = file_name(path)?;
+
+            if let Some(asset) = release["assets"]
+                .as_array()
+                .and_then(|assets| assets.iter().find(|a| a["name"].as_str() == Some(&name)))
+            {
+                warn!(logger, "replacing existing release asset {}", name);
+                let url = asset["url"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("GitHub release asset does not define a URL"))?;
+
+                let response = self.request(&client, reqwest::Method::DELETE, url).send()?;
+                if !response.status().is_success() {
+                    return Err(anyhow!(
+                        "failed to delete release asset {}: {}",
+                        name,
+                        response.status()
+                    ));
+                }
+            }
+
+            let data =
+                std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+            warn!(
+                logger,
+                "uploading {} to GitHub release {} in {}",
+                path.display(),
+                self.tag,
+                self.repo
+            );
+
+            let asset = response_json(
+                self.request(
+                    &client,
+                    reqwest::Method::POST,
+                    github_asset_upload_url(upload_url, &name)?.as_str(),
+                )
+                .header("Content-Type", "application/octet-stream")
+                .body(data)
+                .send()?,
+            )?;
+
+            if let Some(url) = asset["browser_download_url"].as_str() {
+                urls.push(url.to_string());
+            }
+        }
+
+        Ok(urls)
+    }
+}
+
+/// Upload files to an Amazon S3 bucket.
+///
+/// Uploading is performed by the AWS CLI (`aws s3 cp`), which must be
+/// installed. Credentials are resolved by the AWS CLI using its standard
+/// mechanisms (environment variables, configuration files, instance
+/// metadata, etc).
+#[derive(Clone, Debug, PartialEq)]
+pub struct S3Upload {
+    /// Name of the bucket to upload to.
+    pub bucket: String,
+
+    /// Key prefix of uploaded objects.
+    pub prefix: String,
+
+    /// Files to upload.
+    pub files: Vec<PathBuf>,
+
+    /// AWS region of the bucket.
+    pub region: Option<String>,
+}
+
+/// Obtain the S3 object key of a file uploaded under a prefix.
+pub fn s3_object_key(prefix: &str, name: &str) -> String {
+    let prefix = prefix.trim_matches('/');
+
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+impl S3Upload {
+    /// Perform the upload, returning the `s3://` URLs of uploaded files.
+    pub fn upload(&self, logger: &slog::Logger) -> Result<Vec<String>> {
+        let mut urls = vec![];
+
+        for path in &self.files {
+            let url = format!(
+                "s3://{}/{}",
+                self.bucket,
+                s3_object_key(&self.prefix, &file_name(path)?)
+            );
+
+            warn!(logger, "uploading {} to {}", path.display(), url);
+
+            let mut command = std::process::Command::new("aws");
+            command.arg("s3").arg("cp").arg(path).arg(&url);
+            if let Some(region) = &self.region {
+                command.arg("--region").arg(region);
+            }
+
+            let status = command
+                .status()
+                .context("running aws; is the AWS CLI installed?")?;
+            if !status.success() {
+                return Err(anyhow!("aws s3 cp failed uploading {}", path.display()));
+            }
+
+            urls.push(url);
+        }
+
+        Ok(urls)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_asset_upload_url() -> Result<()> {
+        assert_eq!(
+            github_asset_upload_url(
+                "https://uploads.github.com/repos/o/r/releases/1/assets{?name,label}",
+                "app 1.0.tar.gz"
+            )?
+            .as_str(),
+            "https://uploads.github.com/repos/o/r/releases/1/assets?name=app+1.0.tar.gz"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_s3_object_key() {
+        assert_eq!(s3_object_key("", "app.zip"), "app.zip");
+        assert_eq!(s3_object_key("/", "app.zip"), "app.zip");
+        assert_eq!(
+            s3_object_key("releases/1.0/", "app.zip"),
+            "releases/1.0/app.zip"
+        );
+        assert_eq!(s3_object_key("releases", "app.zip"), "releases/app.zip");
+    }
+}
@@ -0,0 +1,155 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Pluggable signing of files.
+
+[SigningCallback] abstracts over how a file is signed. This allows packaging
+code to sign the files it produces without knowing where signing keys live,
+be it a file, an operating system keychain, or a hardware security module or
+key management service only reachable via an external tool.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    slog::warn,
+    std::{
+        collections::BTreeMap,
+        io::{BufRead, BufReader},
+        path::Path,
+    },
+};
+
+/// Placeholder in [CommandSigner] arguments replaced by the path to sign.
+pub const PATH_PLACEHOLDER: &str = "{path}";
+
+/// Signs files in place.
+pub trait SigningCallback: std::fmt::Debug + Send + Sync {
+    /// Sign the file at the given path, replacing it with the signed file.
+    fn sign(&self, logger: &slog::Logger, path: &Path) -> Result<()>;
+}
+
+/// Signs files by running an external program.
+///
+/// This allows signing with keys held by services Tugger doesn't natively
+/// support, such as cloud key management services, via their signing tools.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandSigner {
+    /// Program to execute.
+    pub program: String,
+
+    /// Arguments to pass to the program.
+    ///
+    /// [PATH_PLACEHOLDER] is replaced by the path of the file to sign.
+    pub args: Vec<String>,
+
+    /// Extra environment variables to define when running the program.
+    pub env: BTreeMap<String, String>,
+}
+
+impl CommandSigner {
+    /// Create a new instance invoking a program.
+    pub fn new(program: impl ToString) -> Self {
+        Self {
+            program: program.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Obtain the arguments to sign a given path with.
+    pub fn resolve_args(&self, path: &Path) -> Vec<String> {
+        let path = path.display().to_string();
+
+        self.args
+            .iter()
+            .map(|arg| arg.replace(PATH_PLACEHOLDER, &path))
+            .collect()
+    }
+}
+
+impl SigningCallback for CommandSigner {
+    fn sign(&self, logger: &slog::Logger, path: &Path) -> Result<()> {
+        let mut command = duct::cmd(&self.program, self.resolve_args(path));
+        for (key, value) in &self.env {
+            command = command.env(key, value);
+        }
+
+        let reader = command
+            .stderr_to_stdout()
+            .reader()
+            .with_context(|| format!("running {}", self.program))?;
+        {
+            let reader = BufReader::new(&reader);
+            for line in reader.lines() {
+                warn!(logger, "{}", line?);
+            }
+        }
+
+        let output = reader
+            .try_wait()?
+            .ok_or_else(|| anyhow!("unable to wait on command"))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "{} failed signing {}",
+                self.program,
+                path.display()
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::testutil::{get_logger, DEFAULT_TEMP_DIR},
+    };
+
+    #[test]
+    fn test_resolve_args() {
+        let mut signer = CommandSigner::new("signer");
+        signer.args = vec![
+            "--key".to_string(),
+            "projects/p/keys/k".to_string(),
+            "{path}".to_string(),
+            "--out={path}".to_string(),
+        ];
+
+        assert_eq!(
+            signer.resolve_args(Path::new("app.exe")),
+            vec!["--key", "projects/p/keys/k", "app.exe", "--out=app.exe"]
+        );
+    }
+
+    #[test]
+    fn test_command_signer() -> Result<()> {
+        if cfg!(target_family = "windows") {
+            eprintln!("skipping test because it requires a POSIX shell");
+            return Ok(());
+        }
+
+        let logger = get_logger()?;
+        let path = DEFAULT_TEMP_DIR.path().join("test_command_signer");
+        std::fs::write(&path, b"data")?;
+
+        let mut signer = CommandSigner::new("sh");
+        signer.args = vec![
+            "-c".to_string(),
+            "printf \"$SIGNATURE\" >> \"$0\"".to_string(),
+            "{path}".to_string(),
+        ];
+        signer
+            .env
+            .insert("SIGNATURE".to_string(), "signed".to_string());
+
+        signer.sign(&logger, &path)?;
+        assert_eq!(std::fs::read(&path)?, b"datasigned");
+
+        signer.args[1] = "exit 1".to_string();
+        assert!(signer.sign(&logger, &path).is_err());
+
+        Ok(())
+    }
+}
@@ -8,6 +8,7 @@ use {
         io::{Read, Seek, Write},
         path::Path,
     },
+    zip::{write::FileOptions, CompressionMethod, ZipWriter},
 };
 
 pub fn extract_zip<R: Read + Seek, P: AsRef<Path>>(reader: R, path: P) -> Result<()> {
@@ -31,3 +32,37 @@ pub fn extract_zip<R: Read + Seek, P: AsRef<Path>>(reader: R, path: P) -> Result
 
     Ok(())
 }
+
+/// Write a zip archive.
+///
+/// `files` are tuples of archive member name, content, and whether the
+/// member is executable. `prefix` is written before the archive, which is
+/// useful for producing self-executing archives. Member offsets are relative
+/// to the start of `writer`, so readers don't need to adjust for `prefix`.
+pub fn write_zip<W: Write + Seek>(
+    mut writer: W,
+    prefix: &[u8],
+    files: impl IntoIterator<Item = (String, Vec<u8>, bool)>,
+    compress: bool,
+) -> Result<()> {
+    writer.write_all(prefix)?;
+
+    let mut zf = ZipWriter::new(writer);
+
+    for (name, data, executable) in files {
+        let options = FileOptions::default()
+            .compression_method(if compress {
+                CompressionMethod::Deflated
+            } else {
+                CompressionMethod::Stored
+            })
+            .unix_permissions(if executable { 0o755 } else { 0o644 });
+
+        zf.start_file(name, options)?;
+        zf.write_all(&data)?;
+    }
+
+    zf.finish()?;
+
+    Ok(())
+}
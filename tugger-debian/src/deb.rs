@@ -15,7 +15,7 @@ use {
         path::Path,
         time::SystemTime,
     },
-    tugger_file_manifest::{FileEntry, FileManifest, FileManifestError},
+    tugger_file_manifest::{source_date_epoch, FileEntry, FileManifest, FileManifestError},
 };
 
 /// Represents an error related to .deb file handling.
@@ -81,6 +81,17 @@ impl DebCompression {
 
     /// Compress input data from a reader.
     pub fn compress(&self, reader: &mut impl Read) -> Result<Vec<u8>, DebError> {
+        self.compress_with_mtime(reader, resolve_mtime(None)?)
+    }
+
+    /// Compress input data from a reader, recording a modified time if the format has one.
+    ///
+    /// `mtime` is seconds since the UNIX epoch.
+    pub fn compress_with_mtime(
+        &self,
+        reader: &mut impl Read,
+        mtime: u64,
+    ) -> Result<Vec<u8>, DebError> {
         let mut buffer = vec![];
 
         match self {
@@ -88,7 +99,9 @@ impl DebCompression {
                 std::io::copy(reader, &mut buffer)?;
             }
             Self::Gzip => {
-                let header = libflate::gzip::HeaderBuilder::new().finish();
+                let header = libflate::gzip::HeaderBuilder::new()
+                    .modification_time(mtime as u32)
+                    .finish();
 
                 let mut encoder = libflate::gzip::Encoder::with_options(
                     &mut buffer,
@@ -113,6 +126,22 @@ impl DebCompression {
     }
 }
 
+/// Resolve the modified time of archive members, as seconds since the UNIX epoch.
+///
+/// An explicit time wins over `SOURCE_DATE_EPOCH`, which wins over the
+/// current time.
+fn resolve_mtime(time: Option<SystemTime>) -> Result<u64, DebError> {
+    let time = match time {
+        Some(time) => time,
+        None => source_date_epoch()?.unwrap_or_else(SystemTime::now),
+    };
+
+    Ok(time
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("times before UNIX epoch not accepted")
+        .as_secs())
+}
+
 /// A builder for a `.deb` package file.
 pub struct DebBuilder<'control> {
     control_builder: ControlTarBuilder<'control>,
@@ -144,12 +173,8 @@ impl<'control> DebBuilder<'control> {
         self
     }
 
-    fn mtime(&self) -> u64 {
-        self.mtime
-            .unwrap_or_else(std::time::SystemTime::now)
-            .duration_since(std::time::UNIX_EPOCH)
-            .expect("times before UNIX epoch not accepted")
-            .as_secs()
+    fn mtime(&self) -> Result<u64, DebError> {
+        resolve_mtime(self.mtime)
     }
 
     /// Set the modified time to use on archive members.
@@ -157,7 +182,8 @@ impl<'control> DebBuilder<'control> {
     /// If this is called, all archive members will use the specified time, helping
     /// to make archive content deterministic.
     ///
-    /// If not called, the current time will be used.
+    /// If not called, the time defined by `SOURCE_DATE_EPOCH` is used if set.
+    /// Otherwise the current time will be used.
     pub fn set_mtime(mut self, time: Option<SystemTime>) -> Self {
         self.mtime = time;
         self.control_builder = self.control_builder.set_mtime(time);
@@ -203,13 +229,14 @@ impl<'control> DebBuilder<'control> {
     ///
     /// This effectively materialized the `.deb` package somewhere.
     pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), DebError> {
+        let mtime = self.mtime()?;
         let mut ar_builder = ar::Builder::new(writer);
 
         // First entry is a debian-binary file with static content.
         let data: &[u8] = b"2.0\n";
         let mut header = ar::Header::new(b"debian-binary".to_vec(), data.len() as _);
         header.set_mode(0o644);
-        header.set_mtime(self.mtime());
+        header.set_mtime(mtime);
         header.set_uid(0);
         header.set_gid(0);
         ar_builder.append(&header, data)?;
@@ -220,32 +247,32 @@ impl<'control> DebBuilder<'control> {
         let control_tar = control_writer.into_inner()?;
         let control_tar = self
             .compression
-            .compress(&mut std::io::Cursor::new(control_tar))?;
+            .compress_with_mtime(&mut std::io::Cursor::new(control_tar), mtime)?;
 
         let mut header = ar::Header::new(
             format!("control.tar{}", self.compression.extension()).into_bytes(),
             control_tar.len() as _,
         );
         header.set_mode(0o644);
-        header.set_mtime(self.mtime());
+        header.set_mtime(mtime);
         header.set_uid(0);
         header.set_gid(0);
         ar_builder.append(&header, &*control_tar)?;
 
         // Third entry is a data.tar with file content.
         let mut data_writer = BufWriter::new(Vec::new());
-        write_deb_tar(&mut data_writer, &self.install_files, self.mtime())?;
+        write_deb_tar(&mut data_writer, &self.install_files, mtime)?;
         let data_tar = data_writer.into_inner()?;
         let data_tar = self
             .compression
-            .compress(&mut std::io::Cursor::new(data_tar))?;
+            .compress_with_mtime(&mut std::io::Cursor::new(data_tar), mtime)?;
 
         let mut header = ar::Header::new(
             format!("data.tar{}", self.compression.extension()).into_bytes(),
             data_tar.len() as _,
         );
         header.set_mode(0o644);
-        header.set_mtime(self.mtime());
+        header.set_mtime(mtime);
         header.set_uid(0);
         header.set_gid(0);
         ar_builder.append(&header, &*data_tar)?;
@@ -394,12 +421,8 @@ impl<'a> ControlTarBuilder<'a> {
         Ok(self)
     }
 
-    fn mtime(&self) -> u64 {
-        self.mtime
-            .unwrap_or_else(std::time::SystemTime::now)
-            .duration_since(std::time::UNIX_EPOCH)
-            .expect("times before UNIX epoch not accepted")
-            .as_secs()
+    fn mtime(&self) -> Result<u64, DebError> {
+        resolve_mtime(self.mtime)
     }
 
     pub fn set_mtime(mut self, time: Option<SystemTime>) -> Self {
@@ -429,7 +452,7 @@ impl<'a> ControlTarBuilder<'a> {
             },
         )?;
 
-        write_deb_tar(writer, &manifest, self.mtime())
+        write_deb_tar(writer, &manifest, self.mtime()?)
     }
 }
 
@@ -453,7 +476,7 @@ pub fn write_deb_tar<W: Write>(
     for directory in files.relative_directories() {
         let mut header = new_tar_header(mtime)?;
         set_header_path(&mut builder, &mut header, &directory, true)?;
-        header.set_mode(0o755);
+        header.set_mode(files.get_mode(&directory).unwrap_or(0o755));
         header.set_size(0);
         header.set_cksum();
         builder.append(&header, &*vec![])?;
@@ -465,12 +488,27 @@ pub fn write_deb_tar<W: Write>(
 
         let mut header = new_tar_header(mtime)?;
         set_header_path(&mut builder, &mut header, rel_path, false)?;
-        header.set_mode(if content.executable { 0o755 } else { 0o644 });
+        header.set_mode(files.get_mode(rel_path).unwrap_or(if content.executable {
+            0o755
+        } else {
+            0o644
+        }));
         header.set_size(data.len() as _);
         header.set_cksum();
         builder.append(&header, &*data)?;
     }
 
+    for (rel_path, target) in files.iter_symlinks() {
+        let mut header = new_tar_header(mtime)?;
+        set_header_path(&mut builder, &mut header, rel_path, false)?;
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_link_name(target)?;
+        header.set_mode(0o777);
+        header.set_size(0);
+        header.set_cksum();
+        builder.append(&header, &*vec![])?;
+    }
+
     builder.finish()?;
 
     Ok(())
@@ -559,6 +597,50 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_write_data_tar_symlink_and_modes() -> Result<()> {
+        let mut manifest = FileManifest::default();
+        manifest.add_file_entry(
+            "usr/lib/libfoo.so.1",
+            FileEntry {
+                data: vec![42].into(),
+                executable: false,
+            },
+        )?;
+        manifest.add_symlink("usr/lib/libfoo.so", "libfoo.so.1")?;
+        manifest.set_mode("usr/lib/libfoo.so.1", 0o640)?;
+        manifest.set_mode("usr/lib", 0o750)?;
+
+        let mut buffer = vec![];
+        write_deb_tar(&mut buffer, &manifest, 2)?;
+
+        let mut archive = tar::Archive::new(std::io::Cursor::new(buffer));
+
+        let mut seen = 0;
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let path = entry.path()?.to_path_buf();
+
+            if path == Path::new("./usr/lib/") {
+                assert_eq!(entry.header().mode()?, 0o750);
+                seen += 1;
+            } else if path == Path::new("./usr/lib/libfoo.so.1") {
+                assert_eq!(entry.header().mode()?, 0o640);
+                seen += 1;
+            } else if path == Path::new("./usr/lib/libfoo.so") {
+                assert_eq!(entry.header().entry_type(), tar::EntryType::Symlink);
+                assert_eq!(
+                    entry.link_name()?.map(|p| p.to_path_buf()),
+                    Some(PathBuf::from("libfoo.so.1"))
+                );
+                seen += 1;
+            }
+        }
+        assert_eq!(seen, 3);
+
+        Ok(())
+    }
+
     #[test]
     fn test_write_data_tar_long_path() -> Result<()> {
         let long_path = PathBuf::from(format!("f{}.txt", "u".repeat(200)));
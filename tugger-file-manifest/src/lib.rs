@@ -8,6 +8,7 @@ use std::{
     ffi::OsStr,
     io::Write,
     path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 #[cfg(unix)]
@@ -37,6 +38,99 @@ pub fn set_executable(_file: &mut std::fs::File) -> Result<(), std::io::Error> {
     Ok(())
 }
 
+/// Set the Unix permissions of a path.
+#[cfg(unix)]
+pub fn set_mode(path: &Path, mode: u32) -> Result<(), std::io::Error> {
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+/// Set the Unix permissions of a path.
+///
+/// This is a no-op on Windows.
+#[cfg(windows)]
+pub fn set_mode(_path: &Path, _mode: u32) -> Result<(), std::io::Error> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, path: &Path) -> Result<(), std::io::Error> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, path: &Path) -> Result<(), std::io::Error> {
+    std::os::windows::fs::symlink_file(target, path)
+}
+
+/// Environment variable defining the timestamp of reproducible builds.
+///
+/// See <https://reproducible-builds.org/specs/source-date-epoch/>.
+pub const SOURCE_DATE_EPOCH_ENV: &str = "SOURCE_DATE_EPOCH";
+
+/// Parse a `SOURCE_DATE_EPOCH` value, which is seconds since the UNIX epoch.
+pub fn parse_source_date_epoch(value: &str) -> Result<SystemTime, std::io::Error> {
+    let seconds = value.trim().parse::<u64>().map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "{} must be a non-negative integer; got {}",
+                SOURCE_DATE_EPOCH_ENV, value
+            ),
+        )
+    })?;
+
+    Ok(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Obtain the time defined by the `SOURCE_DATE_EPOCH` environment variable.
+///
+/// Returns `None` if the variable isn't set or is empty. Packaging
+/// primitives use this time, unless given an explicit time, so build
+/// output is reproducible.
+pub fn source_date_epoch() -> Result<Option<SystemTime>, std::io::Error> {
+    match std::env::var(SOURCE_DATE_EPOCH_ENV) {
+        Ok(value) if !value.is_empty() => Ok(Some(parse_source_date_epoch(&value)?)),
+        _ => Ok(None),
+    }
+}
+
+/// Render a template by replacing `{{name}}` with the value of a variable.
+///
+/// Whitespace around the variable name is ignored. Placeholders referring
+/// to undefined variables are an error.
+pub fn render_template(
+    template: &str,
+    variables: &BTreeMap<String, String>,
+) -> Result<String, FileManifestError> {
+    let mut res = String::with_capacity(template.len());
+    let mut remaining = template;
+
+    while let Some(start) = remaining.find("{{") {
+        res.push_str(&remaining[0..start]);
+
+        let after = &remaining[start + 2..];
+        let end = match after.find("}}") {
+            Some(end) => end,
+            None => {
+                remaining = &remaining[start..];
+                break;
+            }
+        };
+
+        let name = after[0..end].trim();
+        let value = variables
+            .get(name)
+            .ok_or_else(|| FileManifestError::UndefinedTemplateVariable(name.to_string()))?;
+        res.push_str(value);
+
+        remaining = &after[end + 2..];
+    }
+
+    res.push_str(remaining);
+
+    Ok(res)
+}
+
 /// Represents an abstract location for binary data.
 ///
 /// Data can be backed by the filesystem or in memory.
@@ -179,6 +273,9 @@ pub enum FileManifestError {
     IllegalRelativePath(String),
     IllegalAbsolutePath(String),
     NoParentDirectory,
+    PathNotInManifest(String),
+    TemplateNotUtf8(String),
+    UndefinedTemplateVariable(String),
     IoError(std::io::Error),
     StripPrefix(std::path::StripPrefixError),
 }
@@ -193,6 +290,15 @@ impl std::fmt::Display for FileManifestError {
                 f.write_str(&format!("path cannot be absolute: {}", path))
             }
             Self::NoParentDirectory => f.write_str("could not resolve parent directory"),
+            Self::PathNotInManifest(path) => {
+                f.write_str(&format!("path not present in manifest: {}", path))
+            }
+            Self::TemplateNotUtf8(path) => {
+                f.write_str(&format!("template is not valid UTF-8: {}", path))
+            }
+            Self::UndefinedTemplateVariable(name) => {
+                f.write_str(&format!("undefined template variable: {}", name))
+            }
             Self::IoError(inner) => inner.fmt(f),
             Self::StripPrefix(inner) => inner.fmt(f),
         }
@@ -217,15 +323,43 @@ impl From<std::path::StripPrefixError> for FileManifestError {
 ///
 /// Files are keyed by their path. The file content is abstract and can be
 /// backed by multiple sources.
+///
+/// Besides files, a manifest can hold symlinks, explicit permissions of
+/// files and directories, and files rendered from templates.
+///
+/// Templates are rendered and the permissions of files are reflected in
+/// their executable flag when entries are added, so file entries obtained
+/// from the manifest are final. Consumers that don't materialize the
+/// manifest must handle symlinks and exact permissions themselves.
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct FileManifest {
     files: BTreeMap<PathBuf, FileEntry>,
+    symlinks: BTreeMap<PathBuf, PathBuf>,
+    modes: BTreeMap<PathBuf, u32>,
+    templates: BTreeSet<PathBuf>,
+    template_variables: BTreeMap<String, String>,
+}
+
+/// Ensure a path is suitable for storing in a manifest.
+fn validate_path(path: &Path) -> Result<(), FileManifestError> {
+    let path_s = path.display().to_string();
+
+    if path_s.contains("..") {
+        return Err(FileManifestError::IllegalRelativePath(path_s));
+    }
+
+    // is_absolute() on Windows doesn't check for leading /.
+    if path_s.starts_with('/') || path.is_absolute() {
+        return Err(FileManifestError::IllegalAbsolutePath(path_s));
+    }
+
+    Ok(())
 }
 
 impl FileManifest {
     /// Whether the instance has any files entries.
     pub fn is_empty(&self) -> bool {
-        self.files.is_empty()
+        self.files.is_empty() && self.symlinks.is_empty()
     }
 
     /// Add a file on the filesystem to the manifest.
@@ -245,8 +379,7 @@ impl FileManifest {
 
         let add_path = path.strip_prefix(strip_prefix)?;
 
-        self.files
-            .insert(add_path.to_path_buf(), FileEntry::try_from(path)?);
+        self.insert_file(add_path.to_path_buf(), FileEntry::try_from(path)?);
 
         Ok(())
     }
@@ -267,7 +400,7 @@ impl FileManifest {
         let add_path = path.strip_prefix(strip_prefix)?;
 
         let entry = FileEntry::try_from(path)?.to_memory()?;
-        self.files.insert(add_path.to_path_buf(), entry);
+        self.insert_file(add_path.to_path_buf(), entry);
 
         Ok(())
     }
@@ -281,22 +414,105 @@ impl FileManifest {
         entry: impl Into<FileEntry>,
     ) -> Result<(), FileManifestError> {
         let path = path.as_ref();
-        let path_s = path.display().to_string();
+        validate_path(path)?;
 
-        if path_s.contains("..") {
-            return Err(FileManifestError::IllegalRelativePath(path_s));
-        }
+        self.insert_file(path.to_path_buf(), entry.into());
 
-        // is_absolute() on Windows doesn't check for leading /.
-        if path_s.starts_with('/') || path.is_absolute() {
-            return Err(FileManifestError::IllegalAbsolutePath(path_s));
+        Ok(())
+    }
+
+    /// Add a `FileEntry` that is a template to this manifest.
+    ///
+    /// The file content must be UTF-8. `{{name}}` placeholders in it are
+    /// replaced with the values of template variables, which must be defined
+    /// before the template is added. The rendered content is stored in memory.
+    pub fn add_template_entry(
+        &mut self,
+        path: impl AsRef<Path>,
+        entry: impl Into<FileEntry>,
+    ) -> Result<(), FileManifestError> {
+        let path = path.as_ref();
+        validate_path(path)?;
+
+        let entry = entry.into();
+        let template = String::from_utf8(entry.data.resolve()?)
+            .map_err(|_| FileManifestError::TemplateNotUtf8(path.display().to_string()))?;
+        let data = render_template(&template, &self.template_variables)?;
+
+        self.insert_file(
+            path.to_path_buf(),
+            FileEntry {
+                data: data.into_bytes().into(),
+                executable: entry.executable,
+            },
+        );
+        self.templates.insert(path.to_path_buf());
+
+        Ok(())
+    }
+
+    /// Add a symlink to this manifest.
+    ///
+    /// `path` is the path of the symlink in the manifest and `target` is the
+    /// path it points to. Any file at `path` is replaced.
+    pub fn add_symlink(
+        &mut self,
+        path: impl AsRef<Path>,
+        target: impl AsRef<Path>,
+    ) -> Result<(), FileManifestError> {
+        let path = path.as_ref();
+        validate_path(path)?;
+
+        self.files.remove(path);
+        self.templates.remove(path);
+        self.symlinks
+            .insert(path.to_path_buf(), target.as_ref().to_path_buf());
+
+        Ok(())
+    }
+
+    /// Set the Unix permissions of a file or directory in this manifest.
+    ///
+    /// Permissions are applied when the manifest is materialized and are
+    /// ignored on Windows. The executable flag of a file entry is updated to
+    /// match the permissions, including for entries later added at the path.
+    pub fn set_mode(&mut self, path: impl AsRef<Path>, mode: u32) -> Result<(), FileManifestError> {
+        let path = path.as_ref();
+
+        if !self.files.contains_key(path) && !self.relative_directories().iter().any(|p| p == path)
+        {
+            return Err(FileManifestError::PathNotInManifest(
+                path.display().to_string(),
+            ));
         }
 
-        self.files.insert(path.to_path_buf(), entry.into());
+        self.modes.insert(path.to_path_buf(), mode);
+
+        if let Some(entry) = self.files.get_mut(path) {
+            entry.executable = mode & 0o111 != 0;
+        }
 
         Ok(())
     }
 
+    /// Define the value of a variable used when rendering templates.
+    ///
+    /// Only templates added after the variable is defined use its value.
+    pub fn set_template_variable(&mut self, name: impl ToString, value: impl ToString) {
+        self.template_variables
+            .insert(name.to_string(), value.to_string());
+    }
+
+    fn insert_file(&mut self, path: PathBuf, mut entry: FileEntry) {
+        if let Some(mode) = self.modes.get(&path) {
+            entry.executable = mode & 0o111 != 0;
+        }
+
+        self.symlinks.remove(&path);
+        self.templates.remove(&path);
+        self.files.insert(path, entry);
+    }
+
     /// Add an iterable of `File` to this manifest.
     pub fn add_files(
         &mut self,
@@ -315,8 +531,25 @@ impl FileManifest {
     /// preserving paths exactly. If this manifest already has an entry for a given
     /// path, it will be overwritten by an entry in the other manifest.
     pub fn add_manifest(&mut self, other: &Self) -> Result<(), FileManifestError> {
+        for (key, target) in &other.symlinks {
+            self.add_symlink(key, target)?;
+        }
+
+        for (key, mode) in &other.modes {
+            self.modes.insert(key.clone(), *mode);
+        }
+
+        // Templates in the other manifest are already rendered.
         for (key, value) in &other.files {
             self.add_file_entry(key, value.clone())?;
+
+            if other.templates.contains(key) {
+                self.templates.insert(key.clone());
+            }
+        }
+
+        for (name, value) in &other.template_variables {
+            self.template_variables.insert(name.clone(), value.clone());
         }
 
         Ok(())
@@ -328,7 +561,7 @@ impl FileManifest {
     pub fn relative_directories(&self) -> Vec<PathBuf> {
         let mut dirs = BTreeSet::new();
 
-        for p in self.files.keys() {
+        for p in self.files.keys().chain(self.symlinks.keys()) {
             let mut ans = p.ancestors();
             ans.next();
 
@@ -367,8 +600,28 @@ impl FileManifest {
         self.files.get(path.as_ref())
     }
 
+    /// Obtain the target of a symlink at a given path.
+    pub fn get_symlink(&self, path: impl AsRef<Path>) -> Option<&Path> {
+        self.symlinks.get(path.as_ref()).map(|p| p.as_path())
+    }
+
+    /// Obtain the explicit Unix permissions of a file or directory.
+    pub fn get_mode(&self, path: impl AsRef<Path>) -> Option<u32> {
+        self.modes.get(path.as_ref()).copied()
+    }
+
+    /// Whether the file at a given path is a template.
+    pub fn is_template(&self, path: impl AsRef<Path>) -> bool {
+        self.templates.contains(path.as_ref())
+    }
+
+    /// Obtain an iterator over symlink paths and their targets.
+    pub fn iter_symlinks(&self) -> std::collections::btree_map::Iter<'_, PathBuf, PathBuf> {
+        self.symlinks.iter()
+    }
+
     /// Obtain an iterator over paths and file entries in this manifest.
-    pub fn iter_entries(&self) -> std::collections::btree_map::Iter<PathBuf, FileEntry> {
+    pub fn iter_entries(&self) -> std::collections::btree_map::Iter<'_, PathBuf, FileEntry> {
         self.files.iter()
     }
 
@@ -387,16 +640,7 @@ impl FileManifest {
         let mut res = BTreeMap::new();
 
         for (path, content) in &self.files {
-            let parent = match path.parent() {
-                Some(p) => {
-                    if p == Path::new("") {
-                        None
-                    } else {
-                        Some(p)
-                    }
-                }
-                None => None,
-            };
+            let parent = path.parent().filter(|p| *p != Path::new(""));
             let filename = path.file_name().unwrap();
 
             let entry = res.entry(parent).or_insert_with(BTreeMap::new);
@@ -419,7 +663,9 @@ impl FileManifest {
 
     /// Write files in this manifest to the specified path.
     ///
-    /// Existing files will be replaced if they exist.
+    /// Existing files will be replaced if they exist. Symlinks are created
+    /// and explicit permissions of directories are applied after all files
+    /// are written.
     pub fn materialize_files(
         &self,
         dest: impl AsRef<Path>,
@@ -440,9 +686,34 @@ impl FileManifest {
             if v.executable {
                 set_executable(&mut fh)?;
             }
+            if let Some(mode) = self.modes.get(k) {
+                set_mode(&dest_path, *mode)?;
+            }
             dest_paths.push(dest_path)
         }
 
+        for (k, target) in self.iter_symlinks() {
+            let dest_path = dest.join(k);
+            let parent = dest_path
+                .parent()
+                .ok_or(FileManifestError::NoParentDirectory)?;
+
+            std::fs::create_dir_all(parent)?;
+            if std::fs::symlink_metadata(&dest_path).is_ok() {
+                std::fs::remove_file(&dest_path)?;
+            }
+            create_symlink(target, &dest_path)?;
+            dest_paths.push(dest_path);
+        }
+
+        // Apply directory permissions deepest first so restrictive modes
+        // don't prevent changing the permissions of children.
+        for (k, mode) in self.modes.iter().rev() {
+            if !self.files.contains_key(k) {
+                set_mode(&dest.join(k), *mode)?;
+            }
+        }
+
         Ok(dest_paths)
     }
 
@@ -539,6 +810,178 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_add_symlink() -> Result<(), FileManifestError> {
+        let mut m = FileManifest::default();
+        let f = FileEntry {
+            data: FileData::from(vec![42]),
+            executable: false,
+        };
+
+        m.add_file_entry(Path::new("bin/foo"), f)?;
+        m.add_symlink(Path::new("bin/foo"), Path::new("foo-1.0"))?;
+        m.add_symlink(Path::new("lib/libfoo.so"), Path::new("libfoo.so.1"))?;
+
+        assert_eq!(m.iter_files().count(), 0);
+        assert_eq!(m.get_symlink("bin/foo"), Some(Path::new("foo-1.0")));
+        assert_eq!(
+            m.relative_directories(),
+            vec![PathBuf::from("bin"), PathBuf::from("lib")]
+        );
+
+        let res = m.add_symlink(Path::new("../foo"), Path::new("foo"));
+        assert!(matches!(
+            res,
+            Err(FileManifestError::IllegalRelativePath(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_mode() -> Result<(), FileManifestError> {
+        let mut m = FileManifest::default();
+        let f = FileEntry {
+            data: FileData::from(vec![42]),
+            executable: false,
+        };
+
+        m.add_file_entry(Path::new("dir0/foo"), f)?;
+        m.set_mode("dir0/foo", 0o755)?;
+        m.set_mode("dir0", 0o700)?;
+
+        assert_eq!(m.get_mode("dir0/foo"), Some(0o755));
+        assert_eq!(m.get_mode("dir0"), Some(0o700));
+        assert!(m.get("dir0/foo").unwrap().executable);
+
+        // The mode also applies to entries replacing the file.
+        m.set_mode("dir0/foo", 0o644)?;
+        m.add_file_entry(
+            Path::new("dir0/foo"),
+            FileEntry {
+                data: FileData::from(vec![42]),
+                executable: true,
+            },
+        )?;
+        assert!(!m.get("dir0/foo").unwrap().executable);
+
+        let res = m.set_mode("missing", 0o644);
+        assert!(matches!(res, Err(FileManifestError::PathNotInManifest(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_template() -> Result<(), FileManifestError> {
+        let mut variables = BTreeMap::new();
+        variables.insert("name".to_string(), "myapp".to_string());
+        variables.insert("version".to_string(), "1.0".to_string());
+
+        assert_eq!(render_template("no variables", &variables)?, "no variables");
+        assert_eq!(
+            render_template("{{name}} {{ version }}", &variables)?,
+            "myapp 1.0"
+        );
+        assert_eq!(
+            render_template("unterminated {{name", &variables)?,
+            "unterminated {{name"
+        );
+
+        let res = render_template("{{missing}}", &variables);
+        assert!(matches!(
+            res,
+            Err(FileManifestError::UndefinedTemplateVariable(name)) if name == "missing"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_template_entry() -> Result<(), FileManifestError> {
+        let mut m = FileManifest::default();
+        let template = FileEntry {
+            data: b"name={{name}}".to_vec().into(),
+            executable: false,
+        };
+
+        let res = m.add_template_entry(Path::new("app.conf"), template.clone());
+        assert!(matches!(
+            res,
+            Err(FileManifestError::UndefinedTemplateVariable(_))
+        ));
+
+        m.set_template_variable("name", "myapp");
+        m.add_template_entry(Path::new("app.conf"), template)?;
+        assert!(m.is_template("app.conf"));
+
+        // Entries are rendered, so consumers not materializing see final content.
+        let entries = m.iter_entries().collect::<Vec<_>>();
+        assert_eq!(entries[0].1.data.resolve()?, b"name=myapp");
+
+        // Merging doesn't render the content again.
+        let mut other = FileManifest::default();
+        other.set_template_variable("name", "{{name}}");
+        m.set_template_variable("name", "{{name}}");
+        other.add_template_entry(
+            Path::new("other.conf"),
+            FileEntry {
+                data: b"{{name}}".to_vec().into(),
+                executable: false,
+            },
+        )?;
+        m.add_manifest(&other)?;
+        assert_eq!(m.get("other.conf").unwrap().data.resolve()?, b"{{name}}");
+        assert!(m.is_template("other.conf"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_materialize() -> Result<(), FileManifestError> {
+        let dest = std::env::temp_dir().join(format!(
+            "tugger-file-manifest-test-materialize-{}",
+            std::process::id()
+        ));
+
+        let mut m = FileManifest::default();
+        m.add_file_entry(
+            Path::new("bin/app"),
+            FileEntry {
+                data: b"binary".to_vec().into(),
+                executable: true,
+            },
+        )?;
+        m.set_template_variable("name", "app");
+        m.add_template_entry(
+            Path::new("bin/launch.sh"),
+            FileEntry {
+                data: b"exec {{ name }}".to_vec().into(),
+                executable: true,
+            },
+        )?;
+        m.add_symlink(Path::new("app"), Path::new("bin/app"))?;
+        m.set_mode("bin/launch.sh", 0o750)?;
+
+        let paths = m.materialize_files_with_replace(&dest)?;
+        assert_eq!(paths.len(), 3);
+        assert_eq!(std::fs::read(dest.join("bin/launch.sh"))?, b"exec app");
+        assert_eq!(std::fs::read(dest.join("app"))?, b"binary");
+
+        #[cfg(unix)]
+        {
+            let metadata = std::fs::metadata(dest.join("bin/launch.sh"))?;
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o750);
+            assert_eq!(
+                std::fs::read_link(dest.join("app"))?,
+                PathBuf::from("bin/app")
+            );
+        }
+
+        std::fs::remove_dir_all(&dest)?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_relative_directories() -> Result<(), FileManifestError> {
         let mut m = FileManifest::default();
@@ -653,4 +1096,15 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_source_date_epoch() {
+        assert_eq!(parse_source_date_epoch("0").unwrap(), UNIX_EPOCH);
+        assert_eq!(
+            parse_source_date_epoch("1600000000\n").unwrap(),
+            UNIX_EPOCH + Duration::from_secs(1600000000)
+        );
+        assert!(parse_source_date_epoch("-1").is_err());
+        assert!(parse_source_date_epoch("yesterday").is_err());
+    }
 }
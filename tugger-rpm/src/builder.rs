@@ -4,13 +4,163 @@
 
 use {
     anyhow::{anyhow, Context, Result},
-    std::path::{Path, PathBuf},
-    tugger_file_manifest::FileManifest,
+    sha2::Digest,
+    std::{
+        convert::TryFrom,
+        path::{Path, PathBuf},
+        time::{SystemTime, UNIX_EPOCH},
+    },
+    tugger_file_manifest::{source_date_epoch, FileManifest},
 };
 
 #[cfg(target_family = "unix")]
 use rpm::{RPMFileOptions, RPMPackage};
 
+/// Magic bytes at the start of RPM files.
+const LEAD_MAGIC: [u8; 4] = [0xed, 0xab, 0xee, 0xdb];
+
+/// Size of the lead preceding the signature header.
+const LEAD_SIZE: usize = 96;
+
+/// Magic bytes at the start of header structures.
+const HEADER_MAGIC: [u8; 4] = [0x8e, 0xad, 0xe8, 0x01];
+
+const RPMTAG_BUILDTIME: u32 = 1006;
+const RPMSIGTAG_SHA1: u32 = 269;
+const RPMSIGTAG_SHA256: u32 = 273;
+const RPMSIGTAG_MD5: u32 = 1004;
+
+/// Signature tags holding signatures made with a private key.
+const RPMSIGTAG_SIGNATURES: &[u32] = &[267, 268, 1002, 1005];
+
+/// An entry in the index of a header structure.
+struct IndexEntry {
+    tag: u32,
+    /// Offset of the entry's data in the RPM.
+    offset: usize,
+}
+
+/// A header structure in an RPM.
+struct Header {
+    /// Offset of the start of the header in the RPM.
+    start: usize,
+    /// Offset of the end of the header's data store in the RPM.
+    end: usize,
+    entries: Vec<IndexEntry>,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let mut value = [0u8; 4];
+    value.copy_from_slice(
+        data.get(offset..offset + 4)
+            .ok_or_else(|| anyhow!("RPM is truncated"))?,
+    );
+
+    Ok(u32::from_be_bytes(value))
+}
+
+/// Parse the header structure starting at an offset in an RPM.
+fn parse_header(data: &[u8], start: usize) -> Result<Header> {
+    if data.get(start..start + 4) != Some(&HEADER_MAGIC[..]) {
+        return Err(anyhow!("RPM header not found at offset {}", start));
+    }
+
+    let index_count = read_u32(data, start + 8)? as usize;
+    let store_size = read_u32(data, start + 12)? as usize;
+
+    let store = start + 16 + 16 * index_count;
+    let end = store + store_size;
+
+    if end > data.len() {
+        return Err(anyhow!("RPM is truncated"));
+    }
+
+    let entries = (0..index_count)
+        .map(|i| {
+            let entry = start + 16 + 16 * i;
+
+            Ok(IndexEntry {
+                tag: read_u32(data, entry)?,
+                offset: store + read_u32(data, entry + 8)? as usize,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Header {
+        start,
+        end,
+        entries,
+    })
+}
+
+/// Offset of the header following the signature header.
+fn header_start(signature: &Header) -> usize {
+    // The signature header is padded to a multiple of 8 bytes.
+    signature.end + (8 - signature.end % 8) % 8
+}
+
+/// Replace bytes of an entry in a header structure.
+fn write_entry(data: &mut [u8], header: &Header, entry: &IndexEntry, value: &[u8]) -> Result<()> {
+    if entry.offset + value.len() > header.end {
+        return Err(anyhow!("RPM header entry {} is truncated", entry.tag));
+    }
+
+    data[entry.offset..entry.offset + value.len()].copy_from_slice(value);
+
+    Ok(())
+}
+
+/// Replace the build time recorded in an RPM.
+///
+/// The digests in the signature header are updated to match. RPMs signed
+/// with a private key are rejected, as their signatures can't be updated.
+fn set_build_time(data: &mut [u8], time: u32) -> Result<()> {
+    if data.get(0..4) != Some(&LEAD_MAGIC[..]) {
+        return Err(anyhow!("data is not an RPM"));
+    }
+
+    let signature = parse_header(data, LEAD_SIZE)?;
+    let header = parse_header(data, header_start(&signature))?;
+
+    if signature
+        .entries
+        .iter()
+        .any(|entry| RPMSIGTAG_SIGNATURES.contains(&entry.tag))
+    {
+        return Err(anyhow!("cannot change the build time of a signed RPM"));
+    }
+
+    let entry = header
+        .entries
+        .iter()
+        .find(|entry| entry.tag == RPMTAG_BUILDTIME)
+        .ok_or_else(|| anyhow!("RPM header does not record a build time"))?;
+    write_entry(data, &header, entry, &time.to_be_bytes())?;
+
+    // Digests are recorded as NUL terminated hex strings.
+    let sha1 = format!(
+        "{}\0",
+        sha1::Sha1::from(&data[header.start..header.end]).digest()
+    );
+    let sha256 = format!(
+        "{}\0",
+        hex::encode(sha2::Sha256::digest(&data[header.start..header.end]))
+    );
+    // The MD5 digest covers the header and the payload.
+    let md5 = md5::compute(&data[header.start..]);
+
+    for entry in &signature.entries {
+        match entry.tag {
+            RPMSIGTAG_SHA1 => write_entry(data, &signature, entry, sha1.as_bytes())?,
+            RPMSIGTAG_SHA256 => write_entry(data, &signature, entry, sha256.as_bytes())?,
+            RPMSIGTAG_MD5 => write_entry(data, &signature, entry, &md5.0)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
 /// Create RPMs.
 ///
 /// This is a thin wrapper around rpm::RPMBuilder which provides some
@@ -21,6 +171,7 @@ pub struct RpmBuilder {
 
     build_path: PathBuf,
     files: FileManifest,
+    mtime: Option<SystemTime>,
 }
 
 impl AsMut<rpm::RPMBuilder> for RpmBuilder {
@@ -45,6 +196,25 @@ impl RpmBuilder {
             inner,
             build_path: build_path.as_ref().to_path_buf(),
             files: FileManifest::default(),
+            mtime: None,
+        }
+    }
+
+    /// Set the modified time of files in the package and its build time.
+    ///
+    /// If not set, the `SOURCE_DATE_EPOCH` environment variable is used, if
+    /// defined. Otherwise files have the time they were written to the build
+    /// path and the build time is the time the package is built.
+    pub fn set_mtime(mut self, time: Option<SystemTime>) -> Self {
+        self.mtime = time;
+        self
+    }
+
+    /// Resolve the time to record for files and the build.
+    fn resolve_mtime(&self) -> Result<Option<SystemTime>> {
+        match self.mtime {
+            Some(time) => Ok(Some(time)),
+            None => source_date_epoch().context("resolving SOURCE_DATE_EPOCH"),
         }
     }
 
@@ -54,18 +224,36 @@ impl RpmBuilder {
             .materialize_files(&self.build_path)
             .context("writing RPM data files")?;
 
+        // The RPM builder only registers regular files.
+        if let Some((path, _)) = self.files.iter_symlinks().next() {
+            return Err(anyhow!(
+                "symlinks are not supported in RPMs: {}",
+                path.display()
+            ));
+        }
+
+        let mtime = self
+            .resolve_mtime()?
+            .map(filetime::FileTime::from_system_time);
+
         for (rel_path, content) in self.files.iter_entries() {
             let real_path = self.build_path.join(rel_path);
 
             let mut options = RPMFileOptions::new(rel_path.display().to_string());
 
-            if content.executable {
+            if let Some(mode) = self.files.get_mode(rel_path) {
+                options = options.mode((0o100_000 | (mode & 0o7777)) as u16);
+            } else if content.executable {
                 options = options.mode(0o100_775);
             }
 
+            // RPMBuilder records the modified time of the file on disk.
+            if let Some(mtime) = mtime {
+                filetime::set_file_mtime(&real_path, mtime)
+                    .with_context(|| format!("setting mtime of {}", real_path.display()))?;
+            }
+
             // TODO support additional attributes, such as owner/group.
-            // TODO make deterministic by modifying upstream to allow control
-            // over what add_data() does.
 
             self.inner = self
                 .inner
@@ -79,6 +267,8 @@ impl RpmBuilder {
 
     /// Build the RPM, consuming self.
     pub fn build(mut self) -> Result<RPMPackage> {
+        let build_time = self.resolve_mtime()?;
+
         self = self
             .populate_files()
             .context("populating files with builder")?;
@@ -89,7 +279,26 @@ impl RpmBuilder {
             .map_err(|e| anyhow!("error building RPM: {}", e))
             .context("building RPM")?;
 
-        Ok(package)
+        if let Some(time) = build_time {
+            let time = time
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .and_then(|duration| u32::try_from(duration.as_secs()).ok())
+                .ok_or_else(|| anyhow!("build time cannot be recorded in an RPM"))?;
+
+            // RPMBuilder records the current time, so it is replaced.
+            let mut data = vec![];
+            package
+                .write(&mut data)
+                .map_err(|e| anyhow!("error writing RPM: {}", e))?;
+
+            set_build_time(&mut data, time).context("setting RPM build time")?;
+
+            RPMPackage::parse(&mut std::io::Cursor::new(data))
+                .map_err(|e| anyhow!("error parsing RPM: {}", e))
+        } else {
+            Ok(package)
+        }
     }
 
     /// Build the RPM, writing it to a filesystem path, consuming self.
@@ -112,3 +321,103 @@ impl RpmBuilder {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serialize a header structure from tags and their data.
+    fn header(entries: &[(u32, u32, &[u8])]) -> Vec<u8> {
+        let mut index = vec![];
+        let mut store = vec![];
+
+        for (tag, kind, value) in entries {
+            index.extend_from_slice(&tag.to_be_bytes());
+            index.extend_from_slice(&kind.to_be_bytes());
+            index.extend_from_slice(&(store.len() as u32).to_be_bytes());
+            index.extend_from_slice(&1u32.to_be_bytes());
+            store.extend_from_slice(value);
+        }
+
+        let mut res = HEADER_MAGIC.to_vec();
+        res.extend_from_slice(&[0; 4]);
+        res.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        res.extend_from_slice(&(store.len() as u32).to_be_bytes());
+        res.extend(index);
+        res.extend(store);
+
+        res
+    }
+
+    fn rpm(signature_tags: &[u32]) -> Vec<u8> {
+        let mut data = LEAD_MAGIC.to_vec();
+        data.resize(LEAD_SIZE, 0);
+
+        let sha1 = [&[b'0'; 40][..], b"\0"].concat();
+        let sha256 = [&[b'0'; 64][..], b"\0"].concat();
+        let signature = signature_tags
+            .iter()
+            .map(|tag| match *tag {
+                RPMSIGTAG_SHA1 => (*tag, 6, &sha1[..]),
+                RPMSIGTAG_SHA256 => (*tag, 6, &sha256[..]),
+                _ => (*tag, 7, &[0u8; 16][..]),
+            })
+            .collect::<Vec<_>>();
+        data.extend(header(&signature));
+        data.resize(data.len() + (8 - data.len() % 8) % 8, 0);
+
+        data.extend(header(&[
+            (1000, 6, b"name\0"),
+            (RPMTAG_BUILDTIME, 4, &1_600_000_000u32.to_be_bytes()),
+        ]));
+        data.extend_from_slice(b"payload");
+
+        data
+    }
+
+    #[test]
+    fn test_set_build_time() -> Result<()> {
+        let mut data = rpm(&[RPMSIGTAG_SHA1, RPMSIGTAG_SHA256, RPMSIGTAG_MD5]);
+        set_build_time(&mut data, 1_000_000)?;
+
+        let signature = parse_header(&data, LEAD_SIZE)?;
+        let header = parse_header(&data, header_start(&signature))?;
+        let entry_data = |header: &Header, tag: u32, len: usize| {
+            let offset = header
+                .entries
+                .iter()
+                .find(|entry| entry.tag == tag)
+                .unwrap()
+                .offset;
+            data[offset..offset + len].to_vec()
+        };
+
+        assert_eq!(
+            entry_data(&header, RPMTAG_BUILDTIME, 4),
+            1_000_000u32.to_be_bytes()
+        );
+
+        let header_data = &data[header.start..header.end];
+        assert_eq!(
+            entry_data(&signature, RPMSIGTAG_SHA1, 41),
+            format!("{}\0", sha1::Sha1::from(header_data).digest()).into_bytes()
+        );
+        assert_eq!(
+            entry_data(&signature, RPMSIGTAG_SHA256, 65),
+            format!("{}\0", hex::encode(sha2::Sha256::digest(header_data))).into_bytes()
+        );
+        assert_eq!(
+            entry_data(&signature, RPMSIGTAG_MD5, 16),
+            md5::compute(&data[header.start..]).0
+        );
+        assert!(data.ends_with(b"payload"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_build_time_signed() {
+        let mut data = rpm(&[RPMSIGTAG_SHA1, 268]);
+        assert!(set_build_time(&mut data, 1_000_000).is_err());
+    }
+}
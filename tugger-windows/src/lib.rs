@@ -12,10 +12,11 @@ mod signing;
 pub use signing::{
     certificate_to_pfx, create_self_signed_code_signing_certificate,
     create_self_signed_code_signing_certificate_params, is_file_signable,
-    is_signable_binary_header, FileBasedX509SigningCertificate, X509SigningCertificate,
+    is_signable_binary_header, FileBasedX509SigningCertificate, ProviderX509SigningCertificate,
+    StoreX509SigningCertificate, X509SigningCertificate,
 };
 mod signtool;
-pub use signtool::{find_signtool, SigntoolSign};
+pub use signtool::{find_signtool, SigntoolSign, TimestampServer};
 mod util;
 mod vc_redistributable;
 pub use vc_redistributable::{
@@ -46,6 +46,96 @@ impl FileBasedX509SigningCertificate {
     }
 }
 
+/// Represents an x509 signing certificate in a Windows certificate store.
+///
+/// This allows signing with certificates whose private key can't be exported,
+/// such as certificates on smart cards.
+#[derive(Clone, Debug)]
+pub struct StoreX509SigningCertificate {
+    /// SHA-1 thumbprint of the certificate, as hex.
+    sha1_thumbprint: String,
+    /// Name of the store to search.
+    store: String,
+    /// Whether to search the machine store instead of the user store.
+    machine_store: bool,
+}
+
+impl StoreX509SigningCertificate {
+    /// Construct an instance searching the `My` user store for a thumbprint.
+    pub fn new(sha1_thumbprint: impl ToString) -> Self {
+        Self {
+            sha1_thumbprint: sha1_thumbprint.to_string(),
+            store: "My".to_string(),
+            machine_store: false,
+        }
+    }
+
+    pub fn sha1_thumbprint(&self) -> &str {
+        &self.sha1_thumbprint
+    }
+
+    pub fn store(&self) -> &str {
+        &self.store
+    }
+
+    pub fn set_store(&mut self, store: impl ToString) {
+        self.store = store.to_string();
+    }
+
+    pub fn machine_store(&self) -> bool {
+        self.machine_store
+    }
+
+    pub fn set_machine_store(&mut self, machine_store: bool) {
+        self.machine_store = machine_store;
+    }
+}
+
+/// Represents an x509 signing certificate whose private key is held by a
+/// cryptographic provider.
+///
+/// Hardware security modules and cloud key management services expose keys
+/// to Windows tools via cryptographic service providers (CSP) or key storage
+/// providers (KSP). The private key never leaves the provider.
+#[derive(Clone, Debug)]
+pub struct ProviderX509SigningCertificate {
+    /// Path to the public certificate file.
+    certificate_path: PathBuf,
+    /// Name of the cryptographic provider.
+    provider: String,
+    /// Name of the key container holding the private key.
+    key_container: String,
+}
+
+impl ProviderX509SigningCertificate {
+    /// Construct an instance.
+    ///
+    /// No validation is done that the path exists or the provider is installed.
+    pub fn new(
+        certificate_path: impl AsRef<Path>,
+        provider: impl ToString,
+        key_container: impl ToString,
+    ) -> Self {
+        Self {
+            certificate_path: certificate_path.as_ref().to_path_buf(),
+            provider: provider.to_string(),
+            key_container: key_container.to_string(),
+        }
+    }
+
+    pub fn certificate_path(&self) -> &Path {
+        &self.certificate_path
+    }
+
+    pub fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    pub fn key_container(&self) -> &str {
+        &self.key_container
+    }
+}
+
 /// Represents an x509 certificate used to sign binaries on Windows.
 #[derive(Clone, Debug)]
 pub enum X509SigningCertificate {
@@ -57,6 +147,12 @@ pub enum X509SigningCertificate {
 
     /// An x509 certificate specified by its subject name or substring thereof.
     SubjectName(String),
+
+    /// An x509 certificate in a certificate store specified by its thumbprint.
+    Store(StoreX509SigningCertificate),
+
+    /// An x509 certificate whose private key is held by a cryptographic provider.
+    Provider(ProviderX509SigningCertificate),
 }
 
 impl From<FileBasedX509SigningCertificate> for X509SigningCertificate {
@@ -65,6 +161,18 @@ impl From<FileBasedX509SigningCertificate> for X509SigningCertificate {
     }
 }
 
+impl From<StoreX509SigningCertificate> for X509SigningCertificate {
+    fn from(v: StoreX509SigningCertificate) -> Self {
+        Self::Store(v)
+    }
+}
+
+impl From<ProviderX509SigningCertificate> for X509SigningCertificate {
+    fn from(v: ProviderX509SigningCertificate) -> Self {
+        Self::Provider(v)
+    }
+}
+
 /// Create parameters for a self-signed x509 certificate suitable for code signing on Windows.
 ///
 /// The self-signed certificate mimics what the powershell
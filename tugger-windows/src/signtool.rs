@@ -12,6 +12,7 @@ use {
         io::{BufRead, BufReader},
         path::{Path, PathBuf},
     },
+    tugger_common::signing::SigningCallback,
 };
 
 #[cfg(target_family = "windows")]
@@ -148,10 +149,8 @@ impl SigntoolSign {
         self
     }
 
-    /// Run `signtool sign` with requested options.
-    pub fn run(&self, logger: &slog::Logger) -> Result<()> {
-        let signtool = find_signtool().context("locating signtool.exe")?;
-
+    /// Obtain the arguments to `signtool.exe` for the requested options.
+    pub fn args(&self) -> Vec<String> {
         let mut args = vec!["sign".to_string()];
 
         if self.verbose {
@@ -178,6 +177,23 @@ impl SigntoolSign {
                 args.push("/n".to_string());
                 args.push(sn.to_string());
             }
+            X509SigningCertificate::Store(cert) => {
+                args.push("/s".to_string());
+                args.push(cert.store().to_string());
+                if cert.machine_store() {
+                    args.push("/sm".to_string());
+                }
+                args.push("/sha1".to_string());
+                args.push(cert.sha1_thumbprint().to_string());
+            }
+            X509SigningCertificate::Provider(cert) => {
+                args.push("/f".to_string());
+                args.push(cert.certificate_path().display().to_string());
+                args.push("/csp".to_string());
+                args.push(cert.provider().to_string());
+                args.push("/kc".to_string());
+                args.push(cert.key_container().to_string());
+            }
         }
 
         if let Some(description) = &self.description {
@@ -209,7 +225,14 @@ impl SigntoolSign {
 
         args.extend(self.sign_files.iter().map(|p| p.display().to_string()));
 
-        let command = duct::cmd(signtool, args)
+        args
+    }
+
+    /// Run `signtool sign` with requested options.
+    pub fn run(&self, logger: &slog::Logger) -> Result<()> {
+        let signtool = find_signtool().context("locating signtool.exe")?;
+
+        let command = duct::cmd(signtool, self.args())
             .stderr_to_stdout()
             .reader()
             .context("running signtool")?;
@@ -231,13 +254,20 @@ impl SigntoolSign {
     }
 }
 
+impl SigningCallback for SigntoolSign {
+    fn sign(&self, logger: &slog::Logger, path: &Path) -> Result<()> {
+        self.clone_settings().sign_file(path).run(logger)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use {
         super::*,
         crate::{
             certificate_to_pfx, create_self_signed_code_signing_certificate,
-            FileBasedX509SigningCertificate,
+            FileBasedX509SigningCertificate, ProviderX509SigningCertificate,
+            StoreX509SigningCertificate,
         },
         tugger_common::testutil::*,
     };
@@ -256,6 +286,50 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_certificate_args() {
+        let mut cert = StoreX509SigningCertificate::new("0123456789abcdef");
+        cert.set_machine_store(true);
+
+        let mut sign = SigntoolSign::new(cert.into());
+        sign.file_digest_algorithm("sha256").sign_file("app.exe");
+        assert_eq!(
+            sign.args(),
+            vec![
+                "sign",
+                "/s",
+                "My",
+                "/sm",
+                "/sha1",
+                "0123456789abcdef",
+                "/fd",
+                "sha256",
+                "app.exe"
+            ]
+        );
+
+        let cert = ProviderX509SigningCertificate::new(
+            "cert.cer",
+            "Google Cloud KMS Provider",
+            "projects/p/locations/global/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1",
+        );
+        let mut sign = SigntoolSign::new(cert.into());
+        sign.sign_file("app.exe");
+        assert_eq!(
+            sign.args(),
+            vec![
+                "sign",
+                "/f",
+                "cert.cer",
+                "/csp",
+                "Google Cloud KMS Provider",
+                "/kc",
+                "projects/p/locations/global/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1",
+                "app.exe"
+            ]
+        );
+    }
+
     #[test]
     fn test_sign_executable() -> Result<()> {
         if cfg!(target_family = "unix") {
@@ -121,6 +121,13 @@ pub fn write_file_manifest_to_wix<W: Write, P: AsRef<Path>>(
     root_directory_id: &str,
     id_prefix: &str,
 ) -> Result<()> {
+    if let Some((path, _)) = manifest.iter_symlinks().next() {
+        return Err(anyhow!(
+            "symlinks are not supported in Windows installers: {}",
+            path.display()
+        ));
+    }
+
     writer.write(XmlEvent::StartDocument {
         version: XmlVersion::Version10,
         encoding: Some("utf-8"),
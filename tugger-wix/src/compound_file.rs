@@ -0,0 +1,463 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Modify times recorded in compound files, such as MSI installers. */
+
+use {
+    anyhow::{anyhow, Result},
+    std::time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Magic bytes at the start of compound files.
+const SIGNATURE: [u8; 8] = [0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1];
+
+const END_OF_CHAIN: u32 = 0xffff_fffe;
+const NO_STREAM: u32 = 0xffff_ffff;
+/// Sector numbers at or above this value denote special sectors.
+const MAX_SECTOR: u32 = 0xffff_fffa;
+
+const DIRECTORY_ENTRY_SIZE: usize = 128;
+const STORAGE_OBJECT: u8 = 1;
+const ROOT_STORAGE_OBJECT: u8 = 5;
+
+const SUMMARY_INFORMATION_NAME: &str = "\u{5}SummaryInformation";
+
+/// FMTID_SummaryInformation, F29F85E0-4FF9-1068-AB91-08002B27B3D9.
+const FMTID_SUMMARY_INFORMATION: [u8; 16] = [
+    0xe0, 0x85, 0x9f, 0xf2, 0xf9, 0x4f, 0x68, 0x10, 0xab, 0x91, 0x08, 0x00, 0x2b, 0x27, 0xb3, 0xd9,
+];
+
+/// Summary information properties holding times: last printed, created and
+/// last saved.
+const TIME_PROPERTIES: &[u32] = &[11, 12, 13];
+const VT_FILETIME: u16 = 64;
+
+/// 100 nanosecond intervals between 1601-01-01 and the UNIX epoch.
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    let mut value = [0u8; 2];
+    value.copy_from_slice(
+        data.get(offset..offset + 2)
+            .ok_or_else(|| anyhow!("compound file is truncated"))?,
+    );
+
+    Ok(u16::from_le_bytes(value))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let mut value = [0u8; 4];
+    value.copy_from_slice(
+        data.get(offset..offset + 4)
+            .ok_or_else(|| anyhow!("compound file is truncated"))?,
+    );
+
+    Ok(u32::from_le_bytes(value))
+}
+
+/// Whether data is a compound file.
+pub fn is_compound_file(data: &[u8]) -> bool {
+    data.starts_with(&SIGNATURE)
+}
+
+/// Convert a time to a Windows `FILETIME`.
+fn filetime(time: SystemTime) -> Result<u64> {
+    let duration = time
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| anyhow!("time is before the UNIX epoch"))?;
+
+    Ok(
+        FILETIME_UNIX_EPOCH
+            + duration.as_secs() * 10_000_000
+            + duration.subsec_nanos() as u64 / 100,
+    )
+}
+
+/// The structure of a compound file.
+struct CompoundFile {
+    major_version: u16,
+    sector_size: usize,
+    mini_sector_size: usize,
+    mini_stream_cutoff: u64,
+    fat: Vec<u32>,
+    mini_fat: Vec<u32>,
+    /// Offsets of directory entries.
+    entries: Vec<usize>,
+}
+
+impl CompoundFile {
+    fn parse(data: &[u8]) -> Result<Self> {
+        if !is_compound_file(data) {
+            return Err(anyhow!("data is not a compound file"));
+        }
+
+        let major_version = read_u16(data, 0x1a)?;
+        let sector_shift = read_u16(data, 0x1e)?;
+        let mini_sector_shift = read_u16(data, 0x20)?;
+
+        if !(sector_shift == 9 || sector_shift == 12) || mini_sector_shift != 6 {
+            return Err(anyhow!("unsupported compound file sector size"));
+        }
+
+        let mut res = Self {
+            major_version,
+            sector_size: 1 << sector_shift,
+            mini_sector_size: 1 << mini_sector_shift,
+            mini_stream_cutoff: read_u32(data, 0x38)? as u64,
+            fat: vec![],
+            mini_fat: vec![],
+            entries: vec![],
+        };
+
+        // The header holds the first 109 FAT sector locations. Others are in
+        // a chain of DIFAT sectors, whose last entry is the next sector.
+        let mut fat_sectors = (0..109)
+            .map(|i| read_u32(data, 0x4c + 4 * i))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut difat_sector = read_u32(data, 0x44)?;
+        for _ in 0..read_u32(data, 0x48)? {
+            if difat_sector >= MAX_SECTOR {
+                break;
+            }
+
+            let offset = res.sector_offset(data, difat_sector)?;
+            let count = res.sector_size / 4 - 1;
+
+            for i in 0..count {
+                fat_sectors.push(read_u32(data, offset + 4 * i)?);
+            }
+            difat_sector = read_u32(data, offset + 4 * count)?;
+        }
+
+        for sector in fat_sectors.into_iter().filter(|s| *s < MAX_SECTOR) {
+            let offset = res.sector_offset(data, sector)?;
+
+            for i in 0..res.sector_size / 4 {
+                res.fat.push(read_u32(data, offset + 4 * i)?);
+            }
+        }
+
+        for sector in res.chain(&res.fat, read_u32(data, 0x3c)?)? {
+            let offset = res.sector_offset(data, sector)?;
+
+            for i in 0..res.sector_size / 4 {
+                res.mini_fat.push(read_u32(data, offset + 4 * i)?);
+            }
+        }
+
+        for sector in res.chain(&res.fat, read_u32(data, 0x30)?)? {
+            let offset = res.sector_offset(data, sector)?;
+
+            for i in 0..res.sector_size / DIRECTORY_ENTRY_SIZE {
+                res.entries.push(offset + DIRECTORY_ENTRY_SIZE * i);
+            }
+        }
+
+        if res.entries.is_empty() {
+            return Err(anyhow!("compound file has no root storage"));
+        }
+
+        Ok(res)
+    }
+
+    /// Resolve the offset of a sector, ensuring it is within the data.
+    fn sector_offset(&self, data: &[u8], sector: u32) -> Result<usize> {
+        let offset = (sector as usize + 1) * self.sector_size;
+
+        if offset + self.sector_size > data.len() {
+            Err(anyhow!("compound file is truncated"))
+        } else {
+            Ok(offset)
+        }
+    }
+
+    /// Follow a chain of sectors in an allocation table.
+    fn chain(&self, table: &[u32], start: u32) -> Result<Vec<u32>> {
+        let mut res = vec![];
+        let mut sector = start;
+
+        while sector != END_OF_CHAIN {
+            if res.len() >= table.len() {
+                return Err(anyhow!("invalid sector chain in compound file"));
+            }
+
+            res.push(sector);
+            sector = *table
+                .get(sector as usize)
+                .ok_or_else(|| anyhow!("invalid sector chain in compound file"))?;
+        }
+
+        Ok(res)
+    }
+
+    /// Resolve the ranges of data holding the content of a stream.
+    fn stream_ranges(&self, data: &[u8], entry: usize) -> Result<Vec<(usize, usize)>> {
+        let start = read_u32(data, entry + 0x74)?;
+        let mut size = read_u32(data, entry + 0x78)? as u64;
+        if self.major_version != 3 {
+            size |= (read_u32(data, entry + 0x7c)? as u64) << 32;
+        }
+
+        let mut res = vec![];
+        let mut remaining = size as usize;
+
+        if entry != self.entries[0] && size < self.mini_stream_cutoff {
+            // Small streams are stored in the mini stream, which is the
+            // content of the root storage.
+            let mini_stream = self.stream_ranges(data, self.entries[0])?;
+
+            for mini_sector in self.chain(&self.mini_fat, start)? {
+                let position = mini_sector as usize * self.mini_sector_size;
+                let (offset, _) = mini_stream
+                    .get(position / self.sector_size)
+                    .ok_or_else(|| anyhow!("invalid mini stream sector in compound file"))?;
+
+                let len = remaining.min(self.mini_sector_size);
+                res.push((offset + position % self.sector_size, len));
+                remaining -= len;
+            }
+        } else {
+            for sector in self.chain(&self.fat, start)? {
+                let len = remaining.min(self.sector_size);
+                res.push((self.sector_offset(data, sector)?, len));
+                remaining -= len;
+            }
+        }
+
+        if remaining > 0 {
+            return Err(anyhow!("compound file stream is truncated"));
+        }
+
+        Ok(res)
+    }
+
+    /// Find the offsets of the directory entries of a storage's children.
+    fn children(&self, data: &[u8], storage: usize) -> Result<Vec<usize>> {
+        let mut res = vec![];
+        let mut pending = vec![read_u32(data, storage + 0x4c)?];
+
+        while let Some(id) = pending.pop() {
+            if id == NO_STREAM {
+                continue;
+            }
+
+            let entry = *self
+                .entries
+                .get(id as usize)
+                .ok_or_else(|| anyhow!("invalid directory entry in compound file"))?;
+
+            if res.len() >= self.entries.len() {
+                return Err(anyhow!("invalid directory tree in compound file"));
+            }
+
+            res.push(entry);
+            pending.push(read_u32(data, entry + 0x44)?);
+            pending.push(read_u32(data, entry + 0x48)?);
+        }
+
+        Ok(res)
+    }
+}
+
+/// Resolve the name of a directory entry.
+fn entry_name(data: &[u8], entry: usize) -> Result<String> {
+    // The length includes the terminating NUL.
+    let len = (read_u16(data, entry + 0x40)? as usize).min(64) / 2;
+
+    let name = (0..len.saturating_sub(1))
+        .map(|i| read_u16(data, entry + 2 * i))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(String::from_utf16_lossy(&name))
+}
+
+/// Replace the times recorded in a property set stream.
+fn set_summary_information_times(stream: &mut [u8], time: u64) -> Result<()> {
+    let sets = read_u32(stream, 24)? as usize;
+
+    for i in 0..sets {
+        let fmtid = stream
+            .get(28 + 20 * i..44 + 20 * i)
+            .ok_or_else(|| anyhow!("summary information is truncated"))?;
+        if fmtid != FMTID_SUMMARY_INFORMATION {
+            continue;
+        }
+
+        let section = read_u32(stream, 44 + 20 * i)? as usize;
+        let properties = read_u32(stream, section + 4)? as usize;
+
+        for j in 0..properties {
+            let id = read_u32(stream, section + 8 + 8 * j)?;
+            let offset = section + read_u32(stream, section + 12 + 8 * j)? as usize;
+
+            if TIME_PROPERTIES.contains(&id) && read_u16(stream, offset)? == VT_FILETIME {
+                stream
+                    .get_mut(offset + 4..offset + 12)
+                    .ok_or_else(|| anyhow!("summary information is truncated"))?
+                    .copy_from_slice(&time.to_le_bytes());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace the times recorded in a compound file.
+///
+/// This replaces the creation, last saved and last printed times in the
+/// summary information and the times recorded for storages.
+pub fn set_compound_file_times(data: &mut [u8], time: SystemTime) -> Result<()> {
+    let time = filetime(time)?;
+    let file = CompoundFile::parse(data)?;
+
+    for entry in &file.entries {
+        let kind = data[*entry + 0x42];
+
+        if kind == STORAGE_OBJECT || kind == ROOT_STORAGE_OBJECT {
+            // Times that aren't recorded are zero.
+            for offset in &[*entry + 0x64, *entry + 0x6c] {
+                let value = &mut data[*offset..*offset + 8];
+
+                if value.iter().any(|b| *b != 0) {
+                    value.copy_from_slice(&time.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    for entry in file.children(data, file.entries[0])? {
+        if entry_name(data, entry)? != SUMMARY_INFORMATION_NAME {
+            continue;
+        }
+
+        let ranges = file.stream_ranges(data, entry)?;
+
+        let mut stream = vec![];
+        for (offset, len) in &ranges {
+            stream.extend_from_slice(&data[*offset..*offset + *len]);
+        }
+
+        set_summary_information_times(&mut stream, time)?;
+
+        let mut position = 0;
+        for (offset, len) in ranges {
+            data[offset..offset + len].copy_from_slice(&stream[position..position + len]);
+            position += len;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::time::Duration};
+
+    fn u32s(values: &[u32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|v| v.to_le_bytes().to_vec())
+            .collect()
+    }
+
+    /// A summary information stream with created and last saved times and a page count.
+    fn summary_information() -> Vec<u8> {
+        let mut res = vec![0xfe, 0xff, 0, 0, 0, 0, 0, 0];
+        res.extend_from_slice(&[0; 16]);
+        res.extend(u32s(&[1]));
+        res.extend_from_slice(&FMTID_SUMMARY_INFORMATION);
+        res.extend(u32s(&[48]));
+
+        res.extend(u32s(&[64, 3, 12, 32, 13, 44, 14, 56]));
+        res.extend(u32s(&[VT_FILETIME as u32, 1, 1]));
+        res.extend(u32s(&[VT_FILETIME as u32, 2, 2]));
+        res.extend(u32s(&[3, 42]));
+
+        res
+    }
+
+    /// A version 3 compound file containing a summary information stream.
+    ///
+    /// Sectors are the FAT, the directory, the mini FAT and the mini stream.
+    fn compound_file() -> Vec<u8> {
+        let mut data = SIGNATURE.to_vec();
+        data.extend_from_slice(&[0; 16]);
+        data.extend_from_slice(&[0x3e, 0, 3, 0, 0xfe, 0xff, 9, 0, 6, 0]);
+        data.extend_from_slice(&[0; 6]);
+        data.extend(u32s(&[0, 1, 1, 0, 4096, 2, 1, END_OF_CHAIN, 0, 0]));
+        data.extend(u32s(&[NO_STREAM; 108]));
+        assert_eq!(data.len(), 512);
+
+        let mut fat = u32s(&[0xffff_fffd, END_OF_CHAIN, END_OF_CHAIN, END_OF_CHAIN]);
+        fat.resize(512, 0xff);
+        data.extend(fat);
+
+        let entry = |name: &str, kind: u8, child: u32, times: u64, start: u32, size: u32| {
+            let mut res = name
+                .encode_utf16()
+                .flat_map(|c| c.to_le_bytes().to_vec())
+                .collect::<Vec<_>>();
+            let name_len = res.len() as u16 + 2;
+            res.resize(64, 0);
+            res.extend_from_slice(&name_len.to_le_bytes());
+            res.extend_from_slice(&[kind, 1]);
+            res.extend(u32s(&[NO_STREAM, NO_STREAM, child]));
+            res.extend_from_slice(&[0; 20]);
+            res.extend_from_slice(&times.to_le_bytes());
+            res.extend_from_slice(&times.to_le_bytes());
+            res.extend(u32s(&[start, size, 0]));
+            res
+        };
+
+        let stream = summary_information();
+        data.extend(entry("Root Entry", ROOT_STORAGE_OBJECT, 1, 5, 3, 128));
+        data.extend(entry(
+            SUMMARY_INFORMATION_NAME,
+            2,
+            NO_STREAM,
+            0,
+            0,
+            stream.len() as u32,
+        ));
+        data.extend(entry("", 0, NO_STREAM, 0, END_OF_CHAIN, 0));
+        data.extend(entry("", 0, NO_STREAM, 0, END_OF_CHAIN, 0));
+
+        let mut mini_fat = u32s(&[1, END_OF_CHAIN]);
+        mini_fat.resize(512, 0xff);
+        data.extend(mini_fat);
+
+        let mut mini_stream = stream;
+        mini_stream.resize(512, 0);
+        data.extend(mini_stream);
+
+        data
+    }
+
+    #[test]
+    fn test_set_compound_file_times() -> Result<()> {
+        let mut data = compound_file();
+        assert!(is_compound_file(&data));
+
+        let time = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        set_compound_file_times(&mut data, time)?;
+
+        let expected = (116_444_736_000_000_000u64 + 10_000_000_000_000).to_le_bytes();
+
+        // Times of the root storage.
+        assert_eq!(data[1024 + 0x64..1024 + 0x6c], expected);
+        assert_eq!(data[1024 + 0x6c..1024 + 0x74], expected);
+        // Streams don't record times.
+        assert_eq!(data[1152 + 0x64..1152 + 0x74], [0; 16]);
+
+        // The mini stream is in the fourth sector. The last saved time spans
+        // two mini sectors.
+        let stream = &data[2048..2048 + 112];
+        assert_eq!(stream[84..92], expected);
+        assert_eq!(stream[96..104], expected);
+        assert_eq!(stream[104..112], u32s(&[3, 42])[..]);
+
+        Ok(())
+    }
+}
@@ -3,13 +3,20 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::{common::extract_wix, *},
+    crate::{
+        common::extract_wix,
+        compound_file::{is_compound_file, set_compound_file_times},
+        *,
+    },
     anyhow::{anyhow, Context, Result},
     std::{
         collections::BTreeMap,
         path::{Path, PathBuf},
+        sync::Arc,
+        time::SystemTime,
     },
-    tugger_file_manifest::{FileEntry, FileManifest},
+    tugger_common::signing::SigningCallback,
+    tugger_file_manifest::{source_date_epoch, FileEntry, FileManifest},
     tugger_windows::{is_file_signable, SigntoolSign},
     xml::EmitterConfig,
 };
@@ -42,8 +49,11 @@ pub struct WiXInstallerBuilder {
     /// Extra files to install in the build directory.
     extra_build_files: FileManifest,
 
-    /// Signtool settings to use to auto sign binaries and the installer.
-    auto_sign_signtool_settings: Option<SigntoolSign>,
+    /// Signer used to auto sign binaries and the installer.
+    auto_signer: Option<Arc<dyn SigningCallback>>,
+
+    /// Modified time to record for installed files.
+    mtime: Option<SystemTime>,
 }
 
 impl WiXInstallerBuilder {
@@ -57,7 +67,8 @@ impl WiXInstallerBuilder {
             variables: BTreeMap::new(),
             wxs_files: BTreeMap::new(),
             extra_build_files: FileManifest::default(),
-            auto_sign_signtool_settings: None,
+            auto_signer: None,
+            mtime: None,
         }
     }
 
@@ -123,7 +134,26 @@ impl WiXInstallerBuilder {
     /// This will automatically sign all installed binaries as well as the
     /// generated installer.
     pub fn auto_sign_signtool(&mut self, settings: SigntoolSign) {
-        self.auto_sign_signtool_settings = Some(settings);
+        self.auto_sign(Arc::new(settings));
+    }
+
+    /// Register a signer to be used to automatically sign binaries.
+    ///
+    /// This will automatically sign all installed binaries as well as the
+    /// generated installer.
+    pub fn auto_sign(&mut self, signer: Arc<dyn SigningCallback>) {
+        self.auto_signer = Some(signer);
+    }
+
+    /// Set the modified time recorded for installed files.
+    ///
+    /// Cabinet files embedded in installers record the modified time of each
+    /// file. MSI installers also record this time as when they were created
+    /// and saved. If not set, the `SOURCE_DATE_EPOCH` environment variable is
+    /// used, if defined. Otherwise files have the time they were staged and
+    /// MSIs the time they were built.
+    pub fn set_mtime(&mut self, time: Option<SystemTime>) {
+        self.mtime = time;
     }
 
     fn stage_path(&self) -> PathBuf {
@@ -172,20 +202,32 @@ impl WiXInstallerBuilder {
             .context("writing install files")?;
 
         // Automatically sign files that are signable.
-        if let Some(signtool_settings) = &self.auto_sign_signtool_settings {
-            for path in installed_files {
+        if let Some(signer) = &self.auto_signer {
+            for path in &installed_files {
                 if is_file_signable(&path)
                     .with_context(|| format!("checking if {} is signable", path.display()))?
                 {
-                    signtool_settings
-                        .clone_settings()
-                        .sign_file(&path)
-                        .run(logger)
+                    signer
+                        .sign(logger, &path)
                         .with_context(|| format!("signing {}", path.display()))?;
                 }
             }
         }
 
+        // Pinned after signing since signing rewrites files.
+        let mtime = match self.mtime {
+            Some(time) => Some(time),
+            None => source_date_epoch().context("resolving SOURCE_DATE_EPOCH")?,
+        };
+        if let Some(mtime) = mtime {
+            let mtime = filetime::FileTime::from_system_time(mtime);
+
+            for path in &installed_files {
+                filetime::set_file_mtime(path, mtime)
+                    .with_context(|| format!("setting mtime of {}", path.display()))?;
+            }
+        }
+
         let wxs_path = self.build_path.join("wxs");
 
         self.extra_build_files
@@ -229,12 +271,22 @@ impl WiXInstallerBuilder {
         )
         .context("running light")?;
 
-        if let Some(signtool_settings) = &self.auto_sign_signtool_settings {
+        // light records the time MSIs are built.
+        if let Some(mtime) = mtime {
+            let mut data = std::fs::read(output_path.as_ref())
+                .with_context(|| format!("reading {}", output_path.as_ref().display()))?;
+
+            if is_compound_file(&data) {
+                set_compound_file_times(&mut data, mtime).context("setting MSI times")?;
+                std::fs::write(output_path.as_ref(), &data)
+                    .with_context(|| format!("writing {}", output_path.as_ref().display()))?;
+            }
+        }
+
+        if let Some(signer) = &self.auto_signer {
             if is_file_signable(output_path.as_ref()).context("checking if file is signable")? {
-                signtool_settings
-                    .clone_settings()
-                    .sign_file(output_path.as_ref())
-                    .run(logger)
+                signer
+                    .sign(logger, output_path.as_ref())
                     .with_context(|| format!("signing {}", output_path.as_ref().display()))?;
             }
         }
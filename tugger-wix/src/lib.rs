@@ -5,6 +5,7 @@
 mod bundle_builder;
 mod chain;
 mod common;
+mod compound_file;
 mod exe_package;
 mod installer_builder;
 mod msi_package;
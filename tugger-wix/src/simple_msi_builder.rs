@@ -9,7 +9,10 @@ use {
         borrow::Cow,
         io::Write,
         path::{Path, PathBuf},
+        sync::Arc,
+        time::SystemTime,
     },
+    tugger_common::signing::SigningCallback,
     tugger_file_manifest::FileManifest,
     tugger_windows::{find_visual_cpp_redistributable, SigntoolSign, VcRedistributablePlatform},
     uuid::Uuid,
@@ -61,8 +64,17 @@ pub struct WiXSimpleMsiBuilder {
     /// Dimensions are 493 x 312.
     dialog_bmp: Option<PathBuf>,
 
-    /// Signtool settings to use to auto sign binaries and the installer.
-    auto_sign_signtool_settings: Option<SigntoolSign>,
+    /// Signer used to auto sign binaries and the installer.
+    auto_signer: Option<Arc<dyn SigningCallback>>,
+
+    /// Namespace for deterministically derived GUIDs.
+    guid_namespace: Option<Uuid>,
+
+    /// Identifier of this build, making the package code deterministic.
+    build_id: Option<String>,
+
+    /// Modified time to record for installed files.
+    mtime: Option<SystemTime>,
 }
 
 impl WiXSimpleMsiBuilder {
@@ -180,8 +192,49 @@ impl WiXSimpleMsiBuilder {
     ///
     /// This will automatically sign all installed binaries as well as the
     /// generated installer.
-    pub fn auto_sign_signtool(mut self, settings: SigntoolSign) -> Self {
-        self.auto_sign_signtool_settings = Some(settings);
+    pub fn auto_sign_signtool(self, settings: SigntoolSign) -> Self {
+        self.auto_sign(Arc::new(settings))
+    }
+
+    /// Register a signer to be used to automatically sign binaries.
+    ///
+    /// This will automatically sign all installed binaries as well as the
+    /// generated installer.
+    pub fn auto_sign(mut self, signer: Arc<dyn SigningCallback>) -> Self {
+        self.auto_signer = Some(signer);
+        self
+    }
+
+    /// Set the namespace used to derive deterministic GUIDs.
+    ///
+    /// GUIDs not explicitly defined, such as the upgrade code, are UUIDv5
+    /// values derived from the product name in this namespace. The default
+    /// namespace is `Uuid::NAMESPACE_DNS`.
+    pub fn guid_namespace(mut self, namespace: Uuid) -> Self {
+        self.guid_namespace = Some(namespace);
+        self
+    }
+
+    /// Set an identifier for this build.
+    ///
+    /// By default the product and package codes are random, so every build
+    /// of an installer differs. When set, the product code is derived from
+    /// the product name and version and the package code is additionally
+    /// derived from this value. Distinct installers of the same product
+    /// version must use distinct build identifiers.
+    ///
+    /// To reproduce installers, the times they record must also be pinned.
+    /// See [WiXSimpleMsiBuilder::mtime].
+    pub fn build_id(mut self, value: String) -> Self {
+        self.build_id = Some(value);
+        self
+    }
+
+    /// Set the modified time recorded for installed files and the installer.
+    ///
+    /// See [WiXInstallerBuilder::set_mtime].
+    pub fn mtime(mut self, time: SystemTime) -> Self {
+        self.mtime = Some(time);
         self
     }
 
@@ -205,8 +258,12 @@ impl WiXSimpleMsiBuilder {
             .add_manifest(&self.program_files_manifest)?;
         builder.add_files_manifest_wxs("APPLICATIONFOLDER")?;
 
-        if let Some(settings) = &self.auto_sign_signtool_settings {
-            builder.auto_sign_signtool(settings.clone_settings());
+        if let Some(signer) = &self.auto_signer {
+            builder.auto_sign(signer.clone());
+        }
+
+        if let Some(time) = self.mtime {
+            builder.set_mtime(Some(time));
         }
 
         Ok(())
@@ -280,7 +337,7 @@ impl WiXSimpleMsiBuilder {
 
         writer.write(
             XmlEvent::start_element("Product")
-                .attr("Id", "*")
+                .attr("Id", &self.product_code())
                 .attr("Name", &self.product_name)
                 .attr("Version", &self.product_version)
                 .attr("Manufacturer", &self.product_manufacturer)
@@ -289,8 +346,9 @@ impl WiXSimpleMsiBuilder {
                 .attr("Codepage", &self.product_codepage),
         )?;
 
+        let package_code = self.package_code();
         let package = XmlEvent::start_element("Package")
-            .attr("Id", "*")
+            .attr("Id", &package_code)
             .attr("Manufacturer", &self.product_manufacturer)
             .attr("InstallerVersion", &self.package_installer_version)
             .attr("Languages", &self.package_languages)
@@ -542,7 +600,7 @@ impl WiXSimpleMsiBuilder {
         } else {
             Cow::Owned(
                 Uuid::new_v5(
-                    &Uuid::NAMESPACE_DNS,
+                    &self.namespace(),
                     format!("tugger.upgrade_code.{}", self.product_name).as_bytes(),
                 )
                 .to_string(),
@@ -550,14 +608,45 @@ impl WiXSimpleMsiBuilder {
         }
     }
 
+    fn namespace(&self) -> Uuid {
+        self.guid_namespace.unwrap_or(Uuid::NAMESPACE_DNS)
+    }
+
+    fn derive_guid(&self, name: &str) -> String {
+        Uuid::new_v5(&self.namespace(), name.as_bytes())
+            .to_hyphenated()
+            .encode_upper(&mut Uuid::encode_buffer())
+            .to_string()
+    }
+
     fn path_component_guid(&self) -> String {
-        Uuid::new_v5(
-            &Uuid::NAMESPACE_DNS,
-            format!("tugger.path_component.{}", self.product_name).as_bytes(),
-        )
-        .to_hyphenated()
-        .encode_upper(&mut Uuid::encode_buffer())
-        .to_string()
+        self.derive_guid(&format!("tugger.path_component.{}", self.product_name))
+    }
+
+    /// Obtain the `<Product Id` attribute value.
+    ///
+    /// `*` has WiX generate a random GUID.
+    fn product_code(&self) -> String {
+        if self.build_id.is_some() {
+            self.derive_guid(&format!(
+                "tugger.product_code.{}.{}",
+                self.product_name, self.product_version
+            ))
+        } else {
+            "*".to_string()
+        }
+    }
+
+    /// Obtain the `<Package Id` attribute value.
+    fn package_code(&self) -> String {
+        if let Some(build_id) = &self.build_id {
+            self.derive_guid(&format!(
+                "tugger.package_code.{}.{}.{}",
+                self.product_name, self.product_version, build_id
+            ))
+        } else {
+            "*".to_string()
+        }
     }
 }
 
@@ -594,6 +683,38 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_deterministic_codes() -> Result<()> {
+        let builder = WiXSimpleMsiBuilder::new("prefix", "myapp", "0.1", "author");
+        assert_eq!(builder.product_code(), "*");
+        assert_eq!(builder.package_code(), "*");
+        assert_eq!(
+            builder.get_upgrade_code(),
+            Uuid::new_v5(&Uuid::NAMESPACE_DNS, b"tugger.upgrade_code.myapp").to_string()
+        );
+
+        let builder = builder.build_id("1".to_string());
+        let product_code = builder.product_code();
+        let package_code = builder.package_code();
+        assert_ne!(product_code, "*");
+        assert_ne!(product_code, package_code);
+        assert_eq!(product_code, product_code.to_uppercase());
+
+        let other = builder.clone().build_id("2".to_string());
+        assert_eq!(other.product_code(), product_code);
+        assert_ne!(other.package_code(), package_code);
+
+        let namespace = Uuid::new_v5(&Uuid::NAMESPACE_URL, b"https://example.com/");
+        let other = builder.guid_namespace(namespace);
+        assert_ne!(other.product_code(), product_code);
+        assert_eq!(
+            other.get_upgrade_code(),
+            Uuid::new_v5(&namespace, b"tugger.upgrade_code.myapp").to_string()
+        );
+
+        Ok(())
+    }
+
     #[cfg(target_family = "windows")]
     #[test]
     fn test_simple_msi_builder_build() -> Result<()> {
@@ -0,0 +1,489 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Extract archives into file manifests.
+
+Tar archives (optionally compressed with gzip, xz, or zstd) and zip
+archives are supported. Regular files and symlinks are extracted. Hard
+links in tar archives become copies of the files they link to.
+Directories are implied by the files they contain.
+
+Symlinks must point to a path inside the extracted tree.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    std::{
+        convert::TryFrom,
+        io::Read,
+        path::{Component, Path, PathBuf},
+    },
+    tugger_file_manifest::{FileEntry, FileManifest},
+};
+
+/// Format of an archive.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+    Zip,
+}
+
+impl TryFrom<&str> for ArchiveFormat {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "tar" => Ok(Self::Tar),
+            "tar.gz" => Ok(Self::TarGz),
+            "tar.xz" => Ok(Self::TarXz),
+            "tar.zst" => Ok(Self::TarZst),
+            "zip" => Ok(Self::Zip),
+            _ => Err(format!("{} is not a valid archive format", value)),
+        }
+    }
+}
+
+impl ArchiveFormat {
+    /// Resolve the format of an archive from its filename.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("unable to resolve file name of {}", path.display()))?
+            .to_string_lossy()
+            .to_lowercase();
+
+        if name.ends_with(".tar") {
+            Ok(Self::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(Self::TarGz)
+        } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            Ok(Self::TarXz)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Ok(Self::TarZst)
+        } else if name.ends_with(".zip") || name.ends_with(".whl") {
+            Ok(Self::Zip)
+        } else {
+            Err(anyhow!(
+                "unable to determine archive format of {}",
+                path.display()
+            ))
+        }
+    }
+}
+
+/// Resolve the manifest path of an archive member.
+///
+/// Returns `None` if the member is stripped away by `strip_components`.
+fn member_path(path: &Path, strip_components: usize) -> Option<PathBuf> {
+    let components = path
+        .components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect::<Vec<_>>();
+
+    if components.len() <= strip_components {
+        None
+    } else {
+        Some(components[strip_components..].iter().collect())
+    }
+}
+
+/// Ensure a symlink target resolves to a path inside the manifest.
+///
+/// Relative targets are evaluated relative to the directory of the symlink.
+fn validate_symlink_target(path: &Path, target: &Path) -> Result<()> {
+    let mut depth = path.components().count().saturating_sub(1);
+
+    for component in target.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(_) => {
+                depth += 1;
+            }
+            Component::ParentDir => {
+                if depth == 0 {
+                    return Err(anyhow!(
+                        "symlink {} points outside the archive: {}",
+                        path.display(),
+                        target.display()
+                    ));
+                }
+                depth -= 1;
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(anyhow!(
+                    "symlink {} has absolute target: {}",
+                    path.display(),
+                    target.display()
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn add_symlink(manifest: &mut FileManifest, path: &Path, target: PathBuf) -> Result<()> {
+    validate_symlink_target(path, &target)?;
+    manifest.add_symlink(path, target)?;
+
+    Ok(())
+}
+
+fn extract_tar(
+    manifest: &mut FileManifest,
+    reader: impl Read,
+    strip_components: usize,
+) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_path_buf();
+
+        let manifest_path = match member_path(&path, strip_components) {
+            Some(p) => p,
+            None => continue,
+        };
+
+        let entry_type = entry.header().entry_type();
+
+        // Local pax extensions are applied to the entries they precede by
+        // the tar crate. Global ones only carry metadata we don't use.
+        if entry_type.is_dir() || entry_type.is_pax_global_extensions() {
+            continue;
+        } else if entry_type.is_symlink() {
+            let target = entry
+                .link_name()?
+                .ok_or_else(|| anyhow!("symlink {} has no target", path.display()))?
+                .to_path_buf();
+            add_symlink(manifest, &manifest_path, target)?;
+        } else if entry_type.is_hard_link() {
+            let target = entry
+                .link_name()?
+                .ok_or_else(|| anyhow!("hard link {} has no target", path.display()))?
+                .to_path_buf();
+
+            // Hard link targets are paths of earlier archive members.
+            let linked = member_path(&target, strip_components)
+                .and_then(|p| manifest.get(&p).cloned())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "hard link {} refers to unknown file {}",
+                        path.display(),
+                        target.display()
+                    )
+                })?;
+
+            manifest.add_file_entry(&manifest_path, linked)?;
+        } else if entry_type.is_file() || entry_type.is_contiguous() {
+            let executable = entry.header().mode()? & 0o111 != 0;
+
+            let mut data = vec![];
+            entry
+                .read_to_end(&mut data)
+                .with_context(|| format!("reading {}", path.display()))?;
+
+            manifest.add_file_entry(
+                &manifest_path,
+                FileEntry {
+                    data: data.into(),
+                    executable,
+                },
+            )?;
+        } else {
+            return Err(anyhow!(
+                "unsupported entry type of {} in tar archive",
+                path.display()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_zip(manifest: &mut FileManifest, path: &Path, strip_components: usize) -> Result<()> {
+    let fh = std::fs::File::open(path)?;
+    let mut archive = zip::ZipArchive::new(std::io::BufReader::new(fh))?;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+
+        if file.is_dir() {
+            continue;
+        }
+
+        let name = PathBuf::from(file.name());
+        let manifest_path = match member_path(&name, strip_components) {
+            Some(p) => p,
+            None => continue,
+        };
+
+        let mode = file.unix_mode().unwrap_or(0);
+        let executable = mode & 0o111 != 0;
+
+        let mut data = vec![];
+        file.read_to_end(&mut data)
+            .with_context(|| format!("reading {}", name.display()))?;
+
+        // Symlinks store their target as the member content.
+        if mode & 0o170000 == 0o120000 {
+            let target = String::from_utf8(data)
+                .map_err(|_| anyhow!("symlink {} has non-UTF-8 target", name.display()))?;
+            add_symlink(manifest, &manifest_path, PathBuf::from(target))?;
+            continue;
+        }
+
+        manifest.add_file_entry(
+            &manifest_path,
+            FileEntry {
+                data: data.into(),
+                executable,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Extract an archive into a new [FileManifest].
+///
+/// `strip_components` leading path components are removed from the path
+/// of each member. Members having no remaining components are ignored.
+///
+/// If `format` is not defined, it is derived from the archive filename.
+pub fn extract_archive(
+    path: &Path,
+    format: Option<ArchiveFormat>,
+    strip_components: usize,
+) -> Result<FileManifest> {
+    let format = if let Some(format) = format {
+        format
+    } else {
+        ArchiveFormat::from_path(path)?
+    };
+
+    let mut manifest = FileManifest::default();
+
+    let open = || -> Result<std::io::BufReader<std::fs::File>> {
+        Ok(std::io::BufReader::new(
+            std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?,
+        ))
+    };
+
+    match format {
+        ArchiveFormat::Tar => extract_tar(&mut manifest, open()?, strip_components),
+        ArchiveFormat::TarGz => extract_tar(
+            &mut manifest,
+            flate2::read::GzDecoder::new(open()?),
+            strip_components,
+        ),
+        ArchiveFormat::TarXz => extract_tar(
+            &mut manifest,
+            xz2::read::XzDecoder::new(open()?),
+            strip_components,
+        ),
+        ArchiveFormat::TarZst => extract_tar(
+            &mut manifest,
+            zstd::stream::read::Decoder::new(open()?)?,
+            strip_components,
+        ),
+        ArchiveFormat::Zip => extract_zip(&mut manifest, path, strip_components),
+    }
+    .with_context(|| format!("extracting {}", path.display()))?;
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, tugger_common::testutil::*};
+
+    #[test]
+    fn test_format_from_path() -> Result<()> {
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("foo.tar"))?,
+            ArchiveFormat::Tar
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("foo.tgz"))?,
+            ArchiveFormat::TarGz
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("foo-1.0.tar.zst"))?,
+            ArchiveFormat::TarZst
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("foo.ZIP"))?,
+            ArchiveFormat::Zip
+        );
+        assert!(ArchiveFormat::from_path(Path::new("foo.txt")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_member_path() {
+        assert_eq!(
+            member_path(Path::new("./foo/bar"), 0),
+            Some(PathBuf::from("foo/bar"))
+        );
+        assert_eq!(
+            member_path(Path::new("foo/bar"), 1),
+            Some(PathBuf::from("bar"))
+        );
+        assert_eq!(member_path(Path::new("foo"), 1), None);
+    }
+
+    #[test]
+    fn test_validate_symlink_target() {
+        assert!(validate_symlink_target(Path::new("app"), Path::new("bin/app")).is_ok());
+        assert!(
+            validate_symlink_target(Path::new("lib/libfoo.so"), Path::new("libfoo.so.1")).is_ok()
+        );
+        assert!(validate_symlink_target(Path::new("bin/app"), Path::new("../lib/app")).is_ok());
+        assert!(validate_symlink_target(Path::new("bin/app"), Path::new("../../app")).is_err());
+        assert!(validate_symlink_target(Path::new("app"), Path::new("../app")).is_err());
+        assert!(validate_symlink_target(Path::new("app"), Path::new("/etc/passwd")).is_err());
+    }
+
+    #[test]
+    fn test_extract_tar() -> Result<()> {
+        let path = DEFAULT_TEMP_DIR.path().join("test_extract_tar.tar");
+
+        {
+            let mut builder = tar::Builder::new(std::fs::File::create(&path)?);
+
+            let mut header = tar::Header::new_gnu();
+            header.set_size(5);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder.append_data(&mut header, "pkg/bin/app", &b"hello"[..])?;
+
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_link_name("bin/app")?;
+            header.set_cksum();
+            builder.append_data(&mut header, "pkg/app", std::io::empty())?;
+
+            builder.finish()?;
+        }
+
+        let manifest = extract_archive(&path, None, 1)?;
+
+        assert_eq!(
+            manifest.get("bin/app"),
+            Some(&FileEntry {
+                data: b"hello".to_vec().into(),
+                executable: true,
+            })
+        );
+        assert_eq!(manifest.get_symlink("app"), Some(Path::new("bin/app")));
+
+        Ok(())
+    }
+
+    /// Format a pax extended header record.
+    fn pax_record(key: &str, value: &str) -> String {
+        let record = format!(" {}={}\n", key, value);
+
+        // The length prefix counts its own digits.
+        let mut length = record.len();
+        loop {
+            let total = length.to_string().len() + record.len();
+            if total == length {
+                break;
+            }
+            length = total;
+        }
+
+        format!("{}{}", length, record)
+    }
+
+    #[test]
+    fn test_extract_tar_hardlink_pax() -> Result<()> {
+        let path = DEFAULT_TEMP_DIR
+            .path()
+            .join("test_extract_tar_hardlink_pax.tar");
+        let long_name = format!("pkg/{}/app", "d".repeat(120));
+
+        {
+            let mut builder = tar::Builder::new(std::fs::File::create(&path)?);
+
+            let pax = pax_record("comment", "something");
+            let mut header = tar::Header::new_ustar();
+            header.set_entry_type(tar::EntryType::XGlobalHeader);
+            header.set_size(pax.len() as _);
+            header.set_cksum();
+            builder.append_data(&mut header, "pax_global_header", pax.as_bytes())?;
+
+            // A path longer than ustar supports is stored in a local pax header.
+            let pax = pax_record("path", &long_name);
+            let mut header = tar::Header::new_ustar();
+            header.set_entry_type(tar::EntryType::XHeader);
+            header.set_size(pax.len() as _);
+            header.set_cksum();
+            builder.append_data(&mut header, "pax_header", pax.as_bytes())?;
+
+            let mut header = tar::Header::new_ustar();
+            header.set_size(5);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, "pkg/placeholder", &b"hello"[..])?;
+
+            let mut header = tar::Header::new_gnu();
+            header.set_size(4);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder.append_data(&mut header, "pkg/lib/data", &b"data"[..])?;
+
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Link);
+            header.set_size(0);
+            header.set_link_name("pkg/lib/data")?;
+            header.set_cksum();
+            builder.append_data(&mut header, "pkg/copy", std::io::empty())?;
+
+            builder.finish()?;
+        }
+
+        let manifest = extract_archive(&path, None, 1)?;
+
+        let long_path = PathBuf::from(format!("{}/app", "d".repeat(120)));
+        assert_eq!(manifest.get(&long_path).unwrap().data.resolve()?, b"hello");
+        assert!(manifest.get("placeholder").is_none());
+        assert_eq!(manifest.get("copy"), manifest.get("lib/data"));
+        assert!(manifest.get("copy").unwrap().executable);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_tar_symlink_escape() -> Result<()> {
+        let path = DEFAULT_TEMP_DIR
+            .path()
+            .join("test_extract_tar_symlink_escape.tar");
+
+        {
+            let mut builder = tar::Builder::new(std::fs::File::create(&path)?);
+
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_link_name("../../etc/passwd")?;
+            header.set_cksum();
+            builder.append_data(&mut header, "pkg/passwd", std::io::empty())?;
+
+            builder.finish()?;
+        }
+
+        assert!(extract_archive(&path, None, 1).is_err());
+
+        Ok(())
+    }
+}
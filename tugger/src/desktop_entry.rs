@@ -0,0 +1,331 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Define freedesktop.org desktop entries for Linux applications.
+
+Linux desktop environments populate application menus from
+[desktop entry](https://specifications.freedesktop.org/desktop-entry-spec/latest/)
+files in `share/applications` and resolve the icons they reference from icon
+themes, such as the `hicolor` fallback theme in `share/icons/hicolor`.
+
+A [DesktopEntry] renders a desktop entry file and lays out its icons as a
+[FileManifest] rooted at a `share` directory. This manifest can then be
+installed or packaged like any other, e.g. into a tarball or into a Debian
+or RPM package produced by a [crate::tool_pipeline::ToolPipeline].
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    std::path::{Path, PathBuf},
+    tugger_file_manifest::{FileData, FileEntry, FileManifest},
+};
+
+/// An icon of a desktop entry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DesktopIcon {
+    /// Path of the icon file.
+    pub path: PathBuf,
+
+    /// Width and height of the icon in pixels.
+    ///
+    /// `None` for scalable (SVG) icons.
+    pub size: Option<u32>,
+}
+
+impl DesktopIcon {
+    /// Path of the icon relative to the `share` directory.
+    fn install_path(&self, name: &str) -> Result<PathBuf> {
+        let extension = self
+            .path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .unwrap_or_default();
+
+        let size_dir = match (self.size, extension.as_str()) {
+            (None, "svg") => "scalable".to_string(),
+            (None, _) => {
+                return Err(anyhow!(
+                    "icon {} must have a size unless it is an SVG",
+                    self.path.display()
+                ))
+            }
+            (Some(size), "png") | (Some(size), "xpm") => format!("{}x{}", size, size),
+            (Some(_), _) => {
+                return Err(anyhow!(
+                    "icon {} with a size must be a PNG or XPM",
+                    self.path.display()
+                ))
+            }
+        };
+
+        Ok(PathBuf::from("icons")
+            .join("hicolor")
+            .join(size_dir)
+            .join("apps")
+            .join(format!("{}.{}", name, extension)))
+    }
+}
+
+/// Escape a string value of a desktop entry.
+fn escape_string(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+/// Format a list of strings value of a desktop entry.
+fn format_list(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|v| format!("{};", escape_string(v).replace(';', "\\;")))
+        .collect()
+}
+
+/// A desktop entry of type `Application`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DesktopEntry {
+    /// Desktop file ID.
+    ///
+    /// The desktop entry file is named after it. Reverse DNS notation, e.g.
+    /// `com.example.App`, is recommended.
+    pub id: String,
+
+    /// Name of the application.
+    pub name: String,
+
+    /// Command line to execute the application.
+    pub exec: String,
+
+    /// Name of the icon.
+    ///
+    /// Defaults to the desktop file ID if icons are defined.
+    pub icon: Option<String>,
+
+    /// Generic name of the application, e.g. `Web Browser`.
+    pub generic_name: Option<String>,
+
+    /// Tooltip for the entry.
+    pub comment: Option<String>,
+
+    /// Categories in which the entry is shown in menus.
+    pub categories: Vec<String>,
+
+    /// Keywords to search for the entry with.
+    pub keywords: Vec<String>,
+
+    /// MIME types supported by the application.
+    pub mime_types: Vec<String>,
+
+    /// Whether the application runs in a terminal.
+    pub terminal: bool,
+
+    /// Icons to install into the `hicolor` icon theme.
+    pub icons: Vec<DesktopIcon>,
+}
+
+impl DesktopEntry {
+    /// Create a new instance.
+    pub fn new(id: impl ToString, name: impl ToString, exec: impl ToString) -> Result<Self> {
+        let id = id.to_string();
+
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        {
+            return Err(anyhow!(
+                "{} is not a valid desktop file ID; must consist of ASCII letters, digits, '-', '_', and '.'",
+                id
+            ));
+        }
+
+        Ok(Self {
+            id,
+            name: name.to_string(),
+            exec: exec.to_string(),
+            icon: None,
+            generic_name: None,
+            comment: None,
+            categories: vec![],
+            keywords: vec![],
+            mime_types: vec![],
+            terminal: false,
+            icons: vec![],
+        })
+    }
+
+    /// Register an icon to install.
+    ///
+    /// `size` is the width and height of a PNG or XPM icon in pixels and
+    /// must be `None` for SVG icons.
+    pub fn add_icon(&mut self, path: impl AsRef<Path>, size: Option<u32>) -> Result<()> {
+        let icon = DesktopIcon {
+            path: path.as_ref().to_path_buf(),
+            size,
+        };
+
+        // Validates the icon type.
+        icon.install_path(&self.id)?;
+
+        self.icons.push(icon);
+
+        Ok(())
+    }
+
+    /// Resolve the name of the icon referenced by the entry.
+    pub fn icon_name(&self) -> Option<&str> {
+        match &self.icon {
+            Some(icon) => Some(icon),
+            None if !self.icons.is_empty() => Some(&self.id),
+            None => None,
+        }
+    }
+
+    /// Render the content of the desktop entry file.
+    pub fn to_desktop_file(&self) -> String {
+        let mut lines = vec![
+            "[Desktop Entry]".to_string(),
+            "Type=Application".to_string(),
+            "Version=1.0".to_string(),
+            format!("Name={}", escape_string(&self.name)),
+        ];
+
+        if let Some(generic_name) = &self.generic_name {
+            lines.push(format!("GenericName={}", escape_string(generic_name)));
+        }
+        if let Some(comment) = &self.comment {
+            lines.push(format!("Comment={}", escape_string(comment)));
+        }
+
+        lines.push(format!("Exec={}", escape_string(&self.exec)));
+
+        if let Some(icon) = self.icon_name() {
+            lines.push(format!("Icon={}", escape_string(icon)));
+        }
+
+        lines.push(format!("Terminal={}", self.terminal));
+
+        if !self.categories.is_empty() {
+            lines.push(format!("Categories={}", format_list(&self.categories)));
+        }
+        if !self.keywords.is_empty() {
+            lines.push(format!("Keywords={}", format_list(&self.keywords)));
+        }
+        if !self.mime_types.is_empty() {
+            lines.push(format!("MimeType={}", format_list(&self.mime_types)));
+        }
+
+        let mut content = lines.join("\n");
+        content.push('\n');
+
+        content
+    }
+
+    /// Obtain a [FileManifest] with the desktop entry file and icons.
+    ///
+    /// Paths in the manifest are relative to `prefix`, which should be the
+    /// `share` directory files are installed into, e.g. `usr/share`.
+    pub fn to_file_manifest(&self, prefix: impl AsRef<Path>) -> Result<FileManifest> {
+        let prefix = prefix.as_ref();
+        let mut manifest = FileManifest::default();
+
+        manifest.add_file_entry(
+            prefix
+                .join("applications")
+                .join(format!("{}.desktop", self.id)),
+            FileEntry {
+                data: FileData::Memory(self.to_desktop_file().into_bytes()),
+                executable: false,
+            },
+        )?;
+
+        let icon_name = self.icon_name().unwrap_or(&self.id);
+
+        for icon in &self.icons {
+            manifest.add_file_entry(
+                prefix.join(icon.install_path(icon_name)?),
+                FileEntry {
+                    data: FileData::Path(icon.path.clone()),
+                    executable: false,
+                },
+            )?;
+        }
+
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        assert!(DesktopEntry::new("com.example.App", "App", "app").is_ok());
+        assert!(DesktopEntry::new("", "App", "app").is_err());
+        assert!(DesktopEntry::new("my app", "App", "app").is_err());
+    }
+
+    #[test]
+    fn test_desktop_file() -> Result<()> {
+        let mut entry = DesktopEntry::new("com.example.App", "My App", "app %F")?;
+        assert_eq!(
+            entry.to_desktop_file(),
+            "[Desktop Entry]\nType=Application\nVersion=1.0\nName=My App\nExec=app %F\nTerminal=false\n"
+        );
+
+        entry.comment = Some("Line 1\nLine 2".to_string());
+        entry.categories = vec!["Utility".to_string(), "Development".to_string()];
+        entry.keywords = vec!["a;b".to_string()];
+        entry.terminal = true;
+        entry.add_icon("icons/app.svg", None)?;
+
+        assert_eq!(
+            entry.to_desktop_file(),
+            "[Desktop Entry]\nType=Application\nVersion=1.0\nName=My App\nComment=Line 1\\nLine 2\nExec=app %F\nIcon=com.example.App\nTerminal=true\nCategories=Utility;Development;\nKeywords=a\\;b;\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_icons() -> Result<()> {
+        let mut entry = DesktopEntry::new("com.example.App", "App", "app")?;
+
+        assert!(entry.add_icon("app.png", None).is_err());
+        assert!(entry.add_icon("app.svg", Some(48)).is_err());
+        assert!(entry.add_icon("app.ico", Some(48)).is_err());
+
+        entry.add_icon("app-48.png", Some(48))?;
+        entry.add_icon("app.svg", None)?;
+        entry.icon = Some("app".to_string());
+
+        let manifest = entry.to_file_manifest("usr/share")?;
+
+        assert_eq!(
+            manifest
+                .iter_entries()
+                .map(|(path, _)| path.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("usr/share/applications/com.example.App.desktop"),
+                PathBuf::from("usr/share/icons/hicolor/48x48/apps/app.png"),
+                PathBuf::from("usr/share/icons/hicolor/scalable/apps/app.svg"),
+            ]
+        );
+        assert_eq!(
+            manifest.get("usr/share/icons/hicolor/48x48/apps/app.png"),
+            Some(&FileEntry {
+                data: FileData::Path(PathBuf::from("app-48.png")),
+                executable: false,
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -19,5 +19,10 @@ Rust crate and utility. It just happens to be developed alongside
 PyOxidizer.
 */
 
+pub mod archive;
+pub mod desktop_entry;
+pub mod man_pages;
 pub mod starlark;
+pub mod systemd_unit;
 pub mod tarball;
+pub mod tool_pipeline;
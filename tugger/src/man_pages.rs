@@ -0,0 +1,321 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Install man pages.
+
+Man pages are installed into `share/man/man<N>/<name>.<section>`, where `N`
+is the leading digit of the section. Debian policy requires man pages to be
+compressed with gzip and RPM builds compress them automatically, so
+[ManPages] compresses them by default. The gzip header doesn't record a
+file name or modification time, so output is reproducible.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    duct::cmd,
+    flate2::{read::GzDecoder, write::GzEncoder, Compression},
+    std::{
+        io::{Read, Write},
+        path::{Path, PathBuf},
+    },
+    tugger_file_manifest::{FileEntry, FileManifest},
+};
+
+/// Ensure a string is a valid man page section.
+///
+/// Sections begin with a digit from 1 to 9, optionally followed by a suffix,
+/// e.g. `3pm`.
+fn validate_section(section: &str) -> Result<()> {
+    let mut chars = section.chars();
+
+    match chars.next() {
+        Some('1'..='9') if chars.all(|c| c.is_ascii_alphanumeric()) => Ok(()),
+        _ => Err(anyhow!(
+            "{:?} is not a valid man page section; must begin with a digit from 1 to 9",
+            section
+        )),
+    }
+}
+
+/// Compress man page content with gzip.
+pub fn gzip_man_page(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data)?;
+
+    Ok(encoder.finish()?)
+}
+
+/// A man page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManPage {
+    /// Name of the page, e.g. the name of the command it documents.
+    pub name: String,
+
+    /// Section of the page, e.g. `1`.
+    pub section: String,
+
+    /// Uncompressed content of the page.
+    pub data: Vec<u8>,
+}
+
+impl ManPage {
+    /// Create a new instance.
+    pub fn new(name: impl ToString, section: impl ToString, data: Vec<u8>) -> Result<Self> {
+        let name = name.to_string();
+        let section = section.to_string();
+
+        if name.is_empty() || name.contains('/') {
+            return Err(anyhow!("{:?} is not a valid man page name", name));
+        }
+        validate_section(&section)?;
+
+        Ok(Self {
+            name,
+            section,
+            data,
+        })
+    }
+
+    /// Create an instance from a file.
+    ///
+    /// The file name must be `<name>.<section>`, optionally followed by `.gz`,
+    /// in which case the file is decompressed. If `section` is given, the
+    /// file name is `<name>` followed by any extension.
+    pub fn from_path(path: impl AsRef<Path>, section: Option<&str>) -> Result<Self> {
+        let path = path.as_ref();
+
+        let mut data =
+            std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("unable to resolve file name of {}", path.display()))?;
+
+        let file_name = if let Some(stem) = file_name.strip_suffix(".gz") {
+            let mut decompressed = vec![];
+            GzDecoder::new(&data[..])
+                .read_to_end(&mut decompressed)
+                .with_context(|| format!("decompressing {}", path.display()))?;
+            data = decompressed;
+
+            stem
+        } else {
+            file_name
+        };
+
+        match (file_name.rsplit_once('.'), section) {
+            (Some((name, _)), Some(section)) => Self::new(name, section, data),
+            (None, Some(section)) => Self::new(file_name, section, data),
+            (Some((name, section)), None) => Self::new(name, section, data),
+            (None, None) => Err(anyhow!(
+                "unable to determine section of man page {}; name it <name>.<section> or specify the section",
+                path.display()
+            )),
+        }
+    }
+
+    /// Create an instance from the standard output of a command.
+    pub fn from_command(
+        name: impl ToString,
+        section: impl ToString,
+        program: &str,
+        args: &[String],
+        cwd: &Path,
+    ) -> Result<Self> {
+        let output = cmd(program, args)
+            .dir(cwd)
+            .stdout_capture()
+            .unchecked()
+            .run()
+            .with_context(|| format!("running {}", program))?;
+
+        if !output.status.success() {
+            return Err(anyhow!("error running {}: {}", program, output.status));
+        }
+        if output.stdout.is_empty() {
+            return Err(anyhow!("{} did not produce a man page", program));
+        }
+
+        Self::new(name, section, output.stdout)
+    }
+
+    /// Path of the page relative to a `share` directory.
+    pub fn install_path(&self, compress: bool) -> PathBuf {
+        // validate_section() ensures the section begins with a digit.
+        let section_dir = format!("man{}", &self.section[0..1]);
+
+        let file_name = if compress {
+            format!("{}.{}.gz", self.name, self.section)
+        } else {
+            format!("{}.{}", self.name, self.section)
+        };
+
+        PathBuf::from("man").join(section_dir).join(file_name)
+    }
+}
+
+/// A collection of man pages.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ManPages {
+    pub pages: Vec<ManPage>,
+}
+
+impl ManPages {
+    /// Obtain a [FileManifest] with all pages.
+    ///
+    /// Paths in the manifest are relative to `prefix`, which should be the
+    /// `share` directory files are installed into, e.g. `usr/share`. Pages
+    /// are gzip compressed if `compress` is true.
+    pub fn to_file_manifest(
+        &self,
+        prefix: impl AsRef<Path>,
+        compress: bool,
+    ) -> Result<FileManifest> {
+        let prefix = prefix.as_ref();
+        let mut manifest = FileManifest::default();
+
+        for page in &self.pages {
+            let data = if compress {
+                gzip_man_page(&page.data)?
+            } else {
+                page.data.clone()
+            };
+
+            manifest.add_file_entry(
+                prefix.join(page.install_path(compress)),
+                FileEntry {
+                    data: data.into(),
+                    executable: false,
+                },
+            )?;
+        }
+
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, tugger_common::testutil::*};
+
+    #[test]
+    fn test_new() {
+        assert!(ManPage::new("app", "1", vec![]).is_ok());
+        assert!(ManPage::new("app", "3pm", vec![]).is_ok());
+        assert!(ManPage::new("app", "0", vec![]).is_err());
+        assert!(ManPage::new("app", "x", vec![]).is_err());
+        assert!(ManPage::new("app", "1 ", vec![]).is_err());
+        assert!(ManPage::new("", "1", vec![]).is_err());
+        assert!(ManPage::new("a/b", "1", vec![]).is_err());
+    }
+
+    #[test]
+    fn test_install_path() -> Result<()> {
+        let page = ManPage::new("app.conf", "5", vec![])?;
+        assert_eq!(
+            page.install_path(false),
+            PathBuf::from("man/man5/app.conf.5")
+        );
+
+        let page = ManPage::new("App::Module", "3pm", vec![])?;
+        assert_eq!(
+            page.install_path(true),
+            PathBuf::from("man/man3/App::Module.3pm.gz")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_path() -> Result<()> {
+        let temp_dir = DEFAULT_TEMP_DIR.path().join("test_man_pages_from_path");
+        std::fs::create_dir_all(&temp_dir)?;
+
+        let path = temp_dir.join("app.1");
+        std::fs::write(&path, b".TH APP 1\n")?;
+        let page = ManPage::from_path(&path, None)?;
+        assert_eq!(page.name, "app");
+        assert_eq!(page.section, "1");
+        assert_eq!(page.data, b".TH APP 1\n".to_vec());
+
+        let page = ManPage::from_path(&path, Some("8"))?;
+        assert_eq!(page.name, "app");
+        assert_eq!(page.section, "8");
+
+        let path = temp_dir.join("app-config.5.gz");
+        std::fs::write(&path, gzip_man_page(b".TH APP-CONFIG 5\n")?)?;
+        let page = ManPage::from_path(&path, None)?;
+        assert_eq!(page.name, "app-config");
+        assert_eq!(page.section, "5");
+        assert_eq!(page.data, b".TH APP-CONFIG 5\n".to_vec());
+
+        let path = temp_dir.join("app");
+        std::fs::write(&path, b"")?;
+        assert!(ManPage::from_path(&path, None).is_err());
+        assert_eq!(ManPage::from_path(&path, Some("1"))?.name, "app");
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_from_command() -> Result<()> {
+        let cwd = DEFAULT_TEMP_DIR.path();
+
+        let page = ManPage::from_command(
+            "app",
+            "1",
+            "sh",
+            &["-c".to_string(), "echo .TH APP 1".to_string()],
+            cwd,
+        )?;
+        assert_eq!(page.data, b".TH APP 1\n".to_vec());
+
+        assert!(ManPage::from_command(
+            "app",
+            "1",
+            "sh",
+            &["-c".to_string(), "exit 1".to_string()],
+            cwd
+        )
+        .is_err());
+        assert!(ManPage::from_command("app", "1", "true", &[], cwd).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_file_manifest() -> Result<()> {
+        let pages = ManPages {
+            pages: vec![ManPage::new("app", "1", b".TH APP 1\n".to_vec())?],
+        };
+
+        let manifest = pages.to_file_manifest("usr/share", false)?;
+        assert_eq!(
+            manifest
+                .get("usr/share/man/man1/app.1")
+                .unwrap()
+                .data
+                .resolve()?,
+            b".TH APP 1\n".to_vec()
+        );
+
+        let manifest = pages.to_file_manifest("usr/share", true)?;
+        let data = manifest
+            .get("usr/share/man/man1/app.1.gz")
+            .unwrap()
+            .data
+            .resolve()?;
+        let mut decompressed = vec![];
+        GzDecoder::new(&data[..]).read_to_end(&mut decompressed)?;
+        assert_eq!(decompressed, b".TH APP 1\n".to_vec());
+
+        // Output is reproducible.
+        assert_eq!(gzip_man_page(b"data")?, gzip_man_page(b"data")?);
+
+        Ok(())
+    }
+}
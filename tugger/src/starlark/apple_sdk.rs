@@ -0,0 +1,317 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, UnsupportedOperation, ValueError},
+            none::NoneType,
+            {Immutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{get_context_value, optional_str_arg, EnvironmentContext},
+    tugger_apple::{find_default_developer_sdks, AppleSdk, AppleSdkSupportedTarget},
+};
+
+fn apple_sdk_error(label: &str, message: String) -> ValueError {
+    ValueError::from(RuntimeError {
+        code: "TUGGER_APPLE_SDK",
+        message,
+        label: label.to_string(),
+    })
+}
+
+fn optional_string_value(value: &Option<String>) -> Value {
+    match value {
+        Some(value) => Value::from(value.as_str()),
+        None => Value::from(NoneType::None),
+    }
+}
+
+fn string_list_value(values: &[String]) -> Value {
+    Value::from(
+        values
+            .iter()
+            .map(|v| Value::from(v.as_str()))
+            .collect::<Vec<_>>(),
+    )
+}
+
+#[derive(Clone, Debug)]
+pub struct AppleSdkValue {
+    pub inner: AppleSdk,
+}
+
+impl TypedValue for AppleSdkValue {
+    type Holder = Immutable<AppleSdkValue>;
+    const TYPE: &'static str = "AppleSdk";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+
+    fn to_str(&self) -> String {
+        format!("{}<name={}>", Self::TYPE, self.inner.name)
+    }
+
+    fn to_repr(&self) -> String {
+        self.to_str()
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        let v = match attribute {
+            "path" => Value::from(format!("{}", self.inner.path.display())),
+            "is_symlink" => Value::from(self.inner.is_symlink),
+            "platform_name" => Value::from(self.inner.platform_name.as_str()),
+            "name" => Value::from(self.inner.name.as_str()),
+            "version" => Value::from(self.inner.version.as_str()),
+            "display_name" => Value::from(self.inner.display_name.as_str()),
+            "default_deployment_target" => {
+                Value::from(self.inner.default_deployment_target.as_str())
+            }
+            "maximum_deployment_target" => {
+                Value::from(self.inner.maximum_deployment_target.as_str())
+            }
+            "default_variant" => optional_string_value(&self.inner.default_variant),
+            "supported_targets" => {
+                let mut names = self
+                    .inner
+                    .supported_targets
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                names.sort();
+
+                string_list_value(&names)
+            }
+            "variants" => string_list_value(
+                &self
+                    .inner
+                    .variants
+                    .iter()
+                    .map(|v| v.name.clone())
+                    .collect::<Vec<_>>(),
+            ),
+            _ => {
+                return Err(ValueError::OperationNotSupported {
+                    op: UnsupportedOperation::GetAttr(attribute.to_string()),
+                    left: Self::TYPE.to_string(),
+                    right: None,
+                })
+            }
+        };
+
+        Ok(v)
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(matches!(
+            attribute,
+            "path"
+                | "is_symlink"
+                | "platform_name"
+                | "name"
+                | "version"
+                | "display_name"
+                | "default_deployment_target"
+                | "maximum_deployment_target"
+                | "default_variant"
+                | "supported_targets"
+                | "variants"
+        ))
+    }
+}
+
+// Starlark functions.
+impl AppleSdkValue {
+    /// apple_sdk_from_directory(path)
+    fn from_directory(type_values: &TypeValues, path: String) -> ValueResult {
+        let context_value = get_context_value(type_values)?;
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let inner = AppleSdk::from_directory(&context.cwd().join(path))
+            .map_err(|e| apple_sdk_error("apple_sdk_from_directory()", format!("{:?}", e)))?;
+
+        Ok(Value::new(AppleSdkValue { inner }))
+    }
+
+    /// find_apple_sdks()
+    fn find_default() -> ValueResult {
+        let mut sdks = find_default_developer_sdks()
+            .map_err(|e| apple_sdk_error("find_apple_sdks()", format!("{:?}", e)))?
+            .into_iter()
+            .filter(|sdk| !sdk.is_symlink)
+            .collect::<Vec<_>>();
+        sdks.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Value::from(
+            sdks.into_iter()
+                .map(|inner| Value::new(AppleSdkValue { inner }))
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    fn supported_target(
+        &self,
+        label: &str,
+        target: &str,
+    ) -> Result<&AppleSdkSupportedTarget, ValueError> {
+        self.inner.supported_target(target).ok_or_else(|| {
+            apple_sdk_error(
+                label,
+                format!("SDK {} does not support target {}", self.inner.name, target),
+            )
+        })
+    }
+
+    /// AppleSdk.archs(target)
+    fn archs(&self, target: String) -> ValueResult {
+        Ok(string_list_value(
+            &self.supported_target("archs()", &target)?.archs,
+        ))
+    }
+
+    /// AppleSdk.deployment_targets(target)
+    fn deployment_targets(&self, target: String) -> ValueResult {
+        Ok(string_list_value(
+            &self
+                .supported_target("deployment_targets()", &target)?
+                .valid_deployment_targets,
+        ))
+    }
+
+    /// AppleSdk.validate_target(target, arch=None, deployment_target=None)
+    fn validate_target(
+        &self,
+        target: String,
+        arch: &Value,
+        deployment_target: &Value,
+    ) -> ValueResult {
+        let arch = optional_str_arg("arch", arch)?;
+        let deployment_target = optional_str_arg("deployment_target", deployment_target)?;
+
+        self.inner
+            .validate_target(&target, arch.as_deref(), deployment_target.as_deref())
+            .map_err(|e| apple_sdk_error("validate_target()", e.to_string()))?;
+
+        Ok(Value::new(NoneType::None))
+    }
+}
+
+starlark_module! { apple_sdk_module =>
+    apple_sdk_from_directory(env env, path: String) {
+        AppleSdkValue::from_directory(env, path)
+    }
+
+    find_apple_sdks() {
+        AppleSdkValue::find_default()
+    }
+
+    AppleSdk.archs(this, target: String) {
+        let this = this.downcast_ref::<AppleSdkValue>().unwrap();
+        this.archs(target)
+    }
+
+    AppleSdk.deployment_targets(this, target: String) {
+        let this = this.downcast_ref::<AppleSdkValue>().unwrap();
+        this.deployment_targets(target)
+    }
+
+    AppleSdk.validate_target(
+        this,
+        target: String,
+        arch = NoneType::None,
+        deployment_target = NoneType::None
+    ) {
+        let this = this.downcast_ref::<AppleSdkValue>().unwrap();
+        this.validate_target(target, &arch, &deployment_target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, crate::starlark::testutil::*, anyhow::Result,
+        tugger_common::testutil::DEFAULT_TEMP_DIR,
+    };
+
+    #[test]
+    fn test_from_directory() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        let sdk_dir = DEFAULT_TEMP_DIR
+            .path()
+            .join("test_apple_sdk_from_directory")
+            .join("MacOSX11.1.sdk");
+        std::fs::create_dir_all(&sdk_dir)?;
+        std::fs::write(
+            sdk_dir.join("SDKSettings.json"),
+            br#"{
+                "CanonicalName": "macosx11.1",
+                "DefaultDeploymentTarget": "11.1",
+                "DefaultProperties": {"PLATFORM_NAME": "macosx"},
+                "DisplayName": "macOS 11.1",
+                "MaximumDeploymentTarget": "11.1.99",
+                "MinimalDisplayName": "11.1",
+                "SupportedTargets": {
+                    "macosx": {
+                        "Archs": ["x86_64", "arm64"],
+                        "DefaultDeploymentTarget": "11.1",
+                        "MinimumDeploymentTarget": "10.9",
+                        "ValidDeploymentTargets": ["10.9", "10.15", "11.0", "11.1"]
+                    }
+                },
+                "Variants": [{"Name": "macos", "BuildSettings": {}}],
+                "Version": "11.1"
+            }"#,
+        )?;
+
+        env.eval(&format!(
+            "sdk = apple_sdk_from_directory({:?})",
+            sdk_dir.display().to_string()
+        ))?;
+
+        let v = env.eval("sdk")?;
+        assert_eq!(v.get_type(), "AppleSdk");
+        let v = v.downcast_ref::<AppleSdkValue>().unwrap();
+        assert_eq!(v.inner.name, "macosx11.1");
+
+        assert_eq!(env.eval("sdk.platform_name")?.to_str(), "macosx");
+        assert_eq!(env.eval("sdk.version")?.to_str(), "11.1");
+        assert_eq!(env.eval("sdk.default_variant")?.get_type(), "NoneType");
+        assert!(env.eval("sdk.supported_targets == ['macosx']")?.to_bool());
+        assert!(env.eval("sdk.variants == ['macos']")?.to_bool());
+        assert!(env
+            .eval("sdk.archs('macosx') == ['x86_64', 'arm64']")?
+            .to_bool());
+        assert_eq!(
+            env.eval("sdk.deployment_targets('macosx')")?
+                .length()
+                .unwrap(),
+            4
+        );
+        assert!(env.eval("sdk.archs('iphoneos')").is_err());
+
+        env.eval("sdk.validate_target('macosx', arch = 'arm64', deployment_target = '10.15')")?;
+        assert!(env
+            .eval("sdk.validate_target('macosx', arch = 'i386')")
+            .is_err());
+        assert!(env
+            .eval("sdk.validate_target('macosx', deployment_target = '10.4')")
+            .is_err());
+
+        assert!(env
+            .eval("apple_sdk_from_directory('does-not-exist.sdk')")
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    anyhow::{anyhow, Result},
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            {Immutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{
+        get_context_value, optional_dict_arg, optional_str_arg, EnvironmentContext,
+    },
+    std::{
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+    tugger_apple::KeychainSigner,
+    tugger_common::signing::{CommandSigner, SigningCallback, PATH_PLACEHOLDER},
+    tugger_windows::{
+        FileBasedX509SigningCertificate, ProviderX509SigningCertificate, SigntoolSign,
+        StoreX509SigningCertificate, TimestampServer, X509SigningCertificate,
+    },
+};
+
+fn signing_error(label: &str, message: String) -> ValueError {
+    ValueError::from(RuntimeError {
+        code: "TUGGER_CODE_SIGNING",
+        message,
+        label: label.to_string(),
+    })
+}
+
+/// Resolve a path relative to the directory of the current config file.
+fn resolve_path(type_values: &TypeValues, path: &str) -> Result<PathBuf, ValueError> {
+    let context_value = get_context_value(type_values)?;
+    let context = context_value
+        .downcast_ref::<EnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    Ok(context.cwd().join(path))
+}
+
+/// Obtain signtool settings for a certificate.
+fn signtool_sign(
+    certificate: X509SigningCertificate,
+    timestamp_url: Option<String>,
+) -> SigntoolSign {
+    let mut sign = SigntoolSign::new(certificate);
+    sign.file_digest_algorithm("sha256");
+
+    if let Some(url) = timestamp_url {
+        sign.timestamp_server(TimestampServer::Rfc3161(url, "sha256".to_string()));
+    }
+
+    sign
+}
+
+/// Signs with a PFX file whose password is read from the environment.
+///
+/// The password is resolved when signing so configuration files can be
+/// evaluated without it.
+#[derive(Clone, Debug)]
+struct PfxFileSigner {
+    path: PathBuf,
+    password_env: Option<String>,
+    timestamp_url: Option<String>,
+}
+
+impl SigningCallback for PfxFileSigner {
+    fn sign(&self, logger: &slog::Logger, path: &Path) -> Result<()> {
+        let mut certificate = FileBasedX509SigningCertificate::new(&self.path);
+
+        if let Some(key) = &self.password_env {
+            certificate.set_password(std::env::var(key).map_err(|_| {
+                anyhow!(
+                    "environment variable {} must be set to the password of {}",
+                    key,
+                    self.path.display()
+                )
+            })?);
+        }
+
+        signtool_sign(certificate.into(), self.timestamp_url.clone()).sign(logger, path)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CodeSignerValue {
+    pub inner: Arc<dyn SigningCallback>,
+}
+
+impl TypedValue for CodeSignerValue {
+    type Holder = Immutable<CodeSignerValue>;
+    const TYPE: &'static str = "CodeSigner";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl CodeSignerValue {
+    fn new(inner: impl SigningCallback + 'static) -> ValueResult {
+        Ok(Value::new(CodeSignerValue {
+            inner: Arc::new(inner),
+        }))
+    }
+}
+
+starlark_module! { code_signing_module =>
+    code_signer_pfx_file(
+        env env,
+        path: String,
+        password_env = NoneType::None,
+        timestamp_url = NoneType::None
+    ) {
+        CodeSignerValue::new(PfxFileSigner {
+            path: resolve_path(env, &path)?,
+            password_env: optional_str_arg("password_env", &password_env)?,
+            timestamp_url: optional_str_arg("timestamp_url", &timestamp_url)?,
+        })
+    }
+
+    code_signer_windows_store(
+        thumbprint: String,
+        store: String = "My".to_string(),
+        machine_store: bool = false,
+        timestamp_url = NoneType::None
+    ) {
+        let timestamp_url = optional_str_arg("timestamp_url", &timestamp_url)?;
+
+        if thumbprint.len() != 40 || !thumbprint.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(signing_error(
+                "code_signer_windows_store()",
+                format!("thumbprint must be a 40 character hex SHA-1; got {}", thumbprint),
+            ));
+        }
+
+        let mut certificate = StoreX509SigningCertificate::new(thumbprint);
+        certificate.set_store(store);
+        certificate.set_machine_store(machine_store);
+
+        CodeSignerValue::new(signtool_sign(certificate.into(), timestamp_url))
+    }
+
+    code_signer_windows_provider(
+        env env,
+        certificate_path: String,
+        provider: String,
+        key_container: String,
+        timestamp_url = NoneType::None
+    ) {
+        let timestamp_url = optional_str_arg("timestamp_url", &timestamp_url)?;
+
+        let certificate = ProviderX509SigningCertificate::new(
+            resolve_path(env, &certificate_path)?,
+            provider,
+            key_container,
+        );
+
+        CodeSignerValue::new(signtool_sign(certificate.into(), timestamp_url))
+    }
+
+    code_signer_macos_keychain(identity: String, keychain = NoneType::None, timestamp: bool = true) {
+        let mut signer = KeychainSigner::new(identity);
+        signer.keychain = optional_str_arg("keychain", &keychain)?.map(PathBuf::from);
+        signer.timestamp = timestamp;
+
+        CodeSignerValue::new(signer)
+    }
+
+    code_signer_command(program: String, args: Vec<String>, env = NoneType::None) {
+        optional_dict_arg("env", "string", "string", &env)?;
+
+        if !args.iter().any(|arg| arg.contains(PATH_PLACEHOLDER)) {
+            return Err(signing_error(
+                "code_signer_command()",
+                "args must reference the file to sign via {path}".to_string(),
+            ));
+        }
+
+        let mut signer = CommandSigner::new(program);
+        signer.args = args;
+
+        if env.get_type() == "dict" {
+            for key in &env.iter()? {
+                signer
+                    .env
+                    .insert(key.to_str(), env.at(key.clone())?.to_str());
+            }
+        }
+
+        CodeSignerValue::new(signer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::starlark::testutil::*, anyhow::Result};
+
+    fn signer_debug(v: &Value) -> String {
+        assert_eq!(v.get_type(), "CodeSigner");
+        format!("{:?}", v.downcast_ref::<CodeSignerValue>().unwrap().inner)
+    }
+
+    #[test]
+    fn test_pfx_file() -> Result<()> {
+        let v = starlark_ok("code_signer_pfx_file('signing.pfx', password_env = 'PFX_PASSWORD')");
+        let debug = signer_debug(&v);
+        assert!(debug.contains("PfxFileSigner"));
+        assert!(debug.contains("signing.pfx"));
+        assert!(debug.contains("PFX_PASSWORD"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_store() -> Result<()> {
+        let v = starlark_ok(
+            "code_signer_windows_store('0123456789abcdef0123456789abcdef01234567', machine_store = True)",
+        );
+        let debug = signer_debug(&v);
+        assert!(debug.contains("0123456789abcdef0123456789abcdef01234567"));
+        assert!(debug.contains("machine_store: true"));
+
+        let mut env = StarlarkEnvironment::new()?;
+        assert!(env.eval("code_signer_windows_store('abc')").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_provider() {
+        let v = starlark_ok(
+            "code_signer_windows_provider('cert.cer', 'Google Cloud KMS Provider', 'projects/p/cryptoKeys/k')",
+        );
+        let debug = signer_debug(&v);
+        assert!(debug.contains("Google Cloud KMS Provider"));
+        assert!(debug.contains("projects/p/cryptoKeys/k"));
+    }
+
+    #[test]
+    fn test_macos_keychain() {
+        let v = starlark_ok("code_signer_macos_keychain('Developer ID Application: Example', keychain = 'build.keychain')");
+        let debug = signer_debug(&v);
+        assert!(debug.contains("KeychainSigner"));
+        assert!(debug.contains("build.keychain"));
+    }
+
+    #[test]
+    fn test_command() -> Result<()> {
+        let v = starlark_ok(
+            "code_signer_command('jsign', ['--storetype', 'AWS', '{path}'], env = {'AWS_REGION': 'us-west-2'})",
+        );
+        let debug = signer_debug(&v);
+        assert!(debug.contains("jsign"));
+        assert!(debug.contains("AWS_REGION"));
+
+        let mut env = StarlarkEnvironment::new()?;
+        assert!(env
+            .eval("code_signer_command('jsign', ['app.exe'])")
+            .is_err());
+
+        Ok(())
+    }
+}
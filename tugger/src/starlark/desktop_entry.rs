@@ -0,0 +1,250 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::{desktop_entry::DesktopEntry, starlark::file_resource::FileManifestValue},
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{
+        get_context_value, optional_int_arg, optional_list_arg, optional_str_arg,
+        EnvironmentContext, ResolvedTarget, ResolvedTargetValue, RunMode,
+    },
+};
+
+fn desktop_entry_error(label: &str, message: String) -> ValueError {
+    ValueError::from(RuntimeError {
+        code: "TUGGER_DESKTOP_ENTRY",
+        message,
+        label: label.to_string(),
+    })
+}
+
+fn optional_str_list(name: &str, value: &Value) -> Result<Vec<String>, ValueError> {
+    optional_list_arg(name, "string", value)?;
+
+    if value.get_type() == "list" {
+        Ok(value.iter()?.iter().map(|x| x.to_str()).collect())
+    } else {
+        Ok(vec![])
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DesktopEntryValue {
+    pub inner: DesktopEntry,
+}
+
+impl TypedValue for DesktopEntryValue {
+    type Holder = Mutable<DesktopEntryValue>;
+    const TYPE: &'static str = "DesktopEntry";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl DesktopEntryValue {
+    /// DesktopEntry(id, name, exec, icon=None, generic_name=None, comment=None, categories=None, keywords=None, mime_types=None, terminal=False)
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_from_args(
+        id: String,
+        name: String,
+        exec: String,
+        icon: &Value,
+        generic_name: &Value,
+        comment: &Value,
+        categories: &Value,
+        keywords: &Value,
+        mime_types: &Value,
+        terminal: bool,
+    ) -> ValueResult {
+        let mut inner = DesktopEntry::new(id, name, exec)
+            .map_err(|e| desktop_entry_error("DesktopEntry()", e.to_string()))?;
+
+        inner.icon = optional_str_arg("icon", icon)?;
+        inner.generic_name = optional_str_arg("generic_name", generic_name)?;
+        inner.comment = optional_str_arg("comment", comment)?;
+        inner.categories = optional_str_list("categories", categories)?;
+        inner.keywords = optional_str_list("keywords", keywords)?;
+        inner.mime_types = optional_str_list("mime_types", mime_types)?;
+        inner.terminal = terminal;
+
+        Ok(Value::new(DesktopEntryValue { inner }))
+    }
+
+    /// DesktopEntry.add_icon(path, size=None)
+    pub fn add_icon(&mut self, path: String, size: &Value) -> ValueResult {
+        let size = match optional_int_arg("size", size)? {
+            Some(size) if size <= 0 || size > u32::MAX as i64 => {
+                return Err(desktop_entry_error(
+                    "add_icon()",
+                    format!("invalid icon size: {}", size),
+                ));
+            }
+            size => size.map(|size| size as u32),
+        };
+
+        self.inner
+            .add_icon(path, size)
+            .map_err(|e| desktop_entry_error("add_icon()", e.to_string()))?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// DesktopEntry.to_file_manifest(prefix="usr/share")
+    pub fn to_file_manifest(&self, prefix: String) -> ValueResult {
+        let manifest = self
+            .inner
+            .to_file_manifest(prefix)
+            .map_err(|e| desktop_entry_error("to_file_manifest()", e.to_string()))?;
+
+        Ok(Value::new(FileManifestValue {
+            manifest,
+            run_path: None,
+        }))
+    }
+
+    /// DesktopEntry.build(target)
+    pub fn build(&self, type_values: &TypeValues, target: String) -> ValueResult {
+        let context_value = get_context_value(type_values)?;
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let output_path = context.target_build_path(&target);
+
+        self.inner
+            .to_file_manifest("share")
+            .map_err(|e| desktop_entry_error("build()", e.to_string()))?
+            .materialize_files_with_replace(&output_path)
+            .map_err(|e| desktop_entry_error("build()", e.to_string()))?;
+
+        Ok(Value::new(ResolvedTargetValue {
+            inner: ResolvedTarget {
+                run_mode: RunMode::None,
+                output_path,
+            },
+        }))
+    }
+}
+
+starlark_module! { desktop_entry_module =>
+    #[allow(non_snake_case)]
+    DesktopEntry(
+        id: String,
+        name: String,
+        exec: String,
+        icon = NoneType::None,
+        generic_name = NoneType::None,
+        comment = NoneType::None,
+        categories = NoneType::None,
+        keywords = NoneType::None,
+        mime_types = NoneType::None,
+        terminal: bool = false
+    ) {
+        DesktopEntryValue::new_from_args(
+            id,
+            name,
+            exec,
+            &icon,
+            &generic_name,
+            &comment,
+            &categories,
+            &keywords,
+            &mime_types,
+            terminal
+        )
+    }
+
+    DesktopEntry.add_icon(this, path: String, size = NoneType::None) {
+        let mut this = this.downcast_mut::<DesktopEntryValue>().unwrap().unwrap();
+        this.add_icon(path, &size)
+    }
+
+    DesktopEntry.build(env env, this, target: String) {
+        let this = this.downcast_ref::<DesktopEntryValue>().unwrap();
+        this.build(env, target)
+    }
+
+    DesktopEntry.to_file_manifest(this, prefix: String = "usr/share".to_string()) {
+        let this = this.downcast_ref::<DesktopEntryValue>().unwrap();
+        this.to_file_manifest(prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::starlark::testutil::*, anyhow::Result, std::path::PathBuf};
+
+    #[test]
+    fn test_new() {
+        let v = starlark_ok(
+            "DesktopEntry('com.example.App', 'App', 'app %F', comment = 'An app', categories = ['Utility'], terminal = True)",
+        );
+        assert_eq!(v.get_type(), "DesktopEntry");
+
+        let v = v.downcast_ref::<DesktopEntryValue>().unwrap();
+        assert_eq!(v.inner.id, "com.example.App");
+        assert_eq!(v.inner.exec, "app %F");
+        assert_eq!(v.inner.comment, Some("An app".to_string()));
+        assert_eq!(v.inner.categories, vec!["Utility".to_string()]);
+        assert!(v.inner.terminal);
+    }
+
+    #[test]
+    fn test_invalid_id() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        assert!(env.eval("DesktopEntry('my app', 'App', 'app')").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_file_manifest() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("e = DesktopEntry('com.example.App', 'App', 'app')")?;
+        env.eval("e.add_icon('app.svg')")?;
+        env.eval("e.add_icon('app-48.png', size = 48)")?;
+        assert!(env.eval("e.add_icon('app.png')").is_err());
+        assert!(env.eval("e.add_icon('app.png', size = 0)").is_err());
+
+        let m = env.eval("e.to_file_manifest()")?;
+        assert_eq!(m.get_type(), "FileManifest");
+        let m = m.downcast_ref::<FileManifestValue>().unwrap();
+
+        let mut paths = m
+            .manifest
+            .iter_entries()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("usr/share/applications/com.example.App.desktop"),
+                PathBuf::from("usr/share/icons/hicolor/48x48/apps/com.example.App.png"),
+                PathBuf::from("usr/share/icons/hicolor/scalable/apps/com.example.App.svg"),
+            ]
+        );
+
+        let m = env.eval("e.to_file_manifest(prefix = 'share')")?;
+        let m = m.downcast_ref::<FileManifestValue>().unwrap();
+        assert!(m
+            .manifest
+            .has_path("share/applications/com.example.App.desktop"));
+
+        Ok(())
+    }
+}
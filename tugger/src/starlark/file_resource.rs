@@ -3,6 +3,7 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
+    crate::archive::{extract_archive, ArchiveFormat},
     slog::warn,
     starlark::{
         environment::TypeValues,
@@ -25,8 +26,11 @@ use {
         convert::TryFrom,
         path::{Path, PathBuf},
     },
-    tugger_common::glob::evaluate_glob,
-    tugger_file_manifest::{FileEntry, FileManifest},
+    tugger_common::{
+        glob::evaluate_glob,
+        http::{download_to_path, RemoteContent},
+    },
+    tugger_file_manifest::{FileEntry, FileManifest, FileManifestError},
 };
 
 // TODO merge this into `FileValue`?
@@ -139,17 +143,25 @@ impl FileManifestValue {
         Ok(Value::new(NoneType::None))
     }
 
-    /// FileManifest.add_path(path, strip_prefix, force_read=False)
+    /// FileManifest.add_path(path, strip_prefix, force_read=False, template=False)
     pub fn add_path(
         &mut self,
         path: String,
         strip_prefix: String,
         force_read: bool,
+        template: bool,
     ) -> ValueResult {
         let path = Path::new(&path);
         let strip_prefix = Path::new(&strip_prefix);
 
-        if force_read {
+        if template {
+            path.strip_prefix(strip_prefix)
+                .map_err(FileManifestError::from)
+                .and_then(|manifest_path| {
+                    let entry = FileEntry::try_from(path)?.to_memory()?;
+                    self.manifest.add_template_entry(manifest_path, entry)
+                })
+        } else if force_read {
             self.manifest.add_path_memory(path, strip_prefix)
         } else {
             self.manifest.add_path(path, strip_prefix)
@@ -165,6 +177,47 @@ impl FileManifestValue {
         Ok(Value::new(NoneType::None))
     }
 
+    /// FileManifest.add_symlink(path, target)
+    pub fn add_symlink(&mut self, path: String, target: String) -> ValueResult {
+        self.manifest.add_symlink(&path, &target).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "TUGGER_FILE_MANIFEST",
+                message: e.to_string(),
+                label: "add_symlink()".to_string(),
+            })
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// FileManifest.set_mode(path, mode)
+    pub fn set_mode(&mut self, path: String, mode: i64) -> ValueResult {
+        if !(0..=0o7777).contains(&mode) {
+            return Err(ValueError::from(RuntimeError {
+                code: "TUGGER_FILE_MANIFEST",
+                message: format!("invalid mode: {:o}", mode),
+                label: "set_mode()".to_string(),
+            }));
+        }
+
+        self.manifest.set_mode(&path, mode as u32).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "TUGGER_FILE_MANIFEST",
+                message: e.to_string(),
+                label: "set_mode()".to_string(),
+            })
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// FileManifest.set_template_variable(name, value)
+    pub fn set_template_variable(&mut self, name: String, value: String) -> ValueResult {
+        self.manifest.set_template_variable(name, value);
+
+        Ok(Value::new(NoneType::None))
+    }
+
     /// FileManifest.install(path, replace=true)
     pub fn install(&self, type_values: &TypeValues, path: String, replace: bool) -> ValueResult {
         let raw_context = get_context_value(type_values)?;
@@ -286,11 +339,140 @@ fn starlark_glob(
     }))
 }
 
+/// download_file(url, sha256, filename=None)
+fn starlark_download_file(
+    type_values: &TypeValues,
+    url: String,
+    sha256: String,
+    filename: &Value,
+) -> ValueResult {
+    let filename = optional_str_arg("filename", filename)?;
+
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ValueError::from(RuntimeError {
+            code: "TUGGER",
+            message: format!("{} is not a valid SHA-256 digest", sha256),
+            label: "download_file()".to_string(),
+        }));
+    }
+
+    let filename = match filename {
+        Some(filename) => filename,
+        None => url
+            .split(|c| c == '?' || c == '#')
+            .next()
+            .and_then(|s| s.rsplit('/').next())
+            .unwrap_or_default()
+            .to_string(),
+    };
+
+    if filename.is_empty() || filename.contains('/') || filename.contains('\\') {
+        return Err(ValueError::from(RuntimeError {
+            code: "TUGGER",
+            message: format!(
+                "unable to derive a file name from {}; specify one with filename",
+                url
+            ),
+            label: "download_file()".to_string(),
+        }));
+    }
+
+    let raw_context = get_context_value(type_values)?;
+    let context = raw_context
+        .downcast_ref::<EnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    let dest_dir = context
+        .cache_path()
+        .join("downloads")
+        .join(sha256.to_lowercase());
+    let dest_path = dest_dir.join(&filename);
+
+    std::fs::create_dir_all(&dest_dir)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| {
+            download_to_path(
+                context.logger(),
+                &RemoteContent {
+                    url: url.clone(),
+                    sha256: sha256.clone(),
+                },
+                &dest_path,
+            )
+        })
+        .map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "TUGGER",
+                message: format!("error downloading {}: {:?}", url, e),
+                label: "download_file()".to_string(),
+            })
+        })?;
+
+    Ok(Value::from(dest_path.display().to_string()))
+}
+
+/// extract_archive(path, strip_components=0, format=None)
+fn starlark_extract_archive(
+    type_values: &TypeValues,
+    path: String,
+    strip_components: i64,
+    format: &Value,
+) -> ValueResult {
+    let format = optional_str_arg("format", format)?;
+
+    let format = match format {
+        Some(format) => Some(ArchiveFormat::try_from(format.as_str()).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "TUGGER",
+                message: e,
+                label: "extract_archive()".to_string(),
+            })
+        })?),
+        None => None,
+    };
+
+    if strip_components < 0 {
+        return Err(ValueError::from(RuntimeError {
+            code: "TUGGER",
+            message: "strip_components cannot be negative".to_string(),
+            label: "extract_archive()".to_string(),
+        }));
+    }
+
+    let raw_context = get_context_value(type_values)?;
+    let context = raw_context
+        .downcast_ref::<EnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    let path = context.cwd().join(path);
+
+    let manifest = extract_archive(&path, format, strip_components as usize).map_err(|e| {
+        ValueError::from(RuntimeError {
+            code: "TUGGER",
+            message: format!("{:?}", e),
+            label: "extract_archive()".to_string(),
+        })
+    })?;
+
+    Ok(Value::new(FileManifestValue {
+        manifest,
+        run_path: None,
+    }))
+}
+
 starlark_module! { file_resource_module =>
     glob(env env, include, exclude=NoneType::None, strip_prefix=NoneType::None) {
         starlark_glob(&env, &include, &exclude, &strip_prefix)
     }
 
+    download_file(env env, url: String, sha256: String, filename=NoneType::None) {
+        starlark_download_file(&env, url, sha256, &filename)
+    }
+
+    extract_archive(env env, path: String, strip_components: i64 = 0, format=NoneType::None) {
+        starlark_extract_archive(&env, path, strip_components, &format)
+    }
+
     #[allow(non_snake_case)]
     FileManifest(env _env) {
         FileManifestValue::new_from_args()
@@ -301,9 +483,20 @@ starlark_module! { file_resource_module =>
         this.add_manifest(other)
     }
 
-    FileManifest.add_path(this, path: String, strip_prefix: String, force_read: bool = false) {
+    FileManifest.add_path(
+        this,
+        path: String,
+        strip_prefix: String,
+        force_read: bool = false,
+        template: bool = false
+    ) {
+        let mut this = this.downcast_mut::<FileManifestValue>().unwrap().unwrap();
+        this.add_path(path, strip_prefix, force_read, template)
+    }
+
+    FileManifest.add_symlink(this, path: String, target: String) {
         let mut this = this.downcast_mut::<FileManifestValue>().unwrap().unwrap();
-        this.add_path(path, strip_prefix, force_read)
+        this.add_symlink(path, target)
     }
 
     FileManifest.build(env env, this, target: String) {
@@ -315,6 +508,16 @@ starlark_module! { file_resource_module =>
         let this = this.downcast_ref::<FileManifestValue>().unwrap();
         this.install(&env, path, replace)
     }
+
+    FileManifest.set_mode(this, path: String, mode: i64) {
+        let mut this = this.downcast_mut::<FileManifestValue>().unwrap().unwrap();
+        this.set_mode(path, mode)
+    }
+
+    FileManifest.set_template_variable(this, name: String, value: String) {
+        let mut this = this.downcast_mut::<FileManifestValue>().unwrap().unwrap();
+        this.set_template_variable(name, value)
+    }
 }
 
 #[cfg(test)]
@@ -383,6 +586,132 @@ mod tests {
             })
         );
 
+        Ok(())
+    }
+    #[test]
+    fn test_add_symlink() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        let manifest_value = env.eval("m = FileManifest(); m")?;
+
+        env.eval("m.add_symlink('lib/libfoo.so', 'libfoo.so.1')")?;
+        assert!(env.eval("m.add_symlink('/foo', 'bar')").is_err());
+
+        let manifest = manifest_value.downcast_ref::<FileManifestValue>().unwrap();
+        assert_eq!(
+            manifest.manifest.get_symlink("lib/libfoo.so"),
+            Some(Path::new("libfoo.so.1"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_mode() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        let manifest_value = env.eval("m = FileManifest(); m")?;
+
+        let temp_file = DEFAULT_TEMP_DIR.path().join("test_set_mode");
+        std::fs::write(&temp_file, vec![42])?;
+        let parent = temp_file.parent().unwrap();
+
+        env.eval(&format!(
+            "m.add_path('{}', '{}')",
+            temp_file.display().to_string().escape_default(),
+            parent.display().to_string().escape_default()
+        ))?;
+
+        env.eval("m.set_mode('test_set_mode', 0o755)")?;
+        assert!(env.eval("m.set_mode('missing', 0o755)").is_err());
+        assert!(env.eval("m.set_mode('test_set_mode', -1)").is_err());
+
+        let manifest = manifest_value.downcast_ref::<FileManifestValue>().unwrap();
+        assert_eq!(manifest.manifest.get_mode("test_set_mode"), Some(0o755));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_template() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        let manifest_value = env.eval("m = FileManifest(); m")?;
+
+        let temp_file = DEFAULT_TEMP_DIR.path().join("test_template");
+        std::fs::write(&temp_file, "name = {{ name }}")?;
+        let parent = temp_file.parent().unwrap();
+        let add_path = format!(
+            "m.add_path('{}', '{}', template = True)",
+            temp_file.display().to_string().escape_default(),
+            parent.display().to_string().escape_default()
+        );
+
+        // Variables must be defined before the template is added.
+        assert!(env.eval(&add_path).is_err());
+
+        env.eval("m.set_template_variable('name', 'myapp')")?;
+        env.eval(&add_path)?;
+
+        let manifest = manifest_value.downcast_ref::<FileManifestValue>().unwrap();
+        assert!(manifest.manifest.is_template("test_template"));
+
+        let dest_path = DEFAULT_TEMP_DIR.path().join("test_template_install");
+        manifest
+            .manifest
+            .materialize_files_with_replace(&dest_path)?;
+        assert_eq!(
+            std::fs::read_to_string(dest_path.join("test_template"))?,
+            "name = myapp"
+        );
+
+        Ok(())
+    }
+    #[test]
+    fn test_download_file_bad_digest() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        assert!(env
+            .eval("download_file('https://example.com/foo.tar.gz', 'abc')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_archive() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        let archive_path = DEFAULT_TEMP_DIR.path().join("test_extract_archive.tar");
+        {
+            let mut builder = tar::Builder::new(std::fs::File::create(&archive_path)?);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(2);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, "bundle/index.html", &[42, 42][..])?;
+            builder.finish()?;
+        }
+
+        let manifest_value = env.eval(&format!(
+            "extract_archive('{}', strip_components = 1)",
+            archive_path.display().to_string().escape_default()
+        ))?;
+        assert_eq!(manifest_value.get_type(), "FileManifest");
+
+        let manifest = manifest_value.downcast_ref::<FileManifestValue>().unwrap();
+        assert_eq!(
+            manifest.manifest.get("index.html"),
+            Some(&FileEntry {
+                executable: false,
+                data: vec![42, 42].into(),
+            })
+        );
+
+        assert!(env
+            .eval(&format!(
+                "extract_archive('{}', format = 'rar')",
+                archive_path.display().to_string().escape_default()
+            ))
+            .is_err());
+
         Ok(())
     }
 }
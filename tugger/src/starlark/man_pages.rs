@@ -0,0 +1,265 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::{
+        man_pages::{ManPage, ManPages},
+        starlark::file_resource::FileManifestValue,
+    },
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{
+        get_context_value, optional_list_arg, optional_str_arg, EnvironmentContext, ResolvedTarget,
+        ResolvedTargetValue, RunMode,
+    },
+};
+
+fn man_pages_error(label: &str, message: String) -> ValueError {
+    ValueError::from(RuntimeError {
+        code: "TUGGER_MAN_PAGES",
+        message,
+        label: label.to_string(),
+    })
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ManPagesValue {
+    pub inner: ManPages,
+}
+
+impl TypedValue for ManPagesValue {
+    type Holder = Mutable<ManPagesValue>;
+    const TYPE: &'static str = "ManPages";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl ManPagesValue {
+    /// ManPages()
+    pub fn new_from_args() -> ValueResult {
+        Ok(Value::new(ManPagesValue::default()))
+    }
+
+    /// ManPages.add_file(path, section=None)
+    pub fn add_file(
+        &mut self,
+        type_values: &TypeValues,
+        path: String,
+        section: &Value,
+    ) -> ValueResult {
+        let section = optional_str_arg("section", section)?;
+
+        let context_value = get_context_value(type_values)?;
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let page = ManPage::from_path(context.cwd().join(path), section.as_deref())
+            .map_err(|e| man_pages_error("add_file()", format!("{:?}", e)))?;
+
+        self.inner.pages.push(page);
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// ManPages.add_command(name, program, args=None, section="1")
+    pub fn add_command(
+        &mut self,
+        type_values: &TypeValues,
+        name: String,
+        program: String,
+        args: &Value,
+        section: String,
+    ) -> ValueResult {
+        optional_list_arg("args", "string", args)?;
+
+        let args = if args.get_type() == "list" {
+            args.iter()?.iter().map(|x| x.to_str()).collect()
+        } else {
+            vec![]
+        };
+
+        let context_value = get_context_value(type_values)?;
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let page = ManPage::from_command(name, section, &program, &args, context.cwd())
+            .map_err(|e| man_pages_error("add_command()", format!("{:?}", e)))?;
+
+        self.inner.pages.push(page);
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// ManPages.to_file_manifest(prefix="usr/share", compress=True)
+    pub fn to_file_manifest(&self, prefix: String, compress: bool) -> ValueResult {
+        let manifest = self
+            .inner
+            .to_file_manifest(prefix, compress)
+            .map_err(|e| man_pages_error("to_file_manifest()", e.to_string()))?;
+
+        Ok(Value::new(FileManifestValue {
+            manifest,
+            run_path: None,
+        }))
+    }
+
+    /// ManPages.build(target)
+    pub fn build(&self, type_values: &TypeValues, target: String) -> ValueResult {
+        let context_value = get_context_value(type_values)?;
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let output_path = context.target_build_path(&target);
+
+        self.inner
+            .to_file_manifest("share", true)
+            .map_err(|e| man_pages_error("build()", e.to_string()))?
+            .materialize_files_with_replace(&output_path)
+            .map_err(|e| man_pages_error("build()", e.to_string()))?;
+
+        Ok(Value::new(ResolvedTargetValue {
+            inner: ResolvedTarget {
+                run_mode: RunMode::None,
+                output_path,
+            },
+        }))
+    }
+}
+
+starlark_module! { man_pages_module =>
+    #[allow(non_snake_case)]
+    ManPages() {
+        ManPagesValue::new_from_args()
+    }
+
+    ManPages.add_command(
+        env env,
+        this,
+        name: String,
+        program: String,
+        args = NoneType::None,
+        section: String = "1".to_string()
+    ) {
+        let mut this = this.downcast_mut::<ManPagesValue>().unwrap().unwrap();
+        this.add_command(env, name, program, &args, section)
+    }
+
+    ManPages.add_file(env env, this, path: String, section = NoneType::None) {
+        let mut this = this.downcast_mut::<ManPagesValue>().unwrap().unwrap();
+        this.add_file(env, path, &section)
+    }
+
+    ManPages.build(env env, this, target: String) {
+        let this = this.downcast_ref::<ManPagesValue>().unwrap();
+        this.build(env, target)
+    }
+
+    ManPages.to_file_manifest(
+        this,
+        prefix: String = "usr/share".to_string(),
+        compress: bool = true
+    ) {
+        let this = this.downcast_ref::<ManPagesValue>().unwrap();
+        this.to_file_manifest(prefix, compress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, crate::starlark::testutil::*, anyhow::Result, std::path::PathBuf,
+        tugger_common::testutil::DEFAULT_TEMP_DIR,
+    };
+
+    #[test]
+    fn test_new() {
+        let v = starlark_ok("ManPages()");
+        assert_eq!(v.get_type(), "ManPages");
+
+        let v = v.downcast_ref::<ManPagesValue>().unwrap();
+        assert!(v.inner.pages.is_empty());
+    }
+
+    #[test]
+    fn test_add_file() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        let temp_dir = DEFAULT_TEMP_DIR.path().join("test_man_pages_add_file");
+        std::fs::create_dir_all(&temp_dir)?;
+        let path = temp_dir.join("app.1");
+        std::fs::write(&path, b".TH APP 1\n")?;
+
+        env.eval("pages = ManPages()")?;
+        env.eval(&format!("pages.add_file({:?})", path.display().to_string()))?;
+        env.eval(&format!(
+            "pages.add_file({:?}, section = '8')",
+            path.display().to_string()
+        ))?;
+        assert!(env
+            .eval(&format!(
+                "pages.add_file({:?}, section = 'x')",
+                path.display().to_string()
+            ))
+            .is_err());
+        assert!(env.eval("pages.add_file('does-not-exist.1')").is_err());
+
+        let m = env.eval("pages.to_file_manifest()")?;
+        assert_eq!(m.get_type(), "FileManifest");
+        let m = m.downcast_ref::<FileManifestValue>().unwrap();
+
+        let mut paths = m
+            .manifest
+            .iter_entries()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("usr/share/man/man1/app.1.gz"),
+                PathBuf::from("usr/share/man/man8/app.8.gz"),
+            ]
+        );
+
+        let m = env.eval("pages.to_file_manifest(prefix = 'share', compress = False)")?;
+        let m = m.downcast_ref::<FileManifestValue>().unwrap();
+        assert!(m.manifest.has_path("share/man/man1/app.1"));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_add_command() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("pages = ManPages()")?;
+        env.eval("pages.add_command('app', 'sh', args = ['-c', 'echo .TH APP 1'])")?;
+        assert!(env
+            .eval("pages.add_command('app', 'sh', args = ['-c', 'exit 1'])")
+            .is_err());
+
+        let v = env.eval("pages")?;
+        let v = v.downcast_ref::<ManPagesValue>().unwrap();
+        assert_eq!(v.inner.pages.len(), 1);
+        assert_eq!(v.inner.pages[0].data, b".TH APP 1\n".to_vec());
+
+        Ok(())
+    }
+}
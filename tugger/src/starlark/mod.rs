@@ -8,10 +8,17 @@ The `starlark` module and related sub-modules define the
 Tugger.
 */
 
+pub mod apple_sdk;
+pub mod code_signing;
+pub mod desktop_entry;
 pub mod file_resource;
+pub mod man_pages;
+pub mod publish;
 pub mod snapcraft;
+pub mod systemd_unit;
 #[cfg(test)]
 mod testutil;
+pub mod tool_pipeline;
 pub mod wix_bundle_builder;
 pub mod wix_installer;
 pub mod wix_msi_builder;
@@ -23,8 +30,15 @@ pub fn register_starlark_dialect(
     env: &mut Environment,
     type_values: &mut TypeValues,
 ) -> Result<(), EnvironmentError> {
+    apple_sdk::apple_sdk_module(env, type_values);
+    code_signing::code_signing_module(env, type_values);
+    desktop_entry::desktop_entry_module(env, type_values);
     file_resource::file_resource_module(env, type_values);
+    man_pages::man_pages_module(env, type_values);
+    publish::publish_module(env, type_values);
     snapcraft::snapcraft_module(env, type_values);
+    systemd_unit::systemd_unit_module(env, type_values);
+    tool_pipeline::tool_pipeline_module(env, type_values);
     wix_bundle_builder::wix_bundle_builder_module(env, type_values);
     wix_installer::wix_installer_module(env, type_values);
     wix_msi_builder::wix_msi_builder_module(env, type_values);
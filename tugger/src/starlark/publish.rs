@@ -0,0 +1,270 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    slog::warn,
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            {Immutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{
+        get_context_value, optional_str_arg, EnvironmentContext, ResolvedTarget,
+        ResolvedTargetValue, RunMode,
+    },
+    std::path::{Path, PathBuf},
+    tugger_common::{
+        glob::evaluate_glob,
+        publish::{GitHubReleaseUpload, S3Upload, GITHUB_API_URL},
+    },
+};
+
+fn publish_error(label: &str, message: String) -> ValueError {
+    ValueError::from(RuntimeError {
+        code: "TUGGER_PUBLISH",
+        message,
+        label: label.to_string(),
+    })
+}
+
+/// Resolve glob patterns of files to upload.
+///
+/// Patterns are evaluated when the upload is performed so they can match
+/// artifacts produced by other build targets.
+fn resolve_files(cwd: &Path, patterns: &[String], label: &str) -> Result<Vec<PathBuf>, ValueError> {
+    let mut files = vec![];
+
+    for pattern in patterns {
+        let mut matches = evaluate_glob(cwd, pattern)
+            .map_err(|e| publish_error(label, e.to_string()))?
+            .into_iter()
+            .filter(|p| p.is_file())
+            .collect::<Vec<_>>();
+
+        if matches.is_empty() {
+            return Err(publish_error(label, format!("no files match {}", pattern)));
+        }
+
+        matches.sort();
+        files.extend(matches);
+    }
+
+    Ok(files)
+}
+
+fn resolved_target(context: &EnvironmentContext, target: &str) -> ValueResult {
+    Ok(Value::new(ResolvedTargetValue {
+        inner: ResolvedTarget {
+            run_mode: RunMode::None,
+            output_path: context.target_build_path(target),
+        },
+    }))
+}
+
+#[derive(Clone, Debug)]
+pub struct GitHubReleaseUploadValue {
+    pub repo: String,
+    pub tag: String,
+    pub files: Vec<String>,
+    pub token_env: String,
+    pub draft: bool,
+}
+
+impl TypedValue for GitHubReleaseUploadValue {
+    type Holder = Immutable<GitHubReleaseUploadValue>;
+    const TYPE: &'static str = "GitHubReleaseUpload";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl GitHubReleaseUploadValue {
+    pub fn build(&self, type_values: &TypeValues, target: String) -> ValueResult {
+        let context_value = get_context_value(type_values)?;
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let token = std::env::var(&self.token_env).map_err(|_| {
+            publish_error(
+                "build()",
+                format!(
+                    "environment variable {} must be set to a GitHub API token",
+                    self.token_env
+                ),
+            )
+        })?;
+
+        let upload = GitHubReleaseUpload {
+            repo: self.repo.clone(),
+            tag: self.tag.clone(),
+            files: resolve_files(context.cwd(), &self.files, "build()")?,
+            token,
+            draft: self.draft,
+            api_url: GITHUB_API_URL.to_string(),
+        };
+
+        for url in upload
+            .upload(context.logger())
+            .map_err(|e| publish_error("build()", format!("{:?}", e)))?
+        {
+            warn!(context.logger(), "published {}", url);
+        }
+
+        resolved_target(context, &target)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct S3UploadValue {
+    pub bucket: String,
+    pub prefix: String,
+    pub files: Vec<String>,
+    pub region: Option<String>,
+}
+
+impl TypedValue for S3UploadValue {
+    type Holder = Immutable<S3UploadValue>;
+    const TYPE: &'static str = "S3Upload";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl S3UploadValue {
+    pub fn build(&self, type_values: &TypeValues, target: String) -> ValueResult {
+        let context_value = get_context_value(type_values)?;
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let upload = S3Upload {
+            bucket: self.bucket.clone(),
+            prefix: self.prefix.clone(),
+            files: resolve_files(context.cwd(), &self.files, "build()")?,
+            region: self.region.clone(),
+        };
+
+        for url in upload
+            .upload(context.logger())
+            .map_err(|e| publish_error("build()", format!("{:?}", e)))?
+        {
+            warn!(context.logger(), "published {}", url);
+        }
+
+        resolved_target(context, &target)
+    }
+}
+
+starlark_module! { publish_module =>
+    upload_github_release(
+        repo: String,
+        tag: String,
+        files: Vec<String>,
+        token_env: String = "GITHUB_TOKEN".to_string(),
+        draft: bool = false
+    ) {
+        if !repo.contains('/') {
+            return Err(publish_error(
+                "upload_github_release()",
+                format!("repo must be of the form owner/name; got {}", repo),
+            ));
+        }
+
+        Ok(Value::new(GitHubReleaseUploadValue {
+            repo,
+            tag,
+            files,
+            token_env,
+            draft,
+        }))
+    }
+
+    upload_s3(bucket: String, prefix: String, files: Vec<String>, region = NoneType::None) {
+        let region = optional_str_arg("region", &region)?;
+
+        Ok(Value::new(S3UploadValue {
+            bucket,
+            prefix,
+            files,
+            region,
+        }))
+    }
+
+    GitHubReleaseUpload.build(env env, this, target: String) {
+        let this = this.downcast_ref::<GitHubReleaseUploadValue>().unwrap();
+        this.build(env, target)
+    }
+
+    S3Upload.build(env env, this, target: String) {
+        let this = this.downcast_ref::<S3UploadValue>().unwrap();
+        this.build(env, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::starlark::testutil::*, anyhow::Result, tugger_common::testutil::*};
+
+    #[test]
+    fn test_upload_github_release() -> Result<()> {
+        let v = starlark_ok("upload_github_release('owner/repo', 'v1.0', ['dist/*.zip'])");
+        assert_eq!(v.get_type(), "GitHubReleaseUpload");
+
+        let v = v.downcast_ref::<GitHubReleaseUploadValue>().unwrap();
+        assert_eq!(v.repo, "owner/repo");
+        assert_eq!(v.tag, "v1.0");
+        assert_eq!(v.files, vec!["dist/*.zip".to_string()]);
+        assert_eq!(v.token_env, "GITHUB_TOKEN");
+        assert!(!v.draft);
+
+        let mut env = StarlarkEnvironment::new()?;
+        assert!(env
+            .eval("upload_github_release('repo', 'v1.0', [])")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_upload_s3() {
+        let v = starlark_ok(
+            "upload_s3('bucket', 'releases/1.0', ['dist/app.zip'], region = 'us-west-2')",
+        );
+        assert_eq!(v.get_type(), "S3Upload");
+
+        let v = v.downcast_ref::<S3UploadValue>().unwrap();
+        assert_eq!(v.bucket, "bucket");
+        assert_eq!(v.prefix, "releases/1.0");
+        assert_eq!(v.files, vec!["dist/app.zip".to_string()]);
+        assert_eq!(v.region, Some("us-west-2".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_files() -> Result<()> {
+        let cwd = DEFAULT_TEMP_DIR.path().join("test_resolve_files");
+        std::fs::create_dir_all(&cwd)?;
+        let cwd = cwd.as_path();
+
+        std::fs::write(cwd.join("b.zip"), b"")?;
+        std::fs::write(cwd.join("a.zip"), b"")?;
+
+        assert_eq!(
+            resolve_files(cwd, &["*.zip".to_string()], "test").unwrap(),
+            vec![cwd.join("a.zip"), cwd.join("b.zip")]
+        );
+        assert!(resolve_files(cwd, &["*.tar.gz".to_string()], "test").is_err());
+
+        Ok(())
+    }
+}
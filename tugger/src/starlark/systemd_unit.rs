@@ -0,0 +1,269 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::{starlark::file_resource::FileManifestValue, systemd_unit::SystemdUnit},
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{
+        get_context_value, optional_dict_arg, optional_list_arg, optional_str_arg,
+        EnvironmentContext, ResolvedTarget, ResolvedTargetValue, RunMode,
+    },
+};
+
+fn systemd_unit_error(label: &str, message: String) -> ValueError {
+    ValueError::from(RuntimeError {
+        code: "TUGGER_SYSTEMD_UNIT",
+        message,
+        label: label.to_string(),
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct SystemdUnitValue {
+    pub inner: SystemdUnit,
+}
+
+impl TypedValue for SystemdUnitValue {
+    type Holder = Mutable<SystemdUnitValue>;
+    const TYPE: &'static str = "SystemdUnit";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl SystemdUnitValue {
+    /// SystemdUnit(name, exec_start, description=None, service_type="simple", user=None, group=None, working_directory=None, environment=None, restart="on-failure", after=None, wanted_by=None)
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_from_args(
+        name: String,
+        exec_start: String,
+        description: &Value,
+        service_type: String,
+        user: &Value,
+        group: &Value,
+        working_directory: &Value,
+        environment: &Value,
+        restart: &Value,
+        after: &Value,
+        wanted_by: &Value,
+    ) -> ValueResult {
+        optional_dict_arg("environment", "string", "string", environment)?;
+        optional_list_arg("after", "string", after)?;
+        optional_list_arg("wanted_by", "string", wanted_by)?;
+
+        let mut inner = SystemdUnit::new(name, exec_start)
+            .map_err(|e| systemd_unit_error("SystemdUnit()", e.to_string()))?;
+
+        inner.description = optional_str_arg("description", description)?;
+        inner
+            .set_service_type(service_type)
+            .map_err(|e| systemd_unit_error("SystemdUnit()", e.to_string()))?;
+        inner.user = optional_str_arg("user", user)?;
+        inner.group = optional_str_arg("group", group)?;
+        inner.working_directory = optional_str_arg("working_directory", working_directory)?;
+        inner
+            .set_restart(optional_str_arg("restart", restart)?)
+            .map_err(|e| systemd_unit_error("SystemdUnit()", e.to_string()))?;
+
+        if environment.get_type() == "dict" {
+            for key in &environment.iter()? {
+                inner
+                    .environment
+                    .insert(key.to_str(), environment.at(key.clone())?.to_str());
+            }
+        }
+
+        if after.get_type() == "list" {
+            inner.after = after.iter()?.iter().map(|x| x.to_str()).collect();
+        }
+
+        if wanted_by.get_type() == "list" {
+            inner.wanted_by = wanted_by.iter()?.iter().map(|x| x.to_str()).collect();
+        }
+
+        Ok(Value::new(SystemdUnitValue { inner }))
+    }
+
+    /// SystemdUnit.add_directive(section, key, value)
+    pub fn add_directive(&mut self, section: String, key: String, value: String) -> ValueResult {
+        self.inner
+            .add_directive(section, key, value)
+            .map_err(|e| systemd_unit_error("add_directive()", e.to_string()))?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// SystemdUnit.to_file_manifest(prefix="usr/lib/systemd/system")
+    pub fn to_file_manifest(&self, prefix: String) -> ValueResult {
+        let manifest = self
+            .inner
+            .to_file_manifest(prefix)
+            .map_err(|e| systemd_unit_error("to_file_manifest()", e.to_string()))?;
+
+        Ok(Value::new(FileManifestValue {
+            manifest,
+            run_path: None,
+        }))
+    }
+
+    /// SystemdUnit.build(target)
+    pub fn build(&self, type_values: &TypeValues, target: String) -> ValueResult {
+        let context_value = get_context_value(type_values)?;
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let output_path = context.target_build_path(&target);
+
+        self.inner
+            .to_file_manifest("lib/systemd/system")
+            .map_err(|e| systemd_unit_error("build()", e.to_string()))?
+            .materialize_files_with_replace(&output_path)
+            .map_err(|e| systemd_unit_error("build()", e.to_string()))?;
+
+        Ok(Value::new(ResolvedTargetValue {
+            inner: ResolvedTarget {
+                run_mode: RunMode::None,
+                output_path,
+            },
+        }))
+    }
+}
+
+starlark_module! { systemd_unit_module =>
+    #[allow(non_snake_case)]
+    SystemdUnit(
+        name: String,
+        exec_start: String,
+        description = NoneType::None,
+        service_type: String = "simple".to_string(),
+        user = NoneType::None,
+        group = NoneType::None,
+        working_directory = NoneType::None,
+        environment = NoneType::None,
+        restart = Value::from("on-failure"),
+        after = NoneType::None,
+        wanted_by = NoneType::None
+    ) {
+        SystemdUnitValue::new_from_args(
+            name,
+            exec_start,
+            &description,
+            service_type,
+            &user,
+            &group,
+            &working_directory,
+            &environment,
+            &restart,
+            &after,
+            &wanted_by
+        )
+    }
+
+    SystemdUnit.add_directive(this, section: String, key: String, value: String) {
+        let mut this = this.downcast_mut::<SystemdUnitValue>().unwrap().unwrap();
+        this.add_directive(section, key, value)
+    }
+
+    SystemdUnit.build(env env, this, target: String) {
+        let this = this.downcast_ref::<SystemdUnitValue>().unwrap();
+        this.build(env, target)
+    }
+
+    SystemdUnit.to_file_manifest(
+        this,
+        prefix: String = "usr/lib/systemd/system".to_string()
+    ) {
+        let this = this.downcast_ref::<SystemdUnitValue>().unwrap();
+        this.to_file_manifest(prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::starlark::testutil::*, anyhow::Result};
+
+    #[test]
+    fn test_new() {
+        let v = starlark_ok("SystemdUnit('app', '/usr/bin/app --serve')");
+        assert_eq!(v.get_type(), "SystemdUnit");
+
+        let v = v.downcast_ref::<SystemdUnitValue>().unwrap();
+        assert_eq!(v.inner.name, "app");
+        assert_eq!(v.inner.exec_start, "/usr/bin/app --serve");
+        assert_eq!(v.inner.service_type, "simple");
+        assert_eq!(v.inner.restart, Some("on-failure".to_string()));
+        assert_eq!(v.inner.wanted_by, vec!["multi-user.target".to_string()]);
+    }
+
+    #[test]
+    fn test_new_options() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        let v = env.eval(
+            "SystemdUnit('app', '/usr/bin/app', description = 'My App', service_type = 'notify', user = 'app', environment = {'HOME': '/var/lib/app'}, restart = None, after = [], wanted_by = ['default.target'])",
+        )?;
+        let v = v.downcast_ref::<SystemdUnitValue>().unwrap();
+        assert_eq!(v.inner.description, Some("My App".to_string()));
+        assert_eq!(v.inner.service_type, "notify");
+        assert_eq!(v.inner.user, Some("app".to_string()));
+        assert_eq!(
+            v.inner.environment.get("HOME"),
+            Some(&"/var/lib/app".to_string())
+        );
+        assert_eq!(v.inner.restart, None);
+        assert!(v.inner.after.is_empty());
+        assert_eq!(v.inner.wanted_by, vec!["default.target".to_string()]);
+
+        assert!(env.eval("SystemdUnit('my app', '/usr/bin/app')").is_err());
+        assert!(env
+            .eval("SystemdUnit('app', '/usr/bin/app', service_type = 'daemon')")
+            .is_err());
+        assert!(env
+            .eval("SystemdUnit('app', '/usr/bin/app', restart = 'sometimes')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_file_manifest() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("unit = SystemdUnit('app', '/usr/bin/app')")?;
+        env.eval("unit.add_directive('Service', 'LimitNOFILE', '65536')")?;
+        assert!(env
+            .eval("unit.add_directive('Timer', 'OnCalendar', 'daily')")
+            .is_err());
+
+        let m = env.eval("unit.to_file_manifest()")?;
+        assert_eq!(m.get_type(), "FileManifest");
+        let m = m.downcast_ref::<FileManifestValue>().unwrap();
+
+        let entry = m
+            .manifest
+            .get("usr/lib/systemd/system/app.service")
+            .unwrap();
+        let content = String::from_utf8(entry.data.resolve()?)?;
+        assert!(content.contains("\nLimitNOFILE=65536\n"));
+
+        let m = env.eval("unit.to_file_manifest(prefix = 'lib/systemd/system')")?;
+        let m = m.downcast_ref::<FileManifestValue>().unwrap();
+        assert!(m.manifest.has_path("lib/systemd/system/app.service"));
+
+        Ok(())
+    }
+}
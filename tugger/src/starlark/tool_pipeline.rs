@@ -0,0 +1,269 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::{
+        starlark::file_resource::FileManifestValue,
+        tool_pipeline::{ConfigFile, ConfigFormat, ToolPipeline},
+    },
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{
+        get_context_value, optional_dict_arg, optional_list_arg, optional_str_arg,
+        EnvironmentContext, ResolvedTarget, ResolvedTargetValue, RunMode,
+    },
+    std::path::PathBuf,
+};
+
+fn pipeline_error(label: &str, message: String) -> ValueError {
+    ValueError::from(RuntimeError {
+        code: "TUGGER_TOOL_PIPELINE",
+        message,
+        label: label.to_string(),
+    })
+}
+
+/// Convert a Starlark value to a JSON value.
+fn value_to_json(value: &Value) -> Result<serde_json::Value, ValueError> {
+    match value.get_type() {
+        "NoneType" => Ok(serde_json::Value::Null),
+        "bool" => Ok(serde_json::Value::Bool(value.to_bool())),
+        "int" => Ok(serde_json::Value::from(value.to_int()?)),
+        "string" => Ok(serde_json::Value::String(value.to_str())),
+        "list" | "tuple" => Ok(serde_json::Value::Array(
+            value
+                .iter()?
+                .iter()
+                .map(|v| value_to_json(&v))
+                .collect::<Result<Vec<_>, _>>()?,
+        )),
+        "dict" => {
+            let mut res = serde_json::Map::new();
+
+            for key in &value.iter()? {
+                if key.get_type() != "string" {
+                    return Err(pipeline_error(
+                        "add_config_file()",
+                        format!("dict keys must be strings; got {}", key.get_type()),
+                    ));
+                }
+
+                res.insert(key.to_str(), value_to_json(&value.at(key.clone())?)?);
+            }
+
+            Ok(serde_json::Value::Object(res))
+        }
+        t => Err(pipeline_error(
+            "add_config_file()",
+            format!("unable to convert {} to a configuration value", t),
+        )),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ToolPipelineValue {
+    pub inner: ToolPipeline,
+}
+
+impl TypedValue for ToolPipelineValue {
+    type Holder = Mutable<ToolPipelineValue>;
+    const TYPE: &'static str = "ToolPipeline";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl ToolPipelineValue {
+    /// ToolPipeline(program, args=None, env=None)
+    pub fn new_from_args(program: String, args: Value, env: Value) -> ValueResult {
+        optional_list_arg("args", "string", &args)?;
+        optional_dict_arg("env", "string", "string", &env)?;
+
+        let mut inner = ToolPipeline::new(program);
+
+        if args.get_type() == "list" {
+            inner.args = args.iter()?.iter().map(|x| x.to_str()).collect();
+        }
+
+        if env.get_type() == "dict" {
+            for key in &env.iter()? {
+                inner
+                    .env
+                    .insert(key.to_str(), env.at(key.clone())?.to_str());
+            }
+        }
+
+        Ok(Value::new(ToolPipelineValue { inner }))
+    }
+
+    pub fn add_file_manifest(&mut self, manifest: FileManifestValue) -> ValueResult {
+        self.inner
+            .stage_files
+            .add_manifest(&manifest.manifest)
+            .map_err(|e| pipeline_error("add_file_manifest()", e.to_string()))?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    pub fn add_config_file(
+        &mut self,
+        path: String,
+        content: &Value,
+        format: &Value,
+    ) -> ValueResult {
+        let path = PathBuf::from(path);
+
+        let format = match optional_str_arg("format", format)?.as_deref() {
+            Some("json") => ConfigFormat::Json,
+            Some("yaml") => ConfigFormat::Yaml,
+            Some(v) => {
+                return Err(pipeline_error(
+                    "add_config_file()",
+                    format!("format must be \"json\" or \"yaml\"; got {}", v),
+                ))
+            }
+            None => ConfigFormat::from_path(&path)
+                .map_err(|e| pipeline_error("add_config_file()", e.to_string()))?,
+        };
+
+        self.inner.config_files.push(ConfigFile {
+            path,
+            format,
+            content: value_to_json(content)?,
+        });
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    pub fn add_output(&mut self, path: String) -> ValueResult {
+        self.inner.outputs.push(PathBuf::from(path));
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    pub fn build(&self, type_values: &TypeValues, target: String) -> ValueResult {
+        let context_value = get_context_value(type_values)?;
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let output_path = context.target_build_path(&target);
+
+        self.inner
+            .build(context.logger(), &output_path)
+            .map_err(|e| pipeline_error("build()", format!("{:?}", e)))?;
+
+        Ok(Value::new(ResolvedTargetValue {
+            inner: ResolvedTarget {
+                run_mode: RunMode::None,
+                output_path,
+            },
+        }))
+    }
+}
+
+starlark_module! { tool_pipeline_module =>
+    #[allow(non_snake_case)]
+    ToolPipeline(program: String, args = NoneType::None, env = NoneType::None) {
+        ToolPipelineValue::new_from_args(program, args, env)
+    }
+
+    ToolPipeline.add_file_manifest(this, manifest: FileManifestValue) {
+        let mut this = this.downcast_mut::<ToolPipelineValue>().unwrap().unwrap();
+        this.add_file_manifest(manifest)
+    }
+
+    ToolPipeline.add_config_file(this, path: String, content, format = NoneType::None) {
+        let mut this = this.downcast_mut::<ToolPipelineValue>().unwrap().unwrap();
+        this.add_config_file(path, &content, &format)
+    }
+
+    ToolPipeline.add_output(this, path: String) {
+        let mut this = this.downcast_mut::<ToolPipelineValue>().unwrap().unwrap();
+        this.add_output(path)
+    }
+
+    ToolPipeline.build(env env, this, target: String) {
+        let this = this.downcast_ref::<ToolPipelineValue>().unwrap();
+        this.build(env, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::starlark::testutil::*, anyhow::Result, std::collections::BTreeMap};
+
+    #[test]
+    fn test_new() {
+        let v = starlark_ok("ToolPipeline('fpm', args = ['-t', 'deb'], env = {'FOO': 'bar'})");
+        assert_eq!(v.get_type(), "ToolPipeline");
+
+        let v = v.downcast_ref::<ToolPipelineValue>().unwrap();
+        assert_eq!(v.inner.program, "fpm");
+        assert_eq!(v.inner.args, vec!["-t".to_string(), "deb".to_string()]);
+        assert_eq!(
+            v.inner.env,
+            [("FOO".to_string(), "bar".to_string())]
+                .iter()
+                .cloned()
+                .collect::<BTreeMap<_, _>>()
+        );
+    }
+
+    #[test]
+    fn test_add_config_file() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("p = ToolPipeline('tool')")?;
+        env.eval(
+            "p.add_config_file('tool.yaml', {'name': 'app', 'files': ['{build_path}/app'], 'level': 3, 'strip': True})",
+        )?;
+        env.eval("p.add_config_file('config', None, format = 'json')")?;
+        env.eval("p.add_output('app.pkg')")?;
+
+        assert!(env.eval("p.add_config_file('tool.toml', {})").is_err());
+        assert!(env
+            .eval("p.add_config_file('tool', {}, format = 'toml')")
+            .is_err());
+        assert!(env.eval("p.add_config_file('tool.json', {1: 2})").is_err());
+
+        let v = env.eval("p")?;
+        let v = v.downcast_ref::<ToolPipelineValue>().unwrap();
+
+        assert_eq!(
+            v.inner.config_files,
+            vec![
+                ConfigFile {
+                    path: PathBuf::from("tool.yaml"),
+                    format: ConfigFormat::Yaml,
+                    content: serde_json::json!({
+                        "name": "app",
+                        "files": ["{build_path}/app"],
+                        "level": 3,
+                        "strip": true,
+                    }),
+                },
+                ConfigFile {
+                    path: PathBuf::from("config"),
+                    format: ConfigFormat::Json,
+                    content: serde_json::Value::Null,
+                },
+            ]
+        );
+        assert_eq!(v.inner.outputs, vec![PathBuf::from("app.pkg")]);
+
+        Ok(())
+    }
+}
@@ -3,7 +3,10 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::starlark::{file_resource::FileManifestValue, wix_msi_builder::WiXMsiBuilderValue},
+    crate::starlark::{
+        code_signing::CodeSignerValue, file_resource::FileManifestValue,
+        wix_msi_builder::WiXMsiBuilderValue,
+    },
     anyhow::Result,
     starlark::{
         environment::TypeValues,
@@ -269,6 +272,12 @@ impl WiXInstallerValue {
         }))
     }
 
+    fn set_code_signer(&mut self, signer: CodeSignerValue) -> ValueResult {
+        self.inner.auto_sign(signer.inner);
+
+        Ok(Value::new(NoneType::None))
+    }
+
     fn set_variable(&mut self, key: String, value: Value) -> ValueResult {
         let value = optional_str_arg("value", &value)?;
         self.inner.set_variable(key, value);
@@ -346,6 +355,11 @@ starlark_module! { wix_installer_module =>
         this.build(env, target)
     }
 
+    WiXInstaller.set_code_signer(this, signer: CodeSignerValue) {
+        let mut this = this.downcast_mut::<WiXInstallerValue>().unwrap().unwrap();
+        this.set_code_signer(signer)
+    }
+
     WiXInstaller.set_variable(this, key: String, value) {
         let mut this = this.downcast_mut::<WiXInstallerValue>().unwrap().unwrap();
         this.set_variable(key, value)
@@ -378,6 +392,16 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_set_code_signer() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("i = WiXInstaller('prefix', 'installer.msi')")?;
+        env.eval("i.set_code_signer(code_signer_macos_keychain('identity'))")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_set_variable() -> Result<()> {
         let mut env = StarlarkEnvironment::new()?;
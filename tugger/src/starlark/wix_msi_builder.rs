@@ -3,7 +3,7 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::starlark::file_resource::FileManifestValue,
+    crate::starlark::{code_signing::CodeSignerValue, file_resource::FileManifestValue},
     anyhow::Result,
     starlark::{
         environment::TypeValues,
@@ -22,9 +22,13 @@ use {
     starlark_dialect_build_targets::{
         get_context_value, EnvironmentContext, ResolvedTarget, ResolvedTargetValue, RunMode,
     },
-    std::convert::TryFrom,
+    std::{
+        convert::TryFrom,
+        time::{Duration, UNIX_EPOCH},
+    },
     tugger_windows::VcRedistributablePlatform,
     tugger_wix::WiXSimpleMsiBuilder,
+    uuid::Uuid,
 };
 
 #[derive(Clone)]
@@ -49,12 +53,25 @@ impl TypedValue for WiXMsiBuilderValue {
             "banner_bmp_path" => {
                 self.inner = self.inner.clone().banner_bmp_path(value.to_string());
             }
+            "build_id" => {
+                self.inner = self.inner.clone().build_id(value.to_string());
+            }
             "dialog_bmp_path" => {
                 self.inner = self.inner.clone().dialog_bmp_path(value.to_string());
             }
             "eula_rtf_path" => {
                 self.inner = self.inner.clone().eula_rtf_path(value.to_string());
             }
+            "guid_namespace" => {
+                let namespace = Uuid::parse_str(&value.to_string()).map_err(|e| {
+                    ValueError::Runtime(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: format!("guid_namespace must be a UUID: {}", e),
+                        label: "guid_namespace".to_string(),
+                    })
+                })?;
+                self.inner = self.inner.clone().guid_namespace(namespace);
+            }
             "help_url" => {
                 self.inner = self.inner.clone().help_url(value.to_string());
             }
@@ -64,6 +81,20 @@ impl TypedValue for WiXMsiBuilderValue {
             "msi_filename" => {
                 self.msi_filename = Some(value.to_string());
             }
+            "mtime" => {
+                let seconds = value.to_int()?;
+                if seconds < 0 {
+                    return Err(ValueError::Runtime(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: "mtime must be seconds since the UNIX epoch".to_string(),
+                        label: "mtime".to_string(),
+                    }));
+                }
+                self.inner = self
+                    .inner
+                    .clone()
+                    .mtime(UNIX_EPOCH + Duration::from_secs(seconds as u64));
+            }
             "package_description" => {
                 self.inner = self.inner.clone().package_description(value.to_string());
             }
@@ -113,6 +144,12 @@ impl WiXMsiBuilderValue {
         }))
     }
 
+    pub fn set_code_signer(&mut self, signer: CodeSignerValue) -> ValueResult {
+        self.inner = self.inner.clone().auto_sign(signer.inner);
+
+        Ok(Value::new(NoneType::None))
+    }
+
     pub fn add_program_files_manifest(&mut self, manifest: FileManifestValue) -> ValueResult {
         self.inner
             .add_program_files_manifest(&manifest.manifest)
@@ -226,6 +263,12 @@ starlark_module! { wix_msi_builder_module =>
         this.add_visual_cpp_redistributable(redist_version, platform)
     }
 
+    #[allow(non_snake_case)]
+    WiXMSIBuilder.set_code_signer(this, signer: CodeSignerValue) {
+        let mut this = this.downcast_mut::<WiXMsiBuilderValue>().unwrap().unwrap();
+        this.set_code_signer(signer)
+    }
+
     #[allow(non_snake_case)]
     WiXMSIBuilder.build(env env, this, target: String) {
         let this = this.downcast_ref::<WiXMsiBuilderValue>().unwrap();
@@ -286,6 +329,32 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_set_code_signer() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("msi = WiXMSIBuilder('prefix', 'name', '0.1', 'manufacturer')")?;
+        env.eval("msi.set_code_signer(code_signer_command('signer', ['{path}']))")?;
+        assert!(env.eval("msi.set_code_signer('signer')").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reproducible_attrs() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("msi = WiXMSIBuilder('prefix', 'name', '0.1', 'manufacturer')")?;
+        env.eval("msi.build_id = '42'")?;
+        env.eval("msi.guid_namespace = '6ba7b811-9dad-11d1-80b4-00c04fd430c8'")?;
+        env.eval("msi.mtime = 1600000000")?;
+        assert!(env.eval("msi.guid_namespace = 'invalid'").is_err());
+        assert!(env.eval("msi.mtime = -1").is_err());
+        assert!(env.eval("msi.mtime = 'now'").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_visual_cpp_redistributable() -> Result<()> {
         if tugger_windows::find_visual_cpp_redistributable("14", VcRedistributablePlatform::X64)
@@ -0,0 +1,328 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Define systemd service units.
+
+[systemd](https://www.freedesktop.org/software/systemd/man/systemd.service.html)
+starts services from unit files installed in `lib/systemd/system`. A
+[SystemdUnit] renders a `.service` unit file for a program and provides it
+as a [FileManifest], which can be installed or packaged like any other,
+e.g. into a Debian or RPM package produced by a
+[crate::tool_pipeline::ToolPipeline].
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    std::{collections::BTreeMap, path::Path},
+    tugger_file_manifest::{FileEntry, FileManifest},
+};
+
+/// Values accepted by the `Type=` directive.
+const SERVICE_TYPES: &[&str] = &[
+    "simple", "exec", "forking", "oneshot", "dbus", "notify", "idle",
+];
+
+/// Values accepted by the `Restart=` directive.
+const RESTART_VALUES: &[&str] = &[
+    "no",
+    "on-success",
+    "on-failure",
+    "on-abnormal",
+    "on-watchdog",
+    "on-abort",
+    "always",
+];
+
+/// Quote an `Environment=` assignment.
+fn quote_environment(key: &str, value: &str) -> String {
+    format!(
+        "\"{}={}\"",
+        key,
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('%', "%%")
+    )
+}
+
+/// A systemd service unit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SystemdUnit {
+    /// Name of the unit, without the `.service` suffix.
+    pub name: String,
+
+    /// Command line starting the service, e.g. `/usr/bin/app --serve`.
+    pub exec_start: String,
+
+    /// Description of the service.
+    pub description: Option<String>,
+
+    /// How the service signals it has started, e.g. `simple` or `notify`.
+    pub service_type: String,
+
+    /// User to run the service as.
+    pub user: Option<String>,
+
+    /// Group to run the service as.
+    pub group: Option<String>,
+
+    /// Working directory of the service.
+    pub working_directory: Option<String>,
+
+    /// Environment variables of the service.
+    pub environment: BTreeMap<String, String>,
+
+    /// When the service is restarted, e.g. `on-failure`.
+    pub restart: Option<String>,
+
+    /// Units the service is started after.
+    pub after: Vec<String>,
+
+    /// Targets which start the service when it is enabled.
+    pub wanted_by: Vec<String>,
+
+    /// Additional directives, as `(section, key, value)`.
+    pub directives: Vec<(String, String, String)>,
+}
+
+impl SystemdUnit {
+    /// Create a new instance.
+    ///
+    /// The service is started after the network is up and by
+    /// `multi-user.target` when enabled.
+    pub fn new(name: impl ToString, exec_start: impl ToString) -> Result<Self> {
+        let name = name.to_string();
+        let name = name.strip_suffix(".service").unwrap_or(&name).to_string();
+
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || ":-_.\\@".contains(c))
+        {
+            return Err(anyhow!(
+                "{} is not a valid unit name; must consist of ASCII letters, digits, ':', '-', '_', '.', '\\', and '@'",
+                name
+            ));
+        }
+
+        Ok(Self {
+            name,
+            exec_start: exec_start.to_string(),
+            description: None,
+            service_type: "simple".to_string(),
+            user: None,
+            group: None,
+            working_directory: None,
+            environment: BTreeMap::new(),
+            restart: None,
+            after: vec!["network.target".to_string()],
+            wanted_by: vec!["multi-user.target".to_string()],
+            directives: vec![],
+        })
+    }
+
+    /// Set the `Type=` of the service.
+    pub fn set_service_type(&mut self, value: impl ToString) -> Result<()> {
+        let value = value.to_string();
+
+        if !SERVICE_TYPES.contains(&value.as_str()) {
+            return Err(anyhow!(
+                "{} is not a valid service type; must be one of {}",
+                value,
+                SERVICE_TYPES.join(", ")
+            ));
+        }
+
+        self.service_type = value;
+
+        Ok(())
+    }
+
+    /// Set the `Restart=` of the service.
+    pub fn set_restart(&mut self, value: Option<String>) -> Result<()> {
+        if let Some(value) = &value {
+            if !RESTART_VALUES.contains(&value.as_str()) {
+                return Err(anyhow!(
+                    "{} is not a valid restart setting; must be one of {}",
+                    value,
+                    RESTART_VALUES.join(", ")
+                ));
+            }
+        }
+
+        self.restart = value;
+
+        Ok(())
+    }
+
+    /// Add a directive to a section of the unit file.
+    pub fn add_directive(
+        &mut self,
+        section: impl ToString,
+        key: impl ToString,
+        value: impl ToString,
+    ) -> Result<()> {
+        let section = section.to_string();
+
+        if !["Unit", "Service", "Install"].contains(&section.as_str()) {
+            return Err(anyhow!(
+                "{} is not a valid section; must be Unit, Service, or Install",
+                section
+            ));
+        }
+
+        let value = value.to_string();
+        if value.contains('\n') {
+            return Err(anyhow!("directive values cannot contain newlines"));
+        }
+
+        self.directives.push((section, key.to_string(), value));
+
+        Ok(())
+    }
+
+    /// File name of the unit file.
+    pub fn file_name(&self) -> String {
+        format!("{}.service", self.name)
+    }
+
+    /// Lines of additional directives in a section.
+    fn section_directives(&self, section: &str) -> Vec<String> {
+        self.directives
+            .iter()
+            .filter(|(s, _, _)| s == section)
+            .map(|(_, key, value)| format!("{}={}", key, value))
+            .collect()
+    }
+
+    /// Render the content of the unit file.
+    pub fn to_unit_file(&self) -> String {
+        let mut lines = vec!["[Unit]".to_string()];
+        if let Some(description) = &self.description {
+            lines.push(format!("Description={}", description));
+        }
+        if !self.after.is_empty() {
+            lines.push(format!("After={}", self.after.join(" ")));
+        }
+        lines.extend(self.section_directives("Unit"));
+
+        lines.push(String::new());
+        lines.push("[Service]".to_string());
+        lines.push(format!("Type={}", self.service_type));
+        lines.push(format!("ExecStart={}", self.exec_start));
+        if let Some(user) = &self.user {
+            lines.push(format!("User={}", user));
+        }
+        if let Some(group) = &self.group {
+            lines.push(format!("Group={}", group));
+        }
+        if let Some(working_directory) = &self.working_directory {
+            lines.push(format!("WorkingDirectory={}", working_directory));
+        }
+        for (key, value) in &self.environment {
+            lines.push(format!("Environment={}", quote_environment(key, value)));
+        }
+        if let Some(restart) = &self.restart {
+            lines.push(format!("Restart={}", restart));
+        }
+        lines.extend(self.section_directives("Service"));
+
+        if !self.wanted_by.is_empty() || self.directives.iter().any(|(s, _, _)| s == "Install") {
+            lines.push(String::new());
+            lines.push("[Install]".to_string());
+            if !self.wanted_by.is_empty() {
+                lines.push(format!("WantedBy={}", self.wanted_by.join(" ")));
+            }
+            lines.extend(self.section_directives("Install"));
+        }
+
+        let mut content = lines.join("\n");
+        content.push('\n');
+
+        content
+    }
+
+    /// Obtain a [FileManifest] with the unit file.
+    ///
+    /// The unit file is installed into `prefix`, which should be the
+    /// directory systemd loads units from, e.g. `usr/lib/systemd/system`.
+    pub fn to_file_manifest(&self, prefix: impl AsRef<Path>) -> Result<FileManifest> {
+        let mut manifest = FileManifest::default();
+
+        manifest.add_file_entry(
+            prefix.as_ref().join(self.file_name()),
+            FileEntry {
+                data: self.to_unit_file().into_bytes().into(),
+                executable: false,
+            },
+        )?;
+
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::path::PathBuf};
+
+    #[test]
+    fn test_new() -> Result<()> {
+        assert_eq!(SystemdUnit::new("app", "/usr/bin/app")?.name, "app");
+        assert_eq!(SystemdUnit::new("app.service", "/usr/bin/app")?.name, "app");
+        assert_eq!(SystemdUnit::new("app@", "/usr/bin/app %i")?.name, "app@");
+        assert!(SystemdUnit::new("", "/usr/bin/app").is_err());
+        assert!(SystemdUnit::new("my app", "/usr/bin/app").is_err());
+        assert!(SystemdUnit::new("a/b", "/usr/bin/app").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unit_file() -> Result<()> {
+        let mut unit = SystemdUnit::new("app", "/usr/bin/app --serve")?;
+        assert_eq!(
+            unit.to_unit_file(),
+            "[Unit]\nAfter=network.target\n\n[Service]\nType=simple\nExecStart=/usr/bin/app --serve\n\n[Install]\nWantedBy=multi-user.target\n"
+        );
+
+        unit.description = Some("My App".to_string());
+        unit.set_service_type("notify")?;
+        unit.user = Some("app".to_string());
+        unit.working_directory = Some("/var/lib/app".to_string());
+        unit.environment
+            .insert("GREETING".to_string(), "say \"hi\" 100%".to_string());
+        unit.set_restart(Some("on-failure".to_string()))?;
+        unit.add_directive("Service", "LimitNOFILE", "65536")?;
+        unit.wanted_by = vec![];
+
+        assert_eq!(
+            unit.to_unit_file(),
+            "[Unit]\nDescription=My App\nAfter=network.target\n\n[Service]\nType=notify\nExecStart=/usr/bin/app --serve\nUser=app\nWorkingDirectory=/var/lib/app\nEnvironment=\"GREETING=say \\\"hi\\\" 100%%\"\nRestart=on-failure\nLimitNOFILE=65536\n"
+        );
+
+        assert!(unit.set_service_type("daemon").is_err());
+        assert!(unit.set_restart(Some("sometimes".to_string())).is_err());
+        assert!(unit.add_directive("Timer", "OnCalendar", "daily").is_err());
+        assert!(unit.add_directive("Service", "Nice", "1\n2").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_file_manifest() -> Result<()> {
+        let unit = SystemdUnit::new("app", "/usr/bin/app")?;
+        let manifest = unit.to_file_manifest("usr/lib/systemd/system")?;
+
+        assert_eq!(
+            manifest
+                .iter_entries()
+                .map(|(path, _)| path.clone())
+                .collect::<Vec<_>>(),
+            vec![PathBuf::from("usr/lib/systemd/system/app.service")]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Invoke arbitrary external packaging tools.
+
+A [ToolPipeline] stages files into a build directory, renders
+configuration files for a tool, runs the tool, and verifies the artifacts
+it is expected to produce exist. This allows packaging formats that
+Tugger doesn't natively implement to be produced by the tools that do.
+
+String values in arguments, environment variables, and configuration files
+can reference `{build_path}`, which is replaced by the absolute path of the
+build directory.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    duct::cmd,
+    slog::warn,
+    std::{
+        collections::BTreeMap,
+        io::{BufRead, BufReader},
+        path::{Path, PathBuf},
+    },
+    tugger_file_manifest::FileManifest,
+};
+
+/// Placeholder in templated strings replaced by the build path.
+const BUILD_PATH_PLACEHOLDER: &str = "{build_path}";
+
+/// Serialization format of a rendered configuration file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Resolve the format of a file from its extension.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Ok(Self::Json),
+            Some("yaml") | Some("yml") => Ok(Self::Yaml),
+            _ => Err(anyhow!(
+                "unable to determine format of {}; expected a .json, .yaml, or .yml extension",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// A configuration file to render before invoking the tool.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigFile {
+    /// Path of the file, relative to the build directory.
+    pub path: PathBuf,
+
+    /// Format to serialize content as.
+    pub format: ConfigFormat,
+
+    /// Content of the file.
+    pub content: serde_json::Value,
+}
+
+/// Replace template placeholders in a string.
+fn render_str(s: &str, build_path: &Path) -> String {
+    s.replace(BUILD_PATH_PLACEHOLDER, &build_path.display().to_string())
+}
+
+/// Replace template placeholders in all strings of a value.
+fn render_value(value: &serde_json::Value, build_path: &Path) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(s) => serde_json::Value::String(render_str(s, build_path)),
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.iter().map(|v| render_value(v, build_path)).collect())
+        }
+        serde_json::Value::Object(values) => serde_json::Value::Object(
+            values
+                .iter()
+                .map(|(k, v)| (k.clone(), render_value(v, build_path)))
+                .collect(),
+        ),
+        v => v.clone(),
+    }
+}
+
+impl ConfigFile {
+    /// Obtain the rendered content of this file.
+    pub fn render(&self, build_path: &Path) -> Result<Vec<u8>> {
+        let content = render_value(&self.content, build_path);
+
+        match self.format {
+            ConfigFormat::Json => {
+                let mut data = serde_json::to_vec_pretty(&content)?;
+                data.push(b'\n');
+                Ok(data)
+            }
+            ConfigFormat::Yaml => Ok(serde_yaml::to_vec(&content)?),
+        }
+    }
+}
+
+/// Stages files and invokes an external tool to produce artifacts.
+#[derive(Clone, Debug, Default)]
+pub struct ToolPipeline {
+    /// Program to execute.
+    pub program: String,
+
+    /// Arguments to pass to the program.
+    pub args: Vec<String>,
+
+    /// Extra environment variables to define when running the program.
+    pub env: BTreeMap<String, String>,
+
+    /// Files to install in the build directory.
+    pub stage_files: FileManifest,
+
+    /// Configuration files to render in the build directory.
+    pub config_files: Vec<ConfigFile>,
+
+    /// Paths, relative to the build directory, the program must produce.
+    pub outputs: Vec<PathBuf>,
+}
+
+impl ToolPipeline {
+    /// Create a new instance invoking a program.
+    pub fn new(program: impl ToString) -> Self {
+        Self {
+            program: program.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Run the pipeline in the given build directory.
+    ///
+    /// Returns the absolute paths of declared outputs.
+    pub fn build(
+        &self,
+        logger: &slog::Logger,
+        build_path: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>> {
+        let build_path = build_path.as_ref();
+
+        std::fs::create_dir_all(build_path)
+            .with_context(|| format!("creating {}", build_path.display()))?;
+        let build_path = std::fs::canonicalize(build_path)?;
+
+        self.stage_files
+            .materialize_files(&build_path)
+            .with_context(|| format!("installing files to {}", build_path.display()))?;
+
+        for config in &self.config_files {
+            let path = build_path.join(&config.path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("creating {}", parent.display()))?;
+            }
+
+            warn!(logger, "writing {}", path.display());
+            std::fs::write(&path, config.render(&build_path)?)
+                .with_context(|| format!("writing {}", path.display()))?;
+        }
+
+        // Outputs from a previous run would satisfy our checks below.
+        for output in &self.outputs {
+            let path = build_path.join(output);
+            if path.is_file() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("removing {}", path.display()))?;
+            }
+        }
+
+        let args = self
+            .args
+            .iter()
+            .map(|arg| render_str(arg, &build_path))
+            .collect::<Vec<_>>();
+
+        warn!(logger, "invoking {} with args: {:?}", self.program, args);
+        let mut expression = cmd(&self.program, &args).dir(&build_path);
+        for (key, value) in &self.env {
+            expression = expression.env(key, render_str(value, &build_path));
+        }
+
+        let command = expression
+            .stderr_to_stdout()
+            .unchecked()
+            .reader()
+            .with_context(|| format!("running {}", self.program))?;
+        {
+            let reader = BufReader::new(&command);
+            for line in reader.lines() {
+                warn!(logger, "{}", line?);
+            }
+        }
+
+        let output = command
+            .try_wait()?
+            .ok_or_else(|| anyhow!("unable to wait on command"))?;
+        if !output.status.success() {
+            return Err(anyhow!("error running {}: {}", self.program, output.status));
+        }
+
+        let mut outputs = vec![];
+        for output in &self.outputs {
+            let path = build_path.join(output);
+            if !path.exists() {
+                return Err(anyhow!(
+                    "{} did not produce expected output {}",
+                    self.program,
+                    output.display()
+                ));
+            }
+
+            outputs.push(path);
+        }
+
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, tugger_common::testutil::*};
+
+    #[test]
+    fn test_config_format() -> Result<()> {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("foo.json"))?,
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("dir/foo.yml"))?,
+            ConfigFormat::Yaml
+        );
+        assert!(ConfigFormat::from_path(Path::new("foo.toml")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_render() -> Result<()> {
+        let config = ConfigFile {
+            path: PathBuf::from("tool.json"),
+            format: ConfigFormat::Json,
+            content: serde_json::json!({
+                "input": "{build_path}/app",
+                "options": [1, true, null],
+            }),
+        };
+
+        let build_path = PathBuf::from("/build");
+        let rendered: serde_json::Value = serde_json::from_slice(&config.render(&build_path)?)?;
+        assert_eq!(
+            rendered,
+            serde_json::json!({
+                "input": "/build/app",
+                "options": [1, true, null],
+            })
+        );
+
+        let config = ConfigFile {
+            format: ConfigFormat::Yaml,
+            ..config
+        };
+        let rendered: serde_json::Value = serde_yaml::from_slice(&config.render(&build_path)?)?;
+        assert_eq!(rendered["input"], "/build/app");
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build() -> Result<()> {
+        let logger = get_logger()?;
+        let build_path = DEFAULT_TEMP_DIR.path().join("test_tool_pipeline_build");
+
+        let mut pipeline = ToolPipeline::new("sh");
+        pipeline.args = vec!["-c".to_string(), "cat config.json > \"$OUT\"".to_string()];
+        pipeline
+            .env
+            .insert("OUT".to_string(), "{build_path}/out.json".to_string());
+        pipeline.config_files.push(ConfigFile {
+            path: PathBuf::from("config.json"),
+            format: ConfigFormat::Json,
+            content: serde_json::json!({"name": "app"}),
+        });
+        pipeline.outputs.push(PathBuf::from("out.json"));
+
+        let outputs = pipeline.build(&logger, &build_path)?;
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&std::fs::read(&outputs[0])?)?,
+            serde_json::json!({"name": "app"})
+        );
+
+        pipeline.outputs.push(PathBuf::from("missing"));
+        assert!(pipeline.build(&logger, &build_path).is_err());
+
+        pipeline.args = vec!["-c".to_string(), "exit 1".to_string()];
+        assert!(pipeline.build(&logger, &build_path).is_err());
+
+        Ok(())
+    }
+}